@@ -54,7 +54,10 @@ impl Contract for FungibleTokenContract {
                 );
             }
         }
-        self.state.initialize_accounts(state).await;
+        self.state
+            .initialize_accounts(state)
+            .await
+            .unwrap_or_else(|error| panic!("{error}"));
     }
 
     async fn execute_operation(&mut self, operation: Self::Operation) -> Self::Response {
@@ -75,9 +78,7 @@ impl Contract for FungibleTokenContract {
                 target_account,
             } => {
                 self.check_account_authentication(owner);
-                self.state.debit(owner, amount).await;
-                self.finish_transfer_to_account(amount, target_account, owner)
-                    .await;
+                self.transfer(owner, amount, target_account).await;
                 FungibleResponse::Ok
             }
 
@@ -90,6 +91,64 @@ impl Contract for FungibleTokenContract {
                 self.claim(source_account, amount, target_account).await;
                 FungibleResponse::Ok
             }
+
+            Operation::Allowance { owner, spender } => {
+                let allowance = self.state.allowance(owner, spender).await;
+                FungibleResponse::Allowance(allowance)
+            }
+
+            Operation::Approve {
+                owner,
+                spender,
+                allowance,
+            } => {
+                self.check_account_authentication(owner);
+                self.state.approve(owner, spender, allowance);
+                FungibleResponse::Ok
+            }
+
+            Operation::IncreaseAllowance {
+                owner,
+                spender,
+                amount,
+            } => {
+                self.check_account_authentication(owner);
+                self.state.increase_allowance(owner, spender, amount).await;
+                FungibleResponse::Ok
+            }
+
+            Operation::TransferFrom {
+                owner,
+                spender,
+                amount,
+                target_account,
+            } => {
+                self.check_account_authentication(spender);
+                self.transfer_from(spender, owner, amount, target_account)
+                    .await;
+                FungibleResponse::Ok
+            }
+
+            Operation::Mint { owner, amount } => {
+                self.check_creator_chain();
+                let admin = (*self.state.admin.get()).expect("This token has no admin");
+                self.check_account_authentication(admin);
+                self.state
+                    .mint(owner, amount)
+                    .await
+                    .unwrap_or_else(|error| panic!("{error}"));
+                FungibleResponse::Ok
+            }
+
+            Operation::Burn { owner, amount } => {
+                self.check_creator_chain();
+                self.check_account_authentication(owner);
+                self.state
+                    .burn(owner, amount)
+                    .await
+                    .unwrap_or_else(|error| panic!("{error}"));
+                FungibleResponse::Ok
+            }
         }
     }
 
@@ -113,9 +172,7 @@ impl Contract for FungibleTokenContract {
                 target_account,
             } => {
                 self.check_account_authentication(owner);
-                self.state.debit(owner, amount).await;
-                self.finish_transfer_to_account(amount, target_account, owner)
-                    .await;
+                self.transfer(owner, amount, target_account).await;
             }
         }
     }
@@ -146,10 +203,19 @@ impl FungibleTokenContract {
         }
     }
 
+    /// Verifies that the current chain is the one where the application was created, which
+    /// maintains the total supply.
+    fn check_creator_chain(&mut self) {
+        assert_eq!(
+            self.runtime.chain_id(),
+            self.runtime.application_creator_chain_id(),
+            "Tokens can only be minted and burned on the chain where the application was created."
+        );
+    }
+
     async fn claim(&mut self, source_account: Account, amount: Amount, target_account: Account) {
         if source_account.chain_id == self.runtime.chain_id() {
-            self.state.debit(source_account.owner, amount).await;
-            self.finish_transfer_to_account(amount, target_account, source_account.owner)
+            self.transfer(source_account.owner, amount, target_account)
                 .await;
         } else {
             let message = Message::Withdraw {
@@ -164,16 +230,43 @@ impl FungibleTokenContract {
         }
     }
 
-    /// Executes the final step of a transfer where the tokens are sent to the destination.
-    async fn finish_transfer_to_account(
+    /// Transfers tokens from a local account to a (possibly remote) account on behalf of a
+    /// `spender`, consuming its allowance.
+    async fn transfer_from(
         &mut self,
+        spender: AccountOwner,
+        source: AccountOwner,
         amount: Amount,
         target_account: Account,
-        source: AccountOwner,
     ) {
         if target_account.chain_id == self.runtime.chain_id() {
-            self.state.credit(target_account.owner, amount).await;
+            self.state
+                .transfer_from(spender, source, target_account.owner, amount)
+                .await
+                .unwrap_or_else(|error| panic!("{error}"));
         } else {
+            // The transaction is reverted if the transfer fails after the allowance is spent.
+            self.state
+                .spend_allowance(spender, source, amount)
+                .await
+                .unwrap_or_else(|error| panic!("{error}"));
+            self.transfer(source, amount, target_account).await;
+        }
+    }
+
+    /// Transfers tokens from a local account to a (possibly remote) account. Tokens sent
+    /// to another chain are debited before the message crediting them is sent.
+    async fn transfer(&mut self, source: AccountOwner, amount: Amount, target_account: Account) {
+        if target_account.chain_id == self.runtime.chain_id() {
+            self.state
+                .transfer(source, target_account.owner, amount)
+                .await
+                .unwrap_or_else(|error| panic!("{error}"));
+        } else {
+            self.state
+                .debit(source, amount)
+                .await
+                .unwrap_or_else(|error| panic!("{error}"));
             let message = Message::Credit {
                 target: target_account.owner,
                 amount,
@@ -187,3 +280,214 @@ impl FungibleTokenContract {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use fungible::{Account, FungibleResponse, InitialStateBuilder, Operation, Parameters};
+    use futures::FutureExt as _;
+    use linera_sdk::{
+        base::{AccountOwner, Amount, ApplicationId, ChainId, Owner, PublicKey},
+        util::BlockingWait,
+        views::View,
+        Contract, ContractRuntime,
+    };
+
+    use super::{FungibleTokenContract, FungibleTokenState};
+
+    #[test]
+    fn transfer_from_by_application() {
+        let alice = Owner::from(PublicKey::test_key(1));
+        let bob = AccountOwner::from(PublicKey::test_key(2));
+        let exchange_id = ApplicationId::default();
+        let exchange = AccountOwner::Application(exchange_id);
+        let mut fungible = create_and_instantiate_fungible(alice, Amount::from_tokens(10));
+
+        fungible.runtime.set_authenticated_signer(alice);
+        execute_operation(
+            &mut fungible,
+            Operation::Approve {
+                owner: AccountOwner::User(alice),
+                spender: exchange,
+                allowance: Amount::from_tokens(5),
+            },
+        );
+
+        fungible
+            .runtime
+            .set_authenticated_signer(None)
+            .set_authenticated_caller_id(exchange_id);
+        execute_operation(
+            &mut fungible,
+            Operation::TransferFrom {
+                owner: AccountOwner::User(alice),
+                spender: exchange,
+                amount: Amount::from_tokens(3),
+                target_account: Account {
+                    chain_id: ChainId::root(0),
+                    owner: bob,
+                },
+            },
+        );
+
+        let response = execute_operation(
+            &mut fungible,
+            Operation::Allowance {
+                owner: AccountOwner::User(alice),
+                spender: exchange,
+            },
+        );
+        assert_matches!(
+            response,
+            FungibleResponse::Allowance(allowance) if allowance == Amount::from_tokens(2)
+        );
+        let response = execute_operation(&mut fungible, Operation::Balance { owner: bob });
+        assert_matches!(
+            response,
+            FungibleResponse::Balance(balance) if balance == Amount::from_tokens(3)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The requested transfer is not correctly authenticated.")]
+    fn transfer_from_by_unauthenticated_application() {
+        let alice = Owner::from(PublicKey::test_key(1));
+        let exchange = AccountOwner::Application(ApplicationId::default());
+        let mut fungible = create_and_instantiate_fungible(alice, Amount::from_tokens(10));
+
+        fungible.runtime.set_authenticated_signer(alice);
+        execute_operation(
+            &mut fungible,
+            Operation::Approve {
+                owner: AccountOwner::User(alice),
+                spender: exchange,
+                allowance: Amount::from_tokens(5),
+            },
+        );
+
+        fungible.runtime.set_authenticated_caller_id(None);
+        execute_operation(
+            &mut fungible,
+            Operation::TransferFrom {
+                owner: AccountOwner::User(alice),
+                spender: exchange,
+                amount: Amount::from_tokens(3),
+                target_account: Account {
+                    chain_id: ChainId::root(0),
+                    owner: AccountOwner::User(alice),
+                },
+            },
+        );
+    }
+
+    #[test]
+    fn mint_by_admin() {
+        let alice = Owner::from(PublicKey::test_key(1));
+        let bob = AccountOwner::from(PublicKey::test_key(2));
+        let mut fungible = create_and_instantiate_fungible(alice, Amount::from_tokens(10));
+
+        fungible.runtime.set_authenticated_signer(alice);
+        execute_operation(
+            &mut fungible,
+            Operation::Mint {
+                owner: bob,
+                amount: Amount::from_tokens(5),
+            },
+        );
+
+        let response = execute_operation(&mut fungible, Operation::Balance { owner: bob });
+        assert_matches!(
+            response,
+            FungibleResponse::Balance(balance) if balance == Amount::from_tokens(5)
+        );
+        assert_eq!(*fungible.state.total_supply.get(), Amount::from_tokens(15));
+    }
+
+    #[test]
+    #[should_panic(expected = "The requested transfer is not correctly authenticated.")]
+    fn unauthorized_mint() {
+        let alice = Owner::from(PublicKey::test_key(1));
+        let bob = Owner::from(PublicKey::test_key(2));
+        let mut fungible = create_and_instantiate_fungible(alice, Amount::from_tokens(10));
+
+        fungible.runtime.set_authenticated_signer(bob);
+        execute_operation(
+            &mut fungible,
+            Operation::Mint {
+                owner: AccountOwner::User(bob),
+                amount: Amount::from_tokens(5),
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Tokens can only be minted and burned on the chain where")]
+    fn mint_on_other_chain() {
+        let alice = Owner::from(PublicKey::test_key(1));
+        let mut fungible = create_and_instantiate_fungible(alice, Amount::from_tokens(10));
+
+        fungible
+            .runtime
+            .set_authenticated_signer(alice)
+            .set_chain_id(ChainId::root(1));
+        execute_operation(
+            &mut fungible,
+            Operation::Mint {
+                owner: AccountOwner::User(alice),
+                amount: Amount::from_tokens(5),
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The requested transfer is not correctly authenticated.")]
+    fn burn_by_other_owner() {
+        let alice = Owner::from(PublicKey::test_key(1));
+        let bob = Owner::from(PublicKey::test_key(2));
+        let mut fungible = create_and_instantiate_fungible(alice, Amount::from_tokens(10));
+
+        fungible.runtime.set_authenticated_signer(bob);
+        execute_operation(
+            &mut fungible,
+            Operation::Burn {
+                owner: AccountOwner::User(alice),
+                amount: Amount::from_tokens(5),
+            },
+        );
+    }
+
+    fn execute_operation(
+        fungible: &mut FungibleTokenContract,
+        operation: Operation,
+    ) -> FungibleResponse {
+        fungible
+            .execute_operation(operation)
+            .now_or_never()
+            .expect("Execution of fungible operation should not await anything")
+    }
+
+    /// Creates a token where `owner` has `amount` tokens and is the admin.
+    fn create_and_instantiate_fungible(owner: Owner, amount: Amount) -> FungibleTokenContract {
+        let runtime = ContractRuntime::new()
+            .with_application_parameters(Parameters::new("FUN"))
+            .with_application_creator_chain_id(ChainId::root(0))
+            .with_chain_id(ChainId::root(0));
+        let mut contract = FungibleTokenContract {
+            state: FungibleTokenState::load(runtime.root_view_storage_context())
+                .blocking_wait()
+                .expect("Failed to read from mock key value store"),
+            runtime,
+        };
+
+        let initial_state = InitialStateBuilder::default()
+            .with_account(AccountOwner::User(owner), amount)
+            .with_admin(AccountOwner::User(owner))
+            .build();
+        contract
+            .instantiate(initial_state)
+            .now_or_never()
+            .expect("Initialization of fungible state should not await anything");
+
+        contract
+    }
+}
@@ -56,6 +56,12 @@ impl FungibleTokenService {
         &self.state.accounts
     }
 
+    /// The number of tokens in existence, as known on the chain where the application was
+    /// created.
+    async fn total_supply(&self) -> Amount {
+        *self.state.total_supply.get()
+    }
+
     async fn ticker_symbol(&self) -> Result<String, async_graphql::Error> {
         Ok(self.runtime.application_parameters().ticker_symbol)
     }
@@ -4,27 +4,67 @@
 use fungible::InitialState;
 use linera_sdk::{
     base::{AccountOwner, Amount},
-    views::{linera_views, MapView, RootView, ViewStorageContext},
+    views::{linera_views, MapView, RegisterView, RootView, ViewStorageContext},
 };
+use thiserror::Error;
 
 /// The application state.
 #[derive(RootView)]
 #[view(context = "ViewStorageContext")]
 pub struct FungibleTokenState {
     pub accounts: MapView<AccountOwner, Amount>,
+    /// The amounts that spenders may transfer from the accounts, by owner and spender.
+    pub allowances: MapView<(AccountOwner, AccountOwner), Amount>,
+    /// The owner allowed to mint new tokens, if any.
+    pub admin: RegisterView<Option<AccountOwner>>,
+    /// The number of tokens in existence. Tokens are only minted and burned on the chain where
+    /// the application was created, so this is only maintained there.
+    pub total_supply: RegisterView<Amount>,
+}
+
+/// An error preventing tokens from being moved, created or destroyed.
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum FungibleTokenError {
+    #[error("The account {owner} has a balance of {balance}, which is less than {amount}")]
+    InsufficientBalance {
+        owner: AccountOwner,
+        balance: Amount,
+        amount: Amount,
+    },
+    #[error(
+        "The account {owner} allows {spender} to transfer {allowance}, which is less than {amount}"
+    )]
+    InsufficientAllowance {
+        owner: AccountOwner,
+        spender: AccountOwner,
+        allowance: Amount,
+        amount: Amount,
+    },
+    #[error("Minting {amount} would overflow the total supply of {total_supply}")]
+    TotalSupplyOverflow {
+        total_supply: Amount,
+        amount: Amount,
+    },
+    #[error("Burning {amount} would underflow the total supply of {total_supply}")]
+    TotalSupplyUnderflow {
+        total_supply: Amount,
+        amount: Amount,
+    },
 }
 
 #[allow(dead_code)]
 impl FungibleTokenState {
-    /// Initializes the application state with some accounts with initial balances.
-    pub(crate) async fn initialize_accounts(&mut self, state: InitialState) {
+    /// Initializes the application state with some accounts with initial balances, which make
+    /// up the initial total supply.
+    pub(crate) async fn initialize_accounts(
+        &mut self,
+        state: InitialState,
+    ) -> Result<(), FungibleTokenError> {
+        self.admin.set(state.admin);
         for (k, v) in state.accounts {
-            if v != Amount::ZERO {
-                self.accounts
-                    .insert(&k, v)
-                    .expect("Error in insert statement");
-            }
+            self.mint(k, v).await?;
         }
+        Ok(())
     }
 
     /// Obtains the balance for an `account`, returning None if there's no entry for the account.
@@ -53,14 +93,22 @@ impl FungibleTokenState {
     }
 
     /// Tries to debit the requested `amount` from an `account`.
-    pub(crate) async fn debit(&mut self, account: AccountOwner, amount: Amount) {
+    pub(crate) async fn debit(
+        &mut self,
+        account: AccountOwner,
+        amount: Amount,
+    ) -> Result<(), FungibleTokenError> {
         if amount == Amount::ZERO {
-            return;
+            return Ok(());
         }
         let mut balance = self.balance_or_default(&account).await;
         balance
             .try_sub_assign(amount)
-            .expect("Source account does not have sufficient balance for transfer");
+            .map_err(|_| FungibleTokenError::InsufficientBalance {
+                owner: account,
+                balance,
+                amount,
+            })?;
         if balance == Amount::ZERO {
             self.accounts
                 .remove(&account)
@@ -70,5 +118,464 @@ impl FungibleTokenState {
                 .insert(&account, balance)
                 .expect("Failed insertion operation");
         }
+        Ok(())
+    }
+
+    /// Moves `amount` tokens from the `source` account to the `target` account. Nothing is
+    /// changed if the `source` account does not have enough tokens.
+    pub(crate) async fn transfer(
+        &mut self,
+        source: AccountOwner,
+        target: AccountOwner,
+        amount: Amount,
+    ) -> Result<(), FungibleTokenError> {
+        self.debit(source, amount).await?;
+        self.credit(target, amount).await;
+        Ok(())
+    }
+
+    /// Creates `amount` new tokens in the account of `owner`. Nothing is changed if the total
+    /// supply would overflow.
+    pub(crate) async fn mint(
+        &mut self,
+        owner: AccountOwner,
+        amount: Amount,
+    ) -> Result<(), FungibleTokenError> {
+        let total_supply = *self.total_supply.get();
+        let new_total_supply =
+            total_supply
+                .try_add(amount)
+                .map_err(|_| FungibleTokenError::TotalSupplyOverflow {
+                    total_supply,
+                    amount,
+                })?;
+        // No balance can exceed the total supply, so this cannot saturate.
+        self.credit(owner, amount).await;
+        self.total_supply.set(new_total_supply);
+        Ok(())
+    }
+
+    /// Destroys `amount` tokens of the account of `owner`. Nothing is changed if the account
+    /// does not have enough tokens.
+    pub(crate) async fn burn(
+        &mut self,
+        owner: AccountOwner,
+        amount: Amount,
+    ) -> Result<(), FungibleTokenError> {
+        let total_supply = *self.total_supply.get();
+        let new_total_supply =
+            total_supply
+                .try_sub(amount)
+                .map_err(|_| FungibleTokenError::TotalSupplyUnderflow {
+                    total_supply,
+                    amount,
+                })?;
+        self.debit(owner, amount).await?;
+        self.total_supply.set(new_total_supply);
+        Ok(())
+    }
+
+    /// Obtains the amount that `spender` may still transfer from the account of `owner`.
+    pub(crate) async fn allowance(&self, owner: AccountOwner, spender: AccountOwner) -> Amount {
+        self.allowances
+            .get(&(owner, spender))
+            .await
+            .expect("Failure in the retrieval")
+            .unwrap_or_default()
+    }
+
+    /// Sets the amount that `spender` may transfer from the account of `owner`, replacing the
+    /// previous allowance. An allowance of zero revokes it.
+    pub(crate) fn approve(
+        &mut self,
+        owner: AccountOwner,
+        spender: AccountOwner,
+        allowance: Amount,
+    ) {
+        if allowance == Amount::ZERO {
+            self.allowances
+                .remove(&(owner, spender))
+                .expect("Failed to remove an allowance");
+        } else {
+            self.allowances
+                .insert(&(owner, spender), allowance)
+                .expect("Failed insert statement");
+        }
+    }
+
+    /// Increases the amount that `spender` may transfer from the account of `owner`, saturating
+    /// at the maximum amount.
+    pub(crate) async fn increase_allowance(
+        &mut self,
+        owner: AccountOwner,
+        spender: AccountOwner,
+        amount: Amount,
+    ) {
+        let allowance = self.allowance(owner, spender).await.saturating_add(amount);
+        self.approve(owner, spender, allowance);
+    }
+
+    /// Decreases the allowance of `spender` for the account of `owner` by `amount`. Nothing is
+    /// changed if the allowance is insufficient.
+    pub(crate) async fn spend_allowance(
+        &mut self,
+        spender: AccountOwner,
+        owner: AccountOwner,
+        amount: Amount,
+    ) -> Result<(), FungibleTokenError> {
+        let remaining = self.remaining_allowance(spender, owner, amount).await?;
+        self.approve(owner, spender, remaining);
+        Ok(())
+    }
+
+    /// Moves `amount` tokens from the account of `owner` to the `target` account on behalf of
+    /// `spender`, whose allowance is decreased accordingly. Nothing is changed if either the
+    /// allowance or the balance is insufficient.
+    pub(crate) async fn transfer_from(
+        &mut self,
+        spender: AccountOwner,
+        owner: AccountOwner,
+        target: AccountOwner,
+        amount: Amount,
+    ) -> Result<(), FungibleTokenError> {
+        let remaining = self.remaining_allowance(spender, owner, amount).await?;
+        self.transfer(owner, target, amount).await?;
+        self.approve(owner, spender, remaining);
+        Ok(())
+    }
+
+    /// Returns what would be left of the allowance of `spender` for the account of `owner`
+    /// after spending `amount`.
+    async fn remaining_allowance(
+        &self,
+        spender: AccountOwner,
+        owner: AccountOwner,
+        amount: Amount,
+    ) -> Result<Amount, FungibleTokenError> {
+        let allowance = self.allowance(owner, spender).await;
+        allowance
+            .try_sub(amount)
+            .map_err(|_| FungibleTokenError::InsufficientAllowance {
+                owner,
+                spender,
+                allowance,
+                amount,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use fungible::InitialStateBuilder;
+    use linera_sdk::{
+        base::{AccountOwner, Amount, PublicKey},
+        util::BlockingWait,
+        views::{KeyValueStore, RootView, View, ViewStorageContext},
+    };
+
+    use super::{FungibleTokenError, FungibleTokenState};
+
+    #[test]
+    fn transfer() {
+        let (mut state, alice, bob) = create_state(Amount::from_tokens(10));
+
+        state
+            .transfer(alice, bob, Amount::from_tokens(4))
+            .blocking_wait()
+            .expect("Alice has enough tokens");
+
+        assert_eq!(balance(&state, alice), Some(Amount::from_tokens(6)));
+        assert_eq!(balance(&state, bob), Some(Amount::from_tokens(4)));
+
+        state
+            .transfer(alice, bob, Amount::from_tokens(6))
+            .blocking_wait()
+            .expect("Alice has enough tokens");
+
+        assert_eq!(balance(&state, alice), None);
+        assert_eq!(balance(&state, bob), Some(Amount::from_tokens(10)));
+    }
+
+    #[test]
+    fn overdraft() {
+        let (mut state, alice, bob) = create_state(Amount::from_tokens(10));
+
+        let result = state
+            .transfer(alice, bob, Amount::from_tokens(11))
+            .blocking_wait();
+
+        assert_matches!(
+            result,
+            Err(FungibleTokenError::InsufficientBalance { owner, balance, amount })
+                if owner == alice
+                    && balance == Amount::from_tokens(10)
+                    && amount == Amount::from_tokens(11)
+        );
+        assert_eq!(balance(&state, alice), Some(Amount::from_tokens(10)));
+        assert_eq!(balance(&state, bob), None);
+
+        let result = state
+            .transfer(bob, alice, Amount::from_attos(1))
+            .blocking_wait();
+
+        assert_matches!(
+            result,
+            Err(FungibleTokenError::InsufficientBalance { owner, .. }) if owner == bob
+        );
+    }
+
+    #[test]
+    fn self_transfer() {
+        let (mut state, alice, _bob) = create_state(Amount::from_tokens(10));
+
+        state
+            .transfer(alice, alice, Amount::from_tokens(10))
+            .blocking_wait()
+            .expect("Alice has enough tokens");
+
+        assert_eq!(balance(&state, alice), Some(Amount::from_tokens(10)));
+
+        let result = state
+            .transfer(alice, alice, Amount::from_tokens(11))
+            .blocking_wait();
+
+        assert_matches!(result, Err(FungibleTokenError::InsufficientBalance { .. }));
+        assert_eq!(balance(&state, alice), Some(Amount::from_tokens(10)));
+    }
+
+    #[test]
+    fn zero_amount() {
+        let (mut state, alice, bob) = create_state(Amount::from_tokens(10));
+
+        state
+            .transfer(bob, alice, Amount::ZERO)
+            .blocking_wait()
+            .expect("Transferring nothing always succeeds");
+
+        assert_eq!(balance(&state, alice), Some(Amount::from_tokens(10)));
+        assert_eq!(balance(&state, bob), None);
+    }
+
+    #[test]
+    fn transfer_from() {
+        let (mut state, alice, bob) = create_state(Amount::from_tokens(10));
+        let exchange = AccountOwner::from(PublicKey::test_key(3));
+
+        state.approve(alice, exchange, Amount::from_tokens(4));
+        state
+            .transfer_from(exchange, alice, bob, Amount::from_tokens(3))
+            .blocking_wait()
+            .expect("The exchange is allowed to transfer Alice's tokens");
+
+        assert_eq!(balance(&state, alice), Some(Amount::from_tokens(7)));
+        assert_eq!(balance(&state, bob), Some(Amount::from_tokens(3)));
+        assert_eq!(allowance(&state, alice, exchange), Amount::from_tokens(1));
+
+        let result = state
+            .transfer_from(exchange, alice, bob, Amount::from_tokens(2))
+            .blocking_wait();
+
+        assert_matches!(
+            result,
+            Err(FungibleTokenError::InsufficientAllowance { allowance, amount, .. })
+                if allowance == Amount::from_tokens(1) && amount == Amount::from_tokens(2)
+        );
+        assert_eq!(balance(&state, alice), Some(Amount::from_tokens(7)));
+
+        let result = state
+            .transfer_from(bob, alice, bob, Amount::from_attos(1))
+            .blocking_wait();
+
+        assert_matches!(
+            result,
+            Err(FungibleTokenError::InsufficientAllowance { spender, .. }) if spender == bob
+        );
+    }
+
+    #[test]
+    fn transfer_from_with_insufficient_balance() {
+        let (mut state, alice, bob) = create_state(Amount::from_tokens(10));
+
+        state.approve(alice, bob, Amount::from_tokens(20));
+        let result = state
+            .transfer_from(bob, alice, bob, Amount::from_tokens(11))
+            .blocking_wait();
+
+        assert_matches!(result, Err(FungibleTokenError::InsufficientBalance { .. }));
+        assert_eq!(balance(&state, alice), Some(Amount::from_tokens(10)));
+        assert_eq!(allowance(&state, alice, bob), Amount::from_tokens(20));
+    }
+
+    #[test]
+    fn set_and_increase_allowances() {
+        let (mut state, alice, bob) = create_state(Amount::from_tokens(10));
+
+        state
+            .increase_allowance(alice, bob, Amount::from_tokens(2))
+            .blocking_wait();
+        state
+            .increase_allowance(alice, bob, Amount::from_tokens(3))
+            .blocking_wait();
+        assert_eq!(allowance(&state, alice, bob), Amount::from_tokens(5));
+
+        state.approve(alice, bob, Amount::from_tokens(1));
+        assert_eq!(allowance(&state, alice, bob), Amount::from_tokens(1));
+        assert_eq!(allowance(&state, bob, alice), Amount::ZERO);
+
+        state.approve(alice, bob, Amount::MAX);
+        state
+            .increase_allowance(alice, bob, Amount::from_tokens(1))
+            .blocking_wait();
+        assert_eq!(allowance(&state, alice, bob), Amount::MAX);
+    }
+
+    #[test]
+    fn revoke_allowance() {
+        let (mut state, alice, bob) = create_state(Amount::from_tokens(10));
+
+        state.approve(alice, bob, Amount::from_tokens(5));
+        state.approve(alice, bob, Amount::ZERO);
+
+        assert_eq!(allowance(&state, alice, bob), Amount::ZERO);
+        assert_eq!(
+            state
+                .allowances
+                .get(&(alice, bob))
+                .blocking_wait()
+                .expect("Failure in the retrieval"),
+            None
+        );
+
+        let result = state
+            .transfer_from(bob, alice, bob, Amount::from_tokens(1))
+            .blocking_wait();
+
+        assert_matches!(
+            result,
+            Err(FungibleTokenError::InsufficientAllowance { .. })
+        );
+    }
+
+    #[test]
+    fn mint_and_burn() {
+        let (mut state, alice, bob) = create_state(Amount::from_tokens(10));
+        assert_eq!(*state.total_supply.get(), Amount::from_tokens(10));
+
+        state
+            .mint(bob, Amount::from_tokens(5))
+            .blocking_wait()
+            .expect("The total supply does not overflow");
+        state
+            .burn(alice, Amount::from_tokens(10))
+            .blocking_wait()
+            .expect("Alice has enough tokens");
+
+        assert_eq!(balance(&state, alice), None);
+        assert_eq!(balance(&state, bob), Some(Amount::from_tokens(5)));
+        assert_eq!(*state.total_supply.get(), Amount::from_tokens(5));
+
+        let result = state.burn(bob, Amount::from_tokens(6)).blocking_wait();
+
+        assert_matches!(result, Err(FungibleTokenError::TotalSupplyUnderflow { .. }));
+        assert_eq!(*state.total_supply.get(), Amount::from_tokens(5));
+
+        let result = state.burn(alice, Amount::from_tokens(1)).blocking_wait();
+
+        assert_matches!(result, Err(FungibleTokenError::InsufficientBalance { .. }));
+        assert_eq!(*state.total_supply.get(), Amount::from_tokens(5));
+    }
+
+    #[test]
+    fn mint_overflow() {
+        let (mut state, alice, bob) = create_state(Amount::from_tokens(10));
+
+        let result = state.mint(bob, Amount::MAX).blocking_wait();
+
+        assert_matches!(
+            result,
+            Err(FungibleTokenError::TotalSupplyOverflow { total_supply, amount })
+                if total_supply == Amount::from_tokens(10) && amount == Amount::MAX
+        );
+        assert_eq!(balance(&state, alice), Some(Amount::from_tokens(10)));
+        assert_eq!(balance(&state, bob), None);
+        assert_eq!(*state.total_supply.get(), Amount::from_tokens(10));
+    }
+
+    #[test]
+    fn initialize_accounts() {
+        let alice = AccountOwner::from(PublicKey::test_key(1));
+        let bob = AccountOwner::from(PublicKey::test_key(2));
+        let initial_state = InitialStateBuilder::default()
+            .with_account(alice, Amount::from_tokens(3))
+            .with_account(bob, Amount::ZERO)
+            .with_admin(bob)
+            .build();
+        let mut state = load_state(KeyValueStore::mock().to_mut());
+
+        state
+            .initialize_accounts(initial_state)
+            .blocking_wait()
+            .expect("The total supply does not overflow");
+
+        assert_eq!(balance(&state, alice), Some(Amount::from_tokens(3)));
+        assert_eq!(balance(&state, bob), None);
+        assert_eq!(*state.admin.get(), Some(bob));
+        assert_eq!(*state.total_supply.get(), Amount::from_tokens(3));
+    }
+
+    /// Checks that the changes are persisted in the views, and found again after loading the
+    /// state from storage.
+    #[test]
+    fn save_and_load() {
+        let (mut state, alice, bob) = create_state(Amount::from_tokens(10));
+        state
+            .transfer(alice, bob, Amount::from_tokens(4))
+            .blocking_wait()
+            .expect("Alice has enough tokens");
+        state.approve(bob, alice, Amount::from_tokens(2));
+        let context = state.context().clone();
+        state.save().blocking_wait().expect("Failed to save state");
+
+        let state = FungibleTokenState::load(context)
+            .blocking_wait()
+            .expect("Failed to read from mock key value store");
+
+        assert_eq!(balance(&state, alice), Some(Amount::from_tokens(6)));
+        assert_eq!(balance(&state, bob), Some(Amount::from_tokens(4)));
+        assert_eq!(allowance(&state, bob, alice), Amount::from_tokens(2));
+        assert_eq!(*state.total_supply.get(), Amount::from_tokens(10));
+        let owners = state
+            .accounts
+            .indices()
+            .blocking_wait()
+            .expect("Failed to read from mock key value store");
+        assert_eq!(owners.len(), 2);
+    }
+
+    /// Creates a state where Alice has `initial_balance` tokens and Bob has none.
+    fn create_state(initial_balance: Amount) -> (FungibleTokenState, AccountOwner, AccountOwner) {
+        let mut state = load_state(KeyValueStore::mock().to_mut());
+        let alice = AccountOwner::from(PublicKey::test_key(1));
+        let bob = AccountOwner::from(PublicKey::test_key(2));
+        state
+            .mint(alice, initial_balance)
+            .blocking_wait()
+            .expect("The total supply does not overflow");
+        (state, alice, bob)
+    }
+
+    fn load_state(store: KeyValueStore) -> FungibleTokenState {
+        FungibleTokenState::load(ViewStorageContext::new_unsafe(store, Vec::new(), ()))
+            .blocking_wait()
+            .expect("Failed to read from mock key value store")
+    }
+
+    fn balance(state: &FungibleTokenState, owner: AccountOwner) -> Option<Amount> {
+        state.balance(&owner).blocking_wait()
+    }
+
+    fn allowance(state: &FungibleTokenState, owner: AccountOwner, spender: AccountOwner) -> Amount {
+        state.allowance(owner, spender).blocking_wait()
     }
 }
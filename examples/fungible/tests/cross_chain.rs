@@ -70,6 +70,82 @@ async fn test_cross_chain_transfer() {
     );
 }
 
+/// Test transferring tokens back and forth between two microchains.
+///
+/// Creates the application on a `sender_chain`, initializing it with a single account with some
+/// tokens for that chain's owner. Transfers some of those tokens to a new `receiver_chain`, which
+/// then sends part of them back, and checks that no tokens are created or lost on the way.
+#[tokio::test]
+async fn test_round_trip_transfer() {
+    let initial_amount = Amount::from_tokens(20);
+    let transfer_amount = Amount::from_tokens(15);
+    let return_amount = Amount::from_tokens(6);
+
+    let (validator, bytecode_id) =
+        TestValidator::with_current_bytecode::<FungibleTokenAbi, Parameters, InitialState>().await;
+    let mut sender_chain = validator.new_chain().await;
+    let sender_account = AccountOwner::from(sender_chain.public_key());
+
+    let initial_state = InitialStateBuilder::default().with_account(sender_account, initial_amount);
+    let params = Parameters::new("RND");
+    let application_id = sender_chain
+        .create_application(bytecode_id, params, initial_state.build(), vec![])
+        .await;
+
+    let receiver_chain = validator.new_chain().await;
+    let receiver_account = AccountOwner::from(receiver_chain.public_key());
+
+    sender_chain
+        .add_block(|block| {
+            block.with_operation(
+                application_id,
+                Operation::Transfer {
+                    owner: sender_account,
+                    amount: transfer_amount,
+                    target_account: Account {
+                        chain_id: receiver_chain.id(),
+                        owner: receiver_account,
+                    },
+                },
+            );
+        })
+        .await;
+
+    receiver_chain.handle_received_messages().await;
+
+    receiver_chain
+        .add_block(|block| {
+            block.with_operation(
+                application_id,
+                Operation::Transfer {
+                    owner: receiver_account,
+                    amount: return_amount,
+                    target_account: Account {
+                        chain_id: sender_chain.id(),
+                        owner: sender_account,
+                    },
+                },
+            );
+        })
+        .await;
+
+    assert_eq!(
+        fungible::query_account(application_id, &receiver_chain, receiver_account).await,
+        Some(transfer_amount.saturating_sub(return_amount)),
+    );
+
+    sender_chain.handle_received_messages().await;
+
+    assert_eq!(
+        fungible::query_account(application_id, &sender_chain, sender_account).await,
+        Some(
+            initial_amount
+                .saturating_sub(transfer_amount)
+                .saturating_add(return_amount)
+        ),
+    );
+}
+
 /// Test bouncing some tokens back to the sender.
 ///
 /// Creates the application on a `sender_chain`, initializing it with a single account with some
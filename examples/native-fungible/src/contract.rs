@@ -89,6 +89,17 @@ impl Contract for NativeFungibleTokenContract {
                 );
                 FungibleResponse::Ok
             }
+
+            Operation::Allowance { .. }
+            | Operation::Approve { .. }
+            | Operation::IncreaseAllowance { .. }
+            | Operation::TransferFrom { .. } => {
+                panic!("Allowances are not supported by the native token")
+            }
+
+            Operation::Mint { .. } | Operation::Burn { .. } => {
+                panic!("The native token cannot be minted or burned")
+            }
         }
     }
 
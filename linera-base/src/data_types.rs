@@ -1205,6 +1205,8 @@ static BYTECODE_DECOMPRESSION_LATENCY: LazyLock<HistogramVec> = LazyLock::new(||
 mod tests {
     use std::str::FromStr;
 
+    use test_strategy::proptest;
+
     use super::Amount;
 
     #[test]
@@ -1232,4 +1234,61 @@ mod tests {
             format!("{:~^+9.1}", Amount::from_str("12.34").unwrap())
         );
     }
+
+    /// Checks that the arithmetic operations on amounts never panic, and either return the
+    /// exact result or report the overflow or underflow.
+    #[proptest]
+    fn amount_arithmetic_does_not_panic(left: Amount, right: Amount, factor: u128) {
+        assert_eq!(
+            left.try_add(right).ok().map(u128::from),
+            left.0.checked_add(right.0)
+        );
+        assert_eq!(
+            left.try_sub(right).ok().map(u128::from),
+            left.0.checked_sub(right.0)
+        );
+        assert_eq!(
+            left.try_mul(factor).ok().map(u128::from),
+            left.0.checked_mul(factor)
+        );
+        assert_eq!(left.saturating_add(right).0, left.0.saturating_add(right.0));
+        assert_eq!(left.saturating_sub(right).0, left.0.saturating_sub(right.0));
+        assert_eq!(left.saturating_mul(factor).0, left.0.saturating_mul(factor));
+        assert_eq!(
+            left.saturating_div(right),
+            left.0.checked_div(right.0).unwrap_or(u128::MAX)
+        );
+
+        let mut sum = left;
+        assert_eq!(
+            sum.try_add_assign(right).is_ok(),
+            left.0.checked_add(right.0).is_some()
+        );
+        let mut difference = left;
+        assert_eq!(
+            difference.try_sub_assign(right).is_ok(),
+            left.0.checked_sub(right.0).is_some()
+        );
+    }
+
+    /// Checks that every amount is displayed as a decimal string that parses back to the
+    /// same amount.
+    #[proptest]
+    fn amount_display_round_trip(amount: Amount) {
+        assert_eq!(Amount::from_str(&amount.to_string()).unwrap(), amount);
+        assert_eq!(Amount::from_str(&format!("{amount:.18}")).unwrap(), amount);
+    }
+
+    /// Checks that parsing arbitrary decimal strings never panics, and that the amounts which
+    /// can be parsed are displayed consistently.
+    #[proptest]
+    fn amount_parsing_does_not_panic(
+        #[strategy("[+]?[0-9_]{0,45}([.][0-9]{0,22})?")] decimal: String,
+        any_string: String,
+    ) {
+        if let Ok(amount) = Amount::from_str(&decimal) {
+            assert_eq!(Amount::from_str(&amount.to_string()).unwrap(), amount);
+        }
+        let _ = Amount::from_str(&any_string);
+    }
 }
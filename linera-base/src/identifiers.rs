@@ -912,15 +912,20 @@ impl FromStr for AccountOwner {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some(owner) = s.strip_prefix("User:") {
-            Ok(AccountOwner::User(
-                Owner::from_str(owner).context("Getting Owner should not fail")?,
-            ))
+            Ok(AccountOwner::User(Owner::from_str(owner).with_context(
+                || format!("Invalid owner in the account owner {s:?}"),
+            )?))
         } else if let Some(app_id) = s.strip_prefix("Application:") {
             Ok(AccountOwner::Application(
-                ApplicationId::from_str(app_id).context("Getting ApplicationId should not fail")?,
+                ApplicationId::from_str(app_id).with_context(|| {
+                    format!("Invalid application ID in the account owner {s:?}")
+                })?,
             ))
         } else {
-            Err(anyhow!("Invalid enum! Enum: {}", s))
+            Err(anyhow!(
+                "Invalid account owner {s:?}: expected `User:<owner>` or \
+                 `Application:<application ID>`"
+            ))
         }
     }
 }
@@ -1018,7 +1023,15 @@ doc_scalar!(
 
 #[cfg(test)]
 mod tests {
-    use super::ChainId;
+    use std::str::FromStr as _;
+
+    use test_strategy::proptest;
+
+    use super::{AccountOwner, ApplicationId, BytecodeId, ChainId, MessageId, Owner};
+    use crate::{
+        crypto::{CryptoHash, PublicKey},
+        data_types::BlockHeight,
+    };
 
     /// Verifies that chain IDs that are explicitly used in some example and test scripts don't
     /// change.
@@ -1037,4 +1050,83 @@ mod tests {
             "9c8a838e8f7b63194f6c7585455667a8379d2b5db19a3300e9961f0b1e9091ea"
         );
     }
+
+    /// Verifies the textual and binary representations of account owners.
+    #[test]
+    fn account_owner_representations() {
+        let owner = Owner::from(PublicKey::test_key(1));
+        let user = AccountOwner::User(owner);
+        let application_id = ApplicationId {
+            bytecode_id: BytecodeId::new(
+                CryptoHash::test_hash("contract"),
+                CryptoHash::test_hash("service"),
+            ),
+            creation: MessageId {
+                chain_id: ChainId::root(0),
+                height: BlockHeight(1),
+                index: 2,
+            },
+        };
+        let application = AccountOwner::Application(application_id);
+
+        assert_eq!(user.to_string(), format!("User:{owner}"));
+        assert_eq!(
+            application.to_string(),
+            format!("Application:{application_id}")
+        );
+        assert_eq!(
+            serde_json::to_string(&user).unwrap(),
+            format!("\"User:{owner}\"")
+        );
+
+        // The binary representation is the one of an enum with the variants in this order.
+        assert_eq!(
+            bcs::to_bytes(&user).unwrap(),
+            [vec![0], bcs::to_bytes(&owner).unwrap()].concat()
+        );
+        assert_eq!(
+            bcs::to_bytes(&application).unwrap(),
+            [vec![1], bcs::to_bytes(&application_id).unwrap()].concat()
+        );
+    }
+
+    #[test]
+    fn malformed_account_owners() {
+        let owner = Owner::from(PublicKey::test_key(1));
+        for malformed in [
+            String::new(),
+            owner.to_string(),
+            format!("user:{owner}"),
+            format!("Key:{owner}"),
+            "User:".to_string(),
+            "User:0123".to_string(),
+            format!("User:{owner}0"),
+            format!("Application:{owner}"),
+        ] {
+            assert!(
+                AccountOwner::from_str(&malformed).is_err(),
+                "{malformed:?} should not be parsed"
+            );
+            assert!(serde_json::from_value::<AccountOwner>(malformed.into()).is_err());
+        }
+    }
+
+    #[proptest]
+    fn account_owner_round_trip(account_owner: AccountOwner) {
+        let string = account_owner.to_string();
+        assert_eq!(AccountOwner::from_str(&string).unwrap(), account_owner);
+
+        let json = serde_json::to_value(account_owner).unwrap();
+        assert_eq!(json, serde_json::Value::String(string));
+        assert_eq!(
+            serde_json::from_value::<AccountOwner>(json).unwrap(),
+            account_owner
+        );
+
+        let bytes = bcs::to_bytes(&account_owner).unwrap();
+        assert_eq!(
+            bcs::from_bytes::<AccountOwner>(&bytes).unwrap(),
+            account_owner
+        );
+    }
 }
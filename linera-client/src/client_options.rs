@@ -161,6 +161,21 @@ pub struct ClientOptions {
     /// as a fraction of time taken to reach quorum.
     #[arg(long, default_value_t = DEFAULT_GRACE_PERIOD)]
     pub grace_period: f64,
+
+    /// The format of the results printed on the standard output. The logs are always
+    /// printed on the standard error.
+    #[arg(long = "output", global = true, default_value = "text")]
+    pub output_format: OutputFormat,
+}
+
+/// The format of the results printed by the client.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable text.
+    #[default]
+    Text,
+    /// One line of JSON, with a `schema_version` field.
+    Json,
 }
 
 impl ClientOptions {
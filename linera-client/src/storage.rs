@@ -12,7 +12,9 @@ use linera_storage_service::{
     common::{ServiceStoreConfig, ServiceStoreInternalConfig},
 };
 #[cfg(feature = "dynamodb")]
-use linera_views::dynamo_db::{get_config, DynamoDbStore, DynamoDbStoreConfig};
+use linera_views::dynamo_db::{
+    get_config, DynamoDbStore, DynamoDbStoreConfig, DynamoDbStoreInternal,
+};
 #[cfg(with_storage)]
 use linera_views::store::LocalAdminKeyValueStore as _;
 use linera_views::{
@@ -243,6 +245,11 @@ example service:tcp:127.0.0.1:7878:table_do_my_test"
                     ))
                 })?
                 .to_string();
+            DynamoDbStoreInternal::check_namespace(&namespace).map_err(|error| {
+                Error::Format(format!(
+                    "Invalid DynamoDB table name {namespace:?}: {error}"
+                ))
+            })?;
             let use_localstack = match parts.next() {
                 None | Some("env") => false,
                 Some("localstack") => true,
@@ -345,6 +352,7 @@ impl StorageConfigNamespace {
                 let config = ServiceStoreConfig {
                     inner_config,
                     cache_size: common_config.cache_size,
+                    max_cache_bytes: None,
                 };
                 Ok(StoreConfig::Service(config, namespace))
             }
@@ -415,6 +423,7 @@ impl StoreConfig {
             StoreConfig::Memory(_, _) => Err(ViewError::StoreError {
                 backend: "memory".to_string(),
                 error: "delete_all does not make sense for memory storage".to_string(),
+                retryable: false,
             }),
             #[cfg(feature = "storage-service")]
             StoreConfig::Service(config, _namespace) => {
@@ -445,6 +454,7 @@ impl StoreConfig {
             StoreConfig::Memory(_, _) => Err(ViewError::StoreError {
                 backend: "memory".to_string(),
                 error: "delete_namespace does not make sense for memory storage".to_string(),
+                retryable: false,
             }),
             #[cfg(feature = "storage-service")]
             StoreConfig::Service(config, namespace) => {
@@ -475,6 +485,7 @@ impl StoreConfig {
             StoreConfig::Memory(_, _) => Err(ViewError::StoreError {
                 backend: "memory".to_string(),
                 error: "test_existence does not make sense for memory storage".to_string(),
+                retryable: false,
             }),
             #[cfg(feature = "storage-service")]
             StoreConfig::Service(config, namespace) => {
@@ -501,6 +512,7 @@ impl StoreConfig {
             StoreConfig::Memory(_, _) => Err(ViewError::StoreError {
                 backend: "memory".to_string(),
                 error: "initialize does not make sense for memory storage".to_string(),
+                retryable: false,
             }),
             #[cfg(feature = "storage-service")]
             StoreConfig::Service(config, namespace) => {
@@ -531,6 +543,7 @@ impl StoreConfig {
             StoreConfig::Memory(_, _) => Err(ViewError::StoreError {
                 backend: "memory".to_string(),
                 error: "list_all is not supported for the memory storage".to_string(),
+                retryable: false,
             }),
             #[cfg(feature = "storage-service")]
             StoreConfig::Service(config, _namespace) => {
@@ -704,6 +717,38 @@ fn test_memory_storage_config_from_str() {
     );
 }
 
+#[test]
+fn test_malformed_storage_config_from_str() {
+    assert!(StorageConfigNamespace::from_str("").is_err());
+    assert!(StorageConfigNamespace::from_str("memor").is_err());
+    assert!(StorageConfigNamespace::from_str("memory_table").is_err());
+    assert!(StorageConfigNamespace::from_str("unknown:table").is_err());
+    assert!(StorageConfigNamespace::from_str(":memory").is_err());
+}
+
+#[test]
+fn test_storage_config_display_round_trip() {
+    let inputs = [
+        "memory:table_linera",
+        #[cfg(feature = "storage-service")]
+        "service:tcp:127.0.0.1:8942:linera",
+        #[cfg(feature = "rocksdb")]
+        "rocksdb:foo.db:block_in_place:chosen_namespace",
+        #[cfg(feature = "dynamodb")]
+        "dynamodb:table:localstack",
+        #[cfg(feature = "scylladb")]
+        "scylladb:tcp:db_hostname:230:table_other_storage",
+    ];
+    for input in inputs {
+        let config = StorageConfigNamespace::from_str(input).unwrap();
+        assert_eq!(config.to_string(), input);
+        assert_eq!(
+            StorageConfigNamespace::from_str(&config.to_string()).unwrap(),
+            config
+        );
+    }
+}
+
 #[cfg(feature = "storage-service")]
 #[test]
 fn test_shared_store_config_from_str() {
@@ -537,7 +537,10 @@ where
     let receiver2_owner = AccountOwner::User(Owner::from(receiver2.key_pair().await?.public()));
 
     let accounts = BTreeMap::from_iter([(sender_owner, Amount::from_tokens(1_000_000))]);
-    let state = fungible::InitialState { accounts };
+    let state = fungible::InitialState {
+        accounts,
+        admin: None,
+    };
     let params = fungible::Parameters::new("FUN");
     let (application_id, _cert) = sender
         .create_application(bytecode_id, &params, &state, vec![])
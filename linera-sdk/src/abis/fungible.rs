@@ -57,6 +57,61 @@ pub enum Operation {
         /// Target account to claim the amount into
         target_account: Account,
     },
+    /// Requests the amount that a spender may still transfer from an account.
+    Allowance {
+        /// Owner of the account
+        owner: AccountOwner,
+        /// Spender to query the allowance for
+        spender: AccountOwner,
+    },
+    /// Sets the amount that a spender may transfer from a (locally owned) account,
+    /// replacing the previous allowance. An allowance of zero revokes it.
+    Approve {
+        /// Owner of the account
+        owner: AccountOwner,
+        /// Spender allowed to transfer from the account
+        spender: AccountOwner,
+        /// Amount that the spender may transfer
+        allowance: Amount,
+    },
+    /// Increases the amount that a spender may transfer from a (locally owned) account.
+    /// The allowance saturates at the maximum amount.
+    IncreaseAllowance {
+        /// Owner of the account
+        owner: AccountOwner,
+        /// Spender allowed to transfer from the account
+        spender: AccountOwner,
+        /// Amount to add to the allowance
+        amount: Amount,
+    },
+    /// Same as `Transfer` but executed by a spender on behalf of the owner, whose
+    /// allowance is decreased by the transferred amount.
+    TransferFrom {
+        /// Owner to transfer from
+        owner: AccountOwner,
+        /// Spender executing the transfer
+        spender: AccountOwner,
+        /// Amount to be transferred
+        amount: Amount,
+        /// Target account to transfer the amount to
+        target_account: Account,
+    },
+    /// Creates new tokens in an account of the chain where the application was created.
+    /// Only the admin may mint tokens.
+    Mint {
+        /// Owner of the account receiving the new tokens
+        owner: AccountOwner,
+        /// Amount to be minted
+        amount: Amount,
+    },
+    /// Destroys tokens of a (locally owned) account of the chain where the application was
+    /// created.
+    Burn {
+        /// Owner of the account to burn tokens from
+        owner: AccountOwner,
+        /// Amount to be burned
+        amount: Amount,
+    },
 }
 
 /// A fungible response
@@ -69,6 +124,8 @@ pub enum FungibleResponse {
     Balance(Amount),
     /// Ticker symbol response
     TickerSymbol(String),
+    /// Allowance response
+    Allowance(Amount),
 }
 
 /// The initial state to instantiate fungible with
@@ -76,6 +133,9 @@ pub enum FungibleResponse {
 pub struct InitialState {
     /// Accounts and their respective initial balances
     pub accounts: BTreeMap<AccountOwner, Amount>,
+    /// Owner allowed to mint new tokens, if any
+    #[serde(default)]
+    pub admin: Option<AccountOwner>,
 }
 
 /// The parameters to instantiate fungible with
@@ -120,6 +180,8 @@ pub struct Account {
 pub struct InitialStateBuilder {
     /// Accounts and their respective initial balances
     account_balances: BTreeMap<AccountOwner, Amount>,
+    /// Owner allowed to mint new tokens, if any
+    admin: Option<AccountOwner>,
 }
 
 impl InitialStateBuilder {
@@ -129,11 +191,18 @@ impl InitialStateBuilder {
         self
     }
 
+    /// Sets the owner allowed to mint new tokens.
+    pub fn with_admin(mut self, admin: AccountOwner) -> Self {
+        self.admin = Some(admin);
+        self
+    }
+
     /// Returns the serialized initial state of the application, ready to used as the
     /// initialization argument.
     pub fn build(&self) -> InitialState {
         InitialState {
             accounts: self.account_balances.clone(),
+            admin: self.admin,
         }
     }
 }
@@ -15,6 +15,9 @@ impl From<WriteOperation> for wit_system_api::WriteOperation {
                 wit_system_api::WriteOperation::DeletePrefix(key_prefix)
             }
             WriteOperation::Put { key, value } => wit_system_api::WriteOperation::Put((key, value)),
+            WriteOperation::DeleteRange { start, end } => {
+                wit_system_api::WriteOperation::DeleteRange((start, end))
+            }
         }
     }
 }
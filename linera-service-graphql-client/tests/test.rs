@@ -62,6 +62,7 @@ async fn test_end_to_end_queries(config: impl LineraNetConfig) {
     let (contract, service) = client.build_example("fungible").await.unwrap();
     let state = InitialState {
         accounts: BTreeMap::new(),
+        admin: None,
     };
     let params = fungible::Parameters::new("FUN");
     let application_id = client
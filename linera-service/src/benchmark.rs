@@ -155,6 +155,7 @@ async fn benchmark_with_fungible(
                     AccountOwner::User(owner),
                     Amount::from_tokens(num_transactions as u128),
                 )]),
+                admin: None,
             };
             let parameters = Parameters::new(format!("FUN{}", i).leak());
             let application_id = node_service
@@ -0,0 +1,187 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use k8s_openapi::api::core::v1::ResourceRequirements;
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// The values of the validator chart for one validator, written to a values file instead
+/// of being passed one by one with `--set`.
+#[derive(Clone, Debug)]
+pub struct ValidatorChartValues {
+    /// The configuration of the validator, relative to the chart.
+    pub server_config: PathBuf,
+    /// The genesis configuration, relative to the chart.
+    pub genesis_config: PathBuf,
+    pub num_shards: usize,
+    pub image_repository: String,
+    pub image_tag: String,
+    /// The resources requested by the proxy and by each shard, if any.
+    pub resources: Option<ResourceRequirements>,
+    /// The log level of the Linera services, e.g. `debug` or `linera=info`.
+    pub log_level: String,
+    /// Other values, merged into the generated ones and taking precedence over them.
+    /// `Value::Null` adds nothing.
+    pub extra: Value,
+}
+
+/// The layout of the values expected by the chart.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChartValues<'a> {
+    linera_image: String,
+    log_level: &'a str,
+    num_shards: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resources: Option<&'a ResourceRequirements>,
+    validator: ValidatorValues<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ValidatorValues<'a> {
+    server_config: &'a Path,
+    genesis_config: &'a Path,
+}
+
+impl ValidatorChartValues {
+    /// The values of the validator with the given ID in a local network, with the
+    /// configuration files written in the `working` directory of the chart.
+    pub fn new(server_config_id: usize, num_shards: usize) -> Self {
+        Self {
+            server_config: PathBuf::from(format!("working/server_{server_config_id}.json")),
+            genesis_config: PathBuf::from("working/genesis.json"),
+            num_shards,
+            image_repository: "linera".to_string(),
+            image_tag: "latest".to_string(),
+            resources: None,
+            log_level: "debug".to_string(),
+            extra: Value::Null,
+        }
+    }
+
+    /// Sets the repository and the tag of the Linera image from an image name such as
+    /// `linera:latest`, whose tag defaults to `latest`.
+    pub fn set_image(&mut self, image: &str) {
+        let (repository, tag) = match image.rsplit_once(':') {
+            // A colon in the repository separates the port of a registry.
+            Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+            _ => (image, "latest"),
+        };
+        self.image_repository = repository.to_string();
+        self.image_tag = tag.to_string();
+    }
+
+    /// Returns the content of the values file.
+    pub fn to_yaml(&self) -> Result<String> {
+        let values = ChartValues {
+            linera_image: format!("{}:{}", self.image_repository, self.image_tag),
+            log_level: &self.log_level,
+            num_shards: self.num_shards,
+            resources: self.resources.as_ref(),
+            validator: ValidatorValues {
+                server_config: &self.server_config,
+                genesis_config: &self.genesis_config,
+            },
+        };
+        let mut values = serde_yaml::to_value(values)?;
+        merge(&mut values, self.extra.clone());
+        Ok(serde_yaml::to_string(&values)?)
+    }
+}
+
+/// Merges the `overrides` into the `base` values, recursively in mappings.
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (_, Value::Null) => {}
+        (Value::Mapping(base), Value::Mapping(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+#[test]
+fn test_set_image() {
+    let mut values = ValidatorChartValues::new(0, 1);
+    for (image, repository, tag) in [
+        ("linera:v1", "linera", "v1"),
+        ("linera", "linera", "latest"),
+        ("localhost:5000/linera", "localhost:5000/linera", "latest"),
+        ("localhost:5000/linera:v1", "localhost:5000/linera", "v1"),
+    ] {
+        values.set_image(image);
+        assert_eq!(values.image_repository, repository);
+        assert_eq!(values.image_tag, tag);
+    }
+}
+
+#[test]
+fn test_validator_chart_values_to_yaml() -> Result<()> {
+    use std::collections::BTreeMap;
+
+    use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+
+    let values = ValidatorChartValues::new(2, 4);
+    let expected = "
+        lineraImage: linera:latest
+        logLevel: debug
+        numShards: 4
+        validator:
+          serverConfig: working/server_2.json
+          genesisConfig: working/genesis.json
+    ";
+    assert_eq!(
+        serde_yaml::from_str::<Value>(&values.to_yaml()?)?,
+        serde_yaml::from_str::<Value>(expected)?
+    );
+
+    let values = ValidatorChartValues {
+        image_repository: "us-docker.pkg.dev/linera/validator".to_string(),
+        image_tag: "v0.12.1".to_string(),
+        resources: Some(ResourceRequirements {
+            requests: Some(BTreeMap::from([(
+                "cpu".to_string(),
+                Quantity("500m".to_string()),
+            )])),
+            ..ResourceRequirements::default()
+        }),
+        log_level: "linera=info".to_string(),
+        extra: serde_yaml::from_str(
+            "
+            numShards: 8
+            validator:
+              serverConfig: custom.json
+            proxyPort: 19200
+            ",
+        )?,
+        ..ValidatorChartValues::new(0, 4)
+    };
+    let expected = "
+        lineraImage: us-docker.pkg.dev/linera/validator:v0.12.1
+        logLevel: linera=info
+        numShards: 8
+        resources:
+          requests:
+            cpu: 500m
+        validator:
+          serverConfig: custom.json
+          genesisConfig: working/genesis.json
+        proxyPort: 19200
+    ";
+    assert_eq!(
+        serde_yaml::from_str::<Value>(&values.to_yaml()?)?,
+        serde_yaml::from_str::<Value>(expected)?
+    );
+    Ok(())
+}
@@ -1,15 +1,22 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{Context, Result};
-use linera_base::command::{current_binary_parent, CommandExt};
+use anyhow::{ensure, Context, Result};
+use linera_base::command::current_binary_parent;
 use pathdiff::diff_paths;
 use tokio::process::Command;
+use tracing::info;
+
+use crate::cli_wrappers::util::run_and_stream;
 
 pub struct DockerImage {
     name: String,
+    id: String,
 }
 
 impl DockerImage {
@@ -17,14 +24,26 @@ impl DockerImage {
         &self.name
     }
 
+    /// The ID of the image, i.e. the digest of its configuration.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Builds the Linera image of the repository with the given binaries, unless an image
+    /// with the same name already exists.
     pub async fn build(name: &String, binaries: &BuildArg, github_root: &PathBuf) -> Result<Self> {
-        let build_arg = match binaries {
+        if let Some(image) = Self::find(name).await? {
+            info!("The Docker image {name} already exists, skipping the build");
+            return Ok(image);
+        }
+        let mut build_args = BTreeMap::new();
+        match binaries {
             BuildArg::Directory(bin_path) => {
                 // Get the binaries from the specified path
                 let bin_path =
                     diff_paths(bin_path, github_root).context("Getting relative path failed")?;
                 let bin_path_str = bin_path.to_str().context("Getting str failed")?;
-                format!("binaries={bin_path_str}")
+                build_args.insert("binaries".to_string(), bin_path_str.to_string());
             }
             BuildArg::ParentDirectory => {
                 // Get the binaries from current_binary_parent
@@ -33,7 +52,7 @@ impl DockerImage {
                 let bin_path =
                     diff_paths(parent_path, github_root).context("Getting relative path failed")?;
                 let bin_path_str = bin_path.to_str().context("Getting str failed")?;
-                format!("binaries={bin_path_str}")
+                build_args.insert("binaries".to_string(), bin_path_str.to_string());
             }
             BuildArg::Build => {
                 // Build inside the Docker container
@@ -43,38 +62,97 @@ impl DockerImage {
                     "arm" => "aarch",
                     _ => arch,
                 };
-                format!("target={}-unknown-linux-gnu", docker_arch)
+                build_args.insert(
+                    "target".to_string(),
+                    format!("{}-unknown-linux-gnu", docker_arch),
+                );
             }
         };
 
-        let docker_image = Self { name: name.clone() };
+        #[cfg(not(with_testing))]
+        {
+            build_args.insert(
+                "git_commit".to_string(),
+                linera_version::VersionInfo::get()?.git_commit.to_string(),
+            );
+            build_args.insert(
+                "build_date".to_string(),
+                // Same format as $(TZ=UTC date)
+                chrono::Utc::now().format("%a %b %d %T UTC %Y").to_string(),
+            );
+        }
+
+        Self::build_from(
+            github_root,
+            Path::new("docker/Dockerfile"),
+            name,
+            &build_args,
+            None,
+        )
+        .await
+    }
+
+    /// Builds an image named `tag` from the `dockerfile`, relative to the `context_dir`, and
+    /// logs the progress of the build. The `platform` is the one of the host by default.
+    pub async fn build_from(
+        context_dir: &Path,
+        dockerfile: &Path,
+        tag: &str,
+        build_args: &BTreeMap<String, String>,
+        platform: Option<&str>,
+    ) -> Result<Self> {
+        let id_dir = tempfile::tempdir()?;
+        let id_file = id_dir.path().join("image-id");
         let mut command = Command::new("docker");
         command
-            .current_dir(github_root)
+            .current_dir(context_dir)
             .arg("build")
-            .args(["-f", "docker/Dockerfile"])
-            .args(["--build-arg", &build_arg]);
+            .arg("--file")
+            .arg(dockerfile)
+            .args(["--tag", tag])
+            .args(["--progress", "plain"])
+            .arg("--iidfile")
+            .arg(&id_file);
+        for (key, value) in build_args {
+            command.args(["--build-arg", &format!("{key}={value}")]);
+        }
+        if let Some(platform) = platform {
+            command.args(["--platform", platform]);
+        }
+        command.arg(".");
+        run_and_stream(&mut command)
+            .await
+            .with_context(|| format!("Failed to build the Docker image {tag}"))?;
 
-        #[cfg(not(with_testing))]
-        command
-            .args([
-                "--build-arg",
-                &format!(
-                    "git_commit={}",
-                    linera_version::VersionInfo::get()?.git_commit
-                ),
-            ])
-            .args([
-                "--build-arg",
-                &format!(
-                    "build_date={}",
-                    // Same format as $(TZ=UTC date)
-                    chrono::Utc::now().format("%a %b %d %T UTC %Y")
-                ),
-            ]);
+        let id = fs_err::read_to_string(&id_file)?.trim().to_string();
+        Ok(Self {
+            name: tag.to_string(),
+            id,
+        })
+    }
 
-        command.arg(".").args(["-t", name]).spawn_and_wait().await?;
-        Ok(docker_image)
+    /// Returns the local image with the given name, if any.
+    pub async fn find(name: &str) -> Result<Option<Self>> {
+        let output = Command::new("docker")
+            .args(["image", "inspect", "--format", "{{.Id}}", name])
+            .output()
+            .await
+            .context("Failed to run `docker`: is it installed and in the PATH?")?;
+        if output.status.success() {
+            return Ok(Some(Self {
+                name: name.to_string(),
+                id: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            }));
+        }
+        // `docker` exits with the same code if the image does not exist and if e.g. the
+        // daemon is not running, so only the former is recognized from its message.
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        ensure!(
+            stderr.to_lowercase().contains("no such image"),
+            "Failed to inspect the Docker image {name}: {}",
+            stderr.trim()
+        );
+        Ok(None)
     }
 }
 
@@ -0,0 +1,215 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::{Context as _, Result};
+use k8s_openapi::api::{
+    apps::v1::StatefulSet,
+    core::v1::{Pod, Service},
+};
+
+use crate::cli_wrappers::kubectl::is_pod_ready;
+
+/// The name of the service and of the pods of the proxy in the validator chart.
+pub const PROXY_NAME: &str = "proxy";
+
+/// The name of the headless service and of the stateful set of the shards in the validator
+/// chart.
+pub const SHARDS_NAME: &str = "shards";
+
+/// A host name and port in the cluster.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+/// A running shard of a validator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ShardEndpoint {
+    pub pod_name: String,
+    pub endpoint: Endpoint,
+}
+
+/// The endpoints of a validator deployed with the validator chart.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidatorEndpoints {
+    pub proxy: Endpoint,
+    /// The names of the ready pods of the proxy.
+    pub proxy_pods: Vec<String>,
+    /// The ready shards, by index.
+    pub shards: BTreeMap<usize, ShardEndpoint>,
+    /// The indices of the shards of the stateful set which are missing or not ready yet.
+    pub missing_shards: Vec<usize>,
+}
+
+impl ValidatorEndpoints {
+    /// Returns the endpoints of a validator from the Kubernetes resources of the namespace.
+    pub fn from_resources(
+        namespace: &str,
+        proxy_service: &Service,
+        shards_service: &Service,
+        shards: &StatefulSet,
+        pods: &[Pod],
+    ) -> Result<Self> {
+        let proxy = Endpoint {
+            host: format!("{PROXY_NAME}.{namespace}.svc.cluster.local"),
+            port: service_port(proxy_service, "linera-port")?,
+        };
+        let shard_port = service_port(shards_service, "http")?;
+        let num_shards = shards
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.replicas)
+            .unwrap_or(1);
+        let num_shards = usize::try_from(num_shards).context("Invalid number of shards")?;
+
+        let mut proxy_pods = Vec::new();
+        let mut ready_shards = BTreeMap::new();
+        for pod in pods.iter().filter(|pod| is_pod_ready(pod)) {
+            let Some(pod_name) = &pod.metadata.name else {
+                continue;
+            };
+            let labels = pod.metadata.labels.as_ref();
+            match labels
+                .and_then(|labels| labels.get("app"))
+                .map(String::as_str)
+            {
+                Some(PROXY_NAME) => proxy_pods.push(pod_name.clone()),
+                Some(SHARDS_NAME) => {
+                    // The pods of a stateful set are named after their index.
+                    let Some(index) = pod_name
+                        .strip_prefix(&format!("{SHARDS_NAME}-"))
+                        .and_then(|index| index.parse::<usize>().ok())
+                    else {
+                        continue;
+                    };
+                    let endpoint = Endpoint {
+                        host: format!("{pod_name}.{SHARDS_NAME}.{namespace}.svc.cluster.local"),
+                        port: shard_port,
+                    };
+                    ready_shards.insert(
+                        index,
+                        ShardEndpoint {
+                            pod_name: pod_name.clone(),
+                            endpoint,
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+        proxy_pods.sort();
+        let missing_shards = (0..num_shards)
+            .filter(|index| !ready_shards.contains_key(index))
+            .collect();
+        Ok(Self {
+            proxy,
+            proxy_pods,
+            shards: ready_shards,
+            missing_shards,
+        })
+    }
+
+    /// Whether the proxy and all the shards are ready.
+    pub fn is_complete(&self) -> bool {
+        !self.proxy_pods.is_empty() && self.missing_shards.is_empty()
+    }
+}
+
+/// Returns the port of a service with the given name, or its only port.
+fn service_port(service: &Service, port_name: &str) -> Result<u16> {
+    let ports = service
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.ports.as_ref())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let port = match ports {
+        [port] => port,
+        _ => ports
+            .iter()
+            .find(|port| port.name.as_deref() == Some(port_name))
+            .with_context(|| {
+                format!(
+                    "The service {:?} has no port named {port_name}",
+                    service.metadata.name
+                )
+            })?,
+    };
+    u16::try_from(port.port).context("Invalid port")
+}
+
+#[test]
+fn test_validator_endpoints_from_resources() -> Result<()> {
+    let proxy_service: Service = serde_json::from_value(serde_json::json!({
+        "metadata": { "name": "proxy" },
+        "spec": { "ports": [ { "name": "linera-port", "port": 19100 } ] },
+    }))?;
+    let shards_service: Service = serde_json::from_value(serde_json::json!({
+        "metadata": { "name": "shards" },
+        "spec": {
+            "ports": [
+                { "name": "http", "port": 19100 },
+                { "name": "metrics", "port": 21100 },
+            ],
+        },
+    }))?;
+    let shards: StatefulSet = serde_json::from_value(serde_json::json!({
+        "metadata": { "name": "shards" },
+        "spec": {
+            "replicas": 3,
+            "selector": {},
+            "serviceName": "shards",
+            "template": {},
+        },
+    }))?;
+    let pod = |name: &str, app: &str, ready: &str| -> Result<Pod> {
+        Ok(serde_json::from_value(serde_json::json!({
+            "metadata": { "name": name, "labels": { "app": app } },
+            "status": { "conditions": [ { "type": "Ready", "status": ready } ] },
+        }))?)
+    };
+    let pods = [
+        pod("proxy-0", "proxy", "True")?,
+        pod("shards-0", "shards", "True")?,
+        pod("shards-1", "shards", "False")?,
+        pod("scylla-0", "scylla", "True")?,
+    ];
+
+    let endpoints = ValidatorEndpoints::from_resources(
+        "default",
+        &proxy_service,
+        &shards_service,
+        &shards,
+        &pods,
+    )?;
+    assert_eq!(
+        endpoints.proxy.to_string(),
+        "proxy.default.svc.cluster.local:19100"
+    );
+    assert_eq!(endpoints.proxy_pods, ["proxy-0"]);
+    assert_eq!(
+        endpoints.shards,
+        BTreeMap::from([(
+            0,
+            ShardEndpoint {
+                pod_name: "shards-0".to_string(),
+                endpoint: Endpoint {
+                    host: "shards-0.shards.default.svc.cluster.local".to_string(),
+                    port: 19100,
+                },
+            }
+        )])
+    );
+    assert_eq!(endpoints.missing_shards, [1, 2]);
+    assert!(!endpoints.is_complete());
+    Ok(())
+}
@@ -1,38 +1,816 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::path::Path;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use anyhow::Result;
+use anyhow::{bail, ensure, Context as _, Result};
 use fs_extra::dir::CopyOptions;
-use linera_base::command::CommandExt;
-use tokio::process::Command;
+use futures::{stream, StreamExt as _};
+use k8s_openapi::{
+    api::{
+        apps::v1::StatefulSet,
+        core::v1::{Pod, Service},
+    },
+    List,
+};
+use serde::Deserialize;
+use tempfile::TempDir;
+use tokio::{process::Command, time::Instant};
+use tracing::{info, warn};
+
+use crate::cli_wrappers::{
+    chart_values::ValidatorChartValues,
+    endpoints::{ValidatorEndpoints, PROXY_NAME, SHARDS_NAME},
+    kubectl::{is_pod_ready, KubeContext, KubectlInstance},
+    util::{new_artifacts_dir, run_and_capture_stdout, run_with_retries, RetryPolicy},
+};
+
+/// The name of the copy of the validator chart in its temporary directory.
+const CHART_COPY_NAME: &str = "linera-validator";
+
+/// The name of the values file of the validator, next to the copy of the chart.
+const VALIDATOR_VALUES_FILE_NAME: &str = "validator-values.yaml";
+
+/// The name of the release of the validator chart.
+const RELEASE_NAME: &str = "linera-core";
+
+/// The namespaces of the releases of the validator chart which keep their data in
+/// persistent volumes, as set in `helmfile.yaml`.
+const STORAGE_NAMESPACES: [&str; 2] = ["scylla", "scylla-manager"];
+
+/// The label selector of the persistent volume claims of the ScyllaDB clusters, which
+/// `helm` leaves behind when removing their releases.
+const STORAGE_CLAIMS_SELECTOR: &str = "app.kubernetes.io/managed-by=scylla-operator";
+
+/// How often [`HelmFile::wait_ready`] checks the pods.
+const READINESS_POLLING_INTERVAL: Duration = Duration::from_secs(2);
 
 pub struct HelmFile;
 
+/// The options of the deployment of the validator chart. The default options deploy the
+/// chart of the repository in the `default` namespace.
+#[derive(Clone, Debug)]
+pub struct HelmFileOptions {
+    /// The directory of the validator chart, instead of `kubernetes/linera-validator` in
+    /// the repository.
+    pub chart_path: Option<PathBuf>,
+    /// Additional values files of the `linera-core` release. Relative paths are resolved
+    /// against the current directory.
+    pub values_files: Vec<PathBuf>,
+    /// The namespace of the `linera-core` release, instead of `default`.
+    pub namespace: Option<String>,
+    /// Whether to create the namespace of the `linera-core` release if it is missing.
+    pub create_namespace: bool,
+    /// Additional values of the `linera-core` release, as with `helm --set`.
+    pub extra_sets: BTreeMap<String, String>,
+    /// How long `helm` waits for each release to be ready.
+    pub timeout: Duration,
+    /// Whether [`HelmFile::sync`] only simulates the deployment, with `--dry-run=server`.
+    pub dry_run: bool,
+    /// How the `helmfile` and `kubectl` commands failing with a transient error, e.g. while
+    /// the cluster is starting, are retried.
+    pub retry_policy: RetryPolicy,
+    /// Whether [`HelmFile::sync`] polls the pods with [`HelmFile::wait_ready`] instead of
+    /// letting `helm` wait for the releases to be ready.
+    pub poll_readiness: bool,
+}
+
+impl Default for HelmFileOptions {
+    fn default() -> Self {
+        Self {
+            chart_path: None,
+            values_files: Vec::new(),
+            namespace: None,
+            create_namespace: true,
+            extra_sets: BTreeMap::new(),
+            timeout: Duration::from_secs(900),
+            dry_run: false,
+            poll_readiness: false,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+}
+
+/// The state of a `helm` release, as returned by [`HelmFile::status`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReleaseState {
+    Deployed,
+    Failed,
+    PendingInstall,
+    PendingUpgrade,
+    PendingRollback,
+    Superseded,
+    Uninstalling,
+    Uninstalled,
+    #[serde(other)]
+    Unknown,
+}
+
+impl ReleaseState {
+    /// Whether an operation on the release is still in progress.
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self,
+            Self::PendingInstall | Self::PendingUpgrade | Self::PendingRollback
+        )
+    }
+}
+
+/// The status of a `helm` release.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReleaseStatus {
+    pub state: ReleaseState,
+    /// The number of the revision, incremented by each upgrade.
+    pub revision: u32,
+    /// The notes rendered by the chart, if any.
+    pub notes: Option<String>,
+}
+
+impl ReleaseStatus {
+    /// Parses the output of `helm status --output json`.
+    fn from_json(json: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Output {
+            info: Info,
+            version: u32,
+        }
+
+        #[derive(Deserialize)]
+        struct Info {
+            status: ReleaseState,
+            #[serde(default)]
+            notes: Option<String>,
+        }
+
+        let output: Output =
+            serde_json::from_str(json).context("Failed to parse the status of the release")?;
+        Ok(Self {
+            state: output.info.status,
+            revision: output.version,
+            notes: output.info.notes.filter(|notes| !notes.is_empty()),
+        })
+    }
+}
+
+/// The release of the validator chart installed by [`HelmFile::sync`], to be removed
+/// later with [`HelmFile::destroy`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HelmRelease {
+    /// The name of the release.
+    pub name: String,
+    /// The namespace of the release.
+    pub namespace: String,
+    /// The Kubernetes context of the cluster of the release.
+    pub context: KubeContext,
+}
+
+/// A validator deployed by [`HelmFile::sync_many`].
+#[derive(Clone, Debug)]
+pub struct SyncSpec {
+    pub values: ValidatorChartValues,
+    pub context: KubeContext,
+}
+
+/// The outcome of [`HelmFile::apply`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HelmFileChanges {
+    /// The releases were already deployed with the same configuration.
+    Unchanged,
+    /// The releases were upgraded.
+    Applied,
+}
+
+/// The shards of a validator before and after [`HelmFile::scale_shards`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ShardScaling {
+    pub old_num_shards: usize,
+    pub new_num_shards: usize,
+    /// The names of the shard pods before scaling.
+    pub pods_before: Vec<String>,
+    /// The names of the shard pods after scaling, all ready.
+    pub pods_after: Vec<String>,
+}
+
 impl HelmFile {
+    /// Deploys the releases of the validator chart in the cluster of the Kubernetes context,
+    /// and returns the `linera-core` release. If `idempotent` is set, the releases left by a
+    /// previous run are removed first, unless this is a dry run. If the deployment fails, the
+    /// releases are removed so that the cluster can be used again.
     pub async fn sync(
-        server_config_id: usize,
         github_root: &Path,
-        num_shards: usize,
-        cluster_id: u32,
+        values: &ValidatorChartValues,
+        context: &KubeContext,
+        idempotent: bool,
+        options: &HelmFileOptions,
+    ) -> Result<HelmRelease> {
+        context.ensure_exists().await?;
+        // A dry run must leave the cluster untouched.
+        if idempotent && !options.dry_run {
+            Self::destroy(github_root, context, options).await?;
+        }
+
+        let temp_dir = Self::copy_chart(github_root, options)?;
+        let mut command = Self::command(&temp_dir, options);
+        command.arg("sync").args(["--kube-context", context.name()]);
+        if !options.poll_readiness {
+            command.arg("--wait");
+        }
+        Self::add_values(&mut command, &temp_dir, values, options)?;
+        if options.dry_run {
+            command.args(["--args", "--dry-run=server"]);
+        }
+        let mut result = run_with_retries(&mut command, &[0], &options.retry_policy)
+            .await
+            .map(|_| ());
+        if result.is_ok() && options.poll_readiness && !options.dry_run {
+            result = Self::poll_readiness(context, options.timeout).await;
+        }
+
+        if let Err(error) = result {
+            if options.dry_run {
+                return Err(error);
+            }
+            // The diagnostics must be collected before the releases are removed.
+            let error = Self::add_diagnostics(error, context).await;
+            if let Err(cleanup_error) = Self::destroy(github_root, context, options).await {
+                warn!(
+                    "Failed to clean up the releases in the context {context}: {cleanup_error:#}"
+                );
+            }
+            return Err(error.context(format!(
+                "Failed to deploy the validator of {} in the context {context}",
+                values.server_config.display()
+            )));
+        }
+        Ok(HelmRelease {
+            name: RELEASE_NAME.to_string(),
+            namespace: Self::namespace(options).to_string(),
+            context: context.clone(),
+        })
+    }
+
+    /// Deploys several validators, at most `max_concurrency` at a time. Since the names of
+    /// the releases are fixed, each validator must be on a different cluster. All the
+    /// failures are listed in the returned error. If `all_or_nothing` is set, the validators
+    /// which were deployed are removed when another one fails. If all the validators are
+    /// deployed, their releases are returned in the order of the `specs`.
+    pub async fn sync_many(
+        github_root: &Path,
+        specs: &[SyncSpec],
+        max_concurrency: usize,
+        all_or_nothing: bool,
+        options: &HelmFileOptions,
+    ) -> Result<Vec<HelmRelease>> {
+        let results = stream::iter(specs)
+            .enumerate()
+            .map(|(index, spec)| async move {
+                let idempotent = false;
+                let result = Self::sync(
+                    github_root,
+                    &spec.values,
+                    &spec.context,
+                    idempotent,
+                    options,
+                )
+                .await;
+                (index, spec, result)
+            })
+            .buffer_unordered(max_concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut deployed = Vec::new();
+        let mut failures = Vec::new();
+        for (index, spec, result) in results {
+            match result {
+                Ok(release) => deployed.push((index, spec, release)),
+                Err(error) => failures.push(format!(
+                    "- validator of {} in the context {}: {error:#}",
+                    spec.values.server_config.display(),
+                    spec.context
+                )),
+            }
+        }
+        if failures.is_empty() {
+            deployed.sort_by_key(|(index, _, _)| *index);
+            return Ok(deployed
+                .into_iter()
+                .map(|(_, _, release)| release)
+                .collect());
+        }
+        if all_or_nothing && !options.dry_run {
+            for (_, spec, _) in deployed {
+                if let Err(error) = Self::destroy(github_root, &spec.context, options).await {
+                    warn!(
+                        "Failed to remove the validator of {} in the context {}: {error:#}",
+                        spec.values.server_config.display(),
+                        spec.context
+                    );
+                }
+            }
+        }
+        bail!(
+            "Failed to deploy {} of the {} validators:\n{}",
+            failures.len(),
+            specs.len(),
+            failures.join("\n")
+        )
+    }
+
+    /// Returns the status of the release of the validator chart in the Kubernetes context.
+    pub async fn status(context: &KubeContext, options: &HelmFileOptions) -> Result<ReleaseStatus> {
+        context.ensure_exists().await?;
+        let mut command = Command::new("helm");
+        command
+            .args(["status", RELEASE_NAME, "--output", "json"])
+            .args(["--kube-context", context.name()]);
+        if let Some(namespace) = &options.namespace {
+            command.args(["--namespace", namespace]);
+        }
+        let (_code, output) = run_with_retries(&mut command, &[0], &options.retry_policy).await?;
+        ReleaseStatus::from_json(&output)
+    }
+
+    /// Returns the endpoints of the proxy and of the shards of the validator deployed in a
+    /// namespace of the Kubernetes context. The shards which are not ready yet are listed in
+    /// [`ValidatorEndpoints::missing_shards`].
+    pub async fn discover_endpoints(
+        context: &KubeContext,
+        namespace: &str,
+    ) -> Result<ValidatorEndpoints> {
+        let (proxy_service, shards_service, shards, pods) = futures::try_join!(
+            KubectlInstance::get_json::<Service>(context, namespace, &["service", PROXY_NAME]),
+            KubectlInstance::get_json::<Service>(context, namespace, &["service", SHARDS_NAME]),
+            KubectlInstance::get_json::<StatefulSet>(
+                context,
+                namespace,
+                &["statefulset", SHARDS_NAME]
+            ),
+            KubectlInstance::get_json::<List<Pod>>(context, namespace, &["pods"]),
+        )
+        .with_context(|| {
+            format!("Failed to find the validator in the namespace {namespace} of {context}")
+        })?;
+        ValidatorEndpoints::from_resources(
+            namespace,
+            &proxy_service,
+            &shards_service,
+            &shards,
+            &pods.items,
+        )
+    }
+
+    /// Waits until all the pods in the Kubernetes context are ready, logging the progress.
+    /// On timeout, the state of the pods is saved in a new artifacts directory, whose path
+    /// is included in the error.
+    pub async fn wait_ready(context: &KubeContext, timeout: Duration) -> Result<()> {
+        match Self::poll_readiness(context, timeout).await {
+            Ok(()) => Ok(()),
+            Err(error) => Err(Self::add_diagnostics(error, context).await),
+        }
+    }
+
+    /// Saves the state of the pods in the Kubernetes context after the given error, and adds
+    /// the path of the saved files to the error. Failing to save them does not hide the
+    /// error.
+    async fn add_diagnostics(error: anyhow::Error, context: &KubeContext) -> anyhow::Error {
+        let dir = match new_artifacts_dir(context.name()) {
+            Ok(dir) => dir,
+            Err(dir_error) => {
+                warn!("Failed to create a directory for the diagnostics: {dir_error:#}");
+                return error;
+            }
+        };
+        if let Err(diagnostics_error) = KubectlInstance::collect_diagnostics(context, &dir).await {
+            warn!(
+                "Failed to collect the diagnostics in the context {context}: {diagnostics_error:#}"
+            );
+        }
+        error.context(format!(
+            "The diagnostics in the context {context} were saved in {}",
+            dir.display()
+        ))
+    }
+
+    /// Polls the pods in the Kubernetes context until they are all ready.
+    async fn poll_readiness(context: &KubeContext, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let mut last_num_ready = None;
+        loop {
+            let pods = KubectlInstance::list_pods(context).await?;
+            let not_ready = pods
+                .iter()
+                .filter(|pod| !is_pod_ready(pod))
+                .filter_map(|pod| pod.metadata.name.as_deref())
+                .collect::<Vec<_>>();
+            if not_ready.is_empty() {
+                info!(
+                    "All the {} pods in the context {context} are ready",
+                    pods.len()
+                );
+                return Ok(());
+            }
+            let num_ready = pods.len() - not_ready.len();
+            if last_num_ready != Some(num_ready) {
+                info!(
+                    "{num_ready}/{} pods in the context {context} are ready, waiting for {}",
+                    pods.len(),
+                    not_ready.join(", ")
+                );
+                last_num_ready = Some(num_ready);
+            }
+            if Instant::now() >= deadline {
+                bail!(
+                    "Timed out after {timeout:?} waiting for the pods in the context {context}: {} \
+                     are not ready",
+                    not_ready.join(", ")
+                );
+            }
+            tokio::time::sleep(READINESS_POLLING_INTERVAL).await;
+        }
+    }
+
+    /// Renders the manifests of the `linera-core` release for the given validator, with
+    /// the same values as [`HelmFile::sync`], without accessing any cluster.
+    pub async fn template(
+        github_root: &Path,
+        values: &ValidatorChartValues,
+        options: &HelmFileOptions,
+    ) -> Result<String> {
+        let temp_dir = Self::copy_chart(github_root, options)?;
+        let mut command = Self::command(&temp_dir, options);
+        command
+            .args(["--selector", &format!("name={RELEASE_NAME}")])
+            .arg("template")
+            .arg("--skip-deps");
+        Self::add_values(&mut command, &temp_dir, values, options)?;
+        let (_code, manifests) = run_and_capture_stdout(&mut command, &[0]).await?;
+        Ok(manifests)
+    }
+
+    /// Upgrades the releases of the validator chart in place, e.g. to change the number of
+    /// shards, then restarts the proxy and waits for the rollout. This requires the
+    /// `helm-diff` plugin, which `helmfile` uses to detect the changes.
+    pub async fn apply(
+        github_root: &Path,
+        values: &ValidatorChartValues,
+        context: &KubeContext,
+        options: &HelmFileOptions,
+    ) -> Result<HelmFileChanges> {
+        context.ensure_exists().await?;
+        let temp_dir = Self::copy_chart(github_root, options)?;
+        let mut command = Self::command(&temp_dir, options);
+        command
+            .arg("apply")
+            // Exits with code 2 if there were changes, and 0 otherwise.
+            .arg("--detailed-exitcode")
+            .args(["--kube-context", context.name()]);
+        Self::add_values(&mut command, &temp_dir, values, options)?;
+        let (code, _output) =
+            run_with_retries(&mut command, &[0, 2], &options.retry_policy).await?;
+        if code == 0 {
+            info!(
+                "The validator of {} in the context {context} is unchanged",
+                values.server_config.display()
+            );
+            return Ok(HelmFileChanges::Unchanged);
+        }
+        if let Err(error) = Self::roll_out(context, options).await {
+            return Err(Self::add_diagnostics(error, context).await);
+        }
+        Ok(HelmFileChanges::Applied)
+    }
+
+    /// Restarts the proxy, which only reads the configuration of the validator when it
+    /// starts, e.g. to see new shards. Then waits until the proxy and the shards are rolled
+    /// out.
+    async fn roll_out(context: &KubeContext, options: &HelmFileOptions) -> Result<()> {
+        let namespace = Self::namespace(options);
+        let kubectl = |args: &[&str]| {
+            let mut command = Command::new("kubectl");
+            command
+                .args(args)
+                .args(["--namespace", namespace])
+                .args(["--context", context.name()]);
+            command
+        };
+        let proxy = format!("deployment/{PROXY_NAME}");
+        let shards = format!("statefulset/{SHARDS_NAME}");
+        run_with_retries(
+            &mut kubectl(&["rollout", "restart", &proxy]),
+            &[0],
+            &options.retry_policy,
+        )
+        .await?;
+        let timeout = format!("--timeout={}s", options.timeout.as_secs());
+        for resource in [&proxy, &shards] {
+            run_and_capture_stdout(
+                &mut kubectl(&["rollout", "status", resource, &timeout]),
+                &[0],
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Changes the number of shards of the validator in the Kubernetes context to
+    /// `values.num_shards` with [`HelmFile::apply`], and waits for the new shards to be
+    /// ready. Removing shards loses their state, so scaling down is refused unless
+    /// `allow_scale_down` is set. Fails if the shards which are kept were recreated.
+    pub async fn scale_shards(
+        github_root: &Path,
+        values: &ValidatorChartValues,
+        context: &KubeContext,
+        allow_scale_down: bool,
+        options: &HelmFileOptions,
+    ) -> Result<ShardScaling> {
+        let new_num_shards = values.num_shards;
+        ensure!(new_num_shards > 0, "A validator needs at least one shard");
+        context.ensure_exists().await?;
+        let namespace = Self::namespace(options);
+        let shards: StatefulSet =
+            KubectlInstance::get_json(context, namespace, &["statefulset", SHARDS_NAME]).await?;
+        let old_num_shards = shards.spec.and_then(|spec| spec.replicas).unwrap_or(1);
+        let old_num_shards = usize::try_from(old_num_shards)?;
+        ensure!(
+            new_num_shards >= old_num_shards || allow_scale_down,
+            "Refusing to scale the shards in the context {context} down from {old_num_shards} \
+             to {new_num_shards}, which would lose the state of the removed shards"
+        );
+        let pods_before = Self::shard_pod_uids(context, namespace).await?;
+
+        let changes = Self::apply(github_root, values, context, options).await?;
+        ensure!(
+            changes == HelmFileChanges::Applied || new_num_shards == old_num_shards,
+            "The number of shards in the context {context} was not changed"
+        );
+
+        let endpoints = Self::discover_endpoints(context, namespace).await?;
+        ensure!(
+            endpoints.missing_shards.is_empty() && endpoints.shards.len() == new_num_shards,
+            "Expected {new_num_shards} shards in the context {context}, but the shards {:?} \
+             are ready and {:?} are missing",
+            endpoints.shards.keys().collect::<Vec<_>>(),
+            endpoints.missing_shards
+        );
+        let pods_after = Self::shard_pod_uids(context, namespace).await?;
+        for (name, uid) in &pods_before {
+            if let Some(new_uid) = pods_after.get(name) {
+                ensure!(
+                    new_uid == uid,
+                    "The shard {name} in the context {context} was recreated while scaling"
+                );
+            }
+        }
+        info!(
+            "Scaled the shards in the context {context} from {old_num_shards} to \
+             {new_num_shards}"
+        );
+        Ok(ShardScaling {
+            old_num_shards,
+            new_num_shards,
+            pods_before: pods_before.into_keys().collect(),
+            pods_after: pods_after.into_keys().collect(),
+        })
+    }
+
+    /// Returns the UIDs of the shard pods of the namespace, by pod name.
+    async fn shard_pod_uids(
+        context: &KubeContext,
+        namespace: &str,
+    ) -> Result<BTreeMap<String, String>> {
+        let selector = format!("app={SHARDS_NAME}");
+        let pods: List<Pod> =
+            KubectlInstance::get_json(context, namespace, &["pods", "--selector", &selector])
+                .await?;
+        Ok(pods
+            .items
+            .into_iter()
+            .filter_map(|pod| Some((pod.metadata.name?, pod.metadata.uid?)))
+            .collect())
+    }
+
+    /// Removes the releases of the validator chart from the Kubernetes context, together
+    /// with the persistent volume claims of their ScyllaDB clusters, which `helm` leaves
+    /// behind. Releases which are not installed are skipped. The `options` must be the ones
+    /// of the deployment, so that the releases are looked up in the right namespace.
+    pub async fn destroy(
+        github_root: &Path,
+        context: &KubeContext,
+        options: &HelmFileOptions,
     ) -> Result<()> {
-        let chart_dir = format!("{}/kubernetes/linera-validator", github_root.display());
+        context.ensure_exists().await?;
+        let temp_dir = Self::copy_chart(github_root, options)?;
+        let mut command = Self::command(&temp_dir, options);
+        command
+            .arg("destroy")
+            .args(["--kube-context", context.name()]);
+        run_with_retries(&mut command, &[0], &options.retry_policy).await?;
 
-        let temp_dir = tempfile::tempdir()?;
-        fs_extra::copy_items(&[&chart_dir], temp_dir.path(), &CopyOptions::new())?;
+        for namespace in STORAGE_NAMESPACES {
+            let mut command = Command::new("kubectl");
+            command
+                .args(["delete", "pvc", "--selector", STORAGE_CLAIMS_SELECTOR])
+                .args(["--namespace", namespace])
+                .args(["--context", context.name()]);
+            run_with_retries(&mut command, &[0], &options.retry_policy).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the namespace of the `linera-core` release.
+    fn namespace(options: &HelmFileOptions) -> &str {
+        options.namespace.as_deref().unwrap_or("default")
+    }
 
-        Command::new("helmfile")
-            .current_dir(temp_dir.path().join("linera-validator"))
+    /// Returns a `helmfile` command in the copy of the chart, with the namespace and the
+    /// timeout of the `options`.
+    fn command(temp_dir: &TempDir, options: &HelmFileOptions) -> Command {
+        let mut command = Command::new("helmfile");
+        command
+            .current_dir(temp_dir.path().join(CHART_COPY_NAME))
             .env(
-                "LINERA_HELMFILE_SET_SERVER_CONFIG",
-                format!("working/server_{server_config_id}.json"),
+                "LINERA_HELMFILE_CREATE_NAMESPACE",
+                options.create_namespace.to_string(),
             )
-            .env("LINERA_HELMFILE_SET_NUM_SHARDS", num_shards.to_string())
-            .arg("sync")
-            .arg("--wait")
-            .args(["--kube-context", &format!("kind-{}", cluster_id)])
-            .spawn_and_wait()
-            .await
+            .env(
+                "LINERA_HELMFILE_TIMEOUT",
+                options.timeout.as_secs().to_string(),
+            );
+        if let Some(namespace) = &options.namespace {
+            command.env("LINERA_HELMFILE_NAMESPACE", namespace);
+        }
+        command
+    }
+
+    /// Adds the values of the validator, then the values files and the values of the
+    /// `options`, to the `linera-core` release of a `helmfile` command. The values of the
+    /// validator are written to a file in `temp_dir`. Unlike `helmfile --values`, this leaves
+    /// the other releases of `helmfile.yaml` unchanged.
+    fn add_values(
+        command: &mut Command,
+        temp_dir: &TempDir,
+        values: &ValidatorChartValues,
+        options: &HelmFileOptions,
+    ) -> Result<()> {
+        let validator_values_file = temp_dir.path().join(VALIDATOR_VALUES_FILE_NAME);
+        fs_err::write(&validator_values_file, values.to_yaml()?)?;
+        // `helmfile` runs in the copy of the chart.
+        let values_files = std::iter::once(Ok(validator_values_file))
+            .chain(options.values_files.iter().map(std::path::absolute))
+            .collect::<Result<Vec<_>, _>>()?;
+        command
+            .env(
+                "LINERA_HELMFILE_EXTRA_VALUES_LINERA_CORE",
+                serde_json::to_string(&values_files)?,
+            )
+            .env(
+                "LINERA_HELMFILE_EXTRA_SETS_LINERA_CORE",
+                serde_json::to_string(&options.extra_sets)?,
+            );
+        Ok(())
     }
+
+    /// Copies the validator chart to a temporary directory, so that `helmfile` does not
+    /// write in the repository.
+    fn copy_chart(github_root: &Path, options: &HelmFileOptions) -> Result<TempDir> {
+        let chart_dir = match &options.chart_path {
+            Some(chart_path) => chart_path.clone(),
+            None => github_root.join("kubernetes/linera-validator"),
+        };
+        let temp_dir = tempfile::tempdir()?;
+        let mut copy_options = CopyOptions::new();
+        copy_options.copy_inside = true;
+        fs_extra::dir::copy(
+            &chart_dir,
+            temp_dir.path().join(CHART_COPY_NAME),
+            &copy_options,
+        )
+        .with_context(|| format!("Failed to copy the chart {}", chart_dir.display()))?;
+        Ok(temp_dir)
+    }
+}
+
+#[tokio::test]
+async fn test_template() -> Result<()> {
+    let chart_dir = tempfile::tempdir()?;
+    let chart_path = chart_dir.path();
+    fs_err::write(
+        chart_path.join("Chart.yaml"),
+        "apiVersion: v2\nname: linera-validator\nversion: 0.1.0\n",
+    )?;
+    fs_err::write(
+        chart_path.join("helmfile.yaml"),
+        "releases:\n  \
+           - name: linera-core\n    \
+             chart: .\n    \
+             values:\n      \
+               - values-local.yaml.gotmpl\n",
+    )?;
+    fs_err::write(
+        chart_path.join("values-local.yaml.gotmpl"),
+        "numShards: 10\nlogLevel: debug\n",
+    )?;
+    fs_err::create_dir(chart_path.join("templates"))?;
+    fs_err::write(
+        chart_path.join("templates/shards.yaml"),
+        "apiVersion: apps/v1\n\
+         kind: StatefulSet\n\
+         metadata:\n  \
+           name: shards\n  \
+           labels:\n    \
+             logLevel: {{ .Values.logLevel }}\n\
+         spec:\n  \
+           replicas: {{ .Values.numShards }}\n",
+    )?;
+
+    let options = HelmFileOptions {
+        chart_path: Some(chart_path.to_path_buf()),
+        extra_sets: BTreeMap::from([("logLevel".to_string(), "info".to_string())]),
+        ..HelmFileOptions::default()
+    };
+    let values = ValidatorChartValues::new(0, 3);
+    let manifests = HelmFile::template(Path::new("/nonexistent"), &values, &options).await?;
+    assert!(manifests.contains("kind: StatefulSet"));
+    assert!(manifests.contains("replicas: 3"));
+    assert!(manifests.contains("logLevel: info"));
+    Ok(())
+}
+
+#[test]
+fn test_release_status_from_json() -> Result<()> {
+    let json = r#"{
+        "name": "linera-core",
+        "info": {
+            "first_deployed": "2024-09-10T12:00:00Z",
+            "status": "pending-upgrade",
+            "notes": ""
+        },
+        "version": 3,
+        "namespace": "default"
+    }"#;
+    let status = ReleaseStatus::from_json(json)?;
+    assert!(status.state.is_pending());
+    assert_eq!(
+        status,
+        ReleaseStatus {
+            state: ReleaseState::PendingUpgrade,
+            revision: 3,
+            notes: None,
+        }
+    );
+
+    let json = r#"{"info": {"status": "unknown-state", "notes": "Hello"}, "version": 1}"#;
+    let status = ReleaseStatus::from_json(json)?;
+    assert_eq!(status.state, ReleaseState::Unknown);
+    assert_eq!(status.notes.as_deref(), Some("Hello"));
+    Ok(())
+}
+
+#[test]
+fn test_add_values() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let values = ValidatorChartValues::new(1, 2);
+    let options = HelmFileOptions {
+        values_files: vec![PathBuf::from("/tmp/values.yaml")],
+        extra_sets: BTreeMap::from([("numShards".to_string(), "4".to_string())]),
+        ..HelmFileOptions::default()
+    };
+    let mut command = Command::new("helmfile");
+    HelmFile::add_values(&mut command, &temp_dir, &values, &options)?;
+    let validator_values_file = temp_dir.path().join(VALIDATOR_VALUES_FILE_NAME);
+    // The values only apply to the `linera-core` release, not to the whole command.
+    assert_eq!(command.as_std().get_args().count(), 0);
+    let envs = command
+        .as_std()
+        .get_envs()
+        .map(|(key, value)| (key.to_str().unwrap(), value.unwrap().to_str().unwrap()))
+        .collect::<BTreeMap<_, _>>();
+    assert_eq!(
+        envs,
+        BTreeMap::from([
+            (
+                "LINERA_HELMFILE_EXTRA_SETS_LINERA_CORE",
+                r#"{"numShards":"4"}"#
+            ),
+            (
+                "LINERA_HELMFILE_EXTRA_VALUES_LINERA_CORE",
+                serde_json::to_string(&[
+                    validator_values_file.as_path(),
+                    Path::new("/tmp/values.yaml")
+                ])?
+                .as_str()
+            ),
+        ])
+    );
+    assert_eq!(
+        fs_err::read_to_string(validator_values_file)?,
+        values.to_yaml()?
+    );
+    Ok(())
 }
@@ -1,14 +1,49 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::Result;
-use linera_base::command::CommandExt;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::{bail, Context as _, Result};
 use rand::Rng;
+use tempfile::TempDir;
 use tokio::process::Command;
+use tracing::{info, warn};
+
+use crate::cli_wrappers::{
+    kubectl::KubeContext,
+    util::{run_and_capture, run_and_capture_stdout},
+};
 
+/// What [`KindCluster::create`] does if a cluster with the same name already exists.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IfExists {
+    /// Uses the existing cluster.
+    Reuse,
+    /// Returns an error.
+    Fail,
+}
+
+/// A `kind` cluster. The clones of a cluster share it: if [`KindCluster::set_delete_on_drop`]
+/// was called, the cluster is deleted when the last clone is dropped.
 #[derive(Clone)]
 pub struct KindCluster {
-    id: u32,
+    inner: Arc<KindClusterInner>,
+}
+
+struct KindClusterInner {
+    name: String,
+    /// The `kind` program managing the cluster.
+    program: PathBuf,
+    delete_on_drop: AtomicBool,
+    deleted: AtomicBool,
+    /// The directory of the exported `kubeconfig` file.
+    kubeconfig_dir: TempDir,
 }
 
 impl KindCluster {
@@ -16,37 +51,193 @@ impl KindCluster {
         rand::thread_rng().gen_range(0..99999)
     }
 
-    pub async fn create() -> Result<Self> {
-        let cluster = Self {
-            id: Self::get_random_cluster_id(),
-        };
+    /// Creates a cluster with a random numeric name.
+    pub async fn create_with_random_name() -> Result<Self> {
+        let name = Self::get_random_cluster_id().to_string();
+        Self::create(name, None, IfExists::Fail).await
+    }
 
-        Command::new("kind")
-            .args(["create", "cluster"])
-            .args(["--name", cluster.id().to_string().as_str()])
-            .spawn_and_wait()
-            .await?;
+    /// Creates a cluster with the given name and optional `kind` configuration file.
+    pub async fn create(
+        name: impl Into<String>,
+        config: Option<PathBuf>,
+        if_exists: IfExists,
+    ) -> Result<Self> {
+        Self::create_with_program("kind", name, config, if_exists).await
+    }
 
-        Ok(cluster)
+    /// Creates a cluster like [`KindCluster::create`], managing it with the `kind` program
+    /// at `program` instead of the one in the `PATH`.
+    pub async fn create_with_program(
+        program: impl Into<PathBuf>,
+        name: impl Into<String>,
+        config: Option<PathBuf>,
+        if_exists: IfExists,
+    ) -> Result<Self> {
+        let program = program.into();
+        let name = name.into();
+        let exists = Self::list(&program).await?.contains(&name);
+        match (exists, if_exists) {
+            (true, IfExists::Fail) => bail!("The kind cluster {name} already exists"),
+            (true, IfExists::Reuse) => info!("Reusing the kind cluster {name}"),
+            (false, _) => {
+                let mut command = Command::new(&program);
+                command.args(["create", "cluster", "--name", &name]);
+                if let Some(config) = &config {
+                    command.arg("--config").arg(config);
+                }
+                run_and_capture(&mut command, &[0])
+                    .await
+                    .with_context(|| format!("Failed to create the kind cluster {name}"))?;
+            }
+        }
+
+        Ok(Self {
+            inner: Arc::new(KindClusterInner {
+                name,
+                program,
+                delete_on_drop: AtomicBool::new(false),
+                deleted: AtomicBool::new(false),
+                kubeconfig_dir: tempfile::tempdir()?,
+            }),
+        })
+    }
+
+    /// Returns the names of the existing clusters.
+    async fn list(program: &Path) -> Result<Vec<String>> {
+        let mut command = Command::new(program);
+        command.args(["get", "clusters"]);
+        let (_code, output) = run_and_capture_stdout(&mut command, &[0]).await?;
+        Ok(output.lines().map(str::to_owned).collect())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// Returns the Kubernetes context created by `kind` for the cluster.
+    pub fn context(&self) -> KubeContext {
+        KubeContext::new(format!("kind-{}", self.inner.name))
     }
 
-    pub fn id(&self) -> u32 {
-        self.id
+    /// Sets whether the cluster is deleted when the last clone of this value is dropped.
+    /// Clusters are kept by default, e.g. so that they can be investigated after a failure.
+    pub fn set_delete_on_drop(&self, delete_on_drop: bool) {
+        self.inner
+            .delete_on_drop
+            .store(delete_on_drop, Ordering::Relaxed);
+    }
+
+    /// Writes the `kubeconfig` file of the cluster and returns its path, e.g. for tools
+    /// which do not use the default configuration.
+    pub async fn kubeconfig_path(&self) -> Result<PathBuf> {
+        let mut command = Command::new(&self.inner.program);
+        command.args(["get", "kubeconfig", "--name", self.name()]);
+        let (_code, kubeconfig) = run_and_capture_stdout(&mut command, &[0]).await?;
+        let path = self.inner.kubeconfig_dir.path().join("kubeconfig");
+        fs_err::write(&path, kubeconfig)?;
+        Ok(path)
     }
 
     pub async fn delete(&self) -> Result<()> {
-        Command::new("kind")
-            .args(["delete", "cluster"])
-            .args(["--name", &self.id.to_string()])
-            .spawn_and_wait()
-            .await
+        let mut command = Command::new(&self.inner.program);
+        command.args(["delete", "cluster", "--name", self.name()]);
+        run_and_capture(&mut command, &[0]).await?;
+        self.inner.deleted.store(true, Ordering::Relaxed);
+        Ok(())
     }
 
-    pub async fn load_docker_image(&self, docker_image: &String) -> Result<()> {
-        Command::new("kind")
+    pub async fn load_docker_image(&self, docker_image: &str) -> Result<()> {
+        let mut command = Command::new(&self.inner.program);
+        command
             .args(["load", "docker-image", docker_image])
-            .args(["--name", self.id.to_string().as_str()])
-            .spawn_and_wait()
-            .await
+            .args(["--name", self.name()]);
+        run_and_capture(&mut command, &[0]).await?;
+        Ok(())
+    }
+}
+
+impl Drop for KindClusterInner {
+    fn drop(&mut self) {
+        if !self.delete_on_drop.load(Ordering::Relaxed) || self.deleted.load(Ordering::Relaxed) {
+            return;
+        }
+        // Dropping may happen outside of a runtime, so the command is run synchronously.
+        let result = std::process::Command::new(&self.program)
+            .args(["delete", "cluster", "--name", &self.name])
+            .output();
+        match result {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => warn!(
+                "Failed to delete the kind cluster {}: {}",
+                self.name,
+                String::from_utf8_lossy(&output.stderr)
+            ),
+            Err(error) => warn!("Failed to delete the kind cluster {}: {error}", self.name),
+        }
     }
 }
+
+/// Writes a fake `kind` program to `dir`, which logs its arguments, and returns the paths
+/// of the program and of its log.
+#[cfg(test)]
+fn install_fake_kind(dir: &Path) -> Result<(PathBuf, PathBuf)> {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let log = dir.join("kind.log");
+    let script = format!(
+        "#!/bin/sh\n\
+         echo \"$@\" >> {log}\n\
+         case \"$*\" in\n  \
+           \"get clusters\") echo existing ;;\n  \
+           \"get kubeconfig\"*) echo \"apiVersion: v1\" ;;\n\
+         esac\n",
+        log = log.display()
+    );
+    let path = dir.join("kind");
+    fs_err::write(&path, script)?;
+    fs_err::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    Ok((path, log))
+}
+
+#[tokio::test]
+async fn test_kind_cluster_commands() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let (kind, log) = install_fake_kind(dir.path())?;
+
+    assert!(
+        KindCluster::create_with_program(&kind, "existing", None, IfExists::Fail)
+            .await
+            .is_err()
+    );
+    let cluster =
+        KindCluster::create_with_program(&kind, "existing", None, IfExists::Reuse).await?;
+    assert_eq!(cluster.context().name(), "kind-existing");
+    let kubeconfig_path = cluster.kubeconfig_path().await?;
+    assert_eq!(fs_err::read_to_string(kubeconfig_path)?, "apiVersion: v1\n");
+
+    let config = dir.path().join("kind-config.yaml");
+    let cluster =
+        KindCluster::create_with_program(&kind, "new", Some(config.clone()), IfExists::Fail)
+            .await?;
+    cluster.load_docker_image("linera:latest").await?;
+    cluster.set_delete_on_drop(true);
+    drop(cluster.clone());
+    assert!(!fs_err::read_to_string(&log)?.contains("delete cluster --name new"));
+    drop(cluster);
+
+    let commands = fs_err::read_to_string(&log)?;
+    assert_eq!(
+        commands.lines().collect::<Vec<_>>(),
+        [
+            "get clusters".to_string(),
+            "get clusters".to_string(),
+            "get kubeconfig --name existing".to_string(),
+            "get clusters".to_string(),
+            format!("create cluster --name new --config {}", config.display()),
+            "load docker-image linera:latest --name new".to_string(),
+            "delete cluster --name new".to_string(),
+        ]
+    );
+    Ok(())
+}
@@ -1,42 +1,209 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::{Context, Result};
-use linera_base::command::CommandExt;
-use tokio::process::{Child, Command};
+use std::{fmt, path::Path};
 
+use anyhow::{ensure, Context, Result};
+use k8s_openapi::{api::core::v1::Pod, List};
+use serde::de::DeserializeOwned;
+use tokio::process::Command;
+use tracing::warn;
+
+use crate::cli_wrappers::{
+    port_forward::PortForward,
+    util::{run_and_capture_stdout, run_with_retries, RetryPolicy},
+};
+
+/// The number of the last lines of the logs of each container saved by
+/// [`KubectlInstance::collect_diagnostics`].
+const NUM_LOG_LINES_IN_DIAGNOSTICS: usize = 200;
+
+/// The name of a context of the Kubernetes configuration, which selects a cluster and the
+/// credentials to access it.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct KubeContext(String);
+
+impl KubeContext {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    /// The context of the kind cluster with the given ID.
+    pub fn kind(cluster_id: u32) -> Self {
+        Self(format!("kind-{}", cluster_id))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// Checks that the context is in the Kubernetes configuration, to fail with a clear
+    /// error before running `helm` or `kubectl` with it.
+    pub async fn ensure_exists(&self) -> Result<()> {
+        let mut command = Command::new("kubectl");
+        command.args(["config", "get-contexts", "--output", "name"]);
+        let (_code, output) = run_and_capture_stdout(&mut command, &[0]).await?;
+        ensure!(
+            output.lines().any(|line| line.trim() == self.0),
+            "The Kubernetes context {self} does not exist: the available contexts are {}",
+            output.split_whitespace().collect::<Vec<_>>().join(", ")
+        );
+        Ok(())
+    }
+}
+
+impl fmt::Display for KubeContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Default)]
 pub struct KubectlInstance {
-    pub port_forward_children: Vec<Child>,
+    pub port_forwards: Vec<PortForward>,
 }
 
 impl KubectlInstance {
-    pub fn new(port_forward_children: Vec<Child>) -> Self {
-        Self {
-            port_forward_children,
-        }
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forwards the local port `local_port` to the same port of a pod of the namespace,
+    /// until the forwardings are dropped.
+    pub async fn port_forward(
+        &mut self,
+        context: &KubeContext,
+        namespace: &str,
+        pod_name: &str,
+        local_port: u16,
+    ) -> Result<()> {
+        let port_forward =
+            PortForward::open_resource(context, namespace, pod_name, local_port, local_port)
+                .await
+                .context("Port forwarding failed")?;
+        self.port_forwards.push(port_forward);
+        Ok(())
     }
 
-    pub fn port_forward(&mut self, pod_name: &str, ports: &str, cluster_id: u32) -> Result<()> {
-        let port_forward_child = Command::new("kubectl")
-            .arg("port-forward")
-            .arg(pod_name)
-            .arg(ports)
-            .args(["--context", &format!("kind-{}", cluster_id)])
-            .spawn()
-            .context("Port forwarding failed")?;
+    /// Writes the state of the pods of the cluster, the recent events, the description of
+    /// the pods which are not ready and the last lines of the logs of the Linera containers
+    /// to the directory `dir`, to investigate a failed deployment.
+    pub async fn collect_diagnostics(context: &KubeContext, dir: &Path) -> Result<()> {
+        fs_err::create_dir_all(dir)?;
+        let kubectl = |args: &[&str]| {
+            let mut command = Command::new("kubectl");
+            command.args(args).args(["--context", context.name()]);
+            command
+        };
 
-        self.port_forward_children.push(port_forward_child);
+        let (_code, pods) =
+            run_and_capture_stdout(&mut kubectl(&["get", "pods", "-A", "-o", "wide"]), &[0])
+                .await?;
+        fs_err::write(dir.join("pods.txt"), pods)?;
+        let (_code, events) = run_and_capture_stdout(
+            &mut kubectl(&["get", "events", "-A", "--sort-by=.lastTimestamp"]),
+            &[0],
+        )
+        .await?;
+        fs_err::write(dir.join("events.txt"), events)?;
+
+        for pod in Self::list_pods(context).await? {
+            let (Some(name), Some(namespace)) = (&pod.metadata.name, &pod.metadata.namespace)
+            else {
+                continue;
+            };
+            if !is_pod_ready(&pod) {
+                // The pod may have been deleted since it was listed.
+                match run_and_capture_stdout(
+                    &mut kubectl(&["describe", "pod", name, "--namespace", namespace]),
+                    &[0],
+                )
+                .await
+                {
+                    Ok((_code, description)) => fs_err::write(
+                        dir.join(format!("describe-{namespace}-{name}.txt")),
+                        description,
+                    )?,
+                    Err(error) => {
+                        warn!("Failed to describe the pod {namespace}/{name}: {error:#}")
+                    }
+                }
+            }
+            let containers = pod.spec.iter().flat_map(|spec| &spec.containers);
+            for container in containers {
+                let is_linera = container
+                    .image
+                    .as_deref()
+                    .is_some_and(|image| image.contains("linera"));
+                if !is_linera {
+                    continue;
+                }
+                let mut command = kubectl(&[
+                    "logs",
+                    name,
+                    "--container",
+                    &container.name,
+                    "--namespace",
+                    namespace,
+                    "--tail",
+                    &NUM_LOG_LINES_IN_DIAGNOSTICS.to_string(),
+                ]);
+                // Containers which did not start have no logs.
+                let logs = match run_and_capture_stdout(&mut command, &[0]).await {
+                    Ok((_code, logs)) => logs,
+                    Err(error) => format!("{error:#}"),
+                };
+                fs_err::write(
+                    dir.join(format!("logs-{namespace}-{name}-{}.txt", container.name)),
+                    logs,
+                )?;
+            }
+        }
         Ok(())
     }
 
-    pub async fn get_pods(&mut self, cluster_id: u32) -> Result<String> {
-        let output = Command::new("kubectl")
+    /// Returns the pods of all the namespaces of the cluster.
+    pub async fn list_pods(context: &KubeContext) -> Result<Vec<Pod>> {
+        let mut command = Command::new("kubectl");
+        command
+            .args(["get", "pods", "--all-namespaces", "--output", "json"])
+            .args(["--context", context.name()]);
+        let (_code, output) = run_with_retries(&mut command, &[0], &RetryPolicy::default()).await?;
+        let pods: List<Pod> =
+            serde_json::from_str(&output).context("Failed to parse the list of pods")?;
+        Ok(pods.items)
+    }
+
+    /// Returns the output of `kubectl get <resource>` in a namespace, parsed from JSON.
+    /// `resource` is e.g. `["service", "proxy"]`, or `["pods"]` to get a `List<Pod>`.
+    pub async fn get_json<T: DeserializeOwned>(
+        context: &KubeContext,
+        namespace: &str,
+        resource: &[&str],
+    ) -> Result<T> {
+        let mut command = Command::new("kubectl");
+        command
             .arg("get")
-            .arg("pods")
-            .args(["--context", &format!("kind-{}", cluster_id)])
-            .spawn_and_wait_for_stdout()
-            .await?;
+            .args(resource)
+            .args(["--namespace", namespace, "--output", "json"])
+            .args(["--context", context.name()]);
+        let (_code, output) = run_with_retries(&mut command, &[0], &RetryPolicy::default()).await?;
+        serde_json::from_str(&output)
+            .with_context(|| format!("Failed to parse the output of `kubectl get {resource:?}`"))
+    }
+}
 
-        Ok(String::from_utf8_lossy(output.as_bytes()).to_string())
+/// Returns whether a pod is ready, or has completed successfully.
+pub fn is_pod_ready(pod: &Pod) -> bool {
+    let Some(status) = &pod.status else {
+        return false;
+    };
+    if status.phase.as_deref() == Some("Succeeded") {
+        return true;
     }
+    status
+        .conditions
+        .iter()
+        .flatten()
+        .any(|condition| condition.type_ == "Ready" && condition.status == "True")
 }
@@ -1,9 +1,9 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
-use anyhow::{anyhow, bail, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
 use async_trait::async_trait;
 use futures::{future, lock::Mutex};
 use k8s_openapi::api::core::v1::Pod;
@@ -12,25 +12,44 @@ use linera_base::{
     command::{resolve_binary, CommandExt},
     data_types::Amount,
 };
+use linera_client::config::ValidatorServerConfig;
 use linera_execution::ResourceControlPolicy;
+use linera_rpc::config::ShardConfig;
 use tempfile::{tempdir, TempDir};
 use tokio::process::Command;
+use tracing::info;
 #[cfg(with_testing)]
 use {
     crate::cli_wrappers::wallet::FaucetOption, linera_base::command::current_binary_parent,
     tokio::sync::OnceCell,
 };
 
-use crate::cli_wrappers::{
-    docker::{BuildArg, DockerImage},
-    helmfile::HelmFile,
-    kind::KindCluster,
-    kubectl::KubectlInstance,
-    local_net::PathProvider,
-    util::get_github_root,
-    ClientWrapper, LineraNet, LineraNetConfig, Network, OnClientDrop,
+use crate::{
+    cli_wrappers::{
+        chart_values::ValidatorChartValues,
+        docker::{BuildArg, DockerImage},
+        endpoints::ValidatorEndpoints,
+        helmfile::{HelmFile, HelmFileOptions, ShardScaling, SyncSpec},
+        kind::KindCluster,
+        kubectl::KubectlInstance,
+        local_net::PathProvider,
+        logs::LogCollector,
+        prerequisites::{self, Tool},
+        util::get_github_root,
+        ClientWrapper, LineraNet, LineraNetConfig, Network, OnClientDrop,
+    },
+    util::read_json,
 };
 
+/// The maximal number of validators deployed at the same time, each on its own cluster.
+const MAX_CONCURRENT_DEPLOYMENTS: usize = 4;
+
+/// The port of the shards, in their pods.
+const SHARD_PORT: u16 = 19100;
+
+/// The port of the metrics of the shards, in their pods.
+const SHARD_METRICS_PORT: u16 = 21100;
+
 #[cfg(with_testing)]
 static SHARED_LOCAL_KUBERNETES_TESTING_NET: OnceCell<(
     Arc<Mutex<LocalKubernetesNet>>,
@@ -49,6 +68,8 @@ pub struct LocalKubernetesNetConfig {
     pub no_build: bool,
     pub docker_image_name: String,
     pub policy: ResourceControlPolicy,
+    /// The namespace of the validators in their clusters, instead of `default`.
+    pub namespace: Option<String>,
 }
 
 /// A wrapper of [`LocalKubernetesNetConfig`] to create a shared local Kubernetes network
@@ -70,6 +91,7 @@ pub struct LocalKubernetesNet {
     kind_clusters: Vec<KindCluster>,
     num_initial_validators: usize,
     num_shards: usize,
+    namespace: String,
 }
 
 #[cfg(with_testing)]
@@ -103,6 +125,7 @@ impl SharedLocalKubernetesNetTestingConfig {
             no_build: false,
             docker_image_name: String::from("linera:latest"),
             policy: ResourceControlPolicy::devnet(),
+            namespace: None,
         })
     }
 }
@@ -116,9 +139,10 @@ impl LineraNetConfig for LocalKubernetesNetConfig {
             self.num_initial_validators > 0,
             "There should be at least one initial validator"
         );
+        prerequisites::check_all(&Tool::ALL).await?;
 
         let clusters = future::join_all((0..self.num_initial_validators).map(|_| async {
-            KindCluster::create()
+            KindCluster::create_with_random_name()
                 .await
                 .expect("Creating kind cluster should not fail")
         }))
@@ -130,10 +154,11 @@ impl LineraNetConfig for LocalKubernetesNetConfig {
             self.binaries,
             self.no_build,
             self.docker_image_name,
-            KubectlInstance::new(Vec::new()),
+            KubectlInstance::new(),
             clusters,
             self.num_initial_validators,
             self.num_shards,
+            self.namespace.unwrap_or_else(|| "default".to_string()),
         )?;
 
         let client = net.make_client().await;
@@ -219,7 +244,7 @@ impl LineraNet for Arc<Mutex<LocalKubernetesNet>> {
 impl LineraNet for LocalKubernetesNet {
     async fn ensure_is_running(&mut self) -> Result<()> {
         let client = Client::try_default().await?;
-        let pods: Api<Pod> = Api::namespaced(client, "default");
+        let pods: Api<Pod> = Api::namespaced(client, &self.namespace);
 
         let list_params = ListParams::default().labels("app=proxy");
         for pod in pods.list(&list_params).await? {
@@ -276,12 +301,8 @@ impl LineraNet for LocalKubernetesNet {
 
     async fn terminate(&mut self) -> Result<()> {
         let mut kubectl_instance = self.kubectl_instance.lock().await;
+        kubectl_instance.port_forwards.clear();
         let mut errors = Vec::new();
-        for port_forward_child in &mut kubectl_instance.port_forward_children {
-            if let Err(e) = port_forward_child.kill().await {
-                errors.push(e.into());
-            }
-        }
 
         for kind_cluster in &mut self.kind_clusters {
             if let Err(e) = kind_cluster.delete().await {
@@ -319,6 +340,7 @@ impl LocalKubernetesNet {
         kind_clusters: Vec<KindCluster>,
         num_initial_validators: usize,
         num_shards: usize,
+        namespace: String,
     ) -> Result<Self> {
         Ok(Self {
             network,
@@ -332,9 +354,18 @@ impl LocalKubernetesNet {
             kind_clusters,
             num_initial_validators,
             num_shards,
+            namespace,
         })
     }
 
+    /// Returns the options to deploy the validators in their namespace.
+    fn helmfile_options(&self) -> HelmFileOptions {
+        HelmFileOptions {
+            namespace: Some(self.namespace.clone()),
+            ..HelmFileOptions::default()
+        }
+    }
+
     async fn command_for_binary(&self, name: &'static str) -> Result<Command> {
         let path = resolve_binary(name, env!("CARGO_PKG_NAME")).await?;
         let mut command = Command::new(path);
@@ -348,14 +379,15 @@ impl LocalKubernetesNet {
         let port = 19100 + server_number;
         let internal_port = 20100;
         let metrics_port = 21100;
+        let namespace = &self.namespace;
         let mut content = format!(
             r#"
                 server_config_path = "server_{n}.json"
                 host = "127.0.0.1"
                 port = {port}
-                internal_host = "proxy-internal.default.svc.cluster.local"
+                internal_host = "proxy-internal.{namespace}.svc.cluster.local"
                 internal_port = {internal_port}
-                metrics_host = "proxy-internal.default.svc.cluster.local"
+                metrics_host = "proxy-internal.{namespace}.svc.cluster.local"
                 metrics_port = {metrics_port}
                 [external_protocol]
                 Grpc = "ClearText"
@@ -364,16 +396,15 @@ impl LocalKubernetesNet {
             "#
         );
         for k in 0..self.num_shards {
-            let shard_port = 19100;
-            let shard_metrics_port = 21100;
+            let shard_host = self.shard_host(k);
             content.push_str(&format!(
                 r#"
 
                 [[shards]]
-                host = "shards-{k}.shards.default.svc.cluster.local"
-                port = {shard_port}
-                metrics_host = "shards-{k}.shards.default.svc.cluster.local"
-                metrics_port = {shard_metrics_port}
+                host = "{shard_host}"
+                port = {SHARD_PORT}
+                metrics_host = "{shard_host}"
+                metrics_port = {SHARD_METRICS_PORT}
                 "#
             ));
         }
@@ -386,6 +417,40 @@ impl LocalKubernetesNet {
         })
     }
 
+    /// Returns the host of the shard `k` of a validator, for the other pods of its cluster.
+    fn shard_host(&self, k: usize) -> String {
+        format!("shards-{k}.shards.{}.svc.cluster.local", self.namespace)
+    }
+
+    /// Writes the configuration of the validator `server_number` with `num_shards` shards,
+    /// keeping its keys, to the `working` directory of the chart, so that the
+    /// `validator-config` ConfigMap lists the new shards once the release is upgraded.
+    fn update_server_config(
+        &self,
+        server_number: usize,
+        num_shards: usize,
+        github_root: &Path,
+    ) -> Result<()> {
+        let server_config_filename = format!("server_{server_number}.json");
+        let mut server_config: ValidatorServerConfig =
+            read_json(self.tmp_dir.path().join(&server_config_filename))?;
+        server_config.internal_network.shards = (0..num_shards)
+            .map(|k| ShardConfig {
+                host: self.shard_host(k),
+                port: SHARD_PORT,
+                metrics_host: self.shard_host(k),
+                metrics_port: Some(SHARD_METRICS_PORT),
+            })
+            .collect();
+        fs_err::write(
+            github_root
+                .join("kubernetes/linera-validator/working")
+                .join(&server_config_filename),
+            serde_json::to_vec_pretty(&server_config)?,
+        )?;
+        Ok(())
+    }
+
     async fn generate_initial_validator_config(&mut self) -> Result<()> {
         let mut command = self.command_for_binary("linera-server").await?;
         command.arg("generate");
@@ -407,12 +472,24 @@ impl LocalKubernetesNet {
     async fn run(&mut self) -> Result<()> {
         let github_root = get_github_root().await?;
         // Build Docker image
-        let docker_image_name = if self.no_build {
-            self.docker_image_name.clone()
+        let docker_image = if self.no_build {
+            DockerImage::find(&self.docker_image_name)
+                .await?
+                .with_context(|| {
+                    format!(
+                        "The Docker image {} was not found: build it or omit `--no-build`",
+                        self.docker_image_name
+                    )
+                })?
         } else {
-            DockerImage::build(&self.docker_image_name, &self.binaries, &github_root).await?;
-            self.docker_image_name.clone()
+            DockerImage::build(&self.docker_image_name, &self.binaries, &github_root).await?
         };
+        info!(
+            "Using the Docker image {} ({})",
+            docker_image.name(),
+            docker_image.id()
+        );
+        let docker_image_name = docker_image.name();
 
         let base_dir = github_root
             .join("kubernetes")
@@ -423,54 +500,125 @@ impl LocalKubernetesNet {
             base_dir.join("genesis.json"),
         )?;
 
-        let kubectl_instance_clone = self.kubectl_instance.clone();
-        let tmp_dir_path_clone = self.tmp_dir.path().to_path_buf();
+        let tmp_dir_path = self.tmp_dir.path();
         let num_shards = self.num_shards;
 
-        let mut validators_initialization_futures = Vec::new();
-        for (i, kind_cluster) in self.kind_clusters.iter().cloned().enumerate() {
-            let base_dir = base_dir.clone();
-            let github_root = github_root.clone();
-
-            let kubectl_instance = kubectl_instance_clone.clone();
-            let tmp_dir_path = tmp_dir_path_clone.clone();
-
-            let docker_image_name = docker_image_name.clone();
-            let future = async move {
-                let cluster_id = kind_cluster.id();
-                kind_cluster.load_docker_image(&docker_image_name).await?;
-
-                let server_config_filename = format!("server_{}.json", i);
-                fs_err::copy(
-                    tmp_dir_path.join(&server_config_filename),
-                    base_dir.join(&server_config_filename),
-                )?;
-
-                HelmFile::sync(i, &github_root, num_shards, cluster_id).await?;
-
-                let mut kubectl_instance = kubectl_instance.lock().await;
-                let output = kubectl_instance.get_pods(cluster_id).await?;
-                let validator_pod_name = output
-                    .split_whitespace()
-                    .find(|&t| t.contains("proxy"))
-                    .expect("Getting validator pod name should not fail");
-
-                let local_port = 19100 + i;
-                kubectl_instance.port_forward(
-                    validator_pod_name,
-                    &format!("{local_port}:{local_port}"),
-                    cluster_id,
-                )?;
-
-                Result::<(), anyhow::Error>::Ok(())
-            };
+        let preparation_futures = self
+            .kind_clusters
+            .iter()
+            .enumerate()
+            .map(|(i, kind_cluster)| {
+                let base_dir = &base_dir;
+                async move {
+                    kind_cluster.load_docker_image(docker_image_name).await?;
+
+                    let server_config_filename = format!("server_{}.json", i);
+                    fs_err::copy(
+                        tmp_dir_path.join(&server_config_filename),
+                        base_dir.join(&server_config_filename),
+                    )?;
+                    Result::<(), anyhow::Error>::Ok(())
+                }
+            });
+        future::try_join_all(preparation_futures).await?;
+
+        let specs = self
+            .kind_clusters
+            .iter()
+            .enumerate()
+            .map(|(i, kind_cluster)| {
+                let mut values = ValidatorChartValues::new(i, num_shards);
+                values.set_image(docker_image_name);
+                SyncSpec {
+                    values,
+                    context: kind_cluster.context(),
+                }
+            })
+            .collect::<Vec<_>>();
+        // The clusters are created for this network, so they have no releases yet.
+        let all_or_nothing = true;
+        HelmFile::sync_many(
+            &github_root,
+            &specs,
+            MAX_CONCURRENT_DEPLOYMENTS,
+            all_or_nothing,
+            &self.helmfile_options(),
+        )
+        .await?;
 
-            validators_initialization_futures.push(future);
+        let mut kubectl_instance = self.kubectl_instance.lock().await;
+        for (i, kind_cluster) in self.kind_clusters.iter().enumerate() {
+            let context = kind_cluster.context();
+            let endpoints = HelmFile::discover_endpoints(&context, &self.namespace).await?;
+            let validator_pod_name = endpoints
+                .proxy_pods
+                .first()
+                .with_context(|| format!("No proxy is ready in the context {context}"))?;
+
+            let local_port = 19100 + u16::try_from(i)?;
+            kubectl_instance
+                .port_forward(&context, &self.namespace, validator_pod_name, local_port)
+                .await?;
         }
+        Ok(())
+    }
 
-        future::join_all(validators_initialization_futures)
-            .await
-            .into_iter()
+    /// Changes the number of shards of the running validators in place with
+    /// [`HelmFile::scale_shards`], without redeploying them, and waits for the new shards to
+    /// be running. The configurations of the validators are updated with the new shards, and
+    /// their proxies restarted.
+    pub async fn set_num_shards(
+        &mut self,
+        num_shards: usize,
+        allow_scale_down: bool,
+    ) -> Result<Vec<ShardScaling>> {
+        let github_root = get_github_root().await?;
+        for i in 0..self.kind_clusters.len() {
+            self.update_server_config(i, num_shards, &github_root)?;
+        }
+        let options = self.helmfile_options();
+        let futures = self
+            .kind_clusters
+            .iter()
+            .enumerate()
+            .map(|(i, kind_cluster)| {
+                let github_root = &github_root;
+                let options = &options;
+                let mut values = ValidatorChartValues::new(i, num_shards);
+                values.set_image(&self.docker_image_name);
+                async move {
+                    let context = kind_cluster.context();
+                    HelmFile::scale_shards(
+                        github_root,
+                        &values,
+                        &context,
+                        allow_scale_down,
+                        options,
+                    )
+                    .await
+                }
+            });
+        let scalings = future::try_join_all(futures).await?;
+        self.num_shards = num_shards;
+        Ok(scalings)
+    }
+
+    /// Starts collecting the logs of the pods matching `selector`, e.g. `app=shards`, with
+    /// one collector per validator.
+    pub fn collect_logs(&self, selector: &str) -> Result<Vec<LogCollector>> {
+        self.kind_clusters
+            .iter()
+            .map(|kind_cluster| {
+                LogCollector::start(&kind_cluster.context(), &self.namespace, selector)
+            })
             .collect()
     }
+
+    /// Returns the endpoints of each validator, as deployed in its cluster.
+    pub async fn validator_endpoints(&self) -> Result<Vec<ValidatorEndpoints>> {
+        let futures = self.kind_clusters.iter().map(|kind_cluster| async move {
+            HelmFile::discover_endpoints(&kind_cluster.context(), &self.namespace).await
+        });
+        future::try_join_all(futures).await
+    }
 }
@@ -192,6 +192,10 @@ pub enum Database {
     ScyllaDb,
 }
 
+/// How long the processes of a stopped validator have to exit after `SIGTERM`, before
+/// being killed.
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
 /// The processes of a running validator.
 struct Validator {
     proxy: Child,
@@ -220,6 +224,53 @@ impl Validator {
         Ok(())
     }
 
+    /// Stops the proxy and the servers gracefully. Every process is stopped even if
+    /// stopping another one failed, and the errors are reported together.
+    async fn stop(&mut self) -> Result<()> {
+        let mut errors = Vec::new();
+        if let Err(e) = Self::stop_child(&mut self.proxy).await {
+            errors.push(e.context("stopping validator proxy"));
+        }
+        for server in &mut self.servers {
+            if let Err(e) = Self::stop_child(server).await {
+                errors.push(e.context("stopping validator server"));
+            }
+        }
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(errors
+            .into_iter()
+            .fold(anyhow!("Failed to stop the validator"), |acc, e| {
+                acc.context(format!("{e:#}"))
+            }))
+    }
+
+    /// Sends `SIGTERM` to a process, and kills it if it is still running after
+    /// [`STOP_GRACE_PERIOD`].
+    async fn stop_child(child: &mut Child) -> Result<()> {
+        if let Some(status) = child.try_wait()? {
+            warn!("Child process {child:?} had already exited with status: {status}");
+            return Ok(());
+        }
+        if let Some(pid) = child.id() {
+            let status = Command::new("kill")
+                .args(["-s", "TERM", &pid.to_string()])
+                .status()
+                .await?;
+            ensure!(status.success(), "Failed to send SIGTERM to {child:?}");
+            match tokio::time::timeout(STOP_GRACE_PERIOD, child.wait()).await {
+                Ok(status) => {
+                    info!("Child process {pid} stopped with status: {}", status?);
+                    return Ok(());
+                }
+                Err(_) => warn!("Child process {pid} did not stop after SIGTERM, killing it"),
+            }
+        }
+        child.kill().await?;
+        Ok(())
+    }
+
     fn add_server(&mut self, server: Child) {
         self.servers.push(server)
     }
@@ -627,6 +678,37 @@ impl LocalNet {
         self.running_validators.insert(validator, validator_proxy);
         Ok(())
     }
+
+    /// Stops the processes of a validator gracefully. Its configuration and storage are
+    /// kept, so that it can be started again with [`LocalNet::restart_validator`].
+    pub async fn stop_validator(&mut self, validator: usize) -> Result<()> {
+        self.running_validators
+            .remove(&validator)
+            .with_context(|| format!("validator {validator} is not running"))?
+            .stop()
+            .await
+            .with_context(|| format!("stopping validator {validator}"))
+    }
+
+    /// Stops a validator if it is running, then starts it again with the same
+    /// configuration, ports and storage.
+    pub async fn restart_validator(&mut self, validator: usize) -> Result<()> {
+        if self.running_validators.contains_key(&validator) {
+            self.stop_validator(validator).await?;
+        }
+        self.start_validator(validator).await
+    }
+
+    /// Returns an error with the exit status of the first process of a validator which
+    /// stopped unexpectedly, if any. [`LineraNet::ensure_is_running`] checks all the
+    /// validators.
+    pub fn ensure_validator_is_running(&mut self, validator: usize) -> Result<()> {
+        self.running_validators
+            .get_mut(&validator)
+            .with_context(|| format!("validator {validator} is not running"))?
+            .ensure_is_running()
+            .with_context(|| format!("validator {validator} stopped unexpectedly"))
+    }
 }
 
 #[cfg(with_testing)]
@@ -658,6 +740,15 @@ impl LocalNet {
         Ok(())
     }
 
+    /// Returns the process ID of the server of a shard of a running validator.
+    pub fn server_pid(&self, validator: usize, shard: usize) -> Option<u32> {
+        self.running_validators
+            .get(&validator)?
+            .servers
+            .get(shard)?
+            .id()
+    }
+
     pub fn remove_validator(&mut self, validator: usize) -> Result<()> {
         self.running_validators
             .remove(&validator)
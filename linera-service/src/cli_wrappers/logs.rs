@@ -0,0 +1,333 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{bail, Context as _, Result};
+use chrono::{DateTime, FixedOffset};
+use k8s_openapi::{api::core::v1::Pod, List};
+use linera_base::command::CommandExt;
+use regex::Regex;
+use tokio::{
+    io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader},
+    process::Command,
+    sync::Notify,
+    task::{AbortHandle, JoinHandle, JoinSet},
+    time::Instant,
+};
+use tracing::{debug, warn};
+
+use crate::cli_wrappers::{
+    kubectl::{KubeContext, KubectlInstance},
+    util::new_artifacts_dir,
+};
+
+/// How often a [`LogCollector`] looks for new pods.
+const POD_POLLING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long to wait before following the logs of a pod again, e.g. after a restart.
+const REATTACH_DELAY: Duration = Duration::from_secs(1);
+
+/// A line of the logs of a pod.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LogLine {
+    pub pod_name: String,
+    pub text: String,
+}
+
+/// Collects the logs of the pods matching a label selector into one file per pod. The pods
+/// created later are followed too, and the logs of a restarted pod are followed again. The
+/// collection stops when this value is dropped.
+pub struct LogCollector {
+    dir: PathBuf,
+    lines: Arc<LogLines>,
+    supervisor: JoinHandle<()>,
+}
+
+/// The lines received by a [`LogCollector`].
+#[derive(Default)]
+struct LogLines {
+    lines: Mutex<Vec<LogLine>>,
+    added: Notify,
+}
+
+impl LogCollector {
+    /// Starts collecting the logs of the pods matching `selector`, e.g. `app=shards`, in a
+    /// namespace of the Kubernetes context. The files are written to a new artifacts
+    /// directory.
+    pub fn start(context: &KubeContext, namespace: &str, selector: &str) -> Result<Self> {
+        let dir = new_artifacts_dir(&format!("logs-{context}"))?;
+        let lines = Arc::new(LogLines::default());
+        let supervisor = tokio::spawn(Self::supervise(
+            context.clone(),
+            namespace.to_owned(),
+            selector.to_owned(),
+            dir.clone(),
+            lines.clone(),
+        ));
+        Ok(Self {
+            dir,
+            lines,
+            supervisor,
+        })
+    }
+
+    /// Returns the directory of the log files, which are named after the pods.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Waits for a line matching `regex`, including the lines received before this call,
+    /// and returns the first one.
+    pub async fn wait_for_line(&self, regex: &Regex, timeout: Duration) -> Result<LogLine> {
+        self.lines.wait_for_line(regex, timeout).await
+    }
+
+    /// Follows the logs of each pod matching `selector`, until aborted.
+    async fn supervise(
+        context: KubeContext,
+        namespace: String,
+        selector: String,
+        dir: PathBuf,
+        lines: Arc<LogLines>,
+    ) {
+        // Dropping the set aborts the followers.
+        let mut followers = JoinSet::new();
+        let mut handles = BTreeMap::<String, AbortHandle>::new();
+        loop {
+            let result = KubectlInstance::get_json::<List<Pod>>(
+                &context,
+                &namespace,
+                &["pods", "--selector", &selector],
+            )
+            .await;
+            match result {
+                Ok(pods) => {
+                    let names = pods
+                        .items
+                        .into_iter()
+                        .filter_map(|pod| pod.metadata.name)
+                        .collect::<BTreeSet<_>>();
+                    // The logs of deleted pods cannot be followed anymore.
+                    handles.retain(|name, handle| {
+                        let exists = names.contains(name);
+                        if !exists {
+                            handle.abort();
+                        }
+                        exists
+                    });
+                    for name in names {
+                        if handles.contains_key(&name) {
+                            continue;
+                        }
+                        let handle = followers.spawn(Self::follow(
+                            context.clone(),
+                            namespace.clone(),
+                            name.clone(),
+                            dir.join(format!("{name}.log")),
+                            lines.clone(),
+                        ));
+                        handles.insert(name, handle);
+                    }
+                }
+                Err(error) => warn!(
+                    "Failed to list the pods matching {selector} in the context {context}: \
+                     {error:#}"
+                ),
+            }
+            tokio::time::sleep(POD_POLLING_INTERVAL).await;
+        }
+    }
+
+    /// Follows the logs of a pod, reattaching whenever `kubectl logs` stops.
+    async fn follow(
+        context: KubeContext,
+        namespace: String,
+        pod_name: String,
+        path: PathBuf,
+        lines: Arc<LogLines>,
+    ) {
+        let mut last_timestamp = None;
+        loop {
+            let result = Self::follow_once(
+                &context,
+                &namespace,
+                &pod_name,
+                &path,
+                &lines,
+                &mut last_timestamp,
+            )
+            .await;
+            if let Err(error) = result {
+                debug!("Stopped following the logs of the pod {pod_name}: {error:#}");
+            }
+            tokio::time::sleep(REATTACH_DELAY).await;
+        }
+    }
+
+    /// Follows the logs of a pod until `kubectl logs` stops, e.g. because the pod was
+    /// restarted. The lines which are not more recent than `last_timestamp` were already
+    /// received, and are skipped.
+    async fn follow_once(
+        context: &KubeContext,
+        namespace: &str,
+        pod_name: &str,
+        path: &Path,
+        lines: &LogLines,
+        last_timestamp: &mut Option<DateTime<FixedOffset>>,
+    ) -> Result<()> {
+        let mut command = Command::new("kubectl");
+        command
+            .args([
+                "logs",
+                pod_name,
+                "--follow",
+                "--timestamps",
+                "--all-containers",
+            ])
+            .args(["--namespace", namespace])
+            .args(["--context", context.name()])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        let since = *last_timestamp;
+        if let Some(since) = since {
+            command.arg(format!("--since-time={}", since.to_rfc3339()));
+        }
+        let mut child = command.spawn_into()?;
+        let stdout = child
+            .stdout
+            .take()
+            .context("The standard output is not piped")?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+
+        let mut reader = BufReader::new(stdout).lines();
+        while let Some(line) = reader.next_line().await? {
+            let (timestamp, text) = parse_log_line(&line);
+            if let Some(timestamp) = timestamp {
+                // `--since-time` has a precision of one second.
+                if since.is_some_and(|since| timestamp <= since) {
+                    continue;
+                }
+                *last_timestamp = (*last_timestamp).max(Some(timestamp));
+            }
+            file.write_all(format!("{text}\n").as_bytes()).await?;
+            lines.push(LogLine {
+                pod_name: pod_name.to_owned(),
+                text: text.to_owned(),
+            });
+        }
+        let status = child.wait().await?;
+        if !status.success() {
+            bail!("`kubectl logs` exited with {status}");
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LogCollector {
+    fn drop(&mut self) {
+        // Dropping the task drops the followers, which kills their commands.
+        self.supervisor.abort();
+    }
+}
+
+impl LogLines {
+    fn push(&self, line: LogLine) {
+        self.lines.lock().unwrap().push(line);
+        self.added.notify_waiters();
+    }
+
+    async fn wait_for_line(&self, regex: &Regex, timeout: Duration) -> Result<LogLine> {
+        let deadline = Instant::now() + timeout;
+        let mut next_index = 0;
+        loop {
+            // Created before looking at the lines, so that no new line is missed.
+            let added = self.added.notified();
+            {
+                let lines = self.lines.lock().unwrap();
+                if let Some(line) = lines[next_index..]
+                    .iter()
+                    .find(|line| regex.is_match(&line.text))
+                {
+                    return Ok(line.clone());
+                }
+                next_index = lines.len();
+            }
+            if tokio::time::timeout_at(deadline, added).await.is_err() {
+                bail!("No line of the logs matches {regex} after {timeout:?}");
+            }
+        }
+    }
+}
+
+/// Splits a line of `kubectl logs --timestamps` into its timestamp, if any, and its text.
+fn parse_log_line(line: &str) -> (Option<DateTime<FixedOffset>>, &str) {
+    let Some((timestamp, text)) = line.split_once(' ') else {
+        return (None, line);
+    };
+    match DateTime::parse_from_rfc3339(timestamp) {
+        Ok(timestamp) => (Some(timestamp), text),
+        Err(_) => (None, line),
+    }
+}
+
+#[test]
+fn test_parse_log_line() {
+    let (timestamp, text) =
+        parse_log_line("2024-05-02T10:11:12.123456789Z INFO linera_server: Block confirmed");
+    assert_eq!(
+        timestamp,
+        Some(DateTime::parse_from_rfc3339("2024-05-02T10:11:12.123456789Z").unwrap())
+    );
+    assert_eq!(text, "INFO linera_server: Block confirmed");
+    assert_eq!(parse_log_line("no timestamp"), (None, "no timestamp"));
+}
+
+#[tokio::test]
+async fn test_wait_for_line() -> Result<()> {
+    let lines = Arc::new(LogLines::default());
+    let line = |text: &str| LogLine {
+        pod_name: "shards-0".to_string(),
+        text: text.to_string(),
+    };
+    lines.push(line("Starting the server"));
+
+    let regex = Regex::new("^Starting")?;
+    assert_eq!(
+        lines.wait_for_line(&regex, Duration::ZERO).await?,
+        line("Starting the server")
+    );
+
+    let regex = Regex::new(r"confirmed at height \d+")?;
+    let producer = tokio::spawn({
+        let lines = lines.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            lines.push(line("Processing a block"));
+            lines.push(line("Block confirmed at height 2"));
+        }
+    });
+    assert_eq!(
+        lines.wait_for_line(&regex, Duration::from_secs(5)).await?,
+        line("Block confirmed at height 2")
+    );
+    producer.await?;
+
+    let regex = Regex::new("height 3")?;
+    assert!(lines
+        .wait_for_line(&regex, Duration::from_millis(100))
+        .await
+        .is_err());
+    Ok(())
+}
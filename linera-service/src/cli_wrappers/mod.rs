@@ -4,9 +4,15 @@
 //! Helper module to call the binaries of `linera-service` with appropriate command-line
 //! arguments.
 
+#[cfg(feature = "kubernetes")]
+/// The values of the validator chart
+mod chart_values;
 #[cfg(feature = "kubernetes")]
 /// How to run docker operations
 pub mod docker;
+#[cfg(feature = "kubernetes")]
+/// How to find the endpoints of a deployed validator
+pub mod endpoints;
 
 #[cfg(feature = "kubernetes")]
 /// How to run helmfile operations
@@ -22,6 +28,15 @@ mod kubectl;
 pub mod local_kubernetes_net;
 /// How to run Linera validators locally as native processes.
 pub mod local_net;
+#[cfg(feature = "kubernetes")]
+/// How to collect the logs of Kubernetes pods
+pub mod logs;
+#[cfg(feature = "kubernetes")]
+/// How to forward ports of Kubernetes resources
+mod port_forward;
+#[cfg(feature = "kubernetes")]
+/// How to check the versions of the tools used by the Kubernetes wrappers
+pub mod prerequisites;
 #[cfg(all(with_testing, feature = "remote-net"))]
 /// How to connect to running GCP DevNet.
 pub mod remote_net;
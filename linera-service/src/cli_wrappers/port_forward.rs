@@ -0,0 +1,154 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{net::TcpListener, process::Stdio, time::Duration};
+
+use anyhow::{bail, Context as _, Result};
+use linera_base::command::CommandExt;
+use tokio::{
+    net::TcpStream,
+    process::{Child, Command},
+    task::JoinHandle,
+    time::Instant,
+};
+use tracing::warn;
+
+use crate::cli_wrappers::kubectl::KubeContext;
+
+/// How long [`PortForward::open`] waits for the tunnel to accept connections.
+const OPENING_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait before restarting a port forwarding which stopped.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// A tunnel from a local port to a port of a Kubernetes resource, run by
+/// `kubectl port-forward`. The command is restarted if it stops, e.g. because the pod was
+/// restarted, and it is killed when this value is dropped.
+pub struct PortForward {
+    local_port: u16,
+    supervisor: JoinHandle<()>,
+}
+
+impl PortForward {
+    /// Forwards a free local port to the port `remote_port` of a service.
+    pub async fn open(
+        context: &KubeContext,
+        namespace: &str,
+        service: &str,
+        remote_port: u16,
+    ) -> Result<Self> {
+        let local_port = TcpListener::bind(("127.0.0.1", 0))?.local_addr()?.port();
+        let resource = format!("svc/{service}");
+        Self::open_resource(context, namespace, &resource, local_port, remote_port).await
+    }
+
+    /// Forwards the given local port to the port `remote_port` of a resource, e.g. a pod
+    /// name or `svc/<service>`.
+    pub async fn open_resource(
+        context: &KubeContext,
+        namespace: &str,
+        resource: &str,
+        local_port: u16,
+        remote_port: u16,
+    ) -> Result<Self> {
+        let mut command = Command::new("kubectl");
+        command
+            .arg("port-forward")
+            .arg(resource)
+            .arg(format!("{local_port}:{remote_port}"))
+            .args(["--namespace", namespace])
+            .args(["--context", context.name()])
+            // `kubectl` logs each connection.
+            .stdout(Stdio::null());
+        Self::supervise(command, local_port)
+            .await
+            .with_context(|| format!("Failed to forward the port {remote_port} of {resource}"))
+    }
+
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+
+    /// Runs the port forwarding `command`, waits until the local port accepts connections,
+    /// and restarts the command whenever it stops.
+    async fn supervise(mut command: Command, local_port: u16) -> Result<Self> {
+        let mut child = command.spawn_into()?;
+        Self::wait_until_open(&mut child, local_port).await?;
+
+        let supervisor = tokio::spawn(async move {
+            loop {
+                match child.wait().await {
+                    Ok(status) => warn!(
+                        "The forwarding of the local port {local_port} stopped with {status}, \
+                         restarting it"
+                    ),
+                    Err(error) => warn!(
+                        "Failed to wait for the forwarding of the local port {local_port}: \
+                         {error}, restarting it"
+                    ),
+                }
+                child = loop {
+                    tokio::time::sleep(RESTART_DELAY).await;
+                    match command.spawn_into() {
+                        Ok(child) => break child,
+                        Err(error) => warn!(
+                            "Failed to restart the forwarding of the local port {local_port}: \
+                             {error:#}"
+                        ),
+                    }
+                };
+            }
+        });
+        Ok(Self {
+            local_port,
+            supervisor,
+        })
+    }
+
+    async fn wait_until_open(child: &mut Child, local_port: u16) -> Result<()> {
+        let deadline = Instant::now() + OPENING_TIMEOUT;
+        loop {
+            if let Some(status) = child.try_wait()? {
+                bail!("`kubectl port-forward` exited with {status}");
+            }
+            if TcpStream::connect(("127.0.0.1", local_port)).await.is_ok() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                bail!("The local port {local_port} is not open after {OPENING_TIMEOUT:?}");
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+impl Drop for PortForward {
+    fn drop(&mut self) {
+        // Dropping the task drops the child, which kills it.
+        self.supervisor.abort();
+    }
+}
+
+#[tokio::test]
+async fn test_port_forward_is_restarted_until_dropped() -> Result<()> {
+    // The tunnel is simulated by a local listener and a short-lived command.
+    let listener = TcpListener::bind(("127.0.0.1", 0))?;
+    let local_port = listener.local_addr()?.port();
+    let temp_dir = tempfile::tempdir()?;
+    let log = temp_dir.path().join("log");
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("echo started >> {}; sleep 0.5", log.display()));
+
+    let port_forward = PortForward::supervise(command, local_port).await?;
+    assert_eq!(port_forward.local_port(), local_port);
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    let num_starts = fs_err::read_to_string(&log)?.lines().count();
+    assert!(num_starts >= 2);
+
+    drop(port_forward);
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert!(fs_err::read_to_string(&log)?.lines().count() <= num_starts + 1);
+    Ok(())
+}
@@ -0,0 +1,152 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::{bail, Result};
+use semver::Version;
+use tokio::process::Command;
+
+use crate::cli_wrappers::util::run_and_capture_stdout;
+
+/// A tool used to run a Linera network on Kubernetes.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Tool {
+    Docker,
+    Helm,
+    Helmfile,
+    Kind,
+    Kubectl,
+}
+
+impl Tool {
+    /// All the tools needed by [`LocalKubernetesNet`](super::local_kubernetes_net::LocalKubernetesNet).
+    pub const ALL: [Tool; 5] = [
+        Tool::Docker,
+        Tool::Helm,
+        Tool::Helmfile,
+        Tool::Kind,
+        Tool::Kubectl,
+    ];
+
+    pub fn program(&self) -> &'static str {
+        match self {
+            Tool::Docker => "docker",
+            Tool::Helm => "helm",
+            Tool::Helmfile => "helmfile",
+            Tool::Kind => "kind",
+            Tool::Kubectl => "kubectl",
+        }
+    }
+
+    /// The oldest supported version of the tool.
+    pub fn minimum_version(&self) -> Version {
+        let (major, minor, patch) = match self {
+            Tool::Docker => (20, 10, 0),
+            // `--dry-run=server` was added in 3.13.
+            Tool::Helm => (3, 13, 0),
+            Tool::Helmfile => (0, 150, 0),
+            Tool::Kind => (0, 20, 0),
+            Tool::Kubectl => (1, 25, 0),
+        };
+        Version::new(major, minor, patch)
+    }
+
+    fn version_args(&self) -> &'static [&'static str] {
+        match self {
+            Tool::Docker | Tool::Helmfile => &["--version"],
+            Tool::Helm => &["version", "--short"],
+            Tool::Kind => &["version"],
+            Tool::Kubectl => &["version", "--client"],
+        }
+    }
+
+    /// Returns the installed version of the tool.
+    pub async fn version(&self) -> Result<Version> {
+        let mut command = Command::new(self.program());
+        command.args(self.version_args());
+        let (_code, output) = run_and_capture_stdout(&mut command, &[0]).await?;
+        match parse_version(&output) {
+            Some(version) => Ok(version),
+            None => bail!("Failed to find the version in {:?}", output.trim()),
+        }
+    }
+}
+
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.program())
+    }
+}
+
+/// The versions of the tools checked by [`check_all`].
+#[derive(Clone, Debug, Default)]
+pub struct ToolVersions(BTreeMap<Tool, Version>);
+
+impl ToolVersions {
+    pub fn get(&self, tool: Tool) -> Option<&Version> {
+        self.0.get(&tool)
+    }
+}
+
+/// Checks that the `requirements` are installed with a supported version. The error lists
+/// all the tools which are missing or too old.
+pub async fn check_all(requirements: &[Tool]) -> Result<ToolVersions> {
+    let results = futures::future::join_all(requirements.iter().map(Tool::version)).await;
+    let mut versions = ToolVersions::default();
+    let mut problems = Vec::new();
+    for (tool, result) in requirements.iter().zip(results) {
+        let minimum_version = tool.minimum_version();
+        match result {
+            Ok(version) if version < minimum_version => problems.push(format!(
+                "- {tool} {version} is too old: please install {tool} {minimum_version} or \
+                 newer"
+            )),
+            Ok(version) => {
+                versions.0.insert(*tool, version);
+            }
+            Err(error) => problems.push(format!("- {tool}: {error:#}")),
+        }
+    }
+    if !problems.is_empty() {
+        bail!(
+            "Some tools are missing or outdated:\n{}",
+            problems.join("\n")
+        );
+    }
+    Ok(versions)
+}
+
+/// Returns the first version in the output of a `version` command, with or without a `v`
+/// prefix.
+fn parse_version(output: &str) -> Option<Version> {
+    output
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .find_map(|word| Version::parse(word.strip_prefix('v').unwrap_or(word)).ok())
+}
+
+#[test]
+fn test_parse_version() {
+    let outputs = [
+        ("Docker version 24.0.7, build afdd53b\n", "24.0.7"),
+        ("v3.14.2+gc309b6f\n", "3.14.2+gc309b6f"),
+        ("helmfile version 0.162.0\n", "0.162.0"),
+        (
+            "\n\u{2593}\u{2593}\u{2593} helmfile\n\n  Version            1.0.0\n  Git Commit         \
+             fde5c9b\n  Build Date         10 Apr 25 07:16 UTC\n",
+            "1.0.0",
+        ),
+        ("kind v0.22.0 go1.21.7 linux/amd64\n", "0.22.0"),
+        (
+            "Client Version: v1.29.2\nKustomize Version: v5.0.4-0.20230601165947-6ce0bf390ce3\n",
+            "1.29.2",
+        ),
+    ];
+    for (output, expected) in outputs {
+        assert_eq!(
+            parse_version(output),
+            Some(Version::parse(expected).unwrap())
+        );
+    }
+    assert_eq!(parse_version("command not found"), None);
+}
@@ -1,11 +1,81 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::path::PathBuf;
+use std::{
+    collections::VecDeque,
+    env, io,
+    path::PathBuf,
+    process::Stdio,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, ensure, Context as _, Result};
 use linera_base::command::CommandExt;
-use tokio::process::Command;
+use tokio::{
+    io::{AsyncBufReadExt as _, BufReader},
+    process::{Child, Command},
+};
+use tracing::{debug, info, warn};
+
+/// The number of the last lines of the standard error of a command included in its errors.
+const NUM_STDERR_LINES_IN_ERRORS: usize = 20;
+
+/// Error messages of `helm` and `kubectl` caused by a cluster which is busy or still
+/// starting, such that the command may succeed if it is retried.
+const TRANSIENT_ERROR_PATTERNS: &[&str] = &[
+    "connection refused",
+    "connection reset by peer",
+    "tls handshake timeout",
+    "i/o timeout",
+    "etcdserver: request timed out",
+    "the server is currently unable to handle the request",
+    "another operation (install/upgrade/rollback) is in progress",
+];
+
+/// How [`run_with_retries`] retries the commands which fail with a transient error.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// The maximal number of runs of the command, including the first one.
+    pub max_attempts: u32,
+    /// The delay before the first retry, doubled at each retry.
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy which never retries.
+    pub fn no_retries() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    fn delay(&self, retry: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay)
+    }
+}
+
+/// Whether the error of a command, including its standard error, shows a transient
+/// failure of the cluster.
+pub fn is_transient_failure(error: &str) -> bool {
+    let error = error.to_lowercase();
+    TRANSIENT_ERROR_PATTERNS
+        .iter()
+        .any(|pattern| error.contains(pattern))
+}
 
 pub async fn get_github_root() -> Result<PathBuf> {
     let github_root = Command::new("git")
@@ -20,3 +90,260 @@ pub async fn get_github_root() -> Result<PathBuf> {
             .to_string(),
     ))
 }
+
+/// Returns a new directory to save the diagnostics of a failure, named after the current
+/// test if any. The directories are created in `$LINERA_KUBERNETES_ARTIFACTS_DIR`, or in
+/// the temporary directory of the system by default, and are not removed.
+pub fn new_artifacts_dir(name: &str) -> Result<PathBuf> {
+    let base_dir = match env::var_os("LINERA_KUBERNETES_ARTIFACTS_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => env::temp_dir().join("linera-kubernetes-artifacts"),
+    };
+    // The test harness names the threads of the tests after them.
+    let thread = std::thread::current();
+    let test_name = thread.name().filter(|name| *name != "main");
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let dir_name = match test_name {
+        Some(test_name) => format!("{}-{name}-{timestamp}", test_name.replace("::", "-")),
+        None => format!("{name}-{timestamp}"),
+    };
+    let dir = base_dir.join(dir_name);
+    fs_err::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Runs a command while capturing its output, which is logged at debug level. If the
+/// command exits with a code other than the `success_codes`, the last lines of its standard
+/// error are included in the returned error. Returns the exit code otherwise.
+pub async fn run_and_capture(command: &mut Command, success_codes: &[i32]) -> Result<i32> {
+    let (code, _stdout) = run_and_capture_stdout(command, success_codes).await?;
+    Ok(code)
+}
+
+/// Same as [`run_and_capture`], but also returns the standard output of the command.
+pub async fn run_and_capture_stdout(
+    command: &mut Command,
+    success_codes: &[i32],
+) -> Result<(i32, String)> {
+    let child = spawn_piped(command)?;
+    let output = child
+        .wait_with_output()
+        .await
+        .with_context(|| command.description())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    debug!("Output of {:?}:\n{stdout}\n{stderr}", command);
+
+    match output.status.code() {
+        Some(code) if success_codes.contains(&code) => Ok((code, stdout.into_owned())),
+        _ => {
+            let lines = stderr.lines().collect::<Vec<_>>();
+            let last_lines = &lines[lines.len().saturating_sub(NUM_STDERR_LINES_IN_ERRORS)..];
+            Err(anyhow!(
+                "{}: got non-zero error code {}\n{}",
+                command.description(),
+                output.status,
+                last_lines.join("\n")
+            ))
+        }
+    }
+}
+
+/// Same as [`run_and_capture_stdout`], but runs the command again, after an exponentially
+/// increasing delay, as long as it fails with a transient error, up to the limit of the
+/// `policy`.
+pub async fn run_with_retries(
+    command: &mut Command,
+    success_codes: &[i32],
+    policy: &RetryPolicy,
+) -> Result<(i32, String)> {
+    let mut retry = 0;
+    loop {
+        match run_and_capture_stdout(command, success_codes).await {
+            Err(error) if retry + 1 < policy.max_attempts => {
+                let message = format!("{error:#}");
+                if !is_transient_failure(&message) {
+                    return Err(error);
+                }
+                let delay = policy.delay(retry);
+                retry += 1;
+                warn!(
+                    "Attempt {retry}/{} of {:?} failed with a transient error, retrying in \
+                     {delay:?}: {}",
+                    policy.max_attempts,
+                    command.as_std(),
+                    message.lines().last().unwrap_or_default()
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Runs a command while logging each line of its output at info level, e.g. to follow the
+/// progress of a long build. The last lines of its standard error are included in the
+/// returned error if the command fails.
+pub async fn run_and_stream(command: &mut Command) -> Result<()> {
+    let mut child = spawn_piped(command)?;
+    let program = program_name(command);
+    let stdout = child.stdout.take().expect("the standard output is piped");
+    let stderr = child.stderr.take().expect("the standard error is piped");
+
+    let log_stdout = async {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Some(line) = lines.next_line().await? {
+            info!("{program}: {line}");
+        }
+        io::Result::Ok(())
+    };
+    let log_stderr = async {
+        let mut last_lines = VecDeque::with_capacity(NUM_STDERR_LINES_IN_ERRORS);
+        let mut lines = BufReader::new(stderr).lines();
+        while let Some(line) = lines.next_line().await? {
+            info!("{program}: {line}");
+            if last_lines.len() == NUM_STDERR_LINES_IN_ERRORS {
+                last_lines.pop_front();
+            }
+            last_lines.push_back(line);
+        }
+        io::Result::Ok(last_lines)
+    };
+    let (stdout_result, stderr_result) = futures::join!(log_stdout, log_stderr);
+    stdout_result.with_context(|| command.description())?;
+    let last_lines = stderr_result.with_context(|| command.description())?;
+
+    let status = child.wait().await.with_context(|| command.description())?;
+    ensure!(
+        status.success(),
+        "{}: got non-zero error code {}\n{}",
+        command.description(),
+        status,
+        Vec::from(last_lines).join("\n")
+    );
+    Ok(())
+}
+
+/// Spawns a command with piped outputs, which is killed if its handle is dropped.
+fn spawn_piped(command: &mut Command) -> Result<Child> {
+    debug!("Running {:?}", command);
+    command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    match command.spawn() {
+        Ok(child) => Ok(child),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            let program = program_name(command);
+            Err(anyhow!(error)).context(format!(
+                "Failed to run `{program}`: is it installed and in the PATH?"
+            ))
+        }
+        Err(error) => Err(error).with_context(|| command.description()),
+    }
+}
+
+fn program_name(command: &Command) -> String {
+    command
+        .as_std()
+        .get_program()
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn test_is_transient_failure() {
+    let transient_errors = [
+        "Error: Kubernetes cluster unreachable: Get \"https://127.0.0.1:35063/version\": dial \
+         tcp 127.0.0.1:35063: connect: connection refused",
+        "Error: UPGRADE FAILED: another operation (install/upgrade/rollback) is in progress",
+        "Unable to connect to the server: net/http: TLS handshake timeout",
+        "Error from server: etcdserver: request timed out",
+        "Error from server (ServiceUnavailable): the server is currently unable to handle the \
+         request",
+    ];
+    for error in transient_errors {
+        assert!(is_transient_failure(error), "{error}");
+    }
+    let permanent_errors = [
+        "Error: INSTALLATION FAILED: chart \"linera-validator\" not found",
+        "Error: INSTALLATION FAILED: values don't meet the specifications of the schema(s)",
+        "error: context \"kind-1\" does not exist",
+        "Error from server (NotFound): pods \"proxy-0\" not found",
+    ];
+    for error in permanent_errors {
+        assert!(!is_transient_failure(error), "{error}");
+    }
+}
+
+#[tokio::test]
+async fn test_run_with_retries() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let attempts = temp_dir.path().join("attempts");
+    let policy = RetryPolicy {
+        max_attempts: 3,
+        initial_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(10),
+    };
+    // Fails with a transient error until the third attempt.
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(format!(
+        "echo >> {0}; if [ $(wc -l < {0}) -lt 3 ]; then echo connection refused >&2; exit 1; fi",
+        attempts.display()
+    ));
+    run_with_retries(&mut command, &[0], &policy).await?;
+    assert_eq!(fs_err::read_to_string(&attempts)?.lines().count(), 3);
+
+    // Permanent errors are not retried.
+    fs_err::remove_file(&attempts)?;
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(format!(
+        "echo >> {}; echo chart not found >&2; exit 1",
+        attempts.display()
+    ));
+    assert!(run_with_retries(&mut command, &[0], &policy).await.is_err());
+    assert_eq!(fs_err::read_to_string(&attempts)?.lines().count(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_and_capture_is_cancellable() -> Result<()> {
+    use std::time::{Duration, Instant};
+
+    let temp_dir = tempfile::tempdir()?;
+    let marker = temp_dir.path().join("marker");
+    // A slow command, like `helmfile sync --wait`, which leaves a marker if it completes.
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("sleep 2 && touch {}", marker.display()));
+
+    let start = Instant::now();
+    let result = tokio::time::timeout(
+        Duration::from_millis(100),
+        run_and_capture(&mut command, &[0]),
+    )
+    .await;
+    assert!(result.is_err());
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    // Dropping the future killed the command.
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert!(!marker.exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_and_stream_reports_last_lines() -> Result<()> {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg("for i in $(seq 1 30); do echo error $i >&2; done; echo output; exit 3");
+    let error = run_and_stream(&mut command).await.unwrap_err().to_string();
+    let (_description, last_lines) = error.split_once('\n').unwrap();
+    let expected_lines = (11..=30).map(|i| format!("error {i}")).collect::<Vec<_>>();
+    assert_eq!(last_lines, expected_lines.join("\n"));
+
+    run_and_stream(Command::new("true").arg("ignored")).await?;
+    Ok(())
+}
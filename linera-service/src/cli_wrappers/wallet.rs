@@ -40,6 +40,9 @@ use crate::{
         Network,
     },
     faucet::ClaimOutcome,
+    output::{
+        Balance, CompletedTransfer, CreatedApplication, JsonOutput, OpenedChain, WalletChains,
+    },
     util::{self, ChildExt},
 };
 
@@ -139,8 +142,12 @@ impl ClientWrapper {
             command.arg("--required-application-ids");
             command.args(required_application_ids);
         }
-        let stdout = command.spawn_and_wait_for_stdout().await?;
-        Ok(stdout.trim().to_string())
+        let stdout = command
+            .args(["--output", "json"])
+            .spawn_and_wait_for_stdout()
+            .await?;
+        let created_application: CreatedApplication = JsonOutput::parse(&stdout)?;
+        Ok(created_application.application_id.to_string())
     }
 
     /// Runs `linera project test`.
@@ -382,8 +389,12 @@ impl ClientWrapper {
                     .map(ApplicationId::to_string),
             );
         }
-        let stdout = command.spawn_and_wait_for_stdout().await?;
-        Ok(stdout.trim().parse::<ApplicationId>()?.with_abi())
+        let stdout = command
+            .args(["--output", "json"])
+            .spawn_and_wait_for_stdout()
+            .await?;
+        let created_application: CreatedApplication = JsonOutput::parse(&stdout)?;
+        Ok(created_application.application_id.with_abi())
     }
 
     /// Runs `linera publish-bytecode`.
@@ -435,8 +446,12 @@ impl ClientWrapper {
                     .map(ApplicationId::to_string),
             );
         }
-        let stdout = command.spawn_and_wait_for_stdout().await?;
-        Ok(stdout.trim().parse::<ApplicationId>()?.with_abi())
+        let stdout = command
+            .args(["--output", "json"])
+            .spawn_and_wait_for_stdout()
+            .await?;
+        let created_application: CreatedApplication = JsonOutput::parse(&stdout)?;
+        Ok(created_application.application_id.with_abi())
     }
 
     /// Runs `linera request-application`
@@ -555,29 +570,39 @@ impl ClientWrapper {
             .await?
             .arg("local-balance")
             .arg(account.to_string())
+            .args(["--output", "json"])
             .spawn_and_wait_for_stdout()
             .await?;
-        let amount = stdout
-            .trim()
-            .parse()
-            .context("error while parsing the result of `linera local-balance`")?;
-        Ok(amount)
+        Ok(JsonOutput::<Balance>::parse(&stdout)?.balance)
     }
 
     /// Runs `linera query-balance`.
     pub async fn query_balance(&self, account: Account) -> Result<Amount> {
+        Ok(self.query_account_balance(account).await?.balance)
+    }
+
+    /// Runs `linera query-balance` on the balance of a chain.
+    pub async fn query_chain_balance(&self, chain_id: ChainId) -> Result<Balance> {
+        self.query_account_balance(Account::chain(chain_id)).await
+    }
+
+    /// Runs `linera query-balance` and returns the account together with its balance.
+    async fn query_account_balance(&self, account: Account) -> Result<Balance> {
         let stdout = self
             .command()
             .await?
             .arg("query-balance")
             .arg(account.to_string())
+            .args(["--output", "json"])
             .spawn_and_wait_for_stdout()
             .await?;
-        let amount = stdout
-            .trim()
-            .parse()
-            .context("error while parsing the result of `linera query-balance`")?;
-        Ok(amount)
+        let balance: Balance = JsonOutput::parse(&stdout)?;
+        ensure!(
+            balance.account == account,
+            "`linera query-balance` returned the balance of {} instead of {account}",
+            balance.account
+        );
+        Ok(balance)
     }
 
     /// Runs `linera sync`.
@@ -604,15 +629,31 @@ impl ClientWrapper {
 
     /// Runs `linera transfer`.
     pub async fn transfer(&self, amount: Amount, from: ChainId, to: ChainId) -> Result<()> {
-        self.command()
+        self.transfer_native(Account::chain(from), Account::chain(to), amount)
+            .await?;
+        Ok(())
+    }
+
+    /// Runs `linera transfer` to send native tokens between two accounts, and returns the
+    /// block that made the transfer.
+    pub async fn transfer_native(
+        &self,
+        from: Account,
+        to: Account,
+        amount: Amount,
+    ) -> Result<CompletedTransfer> {
+        let stdout = self
+            .command()
             .await?
             .arg("transfer")
             .arg(amount.to_string())
             .args(["--from", &from.to_string()])
             .args(["--to", &to.to_string()])
+            .args(["--output", "json"])
             .spawn_and_wait_for_stdout()
-            .await?;
-        Ok(())
+            .await
+            .with_context(|| format!("Failed to transfer {amount} from {from} to {to}"))?;
+        JsonOutput::parse(&stdout)
     }
 
     /// Runs `linera transfer` with no logging.
@@ -641,14 +682,7 @@ impl ClientWrapper {
         from: Account,
         to: Account,
     ) -> Result<()> {
-        self.command()
-            .await?
-            .arg("transfer")
-            .arg(amount.to_string())
-            .args(["--from", &from.to_string()])
-            .args(["--to", &to.to_string()])
-            .spawn_and_wait_for_stdout()
-            .await?;
+        self.transfer_native(from, to, amount).await?;
         Ok(())
     }
 
@@ -697,10 +731,14 @@ impl ClientWrapper {
             command.args(["--owner", &owner.to_string()]);
         }
 
-        let stdout = command.spawn_and_wait_for_stdout().await?;
-        let mut split = stdout.split('\n');
-        let message_id: MessageId = split.next().context("no message ID in output")?.parse()?;
-        let chain_id = ChainId::from_str(split.next().context("no chain ID in output")?)?;
+        let stdout = command
+            .args(["--output", "json"])
+            .spawn_and_wait_for_stdout()
+            .await?;
+        let OpenedChain {
+            message_id,
+            chain_id,
+        } = JsonOutput::parse(&stdout)?;
 
         Ok((message_id, chain_id))
     }
@@ -723,6 +761,26 @@ impl ClientWrapper {
         Ok(new_chain)
     }
 
+    /// Opens a new chain owned by a new key of this wallet, funded with `amount` from the
+    /// default chain, and adds it to the wallet.
+    pub async fn open_and_fund_chain(&self, amount: Amount) -> Result<OpenedChain> {
+        let from = self
+            .load_wallet()?
+            .default_chain()
+            .context("no default chain found")?;
+        let owner = self.keygen().await?;
+        let (message_id, chain_id) = self.open_chain(from, Some(owner), amount).await?;
+        let assigned_chain_id = self.assign(owner, message_id).await?;
+        ensure!(
+            assigned_chain_id == chain_id,
+            "`linera assign` added the chain {assigned_chain_id} instead of {chain_id}"
+        );
+        Ok(OpenedChain {
+            message_id,
+            chain_id,
+        })
+    }
+
     pub async fn open_multi_owner_chain(
         &self,
         from: ChainId,
@@ -748,10 +806,14 @@ impl ClientWrapper {
             .args(["--multi-leader-rounds", &multi_leader_rounds.to_string()])
             .args(["--initial-balance", &balance.to_string()]);
 
-        let stdout = command.spawn_and_wait_for_stdout().await?;
-        let mut split = stdout.split('\n');
-        let message_id: MessageId = split.next().context("no message ID in output")?.parse()?;
-        let chain_id = ChainId::from_str(split.next().context("no chain ID in output")?)?;
+        let stdout = command
+            .args(["--output", "json"])
+            .spawn_and_wait_for_stdout()
+            .await?;
+        let OpenedChain {
+            message_id,
+            chain_id,
+        } = JsonOutput::parse(&stdout)?;
 
         Ok((message_id, chain_id))
     }
@@ -903,6 +965,17 @@ impl ClientWrapper {
         Ok(Owner::from_str(stdout.trim())?)
     }
 
+    /// Runs `linera wallet show`.
+    pub async fn wallet_show(&self) -> Result<WalletChains> {
+        let stdout = self
+            .command()
+            .await?
+            .args(["wallet", "show", "--output", "json"])
+            .spawn_and_wait_for_stdout()
+            .await?;
+        JsonOutput::parse(&stdout)
+    }
+
     /// Returns the default chain.
     pub fn default_chain(&self) -> Option<ChainId> {
         self.load_wallet().ok()?.default_chain()
@@ -986,10 +1059,10 @@ impl Drop for ClientWrapper {
 
         let Ok(wallet_show_output) = wallet_show_command
             .current_dir(working_directory)
-            .args(["wallet", "show", "--short", "--owned"])
+            .args(["wallet", "show", "--owned", "--output", "json"])
             .output()
         else {
-            warn!("Failed to execute `wallet show --short` to list chains to close");
+            warn!("Failed to execute `wallet show` to list chains to close");
             return;
         };
 
@@ -998,20 +1071,16 @@ impl Drop for ClientWrapper {
             return;
         }
 
-        let Ok(chain_list_string) = String::from_utf8(wallet_show_output.stdout) else {
-            warn!(
-                "Failed to close chains because `linera wallet show --short` \
-                returned a non-UTF-8 output"
-            );
-            return;
+        let stdout = String::from_utf8_lossy(&wallet_show_output.stdout);
+        let wallet_chains = match JsonOutput::<WalletChains>::parse(&stdout) {
+            Ok(wallet_chains) => wallet_chains,
+            Err(error) => {
+                warn!("Failed to close chains: {error:#}");
+                return;
+            }
         };
 
-        let chain_ids = chain_list_string
-            .split('\n')
-            .map(|line| line.trim())
-            .filter(|line| !line.is_empty());
-
-        for chain_id in chain_ids {
+        for chain_id in wallet_chains.chains.iter().map(|chain| chain.chain_id) {
             let mut close_chain_command = SyncCommand::new(binary_path);
 
             for argument in self.command_arguments() {
@@ -1020,7 +1089,10 @@ impl Drop for ClientWrapper {
 
             close_chain_command.current_dir(working_directory);
 
-            match close_chain_command.args(["close-chain", chain_id]).status() {
+            match close_chain_command
+                .args(["close-chain", &chain_id.to_string()])
+                .status()
+            {
                 Ok(status) if status.success() => (),
                 Ok(failure) => warn!("Failed to close chain {chain_id}: {failure}"),
                 Err(error) => warn!("Failed to close chain {chain_id}: {error}"),
@@ -9,6 +9,8 @@
 pub mod cli_wrappers;
 pub mod faucet;
 pub mod node_service;
+/// The results printed by the `linera` client with `--output json`.
+pub mod output;
 pub mod project;
 #[cfg(with_metrics)]
 pub mod prometheus_server;
@@ -25,8 +25,8 @@ use linera_client::{
     chain_listener::ClientContext as _,
     client_context::ClientContext,
     client_options::{
-        ClientCommand, ClientOptions, DatabaseToolCommand, NetCommand, ProjectCommand,
-        WalletCommand,
+        ClientCommand, ClientOptions, DatabaseToolCommand, NetCommand, OutputFormat,
+        ProjectCommand, WalletCommand,
     },
     config::{CommitteeConfig, GenesisConfig},
     persistent::{self, Persist},
@@ -49,11 +49,13 @@ use linera_service::{
     cli_wrappers,
     faucet::FaucetService,
     node_service::NodeService,
+    output::{Balance, CompletedTransfer, CreatedApplication, JsonOutput, OpenedChain},
     project::{self, Project},
     util, wallet,
 };
 use linera_storage::Storage;
 use linera_views::store::CommonStoreConfig;
+use serde::Serialize;
 use serde_json::Value;
 use tokio::task::JoinSet;
 use tracing::{debug, error, info, warn, Instrument as _};
@@ -101,6 +103,20 @@ fn read_json(string: Option<String>, path: Option<PathBuf>) -> anyhow::Result<Ve
     Ok(serde_json::to_vec(&value)?)
 }
 
+/// Prints the result of a command on the standard output: as JSON if requested, and
+/// otherwise with `print_text`.
+fn print_result<T: Serialize>(
+    output_format: OutputFormat,
+    result: T,
+    print_text: impl FnOnce(&T),
+) -> anyhow::Result<()> {
+    match output_format {
+        OutputFormat::Text => print_text(&result),
+        OutputFormat::Json => println!("{}", JsonOutput::new(result).to_json()?),
+    }
+    Ok(())
+}
+
 #[async_trait]
 impl Runnable for Job {
     type Output = anyhow::Result<()>;
@@ -110,6 +126,7 @@ impl Runnable for Job {
         S: Storage + Clone + Send + Sync + 'static,
     {
         let Job(options) = self;
+        let output_format = options.output_format;
         let wallet = options.wallet().await?;
         let mut context = ClientContext::new(storage.clone(), options.clone(), wallet);
         let command = options.command;
@@ -148,6 +165,15 @@ impl Runnable for Job {
                 let time_total = time_start.elapsed();
                 info!("Transfer confirmed after {} ms", time_total.as_millis());
                 debug!("{:?}", certificate);
+                let transfer = CompletedTransfer {
+                    sender,
+                    recipient,
+                    amount,
+                    certificate_hash: certificate.hash(),
+                    height: certificate.block().header.height,
+                };
+                // Nothing is printed in the text format, for compatibility with scripts.
+                print_result(output_format, transfer, |_| ())?;
             }
 
             OpenChain {
@@ -190,8 +216,14 @@ impl Runnable for Job {
                 );
                 debug!("{:?}", certificate);
                 // Print the new chain ID and message ID on stdout for scripting purposes.
-                println!("{}", message_id);
-                println!("{}", ChainId::child(message_id));
+                let opened_chain = OpenedChain {
+                    message_id,
+                    chain_id: id,
+                };
+                print_result(output_format, opened_chain, |opened_chain| {
+                    println!("{}", opened_chain.message_id);
+                    println!("{}", opened_chain.chain_id);
+                })?;
             }
 
             OpenMultiOwnerChain {
@@ -237,8 +269,14 @@ impl Runnable for Job {
                 );
                 debug!("{:?}", certificate);
                 // Print the new chain ID and message ID on stdout for scripting purposes.
-                println!("{}", message_id);
-                println!("{}", ChainId::child(message_id));
+                let opened_chain = OpenedChain {
+                    message_id,
+                    chain_id: id,
+                };
+                print_result(output_format, opened_chain, |opened_chain| {
+                    println!("{}", opened_chain.message_id);
+                    println!("{}", opened_chain.chain_id);
+                })?;
             }
 
             ChangeOwnership {
@@ -313,7 +351,10 @@ impl Runnable for Job {
                 };
                 let time_total = time_start.elapsed();
                 info!("Local balance obtained after {} ms", time_total.as_millis());
-                println!("{}", balance);
+                let balance = Balance { account, balance };
+                print_result(output_format, balance, |balance| {
+                    println!("{}", balance.balance)
+                })?;
             }
 
             QueryBalance { account } => {
@@ -330,7 +371,10 @@ impl Runnable for Job {
                 };
                 let time_total = time_start.elapsed();
                 info!("Balance obtained after {} ms", time_total.as_millis());
-                println!("{}", balance);
+                let balance = Balance { account, balance };
+                print_result(output_format, balance, |balance| {
+                    println!("{}", balance.balance)
+                })?;
             }
 
             SyncBalance { account } => {
@@ -351,7 +395,10 @@ impl Runnable for Job {
                     "Synchronizing balance confirmed after {} ms",
                     time_total.as_millis()
                 );
-                println!("{}", balance);
+                let balance = Balance { account, balance };
+                print_result(output_format, balance, |balance| {
+                    println!("{}", balance.balance)
+                })?;
             }
 
             Sync { chain_id } => {
@@ -982,7 +1029,13 @@ impl Runnable for Job {
                     "Application created in {} ms",
                     start_time.elapsed().as_millis()
                 );
-                println!("{}", application_id);
+                let created_application = CreatedApplication {
+                    bytecode_id,
+                    application_id,
+                };
+                print_result(output_format, created_application, |created_application| {
+                    println!("{}", created_application.application_id)
+                })?;
             }
 
             PublishAndCreate {
@@ -1030,7 +1083,13 @@ impl Runnable for Job {
                     "Application published and created in {} ms",
                     start_time.elapsed().as_millis()
                 );
-                println!("{}", application_id);
+                let created_application = CreatedApplication {
+                    bytecode_id,
+                    application_id,
+                };
+                print_result(output_format, created_application, |created_application| {
+                    println!("{}", created_application.application_id)
+                })?;
             }
 
             RequestApplication {
@@ -1136,7 +1195,13 @@ impl Runnable for Job {
                         "Project published and created in {} ms",
                         start_time.elapsed().as_millis()
                     );
-                    println!("{}", application_id);
+                    let created_application = CreatedApplication {
+                        bytecode_id,
+                        application_id,
+                    };
+                    print_result(output_format, created_application, |created_application| {
+                        println!("{}", created_application.application_id)
+                    })?;
                 }
                 _ => unreachable!("other project commands do not require storage"),
             },
@@ -1789,7 +1854,10 @@ async fn run(options: &ClientOptions) -> Result<i32, anyhow::Error> {
                 } else {
                     options.wallet().await?.chain_ids()
                 };
-                if *short {
+                if options.output_format == OutputFormat::Json {
+                    let chains = wallet::summary(&*options.wallet().await?, chain_ids);
+                    println!("{}", JsonOutput::new(chains).to_json()?);
+                } else if *short {
                     for chain_id in chain_ids {
                         println!("{chain_id}");
                     }
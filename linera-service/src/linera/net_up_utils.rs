@@ -139,6 +139,7 @@ pub async fn handle_net_up_kubernetes(
         no_build,
         docker_image_name,
         policy,
+        namespace: None,
     };
     let (mut net, client) = config.instantiate().await?;
     let faucet_service = create_wallets_and_faucets(
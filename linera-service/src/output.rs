@@ -0,0 +1,200 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{ensure, Context as _, Result};
+use linera_base::{
+    crypto::CryptoHash,
+    data_types::{Amount, BlockHeight, Timestamp},
+    identifiers::{Account, ApplicationId, BytecodeId, ChainId, MessageId, Owner},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The version of the results printed by `linera --output json`. It must be incremented
+/// whenever one of the types of this module changes in an incompatible way.
+pub const OUTPUT_SCHEMA_VERSION: u32 = 1;
+
+/// A result printed by `linera --output json`, together with the version of its schema.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct JsonOutput<T> {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub result: T,
+}
+
+impl<T: Serialize> JsonOutput<T> {
+    pub fn new(result: T) -> Self {
+        Self {
+            schema_version: OUTPUT_SCHEMA_VERSION,
+            result,
+        }
+    }
+
+    /// Returns the result as a single line of JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl<T: DeserializeOwned> JsonOutput<T> {
+    /// Parses the standard output of a `linera` subcommand run with `--output json`, and
+    /// returns the result if its schema is the one of this version.
+    pub fn parse(stdout: &str) -> Result<T> {
+        let output: JsonOutput<serde_json::Value> = serde_json::from_str(stdout.trim())
+            .with_context(|| format!("error while parsing the output of `linera`: {stdout:?}"))?;
+        ensure!(
+            output.schema_version == OUTPUT_SCHEMA_VERSION,
+            "the output of `linera` has the schema version {}, but {OUTPUT_SCHEMA_VERSION} \
+             is expected",
+            output.schema_version
+        );
+        serde_json::from_value(output.result)
+            .with_context(|| format!("unexpected output of `linera`: {stdout:?}"))
+    }
+}
+
+/// The output of `linera open-chain` and `linera open-multi-owner-chain`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct OpenedChain {
+    /// The ID of the message which created the chain.
+    pub message_id: MessageId,
+    pub chain_id: ChainId,
+}
+
+/// The output of `linera local-balance`, `linera query-balance` and `linera sync-balance`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Balance {
+    pub account: Account,
+    pub balance: Amount,
+}
+
+/// The output of `linera transfer`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CompletedTransfer {
+    pub sender: Account,
+    pub recipient: Account,
+    pub amount: Amount,
+    /// The hash of the certificate of the block making the transfer.
+    pub certificate_hash: CryptoHash,
+    /// The height of that block in the chain of the sender.
+    pub height: BlockHeight,
+}
+
+/// The output of `linera create-application` and `linera publish-and-create`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CreatedApplication {
+    pub bytecode_id: BytecodeId,
+    pub application_id: ApplicationId,
+}
+
+/// The output of `linera wallet show`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WalletChains {
+    pub default_chain: Option<ChainId>,
+    pub chains: Vec<WalletChain>,
+}
+
+/// A chain of the wallet.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WalletChain {
+    pub chain_id: ChainId,
+    /// The owner of the key of the chain in the wallet, if any.
+    pub owner: Option<Owner>,
+    /// The hash of the last block known by the wallet.
+    pub block_hash: Option<CryptoHash>,
+    pub next_block_height: BlockHeight,
+    pub timestamp: Timestamp,
+}
+
+#[test]
+fn test_json_output() -> Result<()> {
+    let message_id = MessageId {
+        chain_id: ChainId::root(0),
+        height: BlockHeight(3),
+        index: 1,
+    };
+    let opened_chain = OpenedChain {
+        message_id,
+        chain_id: ChainId::child(message_id),
+    };
+    let stdout = format!("{}\n", JsonOutput::new(opened_chain).to_json()?);
+    assert_eq!(JsonOutput::<OpenedChain>::parse(&stdout)?, opened_chain);
+
+    let stdout = format!(
+        "{{\"schema_version\":1,\"account\":{{\"chain_id\":\"{}\",\"owner\":null}},\
+         \"balance\":\"100.\"}}\n",
+        ChainId::root(1)
+    );
+    assert_eq!(
+        JsonOutput::<Balance>::parse(&stdout)?,
+        Balance {
+            account: Account::chain(ChainId::root(1)),
+            balance: Amount::from_tokens(100),
+        }
+    );
+
+    let stdout = stdout.replace("\"schema_version\":1", "\"schema_version\":2");
+    assert!(JsonOutput::<Balance>::parse(&stdout).is_err());
+    assert!(JsonOutput::<Balance>::parse("100.\n").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_recorded_outputs() -> Result<()> {
+    use std::str::FromStr as _;
+
+    let chain0 = "e476187f6ddfeb9d588c7b45d3df334d5501d6499b3f9ad5595cae86cce16a65";
+    let chain1 = "256e1dbc00482ddd619c293cc0df94d366afe7980022bb22d99e33036fd465dd";
+    let hash = "f61bbbd4a9a2e7c5b3c0f6a2bc4e2b7cb7a4e8a33f7e5ac01b1d1f1b4f0d8e3a";
+
+    // `linera open-chain --output json`
+    let stdout = format!(
+        "{{\"schema_version\":1,\"message_id\":{{\"chain_id\":\"{chain0}\",\"height\":3,\
+         \"index\":1}},\"chain_id\":\"{chain1}\"}}\n"
+    );
+    assert_eq!(
+        JsonOutput::<OpenedChain>::parse(&stdout)?,
+        OpenedChain {
+            message_id: MessageId {
+                chain_id: ChainId::from_str(chain0)?,
+                height: BlockHeight(3),
+                index: 1,
+            },
+            chain_id: ChainId::from_str(chain1)?,
+        }
+    );
+
+    // `linera query-balance --output json`
+    let stdout = format!(
+        "{{\"schema_version\":1,\"account\":{{\"chain_id\":\"{chain1}\",\"owner\":null}},\
+         \"balance\":\"2.5\"}}\n"
+    );
+    assert_eq!(
+        JsonOutput::<Balance>::parse(&stdout)?,
+        Balance {
+            account: Account::chain(ChainId::from_str(chain1)?),
+            balance: Amount::from_str("2.5")?,
+        }
+    );
+
+    // `linera transfer --output json`
+    let stdout = format!(
+        "{{\"schema_version\":1,\"sender\":{{\"chain_id\":\"{chain0}\",\"owner\":null}},\
+         \"recipient\":{{\"chain_id\":\"{chain1}\",\"owner\":null}},\"amount\":\"2.5\",\
+         \"certificate_hash\":\"{hash}\",\"height\":4}}\n"
+    );
+    assert_eq!(
+        JsonOutput::<CompletedTransfer>::parse(&stdout)?,
+        CompletedTransfer {
+            sender: Account::chain(ChainId::from_str(chain0)?),
+            recipient: Account::chain(ChainId::from_str(chain1)?),
+            amount: Amount::from_str("2.5")?,
+            certificate_hash: CryptoHash::from_str(hash)?,
+            height: BlockHeight(4),
+        }
+    );
+
+    // The text output of a failed command is not a result.
+    let stdout = "Error: Failed to make transfer\n";
+    assert!(JsonOutput::<CompletedTransfer>::parse(stdout).is_err());
+    Ok(())
+}
@@ -338,19 +338,27 @@ where
     /// Returns the appropriate gRPC status for the given [`ViewError`].
     fn error_to_status(err: ViewError) -> Status {
         let mut status = match &err {
-            ViewError::TooLargeValue | ViewError::BcsError(_) => {
-                Status::invalid_argument(err.to_string())
-            }
+            ViewError::TooLargeValue
+            | ViewError::BcsError(_)
+            | ViewError::Serialization { .. }
+            | ViewError::ReservedKey(_)
+            | ViewError::ZeroChunkSize => Status::invalid_argument(err.to_string()),
             ViewError::StoreError { .. }
             | ViewError::TokioJoinError(_)
             | ViewError::TryLockError(_)
             | ViewError::InconsistentEntries
+            | ViewError::CopyMismatch(_)
             | ViewError::PostLoadValuesError
             | ViewError::IoError(_) => Status::internal(err.to_string()),
             ViewError::KeyTooLong | ViewError::ArithmeticError(_) => {
                 Status::out_of_range(err.to_string())
             }
+            ViewError::CompareAndPutUnsupported => Status::unimplemented(err.to_string()),
+            ViewError::LockHeld | ViewError::LockLost => {
+                Status::failed_precondition(err.to_string())
+            }
             ViewError::NotFound(_)
+            | ViewError::KeyNotFound(_)
             | ViewError::BlobsNotFound(_)
             | ViewError::CannotAcquireCollectionEntry
             | ViewError::MissingEntries => Status::not_found(err.to_string()),
@@ -8,6 +8,8 @@ use comfy_table::{
 use linera_base::identifiers::{ChainId, Owner};
 pub use linera_client::wallet::*;
 
+use crate::output::{WalletChain, WalletChains};
+
 pub fn pretty_print(wallet: &Wallet, chain_ids: impl IntoIterator<Item = ChainId>) {
     let mut table = Table::new();
     table
@@ -32,6 +34,33 @@ pub fn pretty_print(wallet: &Wallet, chain_ids: impl IntoIterator<Item = ChainId
     println!("{}", table);
 }
 
+/// Returns the chains of the wallet with the given IDs, as printed by
+/// `linera wallet show --output json`.
+pub fn summary(wallet: &Wallet, chain_ids: impl IntoIterator<Item = ChainId>) -> WalletChains {
+    let chains = chain_ids
+        .into_iter()
+        .map(|chain_id| {
+            let Some(user_chain) = wallet.chains.get(&chain_id) else {
+                panic!("Chain {} not found.", chain_id);
+            };
+            WalletChain {
+                chain_id,
+                owner: user_chain
+                    .key_pair
+                    .as_ref()
+                    .map(|key_pair| Owner::from(key_pair.public())),
+                block_hash: user_chain.block_hash,
+                next_block_height: user_chain.next_block_height,
+                timestamp: user_chain.timestamp,
+            }
+        })
+        .collect();
+    WalletChains {
+        default_chain: wallet.default,
+        chains,
+    }
+}
+
 fn update_table_with_chain(
     table: &mut Table,
     chain_id: ChainId,
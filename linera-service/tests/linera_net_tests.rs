@@ -719,7 +719,10 @@ async fn test_wasm_end_to_end_fungible(
         (account_owner1, Amount::from_tokens(5)),
         (account_owner2, Amount::from_tokens(2)),
     ]);
-    let state = InitialState { accounts };
+    let state = InitialState {
+        accounts,
+        admin: None,
+    };
     // Setting up the application and verifying
     let (contract, service) = client1.build_example(example_name).await?;
     let params = if example_name == "native-fungible" {
@@ -891,7 +894,10 @@ async fn test_wasm_end_to_end_same_wallet_fungible(
         (account_owner1, Amount::from_tokens(5)),
         (account_owner2, Amount::from_tokens(2)),
     ]);
-    let state = InitialState { accounts };
+    let state = InitialState {
+        accounts,
+        admin: None,
+    };
     // Setting up the application and verifying
     let (contract, service) = client1.build_example(example_name).await?;
     let params = if example_name == "native-fungible" {
@@ -1280,7 +1286,10 @@ async fn test_wasm_end_to_end_crowd_funding(config: impl LineraNetConfig) -> Res
 
     // The initial accounts on chain1
     let accounts = BTreeMap::from([(account_owner1, Amount::from_tokens(6))]);
-    let state_fungible = InitialState { accounts };
+    let state_fungible = InitialState {
+        accounts,
+        admin: None,
+    };
 
     // Setting up the application fungible
     let (contract_fungible, service_fungible) = client1.build_example("fungible").await?;
@@ -1426,10 +1435,12 @@ async fn test_wasm_end_to_end_matching_engine(config: impl LineraNetConfig) -> R
     let accounts0 = BTreeMap::from([(owner_a, Amount::from_tokens(10))]);
     let state_fungible0 = fungible::InitialState {
         accounts: accounts0,
+        admin: None,
     };
     let accounts1 = BTreeMap::from([(owner_b, Amount::from_tokens(9))]);
     let state_fungible1 = fungible::InitialState {
         accounts: accounts1,
+        admin: None,
     };
 
     // Setting up the application fungible on chain_a and chain_b
@@ -1727,11 +1738,13 @@ async fn test_wasm_end_to_end_amm(config: impl LineraNetConfig) -> Result<()> {
     // Amounts of token0 that will be owned by each user
     let state_fungible0 = fungible::InitialState {
         accounts: BTreeMap::from([(owner_amm_chain, Amount::from_tokens(270))]),
+        admin: None,
     };
 
     // Amounts of token1 that will be owned by each user
     let state_fungible1 = fungible::InitialState {
         accounts: BTreeMap::from([(owner_amm_chain, Amount::from_tokens(250))]),
+        admin: None,
     };
 
     // Create fungible applications on the AMM chain, which will hold
@@ -2449,7 +2462,10 @@ async fn test_open_chain_node_service(config: impl LineraNetConfig) -> Result<()
     // Create a fungible token application with 10 tokens for owner 1.
     let owner = get_fungible_account_owner(&client);
     let accounts = BTreeMap::from([(owner, Amount::from_tokens(10))]);
-    let state = fungible::InitialState { accounts };
+    let state = fungible::InitialState {
+        accounts,
+        admin: None,
+    };
     let (contract, service) = client.build_example("fungible").await?;
     let params = fungible::Parameters::new("FUN");
     let application_id = client
@@ -3219,3 +3235,61 @@ async fn test_end_to_end_repeated_transfers(config: impl LineraNetConfig) -> Res
 
     Ok(())
 }
+
+#[cfg(feature = "kubernetes")]
+#[test_log::test(tokio::test)]
+async fn test_kubernetes_set_num_shards() -> Result<()> {
+    use linera_execution::ResourceControlPolicy;
+    use linera_service::cli_wrappers::local_kubernetes_net::LocalKubernetesNetConfig;
+
+    let _guard = INTEGRATION_TEST_GUARD.lock().await;
+    tracing::info!("Starting test {}", test_name!());
+
+    // This network is not shared with the other tests, since its shards are changed.
+    let config = LocalKubernetesNetConfig {
+        network: Network::Grpc,
+        testing_prng_seed: Some(37),
+        num_other_initial_chains: 1,
+        initial_amount: Amount::from_tokens(100),
+        num_initial_validators: 1,
+        num_shards: 2,
+        binaries: BuildArg::Build,
+        no_build: false,
+        docker_image_name: String::from("linera:latest"),
+        policy: ResourceControlPolicy::devnet(),
+        namespace: None,
+    };
+    let (mut net, client) = config.instantiate().await?;
+    let chain1 = client.load_wallet()?.default_chain().unwrap();
+    let chain2 = client.open_and_assign(&client, Amount::ONE).await?;
+
+    for scaling in net.set_num_shards(4, false).await? {
+        assert_eq!(scaling.old_num_shards, 2);
+        assert_eq!(scaling.pods_before, ["shards-0", "shards-1"]);
+        assert_eq!(
+            scaling.pods_after,
+            ["shards-0", "shards-1", "shards-2", "shards-3"]
+        );
+    }
+    // Removing shards would lose their state.
+    assert!(net.set_num_shards(2, false).await.is_err());
+    net.ensure_is_running().await?;
+    for endpoints in net.validator_endpoints().await? {
+        assert!(endpoints.missing_shards.is_empty());
+        assert_eq!(
+            endpoints.shards.keys().copied().collect::<Vec<_>>(),
+            [0, 1, 2, 3]
+        );
+    }
+
+    // The chains are still served after the shards are added.
+    client.transfer(Amount::ONE, chain1, chain2).await?;
+    client.sync(chain2).await?;
+    assert_eq!(
+        client.query_balance(Account::chain(chain2)).await?,
+        Amount::from_tokens(2)
+    );
+
+    net.terminate().await?;
+    Ok(())
+}
@@ -558,6 +558,60 @@ async fn test_end_to_end_retry_pending_block(config: LocalNetConfig) -> Result<(
     Ok(())
 }
 
+#[cfg_attr(feature = "storage-service", test_case(LocalNetConfig::new_test(Database::Service, Network::Grpc) ; "storage_service_grpc"))]
+#[cfg_attr(feature = "scylladb", test_case(LocalNetConfig::new_test(Database::ScyllaDb, Network::Grpc) ; "scylladb_grpc"))]
+#[cfg_attr(feature = "dynamodb", test_case(LocalNetConfig::new_test(Database::DynamoDb, Network::Grpc) ; "aws_grpc"))]
+#[test_log::test(tokio::test)]
+async fn test_end_to_end_validator_restart(config: LocalNetConfig) -> Result<()> {
+    let _guard = INTEGRATION_TEST_GUARD.lock().await;
+    tracing::info!("Starting test {}", test_name!());
+
+    let (mut net, client) = config.instantiate().await?;
+    let chain1 = client.load_wallet()?.default_chain().unwrap();
+    let chain2 = ChainId::root(1);
+    let account = Account::chain(chain1);
+    let balance = client.local_balance(account).await?;
+
+    // A server killed out-of-band is reported with its exit status.
+    let pid = net.server_pid(0, 0).expect("validator 0 should be running");
+    assert!(Command::new("kill")
+        .args(["-s", "KILL", &pid.to_string()])
+        .status()?
+        .success());
+    let mut error = None;
+    for _ in 0..50 {
+        if let Err(e) = net.ensure_validator_is_running(0) {
+            error = Some(e);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let error = error.expect("the killed server should be reported");
+    assert!(format!("{error:#}").contains("signal: 9"), "{error:#}");
+    net.restart_validator(0).await?;
+    net.ensure_validator_is_running(0)?;
+
+    // The other three validators are a quorum.
+    net.stop_validator(3).await?;
+    assert!(net.ensure_validator_is_running(3).is_err());
+    client.transfer(Amount::ONE, chain1, chain2).await?;
+    client.transfer(Amount::ONE, chain1, chain2).await?;
+
+    // Validator 3 is needed for a quorum without validator 0, so it must catch up.
+    net.restart_validator(3).await?;
+    net.ensure_validator_is_running(3)?;
+    net.stop_validator(0).await?;
+    client.transfer(Amount::ONE, chain1, chain2).await?;
+    client.sync(chain1).await?;
+    assert!(client.local_balance(account).await? <= balance - Amount::from_tokens(3));
+
+    net.restart_validator(0).await?;
+    net.ensure_is_running().await?;
+    net.terminate().await?;
+
+    Ok(())
+}
+
 #[cfg_attr(feature = "storage-service", test_case(Database::Service, Network::Grpc ; "storage_service_grpc"))]
 #[cfg_attr(feature = "scylladb", test_case(Database::ScyllaDb, Network::Grpc ; "scylladb_grpc"))]
 #[cfg_attr(feature = "dynamodb", test_case(Database::DynamoDb, Network::Grpc ; "aws_grpc"))]
@@ -825,7 +879,10 @@ async fn test_end_to_end_benchmark(mut config: LocalNetConfig) -> Result<()> {
     // native token.
     let account_owner = get_fungible_account_owner(&client);
     let accounts = BTreeMap::from([(account_owner, Amount::from_tokens(1_000_000))]);
-    let state = InitialState { accounts };
+    let state = InitialState {
+        accounts,
+        admin: None,
+    };
     let (contract, service) = client.build_example("fungible").await?;
     let params = Parameters::new("FUN");
     let application_id = client
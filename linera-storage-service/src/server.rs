@@ -445,6 +445,9 @@ impl StoreProcessor for ServiceStoreServer {
                 Operation::DeletePrefix(key_prefix) => {
                     batch.delete_key_prefix(key_prefix);
                 }
+                Operation::DeleteRange(key_range) => {
+                    batch.delete_key_range(key_range.start, key_range.end);
+                }
             }
         }
         if !batch.is_empty() {
@@ -749,6 +749,12 @@ where
         Ok(())
     }
 
+    /// Returns the key-value store of the storage, e.g. for the administration of its
+    /// backend, such as reporting the statistics of a RocksDB database.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
     fn create(store: Store, wasm_runtime: Option<WasmRuntime>, clock: C) -> Self {
         Self {
             store: Arc::new(store),
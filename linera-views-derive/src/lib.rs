@@ -5,12 +5,149 @@
 
 use proc_macro::TokenStream;
 use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, ToTokens as _};
 use syn::{
-    parse_macro_input, parse_quote, punctuated::Punctuated, Attribute, ItemStruct, Lit, LitStr,
-    MetaNameValue, Token, Type, TypePath, WhereClause,
+    parse::{Parse, ParseStream},
+    parse_macro_input, parse_quote,
+    punctuated::Punctuated,
+    Attribute, Fields, Item, ItemStruct, Lit, LitStr, MetaNameValue, Token, Type, TypePath,
+    WhereClause,
 };
 
+/// A struct accepted by the view derives, i.e. with named fields, at least one of them
+/// not optional, and a context given either as the first type parameter or by a `context`
+/// attribute.
+struct ViewStruct(ItemStruct);
+
+impl Parse for ViewStruct {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let item = match input.parse::<Item>()? {
+            Item::Struct(item) => item,
+            Item::Enum(item) => {
+                return Err(syn::Error::new_spanned(
+                    item.enum_token,
+                    "views cannot be derived for enums, since the keys of a view must be \
+                    known before loading it; use a struct with a view for the data of each \
+                    variant, and a `RegisterView` holding the current variant",
+                ))
+            }
+            Item::Union(item) => {
+                return Err(syn::Error::new_spanned(
+                    item.union_token,
+                    "views cannot be derived for unions; use a struct with named fields",
+                ))
+            }
+            item => {
+                return Err(syn::Error::new_spanned(
+                    item,
+                    "views can only be derived for structs with named fields",
+                ))
+            }
+        };
+        let Fields::Named(fields) = &item.fields else {
+            return Err(syn::Error::new_spanned(
+                &item,
+                "views can only be derived for structs with named fields, since the fields \
+                are accessed by name; name the fields of the struct",
+            ));
+        };
+        if fields.named.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &item,
+                "a view needs at least one field, whose context is the context of the view",
+            ));
+        }
+        if fields
+            .named
+            .iter()
+            .all(|field| optional_view_type(&field.ty).is_some())
+        {
+            return Err(syn::Error::new_spanned(
+                &item,
+                "a view needs at least one field that is not optional, whose context is the \
+                context of the view",
+            ));
+        }
+        if custom_attribute(&item.attrs, "context").is_none()
+            && get_seq_parameter(item.generics.clone()).is_empty()
+        {
+            return Err(syn::Error::new_spanned(
+                &item.ident,
+                "missing context: add a type parameter for the context, e.g. \
+                `struct MyView<C>`, or specify it with `#[view(context = \"MyContext\")]`",
+            ));
+        }
+        Ok(ViewStruct(item))
+    }
+}
+
+/// Returns the type of the view of an optional field, e.g. `RegisterView<C, u64>` for a
+/// field of type `Option<RegisterView<C, u64>>`.
+fn optional_view_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(TypePath { qself: None, path }) = ty else {
+        return None;
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first() {
+        Some(syn::GenericArgument::Type(ty)) if arguments.args.len() == 1 => Some(ty),
+        _ => None,
+    }
+}
+
+/// Returns the type of the view of a field, which is optional or not.
+fn view_type(ty: &Type) -> &Type {
+    optional_view_type(ty).unwrap_or(ty)
+}
+
+/// Returns the code flushing a field into `batch`, which evaluates to whether the field
+/// is deleted. The presence of an optional field is marked by an empty value under
+/// `linera_views::views::OPTIONAL_VIEW_TAG`, which is written when its view is changed,
+/// and deleted with the keys of the view when the field is `None`.
+fn flush_field_code(
+    name: &syn::Ident,
+    idx_lit: &syn::LitInt,
+    ty: &Type,
+    batch: TokenStream2,
+) -> TokenStream2 {
+    if optional_view_type(ty).is_none() {
+        return quote! { self.#name.flush(#batch)? };
+    }
+    quote! {
+        {
+            use linera_views::context::Context as _;
+            let index = #idx_lit;
+            let marker_key = self
+                .context()
+                .derive_tag_key(linera_views::views::OPTIONAL_VIEW_TAG, &index)?;
+            let base_key = self
+                .context()
+                .derive_tag_key(linera_views::views::MIN_VIEW_TAG, &index)?;
+            let batch = #batch;
+            match &mut self.#name {
+                Some(view) => {
+                    let num_operations = batch.operations.len();
+                    view.flush(batch)?;
+                    if batch.operations.len() > num_operations {
+                        batch.put_key_value_bytes(marker_key, Vec::new());
+                    }
+                    false
+                }
+                None => {
+                    batch.delete_key(marker_key);
+                    batch.delete_key_prefix(base_key);
+                    true
+                }
+            }
+        }
+    }
+}
+
 fn get_seq_parameter(generics: syn::Generics) -> Vec<syn::Ident> {
     let mut generic_vect = Vec::new();
     for param in generics.params {
@@ -79,18 +216,57 @@ fn empty_where_clause() -> WhereClause {
     }
 }
 
+/// Returns the type of a field in expression position, so that its associated items can
+/// be used, e.g. `a::MyView::<C, T>` for the type `a::MyView<C, T>`.
 fn get_extended_entry(e: Type) -> TokenStream2 {
-    let syn::Type::Path(typepath) = e else {
-        panic!("The type should be a path");
-    };
-    let path_segment = typepath.path.segments.into_iter().next().unwrap();
-    let ident = path_segment.ident;
-    let arguments = path_segment.arguments;
-    quote! { #ident :: #arguments }
+    match e {
+        Type::Path(TypePath {
+            qself: None,
+            mut path,
+        }) => {
+            for segment in &mut path.segments {
+                if let syn::PathArguments::AngleBracketed(arguments) = &mut segment.arguments {
+                    arguments.colon2_token = Some(Token![::](Span::call_site()));
+                }
+            }
+            quote! { #path }
+        }
+        e => quote! { <#e> },
+    }
+}
+
+/// Returns whether `tokens` contain one of the `idents`.
+fn mentions_any(tokens: TokenStream2, idents: &[syn::Ident]) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(ident) => idents.contains(&ident),
+        proc_macro2::TokenTree::Group(group) => mentions_any(group.stream(), idents),
+        _ => false,
+    })
+}
+
+/// Adds the predicate `FieldType: bound` to the `where_clause` for the fields whose types
+/// depend on type parameters other than the context, e.g. a `RegisterView<C, T>` field
+/// of a struct generic over `T`, so that the struct needs no more bounds than its fields.
+fn add_field_bounds(
+    where_clause: &mut WhereClause,
+    input: &ItemStruct,
+    context: &Type,
+    bound: TokenStream2,
+) {
+    let parameters = get_seq_parameter(input.generics.clone())
+        .into_iter()
+        .filter(|parameter| !matches!(context, Type::Path(path) if path.path.is_ident(parameter)))
+        .collect::<Vec<_>>();
+    for field in &input.fields {
+        let ty = view_type(&field.ty);
+        if mentions_any(ty.to_token_stream(), &parameters) {
+            where_clause.predicates.push(parse_quote! { #ty: #bound });
+        }
+    }
 }
 
 fn generate_view_code(input: ItemStruct, root: bool) -> TokenStream2 {
-    let struct_name = input.ident;
+    let struct_name = &input.ident;
     let (impl_generics, type_generics, maybe_where_clause) = input.generics.split_for_impl();
     let template_vect = get_seq_parameter(input.generics.clone());
 
@@ -102,6 +278,12 @@ fn generate_view_code(input: ItemStruct, root: bool) -> TokenStream2 {
     where_clause
         .predicates
         .extend(context_constraints.predicates);
+    add_field_bounds(
+        &mut where_clause,
+        &input,
+        &context,
+        quote! { linera_views::views::View<#context> + Send + Sync },
+    );
 
     let mut name_quotes = Vec::new();
     let mut rollback_quotes = Vec::new();
@@ -112,38 +294,109 @@ fn generate_view_code(input: ItemStruct, root: bool) -> TokenStream2 {
     let mut num_init_keys_quotes = Vec::new();
     let mut pre_load_keys_quotes = Vec::new();
     let mut post_load_keys_quotes = Vec::new();
-    for (idx, e) in input.fields.into_iter().enumerate() {
+    let mut optional_field_methods = Vec::new();
+    let mut first_name_quote = None;
+    for (idx, e) in input.fields.iter().enumerate() {
         let name = e.clone().ident.unwrap();
         let test_flush_ident = format_ident!("deleted{}", idx);
         let idx_lit = syn::LitInt::new(&idx.to_string(), Span::call_site());
-        let g = get_extended_entry(e.ty.clone());
+        let ty = view_type(&e.ty);
+        let g = get_extended_entry(ty.clone());
+        let flush = flush_field_code(&name, &idx_lit, &e.ty, quote! { batch });
         name_quotes.push(quote! { #name });
-        rollback_quotes.push(quote! { self.#name.rollback(); });
-        flush_quotes.push(quote! { let #test_flush_ident = self.#name.flush(batch)?; });
+        flush_quotes.push(quote! { let #test_flush_ident = #flush; });
         test_flush_quotes.push(quote! { #test_flush_ident });
-        clear_quotes.push(quote! { self.#name.clear(); });
+        if optional_view_type(&e.ty).is_none() {
+            first_name_quote.get_or_insert_with(|| quote! { #name });
+            rollback_quotes.push(quote! { self.#name.rollback(); });
+            clear_quotes.push(quote! { self.#name.clear(); });
+            has_pending_changes_quotes.push(quote! {
+                if self.#name.has_pending_changes().await {
+                    return true;
+                }
+            });
+            num_init_keys_quotes.push(quote! { #g :: NUM_INIT_KEYS });
+            pre_load_keys_quotes.push(quote! {
+                let index = #idx_lit;
+                let base_key = context.derive_tag_key(linera_views::views::MIN_VIEW_TAG, &index)?;
+                keys.extend(#g :: pre_load(&context.clone_with_base_key(base_key))?);
+            });
+            post_load_keys_quotes.push(quote! {
+                let index = #idx_lit;
+                let pos_next = pos + #g :: NUM_INIT_KEYS;
+                let base_key = context.derive_tag_key(linera_views::views::MIN_VIEW_TAG, &index)?;
+                let #name = #g :: post_load(context.clone_with_base_key(base_key), &values[pos..pos_next])?;
+                pos = pos_next;
+            });
+            continue;
+        }
+        rollback_quotes.push(quote! {
+            if let Some(view) = &mut self.#name {
+                view.rollback();
+            }
+        });
+        clear_quotes.push(quote! { self.#name = None; });
         has_pending_changes_quotes.push(quote! {
-            if self.#name.has_pending_changes().await {
-                return true;
+            if let Some(view) = &self.#name {
+                if view.has_pending_changes().await {
+                    return true;
+                }
             }
         });
-        num_init_keys_quotes.push(quote! { #g :: NUM_INIT_KEYS });
+        num_init_keys_quotes.push(quote! { 1 + #g :: NUM_INIT_KEYS });
         pre_load_keys_quotes.push(quote! {
             let index = #idx_lit;
+            keys.push(context.derive_tag_key(linera_views::views::OPTIONAL_VIEW_TAG, &index)?);
             let base_key = context.derive_tag_key(linera_views::views::MIN_VIEW_TAG, &index)?;
             keys.extend(#g :: pre_load(&context.clone_with_base_key(base_key))?);
         });
         post_load_keys_quotes.push(quote! {
             let index = #idx_lit;
-            let pos_next = pos + #g :: NUM_INIT_KEYS;
+            let is_present = values[pos].is_some();
+            let pos_next = pos + 1 + #g :: NUM_INIT_KEYS;
             let base_key = context.derive_tag_key(linera_views::views::MIN_VIEW_TAG, &index)?;
-            let #name = #g :: post_load(context.clone_with_base_key(base_key), &values[pos..pos_next])?;
+            let #name = if is_present {
+                Some(#g :: post_load(context.clone_with_base_key(base_key), &values[pos + 1..pos_next])?)
+            } else {
+                None
+            };
             pos = pos_next;
         });
+        let vis = &e.vis;
+        let method = format_ident!("{}_or_insert", name);
+        let doc = format!(
+            "Returns the view of the optional field `{name}`, which is created empty if the \
+            field is `None`."
+        );
+        optional_field_methods.push(quote! {
+            #[doc = #doc]
+            #vis fn #method(&mut self) -> Result<&mut #ty, linera_views::views::ViewError> {
+                use linera_views::{context::Context as _, views::View as _};
+                if self.#name.is_none() {
+                    let index = #idx_lit;
+                    let base_key = self
+                        .context()
+                        .derive_tag_key(linera_views::views::MIN_VIEW_TAG, &index)?;
+                    let context = self.context().clone_with_base_key(base_key);
+                    self.#name = Some(#g :: new(context)?);
+                }
+                Ok(self.#name.as_mut().expect("the field should be present"))
+            }
+        });
     }
-    let first_name_quote = name_quotes
-        .first()
-        .expect("list of names should be non-empty");
+    let first_name_quote =
+        first_name_quote.expect("a view should have a field that is not optional");
+    let optional_field_impl = if optional_field_methods.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            impl #impl_generics #struct_name #type_generics
+            #where_clause
+            {
+                #(#optional_field_methods)*
+            }
+        }
+    };
 
     let load_metrics = if root && cfg!(feature = "metrics") {
         quote! {
@@ -219,11 +472,13 @@ fn generate_view_code(input: ItemStruct, root: bool) -> TokenStream2 {
                 #(#clear_quotes)*
             }
         }
+
+        #optional_field_impl
     }
 }
 
 fn generate_save_delete_view_code(input: ItemStruct) -> TokenStream2 {
-    let struct_name = input.ident;
+    let struct_name = &input.ident;
     let (impl_generics, type_generics, maybe_where_clause) = input.generics.split_for_impl();
     let template_vect = get_seq_parameter(input.generics.clone());
 
@@ -235,12 +490,20 @@ fn generate_save_delete_view_code(input: ItemStruct) -> TokenStream2 {
     where_clause
         .predicates
         .extend(context_constraints.predicates);
+    add_field_bounds(
+        &mut where_clause,
+        &input,
+        &context,
+        quote! { linera_views::views::View<#context> + Send + Sync },
+    );
 
     let mut flushes = Vec::new();
     let mut deletes = Vec::new();
-    for e in input.fields {
+    for (idx, e) in input.fields.into_iter().enumerate() {
         let name = e.clone().ident.unwrap();
-        flushes.push(quote! { self.#name.flush(&mut batch)?; });
+        let idx_lit = syn::LitInt::new(&idx.to_string(), Span::call_site());
+        let flush = flush_field_code(&name, &idx_lit, &e.ty, quote! { &mut batch });
+        flushes.push(quote! { #flush; });
         deletes.push(quote! { self.#name.delete(batch); });
     }
 
@@ -277,7 +540,7 @@ fn generate_save_delete_view_code(input: ItemStruct) -> TokenStream2 {
 }
 
 fn generate_hash_view_code(input: ItemStruct) -> TokenStream2 {
-    let struct_name = input.ident;
+    let struct_name = &input.ident;
     let (impl_generics, type_generics, maybe_where_clause) = input.generics.split_for_impl();
     let template_vect = get_seq_parameter(input.generics.clone());
 
@@ -289,13 +552,42 @@ fn generate_hash_view_code(input: ItemStruct) -> TokenStream2 {
     where_clause
         .predicates
         .extend(context_constraints.predicates);
+    add_field_bounds(
+        &mut where_clause,
+        &input,
+        &context,
+        quote! { linera_views::views::HashableView<#context> + Send + Sync },
+    );
 
     let mut field_hashes_mut = Vec::new();
     let mut field_hashes = Vec::new();
     for e in input.fields {
         let name = e.clone().ident.unwrap();
-        field_hashes_mut.push(quote! { hasher.write_all(self.#name.hash_mut().await?.as_ref())?; });
-        field_hashes.push(quote! { hasher.write_all(self.#name.hash().await?.as_ref())?; });
+        if optional_view_type(&e.ty).is_none() {
+            field_hashes_mut
+                .push(quote! { hasher.write_all(self.#name.hash_mut().await?.as_ref())?; });
+            field_hashes.push(quote! { hasher.write_all(self.#name.hash().await?.as_ref())?; });
+            continue;
+        }
+        // The presence of an optional field is hashed before the hash of its view.
+        field_hashes_mut.push(quote! {
+            match &mut self.#name {
+                Some(view) => {
+                    hasher.write_all(&[1])?;
+                    hasher.write_all(view.hash_mut().await?.as_ref())?;
+                }
+                None => hasher.write_all(&[0])?,
+            }
+        });
+        field_hashes.push(quote! {
+            match &self.#name {
+                Some(view) => {
+                    hasher.write_all(&[1])?;
+                    hasher.write_all(view.hash().await?.as_ref())?;
+                }
+                None => hasher.write_all(&[0])?,
+            }
+        });
     }
 
     quote! {
@@ -325,7 +617,7 @@ fn generate_hash_view_code(input: ItemStruct) -> TokenStream2 {
 }
 
 fn generate_crypto_hash_code(input: ItemStruct) -> TokenStream2 {
-    let struct_name = input.ident;
+    let struct_name = &input.ident;
     let (impl_generics, type_generics, maybe_where_clause) = input.generics.split_for_impl();
     let template_vect = get_seq_parameter(input.generics.clone());
 
@@ -337,6 +629,12 @@ fn generate_crypto_hash_code(input: ItemStruct) -> TokenStream2 {
     where_clause
         .predicates
         .extend(context_constraints.predicates);
+    add_field_bounds(
+        &mut where_clause,
+        &input,
+        &context,
+        quote! { linera_views::views::HashableView<#context> + Send + Sync },
+    );
 
     let hash_type = syn::Ident::new(&format!("{}Hash", struct_name), Span::call_site());
     quote! {
@@ -381,20 +679,42 @@ fn generate_crypto_hash_code(input: ItemStruct) -> TokenStream2 {
 }
 
 fn generate_clonable_view_code(input: ItemStruct) -> TokenStream2 {
-    let struct_name = input.ident;
-    let generics = input.generics;
-    let template_vect = get_seq_parameter(generics.clone());
+    let struct_name = &input.ident;
+    let (impl_generics, type_generics, maybe_where_clause) = input.generics.split_for_impl();
+    let template_vect = get_seq_parameter(input.generics.clone());
 
     let (context, context_constraints) = context_and_constraints(&input.attrs, &template_vect);
 
+    let mut where_clause = maybe_where_clause
+        .cloned()
+        .unwrap_or_else(empty_where_clause);
+    where_clause
+        .predicates
+        .extend(context_constraints.predicates);
+    add_field_bounds(
+        &mut where_clause,
+        &input,
+        &context,
+        quote! { linera_views::views::ClonableView<#context> },
+    );
+
     let clone_unchecked_quotes = input.fields.iter().map(|field| {
         let name = &field.ident;
-        quote! { #name: self.#name.clone_unchecked()?, }
+        if optional_view_type(&field.ty).is_none() {
+            quote! { #name: self.#name.clone_unchecked()?, }
+        } else {
+            quote! {
+                #name: match &mut self.#name {
+                    Some(view) => Some(view.clone_unchecked()?),
+                    None => None,
+                },
+            }
+        }
     });
 
     quote! {
-        impl #generics linera_views::views::ClonableView<#context> for #struct_name #generics
-        #context_constraints
+        impl #impl_generics linera_views::views::ClonableView<#context> for #struct_name #type_generics
+        #where_clause
         {
             fn clone_unchecked(&mut self) -> Result<Self, linera_views::views::ViewError> {
                 Ok(Self {
@@ -407,13 +727,13 @@ fn generate_clonable_view_code(input: ItemStruct) -> TokenStream2 {
 
 #[proc_macro_derive(View, attributes(view))]
 pub fn derive_view(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as ItemStruct);
+    let input = parse_macro_input!(input as ViewStruct).0;
     generate_view_code(input, false).into()
 }
 
 #[proc_macro_derive(HashableView, attributes(view))]
 pub fn derive_hash_view(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as ItemStruct);
+    let input = parse_macro_input!(input as ViewStruct).0;
     let mut stream = generate_view_code(input.clone(), false);
     stream.extend(generate_hash_view_code(input));
     stream.into()
@@ -421,7 +741,7 @@ pub fn derive_hash_view(input: TokenStream) -> TokenStream {
 
 #[proc_macro_derive(RootView, attributes(view))]
 pub fn derive_root_view(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as ItemStruct);
+    let input = parse_macro_input!(input as ViewStruct).0;
     let mut stream = generate_view_code(input.clone(), true);
     stream.extend(generate_save_delete_view_code(input));
     stream.into()
@@ -429,7 +749,7 @@ pub fn derive_root_view(input: TokenStream) -> TokenStream {
 
 #[proc_macro_derive(CryptoHashView, attributes(view))]
 pub fn derive_crypto_hash_view(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as ItemStruct);
+    let input = parse_macro_input!(input as ViewStruct).0;
     let mut stream = generate_view_code(input.clone(), false);
     stream.extend(generate_hash_view_code(input.clone()));
     stream.extend(generate_crypto_hash_code(input));
@@ -438,7 +758,7 @@ pub fn derive_crypto_hash_view(input: TokenStream) -> TokenStream {
 
 #[proc_macro_derive(CryptoHashRootView, attributes(view))]
 pub fn derive_crypto_hash_root_view(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as ItemStruct);
+    let input = parse_macro_input!(input as ViewStruct).0;
     let mut stream = generate_view_code(input.clone(), true);
     stream.extend(generate_save_delete_view_code(input.clone()));
     stream.extend(generate_hash_view_code(input.clone()));
@@ -449,7 +769,7 @@ pub fn derive_crypto_hash_root_view(input: TokenStream) -> TokenStream {
 #[proc_macro_derive(HashableRootView, attributes(view))]
 #[cfg(test)]
 pub fn derive_hashable_root_view(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as ItemStruct);
+    let input = parse_macro_input!(input as ViewStruct).0;
     let mut stream = generate_view_code(input.clone(), true);
     stream.extend(generate_save_delete_view_code(input.clone()));
     stream.extend(generate_hash_view_code(input));
@@ -458,7 +778,7 @@ pub fn derive_hashable_root_view(input: TokenStream) -> TokenStream {
 
 #[proc_macro_derive(ClonableView, attributes(view))]
 pub fn derive_clonable_view(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as ItemStruct);
+    let input = parse_macro_input!(input as ViewStruct).0;
     generate_clonable_view_code(input).into()
 }
 
@@ -541,6 +861,90 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_field_bounds_of_generic_views() {
+        let input: ItemStruct = parse_quote! {
+            struct TestView<C, T, U>
+            where
+                U: Clone,
+            {
+                register: linera_views::register_view::RegisterView<C, Option<T>>,
+                log: LogView<C, usize>,
+                inner: U,
+            }
+        };
+        // The spaces of the generated tokens are removed, as the lines are too long to be
+        // compared after formatting.
+        let code = generate_view_code(input.clone(), false)
+            .to_string()
+            .replace(' ', "");
+        assert!(code.contains(
+            "linera_views::register_view::RegisterView<C,Option<T>>:linera_views::views::View<C>+Send+Sync"
+        ));
+        assert!(code.contains("U:linera_views::views::View<C>+Send+Sync"));
+        assert!(!code.contains("LogView<C,usize>:"));
+        assert!(code
+            .contains("linera_views::register_view::RegisterView::<C,Option<T>>::NUM_INIT_KEYS"));
+        let code = generate_clonable_view_code(input)
+            .to_string()
+            .replace(' ', "");
+        assert!(code.contains("U:Clone"));
+        assert!(code.contains("U:linera_views::views::ClonableView<C>"));
+    }
+
+    #[test]
+    fn test_optional_fields() {
+        let input: ItemStruct = parse_quote! {
+            struct TestView<C, T> {
+                register: RegisterView<C, usize>,
+                pub details: Option<MapView<C, u64, T>>,
+            }
+        };
+        let code = generate_view_code(input.clone(), false)
+            .to_string()
+            .replace(' ', "");
+        assert!(code.contains("MapView<C,u64,T>:linera_views::views::View<C>+Send+Sync"));
+        assert!(code.contains("1+MapView::<C,u64,T>::NUM_INIT_KEYS"));
+        assert!(code.contains("linera_views::views::OPTIONAL_VIEW_TAG"));
+        assert!(code.contains(
+            "fncontext(&self)->&C{uselinera_views::views::View;self.register.context()}"
+        ));
+        assert!(code.contains("pubfndetails_or_insert(&mutself)"));
+        let code = generate_save_delete_view_code(input.clone())
+            .to_string()
+            .replace(' ', "");
+        assert!(code.contains("batch.delete_key_prefix(base_key)"));
+        let code = generate_hash_view_code(input).to_string().replace(' ', "");
+        assert!(code.contains("None=>hasher.write_all(&[0])?"));
+    }
+
+    #[test]
+    fn test_unsupported_shapes_are_rejected() {
+        let cases = [
+            (
+                quote! { enum TestView<C> { A(RegisterView<C, usize>) } },
+                "enums",
+            ),
+            (
+                quote! { struct TestView<C>(RegisterView<C, usize>); },
+                "named fields",
+            ),
+            (quote! { struct TestView<C> {} }, "at least one field"),
+            (
+                quote! { struct TestView<C> { register: Option<RegisterView<C, usize>> } },
+                "not optional",
+            ),
+            (
+                quote! { struct TestView { register: RegisterView<MyContext, usize> } },
+                "missing context",
+            ),
+        ];
+        for (input, message) in cases {
+            let error = syn::parse2::<ViewStruct>(input).err().unwrap();
+            assert!(error.to_string().contains(message), "{error}");
+        }
+    }
+
     #[derive(Clone)]
     pub struct SpecificContextInfo {
         name: String,
@@ -0,0 +1,112 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant},
+};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+#[cfg(with_rocksdb)]
+use linera_views::rocks_db::RocksDbStore;
+use linera_views::{
+    context::ViewContext,
+    log_view::LogView,
+    map_view::MapView,
+    memory::MemoryStore,
+    store::{KeyValueStore, TestKeyValueStore as _},
+    views::{RootView, View},
+};
+use tokio::runtime::Runtime;
+
+/// The number of entries loaded in the map and in the log.
+const N_ENTRIES: u64 = 100_000;
+
+/// The maximal size of the batches written by `flush_in_chunks`.
+const MAX_BATCH_BYTES: usize = 64 * 1024;
+
+#[derive(RootView)]
+pub struct BulkLoadView<C> {
+    pub map: MapView<C, u64, u64>,
+    pub log: LogView<C, u64>,
+}
+
+/// How the entries are added to the view.
+#[derive(Clone, Copy)]
+enum Loading {
+    /// One `insert` and one `push` per entry, then a single `save`.
+    OneByOne,
+    /// One `extend` for the map and one for the log, then a `flush_in_chunks`.
+    Bulk,
+}
+
+async fn performance_bulk_load<S: KeyValueStore + Clone + Sync + 'static>(
+    store: S,
+    loading: Loading,
+    iterations: u64,
+) -> Duration
+where
+    S::Error: Debug + Send + Sync + 'static,
+{
+    let context = ViewContext::<(), S>::create_root_context(store, ())
+        .await
+        .unwrap();
+    let mut total_time = Duration::ZERO;
+    for _ in 0..iterations {
+        let mut view = BulkLoadView::load(context.clone()).await.unwrap();
+        let measurement = Instant::now();
+        match loading {
+            Loading::OneByOne => {
+                for index in 0..N_ENTRIES {
+                    view.map.insert(&index, index * index).unwrap();
+                    view.log.push(index);
+                }
+                view.save().await.unwrap();
+            }
+            Loading::Bulk => {
+                view.map
+                    .extend((0..N_ENTRIES).map(|index| (index, index * index)))
+                    .unwrap();
+                view.log.extend(0..N_ENTRIES);
+                view.flush_in_chunks(MAX_BATCH_BYTES).await.unwrap();
+            }
+        }
+        total_time += measurement.elapsed();
+        black_box(view.log.count());
+
+        view.clear();
+        view.save().await.unwrap();
+    }
+
+    total_time
+}
+
+fn bench_bulk_load(criterion: &mut Criterion) {
+    for (name, loading) in [("one_by_one", Loading::OneByOne), ("bulk", Loading::Bulk)] {
+        criterion.bench_function(&format!("memory_map_view_load_{name}"), |bencher| {
+            bencher
+                .to_async(Runtime::new().expect("Failed to create Tokio runtime"))
+                .iter_custom(|iterations| async move {
+                    let store = MemoryStore::new_test_store().await.unwrap();
+                    performance_bulk_load(store, loading, iterations).await
+                })
+        });
+
+        #[cfg(with_rocksdb)]
+        criterion.bench_function(&format!("rocksdb_map_view_load_{name}"), |bencher| {
+            bencher
+                .to_async(Runtime::new().expect("Failed to create Tokio runtime"))
+                .iter_custom(|iterations| async move {
+                    let store = RocksDbStore::new_test_store().await.unwrap();
+                    performance_bulk_load(store, loading, iterations).await
+                })
+        });
+    }
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_bulk_load
+);
+criterion_main!(benches);
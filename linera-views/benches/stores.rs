@@ -2,12 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-#[cfg(with_dynamodb)]
-use linera_views::dynamo_db::DynamoDbStore;
 #[cfg(with_rocksdb)]
 use linera_views::rocks_db::RocksDbStore;
 #[cfg(with_scylladb)]
 use linera_views::scylla_db::ScyllaDbStore;
+#[cfg(with_dynamodb)]
+use linera_views::{
+    dynamo_db::DynamoDbStore, random::generate_test_namespace, store::AdminKeyValueStore as _,
+};
 use linera_views::{memory::MemoryStore, store::TestKeyValueStore as _, test_utils::performance};
 use tokio::runtime::Runtime;
 
@@ -298,6 +300,94 @@ fn bench_write_batch(criterion: &mut Criterion) {
     });
 }
 
+fn bench_write_large_batch(criterion: &mut Criterion) {
+    criterion.bench_function("store_memory_write_large_batch", |bencher| {
+        bencher
+            .to_async(Runtime::new().expect("Failed to create Tokio runtime"))
+            .iter_custom(|iterations| async move {
+                let store = MemoryStore::new_test_store().await.unwrap();
+                performance::write_large_batch(store, iterations).await
+            })
+    });
+
+    #[cfg(with_dynamodb)]
+    criterion.bench_function("store_dynamodb_write_large_batch_sequential", |bencher| {
+        bencher
+            .to_async(Runtime::new().expect("Failed to create Tokio runtime"))
+            .iter_custom(|iterations| async move {
+                let config = DynamoDbStore::new_test_config()
+                    .await
+                    .unwrap()
+                    .with_max_concurrent_batches(1);
+                let namespace = generate_test_namespace();
+                let store = DynamoDbStore::recreate_and_connect(&config, &namespace, &[])
+                    .await
+                    .unwrap();
+                performance::write_large_batch(store, iterations).await
+            })
+    });
+
+    #[cfg(with_dynamodb)]
+    criterion.bench_function("store_dynamodb_write_large_batch", |bencher| {
+        bencher
+            .to_async(Runtime::new().expect("Failed to create Tokio runtime"))
+            .iter_custom(|iterations| async move {
+                let store = DynamoDbStore::new_test_store().await.unwrap();
+                performance::write_large_batch(store, iterations).await
+            })
+    });
+}
+
+#[cfg(with_rocksdb)]
+fn bench_write_batch_of_50k_operations(criterion: &mut Criterion) {
+    use linera_views::{
+        random::generate_test_namespace, rocks_db::RocksDbWriteOptions,
+        store::AdminKeyValueStore as _,
+    };
+
+    const NUM_OPERATIONS: usize = 50_000;
+
+    for (name, write_options) in [
+        (
+            "store_rocksdb_write_50k_batch",
+            RocksDbWriteOptions::default(),
+        ),
+        (
+            "store_rocksdb_write_50k_batch_synced",
+            RocksDbWriteOptions {
+                sync: true,
+                ..RocksDbWriteOptions::default()
+            },
+        ),
+        (
+            "store_rocksdb_write_50k_batch_without_wal",
+            RocksDbWriteOptions {
+                disable_wal: true,
+                ..RocksDbWriteOptions::default()
+            },
+        ),
+    ] {
+        criterion.bench_function(name, |bencher| {
+            bencher
+                .to_async(Runtime::new().expect("Failed to create Tokio runtime"))
+                .iter_custom(|iterations| async move {
+                    let config = RocksDbStore::new_test_config()
+                        .await
+                        .unwrap()
+                        .with_write_options(write_options);
+                    let namespace = generate_test_namespace();
+                    let store = RocksDbStore::recreate_and_connect(&config, &namespace, &[])
+                        .await
+                        .unwrap();
+                    performance::write_batch_of_puts(store, iterations, NUM_OPERATIONS).await
+                })
+        });
+    }
+}
+
+#[cfg(not(with_rocksdb))]
+fn bench_write_batch_of_50k_operations(_criterion: &mut Criterion) {}
+
 criterion_group!(
     benches,
     bench_contains_key,
@@ -306,6 +396,8 @@ criterion_group!(
     bench_find_key_values_by_prefix,
     bench_read_value_bytes,
     bench_read_multi_values_bytes,
-    bench_write_batch
+    bench_write_batch,
+    bench_write_large_batch,
+    bench_write_batch_of_50k_operations
 );
 criterion_main!(benches);
@@ -5,10 +5,14 @@ fn main() {
     cfg_aliases::cfg_aliases! {
         web: { all(target_arch = "wasm32", feature = "web") },
         with_testing: { any(test, feature = "test") },
+        with_bench: { all(not(target_arch = "wasm32"), feature = "bench") },
         with_metrics: { all(not(target_arch = "wasm32"), feature = "metrics") },
         with_dynamodb: { all(not(target_arch = "wasm32"), feature = "dynamodb") },
         with_indexeddb: { all(web, feature = "indexeddb") },
         with_rocksdb: { all(not(target_arch = "wasm32"), feature = "rocksdb") },
         with_scylladb: { all(not(target_arch = "wasm32"), feature = "scylladb") },
+        with_s3: { all(not(target_arch = "wasm32"), feature = "s3") },
+        with_fs: { all(not(target_arch = "wasm32"), feature = "fs") },
+        with_encryption: { feature = "encryption" },
     };
 }
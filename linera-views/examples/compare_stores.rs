@@ -0,0 +1,59 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Runs the workloads of `linera_views::bench` against the available backends and prints
+//! a comparison table.
+//!
+//! RocksDB is included with the `rocksdb` feature. DynamoDB is included with the
+//! `dynamodb` feature, when the `LOCALSTACK_ENDPOINT` environment variable points to a
+//! running LocalStack instance.
+
+use linera_views::{
+    bench::{run_all_workloads, BenchReport, WorkloadConfig},
+    memory::MemoryStore,
+    store::TestKeyValueStore as _,
+    views::ViewError,
+};
+
+fn print_reports(backend: &str, reports: &[BenchReport]) {
+    for report in reports {
+        println!(
+            "{:<10} {:<16} {:>12.3?} {:>12.3?} {:>12.3?} {:>14.1}",
+            backend,
+            report.workload.to_string(),
+            report.p50,
+            report.p95,
+            report.p99,
+            report.throughput,
+        );
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ViewError> {
+    let config = WorkloadConfig {
+        zipf_exponent: 1.1,
+        ..WorkloadConfig::default()
+    };
+    println!(
+        "{:<10} {:<16} {:>12} {:>12} {:>12} {:>14}",
+        "backend", "workload", "p50", "p95", "p99", "operations/s"
+    );
+
+    let store = MemoryStore::new_test_store().await?;
+    print_reports("memory", &run_all_workloads(&store, &config).await?);
+
+    #[cfg(with_rocksdb)]
+    {
+        let store = linera_views::rocks_db::RocksDbStore::new_test_store().await?;
+        print_reports("rocksdb", &run_all_workloads(&store, &config).await?);
+    }
+
+    #[cfg(with_dynamodb)]
+    if std::env::var("LOCALSTACK_ENDPOINT").is_ok() {
+        let store = linera_views::dynamo_db::DynamoDbStore::new_test_store().await?;
+        print_reports("dynamodb", &run_all_workloads(&store, &config).await?);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,587 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Counts the operations and the bytes going through a key-value store.
+//!
+//! Unlike [`crate::metering`], the counters do not depend on Prometheus: they are plain
+//! atomics that can be read at any time through a [`StoreMetrics`] handle. With the
+//! `metrics` feature, they can also be exported with [`StoreMetrics::register`].
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use linera_base::time::Instant;
+
+#[cfg(with_testing)]
+use crate::store::TestKeyValueStore;
+use crate::{
+    batch::{Batch, BatchStats},
+    store::{
+        AdminKeyValueStore, KeyIterable as _, KeyValueIterable as _, Order, ReadableKeyValueStore,
+        ResultStream, WithError, WritableKeyValueStore,
+    },
+    views::ViewError,
+};
+
+/// The upper bounds of the buckets of the latency histograms, in microseconds.
+pub const LATENCY_BUCKETS_MICROS: [u64; 7] =
+    [10, 100, 1_000, 10_000, 100_000, 1_000_000, 10_000_000];
+
+/// The number of buckets of the latency histograms, including the one above every bound.
+const NUM_LATENCY_BUCKETS: usize = LATENCY_BUCKETS_MICROS.len() + 1;
+
+/// A histogram of latencies, with the buckets of [`LATENCY_BUCKETS_MICROS`].
+#[derive(Debug, Default)]
+struct LatencyHistogram {
+    /// The number of samples of each bucket. They are not cumulative.
+    buckets: [AtomicU64; NUM_LATENCY_BUCKETS],
+    /// The sum of the samples, in microseconds.
+    sum_micros: AtomicU64,
+}
+
+impl LatencyHistogram {
+    fn observe(&self, start: Instant) {
+        let micros = start.elapsed().as_micros() as u64;
+        let index = LATENCY_BUCKETS_MICROS
+            .iter()
+            .position(|bound| micros <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MICROS.len());
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> LatencySnapshot {
+        LatencySnapshot {
+            buckets: std::array::from_fn(|index| self.buckets[index].load(Ordering::Relaxed)),
+            sum_micros: self.sum_micros.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The values of a latency histogram at some point in time.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LatencySnapshot {
+    /// The number of samples of each bucket of [`LATENCY_BUCKETS_MICROS`], the last one
+    /// counting the samples above every bound. They are not cumulative.
+    pub buckets: [u64; NUM_LATENCY_BUCKETS],
+    /// The sum of the samples, in microseconds.
+    pub sum_micros: u64,
+}
+
+impl LatencySnapshot {
+    /// Returns the number of samples.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+}
+
+/// The counters of a [`CountingStore`]. They are shared by the stores obtained with
+/// `clone_with_root_key`.
+#[derive(Debug, Default)]
+pub struct StoreMetrics {
+    reads: AtomicU64,
+    keys_read: AtomicU64,
+    prefix_scans: AtomicU64,
+    batch_writes: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    read_latency: LatencyHistogram,
+    write_latency: LatencyHistogram,
+}
+
+/// The values of the counters of a [`StoreMetrics`] at some point in time.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StoreMetricsSnapshot {
+    /// The number of point reads, each one being on one or several keys.
+    pub reads: u64,
+    /// The number of keys looked up by the point reads.
+    pub keys_read: u64,
+    /// The number of scans of the keys of a prefix or a range.
+    pub prefix_scans: u64,
+    /// The number of written batches, including the conditional writes.
+    pub batch_writes: u64,
+    /// The number of bytes of the values returned by the point reads, and of the keys and
+    /// values returned by the scans.
+    pub bytes_read: u64,
+    /// The number of bytes of the written batches.
+    pub bytes_written: u64,
+    /// The latencies of the reads and the scans.
+    pub read_latency: LatencySnapshot,
+    /// The latencies of the writes.
+    pub write_latency: LatencySnapshot,
+}
+
+impl StoreMetrics {
+    /// Reads the current values of the counters.
+    pub fn snapshot(&self) -> StoreMetricsSnapshot {
+        StoreMetricsSnapshot {
+            reads: self.reads.load(Ordering::Relaxed),
+            keys_read: self.keys_read.load(Ordering::Relaxed),
+            prefix_scans: self.prefix_scans.load(Ordering::Relaxed),
+            batch_writes: self.batch_writes.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            read_latency: self.read_latency.snapshot(),
+            write_latency: self.write_latency.snapshot(),
+        }
+    }
+
+    /// Registers the counters in the default Prometheus registry, as metrics whose names
+    /// start with `prefix`.
+    #[cfg(with_metrics)]
+    pub fn register(self: &Arc<Self>, prefix: &str) -> Result<(), prometheus::Error> {
+        let collector = exporter::StoreMetricsCollector::new(self.clone(), prefix)?;
+        prometheus::register(Box::new(collector))
+    }
+
+    fn add(counter: &AtomicU64, value: usize) {
+        counter.fetch_add(value as u64, Ordering::Relaxed);
+    }
+
+    fn record_read(&self, num_keys: usize, start: Instant) {
+        Self::add(&self.reads, 1);
+        Self::add(&self.keys_read, num_keys);
+        self.read_latency.observe(start);
+    }
+
+    fn record_scan(&self, start: Instant) {
+        Self::add(&self.prefix_scans, 1);
+        self.read_latency.observe(start);
+    }
+
+    fn record_write(&self, num_bytes: usize, start: Instant) {
+        Self::add(&self.batch_writes, 1);
+        Self::add(&self.bytes_written, num_bytes);
+        self.write_latency.observe(start);
+    }
+}
+
+#[cfg(with_metrics)]
+mod exporter {
+    use std::sync::Arc;
+
+    use prometheus::{
+        core::{Collector, Desc},
+        proto::{Bucket, Counter, Histogram, Metric, MetricFamily, MetricType},
+    };
+
+    use super::{LatencySnapshot, StoreMetrics, LATENCY_BUCKETS_MICROS};
+
+    /// The metrics exported for a [`StoreMetrics`]: their suffix, their description and
+    /// whether they are histograms.
+    const METRICS: [(&str, &str, bool); 8] = [
+        ("reads", "The number of point reads", false),
+        (
+            "keys_read",
+            "The number of keys looked up by the point reads",
+            false,
+        ),
+        (
+            "prefix_scans",
+            "The number of scans of a prefix or a range",
+            false,
+        ),
+        ("batch_writes", "The number of written batches", false),
+        ("bytes_read", "The number of bytes read", false),
+        ("bytes_written", "The number of bytes written", false),
+        (
+            "read_latency",
+            "The latency of the reads and the scans, in seconds",
+            true,
+        ),
+        (
+            "write_latency",
+            "The latency of the writes, in seconds",
+            true,
+        ),
+    ];
+
+    /// A Prometheus collector reading the counters of a [`StoreMetrics`] when gathered.
+    pub(super) struct StoreMetricsCollector {
+        metrics: Arc<StoreMetrics>,
+        descs: Vec<Desc>,
+    }
+
+    impl StoreMetricsCollector {
+        pub(super) fn new(
+            metrics: Arc<StoreMetrics>,
+            prefix: &str,
+        ) -> Result<Self, prometheus::Error> {
+            let descs = METRICS
+                .iter()
+                .map(|(suffix, help, _)| {
+                    Desc::new(
+                        format!("{prefix}_{suffix}"),
+                        help.to_string(),
+                        Vec::new(),
+                        Default::default(),
+                    )
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(Self { metrics, descs })
+        }
+    }
+
+    fn counter(value: u64) -> Metric {
+        let mut counter = Counter::default();
+        counter.set_value(value as f64);
+        let mut metric = Metric::default();
+        metric.set_counter(counter);
+        metric
+    }
+
+    fn histogram(snapshot: &LatencySnapshot) -> Metric {
+        let mut histogram = Histogram::default();
+        let mut cumulative_count = 0;
+        for (bound, count) in LATENCY_BUCKETS_MICROS.iter().zip(&snapshot.buckets) {
+            cumulative_count += count;
+            let mut bucket = Bucket::default();
+            bucket.set_cumulative_count(cumulative_count);
+            bucket.set_upper_bound(*bound as f64 / 1e6);
+            histogram.mut_bucket().push(bucket);
+        }
+        histogram.set_sample_count(snapshot.count());
+        histogram.set_sample_sum(snapshot.sum_micros as f64 / 1e6);
+        let mut metric = Metric::default();
+        metric.set_histogram(histogram);
+        metric
+    }
+
+    impl Collector for StoreMetricsCollector {
+        fn desc(&self) -> Vec<&Desc> {
+            self.descs.iter().collect()
+        }
+
+        fn collect(&self) -> Vec<MetricFamily> {
+            let snapshot = self.metrics.snapshot();
+            let values = [
+                counter(snapshot.reads),
+                counter(snapshot.keys_read),
+                counter(snapshot.prefix_scans),
+                counter(snapshot.batch_writes),
+                counter(snapshot.bytes_read),
+                counter(snapshot.bytes_written),
+                histogram(&snapshot.read_latency),
+                histogram(&snapshot.write_latency),
+            ];
+            self.descs
+                .iter()
+                .zip(METRICS)
+                .zip(values)
+                .map(|((desc, (_, _, is_histogram)), metric)| {
+                    let mut family = MetricFamily::default();
+                    family.set_name(desc.fq_name.clone());
+                    family.set_help(desc.help.clone());
+                    family.set_field_type(if is_histogram {
+                        MetricType::HISTOGRAM
+                    } else {
+                        MetricType::COUNTER
+                    });
+                    family.mut_metric().push(metric);
+                    family
+                })
+                .collect()
+        }
+    }
+}
+
+/// A wrapper counting the operations of a store in a [`StoreMetrics`].
+#[derive(Clone)]
+pub struct CountingStore<K> {
+    /// The underlying store.
+    store: K,
+    /// The counters.
+    metrics: Arc<StoreMetrics>,
+}
+
+impl<K> CountingStore<K> {
+    /// Creates a store counting the operations of `store` in new counters.
+    pub fn new(store: K) -> Self {
+        let metrics = Arc::default();
+        Self { store, metrics }
+    }
+
+    /// Returns the counters of the store.
+    pub fn metrics(&self) -> &Arc<StoreMetrics> {
+        &self.metrics
+    }
+}
+
+impl<K> WithError for CountingStore<K>
+where
+    K: WithError,
+{
+    type Error = K::Error;
+}
+
+impl<K> ReadableKeyValueStore for CountingStore<K>
+where
+    K: ReadableKeyValueStore + Send + Sync,
+{
+    const MAX_KEY_SIZE: usize = K::MAX_KEY_SIZE;
+    type Keys = K::Keys;
+    type KeyValues = K::KeyValues;
+
+    fn max_stream_queries(&self) -> usize {
+        self.store.max_stream_queries()
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        let start = Instant::now();
+        let value = self.store.read_value_bytes(key).await?;
+        let num_bytes = value.as_ref().map_or(0, Vec::len);
+        StoreMetrics::add(&self.metrics.bytes_read, num_bytes);
+        self.metrics.record_read(1, start);
+        Ok(value)
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        let start = Instant::now();
+        let result = self.store.contains_key(key).await?;
+        self.metrics.record_read(1, start);
+        Ok(result)
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, Self::Error> {
+        let start = Instant::now();
+        let num_keys = keys.len();
+        let results = self.store.contains_keys(keys).await?;
+        self.metrics.record_read(num_keys, start);
+        Ok(results)
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let start = Instant::now();
+        let num_keys = keys.len();
+        let values = self.store.read_multi_values_bytes(keys).await?;
+        let num_bytes = values.iter().flatten().map(Vec::len).sum();
+        StoreMetrics::add(&self.metrics.bytes_read, num_bytes);
+        self.metrics.record_read(num_keys, start);
+        Ok(values)
+    }
+
+    async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Self::Keys, Self::Error> {
+        let start = Instant::now();
+        let keys = self.store.find_keys_by_prefix(key_prefix).await?;
+        let mut num_bytes = 0;
+        for key in keys.iterator() {
+            num_bytes += key?.len();
+        }
+        StoreMetrics::add(&self.metrics.bytes_read, num_bytes);
+        self.metrics.record_scan(start);
+        Ok(keys)
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Self::KeyValues, Self::Error> {
+        let start = Instant::now();
+        let key_values = self.store.find_key_values_by_prefix(key_prefix).await?;
+        let mut num_bytes = 0;
+        for key_value in key_values.iterator() {
+            let (key, value) = key_value?;
+            num_bytes += key.len() + value.len();
+        }
+        StoreMetrics::add(&self.metrics.bytes_read, num_bytes);
+        self.metrics.record_scan(start);
+        Ok(key_values)
+    }
+
+    async fn find_keys_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        let start = Instant::now();
+        let keys = self
+            .store
+            .find_keys_by_prefix_with_options(key_prefix, order)
+            .await?;
+        let num_bytes = keys.iter().map(Vec::len).sum();
+        StoreMetrics::add(&self.metrics.bytes_read, num_bytes);
+        self.metrics.record_scan(start);
+        Ok(keys)
+    }
+
+    async fn find_key_values_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        let start = Instant::now();
+        let key_values = self
+            .store
+            .find_key_values_by_prefix_with_options(key_prefix, order)
+            .await?;
+        let num_bytes = key_values
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .sum();
+        StoreMetrics::add(&self.metrics.bytes_read, num_bytes);
+        self.metrics.record_scan(start);
+        Ok(key_values)
+    }
+
+    async fn find_keys_by_prefix_paginated(
+        &self,
+        key_prefix: &[u8],
+        limit: usize,
+        continuation: Option<Vec<u8>>,
+    ) -> Result<(Vec<Vec<u8>>, Option<Vec<u8>>), Self::Error> {
+        let start = Instant::now();
+        let (keys, continuation) = self
+            .store
+            .find_keys_by_prefix_paginated(key_prefix, limit, continuation)
+            .await?;
+        let num_bytes = keys.iter().map(Vec::len).sum();
+        StoreMetrics::add(&self.metrics.bytes_read, num_bytes);
+        self.metrics.record_scan(start);
+        Ok((keys, continuation))
+    }
+
+    async fn find_keys_in_range(
+        &self,
+        start_key: &[u8],
+        end_key: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        let start = Instant::now();
+        let keys = self.store.find_keys_in_range(start_key, end_key).await?;
+        let num_bytes = keys.iter().map(Vec::len).sum();
+        StoreMetrics::add(&self.metrics.bytes_read, num_bytes);
+        self.metrics.record_scan(start);
+        Ok(keys)
+    }
+
+    async fn count_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<usize, Self::Error> {
+        let start = Instant::now();
+        let count = self.store.count_keys_by_prefix(key_prefix).await?;
+        self.metrics.record_scan(start);
+        Ok(count)
+    }
+
+    // The streamed keys and values are not counted in the bytes read.
+    async fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, Vec<u8>, Self::Error>, Self::Error> {
+        let start = Instant::now();
+        let stream = self.store.find_keys_by_prefix_stream(key_prefix).await?;
+        self.metrics.record_scan(start);
+        Ok(stream)
+    }
+
+    async fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error>, Self::Error> {
+        let start = Instant::now();
+        let stream = self
+            .store
+            .find_key_values_by_prefix_stream(key_prefix)
+            .await?;
+        self.metrics.record_scan(start);
+        Ok(stream)
+    }
+}
+
+impl<K> WritableKeyValueStore for CountingStore<K>
+where
+    K: WritableKeyValueStore + Send + Sync,
+{
+    const MAX_VALUE_SIZE: usize = K::MAX_VALUE_SIZE;
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let num_bytes = batch.size();
+        self.store.write_batch(batch).await?;
+        self.metrics.record_write(num_bytes, start);
+        Ok(())
+    }
+
+    async fn write_batch_with_stats(&self, batch: Batch) -> Result<BatchStats, Self::Error> {
+        let start = Instant::now();
+        let num_bytes = batch.size();
+        let stats = self.store.write_batch_with_stats(batch).await?;
+        self.metrics.record_write(num_bytes, start);
+        Ok(stats)
+    }
+
+    async fn compare_and_put(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, ViewError> {
+        let start = Instant::now();
+        let num_bytes = key.len() + new.map_or(0, <[u8]>::len);
+        let result = self.store.compare_and_put(key, expected, new).await?;
+        self.metrics.record_write(num_bytes, start);
+        Ok(result)
+    }
+
+    async fn clear_journal(&self) -> Result<(), Self::Error> {
+        self.store.clear_journal().await
+    }
+}
+
+impl<K> AdminKeyValueStore for CountingStore<K>
+where
+    K: AdminKeyValueStore + Send + Sync,
+{
+    type Config = K::Config;
+
+    fn get_name() -> String {
+        format!("counting {}", K::get_name())
+    }
+
+    async fn connect(
+        config: &Self::Config,
+        namespace: &str,
+        root_key: &[u8],
+    ) -> Result<Self, Self::Error> {
+        let store = K::connect(config, namespace, root_key).await?;
+        Ok(Self::new(store))
+    }
+
+    fn clone_with_root_key(&self, root_key: &[u8]) -> Result<Self, Self::Error> {
+        let store = self.store.clone_with_root_key(root_key)?;
+        let metrics = self.metrics.clone();
+        Ok(Self { store, metrics })
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, Self::Error> {
+        K::list_all(config).await
+    }
+
+    async fn delete_all(config: &Self::Config) -> Result<(), Self::Error> {
+        K::delete_all(config).await
+    }
+
+    async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, Self::Error> {
+        K::exists(config, namespace).await
+    }
+
+    async fn create(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        K::create(config, namespace).await
+    }
+
+    async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        K::delete(config, namespace).await
+    }
+}
+
+#[cfg(with_testing)]
+impl<K> TestKeyValueStore for CountingStore<K>
+where
+    K: TestKeyValueStore + Send + Sync,
+{
+    async fn new_test_config() -> Result<K::Config, Self::Error> {
+        K::new_test_config().await
+    }
+}
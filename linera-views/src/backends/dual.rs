@@ -8,7 +8,7 @@ use thiserror::Error;
 #[cfg(with_testing)]
 use crate::store::TestKeyValueStore;
 use crate::{
-    batch::Batch,
+    batch::{Batch, BatchStats},
     store::{
         AdminKeyValueStore, KeyIterable, KeyValueIterable, KeyValueStoreError,
         ReadableKeyValueStore, WithError, WritableKeyValueStore,
@@ -214,6 +214,21 @@ where
         Ok(())
     }
 
+    async fn write_batch_with_stats(&self, batch: Batch) -> Result<BatchStats, Self::Error> {
+        match self.store_in_use {
+            StoreInUse::First => self
+                .first_store
+                .write_batch_with_stats(batch)
+                .await
+                .map_err(DualStoreError::First),
+            StoreInUse::Second => self
+                .second_store
+                .write_batch_with_stats(batch)
+                .await
+                .map_err(DualStoreError::Second),
+        }
+    }
+
     async fn clear_journal(&self) -> Result<(), Self::Error> {
         match self.store_in_use {
             StoreInUse::First => self
@@ -370,6 +385,14 @@ where
     E2: KeyValueStoreError,
 {
     const BACKEND: &'static str = "dual_store";
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            DualStoreError::BcsError(_) => false,
+            DualStoreError::First(error) => error.is_retryable(),
+            DualStoreError::Second(error) => error.is_retryable(),
+        }
+    }
 }
 
 /// A set of keys returned by [`DualStore::find_keys_by_prefix`].
@@ -3,51 +3,88 @@
 
 //! Implements [`crate::store::KeyValueStore`] for the DynamoDB database.
 
-use std::{collections::HashMap, env, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env,
+    fmt::Debug,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
 
 use async_lock::{Semaphore, SemaphoreGuard};
 use async_trait::async_trait;
+use aws_config::{sts::AssumeRoleProvider, BehaviorVersion, SdkConfig};
 use aws_sdk_dynamodb::{
-    error::SdkError,
+    config::{
+        interceptors::{
+            BeforeSerializationInterceptorContextRef, BeforeTransmitInterceptorContextRef,
+        },
+        timeout::TimeoutConfig,
+        ConfigBag, Intercept, ProvideCredentials, Region, RuntimeComponents,
+    },
+    error::{BoxError, ProvideErrorMetadata, SdkError},
     operation::{
         batch_write_item::BatchWriteItemError,
         create_table::CreateTableError,
+        delete_item::DeleteItemError,
         delete_table::DeleteTableError,
+        describe_table::DescribeTableError,
         get_item::GetItemError,
         list_tables::ListTablesError,
+        put_item::PutItemError,
         query::{QueryError, QueryOutput},
+        scan::ScanError,
+        tag_resource::TagResourceError,
         transact_write_items::TransactWriteItemsError,
+        update_continuous_backups::UpdateContinuousBackupsError,
     },
     primitives::Blob,
     types::{
-        AttributeDefinition, AttributeValue, Delete, KeySchemaElement, KeyType,
-        ProvisionedThroughput, Put, ScalarAttributeType, TransactWriteItem,
+        AttributeDefinition, AttributeValue, ConsumedCapacity, Delete, KeySchemaElement, KeyType,
+        PointInTimeRecoverySpecification, ProvisionedThroughput, Put, ReturnConsumedCapacity,
+        ScalarAttributeType, Select, TableDescription, TableStatus, Tag, TransactWriteItem,
     },
     Client,
 };
 use aws_smithy_types::error::operation::BuildError;
-use futures::future::{join_all, FutureExt as _};
-use linera_base::ensure;
+use futures::{
+    future::{join_all, FutureExt as _},
+    stream::{self, Stream, TryStreamExt as _},
+};
+use linera_base::{
+    ensure,
+    time::{timer, Duration, Instant},
+};
 use thiserror::Error;
+#[cfg(with_metrics)]
+use tracing::instrument;
+use tracing::Span;
 
+#[cfg(with_encryption)]
+use crate::encrypted::{EncryptedStore, EncryptedStoreConfig, EncryptionKey};
 #[cfg(with_metrics)]
 use crate::metering::MeteredStore;
-#[cfg(with_testing)]
-use crate::store::TestKeyValueStore;
 use crate::{
-    batch::SimpleUnorderedBatch,
+    batch::{Batch, BatchStats, SimpleUnorderedBatch, SimplifiedBatch as _},
     common::get_uleb128_size,
+    context::ViewContext,
     journaling::{DirectWritableKeyValueStore, JournalConsistencyError, JournalingKeyValueStore},
+    localstack::LOCALSTACK_ENDPOINT,
     lru_caching::{LruCachingConfig, LruCachingStore},
+    read_only::ReadOnlyStore,
     store::{
         AdminKeyValueStore, CommonStoreInternalConfig, KeyIterable, KeyValueIterable,
-        KeyValueStoreError, ReadableKeyValueStore, WithError,
+        KeyValueStoreError, Order, ReadableKeyValueStore, ResultStream, WithError,
+        WritableKeyValueStore as _,
     },
     value_splitting::{ValueSplittingError, ValueSplittingStore},
+    views::ViewError,
 };
-
-/// Name of the environment variable with the address to a LocalStack instance.
-const LOCALSTACK_ENDPOINT: &str = "LOCALSTACK_ENDPOINT";
+#[cfg(with_testing)]
+use crate::{localstack, store::TestKeyValueStore};
 
 /// The configuration to connect to DynamoDB.
 pub type Config = aws_sdk_dynamodb::Config;
@@ -60,12 +97,10 @@ async fn get_base_config() -> Result<aws_sdk_dynamodb::Config, DynamoDbStoreInte
     Ok((&base_config).into())
 }
 
-fn get_endpoint_address() -> Option<String> {
-    let endpoint_address = env::var(LOCALSTACK_ENDPOINT);
-    match endpoint_address {
-        Err(_) => None,
-        Ok(address) => Some(address),
-    }
+/// Gets the address of the LocalStack instance of the `LOCALSTACK_ENDPOINT` environment
+/// variable.
+fn get_endpoint_address() -> Result<String, DynamoDbStoreInternalError> {
+    env::var(LOCALSTACK_ENDPOINT).map_err(|_| DynamoDbStoreInternalError::LocalStackEndpoint)
 }
 
 /// Gets the localstack config
@@ -73,13 +108,32 @@ async fn get_localstack_config() -> Result<aws_sdk_dynamodb::Config, DynamoDbSto
     let base_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest())
         .boxed()
         .await;
-    let endpoint_address = get_endpoint_address().unwrap();
+    let endpoint_address = get_endpoint_address()?;
     let config = aws_sdk_dynamodb::config::Builder::from(&base_config)
         .endpoint_url(endpoint_address)
         .build();
     Ok(config)
 }
 
+/// Builds a configuration from explicit credentials and region, instead of the ones of
+/// the environment.
+fn get_config_with_credentials_internal(
+    credentials_provider: impl ProvideCredentials + 'static,
+    region: Region,
+    use_localstack: bool,
+) -> Result<aws_sdk_dynamodb::Config, DynamoDbStoreInternalError> {
+    let builder = aws_sdk_dynamodb::config::Builder::new()
+        .behavior_version(BehaviorVersion::latest())
+        .credentials_provider(credentials_provider)
+        .region(region);
+    if use_localstack {
+        let endpoint_address = get_endpoint_address()?;
+        Ok(builder.endpoint_url(endpoint_address).build())
+    } else {
+        Ok(builder.build())
+    }
+}
+
 /// Getting a configuration for the system
 async fn get_config_internal(
     use_localstack: bool,
@@ -91,6 +145,103 @@ async fn get_config_internal(
     }
 }
 
+/// The kind of DynamoDB operation whose consumed capacity is reported.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum DynamoDbOperation {
+    /// A `GetItem` request.
+    GetItem,
+    /// A `Query` request.
+    Query,
+    /// A `TransactWriteItems` request.
+    TransactWriteItems,
+    /// A conditional `PutItem` or `DeleteItem` request.
+    ConditionalWrite,
+}
+
+/// The capacity units consumed by DynamoDB operations.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConsumedCapacityUnits {
+    /// The read capacity units (RCU).
+    pub read_capacity_units: f64,
+    /// The write capacity units (WCU).
+    pub write_capacity_units: f64,
+}
+
+impl ConsumedCapacityUnits {
+    /// Adds the units of `other` to `self`.
+    pub fn add(&mut self, other: &ConsumedCapacityUnits) {
+        self.read_capacity_units += other.read_capacity_units;
+        self.write_capacity_units += other.write_capacity_units;
+    }
+}
+
+/// A sink receiving the capacity consumed by the operations of a [`DynamoDbStoreInternal`].
+pub trait ConsumedCapacitySink: Debug + Send + Sync {
+    /// Records the capacity units consumed by one operation on the given table.
+    fn record(&self, table: &str, operation: DynamoDbOperation, units: ConsumedCapacityUnits);
+}
+
+/// A [`ConsumedCapacitySink`] accumulating the consumed capacity in memory, per table and
+/// per operation.
+#[derive(Debug, Default)]
+pub struct CapacityCounter {
+    totals: Mutex<BTreeMap<(String, DynamoDbOperation), ConsumedCapacityUnits>>,
+}
+
+impl CapacityCounter {
+    fn sum_filtered(
+        &self,
+        filter: impl Fn(&str, DynamoDbOperation) -> bool,
+    ) -> ConsumedCapacityUnits {
+        let totals = self
+            .totals
+            .lock()
+            .expect("CapacityCounter lock should not be poisoned");
+        let mut sum = ConsumedCapacityUnits::default();
+        for ((table, operation), units) in totals.iter() {
+            if filter(table.as_str(), *operation) {
+                sum.add(units);
+            }
+        }
+        sum
+    }
+
+    /// Returns the total capacity consumed so far.
+    pub fn total_consumed(&self) -> ConsumedCapacityUnits {
+        self.sum_filtered(|_, _| true)
+    }
+
+    /// Returns the capacity consumed so far on the given table.
+    pub fn consumed_by_table(&self, table: &str) -> ConsumedCapacityUnits {
+        self.sum_filtered(|entry_table, _| entry_table == table)
+    }
+
+    /// Returns the capacity consumed so far by the given kind of operation.
+    pub fn consumed_by_operation(&self, operation: DynamoDbOperation) -> ConsumedCapacityUnits {
+        self.sum_filtered(|_, entry_operation| entry_operation == operation)
+    }
+}
+
+impl ConsumedCapacitySink for CapacityCounter {
+    fn record(&self, table: &str, operation: DynamoDbOperation, units: ConsumedCapacityUnits) {
+        let mut totals = self
+            .totals
+            .lock()
+            .expect("CapacityCounter lock should not be poisoned");
+        totals
+            .entry((table.to_string(), operation))
+            .or_default()
+            .add(&units);
+    }
+}
+
+/// Fundamental constants in DynamoDB: Table names have between 3 and 255 characters.
+/// See <https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/HowItWorks.NamingRulesDataTypes.html>
+const MIN_TABLE_NAME_LENGTH: usize = 3;
+
+/// The maximal length of a table name.
+const MAX_TABLE_NAME_LENGTH: usize = 255;
+
 /// The attribute name of the partition key.
 const PARTITION_ATTRIBUTE: &str = "item_partition";
 
@@ -106,8 +257,67 @@ const KEY_ATTRIBUTE: &str = "item_key";
 /// The attribute name of the table value blob.
 const VALUE_ATTRIBUTE: &str = "item_value";
 
-/// The attribute for obtaining the primary key (used as a sort key) with the stored value.
-const KEY_VALUE_ATTRIBUTE: &str = "item_key, item_value";
+/// The header byte of a value compressed with zstd. It is followed by the magic number
+/// of the zstd frame, which tells compressed values apart from the values written without
+/// compression.
+const COMPRESSED_VALUE_TAG: u8 = 0xff;
+
+/// The magic number starting a zstd frame.
+const ZSTD_FRAME_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The values of at most this size are not worth compressing.
+const COMPRESSION_THRESHOLD: usize = 256;
+
+/// The placeholder of the partition attribute in expressions.
+const PARTITION_NAME: &str = "#p";
+
+/// The placeholder of the key attribute in expressions.
+const KEY_NAME: &str = "#k";
+
+/// The placeholder of the value attribute in expressions.
+const VALUE_NAME: &str = "#v";
+
+/// The attributes returned by a query. The attributes are always referred to by
+/// placeholders, so that their names cannot collide with the reserved words of DynamoDB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Projection {
+    /// Only the keys.
+    Keys,
+    /// The keys together with the stored values.
+    KeysAndValues,
+    /// No attribute: only the number of matching items is returned.
+    Count,
+}
+
+impl Projection {
+    /// The projection expression, using placeholders for the attribute names.
+    fn expression(&self) -> Option<String> {
+        match self {
+            Projection::Keys => Some(KEY_NAME.to_string()),
+            Projection::KeysAndValues => Some(format!("{KEY_NAME}, {VALUE_NAME}")),
+            Projection::Count => None,
+        }
+    }
+
+    /// What the query returns.
+    fn select(&self) -> Select {
+        match self {
+            Projection::Keys | Projection::KeysAndValues => Select::SpecificAttributes,
+            Projection::Count => Select::Count,
+        }
+    }
+
+    /// The placeholders used by the projection, with the attribute names they stand for.
+    fn attribute_names(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Projection::Keys => &[(KEY_NAME, KEY_ATTRIBUTE)],
+            Projection::KeysAndValues => {
+                &[(KEY_NAME, KEY_ATTRIBUTE), (VALUE_NAME, VALUE_ATTRIBUTE)]
+            }
+            Projection::Count => &[],
+        }
+    }
+}
 
 /// TODO(#1084): The scheme below with the MAX_VALUE_SIZE has to be checked
 /// This is the maximum size of a raw value in DynamoDb.
@@ -142,6 +352,10 @@ const MAX_KEY_SIZE: usize = 1024;
 /// We're taking a conservative value because the mode of computation is unclear.
 const MAX_TRANSACT_WRITE_ITEM_TOTAL_SIZE: usize = 4000000;
 
+/// The lowest byte budget that can be configured for a transaction. The journal has to
+/// fit at least one block, that is one value together with its key, in a transaction.
+const MIN_TRANSACT_WRITE_ITEM_TOTAL_SIZE: usize = 2 * RAW_MAX_VALUE_SIZE;
+
 /// The DynamoDb database is potentially handling an infinite number of connections.
 /// However, for testing or some other purpose we really need to decrease the number of
 /// connections.
@@ -152,31 +366,74 @@ const TEST_DYNAMO_DB_MAX_CONCURRENT_QUERIES: usize = 10;
 #[cfg(with_testing)]
 const TEST_DYNAMO_DB_MAX_STREAM_QUERIES: usize = 10;
 
+/// LocalStack creates and deletes tables quickly, so the tests poll their status often.
+#[cfg(with_testing)]
+const TEST_TABLE_STATUS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The default number of journal transactions that are written concurrently.
+const DEFAULT_MAX_CONCURRENT_BATCHES: usize = 4;
+
+/// The default interval between two checks of the status of a table that is being created
+/// or deleted.
+const TABLE_STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The default time after which we stop waiting for a table to be created or deleted.
+const TABLE_STATUS_TIMEOUT: Duration = Duration::from_secs(300);
+
 /// Fundamental constants in DynamoDB: The maximum size of a TransactWriteItem is 100.
 /// See <https://docs.aws.amazon.com/amazondynamodb/latest/APIReference/API_TransactWriteItems.html>
 const MAX_TRANSACT_WRITE_ITEM_SIZE: usize = 100;
 
-/// Keys of length 0 are not allowed, so we extend by having a prefix on start
-fn extend_root_key(root_key: &[u8]) -> Vec<u8> {
-    let mut vec = vec![0];
-    vec.extend(root_key);
-    vec
+/// The first byte of the partitions of the stores without a key namespace. Keys of
+/// length 0 are not allowed, so this also makes the partition of the empty root key valid.
+const DEFAULT_PARTITION_TAG: u8 = 0;
+
+/// The first byte of the partitions of the stores with a key namespace. It is followed
+/// by the length of the key namespace on one byte, then by the key namespace itself.
+const KEY_NAMESPACE_PARTITION_TAG: u8 = 1;
+
+/// Builds the partition holding the items of a root key, within the key namespace if
+/// there is one.
+fn partition_key(key_namespace: Option<&str>, root_key: &[u8]) -> Vec<u8> {
+    let mut partition = match key_namespace {
+        None => vec![DEFAULT_PARTITION_TAG],
+        Some(key_namespace) => key_namespace_prefix(key_namespace),
+    };
+    partition.extend(root_key);
+    partition
+}
+
+/// The prefix shared by all the partitions of a key namespace. Key namespaces follow the
+/// rules of the table names, so their length fits in one byte.
+fn key_namespace_prefix(key_namespace: &str) -> Vec<u8> {
+    let mut prefix = vec![KEY_NAMESPACE_PARTITION_TAG, key_namespace.len() as u8];
+    prefix.extend(key_namespace.as_bytes());
+    prefix
+}
+
+/// Extracts the key namespace of a partition, if it belongs to one.
+fn parse_key_namespace(partition: &[u8]) -> Option<String> {
+    let [KEY_NAMESPACE_PARTITION_TAG, len, rest @ ..] = partition else {
+        return None;
+    };
+    let key_namespace = rest.get(..*len as usize)?;
+    String::from_utf8(key_namespace.to_vec()).ok()
 }
 
 /// Builds the key attributes for a table item.
 ///
-/// The key is composed of two attributes that are both binary blobs. The first attribute is a
-/// partition key and is currently just a dummy value that ensures all items are in the same
-/// partion. This is necessary for range queries to work correctly.
+/// The key is composed of two attributes that are both binary blobs. The first attribute is
+/// the partition key, obtained from the root key by [`partition_key`]. All the items of a
+/// root key are in the same partition, which is necessary for range queries to work
+/// correctly.
 ///
 /// The second attribute is the actual key value, which is generated by concatenating the
 /// context prefix. The Vec<u8> expression is obtained from self.derive_key.
-fn build_key(root_key: &[u8], key: Vec<u8>) -> HashMap<String, AttributeValue> {
-    let big_root = extend_root_key(root_key);
+fn build_key(partition: &[u8], key: Vec<u8>) -> HashMap<String, AttributeValue> {
     [
         (
             PARTITION_ATTRIBUTE.to_owned(),
-            AttributeValue::B(Blob::new(big_root)),
+            AttributeValue::B(Blob::new(partition.to_vec())),
         ),
         (KEY_ATTRIBUTE.to_owned(), AttributeValue::B(Blob::new(key))),
     ]
@@ -185,15 +442,14 @@ fn build_key(root_key: &[u8], key: Vec<u8>) -> HashMap<String, AttributeValue> {
 
 /// Builds the value attribute for storing a table item.
 fn build_key_value(
-    root_key: &[u8],
+    partition: &[u8],
     key: Vec<u8>,
     value: Vec<u8>,
 ) -> HashMap<String, AttributeValue> {
-    let big_root = extend_root_key(root_key);
     [
         (
             PARTITION_ATTRIBUTE.to_owned(),
-            AttributeValue::B(Blob::new(big_root)),
+            AttributeValue::B(Blob::new(partition.to_vec())),
         ),
         (KEY_ATTRIBUTE.to_owned(), AttributeValue::B(Blob::new(key))),
         (
@@ -214,6 +470,128 @@ fn check_key_size(key: &[u8]) -> Result<(), DynamoDbStoreInternalError> {
     Ok(())
 }
 
+/// Awaits the response to a request, reporting the expiration of the timeouts of the SDK
+/// as [`DynamoDbStoreInternalError::Timeout`].
+async fn timed<T, E>(
+    operation: &'static str,
+    response: impl Future<Output = Result<T, SdkError<E>>>,
+) -> Result<T, DynamoDbStoreInternalError>
+where
+    DynamoDbStoreInternalError: From<SdkError<E>>,
+{
+    let start = Instant::now();
+    match response.await {
+        Err(SdkError::TimeoutError(_)) => Err(DynamoDbStoreInternalError::Timeout {
+            operation,
+            elapsed: start.elapsed(),
+        }),
+        result => Ok(result?),
+    }
+}
+
+/// Counts the requests sent by the SDK and their attempts, retries included.
+#[derive(Clone, Debug, Default)]
+struct AttemptCounter {
+    requests: Arc<AtomicUsize>,
+    attempts: Arc<AtomicUsize>,
+}
+
+impl AttemptCounter {
+    /// The number of retries of the requests.
+    fn retries(&self) -> usize {
+        let requests = self.requests.load(Ordering::Relaxed);
+        self.attempts
+            .load(Ordering::Relaxed)
+            .saturating_sub(requests)
+    }
+}
+
+impl Intercept for AttemptCounter {
+    fn name(&self) -> &'static str {
+        "AttemptCounter"
+    }
+
+    fn read_before_execution(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn read_before_attempt(
+        &self,
+        _context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Records the retries and the elapsed time of an operation in the `retries` and
+/// `elapsed_ms` fields of the current span when dropped.
+struct SpanStats {
+    start: Instant,
+    attempts: AttemptCounter,
+}
+
+impl SpanStats {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            attempts: AttemptCounter::default(),
+        }
+    }
+}
+
+impl Drop for SpanStats {
+    fn drop(&mut self) {
+        let elapsed_ms = u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX);
+        Span::current()
+            .record("retries", self.attempts.retries())
+            .record("elapsed_ms", elapsed_ms);
+    }
+}
+
+/// Whether the stored value starts with the header of a compressed value.
+fn is_compressed_value(value: &[u8]) -> bool {
+    value.len() > ZSTD_FRAME_MAGIC.len()
+        && value[0] == COMPRESSED_VALUE_TAG
+        && value[1..=ZSTD_FRAME_MAGIC.len()] == ZSTD_FRAME_MAGIC
+}
+
+/// Compresses a value if this makes it smaller. A value that would be mistaken for a
+/// compressed one is always compressed.
+fn compress_value(value: Vec<u8>, level: i32) -> Result<Vec<u8>, DynamoDbStoreInternalError> {
+    let ambiguous = is_compressed_value(&value);
+    if value.len() <= COMPRESSION_THRESHOLD && !ambiguous {
+        return Ok(value);
+    }
+    let mut compressed = vec![COMPRESSED_VALUE_TAG];
+    zstd::stream::copy_encode(&value[..], &mut compressed, level)?;
+    if compressed.len() < value.len() || ambiguous {
+        Ok(compressed)
+    } else {
+        Ok(value)
+    }
+}
+
+/// Decompresses the value attribute of an item, if it was compressed.
+fn decompress_item_value(
+    item: &mut HashMap<String, AttributeValue>,
+) -> Result<(), DynamoDbStoreInternalError> {
+    if let Some(AttributeValue::B(blob)) = item.get_mut(VALUE_ATTRIBUTE) {
+        if is_compressed_value(blob.as_ref()) {
+            let value = zstd::stream::decode_all(&blob.as_ref()[1..])?;
+            *blob = Blob::new(value);
+        }
+    }
+    Ok(())
+}
+
 /// Extracts the key attribute from an item.
 fn extract_key(
     prefix_len: usize,
@@ -277,16 +655,16 @@ fn extract_key_value_owned(
 }
 
 struct TransactionBuilder {
-    root_key: Vec<u8>,
+    partition: Vec<u8>,
     transacts: Vec<TransactWriteItem>,
 }
 
 impl TransactionBuilder {
-    fn new(root_key: &[u8]) -> Self {
-        let root_key = root_key.to_vec();
+    fn new(partition: &[u8]) -> Self {
+        let partition = partition.to_vec();
         let transacts = Vec::new();
         Self {
-            root_key,
+            partition,
             transacts,
         }
     }
@@ -296,7 +674,7 @@ impl TransactionBuilder {
         key: Vec<u8>,
         store: &DynamoDbStoreInternal,
     ) -> Result<(), DynamoDbStoreInternalError> {
-        let transact = store.build_delete_transact(&self.root_key, key)?;
+        let transact = store.build_delete_transact(&self.partition, key)?;
         self.transacts.push(transact);
         Ok(())
     }
@@ -307,7 +685,7 @@ impl TransactionBuilder {
         value: Vec<u8>,
         store: &DynamoDbStoreInternal,
     ) -> Result<(), DynamoDbStoreInternalError> {
-        let transact = store.build_put_transact(&self.root_key, key, value)?;
+        let transact = store.build_put_transact(&self.partition, key, value)?;
         self.transacts.push(transact);
         Ok(())
     }
@@ -320,16 +698,98 @@ pub struct DynamoDbStoreInternal {
     namespace: String,
     semaphore: Option<Arc<Semaphore>>,
     max_stream_queries: usize,
-    root_key: Vec<u8>,
+    key_namespace: Option<String>,
+    partition: Vec<u8>,
+    capacity_sink: Option<Arc<dyn ConsumedCapacitySink>>,
+    max_concurrent_batches: usize,
+    max_batch_total_size: usize,
+    compression_level: Option<i32>,
 }
 
 /// The initial configuration of the system
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct DynamoDbStoreInternalConfig {
     /// The AWS configuration
     config: aws_sdk_dynamodb::Config,
     /// The common configuration of the key value store
     common_config: CommonStoreInternalConfig,
+    /// The optional sink receiving the consumed capacity of the operations
+    capacity_sink: Option<Arc<dyn ConsumedCapacitySink>>,
+    /// The maximal number of journal transactions written concurrently
+    max_concurrent_batches: usize,
+    /// The maximal number of bytes written in a single transaction
+    max_batch_total_size: usize,
+    /// The options applied to the table of the namespace
+    table_options: TableOptions,
+    /// How to wait for the table of the namespace to be created or deleted
+    table_wait_options: TableWaitOptions,
+    /// The timeout of the operations, including their retries
+    operation_timeout: Option<Duration>,
+    /// The timeout for establishing a connection
+    connect_timeout: Option<Duration>,
+    /// The zstd level of the compression of the written values, if they are compressed
+    compression_level: Option<i32>,
+    /// The key namespace isolating the items of the store from the rest of the table
+    key_namespace: Option<String>,
+}
+
+impl DynamoDbStoreInternalConfig {
+    /// Propagates the timeouts to the configuration of the AWS client.
+    fn update_timeout_config(&mut self) {
+        let mut timeout_config = TimeoutConfig::builder();
+        timeout_config
+            .set_operation_timeout(self.operation_timeout)
+            .set_connect_timeout(self.connect_timeout);
+        self.config = self
+            .config
+            .to_builder()
+            .timeout_config(timeout_config.build())
+            .build();
+    }
+}
+
+/// The options applied to a table when it is created, and again when an existing table
+/// is connected to.
+#[derive(Clone, Debug, Default)]
+pub struct TableOptions {
+    /// Whether point-in-time recovery is enabled. `None` leaves the setting of the table
+    /// unchanged.
+    pub point_in_time_recovery: Option<bool>,
+    /// The tags of the table, e.g. for cost allocation.
+    pub tags: Vec<(String, String)>,
+}
+
+/// How to wait for a table to be created or deleted. DynamoDB creates and deletes tables
+/// asynchronously, so a table is not usable right after `CreateTable` and its name cannot
+/// be reused right after `DeleteTable`.
+#[derive(Clone, Copy, Debug)]
+pub struct TableWaitOptions {
+    /// The interval between two checks of the status of the table.
+    pub poll_interval: Duration,
+    /// The time after which waiting fails with [`DynamoDbStoreInternalError::TableWaitTimeout`].
+    pub timeout: Duration,
+}
+
+impl Default for TableWaitOptions {
+    fn default() -> Self {
+        TableWaitOptions {
+            poll_interval: TABLE_STATUS_POLL_INTERVAL,
+            timeout: TABLE_STATUS_TIMEOUT,
+        }
+    }
+}
+
+impl TableOptions {
+    fn is_empty(&self) -> bool {
+        self.point_in_time_recovery.is_none() && self.tags.is_empty()
+    }
+
+    fn aws_tags(&self) -> Result<Vec<Tag>, BuildError> {
+        self.tags
+            .iter()
+            .map(|(key, value)| Tag::builder().key(key).value(value).build())
+            .collect()
+    }
 }
 
 impl AdminKeyValueStore for DynamoDbStoreInternal {
@@ -345,20 +805,43 @@ impl AdminKeyValueStore for DynamoDbStoreInternal {
         root_key: &[u8],
     ) -> Result<Self, DynamoDbStoreInternalError> {
         Self::check_namespace(namespace)?;
+        if let Some(key_namespace) = &config.key_namespace {
+            Self::check_namespace(key_namespace)?;
+        }
         let client = Client::from_conf(config.config.clone());
+        Self::apply_table_options(
+            &client,
+            namespace,
+            &config.table_options,
+            config.table_wait_options,
+        )
+        .await?;
         let semaphore = config
             .common_config
             .max_concurrent_queries
             .map(|n| Arc::new(Semaphore::new(n)));
         let max_stream_queries = config.common_config.max_stream_queries;
         let namespace = namespace.to_string();
-        let root_key = root_key.to_vec();
+        let key_namespace = config.key_namespace.clone();
+        let partition = partition_key(key_namespace.as_deref(), root_key);
+        let capacity_sink = config.capacity_sink.clone();
+        let max_concurrent_batches = config.max_concurrent_batches;
+        let max_batch_total_size = config.max_batch_total_size.clamp(
+            MIN_TRANSACT_WRITE_ITEM_TOTAL_SIZE,
+            MAX_TRANSACT_WRITE_ITEM_TOTAL_SIZE,
+        );
+        let compression_level = config.compression_level;
         Ok(Self {
             client,
             namespace,
             semaphore,
             max_stream_queries,
-            root_key,
+            key_namespace,
+            partition,
+            capacity_sink,
+            max_concurrent_batches,
+            max_batch_total_size,
+            compression_level,
         })
     }
 
@@ -367,13 +850,23 @@ impl AdminKeyValueStore for DynamoDbStoreInternal {
         let namespace = self.namespace.clone();
         let semaphore = self.semaphore.clone();
         let max_stream_queries = self.max_stream_queries;
-        let root_key = root_key.to_vec();
+        let key_namespace = self.key_namespace.clone();
+        let partition = partition_key(key_namespace.as_deref(), root_key);
+        let capacity_sink = self.capacity_sink.clone();
+        let max_concurrent_batches = self.max_concurrent_batches;
+        let max_batch_total_size = self.max_batch_total_size;
+        let compression_level = self.compression_level;
         Ok(Self {
             client,
             namespace,
             semaphore,
             max_stream_queries,
-            root_key,
+            key_namespace,
+            partition,
+            capacity_sink,
+            max_concurrent_batches,
+            max_batch_total_size,
+            compression_level,
         })
     }
 
@@ -420,7 +913,7 @@ impl AdminKeyValueStore for DynamoDbStoreInternal {
     ) -> Result<bool, DynamoDbStoreInternalError> {
         Self::check_namespace(namespace)?;
         let client = Client::from_conf(config.config.clone());
-        let key_db = build_key(EMPTY_ROOT_KEY, DB_KEY.to_vec());
+        let key_db = build_key(&partition_key(None, EMPTY_ROOT_KEY), DB_KEY.to_vec());
         let response = client
             .get_item()
             .table_name(namespace)
@@ -487,9 +980,18 @@ impl AdminKeyValueStore for DynamoDbStoreInternal {
                     .write_capacity_units(10)
                     .build()?,
             )
+            .set_tags(Some(config.table_options.aws_tags()?).filter(|tags| !tags.is_empty()))
             .send()
             .boxed()
             .await?;
+        Self::wait_for_active_table(&client, namespace, config.table_wait_options).await?;
+        Self::apply_table_options(
+            &client,
+            namespace,
+            &config.table_options,
+            config.table_wait_options,
+        )
+        .await?;
         Ok(())
     }
 
@@ -505,19 +1007,260 @@ impl AdminKeyValueStore for DynamoDbStoreInternal {
             .send()
             .boxed()
             .await?;
-        Ok(())
+        Self::wait_for_deleted_table(&client, namespace, config.table_wait_options).await
     }
 }
 
 impl DynamoDbStoreInternal {
+    /// Connects to the table of an existing namespace. Unlike
+    /// [`AdminKeyValueStore::maybe_create_and_connect`], the table is never created: a
+    /// missing table results in [`DynamoDbStoreInternalError::TableMissing`].
+    pub async fn connect_existing(
+        config: &DynamoDbStoreInternalConfig,
+        namespace: &str,
+        root_key: &[u8],
+    ) -> Result<Self, DynamoDbStoreInternalError> {
+        Self::check_table_exists(config, namespace).await?;
+        Self::connect(config, namespace, root_key).await
+    }
+
+    /// Waits until the table of `namespace` is active, e.g. after it was created by
+    /// another client.
+    pub async fn wait_until_active(
+        config: &DynamoDbStoreInternalConfig,
+        namespace: &str,
+    ) -> Result<(), DynamoDbStoreInternalError> {
+        Self::check_namespace(namespace)?;
+        let client = Client::from_conf(config.config.clone());
+        Self::wait_for_active_table(&client, namespace, config.table_wait_options).await?;
+        Ok(())
+    }
+
+    /// Waits until the table of `namespace` no longer exists, so that its name can be
+    /// reused.
+    pub async fn wait_for_table_deletion(
+        config: &DynamoDbStoreInternalConfig,
+        namespace: &str,
+    ) -> Result<(), DynamoDbStoreInternalError> {
+        Self::check_namespace(namespace)?;
+        let client = Client::from_conf(config.config.clone());
+        Self::wait_for_deleted_table(&client, namespace, config.table_wait_options).await
+    }
+
+    /// Lists the key namespaces having items in the table of `namespace`. This scans the
+    /// whole table.
+    pub async fn list_key_namespaces(
+        config: &DynamoDbStoreInternalConfig,
+        namespace: &str,
+    ) -> Result<Vec<String>, DynamoDbStoreInternalError> {
+        Self::check_namespace(namespace)?;
+        let client = Client::from_conf(config.config.clone());
+        let mut key_namespaces = BTreeSet::new();
+        let mut start_key = None;
+        loop {
+            let response = client
+                .scan()
+                .table_name(namespace)
+                .projection_expression(PARTITION_NAME)
+                .expression_attribute_names(PARTITION_NAME, PARTITION_ATTRIBUTE)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .boxed()
+                .await?;
+            for item in response.items.iter().flatten() {
+                if let Some(AttributeValue::B(partition)) = item.get(PARTITION_ATTRIBUTE) {
+                    key_namespaces.extend(parse_key_namespace(partition.as_ref()));
+                }
+            }
+            start_key = response.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+        Ok(key_namespaces.into_iter().collect())
+    }
+
+    /// Deletes all the items of a key namespace in the table of `namespace`, whatever
+    /// their root key. The other items of the table are left untouched.
+    pub async fn delete_key_namespace(
+        config: &DynamoDbStoreInternalConfig,
+        namespace: &str,
+        key_namespace: &str,
+    ) -> Result<(), DynamoDbStoreInternalError> {
+        Self::check_namespace(namespace)?;
+        Self::check_namespace(key_namespace)?;
+        let client = Client::from_conf(config.config.clone());
+        let prefix = key_namespace_prefix(key_namespace);
+        let mut start_key = None;
+        loop {
+            let response = client
+                .scan()
+                .table_name(namespace)
+                .projection_expression(format!("{PARTITION_NAME}, {KEY_NAME}"))
+                .filter_expression(format!("begins_with({PARTITION_NAME}, :prefix)"))
+                .expression_attribute_names(PARTITION_NAME, PARTITION_ATTRIBUTE)
+                .expression_attribute_names(KEY_NAME, KEY_ATTRIBUTE)
+                .expression_attribute_values(
+                    ":prefix",
+                    AttributeValue::B(Blob::new(prefix.clone())),
+                )
+                .set_exclusive_start_key(start_key)
+                .send()
+                .boxed()
+                .await?;
+            // The items only have the key attributes, so they can be used as keys.
+            for keys in response.items().chunks(MAX_TRANSACT_WRITE_ITEM_SIZE) {
+                let transacts = keys
+                    .iter()
+                    .map(|key| {
+                        let request = Delete::builder()
+                            .table_name(namespace)
+                            .set_key(Some(key.clone()))
+                            .build()?;
+                        Ok(TransactWriteItem::builder().delete(request).build())
+                    })
+                    .collect::<Result<Vec<_>, DynamoDbStoreInternalError>>()?;
+                client
+                    .transact_write_items()
+                    .set_transact_items(Some(transacts))
+                    .send()
+                    .boxed()
+                    .await?;
+            }
+            start_key = response.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks with `DescribeTable` that the table of the namespace exists.
+    async fn check_table_exists(
+        config: &DynamoDbStoreInternalConfig,
+        namespace: &str,
+    ) -> Result<(), DynamoDbStoreInternalError> {
+        Self::check_namespace(namespace)?;
+        let client = Client::from_conf(config.config.clone());
+        ensure!(
+            Self::describe_table(&client, namespace).await?.is_some(),
+            DynamoDbStoreInternalError::TableMissing(namespace.to_string())
+        );
+        Ok(())
+    }
+
+    /// Waits for the table to be active and applies the options to it. Enabling
+    /// point-in-time recovery and tagging are idempotent, so this is also done for tables
+    /// that already exist.
+    async fn apply_table_options(
+        client: &Client,
+        namespace: &str,
+        table_options: &TableOptions,
+        wait_options: TableWaitOptions,
+    ) -> Result<(), DynamoDbStoreInternalError> {
+        if table_options.is_empty() {
+            return Ok(());
+        }
+        let table = Self::wait_for_active_table(client, namespace, wait_options).await?;
+        if let Some(enabled) = table_options.point_in_time_recovery {
+            client
+                .update_continuous_backups()
+                .table_name(namespace)
+                .point_in_time_recovery_specification(
+                    PointInTimeRecoverySpecification::builder()
+                        .point_in_time_recovery_enabled(enabled)
+                        .build()?,
+                )
+                .send()
+                .boxed()
+                .await?;
+        }
+        if !table_options.tags.is_empty() {
+            let table_arn = table
+                .table_arn
+                .ok_or(DynamoDbStoreInternalError::MissingTableArn)?;
+            client
+                .tag_resource()
+                .resource_arn(table_arn)
+                .set_tags(Some(table_options.aws_tags()?))
+                .send()
+                .boxed()
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Polls the description of the table until it is active. A table that does not
+    /// exist yet is waited for as well, since a table being created may not be visible
+    /// right away.
+    async fn wait_for_active_table(
+        client: &Client,
+        namespace: &str,
+        wait_options: TableWaitOptions,
+    ) -> Result<TableDescription, DynamoDbStoreInternalError> {
+        let start = Instant::now();
+        loop {
+            match Self::describe_table(client, namespace).await? {
+                Some(table) if table.table_status == Some(TableStatus::Active) => return Ok(table),
+                _ => {}
+            }
+            ensure!(
+                start.elapsed() < wait_options.timeout,
+                DynamoDbStoreInternalError::TableWaitTimeout(namespace.to_string())
+            );
+            timer::sleep(wait_options.poll_interval).await;
+        }
+    }
+
+    /// Polls the description of the table until it no longer exists.
+    async fn wait_for_deleted_table(
+        client: &Client,
+        namespace: &str,
+        wait_options: TableWaitOptions,
+    ) -> Result<(), DynamoDbStoreInternalError> {
+        let start = Instant::now();
+        while Self::describe_table(client, namespace).await?.is_some() {
+            ensure!(
+                start.elapsed() < wait_options.timeout,
+                DynamoDbStoreInternalError::TableWaitTimeout(namespace.to_string())
+            );
+            timer::sleep(wait_options.poll_interval).await;
+        }
+        Ok(())
+    }
+
+    /// Returns the description of the table, or `None` if it does not exist.
+    async fn describe_table(
+        client: &Client,
+        namespace: &str,
+    ) -> Result<Option<TableDescription>, DynamoDbStoreInternalError> {
+        let response = client
+            .describe_table()
+            .table_name(namespace)
+            .send()
+            .boxed()
+            .await;
+        match response {
+            Ok(response) => Ok(response.table),
+            Err(error)
+                if error
+                    .as_service_error()
+                    .is_some_and(DescribeTableError::is_resource_not_found_exception) =>
+            {
+                Ok(None)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
     /// Namespaces are named table names in DynamoDb [naming
     /// rules](https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/HowItWorks.NamingRulesDataTypes.html#HowItWorks.NamingRules),
     /// so we need to check correctness of the namespace
-    fn check_namespace(namespace: &str) -> Result<(), InvalidNamespace> {
-        if namespace.len() < 3 {
+    pub fn check_namespace(namespace: &str) -> Result<(), InvalidNamespace> {
+        if namespace.len() < MIN_TABLE_NAME_LENGTH {
             return Err(InvalidNamespace::TooShort);
         }
-        if namespace.len() > 255 {
+        if namespace.len() > MAX_TABLE_NAME_LENGTH {
             return Err(InvalidNamespace::TooLong);
         }
         if !namespace.chars().all(|character| {
@@ -533,35 +1276,47 @@ impl DynamoDbStoreInternal {
 
     fn build_delete_transact(
         &self,
-        root_key: &[u8],
+        partition: &[u8],
         key: Vec<u8>,
     ) -> Result<TransactWriteItem, DynamoDbStoreInternalError> {
         check_key_size(&key)?;
         let request = Delete::builder()
             .table_name(&self.namespace)
-            .set_key(Some(build_key(root_key, key)))
+            .set_key(Some(build_key(partition, key)))
             .build()?;
         Ok(TransactWriteItem::builder().delete(request).build())
     }
 
     fn build_put_transact(
         &self,
-        root_key: &[u8],
+        partition: &[u8],
         key: Vec<u8>,
         value: Vec<u8>,
     ) -> Result<TransactWriteItem, DynamoDbStoreInternalError> {
         check_key_size(&key)?;
+        let value = self.encode_value(value)?;
         ensure!(
-            value.len() <= RAW_MAX_VALUE_SIZE,
-            DynamoDbStoreInternalError::ValueLengthTooLarge
+            key.len() + value.len() <= RAW_MAX_VALUE_SIZE,
+            DynamoDbStoreInternalError::ValueTooLarge {
+                key_len: key.len(),
+                value_len: value.len(),
+            }
         );
         let request = Put::builder()
             .table_name(&self.namespace)
-            .set_item(Some(build_key_value(root_key, key, value)))
+            .set_item(Some(build_key_value(partition, key, value)))
             .build()?;
         Ok(TransactWriteItem::builder().put(request).build())
     }
 
+    /// Compresses the value to be written if compression is enabled.
+    fn encode_value(&self, value: Vec<u8>) -> Result<Vec<u8>, DynamoDbStoreInternalError> {
+        match self.compression_level {
+            None => Ok(value),
+            Some(level) => compress_value(value, level),
+        }
+    }
+
     /// Obtains the semaphore lock on the database if needed.
     async fn acquire(&self) -> Option<SemaphoreGuard<'_>> {
         match &self.semaphore {
@@ -570,48 +1325,248 @@ impl DynamoDbStoreInternal {
         }
     }
 
+    /// Requests the consumed capacity from DynamoDB only if there is a sink to report it to.
+    fn return_consumed_capacity(&self) -> Option<ReturnConsumedCapacity> {
+        self.capacity_sink
+            .as_ref()
+            .map(|_| ReturnConsumedCapacity::Total)
+    }
+
+    /// Reports the capacity consumed by an operation to the sink, if any.
+    fn record_consumed_capacity<'a>(
+        &self,
+        operation: DynamoDbOperation,
+        capacities: impl IntoIterator<Item = &'a ConsumedCapacity>,
+    ) {
+        let Some(sink) = &self.capacity_sink else {
+            return;
+        };
+        for capacity in capacities {
+            // With `ReturnConsumedCapacity::Total`, DynamoDB may only report the aggregated
+            // `capacity_units`, which we attribute according to the kind of operation.
+            let total = capacity.capacity_units().unwrap_or_default();
+            let units = match operation {
+                DynamoDbOperation::GetItem | DynamoDbOperation::Query => ConsumedCapacityUnits {
+                    read_capacity_units: capacity.read_capacity_units().unwrap_or(total),
+                    write_capacity_units: capacity.write_capacity_units().unwrap_or_default(),
+                },
+                DynamoDbOperation::TransactWriteItems | DynamoDbOperation::ConditionalWrite => {
+                    ConsumedCapacityUnits {
+                        read_capacity_units: capacity.read_capacity_units().unwrap_or_default(),
+                        write_capacity_units: capacity.write_capacity_units().unwrap_or(total),
+                    }
+                }
+            };
+            let table = capacity.table_name().unwrap_or(&self.namespace);
+            sink.record(table, operation, units);
+        }
+    }
+
+    #[expect(clippy::too_many_arguments)]
     async fn get_query_output(
         &self,
-        attribute_str: &str,
-        root_key: &[u8],
-        key_prefix: &[u8],
+        projection: Projection,
+        partition: &[u8],
+        condition: KeyCondition<'_>,
         start_key_map: Option<HashMap<String, AttributeValue>>,
+        order: Order,
+        limit: Option<i32>,
+        attempts: &AttemptCounter,
     ) -> Result<QueryOutput, DynamoDbStoreInternalError> {
         let _guard = self.acquire().await;
-        let big_root = extend_root_key(root_key);
-        let response = self
-            .client
-            .query()
-            .table_name(&self.namespace)
-            .projection_expression(attribute_str)
-            .key_condition_expression(format!(
-                "{PARTITION_ATTRIBUTE} = :partition and begins_with({KEY_ATTRIBUTE}, :prefix)"
-            ))
-            .expression_attribute_values(":partition", AttributeValue::B(Blob::new(big_root)))
-            .expression_attribute_values(":prefix", AttributeValue::B(Blob::new(key_prefix)))
-            .set_exclusive_start_key(start_key_map)
-            .send()
-            .boxed()
-            .await?;
-        Ok(response)
+        let mut query = self
+            .client
+            .query()
+            .table_name(&self.namespace)
+            .select(projection.select())
+            .set_projection_expression(projection.expression())
+            .expression_attribute_names(PARTITION_NAME, PARTITION_ATTRIBUTE)
+            .expression_attribute_values(
+                ":partition",
+                AttributeValue::B(Blob::new(partition.to_vec())),
+            );
+        for (name, attribute) in projection.attribute_names() {
+            query = query.expression_attribute_names(*name, *attribute);
+        }
+        let query = match condition {
+            KeyCondition::Prefix(key_prefix) => query
+                .key_condition_expression(format!(
+                    "{PARTITION_NAME} = :partition and begins_with({KEY_NAME}, :prefix)"
+                ))
+                .expression_attribute_values(":prefix", AttributeValue::B(Blob::new(key_prefix))),
+            KeyCondition::Between(start, end) => query
+                .key_condition_expression(format!(
+                    "{PARTITION_NAME} = :partition and {KEY_NAME} BETWEEN :start AND :end"
+                ))
+                .expression_attribute_values(":start", AttributeValue::B(Blob::new(start)))
+                .expression_attribute_values(":end", AttributeValue::B(Blob::new(end))),
+        };
+        let request = query
+            .set_exclusive_start_key(start_key_map)
+            .set_limit(limit)
+            .scan_index_forward(order == Order::Forward)
+            .set_return_consumed_capacity(self.return_consumed_capacity())
+            .customize()
+            .interceptor(attempts.clone())
+            .send()
+            .boxed();
+        let mut response = timed("Query", request).await?;
+        self.record_consumed_capacity(DynamoDbOperation::Query, &response.consumed_capacity);
+        for item in response.items.iter_mut().flatten() {
+            decompress_item_value(item)?;
+        }
+        Ok(response)
+    }
+
+    /// Streams the pages of the query for the keys starting with `key_prefix`. A page is
+    /// only fetched once the previous one has been consumed.
+    fn get_query_output_stream<'a>(
+        &'a self,
+        projection: Projection,
+        key_prefix: &'a [u8],
+    ) -> impl Stream<Item = Result<QueryOutput, DynamoDbStoreInternalError>> + Send + 'a {
+        // The state is `None` once the last page has been fetched.
+        stream::try_unfold(Some(None), move |start_key| async move {
+            let Some(start_key) = start_key else {
+                return Ok(None);
+            };
+            let response = self
+                .get_query_output(
+                    projection,
+                    &self.partition,
+                    KeyCondition::Prefix(key_prefix),
+                    start_key,
+                    Order::Forward,
+                    None,
+                    &AttemptCounter::default(),
+                )
+                .await?;
+            let next_start_key = response.last_evaluated_key.clone().map(Some);
+            Ok(Some((response, next_start_key)))
+        })
+    }
+
+    /// Writes the value of `key`, or deletes it, under the condition that its current
+    /// value is `expected`. Returns `false` if the condition does not hold.
+    ///
+    /// With compression, the stored value is compared to the compressed `expected` value.
+    /// A large value written by a client without compression therefore never matches.
+    async fn conditional_write(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, DynamoDbStoreInternalError> {
+        check_key_size(key)?;
+        let expected = expected
+            .map(|expected| self.encode_value(expected.to_vec()))
+            .transpose()?;
+        // Only the placeholders occurring in the condition may be declared.
+        let (condition, name, attribute, expected_values) = match expected {
+            None => (
+                format!("attribute_not_exists({KEY_NAME})"),
+                KEY_NAME,
+                KEY_ATTRIBUTE,
+                None,
+            ),
+            Some(expected) => (
+                format!("{VALUE_NAME} = :expected"),
+                VALUE_NAME,
+                VALUE_ATTRIBUTE,
+                Some(HashMap::from([(
+                    ":expected".to_string(),
+                    AttributeValue::B(Blob::new(expected)),
+                )])),
+            ),
+        };
+        let _guard = self.acquire().await;
+        let consumed_capacity = match new {
+            Some(value) => {
+                let value = self.encode_value(value.to_vec())?;
+                ensure!(
+                    key.len() + value.len() <= RAW_MAX_VALUE_SIZE,
+                    DynamoDbStoreInternalError::ValueTooLarge {
+                        key_len: key.len(),
+                        value_len: value.len(),
+                    }
+                );
+                let item = build_key_value(&self.partition, key.to_vec(), value);
+                let response = self
+                    .client
+                    .put_item()
+                    .table_name(&self.namespace)
+                    .set_item(Some(item))
+                    .condition_expression(condition)
+                    .expression_attribute_names(name, attribute)
+                    .set_expression_attribute_values(expected_values)
+                    .set_return_consumed_capacity(self.return_consumed_capacity())
+                    .send()
+                    .boxed()
+                    .await;
+                match response {
+                    Ok(response) => response.consumed_capacity,
+                    Err(error)
+                        if error
+                            .as_service_error()
+                            .is_some_and(PutItemError::is_conditional_check_failed_exception) =>
+                    {
+                        return Ok(false);
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+            }
+            None => {
+                let response = self
+                    .client
+                    .delete_item()
+                    .table_name(&self.namespace)
+                    .set_key(Some(build_key(&self.partition, key.to_vec())))
+                    .condition_expression(condition)
+                    .expression_attribute_names(name, attribute)
+                    .set_expression_attribute_values(expected_values)
+                    .set_return_consumed_capacity(self.return_consumed_capacity())
+                    .send()
+                    .boxed()
+                    .await;
+                match response {
+                    Ok(response) => response.consumed_capacity,
+                    Err(error)
+                        if error.as_service_error().is_some_and(
+                            DeleteItemError::is_conditional_check_failed_exception,
+                        ) =>
+                    {
+                        return Ok(false);
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+            }
+        };
+        self.record_consumed_capacity(DynamoDbOperation::ConditionalWrite, &consumed_capacity);
+        Ok(true)
     }
 
     async fn read_value_bytes_general(
         &self,
         key_db: HashMap<String, AttributeValue>,
+        attempts: &AttemptCounter,
     ) -> Result<Option<Vec<u8>>, DynamoDbStoreInternalError> {
         let _guard = self.acquire().await;
-        let response = self
+        let request = self
             .client
             .get_item()
             .table_name(&self.namespace)
             .set_key(Some(key_db))
+            .set_return_consumed_capacity(self.return_consumed_capacity())
+            .customize()
+            .interceptor(attempts.clone())
             .send()
-            .boxed()
-            .await?;
+            .boxed();
+        let response = timed("GetItem", request).await?;
+        self.record_consumed_capacity(DynamoDbOperation::GetItem, &response.consumed_capacity);
 
         match response.item {
             Some(mut item) => {
+                decompress_item_value(&mut item)?;
                 let value = extract_value_owned(&mut item)?;
                 Ok(Some(value))
             }
@@ -622,33 +1577,92 @@ impl DynamoDbStoreInternal {
     async fn contains_key_general(
         &self,
         key_db: HashMap<String, AttributeValue>,
+        attempts: &AttemptCounter,
     ) -> Result<bool, DynamoDbStoreInternalError> {
         let _guard = self.acquire().await;
-        let response = self
+        let request = self
             .client
             .get_item()
             .table_name(&self.namespace)
             .set_key(Some(key_db))
             .projection_expression(PARTITION_ATTRIBUTE)
+            .set_return_consumed_capacity(self.return_consumed_capacity())
+            .customize()
+            .interceptor(attempts.clone())
             .send()
-            .boxed()
-            .await?;
+            .boxed();
+        let response = timed("GetItem", request).await?;
+        self.record_consumed_capacity(DynamoDbOperation::GetItem, &response.consumed_capacity);
 
         Ok(response.item.is_some())
     }
 
+    /// Writes the batch in a single transaction, counting the attempts in `attempts`.
+    async fn write_batch_general(
+        &self,
+        batch: SimpleUnorderedBatch,
+        attempts: &AttemptCounter,
+    ) -> Result<BatchStats, DynamoDbStoreInternalError> {
+        let bytes_written = batch.num_bytes();
+        let mut builder = TransactionBuilder::new(&self.partition);
+        for key in batch.deletions {
+            builder.insert_delete_request(key, self)?;
+        }
+        for (key, value) in batch.insertions {
+            builder.insert_put_request(key, value, self)?;
+        }
+        if builder.transacts.is_empty() {
+            return Ok(BatchStats::default());
+        }
+        let _guard = self.acquire().await;
+        let request = self
+            .client
+            .transact_write_items()
+            .set_transact_items(Some(builder.transacts))
+            .set_return_consumed_capacity(self.return_consumed_capacity())
+            .customize()
+            .interceptor(attempts.clone())
+            .send()
+            .boxed();
+        let response = timed("TransactWriteItems", request).await?;
+        self.record_consumed_capacity(
+            DynamoDbOperation::TransactWriteItems,
+            response.consumed_capacity.iter().flatten(),
+        );
+        Ok(BatchStats {
+            bytes_written,
+            round_trips: 1,
+            retries: attempts.retries(),
+            ..BatchStats::default()
+        })
+    }
+
     async fn get_list_responses(
         &self,
-        attribute: &str,
-        root_key: &[u8],
-        key_prefix: &[u8],
+        projection: Projection,
+        partition: &[u8],
+        condition: KeyCondition<'_>,
+        order: Order,
+        attempts: &AttemptCounter,
     ) -> Result<QueryResponses, DynamoDbStoreInternalError> {
-        check_key_size(key_prefix)?;
+        let prefix_len = match condition {
+            KeyCondition::Prefix(key_prefix) => {
+                check_key_size(key_prefix)?;
+                key_prefix.len()
+            }
+            KeyCondition::Between(start, end) => {
+                check_key_size(start)?;
+                check_key_size(end)?;
+                0
+            }
+        };
         let mut responses = Vec::new();
         let mut start_key = None;
         loop {
             let response = self
-                .get_query_output(attribute, root_key, key_prefix, start_key)
+                .get_query_output(
+                    projection, partition, condition, start_key, order, None, attempts,
+                )
                 .await?;
             let last_evaluated = response.last_evaluated_key.clone();
             responses.push(response);
@@ -662,12 +1676,21 @@ impl DynamoDbStoreInternal {
             }
         }
         Ok(QueryResponses {
-            prefix_len: key_prefix.len(),
+            prefix_len,
             responses,
         })
     }
 }
 
+/// The condition on the sort key of a query.
+#[derive(Clone, Copy)]
+enum KeyCondition<'a> {
+    /// The keys starting with the given prefix.
+    Prefix(&'a [u8]),
+    /// The keys between the two bounds, both inclusive.
+    Between(&'a [u8], &'a [u8]),
+}
+
 struct QueryResponses {
     prefix_len: usize,
     responses: Vec<QueryOutput>,
@@ -850,30 +1873,46 @@ impl ReadableKeyValueStore for DynamoDbStoreInternal {
         self.max_stream_queries
     }
 
+    #[cfg_attr(
+        with_metrics,
+        instrument(
+            name = "dynamo_db_read_value_bytes",
+            level = "debug",
+            skip_all,
+            fields(
+                key_len = key.len(),
+                retries = tracing::field::Empty,
+                elapsed_ms = tracing::field::Empty
+            )
+        )
+    )]
     async fn read_value_bytes(
         &self,
         key: &[u8],
     ) -> Result<Option<Vec<u8>>, DynamoDbStoreInternalError> {
         check_key_size(key)?;
-        let key_db = build_key(&self.root_key, key.to_vec());
-        self.read_value_bytes_general(key_db).await
+        let stats = SpanStats::new();
+        let key_db = build_key(&self.partition, key.to_vec());
+        self.read_value_bytes_general(key_db, &stats.attempts).await
     }
 
     async fn contains_key(&self, key: &[u8]) -> Result<bool, DynamoDbStoreInternalError> {
         check_key_size(key)?;
-        let key_db = build_key(&self.root_key, key.to_vec());
-        self.contains_key_general(key_db).await
+        let key_db = build_key(&self.partition, key.to_vec());
+        self.contains_key_general(key_db, &AttemptCounter::default())
+            .await
     }
 
     async fn contains_keys(
         &self,
         keys: Vec<Vec<u8>>,
     ) -> Result<Vec<bool>, DynamoDbStoreInternalError> {
+        let attempts = AttemptCounter::default();
         let mut handles = Vec::new();
         for key in keys {
             check_key_size(&key)?;
-            let key_db = build_key(&self.root_key, key);
-            let handle = self.contains_key_general(key_db);
+            let key_db = build_key(&self.partition, key);
+            let handle = self.contains_key_general(key_db, &attempts);
             handles.push(handle);
         }
         join_all(handles)
@@ -886,11 +1925,12 @@ impl ReadableKeyValueStore for DynamoDbStoreInternal {
         &self,
         keys: Vec<Vec<u8>>,
     ) -> Result<Vec<Option<Vec<u8>>>, DynamoDbStoreInternalError> {
+        let attempts = AttemptCounter::default();
         let mut handles = Vec::new();
         for key in keys {
             check_key_size(&key)?;
-            let key_db = build_key(&self.root_key, key);
-            let handle = self.read_value_bytes_general(key_db);
+            let key_db = build_key(&self.partition, key);
+            let handle = self.read_value_bytes_general(key_db, &attempts);
             handles.push(handle);
         }
         join_all(handles)
@@ -899,25 +1939,293 @@ impl ReadableKeyValueStore for DynamoDbStoreInternal {
             .collect::<Result<_, _>>()
     }
 
+    #[cfg_attr(
+        with_metrics,
+        instrument(
+            name = "dynamo_db_find_keys_by_prefix",
+            level = "debug",
+            skip_all,
+            fields(
+                key_prefix_len = key_prefix.len(),
+                retries = tracing::field::Empty,
+                elapsed_ms = tracing::field::Empty
+            )
+        )
+    )]
     async fn find_keys_by_prefix(
         &self,
         key_prefix: &[u8],
     ) -> Result<DynamoDbKeys, DynamoDbStoreInternalError> {
+        let stats = SpanStats::new();
         let result_queries = self
-            .get_list_responses(KEY_ATTRIBUTE, &self.root_key, key_prefix)
+            .get_list_responses(
+                Projection::Keys,
+                &self.partition,
+                KeyCondition::Prefix(key_prefix),
+                Order::Forward,
+                &stats.attempts,
+            )
             .await?;
         Ok(DynamoDbKeys { result_queries })
     }
 
+    #[cfg_attr(
+        with_metrics,
+        instrument(
+            name = "dynamo_db_find_key_values_by_prefix",
+            level = "debug",
+            skip_all,
+            fields(
+                key_prefix_len = key_prefix.len(),
+                retries = tracing::field::Empty,
+                elapsed_ms = tracing::field::Empty
+            )
+        )
+    )]
     async fn find_key_values_by_prefix(
         &self,
         key_prefix: &[u8],
     ) -> Result<DynamoDbKeyValues, DynamoDbStoreInternalError> {
+        let stats = SpanStats::new();
         let result_queries = self
-            .get_list_responses(KEY_VALUE_ATTRIBUTE, &self.root_key, key_prefix)
+            .get_list_responses(
+                Projection::KeysAndValues,
+                &self.partition,
+                KeyCondition::Prefix(key_prefix),
+                Order::Forward,
+                &stats.attempts,
+            )
             .await?;
         Ok(DynamoDbKeyValues { result_queries })
     }
+
+    async fn find_keys_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<Vec<u8>>, DynamoDbStoreInternalError> {
+        let result_queries = self
+            .get_list_responses(
+                Projection::Keys,
+                &self.partition,
+                KeyCondition::Prefix(key_prefix),
+                order,
+                &AttemptCounter::default(),
+            )
+            .await?;
+        let keys = DynamoDbKeys { result_queries };
+        keys.iterator().map(|key| key.map(<[u8]>::to_vec)).collect()
+    }
+
+    async fn find_key_values_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, DynamoDbStoreInternalError> {
+        let result_queries = self
+            .get_list_responses(
+                Projection::KeysAndValues,
+                &self.partition,
+                KeyCondition::Prefix(key_prefix),
+                order,
+                &AttemptCounter::default(),
+            )
+            .await?;
+        let key_values = DynamoDbKeyValues { result_queries };
+        key_values.into_iterator_owned().collect()
+    }
+
+    #[cfg_attr(
+        with_metrics,
+        instrument(
+            name = "dynamo_db_find_keys_by_prefix_paginated",
+            level = "debug",
+            skip_all,
+            fields(
+                key_prefix_len = key_prefix.len(),
+                limit = limit,
+                retries = tracing::field::Empty,
+                elapsed_ms = tracing::field::Empty
+            )
+        )
+    )]
+    async fn find_keys_by_prefix_paginated(
+        &self,
+        key_prefix: &[u8],
+        limit: usize,
+        continuation: Option<Vec<u8>>,
+    ) -> Result<(Vec<Vec<u8>>, Option<Vec<u8>>), DynamoDbStoreInternalError> {
+        check_key_size(key_prefix)?;
+        if limit == 0 {
+            return Ok((Vec::new(), continuation));
+        }
+        let stats = SpanStats::new();
+        // The continuation does not need to be an existing key: DynamoDB resumes the
+        // query after the position where it would be.
+        let mut start_key = continuation.map(|continuation| {
+            let mut key = key_prefix.to_vec();
+            key.extend(continuation);
+            build_key(&self.partition, key)
+        });
+        let mut keys = Vec::new();
+        loop {
+            let remaining = i32::try_from(limit - keys.len()).unwrap_or(i32::MAX);
+            let response = self
+                .get_query_output(
+                    Projection::Keys,
+                    &self.partition,
+                    KeyCondition::Prefix(key_prefix),
+                    start_key,
+                    Order::Forward,
+                    Some(remaining),
+                    &stats.attempts,
+                )
+                .await?;
+            for item in response.items.iter().flatten() {
+                keys.push(extract_key(key_prefix.len(), item)?.to_vec());
+            }
+            match response.last_evaluated_key {
+                None => return Ok((keys, None)),
+                Some(last_evaluated_key) => {
+                    if keys.len() >= limit {
+                        let continuation = keys.last().cloned();
+                        return Ok((keys, continuation));
+                    }
+                    start_key = Some(last_evaluated_key);
+                }
+            }
+        }
+    }
+
+    #[cfg_attr(
+        with_metrics,
+        instrument(
+            name = "dynamo_db_find_keys_in_range",
+            level = "debug",
+            skip_all,
+            fields(
+                start_len = start.len(),
+                end_len = end.len(),
+                retries = tracing::field::Empty,
+                elapsed_ms = tracing::field::Empty
+            )
+        )
+    )]
+    async fn find_keys_in_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, DynamoDbStoreInternalError> {
+        if start >= end {
+            return Ok(Vec::new());
+        }
+        let stats = SpanStats::new();
+        // `BETWEEN` includes both bounds, so the `end` key is removed afterwards.
+        let result_queries = self
+            .get_list_responses(
+                Projection::Keys,
+                &self.partition,
+                KeyCondition::Between(start, end),
+                Order::Forward,
+                &stats.attempts,
+            )
+            .await?;
+        let keys = DynamoDbKeys { result_queries };
+        let mut result = Vec::new();
+        for key in keys.iterator() {
+            let key = key?;
+            if key != end {
+                result.push(key.to_vec());
+            }
+        }
+        Ok(result)
+    }
+
+    async fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, Vec<u8>, DynamoDbStoreInternalError>, DynamoDbStoreInternalError>
+    {
+        check_key_size(key_prefix)?;
+        let keys = self
+            .get_query_output_stream(Projection::Keys, key_prefix)
+            .map_ok(move |response| {
+                let keys = response
+                    .items
+                    .iter()
+                    .flatten()
+                    .map(|item| extract_key(key_prefix.len(), item).map(<[u8]>::to_vec))
+                    .collect::<Vec<_>>();
+                stream::iter(keys)
+            })
+            .try_flatten();
+        Ok(Box::pin(keys))
+    }
+
+    async fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<
+        ResultStream<'a, (Vec<u8>, Vec<u8>), DynamoDbStoreInternalError>,
+        DynamoDbStoreInternalError,
+    > {
+        check_key_size(key_prefix)?;
+        let key_values = self
+            .get_query_output_stream(Projection::KeysAndValues, key_prefix)
+            .map_ok(move |response| {
+                let key_values = response
+                    .items
+                    .into_iter()
+                    .flatten()
+                    .map(|mut item| extract_key_value_owned(key_prefix.len(), &mut item))
+                    .collect::<Vec<_>>();
+                stream::iter(key_values)
+            })
+            .try_flatten();
+        Ok(Box::pin(key_values))
+    }
+
+    #[cfg_attr(
+        with_metrics,
+        instrument(
+            name = "dynamo_db_count_keys_by_prefix",
+            level = "debug",
+            skip_all,
+            fields(
+                key_prefix_len = key_prefix.len(),
+                retries = tracing::field::Empty,
+                elapsed_ms = tracing::field::Empty
+            )
+        )
+    )]
+    async fn count_keys_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<usize, DynamoDbStoreInternalError> {
+        check_key_size(key_prefix)?;
+        let stats = SpanStats::new();
+        // With `Select::Count`, the items are counted by DynamoDB and none is returned.
+        let mut count = 0;
+        let mut start_key = None;
+        loop {
+            let response = self
+                .get_query_output(
+                    Projection::Count,
+                    &self.partition,
+                    KeyCondition::Prefix(key_prefix),
+                    start_key,
+                    Order::Forward,
+                    None,
+                    &stats.attempts,
+                )
+                .await?;
+            count += usize::try_from(response.count).unwrap_or_default();
+            match response.last_evaluated_key {
+                None => return Ok(count),
+                Some(last_evaluated_key) => start_key = Some(last_evaluated_key),
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -929,25 +2237,49 @@ impl DirectWritableKeyValueStore for DynamoDbStoreInternal {
     // DynamoDB does not support the `DeletePrefix` operation.
     type Batch = SimpleUnorderedBatch;
 
+    fn max_concurrent_batches(&self) -> usize {
+        self.max_concurrent_batches
+    }
+
+    fn max_batch_total_size(&self) -> usize {
+        self.max_batch_total_size
+    }
+
+    #[cfg_attr(
+        with_metrics,
+        instrument(
+            name = "dynamo_db_write_batch",
+            level = "debug",
+            skip_all,
+            fields(
+                num_deletions = batch.deletions.len(),
+                num_insertions = batch.insertions.len(),
+                retries = tracing::field::Empty,
+                elapsed_ms = tracing::field::Empty
+            )
+        )
+    )]
     async fn write_batch(&self, batch: Self::Batch) -> Result<(), DynamoDbStoreInternalError> {
-        let mut builder = TransactionBuilder::new(&self.root_key);
-        for key in batch.deletions {
-            builder.insert_delete_request(key, self)?;
-        }
-        for (key, value) in batch.insertions {
-            builder.insert_put_request(key, value, self)?;
-        }
-        if !builder.transacts.is_empty() {
-            let _guard = self.acquire().await;
-            self.client
-                .transact_write_items()
-                .set_transact_items(Some(builder.transacts))
-                .send()
-                .boxed()
-                .await?;
-        }
+        let stats = SpanStats::new();
+        self.write_batch_general(batch, &stats.attempts).await?;
         Ok(())
     }
+
+    async fn write_batch_with_stats(
+        &self,
+        batch: Self::Batch,
+    ) -> Result<BatchStats, DynamoDbStoreInternalError> {
+        self.write_batch_general(batch, &AttemptCounter::default())
+            .await
+    }
+    async fn compare_and_put(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, ViewError> {
+        Ok(self.conditional_write(key, expected, new).await?)
+    }
 }
 
 /// Error when validating a namespace
@@ -957,12 +2289,12 @@ pub enum InvalidNamespace {
     #[error("Namespace must have at least 3 characters")]
     TooShort,
 
-    /// The namespace should be at most 63 characters.
-    #[error("Namespace must be at most 63 characters")]
+    /// The namespace should be at most 255 characters.
+    #[error("Namespace must be at most 255 characters")]
     TooLong,
 
-    /// allowed characters are lowercase letters, numbers, periods and hyphens
-    #[error("Namespace must only contain lowercase letters, numbers, periods and hyphens")]
+    /// allowed characters are letters, numbers, underscores, periods and hyphens
+    #[error("Namespace must only contain letters, numbers, underscores, periods and hyphens")]
     InvalidCharacter,
 }
 
@@ -985,6 +2317,10 @@ pub enum DynamoDbStoreInternalError {
     #[error(transparent)]
     Query(#[from] Box<SdkError<QueryError>>),
 
+    /// An error occurred while doing a Scan.
+    #[error(transparent)]
+    Scan(#[from] Box<SdkError<ScanError>>),
+
     /// An error occurred while deleting a table
     #[error(transparent)]
     DeleteTable(#[from] Box<SdkError<DeleteTableError>>),
@@ -993,6 +2329,55 @@ pub enum DynamoDbStoreInternalError {
     #[error(transparent)]
     ListTables(#[from] Box<SdkError<ListTablesError>>),
 
+    /// An error occurred while conditionally writing an item
+    #[error(transparent)]
+    PutItem(#[from] Box<SdkError<PutItemError>>),
+
+    /// An error occurred while conditionally deleting an item
+    #[error(transparent)]
+    DeleteItem(#[from] Box<SdkError<DeleteItemError>>),
+
+    /// An error occurred while describing a table
+    #[error(transparent)]
+    DescribeTable(#[from] Box<SdkError<DescribeTableError>>),
+
+    /// An error occurred while updating the continuous backups of a table
+    #[error(transparent)]
+    UpdateContinuousBackups(#[from] Box<SdkError<UpdateContinuousBackupsError>>),
+
+    /// An error occurred while tagging a table
+    #[error(transparent)]
+    TagResource(#[from] Box<SdkError<TagResourceError>>),
+
+    /// The address of LocalStack is not set.
+    #[error("The environment variable {LOCALSTACK_ENDPOINT} is not set")]
+    LocalStackEndpoint,
+
+    /// A request did not complete within the configured timeout.
+    #[error("The DynamoDB {operation} request timed out after {elapsed:?}")]
+    Timeout {
+        /// The name of the DynamoDB operation.
+        operation: &'static str,
+        /// The time elapsed since the request was sent.
+        elapsed: Duration,
+    },
+
+    /// A value could not be compressed or decompressed.
+    #[error("Failed to compress or decompress a value: {0}")]
+    Compression(#[from] std::io::Error),
+
+    /// The table of the namespace does not exist.
+    #[error("The DynamoDB table {0} does not exist")]
+    TableMissing(String),
+
+    /// The table did not reach the expected state in time.
+    #[error("Timed out waiting for the DynamoDB table {0} to be created or deleted")]
+    TableWaitTimeout(String),
+
+    /// The description of the table has no ARN.
+    #[error("The description of the table has no ARN")]
+    MissingTableArn,
+
     /// The transact maximum size is MAX_TRANSACT_WRITE_ITEM_SIZE.
     #[error("The transact must have length at most MAX_TRANSACT_WRITE_ITEM_SIZE")]
     TransactUpperLimitSize,
@@ -1021,9 +2406,17 @@ pub enum DynamoDbStoreInternalError {
     #[error(transparent)]
     JournalConsistencyError(#[from] JournalConsistencyError),
 
-    /// The length of the value should be at most 400KB.
-    #[error("The DynamoDB value should be less than 400KB")]
-    ValueLengthTooLarge,
+    /// The key and the value of an item should have at most 400KB together.
+    #[error(
+        "The DynamoDB item should be less than 400KB, but the key has {key_len} bytes \
+         and the value has {value_len} bytes"
+    )]
+    ValueTooLarge {
+        /// The length of the key.
+        key_len: usize,
+        /// The length of the value.
+        value_len: usize,
+    },
 
     /// The stored key is missing.
     #[error("The stored key attribute is missing")]
@@ -1112,6 +2505,57 @@ impl DynamoDbStoreInternalError {
 
 impl KeyValueStoreError for DynamoDbStoreInternalError {
     const BACKEND: &'static str = "dynamo_db";
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            DynamoDbStoreInternalError::Get(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::BatchWriteItem(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::TransactWriteItem(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::Query(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::Scan(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::DeleteTable(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::ListTables(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::PutItem(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::DeleteItem(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::DescribeTable(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::UpdateContinuousBackups(error) => {
+                is_retryable_sdk_error(error)
+            }
+            DynamoDbStoreInternalError::TagResource(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::CreateTable(error) => is_retryable_sdk_error(error),
+            DynamoDbStoreInternalError::Timeout { .. }
+            | DynamoDbStoreInternalError::TableWaitTimeout(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The error codes of DynamoDB reporting a transient condition.
+const RETRYABLE_ERROR_CODES: &[&str] = &[
+    "ThrottlingException",
+    "ProvisionedThroughputExceededException",
+    "RequestLimitExceeded",
+    "TransactionConflictException",
+    "InternalServerError",
+    "ServiceUnavailable",
+];
+
+/// Whether a service error with the given code and HTTP status is worth retrying: server
+/// errors and throttling are, while validation and schema errors are not.
+fn is_retryable_service_error(code: Option<&str>, status: u16) -> bool {
+    (500..600).contains(&status) || code.is_some_and(|code| RETRYABLE_ERROR_CODES.contains(&code))
+}
+
+/// Whether an error of the SDK is caused by a transient condition.
+fn is_retryable_sdk_error<E: ProvideErrorMetadata>(error: &SdkError<E>) -> bool {
+    match error {
+        SdkError::TimeoutError(_) | SdkError::ResponseError(_) => true,
+        SdkError::DispatchFailure(failure) => failure.is_io() || failure.is_timeout(),
+        SdkError::ServiceError(context) => {
+            is_retryable_service_error(context.err().code(), context.raw().status().as_u16())
+        }
+        _ => false,
+    }
 }
 
 #[cfg(with_testing)]
@@ -1121,11 +2565,24 @@ impl TestKeyValueStore for JournalingKeyValueStore<DynamoDbStoreInternal> {
             max_concurrent_queries: Some(TEST_DYNAMO_DB_MAX_CONCURRENT_QUERIES),
             max_stream_queries: TEST_DYNAMO_DB_MAX_STREAM_QUERIES,
         };
-        let use_localstack = true;
-        let config = get_config_internal(use_localstack).await?;
+        let config = localstack::get_test_config()
+            .await
+            .expect("failed to get the configuration of LocalStack");
         Ok(DynamoDbStoreInternalConfig {
             config,
             common_config,
+            capacity_sink: None,
+            max_concurrent_batches: DEFAULT_MAX_CONCURRENT_BATCHES,
+            max_batch_total_size: MAX_TRANSACT_WRITE_ITEM_TOTAL_SIZE,
+            table_options: TableOptions::default(),
+            table_wait_options: TableWaitOptions {
+                poll_interval: TEST_TABLE_STATUS_POLL_INTERVAL,
+                ..TableWaitOptions::default()
+            },
+            operation_timeout: None,
+            connect_timeout: None,
+            compression_level: None,
+            key_namespace: None,
         })
     }
 }
@@ -1151,11 +2608,134 @@ pub type DynamoDbStoreError = ValueSplittingError<DynamoDbStoreInternalError>;
 /// The config type for DynamoDbStore
 pub type DynamoDbStoreConfig = LruCachingConfig<DynamoDbStoreInternalConfig>;
 
+/// A DynamoDB store whose values are encrypted before being sent to DynamoDB.
+#[cfg(with_encryption)]
+pub type EncryptedDynamoDbStore = EncryptedStore<DynamoDbStore>;
+
+/// The config type for `EncryptedDynamoDbStore`
+#[cfg(with_encryption)]
+pub type EncryptedDynamoDbStoreConfig = EncryptedStoreConfig<DynamoDbStoreConfig>;
+
+/// A DynamoDB store rejecting all writes, e.g. to inspect a live table.
+pub type ReadOnlyDynamoDbStore = ReadOnlyStore<DynamoDbStore>;
+
+/// A context for views that can only be read from DynamoDB.
+pub type ReadOnlyDynamoDbContext<E> = ViewContext<E, ReadOnlyDynamoDbStore>;
+
 /// Getting a configuration for the system
 pub async fn get_config(use_localstack: bool) -> Result<Config, DynamoDbStoreError> {
     Ok(get_config_internal(use_localstack).await?)
 }
 
+/// Gets a configuration using the given credentials provider and region, e.g. to access a
+/// table of another AWS account through [`assume_role_credentials_provider`].
+pub fn get_config_with_credentials(
+    credentials_provider: impl ProvideCredentials + 'static,
+    region: Region,
+    use_localstack: bool,
+) -> Result<Config, DynamoDbStoreError> {
+    Ok(get_config_with_credentials_internal(
+        credentials_provider,
+        region,
+        use_localstack,
+    )?)
+}
+
+/// Gets a configuration from an already loaded AWS configuration, which may for instance
+/// come with its own chain of role assumptions.
+pub fn get_config_from_sdk_config(sdk_config: &SdkConfig) -> Config {
+    sdk_config.into()
+}
+
+/// Builds a credentials provider assuming the IAM role `role_arn` through STS, with the
+/// credentials of the environment.
+pub async fn assume_role_credentials_provider(
+    role_arn: &str,
+    external_id: Option<&str>,
+    session_name: &str,
+) -> AssumeRoleProvider {
+    let mut builder = AssumeRoleProvider::builder(role_arn).session_name(session_name);
+    if let Some(external_id) = external_id {
+        builder = builder.external_id(external_id);
+    }
+    builder.build().await
+}
+
+impl DynamoDbStore {
+    /// Connects to the table of an existing namespace, without ever creating it. See
+    /// [`DynamoDbStoreInternal::connect_existing`].
+    pub async fn connect_existing(
+        config: &DynamoDbStoreConfig,
+        namespace: &str,
+        root_key: &[u8],
+    ) -> Result<Self, DynamoDbStoreError> {
+        DynamoDbStoreInternal::check_table_exists(&config.inner_config, namespace).await?;
+        Self::connect(config, namespace, root_key).await
+    }
+
+    /// Waits until the table of `namespace` is active. See
+    /// [`DynamoDbStoreInternal::wait_until_active`].
+    pub async fn wait_until_active(
+        config: &DynamoDbStoreConfig,
+        namespace: &str,
+    ) -> Result<(), DynamoDbStoreError> {
+        Ok(DynamoDbStoreInternal::wait_until_active(&config.inner_config, namespace).await?)
+    }
+
+    /// Waits until the table of `namespace` no longer exists. See
+    /// [`DynamoDbStoreInternal::wait_for_table_deletion`].
+    pub async fn wait_for_table_deletion(
+        config: &DynamoDbStoreConfig,
+        namespace: &str,
+    ) -> Result<(), DynamoDbStoreError> {
+        Ok(DynamoDbStoreInternal::wait_for_table_deletion(&config.inner_config, namespace).await?)
+    }
+
+    /// Lists the key namespaces having items in the table of `namespace`. See
+    /// [`DynamoDbStoreInternal::list_key_namespaces`].
+    pub async fn list_key_namespaces(
+        config: &DynamoDbStoreConfig,
+        namespace: &str,
+    ) -> Result<Vec<String>, DynamoDbStoreError> {
+        Ok(DynamoDbStoreInternal::list_key_namespaces(&config.inner_config, namespace).await?)
+    }
+
+    /// Deletes all the items of a key namespace in the table of `namespace`. See
+    /// [`DynamoDbStoreInternal::delete_key_namespace`].
+    pub async fn delete_key_namespace(
+        config: &DynamoDbStoreConfig,
+        namespace: &str,
+        key_namespace: &str,
+    ) -> Result<(), DynamoDbStoreError> {
+        Ok(DynamoDbStoreInternal::delete_key_namespace(
+            &config.inner_config,
+            namespace,
+            key_namespace,
+        )
+        .await?)
+    }
+
+    /// Writes a batch that may be too large for one transaction as a succession of
+    /// transactions, instead of going through the journal. The batch is split with
+    /// [`Batch::split_at_size`], then by number of operations, to fit the limits of
+    /// `TransactWriteItems`. Unlike `write_batch`, this is not atomic: if a transaction
+    /// fails, the previous ones remain written. A part whose prefix deletions or large
+    /// values exceed these limits once expanded is still written through the journal.
+    pub async fn write_batch_in_chunks(&self, batch: Batch) -> Result<(), DynamoDbStoreError> {
+        for batch in batch.split_at_size(MAX_TRANSACT_WRITE_ITEM_TOTAL_SIZE) {
+            let mut operations = batch.operations.into_iter().peekable();
+            while operations.peek().is_some() {
+                let operations = operations
+                    .by_ref()
+                    .take(MAX_TRANSACT_WRITE_ITEM_SIZE)
+                    .collect();
+                self.write_batch(Batch { operations }).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl DynamoDbStoreConfig {
     /// Creates a `DynamoDbStoreConfig` from the input.
     pub fn new(
@@ -1165,19 +2745,160 @@ impl DynamoDbStoreConfig {
         let inner_config = DynamoDbStoreInternalConfig {
             config,
             common_config: common_config.reduced(),
+            capacity_sink: None,
+            max_concurrent_batches: DEFAULT_MAX_CONCURRENT_BATCHES,
+            max_batch_total_size: MAX_TRANSACT_WRITE_ITEM_TOTAL_SIZE,
+            table_options: TableOptions::default(),
+            table_wait_options: TableWaitOptions::default(),
+            operation_timeout: None,
+            connect_timeout: None,
+            compression_level: None,
+            key_namespace: None,
         };
         DynamoDbStoreConfig {
             inner_config,
             cache_size: common_config.cache_size,
+            max_cache_bytes: None,
+        }
+    }
+
+    /// Reports the capacity consumed by the DynamoDB operations to the given sink.
+    pub fn with_capacity_sink(mut self, capacity_sink: Arc<dyn ConsumedCapacitySink>) -> Self {
+        self.inner_config.capacity_sink = Some(capacity_sink);
+        self
+    }
+
+    /// Sets the maximal number of journal transactions written concurrently when a batch
+    /// is too large to be written in one transaction.
+    pub fn with_max_concurrent_batches(mut self, max_concurrent_batches: usize) -> Self {
+        self.inner_config.max_concurrent_batches = max_concurrent_batches;
+        self
+    }
+
+    /// Sets the maximal number of bytes written in a single transaction. Larger batches
+    /// go through the journal. The value is capped by the 4MB limit of DynamoDB and
+    /// raised if needed so that any single value still fits.
+    pub fn with_max_batch_total_size(mut self, max_batch_total_size: usize) -> Self {
+        self.inner_config.max_batch_total_size = max_batch_total_size;
+        self
+    }
+
+    /// Sets the timeout of the operations, including their retries. An expired timeout
+    /// results in a [`DynamoDbStoreInternalError::Timeout`] error.
+    pub fn with_operation_timeout(mut self, operation_timeout: Duration) -> Self {
+        self.inner_config.operation_timeout = Some(operation_timeout);
+        self.inner_config.update_timeout_config();
+        self
+    }
+
+    /// Sets the timeout for establishing a connection to DynamoDB.
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.inner_config.connect_timeout = Some(connect_timeout);
+        self.inner_config.update_timeout_config();
+        self
+    }
+
+    /// Compresses the written values with zstd at the given level. Only the values above
+    /// a small size threshold are compressed, and the values written without compression
+    /// can still be read.
+    pub fn with_compression(mut self, level: i32) -> Self {
+        self.inner_config.compression_level = Some(level);
+        self
+    }
+
+    /// Turns the configuration into one of an [`EncryptedDynamoDbStore`], encrypting
+    /// the values with the given key.
+    #[cfg(with_encryption)]
+    pub fn with_encryption(self, encryption_key: EncryptionKey) -> EncryptedDynamoDbStoreConfig {
+        EncryptedStoreConfig {
+            inner_config: self,
+            encryption_key,
         }
     }
+
+    /// Keeps the items of the store within a key namespace, so that several stores can
+    /// share the table of a namespace without seeing each other's keys. The key
+    /// namespace applies to all the root keys of the store, and follows the same naming
+    /// rules as the tables.
+    pub fn with_key_namespace(mut self, key_namespace: &str) -> Self {
+        self.inner_config.key_namespace = Some(key_namespace.to_string());
+        self
+    }
+
+    /// Sets the options applied to the table when it is created or connected to.
+    pub fn with_table_options(mut self, table_options: TableOptions) -> Self {
+        self.inner_config.table_options = table_options;
+        self
+    }
+
+    /// Sets how to wait for the table to be created or deleted.
+    pub fn with_table_wait_options(mut self, table_wait_options: TableWaitOptions) -> Self {
+        self.inner_config.table_wait_options = table_wait_options;
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
+    use aws_sdk_dynamodb::{
+        error::SdkError, operation::query::QueryError, primitives::Blob, types::AttributeValue,
+    };
     use bcs::serialized_size;
+    use rand::{seq::SliceRandom as _, Rng as _};
+    use test_case::test_case;
+
+    use super::{
+        compress_value, decompress_item_value, extract_value_owned, is_compressed_value,
+        is_retryable_sdk_error, is_retryable_service_error, key_namespace_prefix,
+        parse_key_namespace, partition_key, CapacityCounter, ConsumedCapacitySink as _,
+        ConsumedCapacityUnits, DynamoDbOperation, DynamoDbStoreInternal,
+        DynamoDbStoreInternalError, InvalidNamespace, Projection, COMPRESSED_VALUE_TAG,
+        COMPRESSION_THRESHOLD, KEY_ATTRIBUTE, VALUE_ATTRIBUTE, ZSTD_FRAME_MAGIC,
+    };
+    use crate::{
+        common::get_uleb128_size, random::make_deterministic_rng, store::KeyValueStoreError as _,
+        views::ViewError,
+    };
 
-    use crate::common::get_uleb128_size;
+    #[test]
+    fn test_capacity_counter() {
+        let counter = CapacityCounter::default();
+        let read = ConsumedCapacityUnits {
+            read_capacity_units: 0.5,
+            write_capacity_units: 0.0,
+        };
+        let write = ConsumedCapacityUnits {
+            read_capacity_units: 0.0,
+            write_capacity_units: 2.0,
+        };
+        counter.record("table_a", DynamoDbOperation::GetItem, read);
+        counter.record("table_a", DynamoDbOperation::Query, read);
+        counter.record("table_b", DynamoDbOperation::TransactWriteItems, write);
+        assert_eq!(
+            counter.total_consumed(),
+            ConsumedCapacityUnits {
+                read_capacity_units: 1.0,
+                write_capacity_units: 2.0,
+            }
+        );
+        assert_eq!(
+            counter.consumed_by_table("table_a"),
+            ConsumedCapacityUnits {
+                read_capacity_units: 1.0,
+                write_capacity_units: 0.0,
+            }
+        );
+        assert_eq!(
+            counter.consumed_by_operation(DynamoDbOperation::TransactWriteItems),
+            write
+        );
+        assert_eq!(
+            counter.consumed_by_table("table_c"),
+            ConsumedCapacityUnits::default()
+        );
+    }
 
     #[test]
     fn test_serialization_len() {
@@ -1188,4 +2909,160 @@ mod tests {
             assert_eq!(est_size, serial_size);
         }
     }
+
+    #[test_case("abc"; "shortest")]
+    #[test_case(&"a".repeat(255); "longest")]
+    #[test_case("My_Table-1.0"; "all allowed character classes")]
+    #[test_case("..."; "only periods")]
+    fn test_valid_namespace(namespace: &str) {
+        assert!(DynamoDbStoreInternal::check_namespace(namespace).is_ok());
+    }
+
+    #[test_case("", InvalidNamespace::TooShort; "empty")]
+    #[test_case("ab", InvalidNamespace::TooShort; "too short")]
+    #[test_case(&"a".repeat(256), InvalidNamespace::TooLong; "too long")]
+    #[test_case("table name", InvalidNamespace::InvalidCharacter; "space")]
+    #[test_case("table/name", InvalidNamespace::InvalidCharacter; "slash")]
+    #[test_case("tablé", InvalidNamespace::InvalidCharacter; "non ascii")]
+    fn test_invalid_namespace(namespace: &str, expected: InvalidNamespace) {
+        let error = DynamoDbStoreInternal::check_namespace(namespace).unwrap_err();
+        assert_eq!(
+            std::mem::discriminant(&error),
+            std::mem::discriminant(&expected)
+        );
+    }
+
+    /// Checks the validation of random names against the rules of DynamoDB.
+    #[test]
+    fn test_random_namespaces() {
+        let alphabet = b"abcXYZ019_.-/ #";
+        let mut rng = make_deterministic_rng();
+        for _ in 0..1000 {
+            let len = rng.gen_range(0..300);
+            let namespace = (0..len)
+                .map(|_| char::from(*alphabet.choose(&mut rng).unwrap()))
+                .collect::<String>();
+            let expected = (3..=255).contains(&namespace.len())
+                && namespace
+                    .bytes()
+                    .all(|byte| byte.is_ascii_alphanumeric() || b"_.-".contains(&byte));
+            assert_eq!(
+                DynamoDbStoreInternal::check_namespace(&namespace).is_ok(),
+                expected,
+                "{namespace}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_key_namespace_partitions() {
+        assert_eq!(partition_key(None, &[]), vec![0]);
+        assert_eq!(partition_key(None, &[1, 2]), vec![0, 1, 2]);
+        let partition = partition_key(Some("abc"), &[1, 2]);
+        assert_eq!(partition, vec![1, 3, b'a', b'b', b'c', 1, 2]);
+        assert!(partition.starts_with(&key_namespace_prefix("abc")));
+        assert!(!partition.starts_with(&key_namespace_prefix("ab")));
+        assert_eq!(parse_key_namespace(&partition), Some("abc".to_string()));
+        assert_eq!(parse_key_namespace(&partition_key(None, b"abc")), None);
+        assert_eq!(parse_key_namespace(&[1, 4, b'a']), None);
+    }
+
+    #[test]
+    fn test_projection_placeholders() {
+        for (projection, attributes) in [
+            (Projection::Keys, vec![KEY_ATTRIBUTE]),
+            (
+                Projection::KeysAndValues,
+                vec![KEY_ATTRIBUTE, VALUE_ATTRIBUTE],
+            ),
+        ] {
+            let expression = projection.expression().unwrap();
+            let names = projection.attribute_names();
+            // Every element of the expression is a declared placeholder, and conversely.
+            let placeholders = expression.split(", ").collect::<Vec<_>>();
+            assert_eq!(
+                placeholders,
+                names.iter().map(|(name, _)| *name).collect::<Vec<_>>()
+            );
+            assert!(placeholders.iter().all(|name| name.starts_with('#')));
+            assert_eq!(
+                names
+                    .iter()
+                    .map(|(_, attribute)| *attribute)
+                    .collect::<Vec<_>>(),
+                attributes
+            );
+        }
+    }
+
+    #[test]
+    fn test_retryable_service_errors() {
+        for code in [
+            "ThrottlingException",
+            "ProvisionedThroughputExceededException",
+        ] {
+            assert!(is_retryable_service_error(Some(code), 400));
+        }
+        assert!(is_retryable_service_error(None, 500));
+        assert!(is_retryable_service_error(Some("InternalServerError"), 500));
+        assert!(is_retryable_service_error(None, 503));
+        for code in [
+            "ValidationException",
+            "ResourceNotFoundException",
+            "ConditionalCheckFailedException",
+        ] {
+            assert!(!is_retryable_service_error(Some(code), 400));
+        }
+        assert!(!is_retryable_service_error(None, 400));
+    }
+
+    #[test]
+    fn test_retryable_sdk_errors() {
+        let error = SdkError::<QueryError>::timeout_error("the request timed out");
+        assert!(is_retryable_sdk_error(&error));
+        let error = SdkError::<QueryError>::construction_failure("invalid request");
+        assert!(!is_retryable_sdk_error(&error));
+        let error = DynamoDbStoreInternalError::Query(Box::new(SdkError::timeout_error(
+            "the request timed out",
+        )));
+        assert!(error.is_retryable());
+        assert!(ViewError::from(error).is_retryable());
+        assert!(!DynamoDbStoreInternalError::KeyTooLong.is_retryable());
+        assert!(!ViewError::from(DynamoDbStoreInternalError::KeyTooLong).is_retryable());
+    }
+
+    fn decompress_value(value: Vec<u8>) -> Vec<u8> {
+        let mut item = HashMap::from([(
+            VALUE_ATTRIBUTE.to_string(),
+            AttributeValue::B(Blob::new(value)),
+        )]);
+        decompress_item_value(&mut item).unwrap();
+        extract_value_owned(&mut item).unwrap()
+    }
+
+    #[test]
+    fn test_value_compression() {
+        let level = 3;
+        // Small and incompressible values are stored as they are.
+        let small = vec![0; COMPRESSION_THRESHOLD];
+        assert_eq!(compress_value(small.clone(), level).unwrap(), small);
+        let mut rng = make_deterministic_rng();
+        let random = (0..4096).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+        assert_eq!(compress_value(random.clone(), level).unwrap(), random);
+        assert_eq!(decompress_value(random.clone()), random);
+
+        let large = vec![7; 10_000];
+        let compressed = compress_value(large.clone(), level).unwrap();
+        assert!(compressed.len() < large.len());
+        assert!(is_compressed_value(&compressed));
+        assert_eq!(decompress_value(compressed), large);
+
+        // A value starting like a compressed one is compressed, even if it is small.
+        let mut ambiguous = vec![COMPRESSED_VALUE_TAG];
+        ambiguous.extend(ZSTD_FRAME_MAGIC);
+        ambiguous.push(1);
+        let compressed = compress_value(ambiguous.clone(), level).unwrap();
+        assert_ne!(compressed, ambiguous);
+        assert_eq!(decompress_value(compressed), ambiguous);
+    }
 }
@@ -0,0 +1,492 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Adds client-side encryption of the values to a given store.
+//!
+//! The values are encrypted with AES-256-GCM and a random nonce for every write, with
+//! the key of the value as associated data. The keys themselves are stored in the clear,
+//! so that the prefix searches of the inner store keep working.
+
+use std::fmt;
+
+use aes_gcm::{
+    aead::{Aead as _, AeadCore as _, KeyInit as _, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use futures::StreamExt as _;
+use thiserror::Error;
+
+use crate::{
+    batch::{Batch, BatchStats, WriteOperation},
+    store::{
+        AdminKeyValueStore, KeyIterable as _, KeyValueIterable as _, KeyValueStoreError, Order,
+        ReadableKeyValueStore, ResultStream, WithError, WritableKeyValueStore,
+    },
+};
+#[cfg(with_testing)]
+use crate::{memory::MemoryStore, store::TestKeyValueStore};
+
+/// The size of the nonce stored in front of every encrypted value.
+const NONCE_SIZE: usize = 12;
+
+/// The size of the authentication tag stored at the end of every encrypted value.
+const TAG_SIZE: usize = 16;
+
+/// The number of bytes added to a value by its encryption.
+const ENCRYPTION_OVERHEAD: usize = NONCE_SIZE + TAG_SIZE;
+
+/// The key used for tests, which obviously must not be used for anything else.
+#[cfg(with_testing)]
+const TEST_ENCRYPTION_KEY: [u8; 32] = [42; 32];
+
+/// The composed error type built from the inner error type.
+#[derive(Error, Debug)]
+pub enum EncryptedStoreError<E> {
+    /// inner store error
+    #[error(transparent)]
+    InnerStoreError(#[from] E),
+
+    /// The stored value is too short to be an encrypted value.
+    #[error("the stored value is too short to be an encrypted value")]
+    TooShortValue,
+
+    /// The stored value failed authentication: it was either not encrypted with this key,
+    /// tampered with, or moved to another key.
+    #[error("the stored value could not be authenticated with the encryption key")]
+    AuthenticationFailed,
+
+    /// The value could not be encrypted.
+    #[error("the value could not be encrypted")]
+    EncryptionFailed,
+}
+
+impl<E: KeyValueStoreError> From<bcs::Error> for EncryptedStoreError<E> {
+    fn from(error: bcs::Error) -> Self {
+        let error = E::from(error);
+        EncryptedStoreError::InnerStoreError(error)
+    }
+}
+
+impl<E: KeyValueStoreError + 'static> KeyValueStoreError for EncryptedStoreError<E> {
+    const BACKEND: &'static str = "encrypted";
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            EncryptedStoreError::InnerStoreError(error) => error.is_retryable(),
+            _ => false,
+        }
+    }
+}
+
+/// A 256-bit key for the encryption of the values.
+#[derive(Clone)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    /// Creates an encryption key from its bytes.
+    pub fn new(bytes: [u8; 32]) -> Self {
+        EncryptionKey(bytes)
+    }
+
+    /// Generates a random encryption key.
+    pub fn generate() -> Self {
+        EncryptionKey(Aes256Gcm::generate_key(&mut OsRng).into())
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// A key-value store encrypting the values of an inner store.
+///
+/// Values read back are authenticated, and a value that was not written by a store with
+/// the same encryption key results in [`EncryptedStoreError::AuthenticationFailed`].
+/// Since the encryption is randomized, `compare_and_put` is not supported.
+#[derive(Clone)]
+pub struct EncryptedStore<K> {
+    /// The underlying store of the transformed store.
+    store: K,
+    /// The cipher of the encryption key.
+    cipher: Aes256Gcm,
+}
+
+impl<K> WithError for EncryptedStore<K>
+where
+    K: WithError,
+    K::Error: 'static,
+{
+    type Error = EncryptedStoreError<K::Error>;
+}
+
+impl<K> ReadableKeyValueStore for EncryptedStore<K>
+where
+    K: ReadableKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    const MAX_KEY_SIZE: usize = K::MAX_KEY_SIZE;
+    type Keys = Vec<Vec<u8>>;
+    type KeyValues = Vec<(Vec<u8>, Vec<u8>)>;
+
+    fn max_stream_queries(&self) -> usize {
+        self.store.max_stream_queries()
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        let value = self.store.read_value_bytes(key).await?;
+        value.map(|value| self.decrypt(key, &value)).transpose()
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        Ok(self.store.contains_key(key).await?)
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, Self::Error> {
+        Ok(self.store.contains_keys(keys).await?)
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let values = self.store.read_multi_values_bytes(keys.clone()).await?;
+        keys.iter()
+            .zip(values)
+            .map(|(key, value)| value.map(|value| self.decrypt(key, &value)).transpose())
+            .collect()
+    }
+
+    async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Self::Keys, Self::Error> {
+        let keys = self.store.find_keys_by_prefix(key_prefix).await?;
+        let mut result = Vec::new();
+        for key in keys.iterator() {
+            result.push(key?.to_vec());
+        }
+        Ok(result)
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Self::KeyValues, Self::Error> {
+        let key_values = self.store.find_key_values_by_prefix(key_prefix).await?;
+        let mut result = Vec::new();
+        for key_value in key_values.into_iterator_owned() {
+            let (key, value) = key_value?;
+            let value = self.decrypt_suffix(key_prefix, &key, &value)?;
+            result.push((key, value));
+        }
+        Ok(result)
+    }
+
+    async fn find_keys_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        Ok(self
+            .store
+            .find_keys_by_prefix_with_options(key_prefix, order)
+            .await?)
+    }
+
+    async fn find_key_values_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        let key_values = self
+            .store
+            .find_key_values_by_prefix_with_options(key_prefix, order)
+            .await?;
+        key_values
+            .into_iter()
+            .map(|(key, value)| -> Result<_, Self::Error> {
+                let value = self.decrypt_suffix(key_prefix, &key, &value)?;
+                Ok((key, value))
+            })
+            .collect()
+    }
+
+    async fn find_keys_by_prefix_paginated(
+        &self,
+        key_prefix: &[u8],
+        limit: usize,
+        continuation: Option<Vec<u8>>,
+    ) -> Result<(Vec<Vec<u8>>, Option<Vec<u8>>), Self::Error> {
+        Ok(self
+            .store
+            .find_keys_by_prefix_paginated(key_prefix, limit, continuation)
+            .await?)
+    }
+
+    async fn find_keys_in_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        Ok(self.store.find_keys_in_range(start, end).await?)
+    }
+
+    async fn count_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<usize, Self::Error> {
+        Ok(self.store.count_keys_by_prefix(key_prefix).await?)
+    }
+
+    async fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, Vec<u8>, Self::Error>, Self::Error> {
+        let keys = self.store.find_keys_by_prefix_stream(key_prefix).await?;
+        Ok(Box::pin(keys.map(|key| {
+            key.map_err(EncryptedStoreError::InnerStoreError)
+        })))
+    }
+
+    async fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error>, Self::Error> {
+        let key_values = self
+            .store
+            .find_key_values_by_prefix_stream(key_prefix)
+            .await?;
+        let key_values = key_values.map(move |key_value| -> Result<_, Self::Error> {
+            let (key, value) = key_value?;
+            let value = self.decrypt_suffix(key_prefix, &key, &value)?;
+            Ok((key, value))
+        });
+        Ok(Box::pin(key_values))
+    }
+}
+
+impl<K> WritableKeyValueStore for EncryptedStore<K>
+where
+    K: WritableKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    const MAX_VALUE_SIZE: usize = K::MAX_VALUE_SIZE.saturating_sub(ENCRYPTION_OVERHEAD);
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
+        let batch = self.encrypt_batch(batch)?;
+        Ok(self.store.write_batch(batch).await?)
+    }
+
+    async fn write_batch_with_stats(&self, batch: Batch) -> Result<BatchStats, Self::Error> {
+        let batch = self.encrypt_batch(batch)?;
+        Ok(self.store.write_batch_with_stats(batch).await?)
+    }
+
+    async fn clear_journal(&self) -> Result<(), Self::Error> {
+        Ok(self.store.clear_journal().await?)
+    }
+}
+
+/// The configuration type for the `EncryptedStore`.
+#[derive(Debug)]
+pub struct EncryptedStoreConfig<C> {
+    /// The inner configuration of the `EncryptedStore`.
+    pub inner_config: C,
+    /// The key encrypting the values.
+    pub encryption_key: EncryptionKey,
+}
+
+impl<K> AdminKeyValueStore for EncryptedStore<K>
+where
+    K: AdminKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    type Config = EncryptedStoreConfig<K::Config>;
+
+    fn get_name() -> String {
+        format!("encrypted {}", K::get_name())
+    }
+
+    async fn connect(
+        config: &Self::Config,
+        namespace: &str,
+        root_key: &[u8],
+    ) -> Result<Self, Self::Error> {
+        let store = K::connect(&config.inner_config, namespace, root_key).await?;
+        Ok(Self::new(store, &config.encryption_key))
+    }
+
+    fn clone_with_root_key(&self, root_key: &[u8]) -> Result<Self, Self::Error> {
+        let store = self.store.clone_with_root_key(root_key)?;
+        let cipher = self.cipher.clone();
+        Ok(Self { store, cipher })
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, Self::Error> {
+        Ok(K::list_all(&config.inner_config).await?)
+    }
+
+    async fn delete_all(config: &Self::Config) -> Result<(), Self::Error> {
+        Ok(K::delete_all(&config.inner_config).await?)
+    }
+
+    async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, Self::Error> {
+        Ok(K::exists(&config.inner_config, namespace).await?)
+    }
+
+    async fn create(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        Ok(K::create(&config.inner_config, namespace).await?)
+    }
+
+    async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        Ok(K::delete(&config.inner_config, namespace).await?)
+    }
+}
+
+#[cfg(with_testing)]
+impl<K> TestKeyValueStore for EncryptedStore<K>
+where
+    K: TestKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    async fn new_test_config() -> Result<EncryptedStoreConfig<K::Config>, Self::Error> {
+        let inner_config = K::new_test_config().await?;
+        let encryption_key = EncryptionKey::new(TEST_ENCRYPTION_KEY);
+        Ok(EncryptedStoreConfig {
+            inner_config,
+            encryption_key,
+        })
+    }
+}
+
+impl<K> EncryptedStore<K>
+where
+    K: WithError,
+{
+    /// Creates a new store encrypting the values of the given one.
+    pub fn new(store: K, encryption_key: &EncryptionKey) -> Self {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&encryption_key.0));
+        EncryptedStore { store, cipher }
+    }
+
+    /// Encrypts the values of the batch.
+    fn encrypt_batch(&self, batch: Batch) -> Result<Batch, EncryptedStoreError<K::Error>> {
+        let mut batch_new = Batch::new();
+        for operation in batch.operations {
+            match operation {
+                WriteOperation::Delete { key } => batch_new.delete_key(key),
+                WriteOperation::Put { key, value } => {
+                    let value = self.encrypt(&key, &value)?;
+                    batch_new.put_key_value_bytes(key, value);
+                }
+                WriteOperation::DeletePrefix { key_prefix } => {
+                    batch_new.delete_key_prefix(key_prefix)
+                }
+                WriteOperation::DeleteRange { start, end } => {
+                    batch_new.delete_key_range(start, end)
+                }
+            }
+        }
+        Ok(batch_new)
+    }
+
+    /// Encrypts a value, binding it to its key. The nonce is stored in front of the
+    /// ciphertext.
+    fn encrypt(&self, key: &[u8], value: &[u8]) -> Result<Vec<u8>, EncryptedStoreError<K::Error>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: value,
+            aad: key,
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| EncryptedStoreError::EncryptionFailed)?;
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend(ciphertext);
+        Ok(encrypted)
+    }
+
+    /// Decrypts and authenticates the value of the given key.
+    fn decrypt(&self, key: &[u8], value: &[u8]) -> Result<Vec<u8>, EncryptedStoreError<K::Error>> {
+        if value.len() < ENCRYPTION_OVERHEAD {
+            return Err(EncryptedStoreError::TooShortValue);
+        }
+        let (nonce, ciphertext) = value.split_at(NONCE_SIZE);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: key,
+        };
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| EncryptedStoreError::AuthenticationFailed)
+    }
+
+    /// Decrypts the value of a key returned by a prefix search.
+    fn decrypt_suffix(
+        &self,
+        key_prefix: &[u8],
+        key_suffix: &[u8],
+        value: &[u8],
+    ) -> Result<Vec<u8>, EncryptedStoreError<K::Error>> {
+        let key = [key_prefix, key_suffix].concat();
+        self.decrypt(&key, value)
+    }
+}
+
+/// A memory store with encryption.
+#[cfg(with_testing)]
+pub type EncryptedMemoryStore = EncryptedStore<MemoryStore>;
+
+#[cfg(test)]
+mod tests {
+    use super::{EncryptedStore, EncryptedStoreError, EncryptionKey, ENCRYPTION_OVERHEAD};
+    use crate::{
+        batch::Batch,
+        memory::MemoryStore,
+        store::{ReadableKeyValueStore as _, TestKeyValueStore as _, WritableKeyValueStore as _},
+    };
+
+    #[tokio::test]
+    async fn test_encrypted_values_at_rest() {
+        let inner_store = MemoryStore::new_test_store().await.unwrap();
+        let key = EncryptionKey::generate();
+        let store = EncryptedStore::new(inner_store.clone(), &key);
+        let value = b"a value that must not be stored in the clear".to_vec();
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![1, 2], value.clone());
+        batch.put_key_value_bytes(vec![1, 3], value.clone());
+        store.write_batch(batch).await.unwrap();
+
+        let stored = inner_store
+            .read_value_bytes(&[1, 2])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.len(), value.len() + ENCRYPTION_OVERHEAD);
+        assert!(!stored.windows(value.len()).any(|window| window == value));
+        // Every write uses its own nonce.
+        let other = inner_store
+            .read_value_bytes(&[1, 3])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(stored, other);
+        assert_eq!(
+            store.read_value_bytes(&[1, 2]).await.unwrap(),
+            Some(value.clone())
+        );
+
+        // A value moved to another key does not authenticate.
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![1, 3], stored);
+        inner_store.write_batch(batch).await.unwrap();
+        assert!(matches!(
+            store.read_value_bytes(&[1, 3]).await,
+            Err(EncryptedStoreError::AuthenticationFailed)
+        ));
+
+        // Nor does a value read with another encryption key.
+        let other_store = EncryptedStore::new(inner_store, &EncryptionKey::generate());
+        assert!(matches!(
+            other_store.read_value_bytes(&[1, 2]).await,
+            Err(EncryptedStoreError::AuthenticationFailed)
+        ));
+    }
+}
@@ -0,0 +1,461 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Injects faults in the operations of a given store, in order to test how the upper
+//! layers behave when the storage fails.
+//!
+//! The faults follow a [`FaultSchedule`] that can be changed at any time, e.g. to let a
+//! retried operation succeed.
+
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use futures::StreamExt as _;
+use linera_base::time::{timer, Duration};
+use rand::Rng as _;
+use thiserror::Error;
+
+use crate::{
+    batch::{Batch, BatchStats, SimpleUnorderedBatch},
+    journaling::{DirectWritableKeyValueStore, JournalConsistencyError},
+    memory::MemoryStore,
+    random::{make_deterministic_rng, DeterministicRng},
+    store::{
+        AdminKeyValueStore, KeyIterable as _, KeyValueIterable as _, KeyValueStoreError, Order,
+        ReadableKeyValueStore, ResultStream, TestKeyValueStore, WithError, WritableKeyValueStore,
+    },
+};
+
+/// The maximal number of operations of a batch written by a [`crate::journaling`] store
+/// on top of a [`FaultyStore`]. It is small, so that the journal is used even by small
+/// tests.
+const FAULTY_MAX_BATCH_SIZE: usize = 8;
+
+/// The maximal number of bytes of a batch written by a [`crate::journaling`] store on top
+/// of a [`FaultyStore`].
+const FAULTY_MAX_BATCH_TOTAL_SIZE: usize = 1_000_000;
+
+/// The faults to inject in the operations of a [`FaultyStore`].
+#[derive(Clone, Debug, Default)]
+pub struct FaultSchedule {
+    /// The positions, starting from 1, of the batch writes failing with a retryable
+    /// error. They are counted from the moment the schedule is set.
+    pub failing_write_batches: BTreeSet<usize>,
+    /// The delay added to every read.
+    pub read_delay: Option<Duration>,
+    /// The probability of every operation to fail with a retryable error.
+    pub error_probability: f64,
+    /// The number of operations after which the connection is lost, and every operation
+    /// fails with a non-retryable error.
+    pub disconnect_after: Option<usize>,
+}
+
+impl FaultSchedule {
+    /// Makes the `n`-th batch write fail, starting from 1.
+    pub fn with_failing_write_batch(mut self, n: usize) -> Self {
+        self.failing_write_batches.insert(n);
+        self
+    }
+
+    /// Delays every read by `delay`.
+    pub fn with_read_delay(mut self, delay: Duration) -> Self {
+        self.read_delay = Some(delay);
+        self
+    }
+
+    /// Makes every operation fail with a retryable error with the given probability.
+    pub fn with_error_probability(mut self, probability: f64) -> Self {
+        self.error_probability = probability;
+        self
+    }
+
+    /// Makes every operation fail after the first `count` ones.
+    pub fn with_disconnection_after(mut self, count: usize) -> Self {
+        self.disconnect_after = Some(count);
+        self
+    }
+}
+
+/// The state of the faults, shared by the stores obtained with `clone_with_root_key`.
+struct FaultState {
+    /// The faults to inject.
+    schedule: FaultSchedule,
+    /// The number of operations since the schedule was set.
+    operations: usize,
+    /// The number of batch writes since the schedule was set.
+    write_batches: usize,
+    /// The source of the random errors.
+    rng: DeterministicRng,
+}
+
+impl FaultState {
+    fn new(schedule: FaultSchedule) -> Self {
+        FaultState {
+            schedule,
+            operations: 0,
+            write_batches: 0,
+            rng: make_deterministic_rng(),
+        }
+    }
+
+    /// Counts an operation and returns the error it should fail with, if any.
+    fn check_operation<E>(&mut self) -> Result<(), FaultyStoreError<E>> {
+        self.operations += 1;
+        if let Some(count) = self.schedule.disconnect_after {
+            if self.operations > count {
+                return Err(FaultyStoreError::Disconnected);
+            }
+        }
+        let probability = self.schedule.error_probability;
+        if probability > 0.0 && self.rng.gen_bool(probability.min(1.0)) {
+            return Err(FaultyStoreError::InjectedFault);
+        }
+        Ok(())
+    }
+}
+
+/// The configuration of a [`FaultyStore`].
+#[derive(Clone, Debug)]
+pub struct FaultyStoreConfig<C> {
+    /// The configuration of the inner store.
+    pub inner_config: C,
+    /// The faults to inject in the connected stores.
+    pub schedule: FaultSchedule,
+}
+
+/// A store failing some of the operations of an inner store, as described by a
+/// [`FaultSchedule`].
+///
+/// It also implements [`DirectWritableKeyValueStore`] with small batches, so that it can
+/// be used under a [`crate::journaling::JournalingKeyValueStore`].
+#[derive(Clone)]
+pub struct FaultyStore<K> {
+    /// The underlying store.
+    store: K,
+    /// The schedule and the counters of the faults.
+    state: Arc<Mutex<FaultState>>,
+}
+
+/// A memory store with injected faults.
+pub type FaultyMemoryStore = FaultyStore<MemoryStore>;
+
+impl<K> FaultyStore<K> {
+    /// Creates a store injecting the faults of `schedule` in the operations of `store`.
+    pub fn new(store: K, schedule: FaultSchedule) -> Self {
+        let state = Arc::new(Mutex::new(FaultState::new(schedule)));
+        Self { store, state }
+    }
+
+    /// Replaces the faults to inject, and resets the counters of the operations.
+    pub fn set_schedule(&self, schedule: FaultSchedule) {
+        *self.state.lock().unwrap() = FaultState::new(schedule);
+    }
+
+    /// Returns the number of operations since the schedule was set.
+    pub fn operation_count(&self) -> usize {
+        self.state.lock().unwrap().operations
+    }
+
+    /// Counts a read, returning its error or the delay to apply.
+    fn check_read<E>(&self) -> Result<Option<Duration>, FaultyStoreError<E>> {
+        let mut state = self.state.lock().unwrap();
+        state.check_operation()?;
+        Ok(state.schedule.read_delay)
+    }
+
+    /// Counts a read and waits for its delay, if any.
+    async fn before_read<E>(&self) -> Result<(), FaultyStoreError<E>> {
+        if let Some(delay) = self.check_read()? {
+            timer::sleep(delay).await;
+        }
+        Ok(())
+    }
+
+    /// Counts a batch write, returning its error if any.
+    fn before_write<E>(&self) -> Result<(), FaultyStoreError<E>> {
+        let mut state = self.state.lock().unwrap();
+        state.check_operation()?;
+        state.write_batches += 1;
+        if state
+            .schedule
+            .failing_write_batches
+            .contains(&state.write_batches)
+        {
+            return Err(FaultyStoreError::InjectedFault);
+        }
+        Ok(())
+    }
+}
+
+/// The error type of a [`FaultyStore`].
+#[derive(Error, Debug)]
+pub enum FaultyStoreError<E> {
+    /// inner store error
+    #[error(transparent)]
+    InnerStoreError(#[from] E),
+
+    /// A transient fault was injected.
+    #[error("injected transient fault")]
+    InjectedFault,
+
+    /// The connection was lost.
+    #[error("the connection to the store was lost")]
+    Disconnected,
+
+    /// The journal is not consistent.
+    #[error(transparent)]
+    JournalConsistencyError(JournalConsistencyError),
+}
+
+impl<E: KeyValueStoreError> From<bcs::Error> for FaultyStoreError<E> {
+    fn from(error: bcs::Error) -> Self {
+        let error = E::from(error);
+        FaultyStoreError::InnerStoreError(error)
+    }
+}
+
+impl<E: KeyValueStoreError> From<JournalConsistencyError> for FaultyStoreError<E> {
+    fn from(error: JournalConsistencyError) -> Self {
+        FaultyStoreError::JournalConsistencyError(error)
+    }
+}
+
+impl<E: KeyValueStoreError + 'static> KeyValueStoreError for FaultyStoreError<E> {
+    const BACKEND: &'static str = "faulty";
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            FaultyStoreError::InnerStoreError(error) => error.is_retryable(),
+            FaultyStoreError::InjectedFault => true,
+            FaultyStoreError::Disconnected | FaultyStoreError::JournalConsistencyError(_) => false,
+        }
+    }
+}
+
+impl<K> WithError for FaultyStore<K>
+where
+    K: WithError,
+    K::Error: 'static,
+{
+    type Error = FaultyStoreError<K::Error>;
+}
+
+impl<K> ReadableKeyValueStore for FaultyStore<K>
+where
+    K: ReadableKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    const MAX_KEY_SIZE: usize = K::MAX_KEY_SIZE;
+    type Keys = Vec<Vec<u8>>;
+    type KeyValues = Vec<(Vec<u8>, Vec<u8>)>;
+
+    fn max_stream_queries(&self) -> usize {
+        self.store.max_stream_queries()
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.before_read().await?;
+        Ok(self.store.read_value_bytes(key).await?)
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        self.before_read().await?;
+        Ok(self.store.contains_key(key).await?)
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, Self::Error> {
+        self.before_read().await?;
+        Ok(self.store.contains_keys(keys).await?)
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        self.before_read().await?;
+        Ok(self.store.read_multi_values_bytes(keys).await?)
+    }
+
+    async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Self::Keys, Self::Error> {
+        self.before_read().await?;
+        let keys = self.store.find_keys_by_prefix(key_prefix).await?;
+        let mut result = Vec::new();
+        for key in keys.iterator() {
+            result.push(key?.to_vec());
+        }
+        Ok(result)
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Self::KeyValues, Self::Error> {
+        self.before_read().await?;
+        let key_values = self.store.find_key_values_by_prefix(key_prefix).await?;
+        let mut result = Vec::new();
+        for key_value in key_values.into_iterator_owned() {
+            result.push(key_value?);
+        }
+        Ok(result)
+    }
+
+    async fn find_keys_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.before_read().await?;
+        Ok(self
+            .store
+            .find_keys_by_prefix_with_options(key_prefix, order)
+            .await?)
+    }
+
+    async fn find_key_values_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        self.before_read().await?;
+        Ok(self
+            .store
+            .find_key_values_by_prefix_with_options(key_prefix, order)
+            .await?)
+    }
+
+    async fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, Vec<u8>, Self::Error>, Self::Error> {
+        self.before_read().await?;
+        let keys = self.store.find_keys_by_prefix_stream(key_prefix).await?;
+        Ok(Box::pin(
+            keys.map(|key| key.map_err(FaultyStoreError::InnerStoreError)),
+        ))
+    }
+
+    async fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error>, Self::Error> {
+        self.before_read().await?;
+        let key_values = self
+            .store
+            .find_key_values_by_prefix_stream(key_prefix)
+            .await?;
+        Ok(Box::pin(key_values.map(|key_value| {
+            key_value.map_err(FaultyStoreError::InnerStoreError)
+        })))
+    }
+}
+
+impl<K> WritableKeyValueStore for FaultyStore<K>
+where
+    K: WritableKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    const MAX_VALUE_SIZE: usize = <K as WritableKeyValueStore>::MAX_VALUE_SIZE;
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
+        self.before_write()?;
+        Ok(self.store.write_batch(batch).await?)
+    }
+
+    async fn write_batch_with_stats(&self, batch: Batch) -> Result<BatchStats, Self::Error> {
+        self.before_write()?;
+        Ok(self.store.write_batch_with_stats(batch).await?)
+    }
+
+    async fn clear_journal(&self) -> Result<(), Self::Error> {
+        Ok(self.store.clear_journal().await?)
+    }
+}
+
+#[async_trait]
+impl<K> DirectWritableKeyValueStore for FaultyStore<K>
+where
+    K: WritableKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    const MAX_BATCH_SIZE: usize = FAULTY_MAX_BATCH_SIZE;
+    const MAX_BATCH_TOTAL_SIZE: usize = FAULTY_MAX_BATCH_TOTAL_SIZE;
+    const MAX_VALUE_SIZE: usize = <K as WritableKeyValueStore>::MAX_VALUE_SIZE;
+
+    type Batch = SimpleUnorderedBatch;
+
+    async fn write_batch(&self, batch: Self::Batch) -> Result<(), Self::Error> {
+        self.before_write()?;
+        let mut inner_batch = Batch::new();
+        for key in batch.deletions {
+            inner_batch.delete_key(key);
+        }
+        for (key, value) in batch.insertions {
+            inner_batch.put_key_value_bytes(key, value);
+        }
+        Ok(self.store.write_batch(inner_batch).await?)
+    }
+}
+
+impl<K> AdminKeyValueStore for FaultyStore<K>
+where
+    K: AdminKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    type Config = FaultyStoreConfig<K::Config>;
+
+    fn get_name() -> String {
+        format!("faulty {}", K::get_name())
+    }
+
+    async fn connect(
+        config: &Self::Config,
+        namespace: &str,
+        root_key: &[u8],
+    ) -> Result<Self, Self::Error> {
+        let store = K::connect(&config.inner_config, namespace, root_key).await?;
+        Ok(Self::new(store, config.schedule.clone()))
+    }
+
+    fn clone_with_root_key(&self, root_key: &[u8]) -> Result<Self, Self::Error> {
+        let store = self.store.clone_with_root_key(root_key)?;
+        let state = self.state.clone();
+        Ok(Self { store, state })
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, Self::Error> {
+        Ok(K::list_all(&config.inner_config).await?)
+    }
+
+    async fn delete_all(config: &Self::Config) -> Result<(), Self::Error> {
+        Ok(K::delete_all(&config.inner_config).await?)
+    }
+
+    async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, Self::Error> {
+        Ok(K::exists(&config.inner_config, namespace).await?)
+    }
+
+    async fn create(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        Ok(K::create(&config.inner_config, namespace).await?)
+    }
+
+    async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        Ok(K::delete(&config.inner_config, namespace).await?)
+    }
+}
+
+impl<K> TestKeyValueStore for FaultyStore<K>
+where
+    K: TestKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    async fn new_test_config() -> Result<FaultyStoreConfig<K::Config>, Self::Error> {
+        let inner_config = K::new_test_config().await?;
+        Ok(FaultyStoreConfig {
+            inner_config,
+            schedule: FaultSchedule::default(),
+        })
+    }
+}
@@ -0,0 +1,569 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements [`crate::store::KeyValueStore`] on a directory of the file system, e.g. for
+//! small test networks and for debugging, since the data can be inspected with `ls` and
+//! `xxd`.
+//!
+//! Each namespace is a directory, containing a directory `{root_key}.root` for each root
+//! key. In there, a key is stored as a file named after the hexadecimal encoding of the
+//! key. The first byte of the key and then each chunk of 64 bytes are subdirectories, so
+//! that the directories and the names of the files remain short, e.g. the key `0a0b0c` of
+//! the root key `01` is stored in `namespace/01.root/0a/0b0c.value`.
+//!
+//! A value is written to a temporary file which is then renamed, so that a value is never
+//! partially written. However, the operations of a batch are applied one after the
+//! other: unlike the other backends, a crash can leave a batch partially written.
+
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rand::{rngs::OsRng, Rng as _};
+use tempfile::TempDir;
+use thiserror::Error;
+
+#[cfg(with_testing)]
+use crate::store::TestKeyValueStore;
+use crate::{
+    batch::{Batch, WriteOperation},
+    context::ViewContext,
+    store::{
+        AdminKeyValueStore, CommonStoreConfig, CommonStoreInternalConfig, KeyValueStoreError,
+        ReadableKeyValueStore, WithError, WritableKeyValueStore,
+    },
+};
+
+/// The maximal size of the keys, so that the paths of the files remain well below the
+/// limits of the file systems.
+const MAX_KEY_SIZE: usize = 1024;
+
+/// The size of the chunks of the keys after the first byte.
+const CHUNK_SIZE: usize = 64;
+
+/// The extension of the files containing the values.
+const VALUE_EXTENSION: &str = ".value";
+
+/// The extension of the directories of the root keys.
+const ROOT_KEY_EXTENSION: &str = ".root";
+
+/// The extension of the temporary files, which are ignored by the reads.
+const TEMPORARY_EXTENSION: &str = ".tmp";
+
+/// The number of streams for the test
+#[cfg(with_testing)]
+const TEST_FS_STORE_MAX_STREAM_QUERIES: usize = 10;
+
+/// The configuration of a [`FileSystemStore`].
+#[derive(Clone, Debug)]
+pub struct FileSystemStoreConfig {
+    /// The directory containing the namespaces.
+    pub path_buf: PathBuf,
+    /// The common configuration of the key value store
+    pub common_config: CommonStoreInternalConfig,
+    /// The guard of the temporary directory of the tests.
+    _dir: Option<Arc<TempDir>>,
+}
+
+impl FileSystemStoreConfig {
+    /// Creates a `FileSystemStoreConfig` for the namespaces in the given directory.
+    pub fn new(path_buf: PathBuf, common_config: CommonStoreConfig) -> Self {
+        Self {
+            path_buf,
+            common_config: common_config.reduced(),
+            _dir: None,
+        }
+    }
+}
+
+/// A key-value store keeping each key in a file.
+#[derive(Clone)]
+pub struct FileSystemStore {
+    /// The directory of the namespace.
+    namespace_path: PathBuf,
+    /// The directory of the root key.
+    directory: Arc<PathBuf>,
+    /// The maximum number of queries used for the stream.
+    max_stream_queries: usize,
+    /// The guard of the temporary directory of the tests.
+    _dir: Option<Arc<TempDir>>,
+}
+
+/// An entry of a directory of a [`FileSystemStore`].
+enum Entry {
+    /// A file containing the value of the key ending with the chunk.
+    Value(Vec<u8>),
+    /// A directory containing the keys continuing with the chunk.
+    Directory(Vec<u8>),
+}
+
+/// Returns the size of the chunk of the keys at the given depth of the directories.
+fn chunk_size(depth: usize) -> usize {
+    if depth == 0 {
+        1
+    } else {
+        CHUNK_SIZE
+    }
+}
+
+/// Returns the directory containing the keys starting with `key_prefix`, together with
+/// the part of the prefix that the entries of the directory have to start with.
+fn descend<'a>(directory: &Path, key_prefix: &'a [u8]) -> (PathBuf, Vec<u8>, &'a [u8]) {
+    let mut path = directory.to_path_buf();
+    let mut parent_key = Vec::new();
+    let mut rest = key_prefix;
+    let mut depth = 0;
+    while rest.len() > chunk_size(depth) {
+        let (chunk, tail) = rest.split_at(chunk_size(depth));
+        path.push(hex::encode(chunk));
+        parent_key.extend_from_slice(chunk);
+        rest = tail;
+        depth += 1;
+    }
+    (path, parent_key, rest)
+}
+
+/// Returns the path of the file containing the value of `key`.
+fn key_path(directory: &Path, key: &[u8]) -> PathBuf {
+    let (mut path, _, rest) = descend(directory, key);
+    path.push(format!("{}{VALUE_EXTENSION}", hex::encode(rest)));
+    path
+}
+
+/// Reads the entries of a directory, ignoring the temporary files. A missing directory
+/// has no entries.
+fn read_entries(path: &Path) -> Result<Vec<Entry>, FileSystemStoreError> {
+    let read_dir = match fs::read_dir(path) {
+        Ok(read_dir) => read_dir,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+    let mut entries = Vec::new();
+    for entry in read_dir {
+        let entry = entry?;
+        let name = entry
+            .file_name()
+            .into_string()
+            .map_err(FileSystemStoreError::IntoStringError)?;
+        if entry.file_type()?.is_dir() {
+            let chunk = hex::decode(&name).map_err(|_| FileSystemStoreError::InvalidEntry(name))?;
+            entries.push(Entry::Directory(chunk));
+        } else if let Some(stem) = name.strip_suffix(VALUE_EXTENSION) {
+            let chunk = hex::decode(stem).map_err(|_| FileSystemStoreError::InvalidEntry(name))?;
+            entries.push(Entry::Value(chunk));
+        } else if !name.ends_with(TEMPORARY_EXTENSION) {
+            return Err(FileSystemStoreError::InvalidEntry(name));
+        }
+    }
+    Ok(entries)
+}
+
+/// Adds the keys of the files in the directory and its subdirectories.
+fn collect_keys(
+    path: &Path,
+    parent_key: &[u8],
+    keys: &mut Vec<Vec<u8>>,
+) -> Result<(), FileSystemStoreError> {
+    for entry in read_entries(path)? {
+        match entry {
+            Entry::Value(chunk) => keys.push([parent_key, chunk.as_slice()].concat()),
+            Entry::Directory(chunk) => {
+                let key = [parent_key, chunk.as_slice()].concat();
+                collect_keys(&path.join(hex::encode(&chunk)), &key, keys)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the sorted keys starting with `key_prefix`, including the prefix.
+fn find_keys(directory: &Path, key_prefix: &[u8]) -> Result<Vec<Vec<u8>>, FileSystemStoreError> {
+    let (path, parent_key, rest) = descend(directory, key_prefix);
+    let mut keys = Vec::new();
+    for entry in read_entries(&path)? {
+        match entry {
+            Entry::Value(chunk) if chunk.starts_with(rest) => {
+                keys.push([parent_key.as_slice(), chunk.as_slice()].concat());
+            }
+            Entry::Directory(chunk) if chunk.starts_with(rest) => {
+                let key = [parent_key.as_slice(), chunk.as_slice()].concat();
+                collect_keys(&path.join(hex::encode(&chunk)), &key, &mut keys)?;
+            }
+            _ => {}
+        }
+    }
+    keys.sort();
+    Ok(keys)
+}
+
+/// Reads the value of a file, if it exists.
+fn read_value(path: &Path) -> Result<Option<Vec<u8>>, FileSystemStoreError> {
+    match fs::read(path) {
+        Ok(value) => Ok(Some(value)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Removes a file or a directory, if it exists.
+fn remove(path: &Path, is_directory: bool) -> Result<(), FileSystemStoreError> {
+    let result = if is_directory {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    match result {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Writes a value to a temporary file, which is then renamed.
+fn put_value(directory: &Path, key: &[u8], value: &[u8]) -> Result<(), FileSystemStoreError> {
+    let path = key_path(directory, key);
+    let parent = path.parent().expect("the path of a key has a parent");
+    fs::create_dir_all(parent)?;
+    let suffix = OsRng.gen::<u64>();
+    let mut temporary_name = path.file_name().expect("a file name").to_os_string();
+    temporary_name.push(format!(".{suffix:016x}{TEMPORARY_EXTENSION}"));
+    let temporary_path = parent.join(temporary_name);
+    let mut file = fs::File::create(&temporary_path)?;
+    file.write_all(value)?;
+    file.sync_all()?;
+    fs::rename(&temporary_path, &path)?;
+    Ok(())
+}
+
+/// Removes the keys starting with `key_prefix`.
+fn delete_prefix(directory: &Path, key_prefix: &[u8]) -> Result<(), FileSystemStoreError> {
+    let (path, _, rest) = descend(directory, key_prefix);
+    for entry in read_entries(&path)? {
+        match entry {
+            Entry::Value(chunk) if chunk.starts_with(rest) => {
+                remove(
+                    &path.join(format!("{}{VALUE_EXTENSION}", hex::encode(chunk))),
+                    false,
+                )?;
+            }
+            Entry::Directory(chunk) if chunk.starts_with(rest) => {
+                remove(&path.join(hex::encode(chunk)), true)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Applies the operations of a batch, one after the other.
+fn apply_batch(directory: &Path, batch: Batch) -> Result<(), FileSystemStoreError> {
+    for operation in batch.operations {
+        match operation {
+            WriteOperation::Put { key, value } => put_value(directory, &key, &value)?,
+            WriteOperation::Delete { key } => remove(&key_path(directory, &key), false)?,
+            WriteOperation::DeletePrefix { key_prefix } => delete_prefix(directory, &key_prefix)?,
+            WriteOperation::DeleteRange { start, end } => {
+                let common_length = start
+                    .iter()
+                    .zip(&end)
+                    .take_while(|(start, end)| start == end)
+                    .count();
+                for key in find_keys(directory, &start[..common_length])? {
+                    if start <= key && key < end {
+                        remove(&key_path(directory, &key), false)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl FileSystemStore {
+    fn check_namespace(namespace: &str) -> Result<(), FileSystemStoreError> {
+        if namespace.is_empty()
+            || !namespace
+                .chars()
+                .all(|character| character.is_ascii_alphanumeric() || character == '_')
+        {
+            return Err(FileSystemStoreError::InvalidNamespace);
+        }
+        Ok(())
+    }
+
+    fn check_key_size(key: &[u8]) -> Result<(), FileSystemStoreError> {
+        if key.len() > MAX_KEY_SIZE {
+            return Err(FileSystemStoreError::KeyTooLong);
+        }
+        Ok(())
+    }
+
+    fn root_key_directory(namespace_path: &Path, root_key: &[u8]) -> PathBuf {
+        namespace_path.join(format!("{}{ROOT_KEY_EXTENSION}", hex::encode(root_key)))
+    }
+
+    /// Runs a blocking operation on the directory of the root key.
+    async fn run_blocking<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Path) -> Result<R, FileSystemStoreError> + Send + 'static,
+    ) -> Result<R, FileSystemStoreError> {
+        let directory = self.directory.clone();
+        tokio::task::spawn_blocking(move || f(&directory)).await?
+    }
+}
+
+impl WithError for FileSystemStore {
+    type Error = FileSystemStoreError;
+}
+
+impl ReadableKeyValueStore for FileSystemStore {
+    const MAX_KEY_SIZE: usize = MAX_KEY_SIZE;
+    type Keys = Vec<Vec<u8>>;
+    type KeyValues = Vec<(Vec<u8>, Vec<u8>)>;
+
+    fn max_stream_queries(&self) -> usize {
+        self.max_stream_queries
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, FileSystemStoreError> {
+        Self::check_key_size(key)?;
+        let key = key.to_vec();
+        self.run_blocking(move |directory| read_value(&key_path(directory, &key)))
+            .await
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, FileSystemStoreError> {
+        Self::check_key_size(key)?;
+        let key = key.to_vec();
+        self.run_blocking(move |directory| Ok(key_path(directory, &key).is_file()))
+            .await
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, FileSystemStoreError> {
+        for key in &keys {
+            Self::check_key_size(key)?;
+        }
+        self.run_blocking(move |directory| {
+            Ok(keys
+                .iter()
+                .map(|key| key_path(directory, key).is_file())
+                .collect())
+        })
+        .await
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, FileSystemStoreError> {
+        for key in &keys {
+            Self::check_key_size(key)?;
+        }
+        self.run_blocking(move |directory| {
+            keys.iter()
+                .map(|key| read_value(&key_path(directory, key)))
+                .collect()
+        })
+        .await
+    }
+
+    async fn find_keys_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<Vec<u8>>, FileSystemStoreError> {
+        Self::check_key_size(key_prefix)?;
+        let key_prefix = key_prefix.to_vec();
+        self.run_blocking(move |directory| {
+            let len = key_prefix.len();
+            Ok(find_keys(directory, &key_prefix)?
+                .into_iter()
+                .map(|key| key[len..].to_vec())
+                .collect())
+        })
+        .await
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, FileSystemStoreError> {
+        Self::check_key_size(key_prefix)?;
+        let key_prefix = key_prefix.to_vec();
+        self.run_blocking(move |directory| {
+            let len = key_prefix.len();
+            let mut key_values = Vec::new();
+            for key in find_keys(directory, &key_prefix)? {
+                // The key may have been deleted since the directory was read.
+                if let Some(value) = read_value(&key_path(directory, &key))? {
+                    key_values.push((key[len..].to_vec(), value));
+                }
+            }
+            Ok(key_values)
+        })
+        .await
+    }
+}
+
+impl WritableKeyValueStore for FileSystemStore {
+    const MAX_VALUE_SIZE: usize = usize::MAX;
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), FileSystemStoreError> {
+        for operation in &batch.operations {
+            match operation {
+                WriteOperation::Put { key, .. } | WriteOperation::Delete { key } => {
+                    Self::check_key_size(key)?;
+                }
+                WriteOperation::DeletePrefix { key_prefix } => {
+                    Self::check_key_size(key_prefix)?;
+                }
+                WriteOperation::DeleteRange { start, end } => {
+                    Self::check_key_size(start)?;
+                    Self::check_key_size(end)?;
+                }
+            }
+        }
+        self.run_blocking(move |directory| apply_batch(directory, batch))
+            .await
+    }
+
+    async fn clear_journal(&self) -> Result<(), FileSystemStoreError> {
+        Ok(())
+    }
+}
+
+impl AdminKeyValueStore for FileSystemStore {
+    type Config = FileSystemStoreConfig;
+
+    fn get_name() -> String {
+        "file system".to_string()
+    }
+
+    async fn connect(
+        config: &Self::Config,
+        namespace: &str,
+        root_key: &[u8],
+    ) -> Result<Self, FileSystemStoreError> {
+        Self::check_namespace(namespace)?;
+        let namespace_path = config.path_buf.join(namespace);
+        if !namespace_path.is_dir() {
+            return Err(FileSystemStoreError::NamespaceNotFound);
+        }
+        let directory = Arc::new(Self::root_key_directory(&namespace_path, root_key));
+        Ok(FileSystemStore {
+            namespace_path,
+            directory,
+            max_stream_queries: config.common_config.max_stream_queries,
+            _dir: config._dir.clone(),
+        })
+    }
+
+    fn clone_with_root_key(&self, root_key: &[u8]) -> Result<Self, FileSystemStoreError> {
+        let mut store = self.clone();
+        store.directory = Arc::new(Self::root_key_directory(&self.namespace_path, root_key));
+        Ok(store)
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, FileSystemStoreError> {
+        let read_dir = match fs::read_dir(&config.path_buf) {
+            Ok(read_dir) => read_dir,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        let mut namespaces = Vec::new();
+        for entry in read_dir {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                return Err(FileSystemStoreError::NonDirectoryNamespace);
+            }
+            let namespace = entry
+                .file_name()
+                .into_string()
+                .map_err(FileSystemStoreError::IntoStringError)?;
+            namespaces.push(namespace);
+        }
+        Ok(namespaces)
+    }
+
+    async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, FileSystemStoreError> {
+        Self::check_namespace(namespace)?;
+        Ok(config.path_buf.join(namespace).is_dir())
+    }
+
+    async fn create(config: &Self::Config, namespace: &str) -> Result<(), FileSystemStoreError> {
+        Self::check_namespace(namespace)?;
+        fs::create_dir_all(config.path_buf.join(namespace))?;
+        Ok(())
+    }
+
+    async fn delete(config: &Self::Config, namespace: &str) -> Result<(), FileSystemStoreError> {
+        Self::check_namespace(namespace)?;
+        fs::remove_dir_all(config.path_buf.join(namespace))?;
+        Ok(())
+    }
+}
+
+#[cfg(with_testing)]
+impl TestKeyValueStore for FileSystemStore {
+    async fn new_test_config() -> Result<FileSystemStoreConfig, FileSystemStoreError> {
+        let dir = TempDir::new()?;
+        let common_config = CommonStoreInternalConfig {
+            max_concurrent_queries: None,
+            max_stream_queries: TEST_FS_STORE_MAX_STREAM_QUERIES,
+        };
+        Ok(FileSystemStoreConfig {
+            path_buf: dir.path().to_path_buf(),
+            common_config,
+            _dir: Some(Arc::new(dir)),
+        })
+    }
+}
+
+/// A context for views stored in a directory of the file system.
+pub type FileSystemContext<E> = ViewContext<E, FileSystemStore>;
+
+/// The error type for [`FileSystemStore`].
+#[derive(Error, Debug)]
+pub enum FileSystemStoreError {
+    /// Tokio join error in the file system store.
+    #[error("tokio join error: {0}")]
+    TokioJoinError(#[from] tokio::task::JoinError),
+
+    /// Filesystem error
+    #[error("Filesystem error: {0}")]
+    FsError(#[from] io::Error),
+
+    /// The key must have at most 1024 bytes
+    #[error("The key must have at most 1024 bytes")]
+    KeyTooLong,
+
+    /// Namespace contains forbidden characters
+    #[error("Namespace contains forbidden characters")]
+    InvalidNamespace,
+
+    /// The namespace does not exist
+    #[error("The namespace does not exist")]
+    NamespaceNotFound,
+
+    /// The directory of the namespaces contains a file which is not a directory
+    #[error("Namespaces should be directories")]
+    NonDirectoryNamespace,
+
+    /// A directory of the store contains a file which was not written by the store.
+    #[error("Unexpected file in the store: {0}")]
+    InvalidEntry(String),
+
+    /// Error converting `OsString` to `String`
+    #[error("error in the conversion from OsString: {0:?}")]
+    IntoStringError(OsString),
+
+    /// BCS serialization error.
+    #[error(transparent)]
+    BcsError(#[from] bcs::Error),
+}
+
+impl KeyValueStoreError for FileSystemStoreError {
+    const BACKEND: &'static str = "fs_store";
+}
@@ -2,6 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! Implements [`crate::store::KeyValueStore`] for the IndexedDB Web database.
+//!
+//! The keys are stored as `Uint8Array`s, which IndexedDB compares lexicographically, so
+//! that the prefix searches are ranges of `IDBKeyRange.bound`. A batch is written in a
+//! single read-write transaction, which is committed or aborted as a whole.
 
 use std::rc::Rc;
 
@@ -214,9 +218,24 @@ impl LocalWritableKeyValueStore for IndexedDbStore {
                         .delete_owned(prefix_to_range(&key_prefix[..])?)?
                         .await?;
                 }
+                WriteOperation::DeleteRange { start, end } => {
+                    if start < end {
+                        let lower = js_sys::Uint8Array::from(&self.full_key(&start)[..]);
+                        let upper = js_sys::Uint8Array::from(&self.full_key(&end)[..]);
+                        let range = web_sys::IdbKeyRange::bound_with_lower_open_and_upper_open(
+                            &lower.into(),
+                            &upper.into(),
+                            false,
+                            true,
+                        )?;
+                        object_store.delete_owned(range)?.await?;
+                    }
+                }
             }
         }
-
+        drop(object_store);
+        // Waiting for the completion reports the errors that abort the transaction.
+        transaction.await.into_result()?;
         Ok(())
     }
 
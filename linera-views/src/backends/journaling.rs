@@ -19,21 +19,30 @@
 //! time the data in a block are written, the journal header is updated in the same
 //! transaction to mark the block as processed.
 
+use std::sync::Mutex;
+
 use async_trait::async_trait;
+use futures::{stream, StreamExt as _, TryStreamExt as _};
+use linera_base::ensure;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use static_assertions as sa;
 use thiserror::Error;
 
 use crate::{
-    batch::{Batch, BatchValueWriter, DeletePrefixExpander, SimplifiedBatch},
+    batch::{Batch, BatchStats, BatchValueWriter, DeletePrefixExpander, SimplifiedBatch},
     store::{
-        AdminKeyValueStore, KeyIterable, ReadableKeyValueStore, WithError, WritableKeyValueStore,
+        AdminKeyValueStore, KeyIterable, Order, ReadableKeyValueStore, ResultStream, WithError,
+        WritableKeyValueStore,
     },
-    views::MIN_VIEW_TAG,
+    views::{ViewError, MIN_VIEW_TAG},
 };
 
 /// The tag used for the journal stuff.
 const JOURNAL_TAG: u8 = 0;
+/// The version of the format of the journal header and blocks.
+const JOURNAL_VERSION: u8 = 1;
+/// The size of the headers written before the version was added, with only the block count.
+const LEGACY_JOURNAL_HEADER_LEN: usize = 4;
 // To prevent collisions, the tag value 0 is reserved for journals.
 // The tags used by views must be greater or equal than `MIN_VIEW_TAG`.
 sa::const_assert!(JOURNAL_TAG < MIN_VIEW_TAG);
@@ -44,6 +53,10 @@ pub enum JournalConsistencyError {
     /// The journal block could not be retrieved, it could be missing or corrupted
     #[error("the journal block could not be retrieved, it could be missing or corrupted")]
     FailureToRetrieveJournalBlock,
+
+    /// The journal was written with an unsupported version of the format
+    #[error("the journal has the unsupported version {0}")]
+    UnsupportedJournalVersion(u8),
 }
 
 #[repr(u8)]
@@ -76,8 +89,43 @@ pub trait DirectWritableKeyValueStore: WithError {
     /// The batch type.
     type Batch: SimplifiedBatch + Serialize + DeserializeOwned + Default;
 
+    /// The maximal number of bytes of a batch for this instance of the store. It may be
+    /// lower than `MAX_BATCH_TOTAL_SIZE`.
+    fn max_batch_total_size(&self) -> usize {
+        Self::MAX_BATCH_TOTAL_SIZE
+    }
+
+    /// The maximal number of journal transactions that can be written concurrently.
+    fn max_concurrent_batches(&self) -> usize {
+        1
+    }
+
     /// Writes the batch to the database.
     async fn write_batch(&self, batch: Self::Batch) -> Result<(), Self::Error>;
+
+    /// Writes the batch to the database, and returns the statistics of the writing. Only
+    /// `bytes_written`, `round_trips` and `retries` are filled.
+    async fn write_batch_with_stats(&self, batch: Self::Batch) -> Result<BatchStats, Self::Error> {
+        let bytes_written = batch.num_bytes();
+        self.write_batch(batch).await?;
+        Ok(BatchStats {
+            bytes_written,
+            round_trips: 1,
+            ..BatchStats::default()
+        })
+    }
+
+    /// Conditionally writes a single value, as described by
+    /// [`WritableKeyValueStore::compare_and_put`]. Unsupported by default.
+    async fn compare_and_put(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, ViewError> {
+        let _ = (key, expected, new);
+        Err(ViewError::CompareAndPutUnsupported)
+    }
 }
 
 /// Low-level, asynchronous direct read/write key-value operations with simplified batch
@@ -92,11 +140,30 @@ impl<T> DirectKeyValueStore for T where
 }
 
 /// The header that contains the current state of the journal.
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug)]
 struct JournalHeader {
+    version: u8,
     block_count: u32,
 }
 
+impl JournalHeader {
+    fn new(block_count: u32) -> Self {
+        Self {
+            version: JOURNAL_VERSION,
+            block_count,
+        }
+    }
+
+    /// Decodes a stored header. The legacy headers only contain the block count, and their
+    /// blocks have the format of the first version.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, bcs::Error> {
+        if bytes.len() == LEGACY_JOURNAL_HEADER_LEN {
+            return Ok(Self::new(bcs::from_bytes(bytes)?));
+        }
+        bcs::from_bytes(bytes)
+    }
+}
+
 /// A journaling Key Value Store built from an inner [`DirectKeyValueStore`].
 #[derive(Clone)]
 pub struct JournalingKeyValueStore<K> {
@@ -116,6 +183,44 @@ where
         }
         Ok(vector_list)
     }
+
+    async fn expand_delete_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        // For DynamoDB, this is a single query bounded by the range.
+        self.store.find_keys_in_range(start, end).await
+    }
+}
+
+/// Expands the deletions of prefixes and ranges like a [`JournalingKeyValueStore`], while
+/// recording the number of keys of each prefix or range.
+struct CountingDeletePrefixExpander<'a, K> {
+    store: &'a JournalingKeyValueStore<K>,
+    counts: Mutex<Vec<usize>>,
+}
+
+impl<K> DeletePrefixExpander for &CountingDeletePrefixExpander<'_, K>
+where
+    K: DirectKeyValueStore + Send + Sync,
+{
+    type Error = K::Error;
+    async fn expand_delete_prefix(&self, key_prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error> {
+        let keys = self.store.expand_delete_prefix(key_prefix).await?;
+        self.counts.lock().unwrap().push(keys.len());
+        Ok(keys)
+    }
+
+    async fn expand_delete_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        let keys = self.store.expand_delete_range(start, end).await?;
+        self.counts.lock().unwrap().push(keys.len());
+        Ok(keys)
+    }
 }
 
 impl<K> WithError for JournalingKeyValueStore<K>
@@ -170,6 +275,65 @@ where
     ) -> Result<Self::KeyValues, Self::Error> {
         self.store.find_key_values_by_prefix(key_prefix).await
     }
+
+    async fn find_keys_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.store
+            .find_keys_by_prefix_with_options(key_prefix, order)
+            .await
+    }
+
+    async fn find_key_values_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        self.store
+            .find_key_values_by_prefix_with_options(key_prefix, order)
+            .await
+    }
+
+    async fn find_keys_by_prefix_paginated(
+        &self,
+        key_prefix: &[u8],
+        limit: usize,
+        continuation: Option<Vec<u8>>,
+    ) -> Result<(Vec<Vec<u8>>, Option<Vec<u8>>), Self::Error> {
+        self.store
+            .find_keys_by_prefix_paginated(key_prefix, limit, continuation)
+            .await
+    }
+
+    async fn find_keys_in_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.store.find_keys_in_range(start, end).await
+    }
+
+    async fn count_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<usize, Self::Error> {
+        self.store.count_keys_by_prefix(key_prefix).await
+    }
+
+    async fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, Vec<u8>, Self::Error>, Self::Error> {
+        self.store.find_keys_by_prefix_stream(key_prefix).await
+    }
+
+    async fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error>, Self::Error> {
+        self.store
+            .find_key_values_by_prefix_stream(key_prefix)
+            .await
+    }
 }
 
 impl<K> AdminKeyValueStore for JournalingKeyValueStore<K>
@@ -226,20 +390,47 @@ where
     const MAX_VALUE_SIZE: usize = K::MAX_VALUE_SIZE;
 
     async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
-        let batch = K::Batch::from_batch(self, batch).await?;
-        if Self::is_fastpath_feasible(&batch) {
-            self.store.write_batch(batch).await
+        self.write_batch_with_stats(batch).await?;
+        Ok(())
+    }
+
+    async fn write_batch_with_stats(&self, batch: Batch) -> Result<BatchStats, Self::Error> {
+        let mut stats = BatchStats::count_operations(&batch);
+        let expander = CountingDeletePrefixExpander {
+            store: self,
+            counts: Mutex::new(Vec::new()),
+        };
+        let batch = K::Batch::from_batch(&expander, batch).await?;
+        stats.delete_prefixes_expanded = expander.counts.into_inner().unwrap();
+        if self.is_fastpath_feasible(&batch) {
+            stats += self.store.write_batch_with_stats(batch).await?;
         } else {
-            let header = self.write_journal(batch).await?;
-            self.coherently_resolve_journal(header).await
+            let header = self.write_journal(batch, &mut stats).await?;
+            self.coherently_resolve_journal(header, &mut stats).await?;
         }
+        Ok(stats)
+    }
+
+    async fn compare_and_put(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, ViewError> {
+        self.store.compare_and_put(key, expected, new).await
     }
 
     async fn clear_journal(&self) -> Result<(), Self::Error> {
         let key = get_journaling_key(KeyTag::Journal as u8, 0)?;
-        let value = self.read_value::<JournalHeader>(&key).await?;
-        if let Some(header) = value {
-            self.coherently_resolve_journal(header).await?;
+        let value = self.read_value_bytes(&key).await?;
+        if let Some(bytes) = value {
+            let header = JournalHeader::from_bytes(&bytes)?;
+            ensure!(
+                header.version == JOURNAL_VERSION,
+                JournalConsistencyError::UnsupportedJournalVersion(header.version)
+            );
+            self.coherently_resolve_journal(header, &mut BatchStats::default())
+                .await?;
         }
         Ok(())
     }
@@ -263,14 +454,20 @@ where
     /// (1) each block contains at most `K::MAX_BATCH_SIZE - 2` operations;
     ///
     /// (2) the total size of the all operations in a block doesn't exceed:
-    /// `K::MAX_BATCH_TOTAL_SIZE - sizeof(block_key) - sizeof(header_key) - sizeof(bcs_header)`
+    /// `max_batch_total_size() - sizeof(block_key) - sizeof(header_key) - sizeof(bcs_header)`
     ///
     /// (3) every operation in a block satisfies the contraints on individual database
     /// operations represented by `K::MAX_KEY_SIZE` and `K::MAX_VALUE_SIZE`.
     ///
     /// (4) `block_key` and `header_key` don't exceed `K::MAX_KEY_SIZE` and `bcs_header`
     /// doesn't exceed `K::MAX_VALUE_SIZE`.
-    async fn coherently_resolve_journal(&self, mut header: JournalHeader) -> Result<(), K::Error> {
+    ///
+    /// The writes to the database are added to `stats`.
+    async fn coherently_resolve_journal(
+        &self,
+        mut header: JournalHeader,
+        stats: &mut BatchStats,
+    ) -> Result<(), K::Error> {
         let header_key = get_journaling_key(KeyTag::Journal as u8, 0)?;
         while header.block_count > 0 {
             let block_key = get_journaling_key(KeyTag::Entry as u8, header.block_count - 1)?;
@@ -289,7 +486,7 @@ where
             } else {
                 batch.add_delete(header_key.clone());
             }
-            self.store.write_batch(batch).await?;
+            *stats += self.store.write_batch_with_stats(batch).await?;
         }
         Ok(())
     }
@@ -303,8 +500,10 @@ where
     /// constraints of the underlying key-value store (see analysis above).
     ///
     /// For efficiency reasons, we write as many blocks as possible in each "transaction"
-    /// batch, using one write-operation per block. Then we also update the journal header
-    /// with the final number of blocks.
+    /// batch, using one write-operation per block. The transactions are independent, so up
+    /// to `K::max_concurrent_batches()` of them are written concurrently. Then we also
+    /// update the journal header with the final number of blocks, once all the blocks have
+    /// been written.
     ///
     /// As a result, the constraints of the underlying database are respected if the
     /// following conditions are met while a "transaction" batch is being built:
@@ -313,7 +512,7 @@ where
     /// But it is perfectly possible to have K::MAX_BATCH_SIZE = usize::MAX.
     ///
     /// (2) The total size of BCS-serialized blocks together with their corresponding keys
-    /// does not exceed `K::MAX_BATCH_TOTAL_SIZE`.
+    /// does not exceed `max_batch_total_size()`.
     ///
     /// (3) The size of each BCS-serialized block doesn't exceed `K::MAX_VALUE_SIZE`.
     ///
@@ -323,7 +522,7 @@ where
     ///       or `key_len`. An upper bound is thus
     ///       `journal_len_upper_bound = key_len + header_value_len`.
     ///   Thus the following has to be taken as upper bound on block_size:
-    ///   `K::MAX_BATCH_TOTAL_SIZE - key_len - journal_len_upper_bound`.
+    ///   `max_batch_total_size() - key_len - journal_len_upper_bound`.
     ///
     /// NOTE:
     /// * Since a block must contain at least one operation and M bytes of the
@@ -334,16 +533,22 @@ where
     /// * Similarly, a transaction must contain at least one block so it is desirable that
     ///   the maximum size of a block insertion `1 + sizeof(block_key) + K::MAX_VALUE_SIZE`
     ///   plus M bytes of overhead doesn't exceed the threshold of condition (2).
-    async fn write_journal(&self, batch: K::Batch) -> Result<JournalHeader, K::Error> {
+    ///
+    /// The writes to the database are added to `stats`.
+    async fn write_journal(
+        &self,
+        batch: K::Batch,
+        stats: &mut BatchStats,
+    ) -> Result<JournalHeader, K::Error> {
         let header_key = get_journaling_key(KeyTag::Journal as u8, 0)?;
         let key_len = header_key.len();
-        let header_value_len = bcs::serialized_size(&JournalHeader::default())?;
+        let header_value_len = bcs::serialized_size(&JournalHeader::new(0))?;
         let journal_len_upper_bound = key_len + header_value_len;
         // Each block in a transaction comes with a key.
-        let max_transaction_size = K::MAX_BATCH_TOTAL_SIZE;
+        let max_transaction_size = self.store.max_batch_total_size();
         let max_block_size = std::cmp::min(
             K::MAX_VALUE_SIZE,
-            K::MAX_BATCH_TOTAL_SIZE - key_len - journal_len_upper_bound,
+            max_transaction_size - key_len - journal_len_upper_bound,
         );
 
         let mut iter = batch.into_iter();
@@ -352,6 +557,7 @@ where
         let mut block_count = 0;
         let mut transaction_batch = K::Batch::default();
         let mut transaction_size = 0;
+        let mut transaction_batches = Vec::new();
         while iter.write_next_value(&mut block_batch, &mut block_size)? {
             let (block_flush, transaction_flush) = {
                 if iter.is_empty() || transaction_batch.len() == K::MAX_BATCH_SIZE - 1 {
@@ -381,22 +587,30 @@ where
             }
             if transaction_flush {
                 let batch = std::mem::take(&mut transaction_batch);
-                self.store.write_batch(batch).await?;
+                transaction_batches.push(batch);
                 transaction_size = 0;
             }
         }
-        let header = JournalHeader { block_count };
+        let transaction_stats = stream::iter(transaction_batches)
+            .map(|batch| self.store.write_batch_with_stats(batch))
+            .buffer_unordered(self.store.max_concurrent_batches().max(1))
+            .try_collect::<Vec<_>>()
+            .await?;
+        for transaction_stats in transaction_stats {
+            *stats += transaction_stats;
+        }
+        let header = JournalHeader::new(block_count);
         if block_count > 0 {
             let value = bcs::to_bytes(&header)?;
             let mut batch = K::Batch::default();
             batch.add_insert(header_key, value);
-            self.store.write_batch(batch).await?;
+            *stats += self.store.write_batch_with_stats(batch).await?;
         }
         Ok(header)
     }
 
-    fn is_fastpath_feasible(batch: &K::Batch) -> bool {
-        batch.len() <= K::MAX_BATCH_SIZE && batch.num_bytes() <= K::MAX_BATCH_TOTAL_SIZE
+    fn is_fastpath_feasible(&self, batch: &K::Batch) -> bool {
+        batch.len() <= K::MAX_BATCH_SIZE && batch.num_bytes() <= self.store.max_batch_total_size()
     }
 }
 
@@ -406,3 +620,155 @@ impl<K> JournalingKeyValueStore<K> {
         Self { store }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DirectWritableKeyValueStore, JournalConsistencyError, JournalingKeyValueStore};
+    use crate::{
+        batch::{Batch, SimpleUnorderedBatch},
+        faulty::{FaultSchedule, FaultyMemoryStore, FaultyStore, FaultyStoreError},
+        memory::MemoryStore,
+        store::{
+            KeyIterable as _, ReadableKeyValueStore, TestKeyValueStore as _,
+            WritableKeyValueStore as _,
+        },
+    };
+
+    async fn new_faulty_store() -> (
+        JournalingKeyValueStore<FaultyMemoryStore>,
+        FaultyMemoryStore,
+    ) {
+        let store = MemoryStore::new_test_store().await.unwrap();
+        let faulty_store = FaultyStore::new(store, FaultSchedule::default());
+        (
+            JournalingKeyValueStore::new(faulty_store.clone()),
+            faulty_store,
+        )
+    }
+
+    /// A batch that needs several blocks of the journal. They are written in a single
+    /// transaction, followed by the journal header.
+    fn large_batch() -> Batch {
+        let mut batch = Batch::new();
+        for i in 0..20u8 {
+            batch.put_key_value_bytes(vec![1, i], vec![i; 10]);
+        }
+        batch
+    }
+
+    async fn read_keys(store: &JournalingKeyValueStore<FaultyMemoryStore>) -> Vec<Vec<u8>> {
+        let keys = store.find_keys_by_prefix(&[1]).await.unwrap();
+        keys.iterator()
+            .map(|key| key.unwrap().to_vec())
+            .collect::<Vec<_>>()
+    }
+
+    #[tokio::test]
+    async fn test_crash_while_writing_journal() {
+        let (store, faulty_store) = new_faulty_store().await;
+        // The write of the journal header fails, so the blocks are never applied.
+        faulty_store.set_schedule(FaultSchedule::default().with_failing_write_batch(2));
+        assert!(store.write_batch(large_batch()).await.is_err());
+        faulty_store.set_schedule(FaultSchedule::default());
+        store.clear_journal().await.unwrap();
+        assert!(read_keys(&store).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_crash_while_resolving_journal() {
+        let (store, faulty_store) = new_faulty_store().await;
+        // The journal is complete, but only one of its blocks gets applied.
+        faulty_store.set_schedule(FaultSchedule::default().with_failing_write_batch(4));
+        assert!(store.write_batch(large_batch()).await.is_err());
+        let partial_keys = read_keys(&store).await;
+        assert!(!partial_keys.is_empty() && partial_keys.len() < 20);
+        faulty_store.set_schedule(FaultSchedule::default());
+        store.clear_journal().await.unwrap();
+        let keys = read_keys(&store).await;
+        assert_eq!(keys, (0..20u8).map(|i| vec![i]).collect::<Vec<_>>());
+        for i in 0..20u8 {
+            let value = store.read_value_bytes(&[1, i]).await.unwrap();
+            assert_eq!(value, Some(vec![i; 10]));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_batch_stats() {
+        let (store, _faulty_store) = new_faulty_store().await;
+        let mut batch = Batch::new();
+        for i in 0..3u8 {
+            batch.put_key_value_bytes(vec![2, i], vec![i]);
+        }
+        store.write_batch(batch).await.unwrap();
+
+        // The fast path writes a single transaction.
+        let mut batch = Batch::new();
+        batch.delete_key_prefix(vec![2]);
+        batch.delete_key(vec![3]);
+        batch.put_key_value_bytes(vec![1, 0], vec![0; 10]);
+        let stats = store.write_batch_with_stats(batch).await.unwrap();
+        assert_eq!(stats.puts, 1);
+        assert_eq!(stats.deletes, 1);
+        assert_eq!(stats.delete_prefixes_expanded, vec![3]);
+        assert_eq!(stats.bytes_written, 3 * 2 + 1 + 2 + 10);
+        assert_eq!(stats.round_trips, 1);
+        assert_eq!(stats.retries, 0);
+
+        // The slow path also counts the writes of the journal: the transaction of the
+        // four blocks and the header, then one write per block.
+        let stats = store.write_batch_with_stats(large_batch()).await.unwrap();
+        assert_eq!(stats.puts, 20);
+        assert_eq!(stats.round_trips, 2 + 4);
+        assert!(stats.bytes_written > 20 * (2 + 10));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_journal_version() {
+        let (store, faulty_store) = new_faulty_store().await;
+        let key = super::get_journaling_key(super::KeyTag::Journal as u8, 0).unwrap();
+        let header = super::JournalHeader {
+            version: super::JOURNAL_VERSION + 1,
+            block_count: 1,
+        };
+        let mut batch = SimpleUnorderedBatch::default();
+        batch
+            .insertions
+            .push((key, bcs::to_bytes(&header).unwrap()));
+        DirectWritableKeyValueStore::write_batch(&faulty_store, batch)
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.clear_journal().await,
+            Err(FaultyStoreError::JournalConsistencyError(
+                JournalConsistencyError::UnsupportedJournalVersion(_)
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_journal_header() {
+        let (store, faulty_store) = new_faulty_store().await;
+        // The legacy header only contains the block count.
+        let header_key = super::get_journaling_key(super::KeyTag::Journal as u8, 0).unwrap();
+        let block_key = super::get_journaling_key(super::KeyTag::Entry as u8, 0).unwrap();
+        let mut block = SimpleUnorderedBatch::default();
+        block.insertions.push((vec![1, 5], vec![5]));
+        let mut batch = SimpleUnorderedBatch::default();
+        batch
+            .insertions
+            .push((header_key.clone(), bcs::to_bytes(&1u32).unwrap()));
+        batch
+            .insertions
+            .push((block_key.clone(), bcs::to_bytes(&block).unwrap()));
+        DirectWritableKeyValueStore::write_batch(&faulty_store, batch)
+            .await
+            .unwrap();
+        store.clear_journal().await.unwrap();
+        assert_eq!(
+            store.read_value_bytes(&[1, 5]).await.unwrap(),
+            Some(vec![5])
+        );
+        assert!(!store.contains_key(&header_key).await.unwrap());
+        assert!(!store.contains_key(&block_key).await.unwrap());
+    }
+}
@@ -0,0 +1,14 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Support for the LocalStack emulation of the AWS services used by the DynamoDB and S3
+//! stores.
+
+#[cfg(all(with_dynamodb, with_testing))]
+mod container;
+
+#[cfg(all(with_dynamodb, with_testing))]
+pub use container::{get_test_config, LocalStackTestContext};
+
+/// Name of the environment variable with the address to an external LocalStack instance.
+pub const LOCALSTACK_ENDPOINT: &str = "LOCALSTACK_ENDPOINT";
@@ -0,0 +1,192 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Runs LocalStack in a Docker container for the tests of the DynamoDB store, so that they
+//! only need Docker to be available.
+
+use std::{
+    env,
+    process::{Child, Command as StdCommand, Stdio},
+};
+
+use anyhow::{bail, Context as _, Result};
+use aws_config::BehaviorVersion;
+use aws_sdk_dynamodb::config::{Credentials, Region};
+use linera_base::{
+    command::CommandExt as _,
+    time::{timer, Duration},
+};
+use tokio::{
+    io::{AsyncReadExt as _, AsyncWriteExt as _},
+    net::TcpStream,
+    process::Command,
+    sync::OnceCell,
+};
+
+use super::LOCALSTACK_ENDPOINT;
+use crate::{dynamo_db::Config, random::generate_random_alphanumeric_string};
+
+/// The Docker image of LocalStack.
+const LOCALSTACK_IMAGE: &str = "localstack/localstack";
+
+/// The port of the LocalStack gateway within the container.
+const LOCALSTACK_PORT: u16 = 4566;
+
+/// The maximal time to wait for the DynamoDB service to be available.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The interval between two checks of the state of the container.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The container shared by the tests of the current process.
+static SHARED_CONTEXT: OnceCell<LocalStackTestContext> = OnceCell::const_new();
+
+/// A LocalStack container running the DynamoDB service. The container is removed when
+/// the context is dropped.
+pub struct LocalStackTestContext {
+    /// The name of the container.
+    name: String,
+    /// The `docker run` process attached to the container.
+    child: Child,
+    /// The address of the LocalStack gateway, e.g. `http://127.0.0.1:32768`.
+    endpoint: String,
+}
+
+impl LocalStackTestContext {
+    /// Starts a LocalStack container and waits for its DynamoDB service to be available.
+    pub async fn new() -> Result<Self> {
+        let suffix =
+            generate_random_alphanumeric_string(12, b"0123456789abcdefghijklmnopqrstuvwxyz");
+        let name = format!("linera-localstack-{suffix}");
+        // The standard input of the container is kept open by this process. The container
+        // stops when it is closed, so that it does not outlive the tests even if the
+        // context is never dropped, as for the shared one.
+        let child = StdCommand::new("docker")
+            .args(["run", "--rm", "--interactive", "--name", &name])
+            .args(["--publish", &format!("127.0.0.1::{LOCALSTACK_PORT}")])
+            .args(["--env", "SERVICES=dynamodb", "--entrypoint", "sh"])
+            .arg(LOCALSTACK_IMAGE)
+            .args([
+                "-c",
+                "docker-entrypoint.sh & cat > /dev/null; kill $!; wait",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to run the LocalStack container with the Docker CLI")?;
+        let mut context = Self {
+            name,
+            child,
+            endpoint: String::new(),
+        };
+        let address = context.wait_for_port().await?;
+        context.endpoint = format!("http://{address}");
+        context.wait_for_dynamo_db(&address).await?;
+        Ok(context)
+    }
+
+    /// Returns the context shared by the tests of the current process, starting it the
+    /// first time.
+    pub async fn shared() -> Result<&'static Self> {
+        SHARED_CONTEXT.get_or_try_init(Self::new).await
+    }
+
+    /// The address of the LocalStack gateway.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Returns a configuration to access the DynamoDB service of the container.
+    pub fn dynamo_db_config(&self) -> Config {
+        let credentials = Credentials::new("test", "test", None, None, "localstack");
+        aws_sdk_dynamodb::config::Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .credentials_provider(credentials)
+            .region(Region::new("us-east-1"))
+            .endpoint_url(&self.endpoint)
+            .build()
+    }
+
+    /// Waits for the container to be created and returns the address of its published
+    /// port.
+    async fn wait_for_port(&mut self) -> Result<String> {
+        let port = format!("{LOCALSTACK_PORT}/tcp");
+        for _ in 0..max_polls() {
+            if let Some(status) = self.child.try_wait()? {
+                bail!("The LocalStack container exited with {status}");
+            }
+            let output = Command::new("docker")
+                .args(["port", &self.name, &port])
+                .stderr(Stdio::null())
+                .spawn_and_wait_for_stdout()
+                .await;
+            if let Some(address) = output.ok().and_then(|output| {
+                let address = output.lines().next()?.trim();
+                (!address.is_empty()).then(|| address.to_string())
+            }) {
+                return Ok(address);
+            }
+            timer::sleep(POLL_INTERVAL).await;
+        }
+        bail!("The port of the LocalStack container was not published in time");
+    }
+
+    /// Polls the health endpoint of LocalStack until the DynamoDB service is available.
+    async fn wait_for_dynamo_db(&mut self, address: &str) -> Result<()> {
+        for _ in 0..max_polls() {
+            if let Some(status) = self.child.try_wait()? {
+                bail!("The LocalStack container exited with {status}");
+            }
+            if let Ok(health) = get_health(address).await {
+                let health = health.split_whitespace().collect::<String>();
+                if health.contains("\"dynamodb\":\"available\"")
+                    || health.contains("\"dynamodb\":\"running\"")
+                {
+                    return Ok(());
+                }
+            }
+            timer::sleep(POLL_INTERVAL).await;
+        }
+        bail!("The DynamoDB service of LocalStack was not available in time");
+    }
+}
+
+impl Drop for LocalStackTestContext {
+    fn drop(&mut self) {
+        let _ = StdCommand::new("docker")
+            .args(["rm", "--force", &self.name])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The number of polls before giving up on the startup of the container.
+fn max_polls() -> u128 {
+    STARTUP_TIMEOUT.as_millis() / POLL_INTERVAL.as_millis()
+}
+
+/// Fetches the response of the health endpoint of LocalStack.
+async fn get_health(address: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(address).await?;
+    let request =
+        format!("GET /_localstack/health HTTP/1.0\r\nHost: {address}\r\nConnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+/// Returns the configuration used by the tests: the LocalStack instance of the
+/// `LOCALSTACK_ENDPOINT` environment variable if it is set, and otherwise the container
+/// shared by the tests of the process.
+pub async fn get_test_config() -> Result<Config> {
+    if env::var(LOCALSTACK_ENDPOINT).is_ok() {
+        let use_localstack = true;
+        return Ok(crate::dynamo_db::get_config(use_localstack).await?);
+    }
+    Ok(LocalStackTestContext::shared().await?.dynamo_db_config())
+}
@@ -18,9 +18,13 @@ use linked_hash_map::LinkedHashMap;
 use {linera_base::prometheus_util::register_int_counter_vec, prometheus::IntCounterVec};
 
 use crate::{
-    batch::{Batch, WriteOperation},
+    batch::{Batch, BatchStats, WriteOperation},
     common::get_interval,
-    store::{AdminKeyValueStore, ReadableKeyValueStore, WithError, WritableKeyValueStore},
+    store::{
+        AdminKeyValueStore, Order, ReadableKeyValueStore, ResultStream, WithError,
+        WritableKeyValueStore,
+    },
+    views::ViewError,
 };
 #[cfg(with_testing)]
 use crate::{memory::MemoryStore, store::TestKeyValueStore};
@@ -48,23 +52,43 @@ struct LruPrefixCache {
     map: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
     queue: LinkedHashMap<Vec<u8>, (), RandomState>,
     max_cache_size: usize,
+    /// The maximal total size of the cached keys and values, if any.
+    max_cache_bytes: Option<usize>,
+    /// The total size of the cached keys and values.
+    total_bytes: usize,
+    /// The number of writes in progress. Their operations are already in the cache.
+    pending_writes: usize,
+    /// The number of completed writes. The values read from the store are only cached if
+    /// no write completed in the meantime, since they may predate it.
+    generation: u64,
+}
+
+/// The number of bytes accounted for a cache entry.
+fn entry_size(key: &[u8], value: &Option<Vec<u8>>) -> usize {
+    key.len() + value.as_ref().map_or(0, Vec::len)
 }
 
 impl<'a> LruPrefixCache {
     /// Creates a LruPrefixCache.
-    pub fn new(max_cache_size: usize) -> Self {
+    pub fn new(max_cache_size: usize, max_cache_bytes: Option<usize>) -> Self {
         Self {
             map: BTreeMap::new(),
             queue: LinkedHashMap::new(),
             max_cache_size,
+            max_cache_bytes,
+            total_bytes: 0,
+            pending_writes: 0,
+            generation: 0,
         }
     }
 
     /// Inserts an entry into the cache.
     pub fn insert(&mut self, key: Vec<u8>, value: Option<Vec<u8>>) {
+        self.total_bytes += entry_size(&key, &value);
         match self.map.entry(key.clone()) {
             btree_map::Entry::Occupied(mut entry) => {
-                entry.insert(value);
+                let old_value = entry.insert(value);
+                self.total_bytes -= entry_size(&key, &old_value);
                 // Put it on first position for LRU
                 self.queue.remove(&key);
                 self.queue.insert(key, ());
@@ -72,20 +96,62 @@ impl<'a> LruPrefixCache {
             btree_map::Entry::Vacant(entry) => {
                 entry.insert(value);
                 self.queue.insert(key, ());
-                if self.queue.len() > self.max_cache_size {
-                    let Some(value) = self.queue.pop_front() else {
-                        unreachable!()
-                    };
-                    self.map.remove(&value.0);
-                }
             }
         }
+        self.evict();
+    }
+
+    /// Evicts the least recently used entries until the cache is within its limits.
+    fn evict(&mut self) {
+        while self.queue.len() > self.max_cache_size
+            || self
+                .max_cache_bytes
+                .is_some_and(|max_cache_bytes| self.total_bytes > max_cache_bytes)
+        {
+            let Some((key, ())) = self.queue.pop_front() else {
+                break;
+            };
+            if let Some(value) = self.map.remove(&key) {
+                self.total_bytes -= entry_size(&key, &value);
+            }
+        }
+    }
+
+    /// Removes an entry from the cache, if present.
+    pub fn remove(&mut self, key: &[u8]) {
+        if let Some(value) = self.map.remove(key) {
+            self.total_bytes -= entry_size(key, &value);
+            self.queue.remove(key);
+        }
     }
 
     /// Marks cached keys that match the prefix as deleted. Importantly, this does not create new entries in the cache.
     pub fn delete_prefix(&mut self, key_prefix: &[u8]) {
         for (_, value) in self.map.range_mut(get_interval(key_prefix.to_vec())) {
-            *value = None;
+            if let Some(value) = value.take() {
+                self.total_bytes -= value.len();
+            }
+        }
+    }
+
+    /// Marks cached keys from `start` (included) to `end` (excluded) as deleted. Like
+    /// [`LruPrefixCache::delete_prefix`], this does not create new entries in the cache.
+    pub fn delete_range(&mut self, start: &[u8], end: &[u8]) {
+        if start >= end {
+            return;
+        }
+        for (_, value) in self.map.range_mut(start.to_vec()..end.to_vec()) {
+            if let Some(value) = value.take() {
+                self.total_bytes -= value.len();
+            }
+        }
+    }
+
+    /// Caches a value read from the store, unless a write may have changed it since
+    /// `generation` was observed.
+    pub fn insert_read_value(&mut self, key: Vec<u8>, value: Option<Vec<u8>>, generation: u64) {
+        if self.pending_writes == 0 && self.generation == generation {
+            self.insert(key, value);
         }
     }
 
@@ -95,6 +161,25 @@ impl<'a> LruPrefixCache {
     }
 }
 
+/// Marks a write as in progress for the cache until it is dropped, even if the write
+/// fails or is cancelled.
+struct PendingWrite<'a>(&'a Mutex<LruPrefixCache>);
+
+impl<'a> PendingWrite<'a> {
+    fn new(cache: &'a Mutex<LruPrefixCache>) -> Self {
+        cache.lock().unwrap().pending_writes += 1;
+        PendingWrite(cache)
+    }
+}
+
+impl Drop for PendingWrite<'_> {
+    fn drop(&mut self) {
+        let mut cache = self.0.lock().unwrap();
+        cache.pending_writes -= 1;
+        cache.generation += 1;
+    }
+}
+
 /// We take a store, a maximum size and build a LRU-based system.
 #[derive(Clone)]
 pub struct LruCachingStore<K> {
@@ -128,19 +213,20 @@ where
             return self.store.read_value_bytes(key).await;
         };
         // First inquiring in the read_value_bytes LRU
-        {
+        let generation = {
             let lru_read_values_container = lru_read_values.lock().unwrap();
             if let Some(value) = lru_read_values_container.query(key) {
                 #[cfg(with_metrics)]
                 NUM_CACHE_SUCCESS.with_label_values(&[]).inc();
                 return Ok(value.clone());
             }
-        }
+            lru_read_values_container.generation
+        };
         #[cfg(with_metrics)]
         NUM_CACHE_FAULT.with_label_values(&[]).inc();
         let value = self.store.read_value_bytes(key).await?;
         let mut lru_read_values = lru_read_values.lock().unwrap();
-        lru_read_values.insert(key.to_vec(), value.clone());
+        lru_read_values.insert_read_value(key.to_vec(), value.clone(), generation);
         Ok(value)
     }
 
@@ -193,7 +279,7 @@ where
         let mut result = Vec::with_capacity(keys.len());
         let mut cache_miss_indices = Vec::new();
         let mut miss_keys = Vec::new();
-        {
+        let generation = {
             let lru_read_values_container = lru_read_values.lock().unwrap();
             for (i, key) in keys.into_iter().enumerate() {
                 if let Some(value) = lru_read_values_container.query(&key) {
@@ -208,7 +294,8 @@ where
                     miss_keys.push(key);
                 }
             }
-        }
+            lru_read_values_container.generation
+        };
         if !miss_keys.is_empty() {
             let values = self
                 .store
@@ -219,7 +306,7 @@ where
                 .into_iter()
                 .zip(miss_keys.into_iter().zip(values))
             {
-                lru_read_values.insert(key, value.clone());
+                lru_read_values.insert_read_value(key, value.clone(), generation);
                 result[i] = value;
             }
         }
@@ -236,6 +323,65 @@ where
     ) -> Result<Self::KeyValues, Self::Error> {
         self.store.find_key_values_by_prefix(key_prefix).await
     }
+
+    async fn find_keys_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.store
+            .find_keys_by_prefix_with_options(key_prefix, order)
+            .await
+    }
+
+    async fn find_key_values_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        self.store
+            .find_key_values_by_prefix_with_options(key_prefix, order)
+            .await
+    }
+
+    async fn find_keys_by_prefix_paginated(
+        &self,
+        key_prefix: &[u8],
+        limit: usize,
+        continuation: Option<Vec<u8>>,
+    ) -> Result<(Vec<Vec<u8>>, Option<Vec<u8>>), Self::Error> {
+        self.store
+            .find_keys_by_prefix_paginated(key_prefix, limit, continuation)
+            .await
+    }
+
+    async fn find_keys_in_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.store.find_keys_in_range(start, end).await
+    }
+
+    async fn count_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<usize, Self::Error> {
+        self.store.count_keys_by_prefix(key_prefix).await
+    }
+
+    async fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, Vec<u8>, Self::Error>, Self::Error> {
+        self.store.find_keys_by_prefix_stream(key_prefix).await
+    }
+
+    async fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error>, Self::Error> {
+        self.store
+            .find_key_values_by_prefix_stream(key_prefix)
+            .await
+    }
 }
 
 impl<K> WritableKeyValueStore for LruCachingStore<K>
@@ -246,27 +392,32 @@ where
     const MAX_VALUE_SIZE: usize = K::MAX_VALUE_SIZE;
 
     async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
-        let Some(lru_read_values) = &self.lru_read_values else {
-            return self.store.write_batch(batch).await;
-        };
+        let _pending_write = self.update_cache(&batch);
+        self.store.write_batch(batch).await
+    }
 
-        {
+    async fn write_batch_with_stats(&self, batch: Batch) -> Result<BatchStats, Self::Error> {
+        let _pending_write = self.update_cache(&batch);
+        self.store.write_batch_with_stats(batch).await
+    }
+
+    async fn compare_and_put(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, ViewError> {
+        let _pending_write = self.lru_read_values.as_deref().map(PendingWrite::new);
+        let result = self.store.compare_and_put(key, expected, new).await;
+        if let Some(lru_read_values) = &self.lru_read_values {
             let mut lru_read_values = lru_read_values.lock().unwrap();
-            for operation in &batch.operations {
-                match operation {
-                    WriteOperation::Put { key, value } => {
-                        lru_read_values.insert(key.to_vec(), Some(value.to_vec()));
-                    }
-                    WriteOperation::Delete { key } => {
-                        lru_read_values.insert(key.to_vec(), None);
-                    }
-                    WriteOperation::DeletePrefix { key_prefix } => {
-                        lru_read_values.delete_prefix(key_prefix);
-                    }
-                }
+            match result {
+                Ok(true) => lru_read_values.insert(key.to_vec(), new.map(<[u8]>::to_vec)),
+                // The cached value may be stale if the condition failed.
+                _ => lru_read_values.remove(key),
             }
         }
-        self.store.write_batch(batch).await
+        result
     }
 
     async fn clear_journal(&self) -> Result<(), Self::Error> {
@@ -280,6 +431,16 @@ pub struct LruCachingConfig<C> {
     pub inner_config: C,
     /// The cache size being used
     pub cache_size: usize,
+    /// The maximal total size in bytes of the cached keys and values, if any
+    pub max_cache_bytes: Option<usize>,
+}
+
+impl<C> LruCachingConfig<C> {
+    /// Bounds the total size of the cached keys and values, on top of their number.
+    pub fn with_max_cache_bytes(mut self, max_cache_bytes: usize) -> Self {
+        self.max_cache_bytes = Some(max_cache_bytes);
+        self
+    }
 }
 
 impl<K> AdminKeyValueStore for LruCachingStore<K>
@@ -299,13 +460,23 @@ where
     ) -> Result<Self, Self::Error> {
         let store = K::connect(&config.inner_config, namespace, root_key).await?;
         let cache_size = config.cache_size;
-        Ok(LruCachingStore::new(store, cache_size))
+        let max_cache_bytes = config.max_cache_bytes;
+        Ok(LruCachingStore::with_max_cache_bytes(
+            store,
+            cache_size,
+            max_cache_bytes,
+        ))
     }
 
     fn clone_with_root_key(&self, root_key: &[u8]) -> Result<Self, Self::Error> {
         let store = self.store.clone_with_root_key(root_key)?;
         let cache_size = self.cache_size();
-        Ok(LruCachingStore::new(store, cache_size))
+        let max_cache_bytes = self.max_cache_bytes();
+        Ok(LruCachingStore::with_max_cache_bytes(
+            store,
+            cache_size,
+            max_cache_bytes,
+        ))
     }
 
     async fn list_all(config: &Self::Config) -> Result<Vec<String>, Self::Error> {
@@ -340,28 +511,75 @@ where
         Ok(LruCachingConfig {
             inner_config,
             cache_size,
+            max_cache_bytes: None,
         })
     }
 }
 
-fn new_lru_prefix_cache(cache_size: usize) -> Option<Arc<Mutex<LruPrefixCache>>> {
+fn new_lru_prefix_cache(
+    cache_size: usize,
+    max_cache_bytes: Option<usize>,
+) -> Option<Arc<Mutex<LruPrefixCache>>> {
     if cache_size == 0 {
         None
     } else {
-        Some(Arc::new(Mutex::new(LruPrefixCache::new(cache_size))))
+        Some(Arc::new(Mutex::new(LruPrefixCache::new(
+            cache_size,
+            max_cache_bytes,
+        ))))
     }
 }
 
 impl<K> LruCachingStore<K> {
     /// Creates a new key-value store that provides LRU caching at top of the given store.
     pub fn new(store: K, cache_size: usize) -> Self {
-        let lru_read_values = new_lru_prefix_cache(cache_size);
+        Self::with_max_cache_bytes(store, cache_size, None)
+    }
+
+    /// Creates a new key-value store that provides LRU caching at top of the given store,
+    /// also bounding the total size of the cached keys and values.
+    pub fn with_max_cache_bytes(
+        store: K,
+        cache_size: usize,
+        max_cache_bytes: Option<usize>,
+    ) -> Self {
+        let lru_read_values = new_lru_prefix_cache(cache_size, max_cache_bytes);
         Self {
             store,
             lru_read_values,
         }
     }
 
+    /// Applies the operations of a batch about to be written to the cache. The write is
+    /// pending until the returned guard is dropped.
+    fn update_cache(&self, batch: &Batch) -> Option<PendingWrite<'_>> {
+        let lru_read_values = self.lru_read_values.as_deref()?;
+        let pending_write = PendingWrite::new(lru_read_values);
+        let mut lru_read_values = lru_read_values.lock().unwrap();
+        for operation in &batch.operations {
+            match operation {
+                WriteOperation::Put { key, value } => {
+                    lru_read_values.insert(key.to_vec(), Some(value.to_vec()));
+                }
+                WriteOperation::Delete { key } => {
+                    lru_read_values.insert(key.to_vec(), None);
+                }
+                WriteOperation::DeletePrefix { key_prefix } => {
+                    lru_read_values.delete_prefix(key_prefix);
+                }
+                WriteOperation::DeleteRange { start, end } => {
+                    lru_read_values.delete_range(start, end);
+                }
+            }
+        }
+        Some(pending_write)
+    }
+
+    /// Gets the inner store.
+    pub fn inner(&self) -> &K {
+        &self.store
+    }
+
     /// Gets the `cache_size`
     pub fn cache_size(&self) -> usize {
         match &self.lru_read_values {
@@ -372,8 +590,60 @@ impl<K> LruCachingStore<K> {
             }
         }
     }
+
+    /// Gets the maximal total size of the cached keys and values, if any.
+    pub fn max_cache_bytes(&self) -> Option<usize> {
+        let lru_read_values = self.lru_read_values.as_ref()?;
+        let lru_read_values = lru_read_values.lock().unwrap();
+        lru_read_values.max_cache_bytes
+    }
 }
 
 /// A memory store with caching.
 #[cfg(with_testing)]
 pub type LruCachingMemoryStore = LruCachingStore<MemoryStore>;
+
+#[cfg(test)]
+mod tests {
+    use super::LruPrefixCache;
+
+    #[test]
+    fn test_max_cache_bytes() {
+        let mut cache = LruPrefixCache::new(10, Some(10));
+        cache.insert(vec![0], Some(vec![0; 4]));
+        cache.insert(vec![1], Some(vec![1; 4]));
+        assert_eq!(cache.total_bytes, 10);
+        // The least recently used entry is evicted to make room for the new one.
+        cache.insert(vec![2], None);
+        assert_eq!(cache.query(&[0]), None);
+        assert_eq!(cache.query(&[1]), Some(&Some(vec![1; 4])));
+        assert_eq!(cache.query(&[2]), Some(&None));
+        assert_eq!(cache.total_bytes, 6);
+        // Overwriting and deleting entries updates the size.
+        cache.insert(vec![1], Some(vec![1; 2]));
+        assert_eq!(cache.total_bytes, 4);
+        cache.delete_prefix(&[1]);
+        assert_eq!(cache.total_bytes, 2);
+        cache.remove(&[2]);
+        assert_eq!(cache.total_bytes, 1);
+        // An entry larger than the budget is not kept.
+        cache.insert(vec![3], Some(vec![3; 20]));
+        assert_eq!(cache.query(&[3]), None);
+        assert_eq!(cache.total_bytes, 0);
+    }
+
+    #[test]
+    fn test_read_values_during_writes() {
+        let mut cache = LruPrefixCache::new(10, None);
+        let generation = cache.generation;
+        cache.pending_writes += 1;
+        cache.insert_read_value(vec![0], Some(vec![0]), generation);
+        assert_eq!(cache.query(&[0]), None);
+        cache.pending_writes -= 1;
+        cache.generation += 1;
+        cache.insert_read_value(vec![0], Some(vec![0]), generation);
+        assert_eq!(cache.query(&[0]), None);
+        cache.insert_read_value(vec![0], Some(vec![1]), cache.generation);
+        assert_eq!(cache.query(&[0]), Some(&Some(vec![1])));
+    }
+}
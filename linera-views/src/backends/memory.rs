@@ -5,9 +5,13 @@
 
 use std::{
     collections::BTreeMap,
+    fs, io,
+    path::Path,
     sync::{Arc, LazyLock, Mutex, RwLock},
 };
 
+use linera_base::time::{timer, Duration};
+use rand::{rngs::OsRng, Rng as _};
 use thiserror::Error;
 
 #[cfg(with_testing)]
@@ -15,12 +19,13 @@ use crate::random::generate_test_namespace;
 #[cfg(with_testing)]
 use crate::store::TestKeyValueStore;
 use crate::{
-    batch::{Batch, WriteOperation},
+    batch::{Batch, BatchStats, WriteOperation},
     common::get_interval,
     store::{
         AdminKeyValueStore, CommonStoreInternalConfig, KeyValueStoreError, ReadableKeyValueStore,
         WithError, WritableKeyValueStore,
     },
+    views::ViewError,
 };
 
 /// The initial configuration of the system
@@ -48,6 +53,80 @@ pub const TEST_MEMORY_MAX_STREAM_QUERIES: usize = 10;
 /// The analog of the database is the BTreeMap
 type MemoryStoreMap = BTreeMap<Vec<u8>, Vec<u8>>;
 
+/// The delay added to a kind of operation by the latency model of a [`MemoryStore`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Latency {
+    /// The operations are not delayed.
+    #[default]
+    None,
+    /// The operations are delayed by a fixed duration.
+    Fixed(Duration),
+    /// The operations are delayed by a duration drawn uniformly between two bounds.
+    Uniform {
+        /// The minimal delay.
+        min: Duration,
+        /// The maximal delay.
+        max: Duration,
+    },
+}
+
+impl Latency {
+    /// Checks that the bounds of a uniform delay are in order.
+    fn check(&self) -> Result<(), MemoryStoreError> {
+        match *self {
+            Latency::Uniform { min, max } if min > max => {
+                Err(MemoryStoreError::InvalidLatency { min, max })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Draws the delay of an operation.
+    fn sample(&self) -> Duration {
+        match *self {
+            Latency::None => Duration::ZERO,
+            Latency::Fixed(delay) => delay,
+            Latency::Uniform { min, max } => OsRng.gen_range(min..=max),
+        }
+    }
+}
+
+/// The latency model of a [`MemoryStore`], so that tests run with a timing closer to the
+/// one of a remote database. By default, nothing is delayed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LatencyConfig {
+    /// The delay of the reads of given keys.
+    pub read: Latency,
+    /// The delay of the searches of the keys matching a prefix.
+    pub scan: Latency,
+    /// The delay of the writes.
+    pub write: Latency,
+}
+
+/// The magic bytes starting a file saved by [`MemoryStore::save_to_file`].
+const SNAPSHOT_FILE_MAGIC: &[u8] = b"LINERAMS";
+
+/// The version of the format of the files saved by [`MemoryStore::save_to_file`].
+const SNAPSHOT_FILE_VERSION: u8 = 1;
+
+/// The content of a [`MemoryStore`] at some point in time, see [`MemoryStore::snapshot`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemorySnapshot {
+    map: MemoryStoreMap,
+}
+
+impl MemorySnapshot {
+    /// Returns the number of key-value pairs in the snapshot.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns whether the snapshot is empty.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
 /// The container for the `MemoryStoreMap`s by namespace and then root key
 #[derive(Default)]
 struct MemoryStores {
@@ -79,6 +158,7 @@ impl MemoryStores {
             namespace,
             root_key,
             kill_on_drop,
+            latency: LatencyConfig::default(),
         })
     }
 
@@ -104,6 +184,11 @@ static MEMORY_STORES: LazyLock<Mutex<MemoryStores>> =
     LazyLock::new(|| Mutex::new(MemoryStores::default()));
 
 /// A virtual DB client where data are persisted in memory.
+///
+/// The clones of a store share its key-value pairs, e.g. between tasks simulating several
+/// workers. The searches of keys are done on a consistent state of the store, even if
+/// another task writes at the same time. See [`MemoryStore::new_isolated`] for a copy
+/// which is not shared.
 #[derive(Clone)]
 pub struct MemoryStore {
     /// The map used for storing the data.
@@ -116,6 +201,8 @@ pub struct MemoryStore {
     root_key: Vec<u8>,
     /// Whether to kill on drop or not the
     kill_on_drop: bool,
+    /// The latency model of the operations.
+    latency: LatencyConfig,
 }
 
 impl Drop for MemoryStore {
@@ -144,6 +231,7 @@ impl ReadableKeyValueStore for MemoryStore {
     }
 
     async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, MemoryStoreError> {
+        self.delay(self.latency.read).await;
         let map = self
             .map
             .read()
@@ -152,6 +240,7 @@ impl ReadableKeyValueStore for MemoryStore {
     }
 
     async fn contains_key(&self, key: &[u8]) -> Result<bool, MemoryStoreError> {
+        self.delay(self.latency.read).await;
         let map = self
             .map
             .read()
@@ -160,6 +249,7 @@ impl ReadableKeyValueStore for MemoryStore {
     }
 
     async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, MemoryStoreError> {
+        self.delay(self.latency.read).await;
         let map = self
             .map
             .read()
@@ -174,6 +264,7 @@ impl ReadableKeyValueStore for MemoryStore {
         &self,
         keys: Vec<Vec<u8>>,
     ) -> Result<Vec<Option<Vec<u8>>>, MemoryStoreError> {
+        self.delay(self.latency.read).await;
         let map = self
             .map
             .read()
@@ -189,6 +280,7 @@ impl ReadableKeyValueStore for MemoryStore {
         &self,
         key_prefix: &[u8],
     ) -> Result<Vec<Vec<u8>>, MemoryStoreError> {
+        self.delay(self.latency.scan).await;
         let map = self
             .map
             .read()
@@ -205,6 +297,7 @@ impl ReadableKeyValueStore for MemoryStore {
         &self,
         key_prefix: &[u8],
     ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, MemoryStoreError> {
+        self.delay(self.latency.scan).await;
         let map = self
             .map
             .read()
@@ -223,6 +316,13 @@ impl WritableKeyValueStore for MemoryStore {
     const MAX_VALUE_SIZE: usize = usize::MAX;
 
     async fn write_batch(&self, batch: Batch) -> Result<(), MemoryStoreError> {
+        self.write_batch_with_stats(batch).await?;
+        Ok(())
+    }
+
+    async fn write_batch_with_stats(&self, batch: Batch) -> Result<BatchStats, MemoryStoreError> {
+        self.delay(self.latency.write).await;
+        let mut stats = BatchStats::from_batch(&batch);
         let mut map = self
             .map
             .write()
@@ -240,13 +340,51 @@ impl WritableKeyValueStore for MemoryStore {
                         .range(get_interval(key_prefix))
                         .map(|x| x.0.to_vec())
                         .collect::<Vec<_>>();
+                    stats.delete_prefixes_expanded.push(key_list.len());
                     for key in key_list {
                         map.remove(&key);
                     }
                 }
+                WriteOperation::DeleteRange { start, end } => {
+                    if start < end {
+                        let key_list = map
+                            .range(start..end)
+                            .map(|x| x.0.to_vec())
+                            .collect::<Vec<_>>();
+                        stats.delete_prefixes_expanded.push(key_list.len());
+                        for key in key_list {
+                            map.remove(&key);
+                        }
+                    }
+                }
             }
         }
-        Ok(())
+        Ok(stats)
+    }
+
+    async fn compare_and_put(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, ViewError> {
+        self.delay(self.latency.write).await;
+        let mut map = self
+            .map
+            .write()
+            .expect("MemoryStore lock should not be poisoned");
+        if map.get(key).map(Vec::as_slice) != expected {
+            return Ok(false);
+        }
+        match new {
+            Some(value) => {
+                map.insert(key.to_vec(), value.to_vec());
+            }
+            None => {
+                map.remove(key);
+            }
+        }
+        Ok(true)
     }
 
     async fn clear_journal(&self) -> Result<(), MemoryStoreError> {
@@ -299,6 +437,98 @@ impl MemoryStore {
         let kill_on_drop = true;
         MemoryStore::sync_maybe_create_and_connect(&config, namespace, root_key, kill_on_drop)
     }
+
+    /// Sets the latency model of the operations of the store and of the stores obtained
+    /// from it with `clone_with_root_key`. Fails if the minimal delay of a uniform latency
+    /// is larger than its maximal delay.
+    pub fn with_latency(mut self, latency: LatencyConfig) -> Result<Self, MemoryStoreError> {
+        latency.read.check()?;
+        latency.scan.check()?;
+        latency.write.check()?;
+        self.latency = latency;
+        Ok(self)
+    }
+
+    /// Waits for the delay of an operation, if any.
+    async fn delay(&self, latency: Latency) {
+        let delay = latency.sample();
+        if !delay.is_zero() {
+            timer::sleep(delay).await;
+        }
+    }
+
+    /// Returns a copy of the key-value pairs of the store, which can be restored later.
+    pub fn snapshot(&self) -> MemorySnapshot {
+        let map = self
+            .map
+            .read()
+            .expect("MemoryStore lock should not be poisoned");
+        MemorySnapshot { map: map.clone() }
+    }
+
+    /// Replaces the key-value pairs of the store by the ones of the `snapshot`.
+    pub fn restore(&self, snapshot: &MemorySnapshot) {
+        let mut map = self
+            .map
+            .write()
+            .expect("MemoryStore lock should not be poisoned");
+        *map = snapshot.map.clone();
+    }
+
+    /// Returns a store with a copy of the key-value pairs of this store, which does not
+    /// share them: the writes to either store are not seen by the other one. The copy is
+    /// not registered under the namespace, so the stores obtained from it with
+    /// `clone_with_root_key` share the data of the namespace again.
+    pub fn new_isolated(&self) -> Self {
+        let map = self.snapshot().map;
+        MemoryStore {
+            map: Arc::new(RwLock::new(map)),
+            max_stream_queries: self.max_stream_queries,
+            namespace: self.namespace.clone(),
+            root_key: self.root_key.clone(),
+            kill_on_drop: false,
+            latency: self.latency,
+        }
+    }
+
+    /// Saves the key-value pairs of the store to a file, behind a versioned header, so
+    /// that they can be loaded back with [`MemoryStore::load_from_file`].
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<(), MemoryStoreError> {
+        let mut bytes = SNAPSHOT_FILE_MAGIC.to_vec();
+        bytes.push(SNAPSHOT_FILE_VERSION);
+        {
+            let map = self
+                .map
+                .read()
+                .expect("MemoryStore lock should not be poisoned");
+            bcs::serialize_into(&mut bytes, &*map)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Replaces the key-value pairs of the store by the ones of a file saved by
+    /// [`MemoryStore::save_to_file`].
+    pub fn load_from_file(&self, path: impl AsRef<Path>) -> Result<(), MemoryStoreError> {
+        let bytes = fs::read(path)?;
+        let Some(bytes) = bytes.strip_prefix(SNAPSHOT_FILE_MAGIC) else {
+            return Err(MemoryStoreError::NotASnapshotFile);
+        };
+        let Some((&version, bytes)) = bytes.split_first() else {
+            return Err(MemoryStoreError::NotASnapshotFile);
+        };
+        if version != SNAPSHOT_FILE_VERSION {
+            return Err(MemoryStoreError::SnapshotVersionMismatch {
+                found: version,
+                expected: SNAPSHOT_FILE_VERSION,
+            });
+        }
+        let snapshot = MemorySnapshot {
+            map: bcs::from_bytes(bytes)?,
+        };
+        self.restore(&snapshot);
+        Ok(())
+    }
 }
 
 impl AdminKeyValueStore for MemoryStore {
@@ -332,7 +562,8 @@ impl AdminKeyValueStore for MemoryStore {
             .expect("MEMORY_STORES lock should not be poisoned");
         let kill_on_drop = self.kill_on_drop;
         let namespace = &self.namespace;
-        memory_stores.sync_connect(&config, namespace, root_key, kill_on_drop)
+        let store = memory_stores.sync_connect(&config, namespace, root_key, kill_on_drop)?;
+        store.with_latency(self.latency)
     }
 
     async fn list_all(_config: &Self::Config) -> Result<Vec<String>, MemoryStoreError> {
@@ -400,6 +631,32 @@ pub enum MemoryStoreError {
     /// The namespace does not exist
     #[error("The namespace does not exist")]
     NamespaceNotFound,
+
+    /// An I/O error occurred while saving or loading a snapshot file.
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+
+    /// The file was not saved by `MemoryStore::save_to_file`.
+    #[error("The file is not a snapshot of a memory store")]
+    NotASnapshotFile,
+
+    /// The snapshot file was saved with another version of the format.
+    #[error("The snapshot file has version {found}, but only version {expected} is supported")]
+    SnapshotVersionMismatch {
+        /// The version of the file.
+        found: u8,
+        /// The supported version.
+        expected: u8,
+    },
+
+    /// The minimal delay of a uniform latency is larger than its maximal delay.
+    #[error("Invalid latency: the minimal delay {min:?} is larger than the maximal delay {max:?}")]
+    InvalidLatency {
+        /// The minimal delay.
+        min: Duration,
+        /// The maximal delay.
+        max: Duration,
+    },
 }
 
 impl KeyValueStoreError for MemoryStoreError {
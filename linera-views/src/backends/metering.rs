@@ -9,19 +9,21 @@ use std::{
 };
 
 use convert_case::{Case, Casing};
+use futures::stream::{self, StreamExt as _};
 use linera_base::prometheus_util::{
-    register_histogram_vec, register_int_counter_vec, MeasureLatency,
+    register_histogram_vec, register_int_counter_vec, ActiveMeasurementGuard, MeasureLatency,
 };
 use prometheus::{HistogramVec, IntCounterVec};
 
 #[cfg(with_testing)]
 use crate::store::TestKeyValueStore;
 use crate::{
-    batch::Batch,
+    batch::{Batch, BatchStats},
     store::{
-        AdminKeyValueStore, KeyIterable as _, KeyValueIterable as _, ReadableKeyValueStore,
-        WithError, WritableKeyValueStore,
+        AdminKeyValueStore, KeyIterable as _, KeyValueIterable as _, Order, ReadableKeyValueStore,
+        ResultStream, WithError, WritableKeyValueStore,
     },
+    views::ViewError,
 };
 
 #[derive(Clone)]
@@ -270,6 +272,28 @@ pub struct MeteredStore<K> {
     store: K,
 }
 
+impl<K> MeteredStore<K> {
+    /// Gets the inner store.
+    pub fn inner(&self) -> &K {
+        &self.store
+    }
+}
+
+/// Keeps measuring the latency until the stream completes or is dropped.
+fn measure_stream<'a, T: 'a, E: 'a>(
+    stream: ResultStream<'a, T, E>,
+    latency: ActiveMeasurementGuard<'a, HistogramVec>,
+) -> ResultStream<'a, T, E> {
+    // The state, and with it the measurement, is dropped after the last item.
+    Box::pin(stream::unfold(
+        (stream, latency),
+        |(mut stream, latency)| async move {
+            let item = stream.next().await?;
+            Some((item, (stream, latency)))
+        },
+    ))
+}
+
 impl<K> WithError for MeteredStore<K>
 where
     K: WithError,
@@ -407,6 +431,105 @@ where
             .observe(key_values_size as f64);
         Ok(result)
     }
+
+    async fn find_keys_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        let _latency = self.counter.find_keys_by_prefix_latency.measure_latency();
+        self.counter
+            .find_keys_by_prefix_prefix_size
+            .with_label_values(&[])
+            .observe(key_prefix.len() as f64);
+        self.store
+            .find_keys_by_prefix_with_options(key_prefix, order)
+            .await
+    }
+
+    async fn find_key_values_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        let _latency = self
+            .counter
+            .find_key_values_by_prefix_latency
+            .measure_latency();
+        self.counter
+            .find_key_values_by_prefix_prefix_size
+            .with_label_values(&[])
+            .observe(key_prefix.len() as f64);
+        self.store
+            .find_key_values_by_prefix_with_options(key_prefix, order)
+            .await
+    }
+
+    async fn find_keys_by_prefix_paginated(
+        &self,
+        key_prefix: &[u8],
+        limit: usize,
+        continuation: Option<Vec<u8>>,
+    ) -> Result<(Vec<Vec<u8>>, Option<Vec<u8>>), Self::Error> {
+        let _latency = self.counter.find_keys_by_prefix_latency.measure_latency();
+        self.counter
+            .find_keys_by_prefix_prefix_size
+            .with_label_values(&[])
+            .observe(key_prefix.len() as f64);
+        self.store
+            .find_keys_by_prefix_paginated(key_prefix, limit, continuation)
+            .await
+    }
+
+    async fn find_keys_in_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        let _latency = self.counter.find_keys_by_prefix_latency.measure_latency();
+        self.store.find_keys_in_range(start, end).await
+    }
+
+    async fn count_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<usize, Self::Error> {
+        let _latency = self.counter.find_keys_by_prefix_latency.measure_latency();
+        self.counter
+            .find_keys_by_prefix_prefix_size
+            .with_label_values(&[])
+            .observe(key_prefix.len() as f64);
+        self.store.count_keys_by_prefix(key_prefix).await
+    }
+
+    async fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, Vec<u8>, Self::Error>, Self::Error> {
+        let latency = self.counter.find_keys_by_prefix_latency.measure_latency();
+        self.counter
+            .find_keys_by_prefix_prefix_size
+            .with_label_values(&[])
+            .observe(key_prefix.len() as f64);
+        let stream = self.store.find_keys_by_prefix_stream(key_prefix).await?;
+        Ok(measure_stream(stream, latency))
+    }
+
+    async fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error>, Self::Error> {
+        let latency = self
+            .counter
+            .find_key_values_by_prefix_latency
+            .measure_latency();
+        self.counter
+            .find_key_values_by_prefix_prefix_size
+            .with_label_values(&[])
+            .observe(key_prefix.len() as f64);
+        let stream = self
+            .store
+            .find_key_values_by_prefix_stream(key_prefix)
+            .await?;
+        Ok(measure_stream(stream, latency))
+    }
 }
 
 impl<K> WritableKeyValueStore for MeteredStore<K>
@@ -424,6 +547,25 @@ where
         self.store.write_batch(batch).await
     }
 
+    async fn write_batch_with_stats(&self, batch: Batch) -> Result<BatchStats, Self::Error> {
+        let _latency = self.counter.write_batch_latency.measure_latency();
+        self.counter
+            .write_batch_size
+            .with_label_values(&[])
+            .observe(batch.size() as f64);
+        self.store.write_batch_with_stats(batch).await
+    }
+
+    async fn compare_and_put(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, ViewError> {
+        let _latency = self.counter.write_batch_latency.measure_latency();
+        self.store.compare_and_put(key, expected, new).await
+    }
+
     async fn clear_journal(&self) -> Result<(), Self::Error> {
         let _metric = self.counter.clear_journal_latency.measure_latency();
         self.store.clear_journal().await
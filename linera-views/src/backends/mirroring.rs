@@ -0,0 +1,498 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements [`crate::store::KeyValueStore`] by writing to two existing stores and
+//! reading from the first one, e.g. while migrating the data from a backend to another.
+
+use thiserror::Error;
+
+#[cfg(with_testing)]
+use crate::store::TestKeyValueStore;
+use crate::{
+    batch::{Batch, BatchStats},
+    store::{
+        AdminKeyValueStore, KeyIterable as _, KeyValueIterable as _, KeyValueStoreError, Order,
+        ReadableKeyValueStore, WithError, WritableKeyValueStore,
+    },
+    views::ViewError,
+};
+
+/// What to do when a write to the secondary store fails after the write to the primary
+/// store succeeded.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SecondaryFailurePolicy {
+    /// Return the error of the secondary store.
+    #[default]
+    Fatal,
+    /// Log the error and report the write as successful.
+    Log,
+}
+
+/// The initial configuration of the system.
+#[derive(Debug)]
+pub struct MirroringStoreConfig<C1, C2> {
+    /// The config of the primary store.
+    pub primary_config: C1,
+    /// The config of the secondary store.
+    pub secondary_config: C2,
+    /// What to do when a write to the secondary store fails.
+    pub secondary_failure_policy: SecondaryFailurePolicy,
+}
+
+/// A store reading from a primary store and writing to both the primary and a secondary
+/// store.
+///
+/// The batches are written to the primary store first, so that the secondary store never
+/// has a value that the primary store does not have, unless the primary store was written
+/// to directly.
+#[derive(Clone)]
+pub struct MirroringStore<P, S> {
+    /// The store used for the reads.
+    primary_store: P,
+    /// The store receiving a copy of the writes.
+    secondary_store: S,
+    /// What to do when a write to the secondary store fails.
+    secondary_failure_policy: SecondaryFailurePolicy,
+}
+
+impl<P, S> MirroringStore<P, S>
+where
+    P: WithError,
+    S: WithError,
+{
+    /// Creates a store mirroring the writes of `primary_store` into `secondary_store`.
+    pub fn new(
+        primary_store: P,
+        secondary_store: S,
+        secondary_failure_policy: SecondaryFailurePolicy,
+    ) -> Self {
+        Self {
+            primary_store,
+            secondary_store,
+            secondary_failure_policy,
+        }
+    }
+
+    /// Applies the policy to the result of a write to the secondary store.
+    fn handle_secondary_result<T>(
+        &self,
+        result: Result<T, S::Error>,
+    ) -> Result<(), MirroringStoreError<P::Error, S::Error>> {
+        match (result, self.secondary_failure_policy) {
+            (Ok(_), _) => Ok(()),
+            (Err(error), SecondaryFailurePolicy::Fatal) => {
+                Err(MirroringStoreError::Secondary(error))
+            }
+            (Err(error), SecondaryFailurePolicy::Log) => {
+                tracing::warn!("Failed to write to the secondary store: {error}");
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<P, S> MirroringStore<P, S>
+where
+    P: ReadableKeyValueStore + Send + Sync,
+    S: ReadableKeyValueStore + Send + Sync,
+{
+    /// Scans the keys starting with `key_prefix` in both stores and returns the keys,
+    /// including the prefix, whose values differ or which exist in only one store.
+    pub async fn verify_consistency(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<Vec<u8>>, MirroringStoreError<P::Error, S::Error>> {
+        let primary_key_values = self
+            .primary_store
+            .find_key_values_by_prefix_with_options(key_prefix, Order::Forward)
+            .await
+            .map_err(MirroringStoreError::Primary)?;
+        let secondary_key_values = self
+            .secondary_store
+            .find_key_values_by_prefix_with_options(key_prefix, Order::Forward)
+            .await
+            .map_err(MirroringStoreError::Secondary)?;
+        let mut divergent_keys = Vec::new();
+        let mut primary_iter = primary_key_values.into_iter().peekable();
+        let mut secondary_iter = secondary_key_values.into_iter().peekable();
+        loop {
+            let suffix = match (primary_iter.peek(), secondary_iter.peek()) {
+                (None, None) => break,
+                (Some((primary_key, _)), None) => {
+                    let suffix = primary_key.clone();
+                    primary_iter.next();
+                    suffix
+                }
+                (None, Some((secondary_key, _))) => {
+                    let suffix = secondary_key.clone();
+                    secondary_iter.next();
+                    suffix
+                }
+                (Some((primary_key, primary_value)), Some((secondary_key, secondary_value))) => {
+                    match primary_key.cmp(secondary_key) {
+                        std::cmp::Ordering::Less => {
+                            let suffix = primary_key.clone();
+                            primary_iter.next();
+                            suffix
+                        }
+                        std::cmp::Ordering::Greater => {
+                            let suffix = secondary_key.clone();
+                            secondary_iter.next();
+                            suffix
+                        }
+                        std::cmp::Ordering::Equal => {
+                            let is_equal = primary_value == secondary_value;
+                            let suffix = primary_key.clone();
+                            primary_iter.next();
+                            secondary_iter.next();
+                            if is_equal {
+                                continue;
+                            }
+                            suffix
+                        }
+                    }
+                }
+            };
+            let mut key = key_prefix.to_vec();
+            key.extend(suffix);
+            divergent_keys.push(key);
+        }
+        Ok(divergent_keys)
+    }
+}
+
+impl<P, S> WithError for MirroringStore<P, S>
+where
+    P: WithError,
+    S: WithError,
+{
+    type Error = MirroringStoreError<P::Error, S::Error>;
+}
+
+impl<P, S> ReadableKeyValueStore for MirroringStore<P, S>
+where
+    P: ReadableKeyValueStore + Send + Sync,
+    S: ReadableKeyValueStore + Send + Sync,
+{
+    const MAX_KEY_SIZE: usize = if P::MAX_KEY_SIZE < S::MAX_KEY_SIZE {
+        P::MAX_KEY_SIZE
+    } else {
+        S::MAX_KEY_SIZE
+    };
+
+    type Keys = Vec<Vec<u8>>;
+    type KeyValues = Vec<(Vec<u8>, Vec<u8>)>;
+
+    fn max_stream_queries(&self) -> usize {
+        self.primary_store.max_stream_queries()
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.primary_store
+            .read_value_bytes(key)
+            .await
+            .map_err(MirroringStoreError::Primary)
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        self.primary_store
+            .contains_key(key)
+            .await
+            .map_err(MirroringStoreError::Primary)
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, Self::Error> {
+        self.primary_store
+            .contains_keys(keys)
+            .await
+            .map_err(MirroringStoreError::Primary)
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        self.primary_store
+            .read_multi_values_bytes(keys)
+            .await
+            .map_err(MirroringStoreError::Primary)
+    }
+
+    async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Self::Keys, Self::Error> {
+        let keys = self
+            .primary_store
+            .find_keys_by_prefix(key_prefix)
+            .await
+            .map_err(MirroringStoreError::Primary)?;
+        let mut result = Vec::new();
+        for key in keys.iterator() {
+            result.push(key.map_err(MirroringStoreError::Primary)?.to_vec());
+        }
+        Ok(result)
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Self::KeyValues, Self::Error> {
+        let key_values = self
+            .primary_store
+            .find_key_values_by_prefix(key_prefix)
+            .await
+            .map_err(MirroringStoreError::Primary)?;
+        let mut result = Vec::new();
+        for key_value in key_values.into_iterator_owned() {
+            result.push(key_value.map_err(MirroringStoreError::Primary)?);
+        }
+        Ok(result)
+    }
+
+    async fn find_keys_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.primary_store
+            .find_keys_by_prefix_with_options(key_prefix, order)
+            .await
+            .map_err(MirroringStoreError::Primary)
+    }
+
+    async fn find_key_values_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        self.primary_store
+            .find_key_values_by_prefix_with_options(key_prefix, order)
+            .await
+            .map_err(MirroringStoreError::Primary)
+    }
+
+    async fn find_keys_by_prefix_paginated(
+        &self,
+        key_prefix: &[u8],
+        limit: usize,
+        continuation: Option<Vec<u8>>,
+    ) -> Result<(Vec<Vec<u8>>, Option<Vec<u8>>), Self::Error> {
+        self.primary_store
+            .find_keys_by_prefix_paginated(key_prefix, limit, continuation)
+            .await
+            .map_err(MirroringStoreError::Primary)
+    }
+
+    async fn find_keys_in_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.primary_store
+            .find_keys_in_range(start, end)
+            .await
+            .map_err(MirroringStoreError::Primary)
+    }
+
+    async fn count_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<usize, Self::Error> {
+        self.primary_store
+            .count_keys_by_prefix(key_prefix)
+            .await
+            .map_err(MirroringStoreError::Primary)
+    }
+}
+
+impl<P, S> WritableKeyValueStore for MirroringStore<P, S>
+where
+    P: WritableKeyValueStore + Send + Sync,
+    S: WritableKeyValueStore + Send + Sync,
+{
+    const MAX_VALUE_SIZE: usize = if P::MAX_VALUE_SIZE < S::MAX_VALUE_SIZE {
+        P::MAX_VALUE_SIZE
+    } else {
+        S::MAX_VALUE_SIZE
+    };
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
+        self.primary_store
+            .write_batch(batch.clone())
+            .await
+            .map_err(MirroringStoreError::Primary)?;
+        let result = self.secondary_store.write_batch(batch).await;
+        self.handle_secondary_result(result)
+    }
+
+    async fn write_batch_with_stats(&self, batch: Batch) -> Result<BatchStats, Self::Error> {
+        let stats = self
+            .primary_store
+            .write_batch_with_stats(batch.clone())
+            .await
+            .map_err(MirroringStoreError::Primary)?;
+        let result = self.secondary_store.write_batch(batch).await;
+        self.handle_secondary_result(result)?;
+        Ok(stats)
+    }
+
+    async fn clear_journal(&self) -> Result<(), Self::Error> {
+        self.primary_store
+            .clear_journal()
+            .await
+            .map_err(MirroringStoreError::Primary)?;
+        let result = self.secondary_store.clear_journal().await;
+        self.handle_secondary_result(result)
+    }
+
+    /// The comparison only involves the primary store. If it succeeds, the new value is
+    /// written to the secondary store.
+    async fn compare_and_put(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, ViewError> {
+        if !self
+            .primary_store
+            .compare_and_put(key, expected, new)
+            .await?
+        {
+            return Ok(false);
+        }
+        let mut batch = Batch::new();
+        match new {
+            Some(value) => batch.put_key_value_bytes(key.to_vec(), value.to_vec()),
+            None => batch.delete_key(key.to_vec()),
+        }
+        let result = self.secondary_store.write_batch(batch).await;
+        self.handle_secondary_result(result)?;
+        Ok(true)
+    }
+}
+
+impl<P, S> AdminKeyValueStore for MirroringStore<P, S>
+where
+    P: AdminKeyValueStore + Send + Sync,
+    S: AdminKeyValueStore + Send + Sync,
+{
+    type Config = MirroringStoreConfig<P::Config, S::Config>;
+
+    fn get_name() -> String {
+        format!("mirroring {} into {}", P::get_name(), S::get_name())
+    }
+
+    async fn connect(
+        config: &Self::Config,
+        namespace: &str,
+        root_key: &[u8],
+    ) -> Result<Self, Self::Error> {
+        let primary_store = P::connect(&config.primary_config, namespace, root_key)
+            .await
+            .map_err(MirroringStoreError::Primary)?;
+        let secondary_store = S::connect(&config.secondary_config, namespace, root_key)
+            .await
+            .map_err(MirroringStoreError::Secondary)?;
+        Ok(Self::new(
+            primary_store,
+            secondary_store,
+            config.secondary_failure_policy,
+        ))
+    }
+
+    fn clone_with_root_key(&self, root_key: &[u8]) -> Result<Self, Self::Error> {
+        let primary_store = self
+            .primary_store
+            .clone_with_root_key(root_key)
+            .map_err(MirroringStoreError::Primary)?;
+        let secondary_store = self
+            .secondary_store
+            .clone_with_root_key(root_key)
+            .map_err(MirroringStoreError::Secondary)?;
+        Ok(Self::new(
+            primary_store,
+            secondary_store,
+            self.secondary_failure_policy,
+        ))
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, Self::Error> {
+        P::list_all(&config.primary_config)
+            .await
+            .map_err(MirroringStoreError::Primary)
+    }
+
+    async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, Self::Error> {
+        P::exists(&config.primary_config, namespace)
+            .await
+            .map_err(MirroringStoreError::Primary)
+    }
+
+    async fn create(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        P::create(&config.primary_config, namespace)
+            .await
+            .map_err(MirroringStoreError::Primary)?;
+        S::create(&config.secondary_config, namespace)
+            .await
+            .map_err(MirroringStoreError::Secondary)?;
+        Ok(())
+    }
+
+    async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        P::delete(&config.primary_config, namespace)
+            .await
+            .map_err(MirroringStoreError::Primary)?;
+        S::delete(&config.secondary_config, namespace)
+            .await
+            .map_err(MirroringStoreError::Secondary)?;
+        Ok(())
+    }
+}
+
+#[cfg(with_testing)]
+impl<P, S> TestKeyValueStore for MirroringStore<P, S>
+where
+    P: TestKeyValueStore + Send + Sync,
+    S: TestKeyValueStore + Send + Sync,
+{
+    async fn new_test_config() -> Result<Self::Config, Self::Error> {
+        let primary_config = P::new_test_config()
+            .await
+            .map_err(MirroringStoreError::Primary)?;
+        let secondary_config = S::new_test_config()
+            .await
+            .map_err(MirroringStoreError::Secondary)?;
+        Ok(MirroringStoreConfig {
+            primary_config,
+            secondary_config,
+            secondary_failure_policy: SecondaryFailurePolicy::Fatal,
+        })
+    }
+}
+
+/// The error type for [`MirroringStore`].
+#[derive(Error, Debug)]
+pub enum MirroringStoreError<E1, E2> {
+    /// Serialization error with BCS.
+    #[error(transparent)]
+    BcsError(#[from] bcs::Error),
+
+    /// Primary store.
+    #[error("Error in primary store: {0}")]
+    Primary(E1),
+
+    /// Secondary store.
+    #[error("Error in secondary store: {0}")]
+    Secondary(E2),
+}
+
+impl<E1, E2> KeyValueStoreError for MirroringStoreError<E1, E2>
+where
+    E1: KeyValueStoreError,
+    E2: KeyValueStoreError,
+{
+    const BACKEND: &'static str = "mirroring_store";
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            MirroringStoreError::BcsError(_) => false,
+            MirroringStoreError::Primary(error) => error.is_retryable(),
+            MirroringStoreError::Secondary(error) => error.is_retryable(),
+        }
+    }
+}
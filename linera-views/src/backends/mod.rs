@@ -6,14 +6,28 @@ pub mod journaling;
 #[cfg(with_metrics)]
 pub mod metering;
 
+pub mod counting;
+
 pub mod value_splitting;
 
+#[cfg(with_encryption)]
+pub mod encrypted;
+
+pub mod read_only;
+
+pub mod snapshot;
+
 pub mod memory;
 
 pub mod lru_caching;
 
 pub mod dual;
 
+pub mod mirroring;
+
+#[cfg(with_testing)]
+pub mod faulty;
+
 #[cfg(with_scylladb)]
 pub mod scylla_db;
 
@@ -23,5 +37,14 @@ pub mod rocks_db;
 #[cfg(with_dynamodb)]
 pub mod dynamo_db;
 
+#[cfg(with_s3)]
+pub mod s3;
+
+#[cfg(with_fs)]
+pub mod fs_store;
+
+#[cfg(any(with_dynamodb, with_s3))]
+pub mod localstack;
+
 #[cfg(with_indexeddb)]
 pub mod indexed_db;
@@ -0,0 +1,272 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Restricts a given store to reads, for instance to inspect a live database with the
+//! guarantee that nothing is written to it.
+
+use futures::StreamExt as _;
+use thiserror::Error;
+
+use crate::{
+    batch::Batch,
+    store::{
+        AdminKeyValueStore, KeyIterable as _, KeyValueIterable as _, KeyValueStoreError, Order,
+        ReadableKeyValueStore, ResultStream, WithError, WritableKeyValueStore,
+    },
+    views::ViewError,
+};
+
+/// The composed error type built from the inner error type.
+#[derive(Error, Debug)]
+pub enum ReadOnlyStoreError<E> {
+    /// inner store error
+    #[error(transparent)]
+    InnerStoreError(#[from] E),
+
+    /// A write was attempted on a read-only store.
+    #[error("the store is read-only and cannot be written to")]
+    WriteForbidden,
+}
+
+impl<E: KeyValueStoreError> From<bcs::Error> for ReadOnlyStoreError<E> {
+    fn from(error: bcs::Error) -> Self {
+        let error = E::from(error);
+        ReadOnlyStoreError::InnerStoreError(error)
+    }
+}
+
+impl<E: KeyValueStoreError + 'static> KeyValueStoreError for ReadOnlyStoreError<E> {
+    const BACKEND: &'static str = "read_only";
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            ReadOnlyStoreError::InnerStoreError(error) => error.is_retryable(),
+            ReadOnlyStoreError::WriteForbidden => false,
+        }
+    }
+}
+
+/// A key-value store delegating the reads to an inner store and rejecting every write
+/// with [`ReadOnlyStoreError::WriteForbidden`], even for an empty batch.
+///
+/// The journal of the inner store is not resolved by `clear_journal`, since this would
+/// require writing to it. A pending journal is left to the writers of the database.
+#[derive(Clone)]
+pub struct ReadOnlyStore<K> {
+    /// The underlying store.
+    store: K,
+}
+
+impl<K> ReadOnlyStore<K> {
+    /// Creates a read-only view of the given store.
+    pub fn new(store: K) -> Self {
+        Self { store }
+    }
+
+    /// Returns the inner store, which is not restricted to reads.
+    pub fn into_inner(self) -> K {
+        self.store
+    }
+}
+
+impl<K> WithError for ReadOnlyStore<K>
+where
+    K: WithError,
+    K::Error: 'static,
+{
+    type Error = ReadOnlyStoreError<K::Error>;
+}
+
+impl<K> ReadableKeyValueStore for ReadOnlyStore<K>
+where
+    K: ReadableKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    const MAX_KEY_SIZE: usize = K::MAX_KEY_SIZE;
+    type Keys = Vec<Vec<u8>>;
+    type KeyValues = Vec<(Vec<u8>, Vec<u8>)>;
+
+    fn max_stream_queries(&self) -> usize {
+        self.store.max_stream_queries()
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self.store.read_value_bytes(key).await?)
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        Ok(self.store.contains_key(key).await?)
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, Self::Error> {
+        Ok(self.store.contains_keys(keys).await?)
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        Ok(self.store.read_multi_values_bytes(keys).await?)
+    }
+
+    async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Self::Keys, Self::Error> {
+        let keys = self.store.find_keys_by_prefix(key_prefix).await?;
+        let mut result = Vec::new();
+        for key in keys.iterator() {
+            result.push(key?.to_vec());
+        }
+        Ok(result)
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Self::KeyValues, Self::Error> {
+        let key_values = self.store.find_key_values_by_prefix(key_prefix).await?;
+        let mut result = Vec::new();
+        for key_value in key_values.into_iterator_owned() {
+            result.push(key_value?);
+        }
+        Ok(result)
+    }
+
+    async fn find_keys_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        Ok(self
+            .store
+            .find_keys_by_prefix_with_options(key_prefix, order)
+            .await?)
+    }
+
+    async fn find_key_values_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        Ok(self
+            .store
+            .find_key_values_by_prefix_with_options(key_prefix, order)
+            .await?)
+    }
+
+    async fn find_keys_by_prefix_paginated(
+        &self,
+        key_prefix: &[u8],
+        limit: usize,
+        continuation: Option<Vec<u8>>,
+    ) -> Result<(Vec<Vec<u8>>, Option<Vec<u8>>), Self::Error> {
+        Ok(self
+            .store
+            .find_keys_by_prefix_paginated(key_prefix, limit, continuation)
+            .await?)
+    }
+
+    async fn find_keys_in_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        Ok(self.store.find_keys_in_range(start, end).await?)
+    }
+
+    async fn count_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<usize, Self::Error> {
+        Ok(self.store.count_keys_by_prefix(key_prefix).await?)
+    }
+
+    async fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, Vec<u8>, Self::Error>, Self::Error> {
+        let keys = self.store.find_keys_by_prefix_stream(key_prefix).await?;
+        Ok(Box::pin(keys.map(|key| {
+            key.map_err(ReadOnlyStoreError::InnerStoreError)
+        })))
+    }
+
+    async fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error>, Self::Error> {
+        let key_values = self
+            .store
+            .find_key_values_by_prefix_stream(key_prefix)
+            .await?;
+        Ok(Box::pin(key_values.map(|key_value| {
+            key_value.map_err(ReadOnlyStoreError::InnerStoreError)
+        })))
+    }
+}
+
+impl<K> WritableKeyValueStore for ReadOnlyStore<K>
+where
+    K: WritableKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    const MAX_VALUE_SIZE: usize = K::MAX_VALUE_SIZE;
+
+    async fn write_batch(&self, _batch: Batch) -> Result<(), Self::Error> {
+        Err(ReadOnlyStoreError::WriteForbidden)
+    }
+
+    async fn clear_journal(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn compare_and_put(
+        &self,
+        _key: &[u8],
+        _expected: Option<&[u8]>,
+        _new: Option<&[u8]>,
+    ) -> Result<bool, ViewError> {
+        Err(ReadOnlyStoreError::<K::Error>::WriteForbidden.into())
+    }
+}
+
+impl<K> AdminKeyValueStore for ReadOnlyStore<K>
+where
+    K: AdminKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    type Config = K::Config;
+
+    fn get_name() -> String {
+        format!("read-only {}", K::get_name())
+    }
+
+    async fn connect(
+        config: &Self::Config,
+        namespace: &str,
+        root_key: &[u8],
+    ) -> Result<Self, Self::Error> {
+        let store = K::connect(config, namespace, root_key).await?;
+        Ok(Self::new(store))
+    }
+
+    fn clone_with_root_key(&self, root_key: &[u8]) -> Result<Self, Self::Error> {
+        let store = self.store.clone_with_root_key(root_key)?;
+        Ok(Self::new(store))
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, Self::Error> {
+        Ok(K::list_all(config).await?)
+    }
+
+    async fn delete_all(_config: &Self::Config) -> Result<(), Self::Error> {
+        Err(ReadOnlyStoreError::WriteForbidden)
+    }
+
+    async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, Self::Error> {
+        Ok(K::exists(config, namespace).await?)
+    }
+
+    async fn create(_config: &Self::Config, _namespace: &str) -> Result<(), Self::Error> {
+        Err(ReadOnlyStoreError::WriteForbidden)
+    }
+
+    async fn delete(_config: &Self::Config, _namespace: &str) -> Result<(), Self::Error> {
+        Err(ReadOnlyStoreError::WriteForbidden)
+    }
+}
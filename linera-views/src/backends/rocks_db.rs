@@ -4,9 +4,10 @@
 //! Implements [`crate::store::KeyValueStore`] for the RocksDB database.
 
 use std::{
+    collections::BTreeSet,
     ffi::OsString,
     ops::{Bound, Bound::Excluded},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
@@ -85,6 +86,147 @@ impl RocksDbSpawnMode {
     }
 }
 
+/// The options of the writes of the batches to RocksDB. A batch is always written
+/// atomically; the options only determine whether it survives a crash.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RocksDbWriteOptions {
+    /// Whether the write-ahead log is synced to disk before a write returns, so that the
+    /// batch survives a crash of the machine and not only of the process.
+    pub sync: bool,
+    /// Whether the write-ahead log is skipped. The writes are faster, but the batches
+    /// that are not flushed from the memtables yet are lost on a crash.
+    pub disable_wal: bool,
+}
+
+impl RocksDbWriteOptions {
+    fn to_rocksdb(self) -> rocksdb::WriteOptions {
+        let mut options = rocksdb::WriteOptions::default();
+        options.set_sync(self.sync);
+        options.disable_wal(self.disable_wal);
+        options
+    }
+}
+
+/// How the RocksDB database is opened. The read-only and secondary modes allow to
+/// inspect the database of a running process, which holds the exclusive read-write
+/// access. Writing a batch in these modes fails with
+/// [`RocksDbStoreInternalError::ReadOnly`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum RocksDbAccessMode {
+    /// The database is opened for reading and writing, and created if missing.
+    #[default]
+    ReadWrite,
+    /// The database is opened for reading the state it had when it was opened.
+    ReadOnly,
+    /// The database is opened as a secondary instance, which follows the writes of the
+    /// primary one with [`RocksDbStoreInternal::try_catch_up_with_primary`].
+    Secondary {
+        /// The directory where the secondary instance keeps its own logs.
+        secondary_path: PathBuf,
+    },
+}
+
+/// The tuning of the RocksDB database, applied when it is opened.
+///
+/// With a `prefix_length`, RocksDB extracts the first bytes of the keys, including the
+/// root key, to build prefix bloom filters, so that the prefix scans only visit the
+/// relevant data blocks. The scans with shorter prefixes fall back to a total-order
+/// iteration. The default configuration is the one of the databases created before
+/// these options existed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RocksDbConfig {
+    /// The length of the fixed prefixes extracted from the keys, if any.
+    pub prefix_length: Option<usize>,
+    /// The number of bits per key of the bloom filters of the block-based tables, if any.
+    pub bloom_filter_bits_per_key: Option<f64>,
+    /// The ratio of the size of the write buffer used by the prefix bloom filter of the
+    /// memtables. It is only used with a `prefix_length`.
+    pub memtable_prefix_bloom_ratio: f64,
+    /// Whether RocksDB collects the statistics of its operations, such as the hits of
+    /// the block cache reported by [`RocksDbStoreInternal::statistics`].
+    pub enable_statistics: bool,
+}
+
+impl RocksDbConfig {
+    fn to_rocksdb(self) -> rocksdb::Options {
+        let mut options = rocksdb::Options::default();
+        if let Some(prefix_length) = self.prefix_length {
+            options
+                .set_prefix_extractor(rocksdb::SliceTransform::create_fixed_prefix(prefix_length));
+            options.set_memtable_prefix_bloom_ratio(self.memtable_prefix_bloom_ratio);
+        }
+        if let Some(bits_per_key) = self.bloom_filter_bits_per_key {
+            let mut table_options = rocksdb::BlockBasedOptions::default();
+            table_options.set_bloom_filter(bits_per_key, false);
+            options.set_block_based_table_factory(&table_options);
+        }
+        if self.enable_statistics {
+            options.enable_statistics();
+        }
+        options
+    }
+}
+
+/// The description of a backup of a RocksDB database.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RocksDbBackupInfo {
+    /// The identifier of the backup in its directory.
+    pub backup_id: u32,
+    /// The time of the backup, in seconds since the Unix epoch.
+    pub timestamp: i64,
+    /// The number of files of the backup.
+    pub num_files: u32,
+    /// The total size of the files of the backup, in bytes.
+    pub size: u64,
+}
+
+impl From<rocksdb::backup::BackupEngineInfo> for RocksDbBackupInfo {
+    fn from(info: rocksdb::backup::BackupEngineInfo) -> Self {
+        RocksDbBackupInfo {
+            backup_id: info.backup_id,
+            timestamp: info.timestamp,
+            num_files: info.num_files,
+            size: info.size,
+        }
+    }
+}
+
+/// The statistics of a RocksDB database, e.g. to tell whether its compactions keep up
+/// with the writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RocksDbStatistics {
+    /// The number of live SST files.
+    pub num_live_sst_files: usize,
+    /// The total size of the live SST files, in bytes.
+    pub total_sst_files_size: u64,
+    /// The estimated number of keys of the database.
+    pub estimated_num_keys: u64,
+    /// The estimated number of bytes that compactions need to rewrite to bring the
+    /// levels of the database under their target sizes.
+    pub pending_compaction_bytes: u64,
+    /// The ratio of the block reads served by the block cache, if the statistics are
+    /// enabled and blocks were read.
+    pub block_cache_hit_rate: Option<f64>,
+}
+
+/// Extracts the count of a ticker from the statistics dump of RocksDB, whose lines are
+/// of the form `rocksdb.block.cache.hit COUNT : 42`.
+fn ticker_count(statistics: &str, ticker: &str) -> Option<u64> {
+    statistics.lines().find_map(|line| {
+        let count = line.strip_prefix(ticker)?.trim().strip_prefix("COUNT :")?;
+        count.trim().parse().ok()
+    })
+}
+
+fn open_backup_engine(
+    backup_dir: &Path,
+) -> Result<rocksdb::backup::BackupEngine, RocksDbStoreInternalError> {
+    let options = rocksdb::backup::BackupEngineOptions::new(backup_dir)
+        .map_err(RocksDbStoreInternalError::Backup)?;
+    let env = rocksdb::Env::new().map_err(RocksDbStoreInternalError::Backup)?;
+    rocksdb::backup::BackupEngine::open(&options, &env).map_err(RocksDbStoreInternalError::Backup)
+}
+
 fn check_key_size(key: &[u8]) -> Result<(), RocksDbStoreInternalError> {
     ensure!(
         key.len() <= MAX_KEY_SIZE,
@@ -97,6 +239,14 @@ fn check_key_size(key: &[u8]) -> Result<(), RocksDbStoreInternalError> {
 struct RocksDbStoreExecutor {
     db: Arc<DB>,
     root_key: Vec<u8>,
+    write_options: RocksDbWriteOptions,
+    prefix_length: Option<usize>,
+    read_only: bool,
+    options: Arc<rocksdb::Options>,
+    /// Called with the index of every operation once it is added to the native write
+    /// batch, see [`RocksDbStoreConfig::with_write_hook`].
+    #[cfg(with_testing)]
+    write_hook: Option<fn(usize)>,
 }
 
 impl RocksDbStoreExecutor {
@@ -151,7 +301,7 @@ impl RocksDbStoreExecutor {
         let mut prefix = self.root_key.clone();
         prefix.extend(key_prefix);
         let len = prefix.len();
-        let mut iter = self.db.raw_iterator();
+        let mut iter = self.prefix_iterator(&prefix);
         let mut keys = Vec::new();
         iter.seek(&prefix);
         let mut next_key = iter.key();
@@ -175,7 +325,7 @@ impl RocksDbStoreExecutor {
         let mut prefix = self.root_key.clone();
         prefix.extend(key_prefix);
         let len = prefix.len();
-        let mut iter = self.db.raw_iterator();
+        let mut iter = self.prefix_iterator(&prefix);
         let mut key_values = Vec::new();
         iter.seek(&prefix);
         let mut next_key = iter.key();
@@ -193,58 +343,130 @@ impl RocksDbStoreExecutor {
         Ok(key_values)
     }
 
-    fn write_batch_internal(&self, mut batch: Batch) -> Result<(), RocksDbStoreInternalError> {
+    fn write_batch_internal(&self, batch: Batch) -> Result<(), RocksDbStoreInternalError> {
+        ensure!(!self.read_only, RocksDbStoreInternalError::ReadOnly);
         // NOTE: The delete_range functionality of RocksDB needs to have an upper bound in order to work.
         // Thus in order to have the system working, we need to handle the unlikely case of having to
-        // delete a key starting with [255, ...., 255]
-        let len = batch.operations.len();
-        let mut keys = Vec::new();
-        for i in 0..len {
-            let op = batch.operations.get(i).unwrap();
-            if let WriteOperation::DeletePrefix { key_prefix } = op {
-                if get_upper_bound(key_prefix) == Bound::Unbounded {
-                    for short_key in self.find_keys_by_prefix_internal(key_prefix.to_vec())? {
-                        let mut full_key = self.root_key.clone();
-                        full_key.extend(key_prefix);
-                        full_key.extend(short_key);
-                        keys.push(full_key);
-                    }
-                }
-            }
-        }
-        for key in keys {
-            batch.operations.push(WriteOperation::Delete { key });
-        }
+        // delete a key starting with [255, ...., 255] by deleting its keys one by one, including the
+        // ones put earlier in the batch.
+        let has_unbounded_prefix = batch.operations.iter().any(|operation| {
+            matches!(operation, WriteOperation::DeletePrefix { key_prefix }
+                if get_upper_bound(&self.full_key(key_prefix)) == Bound::Unbounded)
+        });
+        let mut put_keys = BTreeSet::new();
         let mut inner_batch = rocksdb::WriteBatchWithTransaction::default();
-        for operation in batch.operations {
+        for (_index, operation) in batch.operations.into_iter().enumerate() {
             match operation {
                 WriteOperation::Delete { key } => {
                     check_key_size(&key)?;
-                    let mut full_key = self.root_key.to_vec();
-                    full_key.extend(key);
-                    inner_batch.delete(&full_key)
+                    let full_key = self.full_key(&key);
+                    inner_batch.delete(&full_key);
+                    if has_unbounded_prefix {
+                        put_keys.remove(&full_key);
+                    }
                 }
                 WriteOperation::Put { key, value } => {
                     check_key_size(&key)?;
-                    let mut full_key = self.root_key.to_vec();
-                    full_key.extend(key);
-                    inner_batch.put(&full_key, value)
+                    let full_key = self.full_key(&key);
+                    inner_batch.put(&full_key, value);
+                    if has_unbounded_prefix {
+                        put_keys.insert(full_key);
+                    }
                 }
                 WriteOperation::DeletePrefix { key_prefix } => {
                     check_key_size(&key_prefix)?;
-                    if let Excluded(upper_bound) = get_upper_bound(&key_prefix) {
-                        let mut full_key1 = self.root_key.to_vec();
-                        full_key1.extend(key_prefix);
-                        let mut full_key2 = self.root_key.to_vec();
-                        full_key2.extend(upper_bound);
-                        inner_batch.delete_range(&full_key1, &full_key2);
+                    let full_prefix = self.full_key(&key_prefix);
+                    if let Excluded(upper_bound) = get_upper_bound(&full_prefix) {
+                        inner_batch.delete_range(&full_prefix, &upper_bound);
+                        if has_unbounded_prefix {
+                            put_keys.retain(|full_key| !full_key.starts_with(&full_prefix));
+                        }
+                    } else {
+                        // The keys following a prefix made of 255 bytes all start with it.
+                        for short_key in self.find_keys_by_prefix_internal(key_prefix)? {
+                            inner_batch.delete([&full_prefix[..], &short_key].concat());
+                        }
+                        for full_key in put_keys.split_off(&full_prefix) {
+                            inner_batch.delete(full_key);
+                        }
                     }
                 }
+                WriteOperation::DeleteRange { start, end } => {
+                    check_key_size(&start)?;
+                    check_key_size(&end)?;
+                    if start < end {
+                        let full_start = self.full_key(&start);
+                        let full_end = self.full_key(&end);
+                        inner_batch.delete_range(&full_start, &full_end);
+                        if has_unbounded_prefix {
+                            put_keys
+                                .retain(|full_key| full_key < &full_start || full_key >= &full_end);
+                        }
+                    }
+                }
+            }
+            #[cfg(with_testing)]
+            if let Some(write_hook) = self.write_hook {
+                write_hook(_index);
             }
         }
-        self.db.write(inner_batch)?;
+        // The whole batch is applied atomically by RocksDB.
+        self.db
+            .write_opt(inner_batch, &self.write_options.to_rocksdb())?;
         Ok(())
     }
+
+    /// Creates an iterator for the scan of the keys starting with `full_prefix`. The
+    /// prefix bloom filters can only be used if the prefix is at least as long as the
+    /// extracted ones. The keys must still be compared to the prefix.
+    fn prefix_iterator(&self, full_prefix: &[u8]) -> rocksdb::DBRawIteratorWithThreadMode<'_, DB> {
+        let mut read_options = rocksdb::ReadOptions::default();
+        match self.prefix_length {
+            Some(prefix_length) if full_prefix.len() >= prefix_length => {
+                read_options.set_prefix_same_as_start(true);
+            }
+            Some(_) => read_options.set_total_order_seek(true),
+            None => {}
+        }
+        self.db.raw_iterator_opt(read_options)
+    }
+
+    fn statistics_internal(&self) -> Result<RocksDbStatistics, RocksDbStoreInternalError> {
+        let property = |name: &str| -> Result<u64, RocksDbStoreInternalError> {
+            Ok(self.db.property_int_value(name)?.unwrap_or(0))
+        };
+        let block_cache_hit_rate = self.options.get_statistics().and_then(|statistics| {
+            let hits = ticker_count(&statistics, "rocksdb.block.cache.hit")?;
+            let misses = ticker_count(&statistics, "rocksdb.block.cache.miss")?;
+            (hits + misses > 0).then_some(hits as f64 / (hits + misses) as f64)
+        });
+        Ok(RocksDbStatistics {
+            num_live_sst_files: self.db.live_files()?.len(),
+            total_sst_files_size: property("rocksdb.total-sst-files-size")?,
+            estimated_num_keys: property("rocksdb.estimate-num-keys")?,
+            pending_compaction_bytes: property("rocksdb.estimate-pending-compaction-bytes")?,
+            block_cache_hit_rate,
+        })
+    }
+
+    fn compact_prefix_internal(
+        &self,
+        key_prefix: Vec<u8>,
+    ) -> Result<(), RocksDbStoreInternalError> {
+        check_key_size(&key_prefix)?;
+        let full_prefix = self.full_key(&key_prefix);
+        match get_upper_bound(&full_prefix) {
+            Excluded(upper_bound) => self.db.compact_range(Some(full_prefix), Some(upper_bound)),
+            _ => self.db.compact_range(Some(full_prefix), None::<Vec<u8>>),
+        }
+        Ok(())
+    }
+
+    fn full_key(&self, key: &[u8]) -> Vec<u8> {
+        let mut full_key = self.root_key.clone();
+        full_key.extend_from_slice(key);
+        full_key
+    }
 }
 
 /// The inner client
@@ -263,8 +485,17 @@ pub struct RocksDbStoreInternalConfig {
     path_with_guard: PathWithGuard,
     /// The spawn_mode that is chosen
     spawn_mode: RocksDbSpawnMode,
+    /// The options of the writes
+    write_options: RocksDbWriteOptions,
+    /// The tuning of the database
+    db_config: RocksDbConfig,
+    /// How the database is opened
+    access_mode: RocksDbAccessMode,
     /// The common configuration of the key value store
     common_config: CommonStoreInternalConfig,
+    /// The hook called while the write batches are built
+    #[cfg(with_testing)]
+    write_hook: Option<fn(usize)>,
 }
 
 impl RocksDbStoreInternal {
@@ -281,20 +512,40 @@ impl RocksDbStoreInternal {
     fn build(
         path_with_guard: PathWithGuard,
         spawn_mode: RocksDbSpawnMode,
+        write_options: RocksDbWriteOptions,
+        db_config: RocksDbConfig,
+        access_mode: &RocksDbAccessMode,
+        namespace: &str,
         max_stream_queries: usize,
         root_key: &[u8],
     ) -> Result<RocksDbStoreInternal, RocksDbStoreInternalError> {
         let path = path_with_guard.path_buf.clone();
-        if !std::path::Path::exists(&path) {
-            std::fs::create_dir(path.clone())?;
-        }
-        let mut options = rocksdb::Options::default();
-        options.create_if_missing(true);
-        let db = DB::open(&options, path)?;
+        let mut options = db_config.to_rocksdb();
+        let db = match access_mode {
+            RocksDbAccessMode::ReadWrite => {
+                if !std::path::Path::exists(&path) {
+                    std::fs::create_dir(path.clone())?;
+                }
+                options.create_if_missing(true);
+                DB::open(&options, path)?
+            }
+            RocksDbAccessMode::ReadOnly => DB::open_for_read_only(&options, path, false)?,
+            RocksDbAccessMode::Secondary { secondary_path } => {
+                // The secondary instance must keep all the files open to follow the primary.
+                options.set_max_open_files(-1);
+                DB::open_as_secondary(&options, &path, &secondary_path.join(namespace))?
+            }
+        };
         let root_key = root_key.to_vec();
         let executor = RocksDbStoreExecutor {
             db: Arc::new(db),
             root_key,
+            write_options,
+            prefix_length: db_config.prefix_length,
+            read_only: *access_mode != RocksDbAccessMode::ReadWrite,
+            options: Arc::new(options),
+            #[cfg(with_testing)]
+            write_hook: None,
         };
         Ok(RocksDbStoreInternal {
             executor,
@@ -303,6 +554,107 @@ impl RocksDbStoreInternal {
             spawn_mode,
         })
     }
+
+    /// Applies the writes of the primary instance that happened since the last catch-up
+    /// to a database opened in [`RocksDbAccessMode::Secondary`] mode. For the other modes,
+    /// this fails with a RocksDB error.
+    pub async fn try_catch_up_with_primary(&self) -> Result<(), RocksDbStoreInternalError> {
+        let db = self.executor.db.clone();
+        self.spawn_mode
+            .spawn(move |()| Ok(db.try_catch_up_with_primary()?), ())
+            .await
+    }
+}
+
+impl RocksDbStoreInternal {
+    /// Creates a new backup of the database in `backup_dir`, while the database remains
+    /// usable. Only the files that are not already in the directory are copied.
+    pub async fn create_backup(
+        &self,
+        backup_dir: &Path,
+    ) -> Result<RocksDbBackupInfo, RocksDbStoreInternalError> {
+        let db = self.executor.db.clone();
+        self.spawn_mode
+            .spawn(
+                move |backup_dir: PathBuf| {
+                    let mut engine = open_backup_engine(&backup_dir)?;
+                    engine
+                        .create_new_backup_flush(&*db, true)
+                        .map_err(RocksDbStoreInternalError::Backup)?;
+                    let info = engine.get_backup_info().pop();
+                    Ok(info
+                        .ok_or(RocksDbStoreInternalError::NoBackup(backup_dir))?
+                        .into())
+                },
+                backup_dir.to_path_buf(),
+            )
+            .await
+    }
+
+    /// Returns the statistics of the database, see [`RocksDbStatistics`].
+    pub async fn statistics(&self) -> Result<RocksDbStatistics, RocksDbStoreInternalError> {
+        let executor = self.executor.clone();
+        self.spawn_mode
+            .spawn(move |()| executor.statistics_internal(), ())
+            .await
+    }
+
+    /// Compacts the keys of the root key of the store starting with `key_prefix`, e.g. to
+    /// reclaim the space of many deleted keys.
+    pub async fn trigger_compaction(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<(), RocksDbStoreInternalError> {
+        let executor = self.executor.clone();
+        self.spawn_mode
+            .spawn(
+                move |x| executor.compact_prefix_internal(x),
+                key_prefix.to_vec(),
+            )
+            .await
+    }
+
+    /// Lists the backups of `backup_dir`, from the oldest to the latest.
+    pub fn list_backups(
+        backup_dir: &Path,
+    ) -> Result<Vec<RocksDbBackupInfo>, RocksDbStoreInternalError> {
+        let engine = open_backup_engine(backup_dir)?;
+        Ok(engine
+            .get_backup_info()
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Restores the latest backup of `backup_dir` as the database of `target_dir`, which
+    /// must not be in use. The database can then be opened as a namespace of the parent
+    /// directory of `target_dir`.
+    pub fn restore_latest(
+        backup_dir: &Path,
+        target_dir: &Path,
+    ) -> Result<RocksDbBackupInfo, RocksDbStoreInternalError> {
+        let mut engine = open_backup_engine(backup_dir)?;
+        let info = engine
+            .get_backup_info()
+            .pop()
+            .ok_or_else(|| RocksDbStoreInternalError::NoBackup(backup_dir.to_path_buf()))?;
+        let options = rocksdb::backup::RestoreOptions::default();
+        engine
+            .restore_from_latest_backup(target_dir, target_dir, &options)
+            .map_err(RocksDbStoreInternalError::Backup)?;
+        Ok(info.into())
+    }
+
+    /// Deletes the backups of `backup_dir` except the `keep` latest ones.
+    pub fn purge_old_backups(
+        backup_dir: &Path,
+        keep: usize,
+    ) -> Result<(), RocksDbStoreInternalError> {
+        let mut engine = open_backup_engine(backup_dir)?;
+        engine
+            .purge_old_backups(keep)
+            .map_err(RocksDbStoreInternalError::Backup)
+    }
 }
 
 impl WithError for RocksDbStoreInternal {
@@ -431,8 +783,22 @@ impl AdminKeyValueStore for RocksDbStoreInternal {
         path_buf.push(namespace);
         path_with_guard.path_buf = path_buf;
         let max_stream_queries = config.common_config.max_stream_queries;
-        let spawn_mode = config.spawn_mode;
-        RocksDbStoreInternal::build(path_with_guard, spawn_mode, max_stream_queries, root_key)
+        #[allow(unused_mut)]
+        let mut store = RocksDbStoreInternal::build(
+            path_with_guard,
+            config.spawn_mode,
+            config.write_options,
+            config.db_config,
+            &config.access_mode,
+            namespace,
+            max_stream_queries,
+            root_key,
+        )?;
+        #[cfg(with_testing)]
+        {
+            store.executor.write_hook = config.write_hook;
+        }
+        Ok(store)
     }
 
     fn clone_with_root_key(&self, root_key: &[u8]) -> Result<Self, RocksDbStoreInternalError> {
@@ -517,7 +883,11 @@ impl TestKeyValueStore for RocksDbStoreInternal {
         Ok(RocksDbStoreInternalConfig {
             path_with_guard,
             spawn_mode,
+            write_options: RocksDbWriteOptions::default(),
+            db_config: RocksDbConfig::default(),
+            access_mode: RocksDbAccessMode::ReadWrite,
             common_config,
+            write_hook: None,
         })
     }
 }
@@ -545,6 +915,18 @@ pub enum RocksDbStoreInternalError {
     #[error("The key must have at most 8M")]
     KeyTooLong,
 
+    /// An operation of the backup engine failed.
+    #[error("RocksDB backup error: {0}")]
+    Backup(rocksdb::Error),
+
+    /// The backup directory contains no backup.
+    #[error("No backup found in {0:?}")]
+    NoBackup(PathBuf),
+
+    /// The database is opened in read-only or secondary mode
+    #[error("The RocksDB database is opened in read-only mode")]
+    ReadOnly,
+
     /// Namespace contains forbidden characters
     #[error("Namespace contains forbidden characters")]
     InvalidNamespace,
@@ -616,11 +998,87 @@ impl RocksDbStoreConfig {
         let inner_config = RocksDbStoreInternalConfig {
             path_with_guard,
             spawn_mode,
+            write_options: RocksDbWriteOptions::default(),
+            db_config: RocksDbConfig::default(),
+            access_mode: RocksDbAccessMode::ReadWrite,
             common_config: common_config.reduced(),
+            #[cfg(with_testing)]
+            write_hook: None,
         };
         RocksDbStoreConfig {
             inner_config,
             cache_size: common_config.cache_size,
+            max_cache_bytes: None,
+        }
+    }
+
+    /// Sets the options of the writes of the batches, see [`RocksDbWriteOptions`].
+    pub fn with_write_options(mut self, write_options: RocksDbWriteOptions) -> Self {
+        self.inner_config.write_options = write_options;
+        self
+    }
+
+    /// Sets the tuning of the database, see [`RocksDbConfig`].
+    pub fn with_db_config(mut self, db_config: RocksDbConfig) -> Self {
+        self.inner_config.db_config = db_config;
+        self
+    }
+
+    /// Sets how the database is opened, see [`RocksDbAccessMode`]. In secondary mode, the
+    /// LRU cache is disabled since it would not see the writes of the primary instance.
+    pub fn with_access_mode(mut self, access_mode: RocksDbAccessMode) -> Self {
+        if matches!(access_mode, RocksDbAccessMode::Secondary { .. }) {
+            self.cache_size = 0;
         }
+        self.inner_config.access_mode = access_mode;
+        self
+    }
+
+    /// Sets a hook called with the index of every operation of a batch once it is added
+    /// to the native write batch, before the batch is written. Tests panic in it to
+    /// simulate a crash in the middle of a write.
+    #[cfg(with_testing)]
+    pub fn with_write_hook(mut self, write_hook: fn(usize)) -> Self {
+        self.inner_config.write_hook = Some(write_hook);
+        self
+    }
+}
+
+impl RocksDbStore {
+    #[cfg(with_metrics)]
+    fn internal_store(&self) -> &RocksDbStoreInternal {
+        self.inner().inner().inner().inner().inner()
+    }
+
+    #[cfg(not(with_metrics))]
+    fn internal_store(&self) -> &RocksDbStoreInternal {
+        self.inner().inner()
+    }
+
+    /// Applies the writes of the primary instance to a store opened in
+    /// [`RocksDbAccessMode::Secondary`] mode, see
+    /// [`RocksDbStoreInternal::try_catch_up_with_primary`].
+    pub async fn try_catch_up_with_primary(&self) -> Result<(), RocksDbStoreError> {
+        Ok(self.internal_store().try_catch_up_with_primary().await?)
+    }
+
+    /// Creates a new backup of the database in `backup_dir`, see
+    /// [`RocksDbStoreInternal::create_backup`].
+    pub async fn create_backup(
+        &self,
+        backup_dir: &Path,
+    ) -> Result<RocksDbBackupInfo, RocksDbStoreError> {
+        Ok(self.internal_store().create_backup(backup_dir).await?)
+    }
+
+    /// Returns the statistics of the database, see [`RocksDbStoreInternal::statistics`].
+    pub async fn statistics(&self) -> Result<RocksDbStatistics, RocksDbStoreError> {
+        Ok(self.internal_store().statistics().await?)
+    }
+
+    /// Compacts the keys starting with `key_prefix`, see
+    /// [`RocksDbStoreInternal::trigger_compaction`].
+    pub async fn trigger_compaction(&self, key_prefix: &[u8]) -> Result<(), RocksDbStoreError> {
+        Ok(self.internal_store().trigger_compaction(key_prefix).await?)
     }
 }
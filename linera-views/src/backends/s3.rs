@@ -0,0 +1,727 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements [`crate::store::KeyValueStore`] for Amazon S3, e.g. for cold data such as
+//! old certificates and blocks, which are immutable and large.
+//!
+//! Each key is stored as an object of a configurable bucket. The name of the object is
+//! made of the configurable prefix, the namespace, the hexadecimal encoding of the root
+//! key and the one of the key, e.g. `linera/namespace/0a0b/01020304`. Since the hexadecimal
+//! encoding preserves the order of the keys, the prefix searches are listings of the
+//! objects with `ListObjectsV2`.
+//!
+//! Unlike the other backends, the batches are NOT written atomically: their puts and
+//! deletes are issued concurrently as independent requests, and a failed batch can be
+//! partially written. The batches still take effect as if their operations were applied
+//! in order, and the store passes the conformance suites, but it should only be used for
+//! data that can be written again, such as immutable data.
+//!
+//! The tests use [LocalStack](https://localstack.cloud) with the `s3` service, whose
+//! endpoint is read from the `LOCALSTACK_ENDPOINT` environment variable.
+
+use std::{env, sync::Arc};
+
+use async_lock::{Semaphore, SemaphoreGuard};
+use aws_sdk_s3::{
+    error::SdkError,
+    operation::{
+        create_bucket::CreateBucketError, delete_object::DeleteObjectError,
+        get_object::GetObjectError, head_bucket::HeadBucketError, head_object::HeadObjectError,
+        list_objects_v2::ListObjectsV2Error, put_object::PutObjectError,
+    },
+    primitives::{ByteStream, ByteStreamError},
+    Client,
+};
+use futures::{
+    future::{join_all, FutureExt as _},
+    stream::{self, StreamExt as _, TryStreamExt as _},
+};
+use linera_base::ensure;
+use thiserror::Error;
+
+#[cfg(with_metrics)]
+use crate::metering::MeteredStore;
+#[cfg(with_testing)]
+use crate::store::TestKeyValueStore;
+use crate::{
+    batch::{Batch, DeletePrefixExpander, WriteOperation},
+    common::expand_delete_range,
+    context::ViewContext,
+    localstack::LOCALSTACK_ENDPOINT,
+    lru_caching::{LruCachingConfig, LruCachingStore},
+    store::{
+        AdminKeyValueStore, CommonStoreInternalConfig, KeyValueStoreError, ReadableKeyValueStore,
+        WithError, WritableKeyValueStore,
+    },
+};
+
+/// The maximal length of the names of the objects in S3.
+const MAX_OBJECT_NAME_LENGTH: usize = 1024;
+
+/// The maximal size of the keys. Their hexadecimal encoding takes twice as many bytes of
+/// the name of the objects, and the rest is left for the prefix, the namespace and the
+/// root key.
+const MAX_KEY_SIZE: usize = 400;
+
+/// The maximal size of an object written with a single `PutObject` request is 5 GB.
+const MAX_VALUE_SIZE: usize = 5 * 1024 * 1024 * 1024;
+
+/// The name of the object marking the existence of a namespace, which cannot collide
+/// with the hexadecimal names of the other objects.
+const NAMESPACE_MARKER: &str = ".namespace";
+
+/// The maximal number of requests of a batch in flight at the same time.
+const MAX_CONCURRENT_WRITES: usize = 32;
+
+/// The number of concurrent queries for the tests.
+#[cfg(with_testing)]
+const TEST_S3_MAX_CONCURRENT_QUERIES: usize = 10;
+
+/// The number of streams for the tests.
+#[cfg(with_testing)]
+const TEST_S3_MAX_STREAM_QUERIES: usize = 10;
+
+/// The bucket used by the tests.
+#[cfg(with_testing)]
+const TEST_S3_BUCKET: &str = "linera-test";
+
+/// The configuration to connect to S3.
+pub type Config = aws_sdk_s3::Config;
+
+/// Gets the AWS configuration from the environment.
+async fn get_base_config() -> Config {
+    let base_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest())
+        .boxed()
+        .await;
+    (&base_config).into()
+}
+
+/// Gets the configuration of the LocalStack instance of the `LOCALSTACK_ENDPOINT`
+/// environment variable. LocalStack only serves the buckets with path-style addresses.
+async fn get_localstack_config() -> Result<Config, S3StoreInternalError> {
+    let base_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest())
+        .boxed()
+        .await;
+    let endpoint_address =
+        env::var(LOCALSTACK_ENDPOINT).map_err(|_| S3StoreInternalError::LocalStackEndpoint)?;
+    let config = aws_sdk_s3::config::Builder::from(&base_config)
+        .endpoint_url(endpoint_address)
+        .force_path_style(true)
+        .build();
+    Ok(config)
+}
+
+/// Gets the configuration to connect to S3, or to LocalStack if `use_localstack`.
+pub async fn get_config(use_localstack: bool) -> Result<Config, S3StoreInternalError> {
+    if use_localstack {
+        get_localstack_config().await
+    } else {
+        Ok(get_base_config().await)
+    }
+}
+
+fn check_key_size(key: &[u8]) -> Result<(), S3StoreInternalError> {
+    ensure!(key.len() <= MAX_KEY_SIZE, S3StoreInternalError::KeyTooLong);
+    Ok(())
+}
+
+/// The initial configuration of the system.
+#[derive(Clone, Debug)]
+pub struct S3StoreInternalConfig {
+    /// The AWS configuration
+    config: Config,
+    /// The bucket containing the objects
+    bucket: String,
+    /// The prefix of the names of the objects of all the namespaces
+    object_prefix: String,
+    /// The common configuration of the key value store
+    common_config: CommonStoreInternalConfig,
+}
+
+/// A store of the objects of a namespace in an S3 bucket.
+#[derive(Clone, Debug)]
+pub struct S3StoreInternal {
+    client: Client,
+    bucket: String,
+    /// The name of the objects of the namespace, without the root key.
+    namespace_prefix: String,
+    /// The name of the objects of the root key, without the key.
+    base_name: String,
+    semaphore: Option<Arc<Semaphore>>,
+    max_stream_queries: usize,
+}
+
+impl S3StoreInternal {
+    fn check_namespace(namespace: &str) -> Result<(), S3StoreInternalError> {
+        ensure!(
+            !namespace.is_empty()
+                && namespace
+                    .chars()
+                    .all(|character| character.is_ascii_alphanumeric() || character == '_'),
+            S3StoreInternalError::InvalidNamespace
+        );
+        Ok(())
+    }
+
+    fn namespace_prefix(config: &S3StoreInternalConfig, namespace: &str) -> String {
+        format!("{}{}/", config.object_prefix, namespace)
+    }
+
+    fn base_name(namespace_prefix: &str, root_key: &[u8]) -> Result<String, S3StoreInternalError> {
+        let base_name = format!("{}{}/", namespace_prefix, hex::encode(root_key));
+        ensure!(
+            base_name.len() + 2 * MAX_KEY_SIZE <= MAX_OBJECT_NAME_LENGTH,
+            S3StoreInternalError::BaseNameTooLong(base_name)
+        );
+        Ok(base_name)
+    }
+
+    fn object_name(&self, key: &[u8]) -> String {
+        format!("{}{}", self.base_name, hex::encode(key))
+    }
+
+    /// Obtains the semaphore lock on the database if needed.
+    async fn acquire(&self) -> Option<SemaphoreGuard<'_>> {
+        match &self.semaphore {
+            None => None,
+            Some(count) => Some(count.acquire().await),
+        }
+    }
+
+    /// Lists the names of the objects starting with `prefix`, following the pages of
+    /// the results.
+    async fn list_object_names(
+        client: &Client,
+        bucket: &str,
+        prefix: &str,
+    ) -> Result<Vec<String>, S3StoreInternalError> {
+        let mut names = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let response = client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .boxed()
+                .await?;
+            names.extend(
+                response
+                    .contents()
+                    .iter()
+                    .filter_map(|object| object.key().map(str::to_string)),
+            );
+            match response.next_continuation_token() {
+                Some(token) if response.is_truncated() == Some(true) => {
+                    continuation_token = Some(token.to_string());
+                }
+                _ => return Ok(names),
+            }
+        }
+    }
+
+    /// Streams the value of `key`, e.g. for a multi-megabyte object that should not be
+    /// buffered in memory. Returns `None` if the key is missing.
+    pub async fn read_value_stream(
+        &self,
+        key: &[u8],
+    ) -> Result<Option<ByteStream>, S3StoreInternalError> {
+        check_key_size(key)?;
+        let _guard = self.acquire().await;
+        let response = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.object_name(key))
+            .send()
+            .boxed()
+            .await;
+        match response {
+            Ok(output) => Ok(Some(output.body)),
+            Err(SdkError::ServiceError(error)) if error.err().is_no_such_key() => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn put_object(&self, key: Vec<u8>, value: Vec<u8>) -> Result<(), S3StoreInternalError> {
+        let _guard = self.acquire().await;
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(self.object_name(&key))
+            .body(ByteStream::from(value))
+            .send()
+            .boxed()
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_object(&self, key: Vec<u8>) -> Result<(), S3StoreInternalError> {
+        let _guard = self.acquire().await;
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(self.object_name(&key))
+            .send()
+            .boxed()
+            .await?;
+        Ok(())
+    }
+
+    async fn bucket_exists(client: &Client, bucket: &str) -> Result<bool, S3StoreInternalError> {
+        match client.head_bucket().bucket(bucket).send().boxed().await {
+            Ok(_) => Ok(true),
+            Err(SdkError::ServiceError(error)) if error.err().is_not_found() => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+impl WithError for S3StoreInternal {
+    type Error = S3StoreInternalError;
+}
+
+impl ReadableKeyValueStore for S3StoreInternal {
+    const MAX_KEY_SIZE: usize = MAX_KEY_SIZE;
+    type Keys = Vec<Vec<u8>>;
+    type KeyValues = Vec<(Vec<u8>, Vec<u8>)>;
+
+    fn max_stream_queries(&self) -> usize {
+        self.max_stream_queries
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, S3StoreInternalError> {
+        let Some(body) = self.read_value_stream(key).await? else {
+            return Ok(None);
+        };
+        Ok(Some(body.collect().await?.into_bytes().to_vec()))
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, S3StoreInternalError> {
+        check_key_size(key)?;
+        let _guard = self.acquire().await;
+        let response = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(self.object_name(key))
+            .send()
+            .boxed()
+            .await;
+        match response {
+            Ok(_) => Ok(true),
+            Err(SdkError::ServiceError(error)) if error.err().is_not_found() => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, S3StoreInternalError> {
+        let handles = keys.iter().map(|key| self.contains_key(key));
+        join_all(handles).await.into_iter().collect()
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, S3StoreInternalError> {
+        let handles = keys.iter().map(|key| self.read_value_bytes(key));
+        join_all(handles).await.into_iter().collect()
+    }
+
+    async fn find_keys_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<Vec<u8>>, S3StoreInternalError> {
+        check_key_size(key_prefix)?;
+        let prefix = self.object_name(key_prefix);
+        let names = {
+            let _guard = self.acquire().await;
+            Self::list_object_names(&self.client, &self.bucket, &prefix).await?
+        };
+        names
+            .into_iter()
+            .map(|name| {
+                let suffix = &name[prefix.len()..];
+                hex::decode(suffix).map_err(|_| S3StoreInternalError::InvalidObjectName(name))
+            })
+            .collect()
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, S3StoreInternalError> {
+        let keys = self.find_keys_by_prefix(key_prefix).await?;
+        let handles = keys.iter().map(|short_key| {
+            let key = [key_prefix, short_key].concat();
+            async move { self.read_value_bytes(&key).await }
+        });
+        let values = join_all(handles).await;
+        let mut key_values = Vec::new();
+        for (short_key, value) in keys.into_iter().zip(values) {
+            // A key deleted since it was listed is skipped.
+            if let Some(value) = value? {
+                key_values.push((short_key, value));
+            }
+        }
+        Ok(key_values)
+    }
+}
+
+impl DeletePrefixExpander for &S3StoreInternal {
+    type Error = S3StoreInternalError;
+
+    async fn expand_delete_prefix(&self, key_prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.find_keys_by_prefix(key_prefix).await
+    }
+
+    async fn expand_delete_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        expand_delete_range(self, start, end).await
+    }
+}
+
+impl WritableKeyValueStore for S3StoreInternal {
+    const MAX_VALUE_SIZE: usize = MAX_VALUE_SIZE;
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), S3StoreInternalError> {
+        for operation in &batch.operations {
+            match operation {
+                WriteOperation::Put { key, .. } | WriteOperation::Delete { key } => {
+                    check_key_size(key)?;
+                }
+                WriteOperation::DeletePrefix { key_prefix } => check_key_size(key_prefix)?,
+                WriteOperation::DeleteRange { start, end } => {
+                    check_key_size(start)?;
+                    check_key_size(end)?;
+                }
+            }
+        }
+        // The deletions of the prefixes and ranges are replaced by the deletions of their
+        // keys that the batch does not insert again, so that all the requests can be
+        // issued in any order.
+        let batch = batch.simplify().expand_delete_prefixes(&self).await?;
+        let deletions = batch
+            .deletions
+            .into_iter()
+            .map(|key| self.delete_object(key).boxed());
+        let insertions = batch
+            .insertions
+            .into_iter()
+            .map(|(key, value)| self.put_object(key, value).boxed());
+        stream::iter(deletions.chain(insertions))
+            .buffer_unordered(MAX_CONCURRENT_WRITES)
+            .try_collect::<()>()
+            .await
+    }
+
+    async fn clear_journal(&self) -> Result<(), S3StoreInternalError> {
+        Ok(())
+    }
+}
+
+impl AdminKeyValueStore for S3StoreInternal {
+    type Config = S3StoreInternalConfig;
+
+    fn get_name() -> String {
+        "s3 internal".to_string()
+    }
+
+    async fn connect(
+        config: &Self::Config,
+        namespace: &str,
+        root_key: &[u8],
+    ) -> Result<Self, S3StoreInternalError> {
+        Self::check_namespace(namespace)?;
+        let client = Client::from_conf(config.config.clone());
+        let namespace_prefix = Self::namespace_prefix(config, namespace);
+        let base_name = Self::base_name(&namespace_prefix, root_key)?;
+        let semaphore = config
+            .common_config
+            .max_concurrent_queries
+            .map(|n| Arc::new(Semaphore::new(n)));
+        Ok(S3StoreInternal {
+            client,
+            bucket: config.bucket.clone(),
+            namespace_prefix,
+            base_name,
+            semaphore,
+            max_stream_queries: config.common_config.max_stream_queries,
+        })
+    }
+
+    fn clone_with_root_key(&self, root_key: &[u8]) -> Result<Self, S3StoreInternalError> {
+        let mut store = self.clone();
+        store.base_name = Self::base_name(&self.namespace_prefix, root_key)?;
+        Ok(store)
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, S3StoreInternalError> {
+        let client = Client::from_conf(config.config.clone());
+        if !Self::bucket_exists(&client, &config.bucket).await? {
+            return Ok(Vec::new());
+        }
+        let names = Self::list_object_names(&client, &config.bucket, &config.object_prefix).await?;
+        let marker_suffix = format!("/{NAMESPACE_MARKER}");
+        Ok(names
+            .into_iter()
+            .filter_map(|name| {
+                let namespace = name[config.object_prefix.len()..].strip_suffix(&marker_suffix)?;
+                (!namespace.contains('/')).then(|| namespace.to_string())
+            })
+            .collect())
+    }
+
+    async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, S3StoreInternalError> {
+        Self::check_namespace(namespace)?;
+        let client = Client::from_conf(config.config.clone());
+        if !Self::bucket_exists(&client, &config.bucket).await? {
+            return Ok(false);
+        }
+        let marker = format!(
+            "{}{NAMESPACE_MARKER}",
+            Self::namespace_prefix(config, namespace)
+        );
+        let response = client
+            .head_object()
+            .bucket(&config.bucket)
+            .key(marker)
+            .send()
+            .boxed()
+            .await;
+        match response {
+            Ok(_) => Ok(true),
+            Err(SdkError::ServiceError(error)) if error.err().is_not_found() => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn create(config: &Self::Config, namespace: &str) -> Result<(), S3StoreInternalError> {
+        ensure!(
+            !Self::exists(config, namespace).await?,
+            S3StoreInternalError::NamespaceExists(namespace.to_string())
+        );
+        let client = Client::from_conf(config.config.clone());
+        if !Self::bucket_exists(&client, &config.bucket).await? {
+            client
+                .create_bucket()
+                .bucket(&config.bucket)
+                .send()
+                .boxed()
+                .await?;
+        }
+        let marker = format!(
+            "{}{NAMESPACE_MARKER}",
+            Self::namespace_prefix(config, namespace)
+        );
+        client
+            .put_object()
+            .bucket(&config.bucket)
+            .key(marker)
+            .body(ByteStream::from_static(&[]))
+            .send()
+            .boxed()
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(config: &Self::Config, namespace: &str) -> Result<(), S3StoreInternalError> {
+        Self::check_namespace(namespace)?;
+        let client = Client::from_conf(config.config.clone());
+        let prefix = Self::namespace_prefix(config, namespace);
+        let names = Self::list_object_names(&client, &config.bucket, &prefix).await?;
+        let deletions = names.into_iter().map(|name| {
+            client
+                .delete_object()
+                .bucket(&config.bucket)
+                .key(name)
+                .send()
+                .map(|result| result.map(|_| ()).map_err(S3StoreInternalError::from))
+        });
+        stream::iter(deletions)
+            .buffer_unordered(MAX_CONCURRENT_WRITES)
+            .try_collect::<()>()
+            .await
+    }
+}
+
+#[cfg(with_testing)]
+impl TestKeyValueStore for S3StoreInternal {
+    async fn new_test_config() -> Result<S3StoreInternalConfig, S3StoreInternalError> {
+        let common_config = CommonStoreInternalConfig {
+            max_concurrent_queries: Some(TEST_S3_MAX_CONCURRENT_QUERIES),
+            max_stream_queries: TEST_S3_MAX_STREAM_QUERIES,
+        };
+        let use_localstack = true;
+        let config = get_config(use_localstack).await?;
+        Ok(S3StoreInternalConfig {
+            config,
+            bucket: TEST_S3_BUCKET.to_string(),
+            object_prefix: String::new(),
+            common_config,
+        })
+    }
+}
+
+/// The error type for [`S3StoreInternal`].
+#[derive(Error, Debug)]
+pub enum S3StoreInternalError {
+    /// An error occurred while getting an object.
+    #[error(transparent)]
+    GetObject(#[from] Box<SdkError<GetObjectError>>),
+
+    /// An error occurred while checking an object.
+    #[error(transparent)]
+    HeadObject(#[from] Box<SdkError<HeadObjectError>>),
+
+    /// An error occurred while putting an object.
+    #[error(transparent)]
+    PutObject(#[from] Box<SdkError<PutObjectError>>),
+
+    /// An error occurred while deleting an object.
+    #[error(transparent)]
+    DeleteObject(#[from] Box<SdkError<DeleteObjectError>>),
+
+    /// An error occurred while listing objects.
+    #[error(transparent)]
+    ListObjects(#[from] Box<SdkError<ListObjectsV2Error>>),
+
+    /// An error occurred while checking the bucket.
+    #[error(transparent)]
+    HeadBucket(#[from] Box<SdkError<HeadBucketError>>),
+
+    /// An error occurred while creating the bucket.
+    #[error(transparent)]
+    CreateBucket(#[from] Box<SdkError<CreateBucketError>>),
+
+    /// An error occurred while reading the body of an object.
+    #[error(transparent)]
+    ByteStream(#[from] ByteStreamError),
+
+    /// The key must have at most `MAX_KEY_SIZE` bytes.
+    #[error("The key must have at most 400 bytes")]
+    KeyTooLong,
+
+    /// The name of the objects of a root key leaves no room for the keys.
+    #[error("The object name {0} is too long")]
+    BaseNameTooLong(String),
+
+    /// The name of an object is not the hexadecimal encoding of a key.
+    #[error("The object name {0} is not the encoding of a key")]
+    InvalidObjectName(String),
+
+    /// The namespace contains forbidden characters.
+    #[error("Namespace contains forbidden characters")]
+    InvalidNamespace,
+
+    /// The namespace already exists.
+    #[error("The namespace {0} already exists")]
+    NamespaceExists(String),
+
+    /// The address of LocalStack is not set.
+    #[error("The environment variable {LOCALSTACK_ENDPOINT} is not set")]
+    LocalStackEndpoint,
+
+    /// BCS serialization error.
+    #[error(transparent)]
+    BcsError(#[from] bcs::Error),
+}
+
+impl<InnerError> From<SdkError<InnerError>> for S3StoreInternalError
+where
+    S3StoreInternalError: From<Box<SdkError<InnerError>>>,
+{
+    fn from(error: SdkError<InnerError>) -> Self {
+        Box::new(error).into()
+    }
+}
+
+/// Whether a request failed because of a transient condition of the network or of S3.
+fn is_retryable_sdk_error<E>(error: &SdkError<E>) -> bool {
+    match error {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) => true,
+        SdkError::ResponseError(_) => true,
+        SdkError::ServiceError(error) => {
+            let status = error.raw().status().as_u16();
+            status == 429 || status >= 500
+        }
+        _ => false,
+    }
+}
+
+impl KeyValueStoreError for S3StoreInternalError {
+    const BACKEND: &'static str = "s3";
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            S3StoreInternalError::GetObject(error) => is_retryable_sdk_error(error),
+            S3StoreInternalError::HeadObject(error) => is_retryable_sdk_error(error),
+            S3StoreInternalError::PutObject(error) => is_retryable_sdk_error(error),
+            S3StoreInternalError::DeleteObject(error) => is_retryable_sdk_error(error),
+            S3StoreInternalError::ListObjects(error) => is_retryable_sdk_error(error),
+            S3StoreInternalError::HeadBucket(error) => is_retryable_sdk_error(error),
+            S3StoreInternalError::CreateBucket(error) => is_retryable_sdk_error(error),
+            _ => false,
+        }
+    }
+}
+
+/// The `S3Store` composed type with metrics
+#[cfg(with_metrics)]
+pub type S3Store = MeteredStore<LruCachingStore<MeteredStore<S3StoreInternal>>>;
+
+/// The `S3Store` composed type
+#[cfg(not(with_metrics))]
+pub type S3Store = LruCachingStore<S3StoreInternal>;
+
+/// The error type for the `S3Store`.
+pub type S3StoreError = S3StoreInternalError;
+
+/// The config type for the `S3Store`.
+pub type S3StoreConfig = LruCachingConfig<S3StoreInternalConfig>;
+
+/// A context for views stored in S3.
+pub type S3Context<E> = ViewContext<E, S3Store>;
+
+impl S3StoreConfig {
+    /// Creates an `S3StoreConfig` for the objects of the given bucket.
+    pub fn new(
+        config: Config,
+        bucket: String,
+        common_config: crate::store::CommonStoreConfig,
+    ) -> S3StoreConfig {
+        let inner_config = S3StoreInternalConfig {
+            config,
+            bucket,
+            object_prefix: String::new(),
+            common_config: common_config.reduced(),
+        };
+        S3StoreConfig {
+            inner_config,
+            cache_size: common_config.cache_size,
+            max_cache_bytes: None,
+        }
+    }
+
+    /// Creates an `S3StoreConfig` for the bucket of the LocalStack instance of the
+    /// `LOCALSTACK_ENDPOINT` environment variable.
+    pub async fn new_localstack(
+        bucket: String,
+        common_config: crate::store::CommonStoreConfig,
+    ) -> Result<S3StoreConfig, S3StoreError> {
+        let use_localstack = true;
+        let config = get_config(use_localstack).await?;
+        Ok(S3StoreConfig::new(config, bucket, common_config))
+    }
+
+    /// Sets the prefix of the names of the objects, e.g. `linera/` to share a bucket
+    /// with other data.
+    pub fn with_object_prefix(mut self, object_prefix: String) -> Self {
+        self.inner_config.object_prefix = object_prefix;
+        self
+    }
+}
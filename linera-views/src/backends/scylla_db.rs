@@ -6,6 +6,11 @@
 //! The current connection is done via a Session and a corresponding primary key called
 //! "namespace". The maximum number of concurrent queries is controlled by
 //! `max_concurrent_queries`.
+//!
+//! The tests of this backend, including the conformance suite of the stores, expect a
+//! ScyllaDB instance listening on `localhost:9042`, e.g. started with
+//! `docker run --name scylla -d -p 9042:9042 scylladb/scylla:6.1`, and are run with
+//! `cargo test --features scylladb -- scylla`.
 
 use std::{
     collections::{hash_map::Entry, HashMap},
@@ -33,6 +38,7 @@ use crate::store::TestKeyValueStore;
 use crate::{
     batch::UnorderedBatch,
     common::{get_uleb128_size, get_upper_bound_option},
+    context::ViewContext,
     journaling::{DirectWritableKeyValueStore, JournalConsistencyError, JournalingKeyValueStore},
     lru_caching::{LruCachingConfig, LruCachingStore},
     store::{
@@ -898,9 +904,13 @@ impl ScyllaDbStoreConfig {
         ScyllaDbStoreConfig {
             inner_config,
             cache_size: common_config.cache_size,
+            max_cache_bytes: None,
         }
     }
 }
 
+/// A context for views stored in ScyllaDB.
+pub type ScyllaDbContext<E> = ViewContext<E, ScyllaDbStore>;
+
 /// The combined error type for the `ScyllaDbStore`.
 pub type ScyllaDbStoreError = ValueSplittingError<ScyllaDbStoreInternalError>;
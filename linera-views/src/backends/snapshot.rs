@@ -0,0 +1,404 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Cheap snapshots of a store, isolating readers from the writes done after the snapshot
+//! was taken, e.g. a GraphQL reader iterating a view while a block is being saved.
+//!
+//! Taking a snapshot does not copy anything. Instead, the writer records the values that
+//! a batch is about to replace in every live snapshot before writing it, i.e. the
+//! snapshots are copies-on-write of the replaced values. A snapshot reads the store first
+//! and then substitutes the recorded values, so that its reads are consistent even while
+//! a batch is being written. Recording is only done while snapshots are alive, and costs
+//! reading the replaced values, including the ones under deleted prefixes.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex, Weak},
+};
+
+use thiserror::Error;
+
+use crate::{
+    batch::{Batch, WriteOperation},
+    store::{
+        AdminKeyValueStore, KeyIterable as _, KeyValueIterable as _, KeyValueStoreError,
+        ReadableKeyValueStore, RestrictedKeyValueStore, WithError, WritableKeyValueStore,
+    },
+    views::ViewError,
+};
+
+/// The composed error type built from the inner error type.
+#[derive(Error, Debug)]
+pub enum SnapshotStoreError<E> {
+    /// inner store error
+    #[error(transparent)]
+    InnerStoreError(#[from] E),
+
+    /// A write was attempted through a snapshot.
+    #[error("a snapshot of a store cannot be written to")]
+    WriteToSnapshot,
+}
+
+impl<E: KeyValueStoreError> From<bcs::Error> for SnapshotStoreError<E> {
+    fn from(error: bcs::Error) -> Self {
+        let error = E::from(error);
+        SnapshotStoreError::InnerStoreError(error)
+    }
+}
+
+impl<E: KeyValueStoreError + 'static> KeyValueStoreError for SnapshotStoreError<E> {
+    const BACKEND: &'static str = "snapshot";
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            SnapshotStoreError::InnerStoreError(error) => error.is_retryable(),
+            SnapshotStoreError::WriteToSnapshot => false,
+        }
+    }
+}
+
+/// The values of the keys written since a snapshot was taken, as they were at that time.
+/// `None` stands for a key that was absent.
+type Overlay = Mutex<BTreeMap<Vec<u8>, Option<Vec<u8>>>>;
+
+/// The live snapshots of a store, shared by the writer and the snapshots.
+#[derive(Debug, Default)]
+struct SnapshotRegistry {
+    /// The overlays of the snapshots, which are dropped with the snapshots. The lock is
+    /// held while a batch is written, so that no snapshot is taken in the meantime.
+    overlays: async_lock::Mutex<Vec<Weak<Overlay>>>,
+}
+
+/// A key-value store whose readers can take snapshots with [`SnapshotStore::snapshot`].
+///
+/// The writes must all go through this store or its clones, since the snapshots are only
+/// isolated from them. Writing through a snapshot fails with
+/// [`SnapshotStoreError::WriteToSnapshot`].
+#[derive(Clone)]
+pub struct SnapshotStore<K> {
+    /// The underlying store.
+    store: K,
+    /// The live snapshots of the store.
+    registry: Arc<SnapshotRegistry>,
+    /// The overlay of this snapshot, or `None` if this is not a snapshot.
+    overlay: Option<Arc<Overlay>>,
+}
+
+impl<K> SnapshotStore<K> {
+    /// Creates a store whose readers can take snapshots.
+    pub fn new(store: K) -> Self {
+        Self {
+            store,
+            registry: Arc::default(),
+            overlay: None,
+        }
+    }
+
+    /// Returns whether this store is a snapshot.
+    pub fn is_snapshot(&self) -> bool {
+        self.overlay.is_some()
+    }
+
+    /// Applies the overlay of the snapshot, if any, to the value read for `key`.
+    fn apply_overlay(&self, key: &[u8], value: Option<Vec<u8>>) -> Option<Vec<u8>> {
+        let Some(overlay) = &self.overlay else {
+            return value;
+        };
+        match overlay.lock().unwrap().get(key) {
+            Some(old_value) => old_value.clone(),
+            None => value,
+        }
+    }
+
+    /// Calls `f` on the keys starting with `key_prefix` in the overlay of the snapshot, if
+    /// any, without the prefix, and their values at the time of the snapshot.
+    fn for_each_overlay_entry(
+        &self,
+        key_prefix: &[u8],
+        mut f: impl FnMut(&[u8], &Option<Vec<u8>>),
+    ) {
+        let Some(overlay) = &self.overlay else {
+            return;
+        };
+        let overlay = overlay.lock().unwrap();
+        for (key, value) in overlay.range(key_prefix.to_vec()..) {
+            if !key.starts_with(key_prefix) {
+                break;
+            }
+            f(&key[key_prefix.len()..], value);
+        }
+    }
+}
+
+impl<K> SnapshotStore<K>
+where
+    K: Clone,
+{
+    /// Takes a snapshot of the store, which keeps reading the values stored at this time
+    /// for as long as it is alive. This only waits for the batch being written, if any.
+    pub async fn snapshot(&self) -> Self {
+        if self.overlay.is_some() {
+            return self.clone();
+        }
+        let overlay = Arc::new(Overlay::default());
+        let mut overlays = self.registry.overlays.lock().await;
+        overlays.retain(|overlay| overlay.strong_count() > 0);
+        overlays.push(Arc::downgrade(&overlay));
+        Self {
+            store: self.store.clone(),
+            registry: self.registry.clone(),
+            overlay: Some(overlay),
+        }
+    }
+}
+
+impl<K> SnapshotStore<K>
+where
+    K: ReadableKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    /// Reads the values that the `batch` is about to replace.
+    async fn read_replaced_values(
+        &self,
+        batch: &Batch,
+    ) -> Result<BTreeMap<Vec<u8>, Option<Vec<u8>>>, K::Error> {
+        let mut values = BTreeMap::new();
+        let mut keys = BTreeSet::new();
+        for operation in &batch.operations {
+            match operation {
+                WriteOperation::Put { key, .. } | WriteOperation::Delete { key } => {
+                    keys.insert(key.clone());
+                }
+                WriteOperation::DeletePrefix { key_prefix } => {
+                    let key_values = self.store.find_key_values_by_prefix(key_prefix).await?;
+                    for key_value in key_values.iterator() {
+                        let (key, value) = key_value?;
+                        let key = [key_prefix, key].concat();
+                        values.insert(key, Some(value.to_vec()));
+                    }
+                }
+                WriteOperation::DeleteRange { start, end } => {
+                    keys.extend(self.store.find_keys_in_range(start, end).await?);
+                }
+            }
+        }
+        let keys = keys
+            .into_iter()
+            .filter(|key| !values.contains_key(key))
+            .collect::<Vec<_>>();
+        let read_values = self.store.read_multi_values_bytes(keys.clone()).await?;
+        values.extend(keys.into_iter().zip(read_values));
+        Ok(values)
+    }
+
+    /// Records the values replaced by `batch` in the live snapshots, before it is written.
+    async fn record_replaced_values(
+        &self,
+        overlays: &mut Vec<Weak<Overlay>>,
+        batch: &Batch,
+    ) -> Result<(), K::Error> {
+        overlays.retain(|overlay| overlay.strong_count() > 0);
+        if overlays.is_empty() {
+            return Ok(());
+        }
+        let values = self.read_replaced_values(batch).await?;
+        for overlay in overlays.iter().filter_map(Weak::upgrade) {
+            let mut overlay = overlay.lock().unwrap();
+            for (key, value) in &values {
+                overlay.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K> WithError for SnapshotStore<K>
+where
+    K: WithError,
+    K::Error: 'static,
+{
+    type Error = SnapshotStoreError<K::Error>;
+}
+
+impl<K> ReadableKeyValueStore for SnapshotStore<K>
+where
+    K: ReadableKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    const MAX_KEY_SIZE: usize = K::MAX_KEY_SIZE;
+    type Keys = Vec<Vec<u8>>;
+    type KeyValues = Vec<(Vec<u8>, Vec<u8>)>;
+
+    fn max_stream_queries(&self) -> usize {
+        self.store.max_stream_queries()
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        let value = self.store.read_value_bytes(key).await?;
+        Ok(self.apply_overlay(key, value))
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        let exists = self.store.contains_key(key).await?;
+        Ok(match &self.overlay {
+            Some(overlay) => match overlay.lock().unwrap().get(key) {
+                Some(old_value) => old_value.is_some(),
+                None => exists,
+            },
+            None => exists,
+        })
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, Self::Error> {
+        let exists = self.store.contains_keys(keys.clone()).await?;
+        let Some(overlay) = &self.overlay else {
+            return Ok(exists);
+        };
+        let overlay = overlay.lock().unwrap();
+        Ok(keys
+            .iter()
+            .zip(exists)
+            .map(|(key, exists)| match overlay.get(key) {
+                Some(old_value) => old_value.is_some(),
+                None => exists,
+            })
+            .collect())
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let values = self.store.read_multi_values_bytes(keys.clone()).await?;
+        Ok(keys
+            .iter()
+            .zip(values)
+            .map(|(key, value)| self.apply_overlay(key, value))
+            .collect())
+    }
+
+    async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Self::Keys, Self::Error> {
+        let mut keys = BTreeSet::new();
+        for key in self.store.find_keys_by_prefix(key_prefix).await?.iterator() {
+            keys.insert(key?.to_vec());
+        }
+        self.for_each_overlay_entry(key_prefix, |key, old_value| {
+            if old_value.is_some() {
+                keys.insert(key.to_vec());
+            } else {
+                keys.remove(key);
+            }
+        });
+        Ok(keys.into_iter().collect())
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Self::KeyValues, Self::Error> {
+        let mut key_values = BTreeMap::new();
+        let stored_key_values = self.store.find_key_values_by_prefix(key_prefix).await?;
+        for key_value in stored_key_values.into_iterator_owned() {
+            let (key, value) = key_value?;
+            key_values.insert(key, value);
+        }
+        self.for_each_overlay_entry(key_prefix, |key, old_value| match old_value {
+            Some(old_value) => {
+                key_values.insert(key.to_vec(), old_value.clone());
+            }
+            None => {
+                key_values.remove(key);
+            }
+        });
+        Ok(key_values.into_iter().collect())
+    }
+}
+
+impl<K> WritableKeyValueStore for SnapshotStore<K>
+where
+    K: RestrictedKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    const MAX_VALUE_SIZE: usize = K::MAX_VALUE_SIZE;
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
+        if self.overlay.is_some() {
+            return Err(SnapshotStoreError::WriteToSnapshot);
+        }
+        let mut overlays = self.registry.overlays.lock().await;
+        self.record_replaced_values(&mut overlays, &batch).await?;
+        self.store.write_batch(batch).await?;
+        Ok(())
+    }
+
+    async fn clear_journal(&self) -> Result<(), Self::Error> {
+        if self.overlay.is_some() {
+            return Ok(());
+        }
+        Ok(self.store.clear_journal().await?)
+    }
+
+    async fn compare_and_put(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, ViewError> {
+        if self.overlay.is_some() {
+            return Err(SnapshotStoreError::<K::Error>::WriteToSnapshot.into());
+        }
+        let mut overlays = self.registry.overlays.lock().await;
+        let mut batch = Batch::new();
+        batch.delete_key(key.to_vec());
+        self.record_replaced_values(&mut overlays, &batch).await?;
+        self.store.compare_and_put(key, expected, new).await
+    }
+}
+
+impl<K> AdminKeyValueStore for SnapshotStore<K>
+where
+    K: AdminKeyValueStore + Send + Sync,
+    K::Error: 'static,
+{
+    type Config = K::Config;
+
+    fn get_name() -> String {
+        format!("snapshot {}", K::get_name())
+    }
+
+    async fn connect(
+        config: &Self::Config,
+        namespace: &str,
+        root_key: &[u8],
+    ) -> Result<Self, Self::Error> {
+        let store = K::connect(config, namespace, root_key).await?;
+        Ok(Self::new(store))
+    }
+
+    /// The snapshots of the store with the new root key are independent of the ones of
+    /// this store, and the returned store is not a snapshot.
+    fn clone_with_root_key(&self, root_key: &[u8]) -> Result<Self, Self::Error> {
+        let store = self.store.clone_with_root_key(root_key)?;
+        Ok(Self::new(store))
+    }
+
+    async fn list_all(config: &Self::Config) -> Result<Vec<String>, Self::Error> {
+        Ok(K::list_all(config).await?)
+    }
+
+    async fn delete_all(config: &Self::Config) -> Result<(), Self::Error> {
+        Ok(K::delete_all(config).await?)
+    }
+
+    async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, Self::Error> {
+        Ok(K::exists(config, namespace).await?)
+    }
+
+    async fn create(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        Ok(K::create(config, namespace).await?)
+    }
+
+    async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
+        Ok(K::delete(config, namespace).await?)
+    }
+}
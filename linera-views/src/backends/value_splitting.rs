@@ -3,15 +3,19 @@
 
 //! Adds support for large values to a given store by splitting them between several keys.
 
+use std::collections::BTreeMap;
+
+use futures::{stream, TryStreamExt as _};
 use linera_base::ensure;
 use thiserror::Error;
 
 use crate::{
-    batch::{Batch, WriteOperation},
+    batch::{Batch, BatchStats, WriteOperation},
     store::{
-        AdminKeyValueStore, KeyIterable, KeyValueIterable, KeyValueStoreError,
-        ReadableKeyValueStore, WithError, WritableKeyValueStore,
+        AdminKeyValueStore, KeyIterable, KeyValueIterable, KeyValueStoreError, Order,
+        ReadableKeyValueStore, ResultStream, WithError, WritableKeyValueStore,
     },
+    views::ViewError,
 };
 #[cfg(with_testing)]
 use crate::{
@@ -49,6 +53,13 @@ impl<E: KeyValueStoreError> From<bcs::Error> for ValueSplittingError<E> {
 
 impl<E: KeyValueStoreError + 'static> KeyValueStoreError for ValueSplittingError<E> {
     const BACKEND: &'static str = "value splitting";
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            ValueSplittingError::InnerStoreError(error) => error.is_retryable(),
+            _ => false,
+        }
+    }
 }
 
 /// A key-value store with no size limit for values.
@@ -199,6 +210,94 @@ where
         Ok(keys)
     }
 
+    async fn find_keys_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        let mut keys = Vec::new();
+        for mut big_key in self
+            .store
+            .find_keys_by_prefix_with_options(key_prefix, order)
+            .await?
+        {
+            if Self::read_index_from_key(&big_key)? == 0 {
+                big_key.truncate(big_key.len() - 4);
+                keys.push(big_key);
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn find_keys_by_prefix_paginated(
+        &self,
+        key_prefix: &[u8],
+        limit: usize,
+        continuation: Option<Vec<u8>>,
+    ) -> Result<(Vec<Vec<u8>>, Option<Vec<u8>>), Self::Error> {
+        let mut keys = Vec::new();
+        if limit == 0 {
+            return Ok((keys, continuation));
+        }
+        // The segments of the continuation key come after its first segment and are
+        // skipped below, like the other segments of nonzero index.
+        let mut inner_continuation = match continuation {
+            Some(continuation) => Some(Self::get_segment_key(&continuation, 0)?),
+            None => None,
+        };
+        loop {
+            let (big_keys, next_continuation) = self
+                .store
+                .find_keys_by_prefix_paginated(key_prefix, limit - keys.len(), inner_continuation)
+                .await?;
+            for mut big_key in big_keys {
+                if Self::read_index_from_key(&big_key)? == 0 {
+                    big_key.truncate(big_key.len() - 4);
+                    keys.push(big_key);
+                }
+            }
+            if next_continuation.is_none() {
+                return Ok((keys, None));
+            }
+            if keys.len() == limit {
+                let continuation = keys.last().cloned();
+                return Ok((keys, continuation));
+            }
+            inner_continuation = next_continuation;
+        }
+    }
+
+    async fn find_keys_in_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        // Appending the index 0 to both bounds preserves the order of the keys, and the
+        // segments of index 0 in the resulting range are exactly the keys of the range.
+        let start = Self::get_segment_key(start, 0)?;
+        let end = Self::get_segment_key(end, 0)?;
+        let mut keys = Vec::new();
+        for mut big_key in self.store.find_keys_in_range(&start, &end).await? {
+            if Self::read_index_from_key(&big_key)? == 0 {
+                big_key.truncate(big_key.len() - 4);
+                keys.push(big_key);
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn count_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<usize, Self::Error> {
+        // The inner store counts the segments, so only the first ones are counted here.
+        let mut big_keys = self.store.find_keys_by_prefix_stream(key_prefix).await?;
+        let mut count = 0;
+        while let Some(big_key) = big_keys.try_next().await? {
+            if Self::read_index_from_key(&big_key)? == 0 {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
     async fn find_key_values_by_prefix(
         &self,
         key_prefix: &[u8],
@@ -231,46 +330,77 @@ where
         }
         Ok(key_values)
     }
+
+    async fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, Vec<u8>, Self::Error>, Self::Error> {
+        let big_keys = self.store.find_keys_by_prefix_stream(key_prefix).await?;
+        let keys = big_keys
+            .map_err(ValueSplittingError::InnerStoreError)
+            .try_filter_map(|mut big_key| async move {
+                if Self::read_index_from_key(&big_key)? != 0 {
+                    return Ok(None);
+                }
+                big_key.truncate(big_key.len() - 4);
+                Ok(Some(big_key))
+            });
+        Ok(Box::pin(keys))
+    }
+
+    async fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error>, Self::Error> {
+        let segments = self
+            .store
+            .find_key_values_by_prefix_stream(key_prefix)
+            .await?;
+        let key_values = stream::try_unfold(segments, |mut segments| async move {
+            let key_value = Self::next_key_value(&mut segments).await?;
+            Ok(key_value.map(|key_value| (key_value, segments)))
+        });
+        Ok(Box::pin(key_values))
+    }
 }
 
 impl<K> WritableKeyValueStore for ValueSplittingStore<K>
 where
-    K: WritableKeyValueStore + Send + Sync,
+    K: ReadableKeyValueStore + WritableKeyValueStore + Send + Sync,
     K::Error: 'static,
 {
     const MAX_VALUE_SIZE: usize = usize::MAX;
 
     async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
-        let mut batch_new = Batch::new();
-        for operation in batch.operations {
-            match operation {
-                WriteOperation::Delete { key } => {
-                    let mut big_key = key.to_vec();
-                    big_key.extend(&[0, 0, 0, 0]);
-                    batch_new.delete_key(big_key);
-                }
-                WriteOperation::Put { key, mut value } => {
-                    let big_key = Self::get_segment_key(&key, 0)?;
-                    let mut count: u32 = 1;
-                    let value_ext = if value.len() <= K::MAX_VALUE_SIZE - 4 {
-                        Self::get_initial_count_first_chunk(count, &value)?
-                    } else {
-                        let remainder = value.split_off(K::MAX_VALUE_SIZE - 4);
-                        for value_chunk in remainder.chunks(K::MAX_VALUE_SIZE) {
-                            let big_key_segment = Self::get_segment_key(&key, count)?;
-                            batch_new.put_key_value_bytes(big_key_segment, value_chunk.to_vec());
-                            count += 1;
-                        }
-                        Self::get_initial_count_first_chunk(count, &value)?
-                    };
-                    batch_new.put_key_value_bytes(big_key, value_ext);
-                }
-                WriteOperation::DeletePrefix { key_prefix } => {
-                    batch_new.delete_key_prefix(key_prefix);
-                }
-            }
-        }
-        Ok(self.store.write_batch(batch_new).await?)
+        let batch = self.split_batch(batch).await?;
+        Ok(self.store.write_batch(batch).await?)
+    }
+
+    async fn write_batch_with_stats(&self, batch: Batch) -> Result<BatchStats, Self::Error> {
+        let batch = self.split_batch(batch).await?;
+        Ok(self.store.write_batch_with_stats(batch).await?)
+    }
+
+    async fn compare_and_put(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, ViewError> {
+        // Only values that fit in a single segment can be compared and written atomically.
+        let to_first_segment = |value: &[u8]| -> Result<Vec<u8>, ViewError> {
+            ensure!(
+                value.len() <= K::MAX_VALUE_SIZE - 4,
+                ViewError::TooLargeValue
+            );
+            Ok(Self::get_initial_count_first_chunk(1, value)?)
+        };
+        let big_key = Self::get_segment_key(key, 0)?;
+        let expected = expected.map(to_first_segment).transpose()?;
+        let new = new.map(to_first_segment).transpose()?;
+        self.store
+            .compare_and_put(&big_key, expected.as_deref(), new.as_deref())
+            .await
     }
 
     async fn clear_journal(&self) -> Result<(), Self::Error> {
@@ -344,6 +474,119 @@ where
         ValueSplittingStore { store }
     }
 
+    /// Gets the inner store.
+    pub fn inner(&self) -> &K {
+        &self.store
+    }
+
+    /// Splits the large values of the batch into segments that fit in the inner store.
+    async fn split_batch(&self, batch: Batch) -> Result<Batch, ValueSplittingError<K::Error>>
+    where
+        K: ReadableKeyValueStore + WritableKeyValueStore + Send + Sync,
+        K::Error: 'static,
+    {
+        let mut batch_new = Batch::new();
+        // The number of segments of the values put by the batch, for the ranges deleted
+        // after them.
+        let mut put_counts = BTreeMap::new();
+        for operation in batch.operations {
+            match operation {
+                WriteOperation::Delete { key } => {
+                    let mut big_key = key.to_vec();
+                    big_key.extend(&[0, 0, 0, 0]);
+                    batch_new.delete_key(big_key);
+                }
+                WriteOperation::Put { key, mut value } => {
+                    let big_key = Self::get_segment_key(&key, 0)?;
+                    let mut count: u32 = 1;
+                    let value_ext = if value.len() <= K::MAX_VALUE_SIZE - 4 {
+                        Self::get_initial_count_first_chunk(count, &value)?
+                    } else {
+                        let remainder = value.split_off(K::MAX_VALUE_SIZE - 4);
+                        for value_chunk in remainder.chunks(K::MAX_VALUE_SIZE) {
+                            let big_key_segment = Self::get_segment_key(&key, count)?;
+                            batch_new.put_key_value_bytes(big_key_segment, value_chunk.to_vec());
+                            count += 1;
+                        }
+                        Self::get_initial_count_first_chunk(count, &value)?
+                    };
+                    batch_new.put_key_value_bytes(big_key, value_ext);
+                    let previous_count = put_counts.entry(key).or_insert(count);
+                    *previous_count = count.max(*previous_count);
+                }
+                WriteOperation::DeletePrefix { key_prefix } => {
+                    batch_new.delete_key_prefix(key_prefix);
+                }
+                WriteOperation::DeleteRange { start, end } => {
+                    // The range of the segments would also cover the segments of the keys
+                    // before `start` that are prefixes of it, and miss some segments of the
+                    // keys that are prefixes of `end`. Instead, the segments of each key of
+                    // the range are deleted.
+                    if start >= end {
+                        continue;
+                    }
+                    let mut counts = put_counts
+                        .range(start.clone()..end.clone())
+                        .map(|(key, count)| (key.clone(), *count))
+                        .collect::<BTreeMap<_, _>>();
+                    let keys = self.find_keys_in_range(&start, &end).await?;
+                    let first_segment_keys = keys
+                        .iter()
+                        .map(|key| Self::get_segment_key(key, 0))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let values = self
+                        .store
+                        .read_multi_values_bytes(first_segment_keys)
+                        .await?;
+                    for (key, value) in keys.into_iter().zip(values) {
+                        let count = match value {
+                            Some(value) => Self::read_count_from_value(&value)?,
+                            None => 1,
+                        };
+                        let previous_count = counts.entry(key).or_insert(count);
+                        *previous_count = count.max(*previous_count);
+                    }
+                    for (key, count) in counts {
+                        for index in 0..count {
+                            batch_new.delete_key(Self::get_segment_key(&key, index)?);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(batch_new)
+    }
+
+    /// Reads the next key-value pair from a stream of segments, as they are polled.
+    async fn next_key_value(
+        segments: &mut ResultStream<'_, (Vec<u8>, Vec<u8>), K::Error>,
+    ) -> Result<Option<(Vec<u8>, Vec<u8>)>, ValueSplittingError<K::Error>> {
+        while let Some((mut big_key, value)) = segments.try_next().await? {
+            if Self::read_index_from_key(&big_key)? != 0 {
+                continue; // Leftover segment from an earlier value.
+            }
+            big_key.truncate(big_key.len() - 4);
+            let key = big_key;
+            let count = Self::read_count_from_value(&value)?;
+            let mut big_value = value[4..].to_vec();
+            for idx in 1..count {
+                let (big_key, value) = segments
+                    .try_next()
+                    .await?
+                    .ok_or(ValueSplittingError::MissingSegment)?;
+                ensure!(
+                    Self::read_index_from_key(&big_key)? == idx
+                        && big_key.starts_with(&key)
+                        && big_key.len() == key.len() + 4,
+                    ValueSplittingError::MissingSegment
+                );
+                big_value.extend(value);
+            }
+            return Ok(Some((key, big_value)));
+        }
+        Ok(None)
+    }
+
     fn get_segment_key(key: &[u8], index: u32) -> Result<Vec<u8>, ValueSplittingError<K::Error>> {
         let mut big_key_segment = key.to_vec();
         let mut bytes = bcs::to_bytes(&index)?;
@@ -484,6 +727,7 @@ pub fn create_value_splitting_memory_store() -> ValueSplittingStore<LimitedTestM
 
 #[cfg(test)]
 mod tests {
+    use futures::TryStreamExt as _;
     use linera_views::{
         batch::Batch,
         store::{ReadableKeyValueStore, WritableKeyValueStore},
@@ -583,4 +827,127 @@ mod tests {
         let keys = store.find_keys_by_prefix(&[0]).await.unwrap();
         assert_eq!(keys, vec![vec![0, 0, 0, 0, 1], vec![0, 0, 0, 0, 2]]);
     }
+
+    // The deletion of a range deletes all the segments of the keys in it, and none of the
+    // segments of the keys outside of it, even when the segments cross the bounds.
+    #[tokio::test]
+    async fn test_value_splitting4_delete_range() {
+        let store = LimitedTestMemoryStore::new();
+        const MAX_LEN: usize = LimitedTestMemoryStore::MAX_VALUE_SIZE;
+        let big_store = ValueSplittingStore::new(store.clone());
+        let mut rng = crate::random::make_deterministic_rng();
+        let mut big_value = || {
+            (0..3 * MAX_LEN - 4)
+                .map(|_| rng.gen::<u8>())
+                .collect::<Vec<_>>()
+        };
+        let start = vec![1, 0, 0, 0, 1];
+        let end = vec![1, 0, 0, 0, 3, 0, 0, 0, 1];
+        // The segments of `before` are in the range of the segments of index 0.
+        let before = vec![1];
+        let inside_small = vec![1, 0, 0, 0, 2, 5];
+        let inside_big = vec![1, 0, 0, 0, 3];
+        let inside_put = vec![1, 0, 0, 0, 2, 7];
+        let value_before = big_value();
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(before.clone(), value_before.clone());
+        batch.put_key_value_bytes(inside_small.clone(), vec![5]);
+        batch.put_key_value_bytes(inside_big.clone(), big_value());
+        batch.put_key_value_bytes(end.clone(), vec![6]);
+        big_store.write_batch(batch).await.unwrap();
+
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(inside_put.clone(), big_value());
+        batch.delete_key_range(start, end.clone());
+        big_store.write_batch(batch).await.unwrap();
+        assert_eq!(
+            big_store.read_value_bytes(&before).await.unwrap(),
+            Some(value_before)
+        );
+        assert_eq!(
+            big_store.read_value_bytes(&end).await.unwrap(),
+            Some(vec![6])
+        );
+        for key in [&inside_small, &inside_big, &inside_put] {
+            assert_eq!(big_store.read_value_bytes(key).await.unwrap(), None);
+        }
+        assert!(store
+            .find_keys_by_prefix(&inside_small)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(store
+            .find_keys_by_prefix(&inside_put)
+            .await
+            .unwrap()
+            .is_empty());
+        // Only the first segment of `end` remains below `inside_big`.
+        let mut end_key = end.clone();
+        end_key.extend([0, 0, 0, 0]);
+        assert_eq!(
+            store.find_keys_by_prefix(&inside_big).await.unwrap(),
+            vec![end_key]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_value_splitting5_count_keys() {
+        let store = LimitedTestMemoryStore::new();
+        const MAX_LEN: usize = LimitedTestMemoryStore::MAX_VALUE_SIZE;
+        let big_store = ValueSplittingStore::new(store.clone());
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![0, 0], vec![1; 3 * MAX_LEN - 4]);
+        batch.put_key_value_bytes(vec![0, 1], vec![2]);
+        batch.put_key_value_bytes(vec![1], vec![3]);
+        big_store.write_batch(batch).await.unwrap();
+        assert_eq!(store.count_keys_by_prefix(&[0]).await.unwrap(), 4);
+        assert_eq!(big_store.count_keys_by_prefix(&[0]).await.unwrap(), 2);
+        assert_eq!(big_store.count_keys_by_prefix(&[0, 0]).await.unwrap(), 1);
+        assert_eq!(big_store.count_keys_by_prefix(&[]).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_value_splitting6_streams() {
+        let store = LimitedTestMemoryStore::new();
+        const MAX_LEN: usize = LimitedTestMemoryStore::MAX_VALUE_SIZE;
+        let big_store = ValueSplittingStore::new(store.clone());
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![0, 0], vec![1; 3 * MAX_LEN - 4]);
+        batch.put_key_value_bytes(vec![0, 1], vec![2; 2 * MAX_LEN]);
+        batch.put_key_value_bytes(vec![0, 2], vec![3]);
+        big_store.write_batch(batch).await.unwrap();
+        // Leaves two segments of the previous value of `[0, 0]`.
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![0, 0], vec![4]);
+        big_store.write_batch(batch).await.unwrap();
+
+        let keys = big_store
+            .find_keys_by_prefix_stream(&[0])
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(keys, big_store.find_keys_by_prefix(&[0]).await.unwrap());
+        assert_eq!(keys, vec![vec![0], vec![1], vec![2]]);
+        let key_values = big_store
+            .find_key_values_by_prefix_stream(&[0])
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            key_values,
+            big_store.find_key_values_by_prefix(&[0]).await.unwrap()
+        );
+        assert_eq!(
+            key_values,
+            vec![
+                (vec![0], vec![4]),
+                (vec![1], vec![2; 2 * MAX_LEN]),
+                (vec![2], vec![3]),
+            ]
+        );
+    }
 }
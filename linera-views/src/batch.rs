@@ -2,13 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! A set of functionalities for building batches to be written into the database.
-//! A batch can contain three kinds of operations on a key/value store:
+//! A batch can contain four kinds of operations on a key/value store:
 //! * Insertion of a key with an associated value
 //! * Deletion of a specific key
 //! * Deletion of all keys which contain a specified prefix
+//! * Deletion of all keys in a range
 //!
-//! The deletion using prefixes is generally but not always faster than deleting keys
-//! one by one. The only purpose of the batch is to write some transactions into the
+//! The deletion using prefixes or ranges is generally but not always faster than deleting
+//! keys one by one. The only purpose of the batch is to write some transactions into the
 //! database.
 //!
 //! Note that normal users should not have to manipulate batches. The functionality
@@ -19,25 +20,27 @@ use std::{
     collections::{BTreeMap, BTreeSet, HashSet},
     fmt::Debug,
     iter::Peekable,
-    ops::Bound,
+    ops::{AddAssign, Bound},
     vec::IntoIter,
 };
 
 use async_trait::async_trait;
 use bcs::serialized_size;
+use linera_base::ensure;
 use linera_witty::{WitLoad, WitStore, WitType};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    common::{get_interval, get_uleb128_size},
+    common::{get_interval, get_uleb128_size, Codec},
     views::ViewError,
 };
 
 /// A write operation as requested by a view when it needs to persist staged changes.
-/// There are 3 possibilities for the batch:
+/// There are 4 possibilities for the batch:
 /// * Deletion of a specific key.
 /// * Deletion of all keys matching a specific prefix.
 /// * Insertion or replacement of a key with a value.
+/// * Deletion of all keys between two bounds.
 #[derive(Clone, Debug, Eq, PartialEq, WitType, WitLoad, WitStore)]
 pub enum WriteOperation {
     /// Delete the given key.
@@ -57,6 +60,40 @@ pub enum WriteOperation {
         /// The value to be inserted on the key.
         value: Vec<u8>,
     },
+    /// Delete all the keys `key` such that `start <= key < end`.
+    DeleteRange {
+        /// The smallest key to be deleted.
+        start: Vec<u8>,
+        /// The first key after the deleted ones, which is not deleted.
+        end: Vec<u8>,
+    },
+}
+
+impl WriteOperation {
+    /// The number of bytes of the operation once serialized, with the lengths encoded as
+    /// in BCS.
+    fn size_estimate(&self) -> usize {
+        // The tag of the operation takes a single byte.
+        let data_size = match self {
+            WriteOperation::Delete { key } => get_uleb128_size(key.len()) + key.len(),
+            WriteOperation::DeletePrefix { key_prefix } => {
+                get_uleb128_size(key_prefix.len()) + key_prefix.len()
+            }
+            WriteOperation::Put { key, value } => {
+                get_uleb128_size(key.len())
+                    + key.len()
+                    + get_uleb128_size(value.len())
+                    + value.len()
+            }
+            WriteOperation::DeleteRange { start, end } => {
+                get_uleb128_size(start.len())
+                    + start.len()
+                    + get_uleb128_size(end.len())
+                    + end.len()
+            }
+        };
+        1 + data_size
+    }
 }
 
 /// A batch of write operations.
@@ -66,6 +103,62 @@ pub struct Batch {
     pub operations: Vec<WriteOperation>,
 }
 
+/// Statistics on the writing of a batch, as returned by
+/// [`crate::store::WritableKeyValueStore::write_batch_with_stats`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BatchStats {
+    /// The number of `Put` operations of the batch.
+    pub puts: usize,
+    /// The number of `Delete` operations of the batch.
+    pub deletes: usize,
+    /// For each `DeletePrefix` or `DeleteRange` operation that was expanded into the
+    /// deletion of single keys, the number of these keys. Backends deleting prefixes and
+    /// ranges natively leave it empty.
+    pub delete_prefixes_expanded: Vec<usize>,
+    /// The number of bytes of keys and values sent to the database, including those of a
+    /// journal.
+    pub bytes_written: usize,
+    /// The number of write requests sent to the database.
+    pub round_trips: usize,
+    /// The number of write requests that were retried.
+    pub retries: usize,
+}
+
+impl BatchStats {
+    /// Creates the statistics of a batch written in a single round trip without retry.
+    pub fn from_batch(batch: &Batch) -> Self {
+        let mut stats = Self::count_operations(batch);
+        stats.bytes_written = batch.size();
+        stats.round_trips = 1;
+        stats
+    }
+
+    /// Creates statistics with only the numbers of operations of the batch.
+    pub fn count_operations(batch: &Batch) -> Self {
+        let mut stats = Self::default();
+        for operation in &batch.operations {
+            match operation {
+                WriteOperation::Put { .. } => stats.puts += 1,
+                WriteOperation::Delete { .. } => stats.deletes += 1,
+                WriteOperation::DeletePrefix { .. } | WriteOperation::DeleteRange { .. } => (),
+            }
+        }
+        stats
+    }
+}
+
+impl AddAssign for BatchStats {
+    fn add_assign(&mut self, other: Self) {
+        self.puts += other.puts;
+        self.deletes += other.deletes;
+        self.delete_prefixes_expanded
+            .extend(other.delete_prefixes_expanded);
+        self.bytes_written += other.bytes_written;
+        self.round_trips += other.round_trips;
+        self.retries += other.retries;
+    }
+}
+
 /// A batch of deletions and insertions that operate on disjoint keys, thus can be
 /// executed in any order.
 #[derive(Default, Serialize, Deserialize)]
@@ -76,19 +169,31 @@ pub struct SimpleUnorderedBatch {
     pub insertions: Vec<(Vec<u8>, Vec<u8>)>,
 }
 
-/// An unordered batch of deletions and insertions, together with a set of key-prefixes to
-/// delete. Key-prefix deletions must happen before the insertions and the deletions.
+/// An unordered batch of deletions and insertions, together with a set of key-prefixes
+/// and of key ranges to delete. Key-prefix and key-range deletions must happen before the
+/// insertions and the deletions.
 #[derive(Default, Serialize, Deserialize)]
 pub struct UnorderedBatch {
     /// The key-prefix deletions.
     pub key_prefix_deletions: Vec<Vec<u8>>,
     /// The batch of deletions and insertions.
     pub simple_unordered_batch: SimpleUnorderedBatch,
+    /// The key-range deletions, as pairs `(start, end)` with `end` excluded. They are
+    /// expanded into deletions of keys before the batch is serialized, so that the
+    /// format of the journals is unchanged.
+    #[serde(skip)]
+    pub key_range_deletions: Vec<(Vec<u8>, Vec<u8>)>,
 }
 
 impl UnorderedBatch {
     /// From an `UnorderedBatch`, creates a [`SimpleUnorderedBatch`] that does not contain the
-    /// `key_prefix_deletions`. This requires accessing the database to eliminate them.
+    /// `key_prefix_deletions` nor the `key_range_deletions`. This requires accessing the
+    /// database to eliminate them.
+    ///
+    /// The keys of the deleted prefixes and ranges that are inserted again by the batch
+    /// are not deleted, and no key is deleted twice. Since the deletions and the insertions are
+    /// then disjoint, they can be split among several requests and executed in any order
+    /// without erasing an inserted value.
     pub async fn expand_delete_prefixes<DB: DeletePrefixExpander>(
         self,
         db: &DB,
@@ -99,11 +204,19 @@ impl UnorderedBatch {
         }
         let insertions = self.simple_unordered_batch.insertions;
         let mut deletions = self.simple_unordered_batch.deletions;
+        let mut delete_set = deletions.iter().cloned().collect::<HashSet<_>>();
         for key_prefix in self.key_prefix_deletions {
             for short_key in db.expand_delete_prefix(&key_prefix).await?.iter() {
                 let mut key = key_prefix.clone();
                 key.extend(short_key);
-                if !insert_set.contains(&key) {
+                if !insert_set.contains(&key) && delete_set.insert(key.clone()) {
+                    deletions.push(key);
+                }
+            }
+        }
+        for (start, end) in self.key_range_deletions {
+            for key in db.expand_delete_range(&start, &end).await? {
+                if !insert_set.contains(&key) && delete_set.insert(key.clone()) {
                     deletions.push(key);
                 }
             }
@@ -152,14 +265,45 @@ impl UnorderedBatch {
         Ok(())
     }
 
+    /// Modifies an [`UnorderedBatch`] so that it does not contain `key_range_deletions`,
+    /// by replacing them with the deletions of the keys of the ranges that are not
+    /// inserted again by the batch. This requires accessing the database.
+    pub async fn expand_delete_ranges<DB: DeletePrefixExpander>(
+        &mut self,
+        db: &DB,
+    ) -> Result<(), DB::Error> {
+        if self.key_range_deletions.is_empty() {
+            return Ok(());
+        }
+        let batch = &mut self.simple_unordered_batch;
+        let inserted_keys = batch
+            .insertions
+            .iter()
+            .map(|x| x.0.clone())
+            .collect::<HashSet<_>>();
+        let mut deleted_keys = batch.deletions.iter().cloned().collect::<HashSet<_>>();
+        for (start, end) in std::mem::take(&mut self.key_range_deletions) {
+            for key in db.expand_delete_range(&start, &end).await? {
+                if !inserted_keys.contains(&key) && deleted_keys.insert(key.clone()) {
+                    batch.deletions.push(key);
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// The total number of entries of the batch.
     pub fn len(&self) -> usize {
-        self.key_prefix_deletions.len() + self.simple_unordered_batch.len()
+        self.key_prefix_deletions.len()
+            + self.key_range_deletions.len()
+            + self.simple_unordered_batch.len()
     }
 
     /// Tests whether the batch is empty or not
     pub fn is_empty(&self) -> bool {
-        self.key_prefix_deletions.is_empty() && self.simple_unordered_batch.is_empty()
+        self.key_prefix_deletions.is_empty()
+            && self.key_range_deletions.is_empty()
+            && self.simple_unordered_batch.is_empty()
     }
 }
 
@@ -182,6 +326,42 @@ fn is_prefix_matched(key_prefix_set: &BTreeSet<Vec<u8>>, key: &[u8]) -> bool {
     false
 }
 
+/// Checks if `key` belongs to one of the ranges of `key_range_map`, which maps the start
+/// of each range to its (excluded) end. The ranges must be disjoint.
+fn is_range_matched(key_range_map: &BTreeMap<Vec<u8>, Vec<u8>>, key: &[u8]) -> bool {
+    let range = (Bound::Unbounded, Bound::Included(key.to_vec()));
+    match key_range_map.range(range).next_back() {
+        Some((_, end)) => key < &end[..],
+        None => false,
+    }
+}
+
+/// Inserts the range from `start` to `end` (excluded) into `key_range_map`, merging it
+/// with the ranges it overlaps so that the ranges remain disjoint.
+fn insert_key_range(key_range_map: &mut BTreeMap<Vec<u8>, Vec<u8>>, start: Vec<u8>, end: Vec<u8>) {
+    let mut start = start;
+    let mut end = end;
+    let range = (Bound::Unbounded, Bound::Included(start.clone()));
+    if let Some((previous_start, previous_end)) = key_range_map.range(range).next_back() {
+        if previous_end >= &start {
+            start = previous_start.clone();
+        }
+    }
+    let overlapping_starts = key_range_map
+        .range(start.clone()..=end.clone())
+        .map(|x| x.0.clone())
+        .collect::<Vec<_>>();
+    for overlapping_start in overlapping_starts {
+        let overlapping_end = key_range_map
+            .remove(&overlapping_start)
+            .expect("the start of the range was just found");
+        if overlapping_end > end {
+            end = overlapping_end;
+        }
+    }
+    key_range_map.insert(start, end);
+}
+
 impl Batch {
     /// Creates an empty batch.
     pub fn new() -> Self {
@@ -196,6 +376,7 @@ impl Batch {
                 WriteOperation::Delete { key } => key.len(),
                 WriteOperation::Put { key, value } => key.len() + value.len(),
                 WriteOperation::DeletePrefix { key_prefix } => key_prefix.len(),
+                WriteOperation::DeleteRange { start, end } => start.len() + end.len(),
             })
             .sum()
     }
@@ -210,6 +391,49 @@ impl Batch {
         self.operations.len()
     }
 
+    /// Returns the number of operations in this [`Batch`], like
+    /// [`Batch::num_operations`].
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// The number of bytes of the batch once serialized, with the lengths encoded as in
+    /// BCS. Unlike [`Batch::size`], this accounts for the lengths of the keys and values
+    /// and for the tags of the operations, so that callers can split a batch before it
+    /// exceeds the limits of a backend.
+    pub fn size_estimate(&self) -> usize {
+        let operations_size = self
+            .operations
+            .iter()
+            .map(WriteOperation::size_estimate)
+            .sum::<usize>();
+        get_uleb128_size(self.operations.len()) + operations_size
+    }
+
+    /// Splits the batch into consecutive batches whose [`Batch::size_estimate`] does
+    /// not exceed `max_bytes`, except for the batches made of a single operation larger
+    /// than that. The order of the operations is preserved, but writing the batches one
+    /// after another is of course not atomic.
+    pub fn split_at_size(self, max_bytes: usize) -> Vec<Batch> {
+        let mut batches = Vec::new();
+        let mut batch = Batch::new();
+        let mut operations_size = 0;
+        for operation in self.operations {
+            let operation_size = operation.size_estimate();
+            let new_size = get_uleb128_size(batch.len() + 1) + operations_size + operation_size;
+            if !batch.is_empty() && new_size > max_bytes {
+                batches.push(std::mem::take(&mut batch));
+                operations_size = 0;
+            }
+            batch.operations.push(operation);
+            operations_size += operation_size;
+        }
+        if !batch.is_empty() {
+            batches.push(batch);
+        }
+        batches
+    }
+
     /// Builds a batch from a builder function.
     pub async fn build<F>(builder: F) -> Result<Self, ViewError>
     where
@@ -223,7 +447,15 @@ impl Batch {
     /// Simplifies the batch by removing operations that are overwritten by others.
     ///
     /// A key may appear multiple times in the batch, as an insert, a delete
-    /// or matched by a delete prefix.
+    /// or matched by a delete prefix. The result is in a canonical form, where the
+    /// operations take effect in the order of the original batch:
+    /// * the deleted prefixes are sorted, and none of them is a prefix of another one;
+    /// * the deletions and the insertions are sorted, and each key appears at most once
+    ///   among them;
+    /// * the deleted ranges are sorted, disjoint and not empty;
+    /// * no deletion is matched by a deleted prefix or range, since it would be redundant;
+    /// * an insertion matched by a deleted prefix or range comes from a `Put` after the
+    ///   `DeletePrefix` or the `DeleteRange`, so it must survive the deletion.
     /// ```rust
     /// # use linera_views::batch::Batch;
     /// let mut batch = Batch::new();
@@ -237,12 +469,15 @@ impl Batch {
     pub fn simplify(self) -> UnorderedBatch {
         let mut delete_and_insert_map = BTreeMap::new();
         let mut delete_prefix_set = BTreeSet::new();
+        let mut delete_range_map = BTreeMap::new();
         for operation in self.operations {
             match operation {
                 WriteOperation::Delete { key } => {
-                    // If `key` is matched by a deleted prefix, then remove any inserted
-                    // value. Otherwise, add the key to the set of deletions.
-                    if is_prefix_matched(&delete_prefix_set, &key) {
+                    // If `key` is matched by a deleted prefix or range, then remove any
+                    // inserted value. Otherwise, add the key to the set of deletions.
+                    if is_prefix_matched(&delete_prefix_set, &key)
+                        || is_range_matched(&delete_range_map, &key)
+                    {
                         delete_and_insert_map.remove(&key);
                     } else {
                         delete_and_insert_map.insert(key, None);
@@ -278,9 +513,24 @@ impl Batch {
                     // Then, insert the new key prefix.
                     delete_prefix_set.insert(key_prefix);
                 }
+                WriteOperation::DeleteRange { start, end } => {
+                    if start >= end {
+                        continue;
+                    }
+                    // Remove the previous deletions and insertions in the range.
+                    let keys = delete_and_insert_map
+                        .range(start.clone()..end.clone())
+                        .map(|x| x.0.to_vec())
+                        .collect::<Vec<_>>();
+                    for key in keys {
+                        delete_and_insert_map.remove(&key);
+                    }
+                    insert_key_range(&mut delete_range_map, start, end);
+                }
             }
         }
         let key_prefix_deletions = delete_prefix_set.into_iter().collect();
+        let key_range_deletions = delete_range_map.into_iter().collect();
         let mut deletions = Vec::new();
         let mut insertions = Vec::new();
         for (key, val) in delete_and_insert_map {
@@ -296,9 +546,35 @@ impl Batch {
         UnorderedBatch {
             key_prefix_deletions,
             simple_unordered_batch,
+            key_range_deletions,
         }
     }
 
+    /// Checks the sizes of the keys, key prefixes and range bounds of the batch against
+    /// `max_key_size`, and the sizes of its values against `max_value_size`, so that a
+    /// batch exceeding the limits of a backend can be rejected before being sent.
+    pub fn check_sizes(&self, max_key_size: usize, max_value_size: usize) -> Result<(), ViewError> {
+        for operation in &self.operations {
+            match operation {
+                WriteOperation::Delete { key } => {
+                    ensure!(key.len() <= max_key_size, ViewError::KeyTooLong);
+                }
+                WriteOperation::DeletePrefix { key_prefix } => {
+                    ensure!(key_prefix.len() <= max_key_size, ViewError::KeyTooLong);
+                }
+                WriteOperation::Put { key, value } => {
+                    ensure!(key.len() <= max_key_size, ViewError::KeyTooLong);
+                    ensure!(value.len() <= max_value_size, ViewError::TooLargeValue);
+                }
+                WriteOperation::DeleteRange { start, end } => {
+                    ensure!(start.len() <= max_key_size, ViewError::KeyTooLong);
+                    ensure!(end.len() <= max_key_size, ViewError::KeyTooLong);
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Checks the size of the values of the batch.
     pub fn check_value_size(&self, max_value_size: usize) -> bool {
         for operation in &self.operations {
@@ -328,6 +604,24 @@ impl Batch {
         Ok(())
     }
 
+    /// Adds the insertion of a key-value pair into the batch with a value serialized by
+    /// the codec `K`.
+    /// ```rust
+    /// # use linera_views::{batch::Batch, common::BcsCodec};
+    /// let mut batch = Batch::new();
+    /// batch.put_key_value_with_codec::<BcsCodec>(vec![0, 1], &(34 as u128));
+    /// ```
+    #[inline]
+    pub fn put_key_value_with_codec<K: Codec>(
+        &mut self,
+        key: Vec<u8>,
+        value: &impl Serialize,
+    ) -> Result<(), ViewError> {
+        let bytes = K::encode(value)?;
+        self.put_key_value_bytes(key, bytes);
+        Ok(())
+    }
+
     /// Adds the insertion of a `(key, value)` pair into the batch with `value` a vector of `u8`.
     /// ```rust
     /// # use linera_views::batch::Batch;
@@ -361,12 +655,25 @@ impl Batch {
         self.operations
             .push(WriteOperation::DeletePrefix { key_prefix });
     }
+
+    /// Inserts the deletion of the keys from `start` (included) to `end` (excluded) into
+    /// the batch.
+    /// ```rust
+    /// # use linera_views::batch::Batch;
+    /// let mut batch = Batch::new();
+    /// batch.delete_key_range(vec![0, 1], vec![0, 5]);
+    /// ```
+    #[inline]
+    pub fn delete_key_range(&mut self, start: Vec<u8>, end: Vec<u8>) {
+        self.operations
+            .push(WriteOperation::DeleteRange { start, end });
+    }
 }
 
-/// A trait to expand delete_prefix operations.
-/// Certain databases (e.g. DynamoDB) do not support the deletion by prefix.
-/// Thus we need to access the databases in order to replace a `DeletePrefix`
-/// by a vector of the keys to be removed.
+/// A trait to expand delete_prefix and delete_range operations.
+/// Certain databases (e.g. DynamoDB) do not support the deletion by prefix or by range.
+/// Thus we need to access the databases in order to replace a `DeletePrefix` or a
+/// `DeleteRange` by a vector of the keys to be removed.
 #[trait_variant::make(DeletePrefixExpander: Send)]
 pub trait LocalDeletePrefixExpander {
     /// The error type that can happen when expanding the key_prefix.
@@ -374,6 +681,14 @@ pub trait LocalDeletePrefixExpander {
 
     /// Returns the list of keys to be appended to the list.
     async fn expand_delete_prefix(&self, key_prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error>;
+
+    /// Returns the complete keys `key` such that `start <= key < end`. Implementations
+    /// without a range query can use [`crate::common::expand_delete_range`].
+    async fn expand_delete_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error>;
 }
 
 /// A notion of batch useful for certain computations (notably journaling).
@@ -590,6 +905,7 @@ impl SimplifiedBatch for UnorderedBatch {
         batch: Batch,
     ) -> Result<Self, S::Error> {
         let mut unordered_batch = batch.simplify();
+        unordered_batch.expand_delete_ranges(&store).await?;
         unordered_batch
             .expand_colliding_prefix_deletions(&store)
             .await?;
@@ -643,6 +959,7 @@ mod tests {
     use linera_views::{
         batch::{Batch, SimpleUnorderedBatch, UnorderedBatch},
         context::{create_test_memory_context, Context},
+        views::ViewError,
     };
 
     #[test]
@@ -697,6 +1014,167 @@ mod tests {
         assert!(unordered_batch.simple_unordered_batch.insertions.is_empty());
     }
 
+    #[test]
+    fn test_simplify_batch_put_after_delete_prefix() {
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![1, 2, 3], vec![1]);
+        batch.delete_key_prefix(vec![1, 2]);
+        batch.put_key_value_bytes(vec![1, 2, 3], vec![2]);
+        batch.delete_key(vec![1, 2, 4]);
+        batch.put_key_value_bytes(vec![1, 2, 5], vec![3]);
+        batch.delete_key_prefix(vec![1, 2, 5]);
+        batch.put_key_value_bytes(vec![1, 2, 5, 0], vec![4]);
+        let unordered_batch = batch.simplify();
+        assert_eq!(unordered_batch.key_prefix_deletions, vec![vec![1, 2]]);
+        assert!(unordered_batch.simple_unordered_batch.deletions.is_empty());
+        assert_eq!(
+            unordered_batch.simple_unordered_batch.insertions,
+            vec![(vec![1, 2, 3], vec![2]), (vec![1, 2, 5, 0], vec![4])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expand_delete_prefixes_keeps_put_keys() {
+        let context = create_test_memory_context();
+        let mut batch = Batch::new();
+        for i in 0..4 {
+            batch.put_key_value_bytes(vec![1, 2, i], vec![i]);
+        }
+        context.write_batch(batch).await.unwrap();
+        let mut batch = Batch::new();
+        batch.delete_key_prefix(vec![1, 2]);
+        batch.put_key_value_bytes(vec![1, 2, 1], vec![5]);
+        batch.delete_key(vec![1, 3]);
+        let simple_unordered_batch = batch
+            .simplify()
+            .expand_delete_prefixes(&context)
+            .await
+            .unwrap();
+        let mut deletions = simple_unordered_batch.deletions;
+        deletions.sort();
+        assert_eq!(
+            deletions,
+            vec![vec![1, 2, 0], vec![1, 2, 2], vec![1, 2, 3], vec![1, 3]]
+        );
+        assert_eq!(
+            simple_unordered_batch.insertions,
+            vec![(vec![1, 2, 1], vec![5])]
+        );
+    }
+
+    #[test]
+    fn test_simplify_batch_delete_range() {
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![1, 3], vec![]);
+        batch.delete_key(vec![1, 4]);
+        batch.delete_key_range(vec![1, 2], vec![1, 5]);
+        batch.delete_key_range(vec![1, 5], vec![1, 7]);
+        batch.delete_key_range(vec![3], vec![2]);
+        batch.delete_key(vec![1, 6, 2]);
+        batch.put_key_value_bytes(vec![1, 4], vec![4]);
+        let unordered_batch = batch.simplify();
+        assert_eq!(
+            unordered_batch.key_range_deletions,
+            vec![(vec![1, 2], vec![1, 7])]
+        );
+        assert!(unordered_batch.key_prefix_deletions.is_empty());
+        assert!(unordered_batch.simple_unordered_batch.deletions.is_empty());
+        assert_eq!(
+            unordered_batch.simple_unordered_batch.insertions,
+            vec![(vec![1, 4], vec![4])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expand_delete_ranges_keeps_put_keys() {
+        let context = create_test_memory_context();
+        let mut batch = Batch::new();
+        for i in 0..6 {
+            batch.put_key_value_bytes(vec![1, 2, i], vec![i]);
+        }
+        context.write_batch(batch).await.unwrap();
+        let mut batch = Batch::new();
+        batch.delete_key_range(vec![1, 2, 1], vec![1, 2, 4]);
+        batch.put_key_value_bytes(vec![1, 2, 2], vec![5]);
+        let simple_unordered_batch = batch
+            .simplify()
+            .expand_delete_prefixes(&context)
+            .await
+            .unwrap();
+        assert_eq!(
+            simple_unordered_batch.deletions,
+            vec![vec![1, 2, 1], vec![1, 2, 3]]
+        );
+        assert_eq!(
+            simple_unordered_batch.insertions,
+            vec![(vec![1, 2, 2], vec![5])]
+        );
+    }
+
+    #[test]
+    fn test_batch_check_sizes() {
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![1; 4], vec![2; 10]);
+        batch.delete_key_range(vec![1], vec![1; 4]);
+        assert!(batch.check_sizes(4, 10).is_ok());
+        assert!(matches!(
+            batch.check_sizes(3, 10),
+            Err(ViewError::KeyTooLong)
+        ));
+        assert!(matches!(
+            batch.check_sizes(4, 9),
+            Err(ViewError::TooLargeValue)
+        ));
+        let mut batch = Batch::new();
+        batch.delete_key_prefix(vec![1; 5]);
+        assert!(matches!(
+            batch.check_sizes(4, 10),
+            Err(ViewError::KeyTooLong)
+        ));
+    }
+
+    #[test]
+    fn test_batch_size_estimate() {
+        let mut batch = Batch::new();
+        assert_eq!(batch.size_estimate(), 1);
+        batch.put_key_value(vec![1, 2], &34u64).unwrap();
+        assert_eq!(batch.size_estimate(), 1 + (1 + 1 + 2 + 1 + 8));
+        batch.delete_key(vec![1, 3, 3]);
+        batch.delete_key_prefix(vec![]);
+        batch.put_key_value_bytes(vec![0; 200], vec![7; 300]);
+        assert_eq!(batch.len(), 4);
+        assert_eq!(batch.num_operations(), 4);
+        assert_eq!(batch.size(), 2 + 8 + 3 + 200 + 300);
+        let expected = 1 + (1 + 1 + 2 + 1 + 8) + (1 + 1 + 3) + (1 + 1) + (1 + 2 + 200 + 2 + 300);
+        assert_eq!(batch.size_estimate(), expected);
+    }
+
+    #[test]
+    fn test_batch_split_at_size() {
+        let mut batch = Batch::new();
+        for i in 0..10u8 {
+            batch.put_key_value_bytes(vec![i], vec![i; 10]);
+            batch.delete_key(vec![i, i]);
+        }
+        batch.delete_key_prefix(vec![5]);
+        batch.put_key_value_bytes(vec![11], vec![0; 100]);
+        let operations = batch.operations.clone();
+        let batches = batch.split_at_size(50);
+        for batch in &batches {
+            assert!(batch.size_estimate() <= 50 || batch.len() == 1);
+        }
+        // The large value does not fit with other operations.
+        assert_eq!(batches.last().unwrap().len(), 1);
+        assert_eq!(
+            batches
+                .into_iter()
+                .flat_map(|batch| batch.operations)
+                .collect::<Vec<_>>(),
+            operations
+        );
+        assert!(Batch::new().split_at_size(50).is_empty());
+    }
+
     #[tokio::test]
     async fn test_simplify_batch5() {
         let context = create_test_memory_context();
@@ -732,6 +1210,7 @@ mod tests {
         let mut unordered_batch = UnorderedBatch {
             simple_unordered_batch,
             key_prefix_deletions,
+            key_range_deletions: Vec::new(),
         };
         unordered_batch
             .expand_colliding_prefix_deletions(&context)
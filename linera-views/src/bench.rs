@@ -0,0 +1,304 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Workloads measuring the latency and the throughput of the operations of a key-value
+//! store, so that backends can be compared on the same numbers.
+//!
+//! The workloads run on the keys starting with [`BENCH_PREFIX`], which are deleted at the
+//! end of each workload.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+
+use crate::{
+    batch::Batch,
+    random::{make_deterministic_rng, DeterministicRng},
+    store::{KeyValueIterable as _, LocalKeyValueStore},
+    views::ViewError,
+};
+
+/// The prefix of the keys used by the workloads.
+pub const BENCH_PREFIX: &[u8] = &[0xbe];
+
+/// The number of keys under each prefix of the prefix scans.
+const KEYS_PER_SCANNED_PREFIX: usize = 256;
+
+/// The operations measured by the workloads.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Workload {
+    /// Reads of single keys, chosen with the skew of the configuration.
+    PointReads,
+    /// Scans of the key-value pairs of consecutive prefixes, each holding 256 keys.
+    PrefixScans,
+    /// Writes of batches of key-value pairs, chosen with the skew of the configuration.
+    BatchedWrites,
+}
+
+impl Workload {
+    /// All the workloads, in the order in which they are run by [`run_all_workloads`].
+    pub const ALL: [Workload; 3] = [
+        Workload::PointReads,
+        Workload::PrefixScans,
+        Workload::BatchedWrites,
+    ];
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Workload::PointReads => "point reads",
+            Workload::PrefixScans => "prefix scans",
+            Workload::BatchedWrites => "batched writes",
+        };
+        formatter.write_str(name)
+    }
+}
+
+/// The parameters of the workloads.
+#[derive(Clone, Debug)]
+pub struct WorkloadConfig {
+    /// The number of keys written before the reads and scans.
+    pub num_keys: usize,
+    /// The number of measured operations.
+    pub num_operations: usize,
+    /// The size of the keys, which must be at least 9 bytes.
+    pub key_size: usize,
+    /// The size of the values.
+    pub value_size: usize,
+    /// The number of key-value pairs in each written batch.
+    pub batch_size: usize,
+    /// The exponent of the Zipf distribution of the accessed keys. Zero gives a uniform
+    /// distribution, and larger values concentrate the accesses on fewer keys.
+    pub zipf_exponent: f64,
+}
+
+impl Default for WorkloadConfig {
+    fn default() -> Self {
+        Self {
+            num_keys: 10_000,
+            num_operations: 1_000,
+            key_size: 32,
+            value_size: 1_000,
+            batch_size: 100,
+            zipf_exponent: 0.0,
+        }
+    }
+}
+
+/// The measurements of a workload.
+#[derive(Clone, Debug)]
+pub struct BenchReport {
+    /// The measured workload.
+    pub workload: Workload,
+    /// The median latency of an operation.
+    pub p50: Duration,
+    /// The 95th percentile of the latency of an operation.
+    pub p95: Duration,
+    /// The 99th percentile of the latency of an operation.
+    pub p99: Duration,
+    /// The number of operations per second.
+    pub throughput: f64,
+}
+
+impl BenchReport {
+    /// Computes the report from the latencies of the operations and the total time.
+    fn new(workload: Workload, mut latencies: Vec<Duration>, total: Duration) -> Self {
+        latencies.sort();
+        let percentile = |p: usize| {
+            let index = (latencies.len() * p).div_ceil(100).saturating_sub(1);
+            latencies.get(index).copied().unwrap_or_default()
+        };
+        let throughput = if total.is_zero() {
+            0.0
+        } else {
+            latencies.len() as f64 / total.as_secs_f64()
+        };
+        Self {
+            workload,
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            throughput,
+        }
+    }
+}
+
+/// Samples the indices of the keys according to a Zipf distribution.
+struct ZipfSampler {
+    /// The cumulative distribution of the indices.
+    cumulative: Vec<f64>,
+}
+
+impl ZipfSampler {
+    fn new(num_keys: usize, exponent: f64) -> Self {
+        let mut total = 0.0;
+        let cumulative = (1..=num_keys)
+            .map(|rank| {
+                total += (rank as f64).powf(-exponent);
+                total
+            })
+            .collect();
+        Self { cumulative }
+    }
+
+    fn sample(&self, rng: &mut DeterministicRng) -> usize {
+        let total = self.cumulative.last().copied().unwrap_or_default();
+        let target = rng.gen::<f64>() * total;
+        self.cumulative
+            .partition_point(|&value| value < target)
+            .min(self.cumulative.len() - 1)
+    }
+}
+
+/// Returns the key of the given index.
+fn get_key(config: &WorkloadConfig, index: usize) -> Vec<u8> {
+    let mut key = BENCH_PREFIX.to_vec();
+    key.extend((index as u64).to_be_bytes());
+    key.resize(config.key_size, 0);
+    key
+}
+
+/// Returns a random value.
+fn get_value(config: &WorkloadConfig, rng: &mut DeterministicRng) -> Vec<u8> {
+    (0..config.value_size).map(|_| rng.gen()).collect()
+}
+
+/// Writes the `num_keys` keys of the configuration.
+async fn populate<S: LocalKeyValueStore>(
+    store: &S,
+    config: &WorkloadConfig,
+    rng: &mut DeterministicRng,
+) -> Result<(), ViewError> {
+    let indices = (0..config.num_keys).collect::<Vec<_>>();
+    for chunk in indices.chunks(config.batch_size.max(1)) {
+        let mut batch = Batch::new();
+        for &index in chunk {
+            batch.put_key_value_bytes(get_key(config, index), get_value(config, rng));
+        }
+        store.write_batch(batch).await?;
+    }
+    Ok(())
+}
+
+/// Deletes the keys used by the workloads.
+async fn clear<S: LocalKeyValueStore>(store: &S) -> Result<(), ViewError> {
+    let mut batch = Batch::new();
+    batch.delete_key_prefix(BENCH_PREFIX.to_vec());
+    store.write_batch(batch).await?;
+    Ok(())
+}
+
+/// Runs the `workload` against the `store` and measures its operations.
+pub async fn run_workload<S: LocalKeyValueStore>(
+    store: &S,
+    workload: Workload,
+    config: &WorkloadConfig,
+) -> Result<BenchReport, ViewError> {
+    assert!(
+        config.key_size > BENCH_PREFIX.len() + 8,
+        "the keys must be longer than the prefix and the index"
+    );
+    assert!(config.num_keys > 0, "the workloads need some keys");
+    let mut rng = make_deterministic_rng();
+    let sampler = ZipfSampler::new(config.num_keys, config.zipf_exponent);
+    if workload != Workload::BatchedWrites {
+        populate(store, config, &mut rng).await?;
+    }
+    let num_prefixes = config.num_keys.div_ceil(KEYS_PER_SCANNED_PREFIX);
+    let mut latencies = Vec::with_capacity(config.num_operations);
+    let start = Instant::now();
+    for operation in 0..config.num_operations {
+        let latency = match workload {
+            Workload::PointReads => {
+                let key = get_key(config, sampler.sample(&mut rng));
+                let measurement = Instant::now();
+                store.read_value_bytes(&key).await?;
+                measurement.elapsed()
+            }
+            Workload::PrefixScans => {
+                let index = (operation % num_prefixes) * KEYS_PER_SCANNED_PREFIX;
+                // The keys of a prefix share all the bytes of their index but the last one.
+                let key_prefix = get_key(config, index)[..BENCH_PREFIX.len() + 7].to_vec();
+                let measurement = Instant::now();
+                let key_values = store.find_key_values_by_prefix(&key_prefix).await?;
+                for key_value in key_values.iterator() {
+                    key_value?;
+                }
+                measurement.elapsed()
+            }
+            Workload::BatchedWrites => {
+                let mut batch = Batch::new();
+                for _ in 0..config.batch_size {
+                    let key = get_key(config, sampler.sample(&mut rng));
+                    batch.put_key_value_bytes(key, get_value(config, &mut rng));
+                }
+                let measurement = Instant::now();
+                store.write_batch(batch).await?;
+                measurement.elapsed()
+            }
+        };
+        latencies.push(latency);
+    }
+    let total = start.elapsed();
+    clear(store).await?;
+    Ok(BenchReport::new(workload, latencies, total))
+}
+
+/// Runs all the workloads against the `store`.
+pub async fn run_all_workloads<S: LocalKeyValueStore>(
+    store: &S,
+    config: &WorkloadConfig,
+) -> Result<Vec<BenchReport>, ViewError> {
+    let mut reports = Vec::new();
+    for workload in Workload::ALL {
+        reports.push(run_workload(store, workload, config).await?);
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{memory::MemoryStore, store::TestKeyValueStore as _};
+
+    #[tokio::test]
+    async fn test_run_all_workloads_on_memory() {
+        let store = MemoryStore::new_test_store().await.unwrap();
+        let config = WorkloadConfig {
+            num_keys: 600,
+            num_operations: 50,
+            key_size: 16,
+            value_size: 10,
+            batch_size: 20,
+            zipf_exponent: 1.2,
+        };
+        let reports = run_all_workloads(&store, &config).await.unwrap();
+        let workloads = reports
+            .iter()
+            .map(|report| report.workload)
+            .collect::<Vec<_>>();
+        assert_eq!(workloads, Workload::ALL);
+        for report in reports {
+            assert!(report.p50 <= report.p95 && report.p95 <= report.p99);
+            assert!(report.throughput > 0.0);
+        }
+        // The keys of the workloads are deleted.
+        let keys = store.find_keys_by_prefix(BENCH_PREFIX).await.unwrap();
+        assert!(keys.is_empty());
+    }
+
+    #[test]
+    fn test_zipf_sampler_is_skewed() {
+        let mut rng = make_deterministic_rng();
+        let sampler = ZipfSampler::new(100, 2.0);
+        let mut counts = [0; 100];
+        for _ in 0..1000 {
+            counts[sampler.sample(&mut rng)] += 1;
+        }
+        assert!(counts[0] > counts[1] && counts[1] > counts[10]);
+    }
+}
@@ -5,15 +5,23 @@
 
 use std::{
     collections::BTreeSet,
+    io,
     ops::{
         Bound,
         Bound::{Excluded, Included, Unbounded},
     },
 };
 
-use serde::de::DeserializeOwned;
+use futures::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use linera_base::ensure;
+use rand::{rngs::SmallRng, Rng as _, SeedableRng as _};
+use serde::{de::DeserializeOwned, Serialize};
 
-use crate::views::ViewError;
+use crate::{
+    batch::{Batch, DeletePrefixExpander},
+    store::{ReadableKeyValueStore, WritableKeyValueStore},
+    views::ViewError,
+};
 
 #[doc(hidden)]
 pub type HasherOutputSize = <sha3::Sha3_256 as sha3::digest::OutputSizeUser>::OutputSize;
@@ -101,6 +109,34 @@ pub(crate) fn get_interval(key_prefix: Vec<u8>) -> (Bound<Vec<u8>>, Bound<Vec<u8
     (Included(key_prefix), upper_bound)
 }
 
+/// Expands the deletion of the keys from `start` (included) to `end` (excluded) by
+/// scanning the keys of their longest common prefix. This is the fallback for the
+/// databases that can neither delete nor query a range of keys natively.
+pub async fn expand_delete_range<DB: DeletePrefixExpander>(
+    db: &DB,
+    start: &[u8],
+    end: &[u8],
+) -> Result<Vec<Vec<u8>>, DB::Error> {
+    let mut keys = Vec::new();
+    if start >= end {
+        return Ok(keys);
+    }
+    let prefix_len = start
+        .iter()
+        .zip(end)
+        .take_while(|(byte1, byte2)| byte1 == byte2)
+        .count();
+    let key_prefix = &start[..prefix_len];
+    for short_key in db.expand_delete_prefix(key_prefix).await? {
+        let mut key = key_prefix.to_vec();
+        key.extend(short_key);
+        if start <= &key[..] && &key[..] < end {
+            keys.push(key);
+        }
+    }
+    Ok(keys)
+}
+
 /// Deserializes an Optional vector of u8
 pub(crate) fn from_bytes_option<V: DeserializeOwned, E>(
     key_opt: &Option<Vec<u8>>,
@@ -305,6 +341,41 @@ impl CustomSerialize for u128 {
     }
 }
 
+/// The serialization format of the values stored by the views, so that values which are
+/// already encoded, e.g. in JSON or protobuf, are not encoded a second time with BCS.
+pub trait Codec: Send + Sync + 'static {
+    /// The name of the codec, reported in [`ViewError::Serialization`].
+    const NAME: &'static str;
+
+    /// Serializes the value.
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ViewError>;
+
+    /// Deserializes the bytes.
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ViewError>;
+}
+
+/// The default [`Codec`], using BCS.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BcsCodec;
+
+impl Codec for BcsCodec {
+    const NAME: &'static str = "bcs";
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ViewError> {
+        bcs::to_bytes(value).map_err(|error| ViewError::Serialization {
+            codec: Self::NAME,
+            error: error.to_string(),
+        })
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ViewError> {
+        bcs::from_bytes(bytes).map_err(|error| ViewError::Serialization {
+            codec: Self::NAME,
+            error: error.to_string(),
+        })
+    }
+}
+
 /// This computes the offset of the BCS serialization of a vector.
 /// The formula that should be satisfied is
 /// serialized_size(vec![v_1, ...., v_n]) = get_uleb128_size(n)
@@ -319,6 +390,200 @@ pub(crate) const fn get_uleb128_size(len: usize) -> usize {
     expo
 }
 
+/// The magic bytes starting a dump of key-value pairs.
+const DUMP_MAGIC: &[u8] = b"LINERAKV";
+
+/// The version of the format of the dumps of key-value pairs.
+const DUMP_VERSION: u8 = 1;
+
+/// Writes the header of a dump of key-value pairs: the magic bytes and the version.
+pub(crate) async fn write_dump_header<W: AsyncWrite + Unpin>(writer: &mut W) -> io::Result<()> {
+    writer.write_all(DUMP_MAGIC).await?;
+    writer.write_all(&[DUMP_VERSION]).await
+}
+
+/// Writes a key-value pair of a dump, with the key and the value each prefixed by their
+/// length as a little-endian `u32`.
+pub(crate) async fn write_dump_entry<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    key: &[u8],
+    value: &[u8],
+) -> io::Result<()> {
+    for bytes in [key, value] {
+        let len = u32::try_from(bytes.len()).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "entry too large for a dump")
+        })?;
+        writer.write_all(&len.to_le_bytes()).await?;
+        writer.write_all(bytes).await?;
+    }
+    Ok(())
+}
+
+/// Reads the header of a dump of key-value pairs and checks its version.
+pub(crate) async fn read_dump_header<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<()> {
+    let mut header = [0; DUMP_MAGIC.len() + 1];
+    reader.read_exact(&mut header).await?;
+    let (magic, version) = header.split_at(DUMP_MAGIC.len());
+    if magic != DUMP_MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a dump of key-value pairs",
+        ));
+    }
+    if version[0] != DUMP_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported dump version {}", version[0]),
+        ));
+    }
+    Ok(())
+}
+
+/// Reads the next key-value pair of a dump, or `None` at the end of the dump. Since the
+/// lengths in the dump are not trusted, the keys longer than `max_key_size` and the values
+/// longer than `max_value_size` are rejected.
+pub(crate) async fn read_dump_entry<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_key_size: usize,
+    max_value_size: usize,
+) -> io::Result<Option<(Vec<u8>, Vec<u8>)>> {
+    // The dump may only end between two entries.
+    let mut len = [0; 4];
+    let mut filled = 0;
+    while filled < len.len() {
+        match reader.read(&mut len[filled..]).await? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => filled += n,
+        }
+    }
+    let key = read_dump_bytes(reader, u32::from_le_bytes(len), max_key_size).await?;
+    reader.read_exact(&mut len).await?;
+    let value = read_dump_bytes(reader, u32::from_le_bytes(len), max_value_size).await?;
+    Ok(Some((key, value)))
+}
+
+/// Reads a key or a value of `len` bytes from a dump. The buffer only grows with the bytes
+/// actually read, so that a corrupted length fails on the end of the dump instead of
+/// allocating it upfront.
+async fn read_dump_bytes<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    len: u32,
+    max_len: usize,
+) -> io::Result<Vec<u8>> {
+    let len = len as usize;
+    if len > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("dump entry of {len} bytes exceeds the limit of {max_len} bytes"),
+        ));
+    }
+    let mut bytes = Vec::new();
+    (&mut *reader)
+        .take(len as u64)
+        .read_to_end(&mut bytes)
+        .await?;
+    if bytes.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+/// The counts returned by [`copy_store`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CopyStats {
+    /// The number of copied key-value pairs.
+    pub keys_copied: usize,
+    /// The number of bytes of the copied keys and values.
+    pub bytes_copied: usize,
+    /// The number of batches written to the destination.
+    pub batches_written: usize,
+    /// The number of key-value pairs read back from the destination and checked.
+    pub keys_verified: usize,
+}
+
+/// Copies the key-value pairs matching the `key_prefix` from `source` to `destination`.
+///
+/// The keys are read by pages of `chunk_size` keys, and each page is written as one
+/// batch, overwriting the values already present in the destination. The `progress`
+/// callback is called after each batch. If `verify_sample_size` is set, a random sample
+/// of that many copied pairs is read back from the destination at the end, and a
+/// difference results in [`ViewError::CopyMismatch`]. A `chunk_size` of zero results in
+/// [`ViewError::ZeroChunkSize`].
+pub async fn copy_store<S, D>(
+    source: &S,
+    destination: &D,
+    key_prefix: &[u8],
+    chunk_size: usize,
+    verify_sample_size: Option<usize>,
+    mut progress: impl FnMut(&CopyStats),
+) -> Result<CopyStats, ViewError>
+where
+    S: ReadableKeyValueStore + Sync,
+    D: ReadableKeyValueStore + WritableKeyValueStore + Sync,
+{
+    ensure!(chunk_size > 0, ViewError::ZeroChunkSize);
+    // The sample only needs to be spread over the copied pairs, so a fixed seed is enough.
+    let mut rng = SmallRng::seed_from_u64(0);
+    let sample_size = verify_sample_size.unwrap_or(0);
+    let mut sample = Vec::with_capacity(sample_size);
+    let mut stats = CopyStats::default();
+    let mut continuation = None;
+    loop {
+        let (suffixes, next_continuation) = source
+            .find_keys_by_prefix_paginated(key_prefix, chunk_size, continuation)
+            .await?;
+        let keys = suffixes
+            .into_iter()
+            .map(|suffix| {
+                let mut key = key_prefix.to_vec();
+                key.extend(suffix);
+                key
+            })
+            .collect::<Vec<_>>();
+        let values = source.read_multi_values_bytes(keys.clone()).await?;
+        let mut batch = Batch::new();
+        for (key, value) in keys.into_iter().zip(values) {
+            // The key may have been deleted since the page was read.
+            let Some(value) = value else {
+                continue;
+            };
+            stats.bytes_copied += key.len() + value.len();
+            // Reservoir sampling of the pairs to verify.
+            if sample.len() < sample_size {
+                sample.push((key.clone(), value.clone()));
+            } else if sample_size > 0 {
+                let index = rng.gen_range(0..=stats.keys_copied);
+                if index < sample_size {
+                    sample[index] = (key.clone(), value.clone());
+                }
+            }
+            stats.keys_copied += 1;
+            batch.put_key_value_bytes(key, value);
+        }
+        if !batch.is_empty() {
+            destination.write_batch(batch).await?;
+            stats.batches_written += 1;
+            progress(&stats);
+        }
+        continuation = next_continuation;
+        if continuation.is_none() {
+            break;
+        }
+    }
+    if !sample.is_empty() {
+        let (keys, values): (Vec<_>, Vec<_>) = sample.into_iter().unzip();
+        let copied_values = destination.read_multi_values_bytes(keys.clone()).await?;
+        for ((key, value), copied_value) in keys.into_iter().zip(values).zip(copied_values) {
+            if copied_value.as_ref() != Some(&value) {
+                return Err(ViewError::CopyMismatch(key));
+            }
+            stats.keys_verified += 1;
+        }
+    }
+    Ok(stats)
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeSet;
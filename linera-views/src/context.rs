@@ -1,15 +1,25 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::{fmt::Debug, sync::Arc};
+
 use async_trait::async_trait;
+use linera_base::{ensure, time::Duration};
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-    batch::{Batch, DeletePrefixExpander},
-    common::from_bytes_option,
+    batch::{Batch, DeletePrefixExpander, WriteOperation},
+    common::{from_bytes_option, Codec},
+    lock::{acquire_lock, LockGuard},
     memory::MemoryStore,
-    store::{KeyIterable, KeyValueIterable, KeyValueStoreError, RestrictedKeyValueStore},
-    views::MIN_VIEW_TAG,
+    prefix_compression::PrefixDictionary,
+    read_only::ReadOnlyStore,
+    snapshot::SnapshotStore,
+    store::{
+        KeyIterable, KeyValueIterable, KeyValueStore, KeyValueStoreError, RestrictedKeyValueStore,
+        ResultStream,
+    },
+    views::{ViewError, MIN_VIEW_TAG},
 };
 
 /// The context in which a view is operated. Typically, this includes the client to
@@ -62,8 +72,55 @@ pub trait Context: Clone {
         key_prefix: &[u8],
     ) -> Result<Self::KeyValues, Self::Error>;
 
-    /// Applies the operations from the `batch`, persisting the changes.
-    async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error>;
+    /// Finds the keys `key` such that `start <= key < end`. The returned keys are
+    /// complete and come in increasing order. Stores without native range queries scan
+    /// the common prefix of `start` and `end` instead.
+    async fn find_keys_in_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error>;
+
+    /// Counts the keys starting with the `key_prefix`, without listing them if the
+    /// store supports it.
+    async fn count_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<usize, Self::Error>;
+
+    /// Finds the keys matching the `key_prefix` as a stream, which the store may fetch
+    /// page by page. The `key_prefix` is not included in the returned keys.
+    async fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, Vec<u8>, Self::Error>, Self::Error> {
+        let keys = self
+            .find_keys_by_prefix(key_prefix)
+            .await?
+            .iterator()
+            .map(|key| key.map(<[u8]>::to_vec))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Box::pin(futures::stream::iter(keys.into_iter().map(Ok))))
+    }
+
+    /// Finds the `(key,value)` pairs matching the `key_prefix` as a stream, which the
+    /// store may fetch page by page. The `key_prefix` is not included in the returned
+    /// keys.
+    async fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error>, Self::Error> {
+        let key_values = self
+            .find_key_values_by_prefix(key_prefix)
+            .await?
+            .into_iterator_owned()
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Box::pin(futures::stream::iter(
+            key_values.into_iter().map(Ok),
+        )))
+    }
+
+    /// Applies the operations from the `batch`, persisting the changes. The sizes of the
+    /// keys and values are checked against [`Context::MAX_KEY_SIZE`] and
+    /// [`Context::MAX_VALUE_SIZE`] before anything is sent to the database.
+    async fn write_batch(&self, batch: Batch) -> Result<(), ViewError>;
 
     /// Getter for the user-provided data.
     fn extra(&self) -> &Self::Extra;
@@ -72,12 +129,25 @@ pub trait Context: Clone {
     fn clone_with_base_key(&self, base_key: Vec<u8>) -> Self;
 
     /// Getter for the address of the base key.
-    fn base_key(&self) -> Vec<u8>;
+    fn base_key(&self) -> &[u8];
+
+    /// Obtains a similar [`Context`] implementation whose base key is the current base key
+    /// followed by the serialization of `marker`.
+    fn clone_with_sub_scope<I: Serialize>(&self, marker: &I) -> Result<Self, ViewError> {
+        Ok(self.clone_with_base_key(self.derive_key(marker)?))
+    }
+
+    /// Obtains a similar [`Context`] implementation whose base key is the current base key
+    /// followed by the raw bytes of `suffix`. This is the same scope as the one given by
+    /// [`Context::clone_with_sub_scope`] for a marker serializing to `suffix`.
+    fn clone_with_sub_scope_bytes(&self, suffix: &[u8]) -> Self {
+        self.clone_with_base_key(self.derive_key_bytes(suffix))
+    }
 
     /// Concatenates the base_key and tag.
     fn base_tag(&self, tag: u8) -> Vec<u8> {
         assert!(tag >= MIN_VIEW_TAG, "tag should be at least MIN_VIEW_TAG");
-        let mut key = self.base_key();
+        let mut key = self.base_key().to_vec();
         key.extend([tag]);
         key
     }
@@ -85,7 +155,7 @@ pub trait Context: Clone {
     /// Concatenates the base_key, tag and index.
     fn base_tag_index(&self, tag: u8, index: &[u8]) -> Vec<u8> {
         assert!(tag >= MIN_VIEW_TAG, "tag should be at least MIN_VIEW_TAG");
-        let mut key = self.base_key();
+        let mut key = self.base_key().to_vec();
         key.extend([tag]);
         key.extend_from_slice(index);
         key
@@ -93,28 +163,37 @@ pub trait Context: Clone {
 
     /// Concatenates the base_key and index.
     fn base_index(&self, index: &[u8]) -> Vec<u8> {
-        let mut key = self.base_key();
+        let mut key = self.base_key().to_vec();
         key.extend_from_slice(index);
         key
     }
 
     /// Obtains the `Vec<u8>` key from the key by serialization and using the base_key.
-    fn derive_key<I: Serialize>(&self, index: &I) -> Result<Vec<u8>, Self::Error> {
-        let mut key = self.base_key();
-        bcs::serialize_into(&mut key, index)?;
-        assert!(
-            key.len() > self.base_key().len(),
-            "Empty indices are not allowed"
-        );
+    /// Returns [`ViewError::KeyTooLong`] if the key exceeds [`Context::MAX_KEY_SIZE`].
+    fn derive_key<I: Serialize>(&self, index: &I) -> Result<Vec<u8>, ViewError> {
+        let suffix = bcs::to_bytes(index)?;
+        assert!(!suffix.is_empty(), "Empty indices are not allowed");
+        let key = self.derive_key_bytes(&suffix);
+        ensure!(key.len() <= Self::MAX_KEY_SIZE, ViewError::KeyTooLong);
         Ok(key)
     }
 
+    /// Obtains the `Vec<u8>` key by appending the raw bytes of `suffix` to the `base_key`.
+    /// Unlike [`Context::derive_key`], the size of the key is not checked here.
+    fn derive_key_bytes(&self, suffix: &[u8]) -> Vec<u8> {
+        let mut key = self.base_key().to_vec();
+        key.extend_from_slice(suffix);
+        key
+    }
+
     /// Obtains the `Vec<u8>` key from the key by serialization and using the `base_key`.
-    fn derive_tag_key<I: Serialize>(&self, tag: u8, index: &I) -> Result<Vec<u8>, Self::Error> {
+    /// Returns [`ViewError::KeyTooLong`] if the key exceeds [`Context::MAX_KEY_SIZE`].
+    fn derive_tag_key<I: Serialize>(&self, tag: u8, index: &I) -> Result<Vec<u8>, ViewError> {
         assert!(tag >= MIN_VIEW_TAG, "tag should be at least MIN_VIEW_TAG");
-        let mut key = self.base_key();
+        let mut key = self.base_key().to_vec();
         key.extend([tag]);
         bcs::serialize_into(&mut key, index)?;
+        ensure!(key.len() <= Self::MAX_KEY_SIZE, ViewError::KeyTooLong);
         Ok(key)
     }
 
@@ -139,6 +218,42 @@ pub trait Context: Clone {
         from_bytes_option(&self.read_value_bytes(key).await?)
     }
 
+    /// Retrieves a `Vec<u8>` from the database using the provided `key`, for the cases
+    /// where the absence of the key is a logic error. Returns [`ViewError::KeyNotFound`]
+    /// if the key is missing.
+    async fn read_value_bytes_or_err(&self, key: &[u8]) -> Result<Vec<u8>, ViewError> {
+        self.read_value_bytes(key)
+            .await?
+            .ok_or_else(|| ViewError::KeyNotFound(key.to_vec()))
+    }
+
+    /// Retrieves the bytes stored at `key` as they are, for values that are serialized by
+    /// the application itself.
+    async fn read_value_raw(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.read_value_bytes(key).await
+    }
+
+    /// Retrieves a generic `Item` from the database using the provided `key` prefixed by
+    /// the current context. The `Item` is deserialized using the codec `K`.
+    async fn read_value_with_codec<K, Item>(&self, key: &[u8]) -> Result<Option<Item>, ViewError>
+    where
+        K: Codec,
+        Item: DeserializeOwned,
+    {
+        match self.read_value_bytes(key).await? {
+            Some(bytes) => Ok(Some(K::decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Writes the `bytes` at `key` as they are, for values that are serialized by the
+    /// application itself.
+    async fn write_value_raw(&self, key: Vec<u8>, bytes: Vec<u8>) -> Result<(), ViewError> {
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(key, bytes);
+        self.write_batch(batch).await
+    }
+
     /// Reads multiple `keys` and deserializes the results if present.
     async fn read_multi_values<V: DeserializeOwned + Send>(
         &self,
@@ -155,6 +270,56 @@ pub trait Context: Clone {
     }
 }
 
+/// The kind of a storage operation reported to a [`StoreObserver`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StoreOperation {
+    /// The value of a key is read.
+    ReadValue,
+    /// The existence of a key is checked.
+    ContainsKey,
+    /// The keys matching a prefix are listed.
+    FindKeysByPrefix,
+    /// The key-value pairs matching a prefix are listed.
+    FindKeyValuesByPrefix,
+    /// The keys of a range, given by its start, are listed.
+    FindKeysInRange,
+    /// The keys matching a prefix are counted.
+    CountKeysByPrefix,
+    /// A key-value pair is written as part of a batch.
+    Put,
+    /// A key is deleted as part of a batch.
+    Delete,
+    /// The keys matching a prefix are deleted as part of a batch.
+    DeletePrefix,
+    /// The keys of a range, given by its start, are deleted as part of a batch.
+    DeleteRange,
+}
+
+/// A storage operation of a [`ViewContext`], as reported to a [`StoreObserver`].
+#[derive(Clone, Copy, Debug)]
+pub struct StoreEvent<'a> {
+    /// The kind of the operation.
+    pub operation: StoreOperation,
+    /// The length of the key, or of the key prefix, of the operation.
+    pub key_len: usize,
+    /// The base key of the context doing the operation.
+    pub scope: &'a [u8],
+    /// The key of the operation, if [`StoreObserver::wants_keys`] returns `true`.
+    pub key: Option<&'a [u8]>,
+}
+
+/// A hook notified of every read and every written operation of a [`ViewContext`], e.g.
+/// to trace which views generate which storage traffic.
+pub trait StoreObserver: Debug + Send + Sync {
+    /// Whether the events should carry the keys of the operations.
+    fn wants_keys(&self) -> bool {
+        false
+    }
+
+    /// Called for each storage operation.
+    fn observe(&self, event: StoreEvent<'_>);
+}
+
 /// Implementation of the [`Context`] trait on top of a DB client implementing
 /// [`crate::store::KeyValueStore`].
 #[derive(Debug, Default, Clone)]
@@ -165,6 +330,12 @@ pub struct ViewContext<E, S> {
     base_key: Vec<u8>,
     /// User-defined data attached to the view.
     extra: E,
+    /// The observer of the storage operations, shared with the derived contexts.
+    observer: Option<Arc<dyn StoreObserver>>,
+    /// The advisory lock that must be held to write, shared with the derived contexts.
+    lock: Option<Arc<LockGuard<S>>>,
+    /// The dictionary of the compressed base keys, shared with the derived contexts.
+    prefixes: Option<Arc<PrefixDictionary>>,
 }
 
 impl<E, S> ViewContext<E, S>
@@ -178,6 +349,72 @@ where
         store.clear_journal().await?;
         Ok(Self::new_unsafe(store, Vec::new(), extra))
     }
+
+    /// Enables the compression of the long base keys of the views derived from this
+    /// context, which should be the context of a root view using tags for its keys, such
+    /// as a derived view. See [`crate::prefix_compression`]. If keys are already stored
+    /// under the base key without compression, the context is returned unchanged so that
+    /// they remain readable.
+    pub async fn with_prefix_compression(mut self) -> Result<Self, ViewError> {
+        if let Some(dictionary) = PrefixDictionary::load(&self.store, &self.base_key).await? {
+            self.prefixes = Some(Arc::new(dictionary));
+        }
+        Ok(self)
+    }
+
+    /// Returns whether the long base keys of the derived views are compressed.
+    pub fn has_prefix_compression(&self) -> bool {
+        self.prefixes.is_some()
+    }
+}
+
+impl<E, S> ViewContext<E, S>
+where
+    S: KeyValueStore + Clone + Send + Sync + 'static,
+{
+    /// Creates a context suitable for a root view after acquiring the advisory lock of
+    /// `scope` with the given time-to-live, see [`crate::lock::acquire_lock`]. Writing
+    /// with this context or the contexts derived from it fails with
+    /// [`ViewError::LockLost`] once the lock is lost.
+    pub async fn with_exclusive_access(
+        store: S,
+        scope: &[u8],
+        ttl: Duration,
+        extra: E,
+    ) -> Result<Self, ViewError> {
+        let lock = acquire_lock(&store, scope, ttl).await?;
+        store.clear_journal().await?;
+        let mut context = Self::new_unsafe(store, Vec::new(), extra);
+        context.lock = Some(Arc::new(lock));
+        Ok(context)
+    }
+}
+
+impl<E, S> ViewContext<E, ReadOnlyStore<S>> {
+    /// Creates a context for a root view that can only be read. Saving a view of this
+    /// context fails with a [`ViewError::StoreError`] reporting
+    /// [`crate::read_only::ReadOnlyStoreError::WriteForbidden`] instead of writing the
+    /// changes.
+    pub fn read_only(store: S, extra: E) -> Self {
+        Self::new_unsafe(ReadOnlyStore::new(store), Vec::new(), extra)
+    }
+}
+
+impl<E, S> ViewContext<E, SnapshotStore<S>>
+where
+    E: Clone,
+    S: Clone,
+{
+    /// Creates a context with the same base key reading a snapshot of the store, see
+    /// [`crate::snapshot`]. The views loaded with it observe the state stored when the
+    /// snapshot was taken for as long as they are alive, and cannot be saved.
+    pub async fn snapshot(&self) -> Self {
+        let store = self.store.snapshot().await;
+        let mut context = Self::new_unsafe(store, self.base_key.clone(), self.extra.clone());
+        context.observer = self.observer.clone();
+        context.prefixes = self.prefixes.clone();
+        context
+    }
 }
 
 impl<E, S> ViewContext<E, S> {
@@ -189,6 +426,27 @@ impl<E, S> ViewContext<E, S> {
             store,
             base_key,
             extra,
+            observer: None,
+            lock: None,
+            prefixes: None,
+        }
+    }
+
+    /// Sets the observer notified of the storage operations of this context and of the
+    /// contexts derived from it afterwards.
+    pub fn set_observer(&mut self, observer: Arc<dyn StoreObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Notifies the observer, if any, of an operation on `key`.
+    fn notify(&self, operation: StoreOperation, key: &[u8]) {
+        if let Some(observer) = &self.observer {
+            observer.observe(StoreEvent {
+                operation,
+                key_len: key.len(),
+                scope: &self.base_key,
+                key: observer.wants_keys().then_some(key),
+            });
         }
     }
 }
@@ -215,15 +473,17 @@ where
         &self.extra
     }
 
-    fn base_key(&self) -> Vec<u8> {
-        self.base_key.clone()
+    fn base_key(&self) -> &[u8] {
+        &self.base_key
     }
 
     async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.notify(StoreOperation::ReadValue, key);
         self.store.read_value_bytes(key).await
     }
 
     async fn contains_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        self.notify(StoreOperation::ContainsKey, key);
         self.store.contains_key(key).await
     }
 
@@ -231,6 +491,9 @@ where
         if keys.is_empty() {
             Ok(Vec::new())
         } else {
+            for key in &keys {
+                self.notify(StoreOperation::ContainsKey, key);
+            }
             self.store.contains_keys(keys).await
         }
     }
@@ -242,11 +505,15 @@ where
         if keys.is_empty() {
             Ok(Vec::new())
         } else {
+            for key in &keys {
+                self.notify(StoreOperation::ReadValue, key);
+            }
             self.store.read_multi_values_bytes(keys).await
         }
     }
 
     async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Self::Keys, Self::Error> {
+        self.notify(StoreOperation::FindKeysByPrefix, key_prefix);
         self.store.find_keys_by_prefix(key_prefix).await
     }
 
@@ -254,18 +521,84 @@ where
         &self,
         key_prefix: &[u8],
     ) -> Result<Self::KeyValues, Self::Error> {
+        self.notify(StoreOperation::FindKeyValuesByPrefix, key_prefix);
         self.store.find_key_values_by_prefix(key_prefix).await
     }
 
-    async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
-        self.store.write_batch(batch).await
+    async fn find_keys_in_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.notify(StoreOperation::FindKeysInRange, start);
+        self.store.find_keys_in_range(start, end).await
+    }
+
+    async fn count_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<usize, Self::Error> {
+        self.notify(StoreOperation::CountKeysByPrefix, key_prefix);
+        self.store.count_keys_by_prefix(key_prefix).await
+    }
+
+    async fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, Vec<u8>, Self::Error>, Self::Error> {
+        self.notify(StoreOperation::FindKeysByPrefix, key_prefix);
+        self.store.find_keys_by_prefix_stream(key_prefix).await
+    }
+
+    async fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> Result<ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error>, Self::Error> {
+        self.notify(StoreOperation::FindKeyValuesByPrefix, key_prefix);
+        self.store
+            .find_key_values_by_prefix_stream(key_prefix)
+            .await
+    }
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), ViewError> {
+        let (batch, dictionary_entries) = match &self.prefixes {
+            Some(prefixes) => prefixes.translate_batch(batch)?,
+            None => (batch, Vec::new()),
+        };
+        batch.check_sizes(Self::MAX_KEY_SIZE, Self::MAX_VALUE_SIZE)?;
+        if let Some(lock) = &self.lock {
+            ensure!(!lock.is_lost(), ViewError::LockLost);
+        }
+        if self.observer.is_some() {
+            for operation in &batch.operations {
+                match operation {
+                    WriteOperation::Put { key, .. } => self.notify(StoreOperation::Put, key),
+                    WriteOperation::Delete { key } => self.notify(StoreOperation::Delete, key),
+                    WriteOperation::DeletePrefix { key_prefix } => {
+                        self.notify(StoreOperation::DeletePrefix, key_prefix)
+                    }
+                    WriteOperation::DeleteRange { start, .. } => {
+                        self.notify(StoreOperation::DeleteRange, start)
+                    }
+                }
+            }
+        }
+        self.store.write_batch(batch).await?;
+        if let Some(prefixes) = &self.prefixes {
+            prefixes.mark_stored(&dictionary_entries);
+        }
+        Ok(())
     }
 
     fn clone_with_base_key(&self, base_key: Vec<u8>) -> Self {
+        let base_key = match &self.prefixes {
+            Some(prefixes) => prefixes.compress(&self.base_key, base_key),
+            None => base_key,
+        };
         Self {
             store: self.store.clone(),
             base_key,
             extra: self.extra.clone(),
+            observer: self.observer.clone(),
+            lock: self.lock.clone(),
+            prefixes: self.prefixes.clone(),
         }
     }
 }
@@ -296,12 +629,7 @@ impl<E> MemoryContext<E> {
         extra: E,
     ) -> Self {
         let store = MemoryStore::new_for_testing(max_stream_queries, namespace, root_key).unwrap();
-        let base_key = Vec::new();
-        Self {
-            store,
-            base_key,
-            extra,
-        }
+        Self::new_unsafe(store, Vec::new(), extra)
     }
 }
 
@@ -317,4 +645,12 @@ impl DeletePrefixExpander for MemoryContext<()> {
         }
         Ok(vector_list)
     }
+
+    async fn expand_delete_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Vec<Vec<u8>>, Self::Error> {
+        crate::common::expand_delete_range(self, start, end).await
+    }
 }
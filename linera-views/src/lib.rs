@@ -21,6 +21,8 @@ We provide support for the following databases:
 * `RocksDbStore` is a disk-based key-value store
 * `DynamoDbStore` is the AWS-based DynamoDB service.
 * `ScyllaDbStore` is a cloud-based Cassandra-compatible database.
+* `S3Store` is the AWS-based S3 service, for cold data since its batches are not atomic.
+* `FileSystemStore` keeps each key in a file, for small test networks and for debugging.
 * `ServiceStoreClient` is a gRPC-based storage that uses either memory or RocksDB. It is available in `linera-storage-service`.
 
 The corresponding trait in the code is the [`crate::store::KeyValueStore`](https://docs.rs/linera-views/latest/linera_views/store/trait.KeyValueStore.html).
@@ -50,6 +52,7 @@ The following views implement the `View` trait:
 * `MapView` implements a map with keys and values.
 * `SetView` implements a set with keys.
 * `CollectionView` implements a map whose values are views themselves.
+* `IndexedCollectionView` implements a collection of views indexed by consecutive integers.
 * `ReentrantCollectionView` implements a map for which different keys can be accessed independently.
 * `ViewContainer<C>` implements a `KeyValueStore` and is used internally.
 
@@ -77,6 +80,16 @@ pub mod views;
 /// Backend implementing the [`crate::store::KeyValueStore`] trait.
 pub mod backends;
 
+/// Advisory locks guarding the writers of a database.
+pub mod lock;
+
+/// Compression of the long base keys of nested views.
+pub mod prefix_compression;
+
+/// Workloads comparing the performance of the backends.
+#[cfg(with_bench)]
+pub mod bench;
+
 /// Support for metrics.
 #[cfg(with_metrics)]
 pub mod metrics;
@@ -94,18 +107,31 @@ pub mod test_utils;
 
 #[cfg(with_dynamodb)]
 pub use backends::dynamo_db;
+#[cfg(with_encryption)]
+pub use backends::encrypted;
+#[cfg(with_testing)]
+pub use backends::faulty;
+#[cfg(with_fs)]
+pub use backends::fs_store;
 #[cfg(with_indexeddb)]
 pub use backends::indexed_db;
+#[cfg(any(with_dynamodb, with_s3))]
+pub use backends::localstack;
 #[cfg(with_metrics)]
 pub use backends::metering;
 #[cfg(with_rocksdb)]
 pub use backends::rocks_db;
+#[cfg(with_s3)]
+pub use backends::s3;
 #[cfg(with_scylladb)]
 pub use backends::scylla_db;
-pub use backends::{journaling, lru_caching, memory, value_splitting};
+pub use backends::{
+    counting, journaling, lru_caching, memory, mirroring, read_only, snapshot, value_splitting,
+};
 pub use views::{
-    bucket_queue_view, collection_view, hashable_wrapper, key_value_store_view, log_view, map_view,
-    queue_view, reentrant_collection_view, register_view, set_view,
+    bucket_queue_view, collection_view, hashable_wrapper, incremental_hash,
+    indexed_collection_view, key_value_store_view, log_view, map_view, queue_view,
+    reentrant_collection_view, register_view, set_view,
 };
 /// Re-exports used by the derive macros of this library.
 #[doc(hidden)]
@@ -0,0 +1,222 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Advisory locks preventing several processes from writing to the same scope of a
+//! database, e.g. two validators misconfigured to use the same table.
+//!
+//! A lock is a lease item written with [`WritableKeyValueStore::compare_and_put`], that
+//! is a conditional put relying on `attribute_not_exists` on DynamoDB and a check under
+//! the lock of the map for the memory store. The lease expires after its time-to-live
+//! unless the [`LockGuard`] refreshes it in the background. Backends not supporting
+//! conditional writes fail with [`ViewError::CompareAndPutUnsupported`].
+
+use std::{
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use futures::{
+    channel::oneshot,
+    future::{self, Either},
+};
+use linera_base::{
+    ensure,
+    task::NonBlockingFuture,
+    time::{timer, Duration, SystemTime, UNIX_EPOCH},
+};
+use rand::{rngs::OsRng, Rng as _};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    store::{AdminKeyValueStore, ReadableKeyValueStore, WritableKeyValueStore},
+    views::ViewError,
+};
+
+/// The prefix of the root keys holding the lease items. Root keys starting with it are
+/// reserved and must not be used for data.
+pub const LOCK_ROOT_KEY_PREFIX: &[u8] = b"\xfflinera-lock";
+
+/// The key of the lease item under its root key.
+const LEASE_KEY: &[u8] = &[0];
+
+/// The content of a lease item.
+#[derive(Serialize, Deserialize)]
+struct Lease {
+    /// A random identifier of the holder of the lock.
+    owner: u128,
+    /// The expiry time of the lease, in milliseconds since the Unix epoch.
+    expiry_ms: u64,
+}
+
+/// Returns the current time in milliseconds since the Unix epoch.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as u64)
+}
+
+/// The state of a lease, shared between a [`LockGuard`] and its refresher.
+#[derive(Debug)]
+struct LeaseState {
+    /// The serialized lease as currently stored.
+    bytes: Mutex<Vec<u8>>,
+    /// The expiry time of the stored lease, in milliseconds since the Unix epoch.
+    expiry_ms: AtomicU64,
+    /// Whether the lease was replaced by another holder.
+    lost: AtomicBool,
+}
+
+/// An advisory lock on a scope. The lease is refreshed in the background every third of
+/// its time-to-live until the guard is dropped.
+///
+/// Dropping the guard only stops the refresher, so that other processes have to wait
+/// for the lease to expire. Use [`LockGuard::release`] to hand the lock over at once.
+#[derive(Debug)]
+pub struct LockGuard<S> {
+    /// The store holding the lease item.
+    store: S,
+    /// The state shared with the refresher.
+    state: Arc<LeaseState>,
+    /// Stops the refresher when dropped.
+    stop: oneshot::Sender<()>,
+    /// The task refreshing the lease.
+    refresher: NonBlockingFuture<()>,
+}
+
+impl<S> LockGuard<S> {
+    /// Returns whether the lock was lost, either because another process took it over
+    /// or because the lease expired before it could be refreshed. Nothing should be
+    /// written to the scope after that.
+    pub fn is_lost(&self) -> bool {
+        self.state.lost.load(Ordering::Acquire)
+            || self.state.expiry_ms.load(Ordering::Acquire) <= now_ms()
+    }
+}
+
+impl<S> LockGuard<S>
+where
+    S: WritableKeyValueStore + Sync,
+{
+    /// Releases the lock by deleting its lease. Returns [`ViewError::LockLost`] if the
+    /// lease was taken over in the meantime.
+    pub async fn release(self) -> Result<(), ViewError> {
+        let LockGuard {
+            store,
+            state,
+            stop,
+            refresher,
+        } = self;
+        // The refresher may be replacing the lease, so it has to be stopped first.
+        drop(stop);
+        let _ = refresher.await;
+        let lease = state.bytes.lock().unwrap().clone();
+        ensure!(
+            store.compare_and_put(LEASE_KEY, Some(&lease), None).await?,
+            ViewError::LockLost
+        );
+        Ok(())
+    }
+}
+
+/// Acquires the advisory lock of `scope` in the namespace of `store`, with a lease
+/// expiring after `ttl` unless it is refreshed. Returns [`ViewError::LockHeld`] if
+/// another holder has a lease that has not expired.
+pub async fn acquire_lock<S>(
+    store: &S,
+    scope: &[u8],
+    ttl: Duration,
+) -> Result<LockGuard<S>, ViewError>
+where
+    S: ReadableKeyValueStore
+        + WritableKeyValueStore
+        + AdminKeyValueStore
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    let mut root_key = LOCK_ROOT_KEY_PREFIX.to_vec();
+    root_key.extend_from_slice(scope);
+    let store = store.clone_with_root_key(&root_key)?;
+    let current = store.read_value_bytes(LEASE_KEY).await?;
+    if let Some(bytes) = &current {
+        let lease = bcs::from_bytes::<Lease>(bytes)?;
+        ensure!(lease.expiry_ms <= now_ms(), ViewError::LockHeld);
+    }
+    let owner = OsRng.gen::<u128>();
+    let ttl_ms = ttl.as_millis() as u64;
+    let expiry_ms = now_ms() + ttl_ms;
+    let lease = bcs::to_bytes(&Lease { owner, expiry_ms })?;
+    ensure!(
+        store
+            .compare_and_put(LEASE_KEY, current.as_deref(), Some(&lease))
+            .await?,
+        ViewError::LockHeld
+    );
+    let state = Arc::new(LeaseState {
+        bytes: Mutex::new(lease),
+        expiry_ms: AtomicU64::new(expiry_ms),
+        lost: AtomicBool::new(false),
+    });
+    let (stop, stopped) = oneshot::channel();
+    let refresher = linera_base::task::spawn(refresh_lease(
+        store.clone(),
+        owner,
+        ttl,
+        state.clone(),
+        stopped,
+    ));
+    Ok(LockGuard {
+        store,
+        state,
+        stop,
+        refresher,
+    })
+}
+
+/// Refreshes the lease of `owner` until `stopped` completes, i.e. the guard is dropped,
+/// or the lease is lost.
+async fn refresh_lease<S>(
+    store: S,
+    owner: u128,
+    ttl: Duration,
+    state: Arc<LeaseState>,
+    mut stopped: oneshot::Receiver<()>,
+) where
+    S: WritableKeyValueStore + Sync,
+{
+    let ttl_ms = ttl.as_millis() as u64;
+    loop {
+        let sleep = pin!(timer::sleep(ttl / 3));
+        if let Either::Right(_) = future::select(sleep, &mut stopped).await {
+            return;
+        }
+        if state.expiry_ms.load(Ordering::Acquire) <= now_ms() {
+            // Another process may have taken the lock over in the meantime.
+            state.lost.store(true, Ordering::Release);
+            return;
+        }
+        let current = state.bytes.lock().unwrap().clone();
+        let expiry_ms = now_ms() + ttl_ms;
+        let renewed = bcs::to_bytes(&Lease { owner, expiry_ms }).expect("leases are serializable");
+        match store
+            .compare_and_put(LEASE_KEY, Some(&current), Some(&renewed))
+            .await
+        {
+            Ok(true) => {
+                *state.bytes.lock().unwrap() = renewed;
+                state.expiry_ms.store(expiry_ms, Ordering::Release);
+            }
+            Ok(false) => {
+                state.lost.store(true, Ordering::Release);
+                return;
+            }
+            Err(error) => {
+                tracing::warn!("Failed to refresh the lease of a lock: {error}");
+            }
+        }
+    }
+}
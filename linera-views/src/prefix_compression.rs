@@ -0,0 +1,241 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compression of the long base keys of the views nested in a root view.
+//!
+//! The base key of a view nested in collections repeats the base keys of its ancestors
+//! and the indices leading to it, so that the prefixes dominate the size of the keys of
+//! deeply nested views. With prefix compression, a long base key is replaced by a short
+//! one the first time a context is derived for it: the root key, the bytes `0, 1` and a
+//! 4-byte identifier. The dictionary from the original base keys to the identifiers is
+//! stored under the root key followed by `0, 0`, and its new entries are written with the
+//! next batch. The tags below [`MIN_VIEW_TAG`] are not used by the views, so that the
+//! root view must use tags for its keys, as the derived views do. The batches writing
+//! below the root key followed by `0` outside of the compressed base keys, e.g. to the
+//! dictionary from a root [`crate::map_view::ByteMapView`] with raw keys starting with
+//! `0`, fail with [`ViewError::ReservedKey`].
+//!
+//! Since the contexts derived from a compressed base key only see the short one, reads
+//! and scans need no translation. The deletions of prefixes are extended to the short
+//! prefixes of the base keys starting with them.
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+use linera_base::ensure;
+
+use crate::{
+    batch::{Batch, WriteOperation},
+    common::get_upper_bound_option,
+    store::{KeyValueIterable as _, ReadableKeyValueStore},
+    views::{ViewError, MIN_VIEW_TAG},
+};
+
+/// The tag reserved under the root key, which is not used by the views.
+const RESERVED_TAG: u8 = 0;
+
+/// The tag of the dictionary under the reserved tag.
+const DICTIONARY_TAG: u8 = 0;
+
+/// The tag of the compressed base keys under the reserved tag.
+const COMPRESSED_TAG: u8 = 1;
+
+/// The length of a compressed base key after the root key.
+const COMPRESSED_LEN: usize = 2 + 4;
+
+const _: () = assert!(RESERVED_TAG < MIN_VIEW_TAG);
+
+/// The mutable part of a [`PrefixDictionary`].
+#[derive(Debug, Default)]
+struct DictionaryState {
+    /// The identifiers of the compressed base keys.
+    ids: BTreeMap<Vec<u8>, u32>,
+    /// The entries of `ids` that are not stored yet.
+    pending: BTreeMap<Vec<u8>, u32>,
+    /// The next identifier to allocate.
+    next_id: u32,
+}
+
+/// The dictionary of the compressed base keys of a root view, shared by the contexts
+/// derived from the root context.
+#[derive(Debug)]
+pub struct PrefixDictionary {
+    /// The base key of the root view.
+    root_key: Vec<u8>,
+    /// The entries of the dictionary.
+    state: Mutex<DictionaryState>,
+}
+
+impl PrefixDictionary {
+    /// Loads the dictionary of the root view with the base key `root_key`. Returns `None`
+    /// if keys were stored under `root_key` without compression, so that they remain
+    /// readable.
+    pub(crate) async fn load<S>(store: &S, root_key: &[u8]) -> Result<Option<Self>, ViewError>
+    where
+        S: ReadableKeyValueStore,
+    {
+        let mut state = DictionaryState::default();
+        let prefix = Self::reserved_key(root_key, DICTIONARY_TAG);
+        let key_values = store.find_key_values_by_prefix(&prefix).await?;
+        for key_value in key_values.iterator() {
+            let (base_key, id) = key_value?;
+            let id = u32::from_be_bytes(id.try_into().map_err(|_| ViewError::InconsistentEntries)?);
+            state.ids.insert(base_key.to_vec(), id);
+            state.next_id = state.next_id.max(id.saturating_add(1));
+        }
+        if state.ids.is_empty() && store.count_keys_by_prefix(root_key).await? > 0 {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            root_key: root_key.to_vec(),
+            state: Mutex::new(state),
+        }))
+    }
+
+    /// Returns the root key followed by the reserved tag and `tag`.
+    fn reserved_key(root_key: &[u8], tag: u8) -> Vec<u8> {
+        let mut key = root_key.to_vec();
+        key.extend([RESERVED_TAG, tag]);
+        key
+    }
+
+    /// Returns the compressed base key of the identifier `id`.
+    fn compressed_key(&self, id: u32) -> Vec<u8> {
+        let mut key = Self::reserved_key(&self.root_key, COMPRESSED_TAG);
+        key.extend(id.to_be_bytes());
+        key
+    }
+
+    /// Returns the key of the entry of the dictionary for `base_key`.
+    fn dictionary_key(&self, base_key: &[u8]) -> Vec<u8> {
+        let mut key = Self::reserved_key(&self.root_key, DICTIONARY_TAG);
+        key.extend_from_slice(base_key);
+        key
+    }
+
+    /// Returns the base key to use for the context derived with `base_key` from a
+    /// context with the base key `parent_key`. Base keys are compressed if they are
+    /// longer than the compressed ones and extend their parent by more than a tag, since
+    /// some views derive their inner views with a tag and remove it to recover their own
+    /// base key.
+    pub(crate) fn compress(&self, parent_key: &[u8], base_key: Vec<u8>) -> Vec<u8> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(id) = state.ids.get(&base_key) {
+            return self.compressed_key(*id);
+        }
+        if !base_key.starts_with(&self.root_key)
+            || base_key.len() <= self.root_key.len() + COMPRESSED_LEN
+            || base_key.len() < parent_key.len() + 2
+            || state.next_id == u32::MAX
+        {
+            return base_key;
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        state.ids.insert(base_key.clone(), id);
+        state.pending.insert(base_key, id);
+        self.compressed_key(id)
+    }
+
+    /// Returns whether `key` is, or extends, a compressed base key.
+    fn is_in_compressed_key(&self, key: &[u8]) -> bool {
+        key.len() >= self.root_key.len() + COMPRESSED_LEN
+            && key.starts_with(&Self::reserved_key(&self.root_key, COMPRESSED_TAG))
+    }
+
+    /// Checks that `operation` does not write below the reserved tag of the root key,
+    /// except in the views with compressed base keys.
+    fn check_not_reserved(&self, operation: &WriteOperation) -> Result<(), ViewError> {
+        let mut reserved_prefix = self.root_key.clone();
+        reserved_prefix.push(RESERVED_TAG);
+        let key = match operation {
+            WriteOperation::Put { key, .. } | WriteOperation::Delete { key } => key,
+            WriteOperation::DeletePrefix { key_prefix } => key_prefix,
+            WriteOperation::DeleteRange { start, end } => {
+                // The ranges covering the reserved keys would delete the dictionary or the
+                // compressed base keys of the views outside of them, unless they stay
+                // within a single compressed base key.
+                let overlaps = get_upper_bound_option(&reserved_prefix)
+                    .map_or(true, |reserved_end| start < &reserved_end)
+                    && end > &reserved_prefix;
+                let within_compressed_key = self.is_in_compressed_key(start)
+                    && get_upper_bound_option(&start[..self.root_key.len() + COMPRESSED_LEN])
+                        .map_or(true, |compressed_end| end <= &compressed_end);
+                ensure!(
+                    !overlaps || within_compressed_key,
+                    ViewError::ReservedKey(start.clone())
+                );
+                return Ok(());
+            }
+        };
+        ensure!(
+            !key.starts_with(&reserved_prefix) || self.is_in_compressed_key(key),
+            ViewError::ReservedKey(key.clone())
+        );
+        Ok(())
+    }
+
+    /// Extends the deletions of `batch` to the compressed base keys that they cover, and
+    /// adds the entries of the dictionary that are not stored. Returns the new batch and
+    /// the added entries, to be passed to [`PrefixDictionary::mark_stored`] once the
+    /// batch is written.
+    pub(crate) fn translate_batch(&self, batch: Batch) -> Result<(Batch, Vec<Vec<u8>>), ViewError> {
+        for operation in &batch.operations {
+            self.check_not_reserved(operation)?;
+        }
+        let state = self.state.lock().unwrap();
+        let dictionary_prefix = Self::reserved_key(&self.root_key, DICTIONARY_TAG);
+        let mut dictionary_deleted = false;
+        let mut translated = Batch::new();
+        for operation in batch.operations {
+            let mut prefixes = match &operation {
+                WriteOperation::DeletePrefix { key_prefix } => {
+                    dictionary_deleted |= dictionary_prefix.starts_with(key_prefix);
+                    Self::covered_ids(&state, key_prefix)
+                }
+                WriteOperation::DeleteRange { start, end } => state
+                    .ids
+                    .range(start.clone()..end.clone())
+                    .filter(|(base_key, _)| !end.starts_with(base_key))
+                    .map(|(_, id)| *id)
+                    .collect(),
+                WriteOperation::Put { .. } | WriteOperation::Delete { .. } => Vec::new(),
+            };
+            translated.operations.push(operation);
+            // The compressed base keys may themselves be the prefixes of compressed ones.
+            while let Some(id) = prefixes.pop() {
+                let key_prefix = self.compressed_key(id);
+                prefixes.extend(Self::covered_ids(&state, &key_prefix));
+                translated.delete_key_prefix(key_prefix);
+            }
+        }
+        let entries = if dictionary_deleted {
+            &state.ids
+        } else {
+            &state.pending
+        };
+        for (base_key, id) in entries {
+            translated
+                .put_key_value_bytes(self.dictionary_key(base_key), id.to_be_bytes().to_vec());
+        }
+        let added = entries.keys().cloned().collect();
+        Ok((translated, added))
+    }
+
+    /// Returns the identifiers of the base keys starting with `key_prefix`.
+    fn covered_ids(state: &DictionaryState, key_prefix: &[u8]) -> Vec<u32> {
+        state
+            .ids
+            .range(key_prefix.to_vec()..)
+            .take_while(|(base_key, _)| base_key.starts_with(key_prefix))
+            .map(|(_, id)| *id)
+            .collect()
+    }
+
+    /// Records that the entries of the dictionary for `base_keys` are stored.
+    pub(crate) fn mark_stored(&self, base_keys: &[Vec<u8>]) {
+        let mut state = self.state.lock().unwrap();
+        for base_key in base_keys {
+            state.pending.remove(base_key);
+        }
+    }
+}
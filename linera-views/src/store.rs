@@ -3,13 +3,30 @@
 
 //! This provides the trait definitions for the stores.
 
-use std::{fmt::Debug, future::Future};
+use std::{fmt::Debug, future::Future, pin::Pin};
 
+use futures::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt as _},
+    stream, Stream, TryStreamExt as _,
+};
 use serde::de::DeserializeOwned;
 
 #[cfg(with_testing)]
 use crate::random::generate_test_namespace;
-use crate::{batch::Batch, common::from_bytes_option, views::ViewError};
+use crate::{
+    batch::{Batch, BatchStats},
+    common::{
+        from_bytes_option, read_dump_entry, read_dump_header, write_dump_entry, write_dump_header,
+    },
+    views::ViewError,
+};
+
+/// The maximal number of key-value pairs written in one batch when restoring a dump.
+const RESTORE_BATCH_SIZE: usize = 1000;
+
+/// The maximal total size of the key-value pairs written in one batch when restoring a
+/// dump, unless a single pair is larger.
+const RESTORE_BATCH_TOTAL_SIZE: usize = 1_000_000;
 
 /// The common initialization parameters for the `KeyValueStore`
 #[derive(Debug, Clone)]
@@ -55,6 +72,12 @@ impl Default for CommonStoreConfig {
 pub trait KeyValueStoreError: std::error::Error + Debug + From<bcs::Error> {
     /// The name of the backend.
     const BACKEND: &'static str;
+
+    /// Whether the error is caused by a transient condition, such as throttling or a
+    /// timeout, so that the operation may succeed if it is retried.
+    fn is_retryable(&self) -> bool {
+        false
+    }
 }
 
 impl<E: KeyValueStoreError> From<E> for ViewError {
@@ -62,10 +85,26 @@ impl<E: KeyValueStoreError> From<E> for ViewError {
         Self::StoreError {
             backend: E::BACKEND.to_string(),
             error: error.to_string(),
+            retryable: error.is_retryable(),
         }
     }
 }
 
+/// The order in which the keys are returned by a search query.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Order {
+    /// Increasing lexicographic order of the keys.
+    #[default]
+    Forward,
+    /// Decreasing lexicographic order of the keys.
+    Reverse,
+}
+
+/// A stream of the results of a search, as returned by
+/// [`ReadableKeyValueStore::find_keys_by_prefix_stream`] and
+/// [`ReadableKeyValueStore::find_key_values_by_prefix_stream`].
+pub type ResultStream<'a, T, E> = Pin<Box<dyn Stream<Item = Result<T, E>> + Send + 'a>>;
+
 /// Define an associated [`KeyValueStoreError`].
 pub trait WithError {
     /// The error type.
@@ -126,6 +165,243 @@ pub trait LocalReadableKeyValueStore: WithError {
         async { from_bytes_option(&self.read_value_bytes(key).await?) }
     }
 
+    /// Finds the `key` matching the prefix, in the given `order`. The prefix is not
+    /// included in the returned keys.
+    ///
+    /// The default implementation collects the keys of `find_keys_by_prefix` and reverses
+    /// them if needed.
+    fn find_keys_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> impl Future<Output = Result<Vec<Vec<u8>>, Self::Error>>
+    where
+        Self: Sync,
+    {
+        async move {
+            let mut keys = Vec::new();
+            for key in self.find_keys_by_prefix(key_prefix).await?.iterator() {
+                keys.push(key?.to_vec());
+            }
+            if order == Order::Reverse {
+                keys.reverse();
+            }
+            Ok(keys)
+        }
+    }
+
+    /// Finds the `(key,value)` pairs matching the prefix, in the given `order`. The prefix
+    /// is not included in the returned keys.
+    ///
+    /// The default implementation collects the pairs of `find_key_values_by_prefix` and
+    /// reverses them if needed.
+    fn find_key_values_by_prefix_with_options(
+        &self,
+        key_prefix: &[u8],
+        order: Order,
+    ) -> impl Future<Output = Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error>>
+    where
+        Self: Sync,
+    {
+        async move {
+            let key_values = self.find_key_values_by_prefix(key_prefix).await?;
+            let mut key_values = key_values
+                .into_iterator_owned()
+                .collect::<Result<Vec<_>, _>>()?;
+            if order == Order::Reverse {
+                key_values.reverse();
+            }
+            Ok(key_values)
+        }
+    }
+
+    /// Finds at most `limit` keys matching the prefix, in increasing order, starting after
+    /// the `continuation` key. The prefix is not included in the returned keys.
+    ///
+    /// The returned continuation is to be passed to the next call in order to get the
+    /// following keys. It is `None` once all the keys have been returned, though a
+    /// continuation may also lead to an empty page. The continuation does not need to
+    /// be an existing key, so the scan can proceed even if that key has been deleted.
+    ///
+    /// The default implementation slices the result of `find_keys_by_prefix`.
+    fn find_keys_by_prefix_paginated(
+        &self,
+        key_prefix: &[u8],
+        limit: usize,
+        continuation: Option<Vec<u8>>,
+    ) -> impl Future<Output = Result<(Vec<Vec<u8>>, Option<Vec<u8>>), Self::Error>>
+    where
+        Self: Sync,
+    {
+        async move {
+            let mut keys = Vec::new();
+            if limit == 0 {
+                return Ok((keys, continuation));
+            }
+            for key in self.find_keys_by_prefix(key_prefix).await?.iterator() {
+                let key = key?;
+                if let Some(continuation) = &continuation {
+                    if key <= &continuation[..] {
+                        continue;
+                    }
+                }
+                if keys.len() == limit {
+                    let continuation = keys.last().cloned();
+                    return Ok((keys, continuation));
+                }
+                keys.push(key.to_vec());
+            }
+            Ok((keys, None))
+        }
+    }
+
+    /// Finds the keys `key` such that `start <= key < end`. Unlike the prefix searches,
+    /// the returned keys are complete and come in increasing order.
+    ///
+    /// The default implementation scans the common prefix of `start` and `end` and
+    /// filters the keys outside of the range.
+    fn find_keys_in_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> impl Future<Output = Result<Vec<Vec<u8>>, Self::Error>>
+    where
+        Self: Sync,
+    {
+        async move {
+            let mut keys = Vec::new();
+            if start >= end {
+                return Ok(keys);
+            }
+            let prefix_len = start
+                .iter()
+                .zip(end)
+                .take_while(|(byte1, byte2)| byte1 == byte2)
+                .count();
+            let key_prefix = &start[..prefix_len];
+            for key in self.find_keys_by_prefix(key_prefix).await?.iterator() {
+                let mut full_key = key_prefix.to_vec();
+                full_key.extend_from_slice(key?);
+                if start <= &full_key[..] && &full_key[..] < end {
+                    keys.push(full_key);
+                }
+            }
+            Ok(keys)
+        }
+    }
+
+    /// Counts the keys starting with the `key_prefix`.
+    ///
+    /// The default implementation iterates over the keys found by `find_keys_by_prefix`.
+    fn count_keys_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> impl Future<Output = Result<usize, Self::Error>>
+    where
+        Self: Sync,
+    {
+        async move {
+            let keys = self.find_keys_by_prefix(key_prefix).await?;
+            let mut count = 0;
+            for key in keys.iterator() {
+                key?;
+                count += 1;
+            }
+            Ok(count)
+        }
+    }
+
+    /// Finds the keys matching the prefix as a stream, which implementations may fetch
+    /// lazily, page by page, as it is polled. The prefix is not included in the returned
+    /// keys.
+    ///
+    /// The default implementation streams the keys found by `find_keys_by_prefix`.
+    fn find_keys_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> impl Future<Output = Result<ResultStream<'a, Vec<u8>, Self::Error>, Self::Error>>
+    where
+        Self: Sync,
+    {
+        async move {
+            let keys = self
+                .find_keys_by_prefix(key_prefix)
+                .await?
+                .iterator()
+                .map(|key| key.map(<[u8]>::to_vec))
+                .collect::<Result<Vec<_>, _>>()?;
+            let keys: ResultStream<'a, Vec<u8>, Self::Error> =
+                Box::pin(stream::iter(keys.into_iter().map(Ok)));
+            Ok(keys)
+        }
+    }
+
+    /// Finds the `(key,value)` pairs matching the prefix as a stream, which
+    /// implementations may fetch lazily, page by page, as it is polled. The prefix is not
+    /// included in the returned keys.
+    ///
+    /// The default implementation streams the pairs found by `find_key_values_by_prefix`.
+    fn find_key_values_by_prefix_stream<'a>(
+        &'a self,
+        key_prefix: &'a [u8],
+    ) -> impl Future<Output = Result<ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error>, Self::Error>>
+    where
+        Self: Sync,
+    {
+        async move {
+            let key_values = self
+                .find_key_values_by_prefix(key_prefix)
+                .await?
+                .into_iterator_owned()
+                .collect::<Result<Vec<_>, _>>()?;
+            let key_values: ResultStream<'a, (Vec<u8>, Vec<u8>), Self::Error> =
+                Box::pin(stream::iter(key_values.into_iter().map(Ok)));
+            Ok(key_values)
+        }
+    }
+
+    /// Writes the key-value pairs matching the `key_prefix` to the `writer`, in the
+    /// versioned format read by [`WritableKeyValueStore::restore_from_reader`]. The keys
+    /// are written in full, including the prefix. Returns the number of written pairs.
+    fn dump_to_writer<W: AsyncWrite + Unpin + Send>(
+        &self,
+        key_prefix: &[u8],
+        mut writer: W,
+    ) -> impl Future<Output = Result<usize, ViewError>>
+    where
+        Self: Sync,
+    {
+        async move {
+            write_dump_header(&mut writer).await?;
+            let mut key_values = self.find_key_values_by_prefix_stream(key_prefix).await?;
+            let mut count = 0;
+            while let Some((key, value)) = key_values.try_next().await? {
+                let mut full_key = key_prefix.to_vec();
+                full_key.extend(key);
+                write_dump_entry(&mut writer, &full_key, &value).await?;
+                count += 1;
+            }
+            writer.flush().await?;
+            Ok(count)
+        }
+    }
+
+    /// Retrieves a `Vec<u8>` from the database using the provided `key`, returning
+    /// [`ViewError::KeyNotFound`] if the key is missing.
+    fn read_value_bytes_or_err(
+        &self,
+        key: &[u8],
+    ) -> impl Future<Output = Result<Vec<u8>, ViewError>>
+    where
+        Self: Sync,
+    {
+        async move {
+            self.read_value_bytes(key)
+                .await?
+                .ok_or_else(|| ViewError::KeyNotFound(key.to_vec()))
+        }
+    }
+
     /// Reads multiple `keys` and deserializes the results if present.
     fn read_multi_values<V: DeserializeOwned + Send>(
         &self,
@@ -156,6 +432,85 @@ pub trait LocalWritableKeyValueStore: WithError {
     /// Clears any journal entry that may remain.
     /// The journal is located at the `root_key`.
     async fn clear_journal(&self) -> Result<(), Self::Error>;
+
+    /// Writes the `batch` in the database, and returns statistics on what was written.
+    ///
+    /// The default implementation assumes that the batch is written in a single round
+    /// trip, without expanding the deletions of prefixes.
+    fn write_batch_with_stats(
+        &self,
+        batch: Batch,
+    ) -> impl Future<Output = Result<BatchStats, Self::Error>>
+    where
+        Self: Sync,
+    {
+        async move {
+            let stats = BatchStats::from_batch(&batch);
+            self.write_batch(batch).await?;
+            Ok(stats)
+        }
+    }
+
+    /// Atomically replaces the value of the `key` by `new`, or deletes it if `new` is
+    /// `None`, provided that its current value is `expected`, where `None` stands for an
+    /// absent key. Returns `Ok(false)` without writing anything if the current value
+    /// differs.
+    ///
+    /// Not all backends support this operation: the default implementation returns
+    /// [`ViewError::CompareAndPutUnsupported`].
+    fn compare_and_put(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> impl Future<Output = Result<bool, ViewError>>
+    where
+        Self: Sync,
+    {
+        async move {
+            let _ = (key, expected, new);
+            Err(ViewError::CompareAndPutUnsupported)
+        }
+    }
+
+    /// Writes the key-value pairs of a dump made by
+    /// [`ReadableKeyValueStore::dump_to_writer`], in batches of bounded size. Returns the
+    /// number of restored pairs. The keys and values that the store could not hold are
+    /// rejected before being read.
+    fn restore_from_reader<R: AsyncRead + Unpin + Send>(
+        &self,
+        mut reader: R,
+    ) -> impl Future<Output = Result<usize, ViewError>>
+    where
+        Self: LocalReadableKeyValueStore + Sync,
+    {
+        async move {
+            read_dump_header(&mut reader).await?;
+            let mut count = 0;
+            let mut batch = Batch::new();
+            let mut batch_size = 0;
+            let max_key_size = <Self as LocalReadableKeyValueStore>::MAX_KEY_SIZE;
+            while let Some((key, value)) =
+                read_dump_entry(&mut reader, max_key_size, Self::MAX_VALUE_SIZE).await?
+            {
+                let entry_size = key.len() + value.len();
+                if !batch.is_empty()
+                    && (batch.num_operations() == RESTORE_BATCH_SIZE
+                        || batch_size + entry_size > RESTORE_BATCH_TOTAL_SIZE)
+                {
+                    self.write_batch(std::mem::take(&mut batch)).await?;
+                    batch_size = 0;
+                }
+                batch.put_key_value_bytes(key, value);
+                batch_size += entry_size;
+                count += 1;
+            }
+            if !batch.is_empty() {
+                self.write_batch(batch).await?;
+            }
+            Ok(count)
+        }
+    }
 }
 
 /// Low-level trait for the administration of stores and their namespaces.
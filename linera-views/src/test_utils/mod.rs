@@ -10,8 +10,10 @@ pub mod performance;
 use std::{
     collections::{BTreeMap, BTreeSet, HashSet},
     fmt::Debug,
+    sync::Mutex,
 };
 
+use futures::TryStreamExt as _;
 use rand::{seq::SliceRandom, Rng};
 
 use crate::{
@@ -19,11 +21,13 @@ use crate::{
         Batch, WriteOperation,
         WriteOperation::{Delete, Put},
     },
+    context::{Context as _, StoreEvent, StoreObserver, StoreOperation, ViewContext},
     random::{generate_test_namespace, make_deterministic_rng, make_nondeterministic_rng},
     store::{
-        KeyIterable, KeyValueIterable, LocalKeyValueStore, LocalRestrictedKeyValueStore,
-        TestKeyValueStore,
+        KeyIterable, KeyValueIterable, LocalKeyValueStore, LocalRestrictedKeyValueStore, Order,
+        ReadableKeyValueStore, RestrictedKeyValueStore, TestKeyValueStore, WritableKeyValueStore,
     },
+    views::ViewError,
 };
 
 /// Returns a random key_prefix used for tests
@@ -241,6 +245,298 @@ pub async fn run_reads<S: LocalRestrictedKeyValueStore>(
     }
 }
 
+/// Checks that `find_keys_by_prefix_with_options` and `find_key_values_by_prefix_with_options`
+/// return the same entries as the plain prefix searches, in the requested order.
+pub async fn run_reads_with_order<S: LocalRestrictedKeyValueStore + Sync>(
+    store: S,
+    key_values: Vec<(Vec<u8>, Vec<u8>)>,
+) {
+    let mut batch = Batch::new();
+    for (key, value) in &key_values {
+        batch.put_key_value_bytes(key.clone(), value.clone());
+    }
+    store.write_batch(batch).await.unwrap();
+    let key_prefixes = key_values
+        .iter()
+        .flat_map(|(key, _)| (0..key.len()).map(|u| key[..=u].to_vec()))
+        .collect::<BTreeSet<_>>();
+    for key_prefix in key_prefixes {
+        let keys = store
+            .find_keys_by_prefix(&key_prefix)
+            .await
+            .unwrap()
+            .iterator()
+            .map(|key| key.unwrap().to_vec())
+            .collect::<Vec<_>>();
+        let key_values = store
+            .find_key_values_by_prefix(&key_prefix)
+            .await
+            .unwrap()
+            .into_iterator_owned()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let keys_forward = store
+            .find_keys_by_prefix_with_options(&key_prefix, Order::Forward)
+            .await
+            .unwrap();
+        assert_eq!(keys, keys_forward);
+        let mut keys_reverse = store
+            .find_keys_by_prefix_with_options(&key_prefix, Order::Reverse)
+            .await
+            .unwrap();
+        keys_reverse.reverse();
+        assert_eq!(keys, keys_reverse);
+        let key_values_forward = store
+            .find_key_values_by_prefix_with_options(&key_prefix, Order::Forward)
+            .await
+            .unwrap();
+        assert_eq!(key_values, key_values_forward);
+        let mut key_values_reverse = store
+            .find_key_values_by_prefix_with_options(&key_prefix, Order::Reverse)
+            .await
+            .unwrap();
+        key_values_reverse.reverse();
+        assert_eq!(key_values, key_values_reverse);
+    }
+}
+
+/// Checks that `find_keys_in_range` returns exactly the keys between the bounds, with the
+/// start included and the end excluded.
+pub async fn run_find_keys_in_range<S: LocalRestrictedKeyValueStore + Sync>(
+    store: S,
+    key_values: Vec<(Vec<u8>, Vec<u8>)>,
+) {
+    let mut batch = Batch::new();
+    for (key, value) in &key_values {
+        batch.put_key_value_bytes(key.clone(), value.clone());
+    }
+    store.write_batch(batch).await.unwrap();
+    let keys = key_values
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect::<BTreeSet<_>>();
+    let mut rng = make_deterministic_rng();
+    let bounds = keys.iter().cloned().collect::<Vec<_>>();
+    for _ in 0..20 {
+        let start = bounds.choose(&mut rng).unwrap().clone();
+        let end = bounds.choose(&mut rng).unwrap().clone();
+        let expected = keys
+            .iter()
+            .filter(|key| start <= **key && **key < end)
+            .cloned()
+            .collect::<Vec<_>>();
+        let result = store.find_keys_in_range(&start, &end).await.unwrap();
+        assert_eq!(result, expected);
+    }
+}
+
+/// Checks that the streams of `find_keys_by_prefix_stream` and
+/// `find_key_values_by_prefix_stream` give the results of the non-streaming searches.
+pub async fn run_prefix_streams<S: LocalRestrictedKeyValueStore + Sync>(
+    store: S,
+    key_values: Vec<(Vec<u8>, Vec<u8>)>,
+) {
+    let mut batch = Batch::new();
+    for (key, value) in &key_values {
+        batch.put_key_value_bytes(key.clone(), value.clone());
+    }
+    store.write_batch(batch).await.unwrap();
+    let key_prefixes = key_values
+        .iter()
+        .flat_map(|(key, _)| [key[..1].to_vec(), key[..key.len().min(3)].to_vec()])
+        .collect::<BTreeSet<_>>();
+    for key_prefix in key_prefixes {
+        let keys = store
+            .find_keys_by_prefix(&key_prefix)
+            .await
+            .unwrap()
+            .iterator()
+            .map(|key| key.unwrap().to_vec())
+            .collect::<Vec<_>>();
+        let stream_keys = store
+            .find_keys_by_prefix_stream(&key_prefix)
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(stream_keys, keys);
+        let key_values = store
+            .find_key_values_by_prefix(&key_prefix)
+            .await
+            .unwrap()
+            .into_iterator_owned()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let stream_key_values = store
+            .find_key_values_by_prefix_stream(&key_prefix)
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(stream_key_values, key_values);
+    }
+}
+
+/// Writes the `key_values` in `store1`, dumps them and restores the dump in the empty
+/// `store2`. Checks that both stores then have the same content, and that dumping
+/// `store2` gives exactly the same bytes.
+pub async fn run_dump_and_restore<S1, S2>(
+    store1: S1,
+    store2: S2,
+    key_values: Vec<(Vec<u8>, Vec<u8>)>,
+) where
+    S1: LocalRestrictedKeyValueStore + Sync,
+    S2: LocalRestrictedKeyValueStore + Sync,
+{
+    let mut batch = Batch::new();
+    for (key, value) in &key_values {
+        batch.put_key_value_bytes(key.clone(), value.clone());
+    }
+    store1.write_batch(batch).await.unwrap();
+    let key_prefix = &key_values[0].0[..1];
+    let mut dump1 = Vec::new();
+    let count = store1.dump_to_writer(key_prefix, &mut dump1).await.unwrap();
+    let restored = store2.restore_from_reader(&dump1[..]).await.unwrap();
+    assert_eq!(count, restored);
+    let key_values1 = store1
+        .find_key_values_by_prefix(key_prefix)
+        .await
+        .unwrap()
+        .into_iterator_owned()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    let key_values2 = store2
+        .find_key_values_by_prefix(key_prefix)
+        .await
+        .unwrap()
+        .into_iterator_owned()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(key_values1.len(), count);
+    assert_eq!(key_values1, key_values2);
+    let mut dump2 = Vec::new();
+    store2.dump_to_writer(key_prefix, &mut dump2).await.unwrap();
+    assert_eq!(dump1, dump2);
+}
+
+/// Checks the semantics of `compare_and_put` for absent and present keys, including
+/// when the condition fails.
+pub async fn run_compare_and_put<S: LocalRestrictedKeyValueStore + Sync>(store: S) {
+    let key = vec![0, 1];
+    // Insertion of an absent key.
+    assert!(store
+        .compare_and_put(&key, None, Some(&[1][..]))
+        .await
+        .unwrap());
+    assert!(!store
+        .compare_and_put(&key, None, Some(&[2][..]))
+        .await
+        .unwrap());
+    assert_eq!(store.read_value_bytes(&key).await.unwrap(), Some(vec![1]));
+    // Replacement of the value.
+    assert!(!store
+        .compare_and_put(&key, Some(&[2][..]), Some(&[3][..]))
+        .await
+        .unwrap());
+    assert!(store
+        .compare_and_put(&key, Some(&[1][..]), Some(&[3][..]))
+        .await
+        .unwrap());
+    assert_eq!(store.read_value_bytes(&key).await.unwrap(), Some(vec![3]));
+    // Deletion of the key.
+    assert!(!store
+        .compare_and_put(&key, Some(&[1][..]), None)
+        .await
+        .unwrap());
+    assert!(store
+        .compare_and_put(&key, Some(&[3][..]), None)
+        .await
+        .unwrap());
+    assert_eq!(store.read_value_bytes(&key).await.unwrap(), None);
+    assert!(!store
+        .compare_and_put(&key, Some(&[3][..]), None)
+        .await
+        .unwrap());
+}
+
+/// Checks that `count_keys_by_prefix` matches the number of keys of `find_keys_by_prefix`.
+/// Large values make the scans of the stores span several pages.
+pub async fn run_count_keys_by_prefix<S: LocalRestrictedKeyValueStore + Sync>(
+    store: S,
+    num_entries: usize,
+    value_size: usize,
+) {
+    let mut batch = Batch::new();
+    for index in 0..num_entries {
+        let mut key = vec![0];
+        key.extend((index as u32).to_be_bytes());
+        batch.put_key_value_bytes(key, vec![index as u8; value_size]);
+    }
+    batch.put_key_value_bytes(vec![1, 0], vec![1]);
+    store.write_batch(batch).await.unwrap();
+    for (key_prefix, expected) in [(vec![0], num_entries), (vec![1], 1), (vec![2], 0)] {
+        let keys = store.find_keys_by_prefix(&key_prefix).await.unwrap();
+        assert_eq!(keys.iterator().count(), expected);
+        let count = store.count_keys_by_prefix(&key_prefix).await.unwrap();
+        assert_eq!(count, expected);
+    }
+}
+
+/// Checks that iterating over the pages of `find_keys_by_prefix_paginated` gives the
+/// keys of `find_keys_by_prefix`, including when the continuation key gets deleted.
+pub async fn run_find_keys_by_prefix_paginated<S: LocalRestrictedKeyValueStore + Sync>(
+    store: S,
+    key_values: Vec<(Vec<u8>, Vec<u8>)>,
+) {
+    let mut batch = Batch::new();
+    for (key, value) in &key_values {
+        batch.put_key_value_bytes(key.clone(), value.clone());
+    }
+    store.write_batch(batch).await.unwrap();
+    let key_prefix = &key_values[0].0[..1];
+    let keys = store
+        .find_keys_by_prefix(key_prefix)
+        .await
+        .unwrap()
+        .iterator()
+        .map(|key| key.unwrap().to_vec())
+        .collect::<Vec<_>>();
+    for limit in [1, 3, keys.len(), keys.len() + 10] {
+        let mut paginated_keys = Vec::new();
+        let mut continuation = None;
+        loop {
+            let (page, next_continuation) = store
+                .find_keys_by_prefix_paginated(key_prefix, limit, continuation)
+                .await
+                .unwrap();
+            assert!(page.len() <= limit);
+            paginated_keys.extend(page);
+            continuation = next_continuation;
+            if continuation.is_none() {
+                break;
+            }
+        }
+        assert_eq!(paginated_keys, keys);
+    }
+    // Deleting the continuation key does not prevent the scan from resuming.
+    let (page, continuation) = store
+        .find_keys_by_prefix_paginated(key_prefix, 1, None)
+        .await
+        .unwrap();
+    let continuation = continuation.unwrap();
+    assert_eq!(page, vec![continuation.clone()]);
+    let mut batch = Batch::new();
+    batch.delete_key([key_prefix, &continuation[..]].concat());
+    store.write_batch(batch).await.unwrap();
+    let (page, _) = store
+        .find_keys_by_prefix_paginated(key_prefix, keys.len(), Some(continuation))
+        .await
+        .unwrap();
+    assert_eq!(page, keys[1..]);
+}
+
 fn get_random_key_values1(num_entries: usize, len_value: usize) -> Vec<(Vec<u8>, Vec<u8>)> {
     let key_prefix = vec![0];
     let mut rng = make_deterministic_rng();
@@ -357,6 +653,9 @@ fn update_state_from_batch(kv_state: &mut BTreeMap<Vec<u8>, Vec<u8>>, batch: &Ba
             WriteOperation::DeletePrefix { key_prefix } => {
                 kv_state.retain(|key, _| !key.starts_with(key_prefix));
             }
+            WriteOperation::DeleteRange { start, end } => {
+                kv_state.retain(|key, _| key < start || key >= end);
+            }
         }
     }
 }
@@ -700,3 +999,282 @@ where
             .expect("A successful deletion");
     }
 }
+
+/// Checks that a context rejects the keys longer than the `MAX_KEY_SIZE` of its store
+/// with [`ViewError::KeyTooLong`], and the values larger than its `MAX_VALUE_SIZE` with
+/// [`ViewError::TooLargeValue`], while accepting the keys and values of the maximal
+/// sizes. Limits too large to be reached in a test are not checked.
+pub async fn run_size_limits<S>(store: S)
+where
+    S: RestrictedKeyValueStore + Clone + Send + Sync,
+    S::Error: Send + Sync + 'static,
+{
+    const MAX_TESTED_SIZE: usize = 1 << 16;
+    let context = ViewContext::create_root_context(store, ()).await.unwrap();
+    let max_key_size = <S as ReadableKeyValueStore>::MAX_KEY_SIZE;
+    if max_key_size <= MAX_TESTED_SIZE {
+        let key = vec![1; max_key_size];
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(key.clone(), vec![1]);
+        context.write_batch(batch).await.unwrap();
+        assert_eq!(context.read_value_bytes(&key).await.unwrap(), Some(vec![1]));
+
+        let long_key = vec![1; max_key_size + 1];
+        let mut batches = vec![Batch::new(); 4];
+        batches[0].put_key_value_bytes(long_key.clone(), vec![1]);
+        batches[1].delete_key(long_key.clone());
+        batches[2].delete_key_prefix(long_key.clone());
+        batches[3].delete_key_range(vec![1], long_key.clone());
+        for batch in batches {
+            let result = context.write_batch(batch).await;
+            assert!(matches!(result, Err(ViewError::KeyTooLong)));
+        }
+        let result = context.derive_key(&key);
+        assert!(matches!(result, Err(ViewError::KeyTooLong)));
+    }
+    let max_value_size = <S as WritableKeyValueStore>::MAX_VALUE_SIZE;
+    if max_value_size <= MAX_TESTED_SIZE {
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![2], vec![2; max_value_size]);
+        context.write_batch(batch).await.unwrap();
+
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![2], vec![2; max_value_size + 1]);
+        let result = context.write_batch(batch).await;
+        assert!(matches!(result, Err(ViewError::TooLargeValue)));
+    }
+}
+
+/// Returns a store on its own root key, so that the tests of a suite do not see the
+/// keys written by each other.
+fn get_isolated_store<S: LocalKeyValueStore>(store: &S, suite: u8, index: usize) -> S {
+    let root_key = [
+        b"conformance".as_slice(),
+        &[suite],
+        &(index as u32).to_be_bytes(),
+    ]
+    .concat();
+    store
+        .clone_with_root_key(&root_key)
+        .expect("a store with a new root key")
+}
+
+/// Writes `initial` and then `batch` on a store, and checks the resulting keys under
+/// `key_prefix` against the operations of the batches.
+async fn check_batch_semantics<S: LocalRestrictedKeyValueStore>(
+    store: &S,
+    key_prefix: &[u8],
+    initial: Batch,
+    batch: Batch,
+) {
+    let mut kv_state = realize_batch(&initial);
+    update_state_from_batch(&mut kv_state, &batch);
+    store.write_batch(initial).await.unwrap();
+    store.write_batch(batch).await.unwrap();
+    let key_values = read_key_values_prefix(store, key_prefix).await;
+    assert_eq!(key_values, kv_state);
+}
+
+/// The conformance suite for the reads and the writes of single keys. It can be run by
+/// the authors of a new backend on a store connected to an empty namespace. It checks:
+/// * the results of `run_reads`, `run_writes_from_blank` and `run_writes_from_state`;
+/// * that absent keys are reported as such by all the reading functions;
+/// * that writing a key again replaces its value;
+/// * that an empty batch can be written and changes nothing;
+/// * that a store either rejects the empty key or stores it like any other key.
+pub async fn run_reads_writes_suite<S: LocalKeyValueStore + Sync>(store: &S) {
+    for (index, scenario) in get_random_test_scenarios().into_iter().enumerate() {
+        run_reads(get_isolated_store(store, 0, index), scenario).await;
+    }
+    run_writes_from_blank(&get_isolated_store(store, 1, 0)).await;
+    run_writes_from_state(&get_isolated_store(store, 1, 1)).await;
+
+    let store = get_isolated_store(store, 2, 0);
+    let key = vec![1, 2, 3];
+    assert_eq!(store.read_value_bytes(&key).await.unwrap(), None);
+    assert!(!store.contains_key(&key).await.unwrap());
+    let keys = vec![key.clone(), vec![4], key.clone()];
+    assert_eq!(
+        store.read_multi_values_bytes(keys.clone()).await.unwrap(),
+        vec![None, None, None]
+    );
+    assert_eq!(
+        store.contains_keys(keys.clone()).await.unwrap(),
+        vec![false, false, false]
+    );
+
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(key.clone(), vec![1]);
+    store.write_batch(batch).await.unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(key.clone(), vec![2, 2]);
+    store.write_batch(batch).await.unwrap();
+    assert_eq!(
+        store.read_value_bytes(&key).await.unwrap(),
+        Some(vec![2, 2])
+    );
+    assert_eq!(
+        store.read_multi_values_bytes(keys.clone()).await.unwrap(),
+        vec![Some(vec![2, 2]), None, Some(vec![2, 2])]
+    );
+    assert_eq!(
+        store.contains_keys(keys).await.unwrap(),
+        vec![true, false, true]
+    );
+
+    store.write_batch(Batch::new()).await.unwrap();
+    let key_values = read_key_values_prefix(&store, &[1]).await;
+    assert_eq!(key_values, BTreeMap::from([(key, vec![2, 2])]));
+
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(Vec::new(), vec![3]);
+    if store.write_batch(batch).await.is_ok() {
+        assert_eq!(store.read_value_bytes(&[]).await.unwrap(), Some(vec![3]));
+    }
+}
+
+/// The conformance suite for the searches of keys. It can be run by the authors of a new
+/// backend on a store connected to an empty namespace. It checks:
+/// * the results of `run_reads_with_order`, `run_find_keys_in_range`,
+///   `run_prefix_streams`, `run_find_keys_by_prefix_paginated` and
+///   `run_count_keys_by_prefix`;
+/// * that searching a prefix without keys returns nothing;
+/// * that a store either rejects the empty prefix or returns all its keys in increasing
+///   order.
+pub async fn run_prefix_scan_suite<S: LocalKeyValueStore + Sync>(store: &S) {
+    for (index, scenario) in get_random_test_scenarios().into_iter().enumerate() {
+        run_reads_with_order(get_isolated_store(store, 3, index), scenario.clone()).await;
+        run_find_keys_in_range(get_isolated_store(store, 4, index), scenario.clone()).await;
+        run_prefix_streams(get_isolated_store(store, 5, index), scenario.clone()).await;
+        run_find_keys_by_prefix_paginated(get_isolated_store(store, 6, index), scenario).await;
+    }
+    run_count_keys_by_prefix(get_isolated_store(store, 7, 0), 100, 10).await;
+
+    let store = get_isolated_store(store, 8, 0);
+    let mut batch = Batch::new();
+    for key in [vec![2, 1], vec![1], vec![1, 0, 5], vec![1, 0]] {
+        batch.put_key_value_bytes(key.clone(), key);
+    }
+    store.write_batch(batch).await.unwrap();
+    let keys = store.find_keys_by_prefix(&[3]).await.unwrap();
+    assert_eq!(keys.iterator().count(), 0);
+    let key_values = store.find_key_values_by_prefix(&[1, 1]).await.unwrap();
+    assert_eq!(key_values.iterator().count(), 0);
+    let keys = store.find_keys_by_prefix(&[1]).await.unwrap();
+    let keys = keys.iterator().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(keys, vec![&[][..], &[0], &[0, 5]]);
+    if let Ok(keys) = store.find_keys_by_prefix(&[]).await {
+        let keys = keys.iterator().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(keys, vec![&[1][..], &[1, 0], &[1, 0, 5], &[2, 1]]);
+    }
+}
+
+/// The conformance suite for the semantics of the batches. It can be run by the authors
+/// of a new backend on a store connected to an empty namespace. The operations of a batch
+/// must take effect in their order, which this checks for:
+/// * the deletion and the insertion of the same key, in both orders;
+/// * the insertions before and after the deletion of their prefix or of their range;
+/// * the deletions of absent keys, prefixes and ranges, which are no-ops;
+/// * several insertions of the same key, the last one of which wins.
+pub async fn run_batch_semantics_suite<S: LocalKeyValueStore + Sync>(store: &S) {
+    let key_prefix = vec![0];
+    let get_initial = || {
+        let mut batch = Batch::new();
+        for i in 0..8u8 {
+            batch.put_key_value_bytes(vec![0, i], vec![i]);
+            batch.put_key_value_bytes(vec![0, i, 1], vec![i, 1]);
+        }
+        batch
+    };
+    let mut batches = Vec::new();
+
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0, 9], vec![9]);
+    batch.delete_key(vec![0, 9]);
+    batch.delete_key(vec![0, 1]);
+    batch.put_key_value_bytes(vec![0, 1], vec![10]);
+    batches.push(batch);
+
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0, 2, 2], vec![22]);
+    batch.delete_key_prefix(vec![0, 2]);
+    batch.put_key_value_bytes(vec![0, 2, 1], vec![21]);
+    batch.delete_key_prefix(vec![0, 3, 1]);
+    batches.push(batch);
+
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0, 4, 4], vec![44]);
+    batch.delete_key_range(vec![0, 4], vec![0, 6]);
+    batch.put_key_value_bytes(vec![0, 5], vec![55]);
+    batches.push(batch);
+
+    let mut batch = Batch::new();
+    batch.delete_key(vec![0, 100]);
+    batch.delete_key_prefix(vec![0, 100]);
+    batch.delete_key_prefix(vec![1]);
+    batch.delete_key_range(vec![0, 100], vec![0, 200]);
+    batch.delete_key_range(vec![0, 6], vec![0, 2]);
+    batches.push(batch);
+
+    let mut batch = Batch::new();
+    for i in 0..4u8 {
+        batch.put_key_value_bytes(vec![0, 7], vec![i]);
+    }
+    batch.delete_key_prefix(vec![0, 7, 1]);
+    batches.push(batch);
+
+    for (index, batch) in batches.into_iter().enumerate() {
+        let store = get_isolated_store(store, 9, index);
+        check_batch_semantics(&store, &key_prefix, get_initial(), batch).await;
+    }
+}
+
+/// A storage operation recorded by a [`RecordingObserver`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecordedOperation {
+    /// The kind of the operation.
+    pub operation: StoreOperation,
+    /// The length of the key of the operation.
+    pub key_len: usize,
+    /// The base key of the context doing the operation.
+    pub scope: Vec<u8>,
+    /// The key of the operation, if the observer asked for the keys.
+    pub key: Option<Vec<u8>>,
+}
+
+/// A [`StoreObserver`] recording all the operations of a context, for tests.
+#[derive(Debug, Default)]
+pub struct RecordingObserver {
+    with_keys: bool,
+    operations: Mutex<Vec<RecordedOperation>>,
+}
+
+impl RecordingObserver {
+    /// Creates an observer recording the keys of the operations as well.
+    pub fn with_keys() -> Self {
+        Self {
+            with_keys: true,
+            operations: Mutex::default(),
+        }
+    }
+
+    /// Returns the operations recorded so far and forgets them.
+    pub fn take(&self) -> Vec<RecordedOperation> {
+        std::mem::take(&mut *self.operations.lock().unwrap())
+    }
+}
+
+impl StoreObserver for RecordingObserver {
+    fn wants_keys(&self) -> bool {
+        self.with_keys
+    }
+
+    fn observe(&self, event: StoreEvent<'_>) {
+        self.operations.lock().unwrap().push(RecordedOperation {
+            operation: event.operation,
+            key_len: event.key_len,
+            scope: event.scope.to_vec(),
+            key: event.key.map(<[u8]>::to_vec),
+        });
+    }
+}
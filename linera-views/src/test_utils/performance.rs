@@ -33,6 +33,12 @@ const LEN_KEY: usize = 10;
 /// The length of the values
 const LEN_VALUE: usize = 10000;
 
+/// The number of operations in a large batch
+const NUM_LARGE_BATCH_ENTRIES: usize = 10000;
+
+/// The length of the values in a large batch
+const LEN_LARGE_BATCH_VALUE: usize = 100;
+
 async fn clear_store<S: LocalKeyValueStore>(store: &S) {
     let mut batch = Batch::new();
     batch.delete_key_prefix(PREFIX.to_vec());
@@ -259,3 +265,39 @@ where
 
     total_time
 }
+
+/// Benchmarks the `write_batch` operation on a batch large enough to go through the journal.
+pub async fn write_large_batch<S: LocalKeyValueStore>(store: S, iterations: u64) -> Duration
+where
+    S::Error: Debug,
+{
+    write_batch_of_puts(store, iterations, NUM_LARGE_BATCH_ENTRIES).await
+}
+
+/// Benchmarks the `write_batch` operation on a batch of `num_operations` small puts.
+pub async fn write_batch_of_puts<S: LocalKeyValueStore>(
+    store: S,
+    iterations: u64,
+    num_operations: usize,
+) -> Duration
+where
+    S::Error: Debug,
+{
+    let mut total_time = Duration::ZERO;
+    for _ in 0..iterations {
+        let mut batch = Batch::new();
+        for index in 0..num_operations as u32 {
+            let mut key = PREFIX.to_vec();
+            key.extend(index.to_be_bytes());
+            batch.put_key_value_bytes(key, vec![0; LEN_LARGE_BATCH_VALUE]);
+        }
+
+        let measurement = Instant::now();
+        store.write_batch(batch).await.unwrap();
+        total_time += measurement.elapsed();
+
+        clear_store(&store).await;
+    }
+
+    total_time
+}
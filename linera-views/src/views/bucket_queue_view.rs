@@ -216,12 +216,12 @@ where
     fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
         let mut delete_view = false;
         if self.delete_storage_first {
-            let key_prefix = self.context.base_key();
+            let key_prefix = self.context.base_key().to_vec();
             batch.delete_key_prefix(key_prefix);
             delete_view = true;
         }
         if self.stored_count() == 0 {
-            let key_prefix = self.context.base_key();
+            let key_prefix = self.context.base_key().to_vec();
             batch.delete_key_prefix(key_prefix);
             self.stored_data.clear();
             self.stored_position = 0;
@@ -459,8 +459,7 @@ where
                     let index = *index;
                     if !bucket.is_loaded() {
                         let key = self.get_index_key(index)?;
-                        let value = self.context.read_value_bytes(&key).await?;
-                        let value = value.ok_or(ViewError::MissingEntries)?;
+                        let value = self.context.read_value_bytes_or_err(&key).await?;
                         let data = bcs::from_bytes(&value)?;
                         self.stored_data[i_block].1 = Bucket::Loaded { data };
                     }
@@ -532,9 +531,8 @@ where
         };
         if !bucket.is_loaded() {
             let key = self.get_index_key(*index)?;
-            let value = self.context.read_value_bytes(&key).await?;
-            let value = value.as_ref().ok_or(ViewError::MissingEntries)?;
-            let data = bcs::from_bytes::<Vec<T>>(value)?;
+            let value = self.context.read_value_bytes_or_err(&key).await?;
+            let data = bcs::from_bytes::<Vec<T>>(&value)?;
             self.stored_data.back_mut().unwrap().1 = Bucket::Loaded { data };
         }
         let bucket = &self.stored_data.back_mut().unwrap().1;
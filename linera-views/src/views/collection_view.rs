@@ -9,10 +9,12 @@ use std::{
     io::Write,
     marker::PhantomData,
     mem,
+    ops::Range,
 };
 
 use async_lock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use async_trait::async_trait;
+use futures::{future, stream, StreamExt as _, TryStreamExt as _};
 use serde::{de::DeserializeOwned, Serialize};
 #[cfg(with_metrics)]
 use {
@@ -25,7 +27,7 @@ use crate::{
     common::{CustomSerialize, HasherOutput, Update},
     context::Context,
     hashable_wrapper::WrappedHashableContainerView,
-    store::KeyIterable,
+    store::{KeyIterable, ResultStream},
     views::{ClonableView, HashableView, Hasher, View, ViewError, MIN_VIEW_TAG},
 };
 
@@ -40,6 +42,10 @@ static COLLECTION_VIEW_HASH_RUNTIME: LazyLock<HistogramVec> = LazyLock::new(|| {
     )
 });
 
+/// The number of subviews read at the same time when loading all the entries of a
+/// collection, or the entries of a GraphQL query.
+const MAX_CONCURRENT_LOADS: usize = 16;
+
 /// A view that supports accessing a collection of views of the same kind, indexed by a
 /// `Vec<u8>`, one subview at a time.
 #[derive(Debug)]
@@ -127,7 +133,7 @@ where
         let mut delete_view = false;
         if self.delete_storage_first {
             delete_view = true;
-            batch.delete_key_prefix(self.context.base_key());
+            batch.delete_key_prefix(self.context.base_key().to_vec());
             for (index, update) in mem::take(self.updates.get_mut()) {
                 if let Update::Set(mut view) = update {
                     view.flush(batch)?;
@@ -208,6 +214,27 @@ where
         batch.put_key_value_bytes(key, vec![]);
     }
 
+    /// Discards the pending updates of the entries whose keys are in `range`.
+    pub(crate) fn discard_updates_in_range(&mut self, range: &Range<Vec<u8>>) {
+        self.updates
+            .get_mut()
+            .retain(|short_key, _| !range.contains(short_key));
+    }
+
+    /// Deletes the stored entries whose keys are in `range`, provided that all the keys
+    /// have the same length, with one range deletion for the indices and one for the
+    /// subviews instead of two deletions per entry.
+    pub(crate) fn delete_stored_range(&self, batch: &mut Batch, range: &Range<Vec<u8>>) {
+        batch.delete_key_range(
+            self.get_index_key(&range.start),
+            self.get_index_key(&range.end),
+        );
+        batch.delete_key_range(
+            self.get_subview_key(&range.start),
+            self.get_subview_key(&range.end),
+        );
+    }
+
     /// Loads a subview for the data at the given index in the collection. If an entry
     /// is absent then a default entry is added to the collection. The resulting view
     /// can be modified.
@@ -317,6 +344,88 @@ where
         }
     }
 
+    /// Loads the subviews at the given indices in the collection. Absent entries are
+    /// returned as `None`. The missing subviews are loaded with at most `max_concurrency`
+    /// reads at the same time. May fail if one subview is already being visited.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::ByteCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: ByteCollectionView<_, RegisterView<_, String>> =
+    ///     ByteCollectionView::load(context).await.unwrap();
+    /// {
+    ///     let _subview = view.load_entry_or_insert(&[0, 1]).await.unwrap();
+    /// }
+    /// let subviews = view
+    ///     .try_load_entries(vec![vec![0, 1], vec![0, 2]], 2)
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(*subviews[0].as_ref().unwrap().get(), String::default());
+    /// assert!(subviews[1].is_none());
+    /// # })
+    /// ```
+    pub async fn try_load_entries(
+        &self,
+        short_keys: Vec<Vec<u8>>,
+        max_concurrency: usize,
+    ) -> Result<Vec<Option<ReadGuardedView<W>>>, ViewError> {
+        let mut updates = self
+            .updates
+            .try_write()
+            .ok_or(ViewError::CannotAcquireCollectionEntry)?;
+        if !self.delete_storage_first {
+            let mut short_keys_to_check = short_keys
+                .iter()
+                .filter(|short_key| !updates.contains_key(*short_key))
+                .cloned()
+                .collect::<Vec<_>>();
+            short_keys_to_check.sort();
+            short_keys_to_check.dedup();
+            let keys_to_check = short_keys_to_check
+                .iter()
+                .map(|short_key| self.get_index_key(short_key))
+                .collect();
+            let found_keys = self.context.contains_keys(keys_to_check).await?;
+            let views = stream::iter(
+                short_keys_to_check
+                    .into_iter()
+                    .zip(found_keys)
+                    .filter_map(|(short_key, found)| found.then_some(short_key)),
+            )
+            .map(|short_key| async move {
+                let key = self.get_subview_key(&short_key);
+                let context = self.context.clone_with_base_key(key);
+                let keys = W::pre_load(&context)?;
+                let values = self.context.read_multi_values_bytes(keys).await?;
+                let view = W::post_load(context, &values)?;
+                Ok::<_, ViewError>((short_key, view))
+            })
+            .buffer_unordered(max_concurrency.max(1))
+            .try_collect::<Vec<_>>()
+            .await?;
+            for (short_key, view) in views {
+                updates.insert(short_key, Update::Set(view));
+            }
+        }
+        let guard = RwLockWriteGuard::downgrade(updates);
+        let mut results = Vec::with_capacity(short_keys.len());
+        for short_key in short_keys {
+            if let Some(Update::Set(_)) = guard.get(&short_key) {
+                let guard = self
+                    .updates
+                    .try_read()
+                    .ok_or(ViewError::CannotAcquireCollectionEntry)?;
+                results.push(Some(ReadGuardedView { guard, short_key }));
+            } else {
+                results.push(None);
+            }
+        }
+        Ok(results)
+    }
+
     /// Resets an entry to the default value.
     /// ```rust
     /// # tokio_test::block_on(async {
@@ -586,6 +695,244 @@ where
         Ok(keys)
     }
 
+    /// Applies a function f on each key `key` such that `start <= key < end`. Keys are
+    /// visited in the lexicographic order. If the function returns false, then the
+    /// loop ends prematurely.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::ByteCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: ByteCollectionView<_, RegisterView<_, String>> =
+    ///     ByteCollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&[0, 1]).await.unwrap();
+    /// view.load_entry_mut(&[0, 2]).await.unwrap();
+    /// view.load_entry_mut(&[0, 3]).await.unwrap();
+    /// let mut keys = Vec::new();
+    /// view.for_each_key_in_range_while(
+    ///     |key| {
+    ///         keys.push(key.to_vec());
+    ///         Ok(keys.len() < 1)
+    ///     },
+    ///     vec![0, 2]..vec![1],
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(keys, vec![vec![0, 2]]);
+    /// # })
+    /// ```
+    pub async fn for_each_key_in_range_while<F>(
+        &self,
+        mut f: F,
+        range: Range<Vec<u8>>,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8]) -> Result<bool, ViewError> + Send,
+    {
+        let Range { start, end } = range;
+        if start >= end {
+            return Ok(());
+        }
+        self.for_each_key_from_while(f, start, Some(end)).await
+    }
+
+    /// Applies a function f on each key `key` such that `start <= key`, and `key < end`
+    /// if `end` is given, as long as f returns true.
+    async fn for_each_key_from_while<F>(
+        &self,
+        mut f: F,
+        start: Vec<u8>,
+        end: Option<Vec<u8>>,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8]) -> Result<bool, ViewError> + Send,
+    {
+        let updates = self.updates.write().await;
+        let mut updates = match &end {
+            Some(end) => updates.range(start.clone()..end.clone()),
+            None => updates.range(start.clone()..),
+        };
+        let mut update = updates.next();
+        if !self.delete_storage_first {
+            let base = self.get_index_key(&[]);
+            let mut indices: ResultStream<'_, Vec<u8>, C::Error> = match end {
+                Some(end) => {
+                    let start = self.get_index_key(&start);
+                    let end = self.get_index_key(&end);
+                    let full_keys = self.context.find_keys_in_range(&start, &end).await?;
+                    let indices = full_keys
+                        .into_iter()
+                        .map(|full_key| Ok(full_key[base.len()..].to_vec()))
+                        .collect::<Vec<_>>();
+                    Box::pin(stream::iter(indices))
+                }
+                None => {
+                    // The indices are streamed, so that they are only read as far as `f`
+                    // goes.
+                    let indices = self.context.find_keys_by_prefix_stream(&base).await?;
+                    Box::pin(indices.try_filter(move |index| future::ready(*index >= start)))
+                }
+            };
+            while let Some(index) = indices.try_next().await? {
+                let index = index.as_slice();
+                loop {
+                    match update {
+                        Some((key, value)) if key.as_slice() <= index => {
+                            if let Update::Set(_) = value {
+                                if !f(key)? {
+                                    return Ok(());
+                                }
+                            }
+                            update = updates.next();
+                            if key == index {
+                                break;
+                            }
+                        }
+                        _ => {
+                            if !f(index)? {
+                                return Ok(());
+                            }
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        while let Some((key, value)) = update {
+            if let Update::Set(_) = value {
+                if !f(key)? {
+                    return Ok(());
+                }
+            }
+            update = updates.next();
+        }
+        Ok(())
+    }
+
+    /// Applies a function f on each key `key` such that `start <= key < end`. Keys are
+    /// visited in the lexicographic order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::ByteCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: ByteCollectionView<_, RegisterView<_, String>> =
+    ///     ByteCollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&[0, 1]).await.unwrap();
+    /// view.load_entry_mut(&[1, 2]).await.unwrap();
+    /// let mut count = 0;
+    /// view.for_each_key_in_range(
+    ///     |_key| {
+    ///         count += 1;
+    ///         Ok(())
+    ///     },
+    ///     vec![1]..vec![2],
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(count, 1);
+    /// # })
+    /// ```
+    pub async fn for_each_key_in_range<F>(
+        &self,
+        mut f: F,
+        range: Range<Vec<u8>>,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8]) -> Result<(), ViewError> + Send,
+    {
+        self.for_each_key_in_range_while(
+            |key| {
+                f(key)?;
+                Ok(true)
+            },
+            range,
+        )
+        .await
+    }
+
+    /// Returns the list of keys `key` in the collection such that `start <= key < end`.
+    /// The order is lexicographic.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::ByteCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: ByteCollectionView<_, RegisterView<_, String>> =
+    ///     ByteCollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&[0, 1]).await.unwrap();
+    /// view.load_entry_mut(&[0, 2]).await.unwrap();
+    /// let keys = view.keys_in_range(vec![0, 2]..vec![0, 3]).await.unwrap();
+    /// assert_eq!(keys, vec![vec![0, 2]]);
+    /// # })
+    /// ```
+    pub async fn keys_in_range(&self, range: Range<Vec<u8>>) -> Result<Vec<Vec<u8>>, ViewError> {
+        let mut keys = Vec::new();
+        self.for_each_key_in_range(
+            |key| {
+                keys.push(key.to_vec());
+                Ok(())
+            },
+            range,
+        )
+        .await?;
+        Ok(keys)
+    }
+
+    /// Returns at most `count` keys of the collection in lexicographic order, starting
+    /// with the key following `after`, or with the first key if `after` is `None`. Only
+    /// the keys of that range are scanned, so that the collection can be paginated.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::ByteCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: ByteCollectionView<_, RegisterView<_, String>> =
+    ///     ByteCollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&[0, 1]).await.unwrap();
+    /// view.load_entry_mut(&[1, 2]).await.unwrap();
+    /// view.load_entry_mut(&[2, 2]).await.unwrap();
+    /// assert_eq!(
+    ///     view.keys_page(None, 2).await.unwrap(),
+    ///     vec![vec![0, 1], vec![1, 2]]
+    /// );
+    /// assert_eq!(
+    ///     view.keys_page(Some(&[1, 2]), 2).await.unwrap(),
+    ///     vec![vec![2, 2]]
+    /// );
+    /// # })
+    /// ```
+    pub async fn keys_page(
+        &self,
+        after: Option<&[u8]>,
+        count: usize,
+    ) -> Result<Vec<Vec<u8>>, ViewError> {
+        let mut keys = Vec::new();
+        if count == 0 {
+            return Ok(keys);
+        }
+        // The smallest key following `after` is `after` followed by a zero.
+        let start = after.map_or_else(Vec::new, |after| [after, &[0]].concat());
+        self.for_each_key_from_while(
+            |key| {
+                keys.push(key.to_vec());
+                Ok(keys.len() < count)
+            },
+            start,
+            None,
+        )
+        .await?;
+        Ok(keys)
+    }
+
     /// Returns the number of entries in the collection.
     /// ```rust
     /// # tokio_test::block_on(async {
@@ -602,14 +949,81 @@ where
     /// # })
     /// ```
     pub async fn count(&self) -> Result<usize, ViewError> {
-        let mut count = 0;
-        self.for_each_key(|_key| {
-            count += 1;
-            Ok(())
-        })
-        .await?;
+        let updates = self.updates.read().await;
+        let num_insertions = updates
+            .values()
+            .filter(|update| matches!(update, Update::Set(_)))
+            .count();
+        if self.delete_storage_first {
+            return Ok(num_insertions);
+        }
+        let base = self.get_index_key(&[]);
+        let mut count = self.context.count_keys_by_prefix(&base).await?;
+        let keys = updates
+            .keys()
+            .map(|short_key| self.get_index_key(short_key))
+            .collect();
+        let stored = self.context.contains_keys(keys).await?;
+        for (update, stored) in updates.values().zip(stored) {
+            match update {
+                Update::Set(_) if !stored => count += 1,
+                Update::Removed if stored => count -= 1,
+                _ => {}
+            }
+        }
         Ok(count)
     }
+
+    /// Returns true if the collection has no entries.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::ByteCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: ByteCollectionView<_, RegisterView<_, String>> =
+    ///     ByteCollectionView::load(context).await.unwrap();
+    /// assert!(view.is_empty().await.unwrap());
+    /// view.load_entry_mut(&[0, 1]).await.unwrap();
+    /// assert!(!view.is_empty().await.unwrap());
+    /// # })
+    /// ```
+    pub async fn is_empty(&self) -> Result<bool, ViewError> {
+        Ok(self.count().await? == 0)
+    }
+    /// Loads all the entries of the collection for reading at once, which is meant for
+    /// small collections.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::ByteCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: ByteCollectionView<_, RegisterView<_, String>> =
+    ///     ByteCollectionView::load(context).await.unwrap();
+    /// {
+    ///     let _subview = view.load_entry_or_insert(&[0, 1]).await.unwrap();
+    /// }
+    /// let subviews = view.try_load_all_entries().await.unwrap();
+    /// assert_eq!(subviews.len(), 1);
+    /// assert_eq!(subviews[0].0, vec![0, 1]);
+    /// # })
+    /// ```
+    pub async fn try_load_all_entries(
+        &self,
+    ) -> Result<Vec<(Vec<u8>, ReadGuardedView<W>)>, ViewError> {
+        let short_keys = self.keys().await?;
+        let subviews = self
+            .try_load_entries(short_keys.clone(), MAX_CONCURRENT_LOADS)
+            .await?;
+        Ok(short_keys
+            .into_iter()
+            .zip(subviews)
+            .filter_map(|(short_key, subview)| Some((short_key, subview?)))
+            .collect())
+    }
 }
 
 #[async_trait]
@@ -845,6 +1259,45 @@ where
         self.collection.try_load_entry(&short_key).await
     }
 
+    /// Loads the subviews at the given indices in the collection. Absent entries are
+    /// returned as `None`. The resulting views cannot be modified. The missing subviews
+    /// are loaded with at most `max_concurrency` reads at the same time.
+    /// May fail if one subview is already being visited.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CollectionView<_, u64, RegisterView<_, String>> =
+    ///     CollectionView::load(context).await.unwrap();
+    /// {
+    ///     let _subview = view.load_entry_or_insert(&23).await.unwrap();
+    /// }
+    /// let subviews = view.try_load_entries(&[23, 24], 2).await.unwrap();
+    /// assert_eq!(*subviews[0].as_ref().unwrap().get(), String::default());
+    /// assert!(subviews[1].is_none());
+    /// # })
+    /// ```
+    pub async fn try_load_entries<'a, Q>(
+        &'a self,
+        indices: impl IntoIterator<Item = &'a Q>,
+        max_concurrency: usize,
+    ) -> Result<Vec<Option<ReadGuardedView<W>>>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + 'a,
+    {
+        let short_keys = indices
+            .into_iter()
+            .map(|index| C::derive_short_key(index))
+            .collect::<Result<_, _>>()?;
+        self.collection
+            .try_load_entries(short_keys, max_concurrency)
+            .await
+    }
+
     /// Resets an entry to the default value.
     /// ```rust
     /// # tokio_test::block_on(async {
@@ -959,6 +1412,55 @@ where
     pub async fn count(&self) -> Result<usize, ViewError> {
         self.collection.count().await
     }
+
+    /// Returns true if the collection has no entries.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CollectionView<_, u64, RegisterView<_, String>> =
+    ///     CollectionView::load(context).await.unwrap();
+    /// assert!(view.is_empty().await.unwrap());
+    /// view.load_entry_mut(&23).await.unwrap();
+    /// assert!(!view.is_empty().await.unwrap());
+    /// # })
+    /// ```
+    pub async fn is_empty(&self) -> Result<bool, ViewError> {
+        Ok(self.count().await? == 0)
+    }
+
+    /// Loads all the entries of the collection for reading at once, which is meant for
+    /// small collections.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CollectionView<_, u64, RegisterView<_, String>> =
+    ///     CollectionView::load(context).await.unwrap();
+    /// {
+    ///     let _subview = view.load_entry_or_insert(&23).await.unwrap();
+    /// }
+    /// let subviews = view.try_load_all_entries().await.unwrap();
+    /// assert_eq!(subviews.len(), 1);
+    /// assert_eq!(subviews[0].0, 23);
+    /// # })
+    /// ```
+    pub async fn try_load_all_entries(&self) -> Result<Vec<(I, ReadGuardedView<W>)>, ViewError> {
+        let results = self.collection.try_load_all_entries().await?;
+        results
+            .into_iter()
+            .map(|(short_key, view)| {
+                let index = C::deserialize_value(&short_key)?;
+                Ok((index, view))
+            })
+            .collect()
+    }
 }
 
 impl<C, I, W> CollectionView<C, I, W>
@@ -1040,6 +1542,139 @@ where
             .await?;
         Ok(())
     }
+
+    /// Applies a function f on each index `index` such that `start <= index < end` for
+    /// the order determined by the serialization. Indices are visited in that order. If
+    /// the function returns false then the loop ends prematurely.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CollectionView<_, u64, RegisterView<_, String>> =
+    ///     CollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&23).await.unwrap();
+    /// view.load_entry_mut(&24).await.unwrap();
+    /// view.load_entry_mut(&25).await.unwrap();
+    /// let mut count = 0;
+    /// view.for_each_index_in_range_while(
+    ///     |_index| {
+    ///         count += 1;
+    ///         Ok(count < 1)
+    ///     },
+    ///     &24..&26,
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(count, 1);
+    /// # })
+    /// ```
+    pub async fn for_each_index_in_range_while<Q, F>(
+        &self,
+        mut f: F,
+        range: Range<&Q>,
+    ) -> Result<(), ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        F: FnMut(I) -> Result<bool, ViewError> + Send,
+    {
+        let range = C::derive_short_key(range.start)?..C::derive_short_key(range.end)?;
+        self.collection
+            .for_each_key_in_range_while(
+                |key| {
+                    let index = C::deserialize_value(key)?;
+                    f(index)
+                },
+                range,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Applies a function f on each index `index` such that `start <= index < end` for
+    /// the order determined by the serialization. Indices are visited in that order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CollectionView<_, u64, RegisterView<_, String>> =
+    ///     CollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&23).await.unwrap();
+    /// view.load_entry_mut(&24).await.unwrap();
+    /// view.load_entry_mut(&25).await.unwrap();
+    /// let mut count = 0;
+    /// view.for_each_index_in_range(
+    ///     |_index| {
+    ///         count += 1;
+    ///         Ok(())
+    ///     },
+    ///     &24..&26,
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(count, 2);
+    /// # })
+    /// ```
+    pub async fn for_each_index_in_range<Q, F>(
+        &self,
+        mut f: F,
+        range: Range<&Q>,
+    ) -> Result<(), ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        F: FnMut(I) -> Result<(), ViewError> + Send,
+    {
+        self.for_each_index_in_range_while(
+            |index| {
+                f(index)?;
+                Ok(true)
+            },
+            range,
+        )
+        .await
+    }
+
+    /// Returns the list of indices `index` in the collection such that
+    /// `start <= index < end`. The order is determined by the serialization.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CollectionView<_, u64, RegisterView<_, String>> =
+    ///     CollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&23).await.unwrap();
+    /// view.load_entry_mut(&24).await.unwrap();
+    /// view.load_entry_mut(&25).await.unwrap();
+    /// let indices = view.indices_in_range(&23..&25).await.unwrap();
+    /// assert_eq!(indices, vec![23, 24]);
+    /// # })
+    /// ```
+    pub async fn indices_in_range<Q>(&self, range: Range<&Q>) -> Result<Vec<I>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let mut indices = Vec::new();
+        self.for_each_index_in_range(
+            |index| {
+                indices.push(index);
+                Ok(())
+            },
+            range,
+        )
+        .await?;
+        Ok(indices)
+    }
 }
 
 #[async_trait]
@@ -1225,6 +1860,45 @@ where
         self.collection.try_load_entry(&short_key).await
     }
 
+    /// Loads the subviews at the given indices in the collection. Absent entries are
+    /// returned as `None`. The resulting views cannot be modified. The missing subviews
+    /// are loaded with at most `max_concurrency` reads at the same time.
+    /// May fail if one subview is already being visited.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CustomCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CustomCollectionView<_, u128, RegisterView<_, String>> =
+    ///     CustomCollectionView::load(context).await.unwrap();
+    /// {
+    ///     let _subview = view.load_entry_or_insert(&23).await.unwrap();
+    /// }
+    /// let subviews = view.try_load_entries([23, 24], 2).await.unwrap();
+    /// assert_eq!(*subviews[0].as_ref().unwrap().get(), String::default());
+    /// assert!(subviews[1].is_none());
+    /// # })
+    /// ```
+    pub async fn try_load_entries<Q>(
+        &self,
+        indices: impl IntoIterator<Item = Q>,
+        max_concurrency: usize,
+    ) -> Result<Vec<Option<ReadGuardedView<W>>>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: CustomSerialize,
+    {
+        let short_keys = indices
+            .into_iter()
+            .map(|index| index.to_custom_bytes())
+            .collect::<Result<_, _>>()?;
+        self.collection
+            .try_load_entries(short_keys, max_concurrency)
+            .await
+    }
+
     /// Marks the entry so that it is removed in the next flush.
     /// ```rust
     /// # tokio_test::block_on(async {
@@ -1338,6 +2012,55 @@ where
     pub async fn count(&self) -> Result<usize, ViewError> {
         self.collection.count().await
     }
+
+    /// Returns true if the collection has no entries.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CustomCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CustomCollectionView<_, u128, RegisterView<_, String>> =
+    ///     CustomCollectionView::load(context).await.unwrap();
+    /// assert!(view.is_empty().await.unwrap());
+    /// view.load_entry_mut(&23).await.unwrap();
+    /// assert!(!view.is_empty().await.unwrap());
+    /// # })
+    /// ```
+    pub async fn is_empty(&self) -> Result<bool, ViewError> {
+        Ok(self.count().await? == 0)
+    }
+
+    /// Loads all the entries of the collection for reading at once, which is meant for
+    /// small collections.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CustomCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CustomCollectionView<_, u128, RegisterView<_, String>> =
+    ///     CustomCollectionView::load(context).await.unwrap();
+    /// {
+    ///     let _subview = view.load_entry_or_insert(&23).await.unwrap();
+    /// }
+    /// let subviews = view.try_load_all_entries().await.unwrap();
+    /// assert_eq!(subviews.len(), 1);
+    /// assert_eq!(subviews[0].0, 23);
+    /// # })
+    /// ```
+    pub async fn try_load_all_entries(&self) -> Result<Vec<(I, ReadGuardedView<W>)>, ViewError> {
+        let results = self.collection.try_load_all_entries().await?;
+        results
+            .into_iter()
+            .map(|(short_key, view)| {
+                let index = I::from_custom_bytes(&short_key)?;
+                Ok((index, view))
+            })
+            .collect()
+    }
 }
 
 impl<C, I, W> CustomCollectionView<C, I, W>
@@ -1421,6 +2144,139 @@ where
             .await?;
         Ok(())
     }
+
+    /// Applies a function f on each index `index` such that `start <= index < end` for
+    /// the order determined by the custom serialization. Indices are visited in that order. If
+    /// the function returns false then the loop ends prematurely.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CustomCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CustomCollectionView<_, u128, RegisterView<_, String>> =
+    ///     CustomCollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&23).await.unwrap();
+    /// view.load_entry_mut(&24).await.unwrap();
+    /// view.load_entry_mut(&25).await.unwrap();
+    /// let mut count = 0;
+    /// view.for_each_index_in_range_while(
+    ///     |_index| {
+    ///         count += 1;
+    ///         Ok(count < 1)
+    ///     },
+    ///     &24..&26,
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(count, 1);
+    /// # })
+    /// ```
+    pub async fn for_each_index_in_range_while<Q, F>(
+        &self,
+        mut f: F,
+        range: Range<&Q>,
+    ) -> Result<(), ViewError>
+    where
+        I: Borrow<Q>,
+        Q: CustomSerialize,
+        F: FnMut(I) -> Result<bool, ViewError> + Send,
+    {
+        let range = range.start.to_custom_bytes()?..range.end.to_custom_bytes()?;
+        self.collection
+            .for_each_key_in_range_while(
+                |key| {
+                    let index = I::from_custom_bytes(key)?;
+                    f(index)
+                },
+                range,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Applies a function f on each index `index` such that `start <= index < end` for
+    /// the order determined by the custom serialization. Indices are visited in that order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CustomCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CustomCollectionView<_, u128, RegisterView<_, String>> =
+    ///     CustomCollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&23).await.unwrap();
+    /// view.load_entry_mut(&24).await.unwrap();
+    /// view.load_entry_mut(&25).await.unwrap();
+    /// let mut count = 0;
+    /// view.for_each_index_in_range(
+    ///     |_index| {
+    ///         count += 1;
+    ///         Ok(())
+    ///     },
+    ///     &24..&26,
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(count, 2);
+    /// # })
+    /// ```
+    pub async fn for_each_index_in_range<Q, F>(
+        &self,
+        mut f: F,
+        range: Range<&Q>,
+    ) -> Result<(), ViewError>
+    where
+        I: Borrow<Q>,
+        Q: CustomSerialize,
+        F: FnMut(I) -> Result<(), ViewError> + Send,
+    {
+        self.for_each_index_in_range_while(
+            |index| {
+                f(index)?;
+                Ok(true)
+            },
+            range,
+        )
+        .await
+    }
+
+    /// Returns the list of indices `index` in the collection such that
+    /// `start <= index < end`. The order is determined by the custom serialization.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::{create_test_memory_context, MemoryContext};
+    /// # use linera_views::collection_view::CustomCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: CustomCollectionView<_, u128, RegisterView<_, String>> =
+    ///     CustomCollectionView::load(context).await.unwrap();
+    /// view.load_entry_mut(&23).await.unwrap();
+    /// view.load_entry_mut(&24).await.unwrap();
+    /// view.load_entry_mut(&25).await.unwrap();
+    /// let indices = view.indices_in_range(&23..&25).await.unwrap();
+    /// assert_eq!(indices, vec![23, 24]);
+    /// # })
+    /// ```
+    pub async fn indices_in_range<Q>(&self, range: Range<&Q>) -> Result<Vec<I>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: CustomSerialize,
+    {
+        let mut indices = Vec::new();
+        self.for_each_index_in_range(
+            |index| {
+                indices.push(index);
+                Ok(())
+            },
+            range,
+        )
+        .await?;
+        Ok(indices)
+    }
 }
 
 #[async_trait]
@@ -1527,29 +2383,43 @@ mod graphql {
             Ok(Entry { value, key })
         }
 
+        /// The entries of the collection, filtered by keys, or paginated with at most
+        /// `first` entries following the key `after`.
         async fn entries(
             &self,
             input: Option<MapInput<K>>,
+            first: Option<usize>,
+            after: Option<K>,
         ) -> Result<Vec<Entry<K, ReadGuardedView<V>>>, async_graphql::Error> {
             let keys = if let Some(keys) = input
                 .and_then(|input| input.filters)
                 .and_then(|filters| filters.keys)
             {
                 keys
+            } else if first.is_some() || after.is_some() {
+                let after = after.map(|after| C::derive_short_key(&after)).transpose()?;
+                let short_keys = self
+                    .collection
+                    .keys_page(after.as_deref(), first.unwrap_or(usize::MAX))
+                    .await?;
+                short_keys
+                    .iter()
+                    .map(|short_key| C::deserialize_value(short_key))
+                    .collect::<Result<Vec<_>, _>>()?
             } else {
                 self.indices().await?
             };
 
-            let mut values = vec![];
-            for key in keys {
-                let value = self
-                    .try_load_entry(&key)
-                    .await?
-                    .ok_or_else(|| missing_key_error(&key))?;
-                values.push(Entry { value, key })
-            }
-
-            Ok(values)
+            // The entries are loaded together, since each of them holds the lock of the
+            // collection.
+            let values = self.try_load_entries(&keys, MAX_CONCURRENT_LOADS).await?;
+            keys.into_iter()
+                .zip(values)
+                .map(|(key, value)| {
+                    let value = value.ok_or_else(|| missing_key_error(&key))?;
+                    Ok(Entry { value, key })
+                })
+                .collect()
         }
     }
 
@@ -1594,29 +2464,51 @@ mod graphql {
             Ok(Entry { value, key })
         }
 
+        /// The entries of the collection, filtered by keys, or paginated with at most
+        /// `first` entries following the key `after`.
         async fn entries(
             &self,
             input: Option<MapInput<K>>,
+            first: Option<usize>,
+            after: Option<K>,
         ) -> Result<Vec<Entry<K, ReadGuardedView<V>>>, async_graphql::Error> {
             let keys = if let Some(keys) = input
                 .and_then(|input| input.filters)
                 .and_then(|filters| filters.keys)
             {
                 keys
+            } else if first.is_some() || after.is_some() {
+                let after = after.map(|after| after.to_custom_bytes()).transpose()?;
+                let short_keys = self
+                    .collection
+                    .keys_page(after.as_deref(), first.unwrap_or(usize::MAX))
+                    .await?;
+                short_keys
+                    .iter()
+                    .map(|short_key| K::from_custom_bytes(short_key))
+                    .collect::<Result<Vec<_>, _>>()?
             } else {
                 self.indices().await?
             };
 
-            let mut values = vec![];
-            for key in keys {
-                let value = self
-                    .try_load_entry(&key)
+            // The entries are loaded together, since each of them holds the lock of the
+            // collection.
+            let values = {
+                let short_keys = keys
+                    .iter()
+                    .map(|key| key.to_custom_bytes())
+                    .collect::<Result<_, _>>()?;
+                self.collection
+                    .try_load_entries(short_keys, MAX_CONCURRENT_LOADS)
                     .await?
-                    .ok_or_else(|| missing_key_error(&key))?;
-                values.push(Entry { value, key })
-            }
-
-            Ok(values)
+            };
+            keys.into_iter()
+                .zip(values)
+                .map(|(key, value)| {
+                    let value = value.ok_or_else(|| missing_key_error(&key))?;
+                    Ok(Entry { value, key })
+                })
+                .collect()
         }
     }
 }
@@ -97,13 +97,13 @@ where
         let delete_view = self.inner.flush(batch)?;
         let hash = self.hash.get_mut().unwrap();
         if delete_view {
-            let mut key_prefix = self.inner.context().base_key();
+            let mut key_prefix = self.inner.context().base_key().to_vec();
             key_prefix.pop();
             batch.delete_key_prefix(key_prefix);
             self.stored_hash = None;
             *hash = None;
         } else if self.stored_hash != *hash {
-            let mut key = self.inner.context().base_key();
+            let mut key = self.inner.context().base_key().to_vec();
             let tag = key.last_mut().unwrap();
             *tag = KeyTag::Hash as u8;
             match hash {
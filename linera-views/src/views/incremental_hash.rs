@@ -0,0 +1,362 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hashing of map-like and log-like views maintained incrementally.
+//!
+//! The contents of such views are sets of entries, i.e. pairs of a serialized key and a
+//! serialized value. Each entry is expanded with SHAKE256 into a vector of 16-bit lanes,
+//! and the vectors of all the entries are summed lane by lane, wrapping around. This
+//! multiset hash is updated by adding the vectors of the new entries and subtracting the
+//! ones of the removed entries, so that [`IncrementalHashedView`] only reads the stored
+//! values replaced by the pending changes instead of the whole view. The sum is stored
+//! alongside the view, and the hash of the view is the SHA3-256 hash of the sum.
+//!
+//! The hashes differ from the ones of [`HashableView`] implementations of the wrapped
+//! views, which is why the incremental hashing is a separate view type.
+
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use linera_base::ensure;
+use sha3::{
+    digest::{ExtendableOutput, Update, XofReader},
+    Shake256,
+};
+
+use crate::{
+    batch::Batch,
+    common::HasherOutput,
+    context::Context,
+    views::{ClonableView, HashableView, Hasher, View, ViewError, MIN_VIEW_TAG},
+};
+
+/// The number of lanes of the sums of entry hashes.
+const NUM_LANES: usize = 1024;
+
+/// The changes to the entries of a view made by its pending changes.
+#[derive(Debug, Default)]
+pub struct EntryChanges {
+    /// The stored entries that are removed or replaced.
+    pub removed: Vec<(Vec<u8>, Vec<u8>)>,
+    /// The entries that are added or that replace stored ones.
+    pub added: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// A view whose contents are a set of entries that can be hashed one by one.
+#[async_trait]
+pub trait EntryHashableView<C>: View<C> {
+    /// Calls `f` on the serialized key and value of every entry of the view, including
+    /// the pending changes.
+    async fn for_each_entry<F>(&self, f: F) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8], &[u8]) -> Result<(), ViewError> + Send;
+
+    /// Returns the changes to the stored entries made by the pending changes, or `None`
+    /// if all the stored entries are going to be deleted.
+    async fn pending_entry_changes(&self) -> Result<Option<EntryChanges>, ViewError>;
+}
+
+/// A sum of entry hashes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct EntrySum(Vec<u16>);
+
+impl EntrySum {
+    fn zero() -> Self {
+        Self(vec![0; NUM_LANES])
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, ViewError> {
+        ensure!(bytes.len() == 2 * NUM_LANES, ViewError::InconsistentEntries);
+        Ok(Self(
+            bytes
+                .chunks_exact(2)
+                .map(|lane| u16::from_le_bytes([lane[0], lane[1]]))
+                .collect(),
+        ))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.iter().flat_map(|lane| lane.to_le_bytes()).collect()
+    }
+
+    /// Returns the lanes of the hash of an entry.
+    fn entry_lanes(key: &[u8], value: &[u8]) -> impl Iterator<Item = u16> {
+        let mut hasher = Shake256::default();
+        hasher.update(&(key.len() as u64).to_le_bytes());
+        hasher.update(key);
+        hasher.update(value);
+        let mut bytes = vec![0; 2 * NUM_LANES];
+        hasher.finalize_xof().read(&mut bytes);
+        (0..NUM_LANES).map(move |i| u16::from_le_bytes([bytes[2 * i], bytes[2 * i + 1]]))
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8]) {
+        for (lane, entry_lane) in self.0.iter_mut().zip(Self::entry_lanes(key, value)) {
+            *lane = lane.wrapping_add(entry_lane);
+        }
+    }
+
+    fn remove(&mut self, key: &[u8], value: &[u8]) {
+        for (lane, entry_lane) in self.0.iter_mut().zip(Self::entry_lanes(key, value)) {
+            *lane = lane.wrapping_sub(entry_lane);
+        }
+    }
+
+    fn finalize(&self) -> HasherOutput {
+        let mut hasher = sha3::Sha3_256::default();
+        Hasher::update_with_bytes(&mut hasher, &self.to_bytes())
+            .expect("writing to a hasher cannot fail");
+        hasher.finalize()
+    }
+}
+
+/// Key tags to create the sub-keys of an `IncrementalHashedView` on top of the base key.
+#[repr(u8)]
+enum KeyTag {
+    /// Prefix for the inner view.
+    Inner = MIN_VIEW_TAG,
+    /// Prefix for the sum of the entry hashes.
+    Sum,
+}
+
+/// A wrapper maintaining the hash of a view incrementally.
+///
+/// The sum of the entry hashes is updated when the hash is computed and stored at the
+/// next flush. If the view is flushed with changes made after the last computation of
+/// the hash, the stored sum is deleted and the next hash is computed from scratch.
+#[derive(Debug)]
+pub struct IncrementalHashedView<C, W> {
+    _phantom: PhantomData<C>,
+    /// The stored sum, if it is up to date with the stored entries.
+    stored_sum: Option<EntrySum>,
+    /// The sum including the pending changes, if it was computed since the last change.
+    sum: Mutex<Option<EntrySum>>,
+    inner: W,
+}
+
+#[async_trait]
+impl<C, W> View<C> for IncrementalHashedView<C, W>
+where
+    C: Context + Send + Sync,
+    ViewError: From<C::Error>,
+    W: EntryHashableView<C> + Send + Sync,
+{
+    const NUM_INIT_KEYS: usize = 1 + W::NUM_INIT_KEYS;
+
+    fn context(&self) -> &C {
+        self.inner.context()
+    }
+
+    fn pre_load(context: &C) -> Result<Vec<Vec<u8>>, ViewError> {
+        let mut v = vec![context.base_tag(KeyTag::Sum as u8)];
+        let base_key = context.base_tag(KeyTag::Inner as u8);
+        let context = context.clone_with_base_key(base_key);
+        v.extend(W::pre_load(&context)?);
+        Ok(v)
+    }
+
+    fn post_load(context: C, values: &[Option<Vec<u8>>]) -> Result<Self, ViewError> {
+        let stored_sum = values
+            .first()
+            .ok_or(ViewError::PostLoadValuesError)?
+            .as_deref()
+            .map(EntrySum::from_bytes)
+            .transpose()?;
+        let base_key = context.base_tag(KeyTag::Inner as u8);
+        let context = context.clone_with_base_key(base_key);
+        let inner = W::post_load(
+            context,
+            values.get(1..).ok_or(ViewError::PostLoadValuesError)?,
+        )?;
+        Ok(Self {
+            _phantom: PhantomData,
+            sum: Mutex::new(stored_sum.clone()),
+            stored_sum,
+            inner,
+        })
+    }
+
+    async fn load(context: C) -> Result<Self, ViewError> {
+        let keys = Self::pre_load(&context)?;
+        let values = context.read_multi_values_bytes(keys).await?;
+        Self::post_load(context, &values)
+    }
+
+    fn rollback(&mut self) {
+        self.inner.rollback();
+        *self.sum.get_mut().unwrap() = self.stored_sum.clone();
+    }
+
+    async fn has_pending_changes(&self) -> bool {
+        if self.inner.has_pending_changes().await {
+            return true;
+        }
+        let sum = self.sum.lock().unwrap();
+        self.stored_sum != *sum
+    }
+
+    fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
+        let delete_view = self.inner.flush(batch)?;
+        let sum = self.sum.get_mut().unwrap();
+        if delete_view {
+            let mut key_prefix = self.inner.context().base_key().to_vec();
+            key_prefix.pop();
+            batch.delete_key_prefix(key_prefix);
+            self.stored_sum = None;
+            *sum = None;
+        } else if self.stored_sum != *sum {
+            let mut key = self.inner.context().base_key().to_vec();
+            let tag = key.last_mut().unwrap();
+            *tag = KeyTag::Sum as u8;
+            match sum {
+                None => batch.delete_key(key),
+                Some(sum) => batch.put_key_value_bytes(key, sum.to_bytes()),
+            }
+            self.stored_sum = sum.clone();
+        }
+        Ok(delete_view)
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear();
+        *self.sum.get_mut().unwrap() = None;
+    }
+}
+
+impl<C, W> ClonableView<C> for IncrementalHashedView<C, W>
+where
+    C: Context + Send + Sync,
+    ViewError: From<C::Error>,
+    W: EntryHashableView<C> + ClonableView<C> + Send + Sync,
+{
+    fn clone_unchecked(&mut self) -> Result<Self, ViewError> {
+        Ok(IncrementalHashedView {
+            _phantom: PhantomData,
+            stored_sum: self.stored_sum.clone(),
+            sum: Mutex::new(self.sum.get_mut().unwrap().clone()),
+            inner: self.inner.clone_unchecked()?,
+        })
+    }
+}
+
+impl<C, W> IncrementalHashedView<C, W>
+where
+    C: Context + Send + Sync,
+    ViewError: From<C::Error>,
+    W: EntryHashableView<C> + Send + Sync,
+{
+    /// Computes the sum of the hashes of all the entries, ignoring the stored sum.
+    async fn compute_sum(&self) -> Result<EntrySum, ViewError> {
+        let mut sum = EntrySum::zero();
+        self.inner
+            .for_each_entry(|key, value| {
+                sum.insert(key, value);
+                Ok(())
+            })
+            .await?;
+        Ok(sum)
+    }
+
+    /// Computes the sum of the hashes of the entries from the stored sum and the
+    /// pending changes.
+    async fn update_sum(&self) -> Result<EntrySum, ViewError> {
+        let Some(mut sum) = self.stored_sum.clone() else {
+            return self.compute_sum().await;
+        };
+        let Some(changes) = self.inner.pending_entry_changes().await? else {
+            return self.compute_sum().await;
+        };
+        for (key, value) in &changes.removed {
+            sum.remove(key, value);
+        }
+        for (key, value) in &changes.added {
+            sum.insert(key, value);
+        }
+        Ok(sum)
+    }
+
+    /// Recomputes the hash from all the entries of the view, without using the stored
+    /// sum, e.g. to verify it.
+    pub async fn recompute_hash(&self) -> Result<HasherOutput, ViewError> {
+        Ok(self.compute_sum().await?.finalize())
+    }
+}
+
+#[async_trait]
+impl<C, W> HashableView<C> for IncrementalHashedView<C, W>
+where
+    C: Context + Send + Sync,
+    ViewError: From<C::Error>,
+    W: EntryHashableView<C> + Send + Sync,
+{
+    type Hasher = sha3::Sha3_256;
+
+    async fn hash_mut(&mut self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        self.hash().await
+    }
+
+    async fn hash(&self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        let sum = self.sum.lock().unwrap().clone();
+        let sum = match sum {
+            Some(sum) => sum,
+            None => {
+                let new_sum = self.update_sum().await?;
+                *self.sum.lock().unwrap() = Some(new_sum.clone());
+                new_sum
+            }
+        };
+        Ok(sum.finalize())
+    }
+}
+
+impl<C, W> Deref for IncrementalHashedView<C, W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        &self.inner
+    }
+}
+
+impl<C, W> DerefMut for IncrementalHashedView<C, W> {
+    fn deref_mut(&mut self) -> &mut W {
+        *self.sum.get_mut().unwrap() = None;
+        &mut self.inner
+    }
+}
+
+mod graphql {
+    use std::borrow::Cow;
+
+    use super::IncrementalHashedView;
+    use crate::context::Context;
+
+    impl<C, W> async_graphql::OutputType for IncrementalHashedView<C, W>
+    where
+        C: Context + Send + Sync,
+        W: async_graphql::OutputType + Send + Sync,
+    {
+        fn type_name() -> Cow<'static, str> {
+            W::type_name()
+        }
+
+        fn qualified_type_name() -> String {
+            W::qualified_type_name()
+        }
+
+        fn create_type_info(registry: &mut async_graphql::registry::Registry) -> String {
+            W::create_type_info(registry)
+        }
+
+        async fn resolve(
+            &self,
+            ctx: &async_graphql::ContextSelectionSet<'_>,
+            field: &async_graphql::Positioned<async_graphql::parser::types::Field>,
+        ) -> async_graphql::ServerResult<async_graphql::Value> {
+            (**self).resolve(ctx, field).await
+        }
+    }
+}
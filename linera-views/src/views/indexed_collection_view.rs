@@ -0,0 +1,487 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(with_metrics)]
+use std::sync::LazyLock;
+use std::{io::Write, ops::Range};
+
+use async_trait::async_trait;
+#[cfg(with_metrics)]
+use {
+    linera_base::prometheus_util::{bucket_latencies, register_histogram_vec, MeasureLatency},
+    prometheus::HistogramVec,
+};
+
+use crate::{
+    batch::Batch,
+    collection_view::{ByteCollectionView, ReadGuardedView},
+    common::{from_bytes_option_or_default, HasherOutput},
+    context::Context,
+    hashable_wrapper::WrappedHashableContainerView,
+    views::{ClonableView, HashableView, Hasher, View, ViewError, MIN_VIEW_TAG},
+};
+
+#[cfg(with_metrics)]
+/// The runtime of hash computation
+static INDEXED_COLLECTION_VIEW_HASH_RUNTIME: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec(
+        "indexed_collection_view_hash_runtime",
+        "IndexedCollectionView hash runtime",
+        &[],
+        bucket_latencies(5.0),
+    )
+});
+
+/// Key tags to create the sub-keys of an `IndexedCollectionView` on top of the base key.
+#[repr(u8)]
+enum KeyTag {
+    /// Prefix for the storing of the range of the indices.
+    Store = MIN_VIEW_TAG,
+    /// Prefix for the collection of the entries.
+    Entries,
+}
+
+/// Returns the key of the entry of `index` in the collection, whose lexicographic order
+/// is the order of the indices.
+fn entry_key(index: u64) -> Vec<u8> {
+    index.to_be_bytes().to_vec()
+}
+
+/// A view that supports an ordered collection of views of the same kind, indexed by
+/// consecutive `u64` values that the view allocates itself. Entries are appended at the
+/// back and pruned at the front, so that the index of an entry never changes.
+#[derive(Debug)]
+pub struct IndexedCollectionView<C, W> {
+    context: C,
+    /// The range of the indices of the stored entries.
+    stored_indices: Range<u64>,
+    /// The range of the indices of the entries, including the pending changes.
+    indices: Range<u64>,
+    /// Whether the storage is deleted or not.
+    delete_storage_first: bool,
+    entries: ByteCollectionView<C, W>,
+}
+
+#[async_trait]
+impl<C, W> View<C> for IndexedCollectionView<C, W>
+where
+    C: Context + Send + Sync,
+    ViewError: From<C::Error>,
+    W: View<C> + Send + Sync,
+{
+    const NUM_INIT_KEYS: usize = 1 + ByteCollectionView::<C, W>::NUM_INIT_KEYS;
+
+    fn context(&self) -> &C {
+        &self.context
+    }
+
+    fn pre_load(context: &C) -> Result<Vec<Vec<u8>>, ViewError> {
+        let mut v = vec![context.base_tag(KeyTag::Store as u8)];
+        let base_key = context.base_tag(KeyTag::Entries as u8);
+        let entries_context = context.clone_with_base_key(base_key);
+        v.extend(ByteCollectionView::<C, W>::pre_load(&entries_context)?);
+        Ok(v)
+    }
+
+    fn post_load(context: C, values: &[Option<Vec<u8>>]) -> Result<Self, ViewError> {
+        let stored_indices: Range<u64> =
+            from_bytes_option_or_default(values.first().ok_or(ViewError::PostLoadValuesError)?)?;
+        let base_key = context.base_tag(KeyTag::Entries as u8);
+        let entries_context = context.clone_with_base_key(base_key);
+        let entries = ByteCollectionView::post_load(
+            entries_context,
+            values.get(1..).ok_or(ViewError::PostLoadValuesError)?,
+        )?;
+        Ok(Self {
+            context,
+            indices: stored_indices.clone(),
+            stored_indices,
+            delete_storage_first: false,
+            entries,
+        })
+    }
+
+    async fn load(context: C) -> Result<Self, ViewError> {
+        let keys = Self::pre_load(&context)?;
+        let values = context.read_multi_values_bytes(keys).await?;
+        Self::post_load(context, &values)
+    }
+
+    fn rollback(&mut self) {
+        self.indices = self.stored_indices.clone();
+        self.delete_storage_first = false;
+        self.entries.rollback();
+    }
+
+    async fn has_pending_changes(&self) -> bool {
+        self.delete_storage_first
+            || self.indices != self.stored_indices
+            || self.entries.has_pending_changes().await
+    }
+
+    fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
+        if self.delete_storage_first {
+            batch.delete_key_prefix(self.context.base_key().to_vec());
+            self.stored_indices = Range::default();
+        } else {
+            // The entries pruned since the last flush are stored in a contiguous range of
+            // keys, and their pending updates were discarded.
+            let pruned_end = self.indices.start.min(self.stored_indices.end);
+            if self.stored_indices.start < pruned_end {
+                let range = entry_key(self.stored_indices.start)..entry_key(pruned_end);
+                self.entries.delete_stored_range(batch, &range);
+            }
+        }
+        self.entries.flush(batch)?;
+        let delete_view = self.delete_storage_first && self.indices == Range::default();
+        if self.indices != self.stored_indices && !delete_view {
+            let key = self.context.base_tag(KeyTag::Store as u8);
+            batch.put_key_value(key, &self.indices)?;
+        }
+        self.stored_indices = self.indices.clone();
+        self.delete_storage_first = false;
+        Ok(delete_view)
+    }
+
+    fn clear(&mut self) {
+        self.delete_storage_first = true;
+        self.indices = Range::default();
+        self.entries.clear();
+    }
+}
+
+impl<C, W> ClonableView<C> for IndexedCollectionView<C, W>
+where
+    C: Context + Send + Sync,
+    ViewError: From<C::Error>,
+    W: ClonableView<C> + Send + Sync,
+{
+    fn clone_unchecked(&mut self) -> Result<Self, ViewError> {
+        Ok(IndexedCollectionView {
+            context: self.context.clone(),
+            stored_indices: self.stored_indices.clone(),
+            indices: self.indices.clone(),
+            delete_storage_first: self.delete_storage_first,
+            entries: self.entries.clone_unchecked()?,
+        })
+    }
+}
+
+impl<C, W> IndexedCollectionView<C, W> {
+    /// Returns the range of the indices of the entries, including the pending changes.
+    /// The end of the range is the index of the next pushed entry.
+    pub fn index_range(&self) -> Range<u64> {
+        self.indices.clone()
+    }
+
+    /// Returns the number of entries.
+    pub fn count(&self) -> usize {
+        (self.indices.end - self.indices.start) as usize
+    }
+}
+
+impl<C, W> IndexedCollectionView<C, W>
+where
+    C: Context + Send,
+    ViewError: From<C::Error>,
+    W: View<C>,
+{
+    /// Removes the entries whose index is lower than `index`, or all the entries if
+    /// `index` is past the back. The stored entries are deleted at the next flush with
+    /// ranges of keys, without being read.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::indexed_collection_view::IndexedCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: IndexedCollectionView<_, RegisterView<_, String>> =
+    ///     IndexedCollectionView::load(context).await.unwrap();
+    /// view.push_entry().await.unwrap();
+    /// view.push_entry().await.unwrap();
+    /// view.prune_below(1);
+    /// assert_eq!(view.index_range(), 1..2);
+    /// # })
+    /// ```
+    pub fn prune_below(&mut self, index: u64) {
+        let pruned_end = index.min(self.indices.end);
+        if pruned_end <= self.indices.start {
+            return;
+        }
+        let range = entry_key(self.indices.start)..entry_key(pruned_end);
+        self.entries.discard_updates_in_range(&range);
+        self.indices.start = pruned_end;
+    }
+
+    /// Appends a default entry at the back and returns it, without reading the storage.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::indexed_collection_view::IndexedCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: IndexedCollectionView<_, RegisterView<_, String>> =
+    ///     IndexedCollectionView::load(context).await.unwrap();
+    /// let subview = view.push_entry().await.unwrap();
+    /// subview.set(String::from("Hello"));
+    /// assert_eq!(view.index_range(), 0..1);
+    /// # })
+    /// ```
+    pub async fn push_entry(&mut self) -> Result<&mut W, ViewError> {
+        let short_key = entry_key(self.indices.end);
+        self.entries.reset_entry_to_default(&short_key)?;
+        self.indices.end += 1;
+        self.entries.load_entry_mut(&short_key).await
+    }
+
+    /// Loads the entry of the given index for modification, if it was not pruned.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::indexed_collection_view::IndexedCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: IndexedCollectionView<_, RegisterView<_, String>> =
+    ///     IndexedCollectionView::load(context).await.unwrap();
+    /// view.push_entry().await.unwrap();
+    /// let subview = view.try_load_entry_mut(0).await.unwrap().unwrap();
+    /// subview.set(String::from("Hello"));
+    /// assert!(view.try_load_entry_mut(1).await.unwrap().is_none());
+    /// # })
+    /// ```
+    pub async fn try_load_entry_mut(&mut self, index: u64) -> Result<Option<&mut W>, ViewError> {
+        if !self.indices.contains(&index) {
+            return Ok(None);
+        }
+        Ok(Some(self.entries.load_entry_mut(&entry_key(index)).await?))
+    }
+
+    /// Loads the entry of the given index for reading, if it was not pruned.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::indexed_collection_view::IndexedCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: IndexedCollectionView<_, RegisterView<_, String>> =
+    ///     IndexedCollectionView::load(context).await.unwrap();
+    /// view.push_entry().await.unwrap().set(String::from("Hello"));
+    /// let subview = view.try_load_entry(0).await.unwrap().unwrap();
+    /// assert_eq!(*subview.get(), "Hello");
+    /// # })
+    /// ```
+    pub async fn try_load_entry(
+        &self,
+        index: u64,
+    ) -> Result<Option<ReadGuardedView<W>>, ViewError> {
+        if !self.indices.contains(&index) {
+            return Ok(None);
+        }
+        self.entries.try_load_entry(&entry_key(index)).await
+    }
+
+    /// Loads the front entry, if any.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::indexed_collection_view::IndexedCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: IndexedCollectionView<_, RegisterView<_, u32>> =
+    ///     IndexedCollectionView::load(context).await.unwrap();
+    /// view.push_entry().await.unwrap().set(34);
+    /// view.push_entry().await.unwrap().set(42);
+    /// assert_eq!(*view.front().await.unwrap().unwrap().get(), 34);
+    /// # })
+    /// ```
+    pub async fn front(&self) -> Result<Option<ReadGuardedView<W>>, ViewError> {
+        self.try_load_entry(self.indices.start).await
+    }
+
+    /// Loads the back entry, if any.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::indexed_collection_view::IndexedCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: IndexedCollectionView<_, RegisterView<_, u32>> =
+    ///     IndexedCollectionView::load(context).await.unwrap();
+    /// view.push_entry().await.unwrap().set(34);
+    /// view.push_entry().await.unwrap().set(42);
+    /// assert_eq!(*view.back().await.unwrap().unwrap().get(), 42);
+    /// # })
+    /// ```
+    pub async fn back(&self) -> Result<Option<ReadGuardedView<W>>, ViewError> {
+        match self.indices.end.checked_sub(1) {
+            Some(index) => self.try_load_entry(index).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Loads the entries whose indices are in `range`, in the order of the indices, with
+    /// at most `max_concurrency` reads at the same time. Pruned entries are skipped.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::indexed_collection_view::IndexedCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: IndexedCollectionView<_, RegisterView<_, u32>> =
+    ///     IndexedCollectionView::load(context).await.unwrap();
+    /// for value in 0..4 {
+    ///     view.push_entry().await.unwrap().set(value);
+    /// }
+    /// view.prune_below(1);
+    /// let entries = view.try_load_entries(0..3, 2).await.unwrap();
+    /// let values = entries
+    ///     .iter()
+    ///     .map(|(index, entry)| (*index, *entry.get()))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(values, vec![(1, 1), (2, 2)]);
+    /// # })
+    /// ```
+    pub async fn try_load_entries(
+        &self,
+        range: Range<u64>,
+        max_concurrency: usize,
+    ) -> Result<Vec<(u64, ReadGuardedView<W>)>, ViewError> {
+        let start = range.start.max(self.indices.start);
+        let end = range.end.min(self.indices.end);
+        let short_keys = (start..end).map(entry_key).collect();
+        let entries = self
+            .entries
+            .try_load_entries(short_keys, max_concurrency)
+            .await?;
+        Ok((start..end)
+            .zip(entries)
+            .filter_map(|(index, entry)| Some((index, entry?)))
+            .collect())
+    }
+}
+
+impl<C, W> IndexedCollectionView<C, W>
+where
+    C: Context + Send,
+    ViewError: From<C::Error>,
+    W: View<C> + Sync,
+{
+    /// Applies a function f on the index of each entry, in increasing order, by scanning
+    /// the keys of the range of the indices. If the function returns false, then the
+    /// loop ends prematurely.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::indexed_collection_view::IndexedCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: IndexedCollectionView<_, RegisterView<_, u32>> =
+    ///     IndexedCollectionView::load(context).await.unwrap();
+    /// for _ in 0..3 {
+    ///     view.push_entry().await.unwrap();
+    /// }
+    /// let mut indices = Vec::new();
+    /// view.for_each_index_while(|index| {
+    ///     indices.push(index);
+    ///     Ok(indices.len() < 2)
+    /// })
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(indices, vec![0, 1]);
+    /// # })
+    /// ```
+    pub async fn for_each_index_while<F>(&self, mut f: F) -> Result<(), ViewError>
+    where
+        F: FnMut(u64) -> Result<bool, ViewError> + Send,
+    {
+        let range = entry_key(self.indices.start)..entry_key(self.indices.end);
+        self.entries
+            .for_each_key_in_range_while(
+                |key| {
+                    let bytes = key.try_into().map_err(|_| ViewError::InconsistentEntries)?;
+                    f(u64::from_be_bytes(bytes))
+                },
+                range,
+            )
+            .await
+    }
+
+    /// Applies a function f on the index of each entry, in increasing order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::indexed_collection_view::IndexedCollectionView;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut view: IndexedCollectionView<_, RegisterView<_, u32>> =
+    ///     IndexedCollectionView::load(context).await.unwrap();
+    /// for _ in 0..3 {
+    ///     view.push_entry().await.unwrap();
+    /// }
+    /// view.prune_below(1);
+    /// let mut indices = Vec::new();
+    /// view.for_each_index(|index| {
+    ///     indices.push(index);
+    ///     Ok(())
+    /// })
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(indices, vec![1, 2]);
+    /// # })
+    /// ```
+    pub async fn for_each_index<F>(&self, mut f: F) -> Result<(), ViewError>
+    where
+        F: FnMut(u64) -> Result<(), ViewError> + Send,
+    {
+        self.for_each_index_while(|index| {
+            f(index)?;
+            Ok(true)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl<C, W> HashableView<C> for IndexedCollectionView<C, W>
+where
+    C: Context + Send + Sync,
+    ViewError: From<C::Error>,
+    W: HashableView<C> + Send + Sync + 'static,
+{
+    type Hasher = sha3::Sha3_256;
+
+    async fn hash_mut(&mut self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        self.hash().await
+    }
+
+    async fn hash(&self) -> Result<<Self::Hasher as Hasher>::Output, ViewError> {
+        #[cfg(with_metrics)]
+        let _hash_latency = INDEXED_COLLECTION_VIEW_HASH_RUNTIME.measure_latency();
+        let mut hasher = sha3::Sha3_256::default();
+        hasher.update_with_bcs_bytes(&self.indices)?;
+        // The pruned entries may still be stored, so that only the indices of the range
+        // are hashed.
+        for index in self.indices.clone() {
+            let entry = self
+                .try_load_entry(index)
+                .await?
+                .ok_or(ViewError::MissingEntries)?;
+            let hash = entry.hash().await?;
+            hasher.write_all(hash.as_ref())?;
+        }
+        Ok(hasher.finalize())
+    }
+}
+
+/// Type wrapping `IndexedCollectionView` while memoizing the hash.
+pub type HashedIndexedCollectionView<C, W> =
+    WrappedHashableContainerView<C, IndexedCollectionView<C, W>, HasherOutput>;
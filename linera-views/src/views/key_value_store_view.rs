@@ -301,7 +301,7 @@ where
         if self.deletion_set.delete_storage_first {
             delete_view = true;
             self.stored_total_size = SizeData::default();
-            batch.delete_key_prefix(self.context.base_key());
+            batch.delete_key_prefix(self.context.base_key().to_vec());
             for (index, update) in mem::take(&mut self.updates) {
                 if let Update::Set(value) = update {
                     let key = self.context.base_tag_index(KeyTag::Index as u8, &index);
@@ -853,7 +853,11 @@ where
         let _latency = KEY_VALUE_STORE_VIEW_WRITE_BATCH_LATENCY.measure_latency();
         *self.hash.get_mut().unwrap() = None;
         let max_key_size = self.max_key_size();
-        for operation in batch.operations {
+        // The operations are popped from the end, so that a `DeleteRange` can be replaced
+        // by the deletions of the keys that it matches at this point of the batch.
+        let mut operations = batch.operations;
+        operations.reverse();
+        while let Some(operation) = operations.pop() {
             match operation {
                 WriteOperation::Delete { key } => {
                     ensure!(key.len() <= max_key_size, ViewError::KeyTooLong);
@@ -911,6 +915,31 @@ where
                     self.sizes.remove_by_prefix(key_prefix.clone());
                     self.deletion_set.insert_key_prefix(key_prefix);
                 }
+                WriteOperation::DeleteRange { start, end } => {
+                    ensure!(start.len() <= max_key_size, ViewError::KeyTooLong);
+                    ensure!(end.len() <= max_key_size, ViewError::KeyTooLong);
+                    if start >= end {
+                        continue;
+                    }
+                    let prefix_len = start
+                        .iter()
+                        .zip(&end)
+                        .take_while(|(byte1, byte2)| byte1 == byte2)
+                        .count();
+                    let key_prefix = &start[..prefix_len];
+                    for suffix in self
+                        .find_keys_by_prefix(key_prefix)
+                        .await?
+                        .into_iter()
+                        .rev()
+                    {
+                        let mut key = key_prefix.to_vec();
+                        key.extend(suffix);
+                        if start <= key && key < end {
+                            operations.push(WriteOperation::Delete { key });
+                        }
+                    }
+                }
             }
         }
         Ok(())
@@ -1270,10 +1299,7 @@ where
         view.write_batch(batch).await?;
         let mut batch = Batch::new();
         view.flush(&mut batch)?;
-        view.context()
-            .write_batch(batch)
-            .await
-            .map_err(ViewError::from)?;
+        view.context().write_batch(batch).await?;
         Ok(())
     }
 
@@ -6,6 +6,7 @@ use std::ops::{Bound, Range, RangeBounds};
 use std::sync::LazyLock;
 
 use async_trait::async_trait;
+use futures::{Stream, StreamExt as _, TryStreamExt as _};
 use serde::{de::DeserializeOwned, Serialize};
 #[cfg(with_metrics)]
 use {
@@ -18,6 +19,7 @@ use crate::{
     common::{from_bytes_option_or_default, HasherOutput},
     context::Context,
     hashable_wrapper::WrappedHashableContainerView,
+    incremental_hash::{EntryChanges, EntryHashableView, IncrementalHashedView},
     views::{ClonableView, HashableView, Hasher, View, ViewError, MIN_VIEW_TAG},
 };
 
@@ -99,7 +101,7 @@ where
     fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
         let mut delete_view = false;
         if self.delete_storage_first {
-            batch.delete_key_prefix(self.context.base_key());
+            batch.delete_key_prefix(self.context.base_key().to_vec());
             self.stored_count = 0;
             delete_view = true;
         }
@@ -161,6 +163,25 @@ where
         self.new_values.push(value);
     }
 
+    /// Pushes several values to the end of the log, e.g. to initialize a large log.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::log_view::LogView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut log = LogView::load(context).await.unwrap();
+    /// log.extend([34, 42]);
+    /// assert_eq!(log.count(), 2);
+    /// # })
+    /// ```
+    pub fn extend<I>(&mut self, values: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.new_values.extend(values);
+    }
+
     /// Reads the size of the log.
     /// ```rust
     /// # tokio_test::block_on(async {
@@ -336,6 +357,79 @@ where
             )
         }
     }
+
+    /// Reads the `count` last values of the log (including staged ones), the latest
+    /// one first.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::log_view::LogView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut log = LogView::load(context).await.unwrap();
+    /// log.push(34);
+    /// log.push(42);
+    /// log.push(56);
+    /// assert_eq!(log.read_last(2).await.unwrap(), vec![56, 42]);
+    /// # })
+    /// ```
+    pub async fn read_last(&self, count: usize) -> Result<Vec<T>, ViewError> {
+        let start = self.count().saturating_sub(count);
+        let mut values = self.read(start..).await?;
+        values.reverse();
+        Ok(values)
+    }
+
+    /// Reads at most `limit` values starting from the index `offset` (including staged
+    /// ones). Staging new values does not change the pages before the end of the log.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::log_view::LogView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut log = LogView::load(context).await.unwrap();
+    /// log.push(34);
+    /// log.push(42);
+    /// log.push(56);
+    /// assert_eq!(log.read_paginated(1, 5).await.unwrap(), vec![42, 56]);
+    /// # })
+    /// ```
+    pub async fn read_paginated(&self, offset: usize, limit: usize) -> Result<Vec<T>, ViewError> {
+        self.read(offset..offset.saturating_add(limit)).await
+    }
+
+    /// Returns a stream over the values of the log (including staged ones). The stored
+    /// values are read lazily, `chunk_size` of them at a time.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use futures::TryStreamExt as _;
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::log_view::LogView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut log = LogView::load(context).await.unwrap();
+    /// log.push(34);
+    /// log.push(42);
+    /// log.push(56);
+    /// let values = log.values_stream(2).try_collect::<Vec<_>>().await.unwrap();
+    /// assert_eq!(values, vec![34, 42, 56]);
+    /// # })
+    /// ```
+    pub fn values_stream(
+        &self,
+        chunk_size: usize,
+    ) -> impl Stream<Item = Result<T, ViewError>> + Send + '_
+    where
+        T: Sync,
+    {
+        let count = self.count();
+        let chunk_size = chunk_size.max(1);
+        futures::stream::iter((0..count).step_by(chunk_size))
+            .then(move |start| self.read(start..(start + chunk_size).min(count)))
+            .map_ok(|values| futures::stream::iter(values.into_iter().map(Ok)))
+            .try_flatten()
+    }
 }
 
 #[async_trait]
@@ -361,9 +455,43 @@ where
     }
 }
 
+#[async_trait]
+impl<C, T> EntryHashableView<C> for LogView<C, T>
+where
+    C: Context + Send + Sync,
+    ViewError: From<C::Error>,
+    T: Send + Sync + Clone + Serialize + DeserializeOwned,
+{
+    async fn for_each_entry<F>(&self, mut f: F) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8], &[u8]) -> Result<(), ViewError> + Send,
+    {
+        for (index, value) in self.read(..).await?.iter().enumerate() {
+            f(&bcs::to_bytes(&index)?, &bcs::to_bytes(value)?)?;
+        }
+        Ok(())
+    }
+
+    async fn pending_entry_changes(&self) -> Result<Option<EntryChanges>, ViewError> {
+        if self.delete_storage_first {
+            return Ok(None);
+        }
+        // The log is append-only, so that no stored entry is removed.
+        let mut changes = EntryChanges::default();
+        for (offset, value) in self.new_values.iter().enumerate() {
+            let index = bcs::to_bytes(&(self.stored_count + offset))?;
+            changes.added.push((index, bcs::to_bytes(value)?));
+        }
+        Ok(Some(changes))
+    }
+}
+
 /// Type wrapping `LogView` while memoizing the hash.
 pub type HashedLogView<C, T> = WrappedHashableContainerView<C, LogView<C, T>, HasherOutput>;
 
+/// Type wrapping `LogView` while maintaining its hash incrementally.
+pub type IncrementalHashedLogView<C, T> = IncrementalHashedView<C, LogView<C, T>>;
+
 mod graphql {
     use std::borrow::Cow;
 
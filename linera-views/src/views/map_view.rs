@@ -41,9 +41,11 @@ use std::{
     collections::{btree_map::Entry, BTreeMap},
     marker::PhantomData,
     mem,
+    ops::Range,
 };
 
 use async_trait::async_trait;
+use futures::{future, stream, TryStreamExt as _};
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
@@ -54,11 +56,24 @@ use crate::{
     },
     context::Context,
     hashable_wrapper::WrappedHashableContainerView,
-    store::{KeyIterable, KeyValueIterable},
+    incremental_hash::{EntryChanges, EntryHashableView, IncrementalHashedView},
+    store::{KeyIterable, KeyValueIterable, ResultStream},
     views::{ClonableView, HashableView, Hasher, View, ViewError},
 };
 
 /// A view that supports inserting and removing values indexed by `Vec<u8>`.
+///
+/// The keys are appended verbatim to the base key of the view, without serialization,
+/// so that applications with canonical byte keys such as hashes avoid the length prefix
+/// of BCS and iterate over the entries in the raw byte order. The map owns all the keys
+/// below its base key: as a field of a derived view, the tag of the field keeps its
+/// entries apart from the ones of its siblings. As a root view, however, the map shares
+/// the base key of the context with the keys that the context reserves below
+/// [`MIN_VIEW_TAG`], e.g. for prefix compression, and the batches writing them fail
+/// with [`ViewError::ReservedKey`]. Such maps should use keys starting with a byte of
+/// at least [`MIN_VIEW_TAG`].
+///
+/// [`MIN_VIEW_TAG`]: crate::views::MIN_VIEW_TAG
 #[derive(Debug)]
 pub struct ByteMapView<C, V> {
     context: C,
@@ -142,7 +157,7 @@ where
         let mut delete_view = false;
         if self.deletion_set.delete_storage_first {
             delete_view = true;
-            batch.delete_key_prefix(self.context.base_key());
+            batch.delete_key_prefix(self.context.base_key().to_vec());
             for (index, update) in mem::take(&mut self.updates) {
                 if let Update::Set(value) = update {
                     let key = self.context.base_index(&index);
@@ -209,6 +224,30 @@ where
         self.updates.insert(short_key, Update::Set(value));
     }
 
+    /// Inserts or resets the values of several keys of the map, e.g. to initialize a
+    /// large map.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::ByteMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map = ByteMapView::load(context).await.unwrap();
+    /// map.extend((0..3).map(|i| (vec![i], i)));
+    /// assert_eq!(map.keys().await.unwrap(), vec![vec![0], vec![1], vec![2]]);
+    /// # })
+    /// ```
+    pub fn extend<T>(&mut self, entries: T)
+    where
+        T: IntoIterator<Item = (Vec<u8>, V)>,
+    {
+        self.updates.extend(
+            entries
+                .into_iter()
+                .map(|(short_key, value)| (short_key, Update::Set(value))),
+        );
+    }
+
     /// Removes a value. If absent then nothing is done.
     /// ```rust
     /// # tokio_test::block_on(async {
@@ -516,6 +555,240 @@ where
         .await
     }
 
+    /// Applies the function f on each key `key` such that `start <= key < end`. Keys
+    /// are visited in the lexicographic order. The complete key is sent to the
+    /// function and if it returns false, then the loop exits.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::ByteMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map = ByteMapView::load(context).await.unwrap();
+    /// map.insert(vec![0, 1], String::from("Hello"));
+    /// map.insert(vec![1, 2], String::from("Bonjour"));
+    /// map.insert(vec![1, 3], String::from("Hallo"));
+    /// let mut keys = Vec::new();
+    /// map.for_each_key_in_range_while(
+    ///     |key| {
+    ///         keys.push(key.to_vec());
+    ///         Ok(keys.len() < 2)
+    ///     },
+    ///     vec![0, 2]..vec![2],
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(keys, vec![vec![1, 2], vec![1, 3]]);
+    /// # })
+    /// ```
+    pub async fn for_each_key_in_range_while<F>(
+        &self,
+        mut f: F,
+        range: Range<Vec<u8>>,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8]) -> Result<bool, ViewError> + Send,
+    {
+        let Range { start, end } = range;
+        if start >= end {
+            return Ok(());
+        }
+        self.for_each_key_from_while(f, start, Some(end)).await
+    }
+
+    /// Applies the function f on each key `key` such that `start <= key`, and `key < end`
+    /// if `end` is given, as long as f returns true.
+    async fn for_each_key_from_while<F>(
+        &self,
+        mut f: F,
+        start: Vec<u8>,
+        end: Option<Vec<u8>>,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8]) -> Result<bool, ViewError> + Send,
+    {
+        let mut updates = match &end {
+            Some(end) => self.updates.range(start.clone()..end.clone()),
+            None => self.updates.range(start.clone()..),
+        };
+        let mut update = updates.next();
+        if !self.deletion_set.delete_storage_first {
+            let iter = self.deletion_set.deleted_prefixes.iter();
+            let mut suffix_closed_set = SuffixClosedSetIterator::new(0, iter);
+            let base_key = self.context.base_key();
+            let mut indices: ResultStream<'_, Vec<u8>, C::Error> = match end {
+                Some(end) => {
+                    let start = self.context.base_index(&start);
+                    let end = self.context.base_index(&end);
+                    let full_keys = self.context.find_keys_in_range(&start, &end).await?;
+                    let indices = full_keys
+                        .into_iter()
+                        .map(|full_key| Ok(full_key[base_key.len()..].to_vec()))
+                        .collect::<Vec<_>>();
+                    Box::pin(stream::iter(indices))
+                }
+                None => {
+                    // The keys of the map are streamed, so that they are only read as far
+                    // as `f` goes.
+                    let indices = self.context.find_keys_by_prefix_stream(base_key).await?;
+                    Box::pin(indices.try_filter(move |index| future::ready(*index >= start)))
+                }
+            };
+            while let Some(index) = indices.try_next().await? {
+                let index = index.as_slice();
+                loop {
+                    match update {
+                        Some((key, value)) if key.as_slice() <= index => {
+                            if let Update::Set(_) = value {
+                                if !f(key)? {
+                                    return Ok(());
+                                }
+                            }
+                            update = updates.next();
+                            if key == index {
+                                break;
+                            }
+                        }
+                        _ => {
+                            if !suffix_closed_set.find_key(index) && !f(index)? {
+                                return Ok(());
+                            }
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        while let Some((key, value)) = update {
+            if let Update::Set(_) = value {
+                if !f(key)? {
+                    return Ok(());
+                }
+            }
+            update = updates.next();
+        }
+        Ok(())
+    }
+
+    /// Applies the function f on each key `key` such that `start <= key < end`. Keys
+    /// are visited in the lexicographic order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::ByteMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map = ByteMapView::load(context).await.unwrap();
+    /// map.insert(vec![0, 1], String::from("Hello"));
+    /// map.insert(vec![1, 2], String::from("Bonjour"));
+    /// let mut count = 0;
+    /// map.for_each_key_in_range(
+    ///     |_key| {
+    ///         count += 1;
+    ///         Ok(())
+    ///     },
+    ///     vec![1]..vec![2],
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(count, 1);
+    /// # })
+    /// ```
+    pub async fn for_each_key_in_range<F>(
+        &self,
+        mut f: F,
+        range: Range<Vec<u8>>,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8]) -> Result<(), ViewError> + Send,
+    {
+        self.for_each_key_in_range_while(
+            |key| {
+                f(key)?;
+                Ok(true)
+            },
+            range,
+        )
+        .await
+    }
+
+    /// Returns the list of keys `key` of the map such that `start <= key < end`, in
+    /// lexicographic order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::ByteMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map = ByteMapView::load(context).await.unwrap();
+    /// map.insert(vec![0, 1], String::from("Hello"));
+    /// map.insert(vec![1, 2], String::from("Bonjour"));
+    /// map.insert(vec![2, 2], String::from("Hallo"));
+    /// assert_eq!(
+    ///     map.keys_in_range(vec![0, 2]..vec![2, 2]).await.unwrap(),
+    ///     vec![vec![1, 2]]
+    /// );
+    /// # })
+    /// ```
+    pub async fn keys_in_range(&self, range: Range<Vec<u8>>) -> Result<Vec<Vec<u8>>, ViewError> {
+        let mut keys = Vec::new();
+        self.for_each_key_in_range(
+            |key| {
+                keys.push(key.to_vec());
+                Ok(())
+            },
+            range,
+        )
+        .await?;
+        Ok(keys)
+    }
+
+    /// Returns at most `count` keys of the map in lexicographic order, starting with the
+    /// key following `after`, or with the first key if `after` is `None`. Only the keys
+    /// of that range are scanned, so that the map can be paginated.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::ByteMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map = ByteMapView::load(context).await.unwrap();
+    /// map.insert(vec![0, 1], String::from("Hello"));
+    /// map.insert(vec![1, 2], String::from("Bonjour"));
+    /// map.insert(vec![2, 2], String::from("Hallo"));
+    /// assert_eq!(
+    ///     map.keys_page(None, 2).await.unwrap(),
+    ///     vec![vec![0, 1], vec![1, 2]]
+    /// );
+    /// assert_eq!(
+    ///     map.keys_page(Some(&[1, 2]), 2).await.unwrap(),
+    ///     vec![vec![2, 2]]
+    /// );
+    /// # })
+    /// ```
+    pub async fn keys_page(
+        &self,
+        after: Option<&[u8]>,
+        count: usize,
+    ) -> Result<Vec<Vec<u8>>, ViewError> {
+        let mut keys = Vec::new();
+        if count == 0 {
+            return Ok(keys);
+        }
+        // The smallest key following `after` is `after` followed by a zero.
+        let start = after.map_or_else(Vec::new, |after| [after, &[0]].concat());
+        self.for_each_key_from_while(
+            |key| {
+                keys.push(key.to_vec());
+                Ok(keys.len() < count)
+            },
+            start,
+            None,
+        )
+        .await?;
+        Ok(keys)
+    }
+
     /// Returns the list of keys of the map in lexicographic order.
     /// ```rust
     /// # tokio_test::block_on(async {
@@ -596,19 +869,66 @@ where
     /// # })
     /// ```
     pub async fn count(&self) -> Result<usize, ViewError> {
-        let mut count = 0;
-        let prefix = Vec::new();
-        self.for_each_key(
-            |_key| {
-                count += 1;
-                Ok(())
-            },
-            prefix,
-        )
-        .await?;
+        let num_insertions = self
+            .updates
+            .values()
+            .filter(|update| matches!(update, Update::Set(_)))
+            .count();
+        if self.deletion_set.delete_storage_first {
+            return Ok(num_insertions);
+        }
+        let mut count = self
+            .context
+            .count_keys_by_prefix(self.context.base_key())
+            .await?;
+        for key_prefix in &self.deletion_set.deleted_prefixes {
+            let base = self.context.base_index(key_prefix);
+            count -= self.context.count_keys_by_prefix(&base).await?;
+        }
+        // The keys under a deleted prefix are no longer counted, so only the pending
+        // updates of the other keys need to be checked against the storage.
+        let mut checked_updates = Vec::new();
+        for (short_key, update) in &self.updates {
+            if self.deletion_set.contains_prefix_of(short_key) {
+                if let Update::Set(_) = update {
+                    count += 1;
+                }
+            } else {
+                checked_updates.push((short_key, update));
+            }
+        }
+        let keys = checked_updates
+            .iter()
+            .map(|(short_key, _)| self.context.base_index(short_key))
+            .collect();
+        let stored = self.context.contains_keys(keys).await?;
+        for ((_, update), stored) in checked_updates.into_iter().zip(stored) {
+            match update {
+                Update::Set(_) if !stored => count += 1,
+                Update::Removed if stored => count -= 1,
+                _ => {}
+            }
+        }
         Ok(count)
     }
 
+    /// Returns true if the map has no entries.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::ByteMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map = ByteMapView::load(context).await.unwrap();
+    /// assert!(map.is_empty().await.unwrap());
+    /// map.insert(vec![0, 1], String::from("Hello"));
+    /// assert!(!map.is_empty().await.unwrap());
+    /// # })
+    /// ```
+    pub async fn is_empty(&self) -> Result<bool, ViewError> {
+        Ok(self.count().await? == 0)
+    }
+
     /// Applies a function f on each key/value pair matching a prefix. The key is the
     /// shortened one by the prefix. The value is an enum that can be either a value
     /// or its serialization. This is needed in order to avoid a scenario where we
@@ -724,6 +1044,70 @@ where
         .await
     }
 
+    /// Applies a function f on each key/value pair such that `start <= key < end`.
+    /// Keys and values are visited in the lexicographic order. The complete key is
+    /// sent to the function f and if it returns false then the loop ends prematurely.
+    ///
+    /// The values are obtained by scanning the common prefix of `start` and `end`.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::ByteMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map = ByteMapView::load(context).await.unwrap();
+    /// map.insert(vec![0, 1], String::from("Hello"));
+    /// map.insert(vec![1, 2], String::from("Bonjour"));
+    /// map.insert(vec![1, 3], String::from("Hallo"));
+    /// let mut values = Vec::new();
+    /// map.for_each_key_value_in_range_while(
+    ///     |_key, value| {
+    ///         values.push(value.into_owned());
+    ///         Ok(values.len() < 2)
+    ///     },
+    ///     vec![0, 1]..vec![1, 3],
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(values, vec!["Hello", "Bonjour"]);
+    /// # })
+    /// ```
+    pub async fn for_each_key_value_in_range_while<'a, F>(
+        &'a self,
+        mut f: F,
+        range: Range<Vec<u8>>,
+    ) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8], Cow<'a, V>) -> Result<bool, ViewError> + Send,
+    {
+        let Range { start, end } = range;
+        if start >= end {
+            return Ok(());
+        }
+        let prefix_len = start
+            .iter()
+            .zip(&end)
+            .take_while(|(byte1, byte2)| byte1 == byte2)
+            .count();
+        let prefix = start[..prefix_len].to_vec();
+        let mut key = prefix.clone();
+        self.for_each_key_value_or_bytes_while(
+            |suffix, value| {
+                key.truncate(prefix_len);
+                key.extend_from_slice(suffix);
+                if key < start {
+                    return Ok(true);
+                }
+                if key >= end {
+                    return Ok(false);
+                }
+                f(&key, value.to_value()?)
+            },
+            prefix,
+        )
+        .await
+    }
+
     /// Applies a function f on each key/value pair matching a prefix. The key is the
     /// shortened one by the prefix. The value is an enum that can be either a value
     /// or its serialization. This is needed in order to avoid a scenario where we
@@ -852,6 +1236,39 @@ where
     pub async fn key_values(&self) -> Result<Vec<(Vec<u8>, V)>, ViewError> {
         self.key_values_by_prefix(Vec::new()).await
     }
+
+    /// Returns the list of keys and values of the map such that `start <= key < end`,
+    /// in lexicographic order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::ByteMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map = ByteMapView::load(context).await.unwrap();
+    /// map.insert(vec![1, 2], String::from("Hello"));
+    /// map.insert(vec![2, 2], String::from("Bonjour"));
+    /// assert_eq!(
+    ///     map.key_values_in_range(vec![1]..vec![2]).await.unwrap(),
+    ///     vec![(vec![1, 2], String::from("Hello"))]
+    /// );
+    /// # })
+    /// ```
+    pub async fn key_values_in_range(
+        &self,
+        range: Range<Vec<u8>>,
+    ) -> Result<Vec<(Vec<u8>, V)>, ViewError> {
+        let mut key_values = Vec::new();
+        self.for_each_key_value_in_range_while(
+            |key, value| {
+                key_values.push((key.to_vec(), value.into_owned()));
+                Ok(true)
+            },
+            range,
+        )
+        .await?;
+        Ok(key_values)
+    }
 }
 
 impl<C, V> ByteMapView<C, V>
@@ -940,7 +1357,69 @@ where
     }
 }
 
-/// A `View` that has a type for keys. The ordering of the entries
+#[async_trait]
+impl<C, V> EntryHashableView<C> for ByteMapView<C, V>
+where
+    C: Context + Send + Sync,
+    ViewError: From<C::Error>,
+    V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    async fn for_each_entry<F>(&self, mut f: F) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8], &[u8]) -> Result<(), ViewError> + Send,
+    {
+        self.for_each_key_value_or_bytes(
+            |index, value| {
+                let bytes = value.into_bytes()?;
+                f(index, &bytes)
+            },
+            Vec::new(),
+        )
+        .await
+    }
+
+    async fn pending_entry_changes(&self) -> Result<Option<EntryChanges>, ViewError> {
+        if self.deletion_set.delete_storage_first {
+            return Ok(None);
+        }
+        let mut changes = EntryChanges::default();
+        for prefix in &self.deletion_set.deleted_prefixes {
+            let key_prefix = self.context.base_index(prefix);
+            let key_values = self.context.find_key_values_by_prefix(&key_prefix).await?;
+            for entry in key_values.iterator() {
+                let (suffix, value) = entry?;
+                let mut index = prefix.clone();
+                index.extend_from_slice(suffix);
+                changes.removed.push((index, value.to_vec()));
+            }
+        }
+        // The stored values replaced by the updates, except the ones already deleted.
+        let indices = self
+            .updates
+            .keys()
+            .filter(|index| !self.deletion_set.contains_prefix_of(index))
+            .cloned()
+            .collect::<Vec<_>>();
+        let keys = indices
+            .iter()
+            .map(|index| self.context.base_index(index))
+            .collect();
+        let values = self.context.read_multi_values_bytes(keys).await?;
+        for (index, value) in indices.into_iter().zip(values) {
+            if let Some(value) = value {
+                changes.removed.push((index, value));
+            }
+        }
+        for (index, update) in &self.updates {
+            if let Update::Set(value) = update {
+                changes.added.push((index.clone(), bcs::to_bytes(value)?));
+            }
+        }
+        Ok(Some(changes))
+    }
+}
+
+/// A `View` that has a type for keys. The ordering of the entries
 /// is determined by the serialization of the context.
 #[derive(Debug)]
 pub struct MapView<C, I, V> {
@@ -1041,6 +1520,30 @@ where
         Ok(())
     }
 
+    /// Inserts or resets the values of several indices, e.g. to initialize a large map.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::MapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: MapView<_, u32, _> = MapView::load(context).await.unwrap();
+    /// map.extend((0..3).map(|i| (i, i.to_string()))).unwrap();
+    /// assert_eq!(map.indices().await.unwrap(), vec![0, 1, 2]);
+    /// # })
+    /// ```
+    pub fn extend<T>(&mut self, entries: T) -> Result<(), ViewError>
+    where
+        T: IntoIterator<Item = (I, V)>,
+    {
+        let entries = entries
+            .into_iter()
+            .map(|(index, value)| Ok((C::derive_short_key(&index)?, value)))
+            .collect::<Result<Vec<_>, ViewError>>()?;
+        self.map.extend(entries);
+        Ok(())
+    }
+
     /// Removes a value. If absent then the operation does nothing.
     /// ```rust
     /// # tokio_test::block_on(async {
@@ -1333,6 +1836,137 @@ where
             .await?;
         Ok(())
     }
+
+    /// Applies a function f on each index `index` such that `start <= index < end` for
+    /// the order determined by the serialization. Indices are visited in that order. If
+    /// the function returns false, then the loop ends prematurely.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::MapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: MapView<_, u32, String> = MapView::load(context).await.unwrap();
+    /// map.insert(&(1 as u32), String::from("One"));
+    /// map.insert(&(2 as u32), String::from("Two"));
+    /// map.insert(&(3 as u32), String::from("Three"));
+    /// let mut indices = Vec::new();
+    /// map.for_each_index_in_range_while(
+    ///     |index| {
+    ///         indices.push(index);
+    ///         Ok(indices.len() < 1)
+    ///     },
+    ///     &(2 as u32)..&(4 as u32),
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(indices, vec![2]);
+    /// # })
+    /// ```
+    pub async fn for_each_index_in_range_while<Q, F>(
+        &self,
+        mut f: F,
+        range: Range<&Q>,
+    ) -> Result<(), ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        F: FnMut(I) -> Result<bool, ViewError> + Send,
+    {
+        let range = C::derive_short_key(range.start)?..C::derive_short_key(range.end)?;
+        self.map
+            .for_each_key_in_range_while(
+                |key| {
+                    let index = C::deserialize_value(key)?;
+                    f(index)
+                },
+                range,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Applies a function f on each index `index` such that `start <= index < end` for
+    /// the order determined by the serialization. Indices are visited in that order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::MapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: MapView<_, u32, String> = MapView::load(context).await.unwrap();
+    /// map.insert(&(1 as u32), String::from("One"));
+    /// map.insert(&(2 as u32), String::from("Two"));
+    /// map.insert(&(3 as u32), String::from("Three"));
+    /// let mut count = 0;
+    /// map.for_each_index_in_range(
+    ///     |_index| {
+    ///         count += 1;
+    ///         Ok(())
+    ///     },
+    ///     &(1 as u32)..&(3 as u32),
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(count, 2);
+    /// # })
+    /// ```
+    pub async fn for_each_index_in_range<Q, F>(
+        &self,
+        mut f: F,
+        range: Range<&Q>,
+    ) -> Result<(), ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        F: FnMut(I) -> Result<(), ViewError> + Send,
+    {
+        self.for_each_index_in_range_while(
+            |index| {
+                f(index)?;
+                Ok(true)
+            },
+            range,
+        )
+        .await
+    }
+
+    /// Returns the list of indices `index` of the map such that `start <= index < end`.
+    /// The order is determined by the serialization.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::MapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: MapView<_, u32, String> = MapView::load(context).await.unwrap();
+    /// map.insert(&(1 as u32), String::from("One"));
+    /// map.insert(&(2 as u32), String::from("Two"));
+    /// map.insert(&(3 as u32), String::from("Three"));
+    /// assert_eq!(
+    ///     map.indices_in_range(&(2 as u32)..&(3 as u32))
+    ///         .await
+    ///         .unwrap(),
+    ///     vec![2]
+    /// );
+    /// # })
+    /// ```
+    pub async fn indices_in_range<Q>(&self, range: Range<&Q>) -> Result<Vec<I>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let mut indices = Vec::new();
+        self.for_each_index_in_range(
+            |index| {
+                indices.push(index);
+                Ok(())
+            },
+            range,
+        )
+        .await?;
+        Ok(indices)
+    }
 }
 
 impl<C, I, V> MapView<C, I, V>
@@ -1369,6 +2003,46 @@ where
         Ok(key_values)
     }
 
+    /// Obtains the `(index,value)` pairs such that `start <= index < end` for the
+    /// order determined by the serialization.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::MapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: MapView<_, u32, String> = MapView::load(context).await.unwrap();
+    /// map.insert(&(1 as u32), String::from("One"));
+    /// map.insert(&(2 as u32), String::from("Two"));
+    /// map.insert(&(3 as u32), String::from("Three"));
+    /// assert_eq!(
+    ///     map.index_values_in_range(&(2 as u32)..&(3 as u32))
+    ///         .await
+    ///         .unwrap(),
+    ///     vec![(2, String::from("Two"))]
+    /// );
+    /// # })
+    /// ```
+    pub async fn index_values_in_range<Q>(&self, range: Range<&Q>) -> Result<Vec<(I, V)>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let range = C::derive_short_key(range.start)?..C::derive_short_key(range.end)?;
+        let mut index_values = Vec::new();
+        self.map
+            .for_each_key_value_in_range_while(
+                |key, value| {
+                    let index = C::deserialize_value(key)?;
+                    index_values.push((index, value.into_owned()));
+                    Ok(true)
+                },
+                range,
+            )
+            .await?;
+        Ok(index_values)
+    }
+
     /// Obtains the number of entries in the map
     /// ```rust
     /// # tokio_test::block_on(async {
@@ -1385,6 +2059,23 @@ where
     pub async fn count(&self) -> Result<usize, ViewError> {
         self.map.count().await
     }
+
+    /// Returns true if the map has no entries.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::MapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: MapView<_, String, _> = MapView::load(context).await.unwrap();
+    /// assert!(map.is_empty().await.unwrap());
+    /// map.insert("Italian", String::from("Ciao"));
+    /// assert!(!map.is_empty().await.unwrap());
+    /// # })
+    /// ```
+    pub async fn is_empty(&self) -> Result<bool, ViewError> {
+        Ok(self.count().await? == 0)
+    }
 }
 
 impl<C, I, V> MapView<C, I, V>
@@ -1436,6 +2127,26 @@ where
     }
 }
 
+#[async_trait]
+impl<C, I, V> EntryHashableView<C> for MapView<C, I, V>
+where
+    C: Context + Send + Sync,
+    ViewError: From<C::Error>,
+    I: Send + Sync + Serialize + DeserializeOwned,
+    V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    async fn for_each_entry<F>(&self, f: F) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8], &[u8]) -> Result<(), ViewError> + Send,
+    {
+        self.map.for_each_entry(f).await
+    }
+
+    async fn pending_entry_changes(&self) -> Result<Option<EntryChanges>, ViewError> {
+        self.map.pending_entry_changes().await
+    }
+}
+
 /// A Custom MapView that uses the custom serialization
 #[derive(Debug)]
 pub struct CustomMapView<C, I, V> {
@@ -1536,6 +2247,30 @@ where
         Ok(())
     }
 
+    /// Inserts or resets the values of several indices, e.g. to initialize a large map.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::CustomMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: CustomMapView<_, u128, _> = CustomMapView::load(context).await.unwrap();
+    /// map.extend((0..3).map(|i| (i, i.to_string()))).unwrap();
+    /// assert_eq!(map.indices().await.unwrap(), vec![0, 1, 2]);
+    /// # })
+    /// ```
+    pub fn extend<T>(&mut self, entries: T) -> Result<(), ViewError>
+    where
+        T: IntoIterator<Item = (I, V)>,
+    {
+        let entries = entries
+            .into_iter()
+            .map(|(index, value)| Ok((index.to_custom_bytes()?, value)))
+            .collect::<Result<Vec<_>, ViewError>>()?;
+        self.map.extend(entries);
+        Ok(())
+    }
+
     /// Removes a value. If absent then this does not do anything.
     /// ```rust
     /// # tokio_test::block_on(async {
@@ -1831,6 +2566,137 @@ where
             .await?;
         Ok(())
     }
+
+    /// Applies a function f on each index `index` such that `start <= index < end` for
+    /// the order determined by the custom serialization. Indices are visited in that order. If
+    /// the function returns false, then the loop ends prematurely.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::CustomMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: CustomMapView<_, u128, String> = CustomMapView::load(context).await.unwrap();
+    /// map.insert(&(1 as u128), String::from("One"));
+    /// map.insert(&(2 as u128), String::from("Two"));
+    /// map.insert(&(3 as u128), String::from("Three"));
+    /// let mut indices = Vec::new();
+    /// map.for_each_index_in_range_while(
+    ///     |index| {
+    ///         indices.push(index);
+    ///         Ok(indices.len() < 1)
+    ///     },
+    ///     &(2 as u128)..&(4 as u128),
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(indices, vec![2]);
+    /// # })
+    /// ```
+    pub async fn for_each_index_in_range_while<Q, F>(
+        &self,
+        mut f: F,
+        range: Range<&Q>,
+    ) -> Result<(), ViewError>
+    where
+        I: Borrow<Q>,
+        Q: CustomSerialize,
+        F: FnMut(I) -> Result<bool, ViewError> + Send,
+    {
+        let range = range.start.to_custom_bytes()?..range.end.to_custom_bytes()?;
+        self.map
+            .for_each_key_in_range_while(
+                |key| {
+                    let index = I::from_custom_bytes(key)?;
+                    f(index)
+                },
+                range,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Applies a function f on each index `index` such that `start <= index < end` for
+    /// the order determined by the custom serialization. Indices are visited in that order.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::CustomMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: CustomMapView<_, u128, String> = CustomMapView::load(context).await.unwrap();
+    /// map.insert(&(1 as u128), String::from("One"));
+    /// map.insert(&(2 as u128), String::from("Two"));
+    /// map.insert(&(3 as u128), String::from("Three"));
+    /// let mut count = 0;
+    /// map.for_each_index_in_range(
+    ///     |_index| {
+    ///         count += 1;
+    ///         Ok(())
+    ///     },
+    ///     &(1 as u128)..&(3 as u128),
+    /// )
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(count, 2);
+    /// # })
+    /// ```
+    pub async fn for_each_index_in_range<Q, F>(
+        &self,
+        mut f: F,
+        range: Range<&Q>,
+    ) -> Result<(), ViewError>
+    where
+        I: Borrow<Q>,
+        Q: CustomSerialize,
+        F: FnMut(I) -> Result<(), ViewError> + Send,
+    {
+        self.for_each_index_in_range_while(
+            |index| {
+                f(index)?;
+                Ok(true)
+            },
+            range,
+        )
+        .await
+    }
+
+    /// Returns the list of indices `index` of the map such that `start <= index < end`.
+    /// The order is determined by the custom serialization.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::CustomMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: CustomMapView<_, u128, String> = CustomMapView::load(context).await.unwrap();
+    /// map.insert(&(1 as u128), String::from("One"));
+    /// map.insert(&(2 as u128), String::from("Two"));
+    /// map.insert(&(3 as u128), String::from("Three"));
+    /// assert_eq!(
+    ///     map.indices_in_range(&(2 as u128)..&(3 as u128))
+    ///         .await
+    ///         .unwrap(),
+    ///     vec![2]
+    /// );
+    /// # })
+    /// ```
+    pub async fn indices_in_range<Q>(&self, range: Range<&Q>) -> Result<Vec<I>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: CustomSerialize,
+    {
+        let mut indices = Vec::new();
+        self.for_each_index_in_range(
+            |index| {
+                indices.push(index);
+                Ok(())
+            },
+            range,
+        )
+        .await?;
+        Ok(indices)
+    }
 }
 
 impl<C, I, V> CustomMapView<C, I, V>
@@ -1867,6 +2733,46 @@ where
         Ok(key_values)
     }
 
+    /// Obtains the `(index,value)` pairs such that `start <= index < end` for the
+    /// order determined by the custom serialization.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::CustomMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: CustomMapView<_, u128, String> = CustomMapView::load(context).await.unwrap();
+    /// map.insert(&(1 as u128), String::from("One"));
+    /// map.insert(&(2 as u128), String::from("Two"));
+    /// map.insert(&(3 as u128), String::from("Three"));
+    /// assert_eq!(
+    ///     map.index_values_in_range(&(2 as u128)..&(3 as u128))
+    ///         .await
+    ///         .unwrap(),
+    ///     vec![(2, String::from("Two"))]
+    /// );
+    /// # })
+    /// ```
+    pub async fn index_values_in_range<Q>(&self, range: Range<&Q>) -> Result<Vec<(I, V)>, ViewError>
+    where
+        I: Borrow<Q>,
+        Q: CustomSerialize,
+    {
+        let range = range.start.to_custom_bytes()?..range.end.to_custom_bytes()?;
+        let mut index_values = Vec::new();
+        self.map
+            .for_each_key_value_in_range_while(
+                |key, value| {
+                    let index = I::from_custom_bytes(key)?;
+                    index_values.push((index, value.into_owned()));
+                    Ok(true)
+                },
+                range,
+            )
+            .await?;
+        Ok(index_values)
+    }
+
     /// Obtains the number of entries in the map
     /// ```rust
     /// # tokio_test::block_on(async {
@@ -1883,6 +2789,23 @@ where
     pub async fn count(&self) -> Result<usize, ViewError> {
         self.map.count().await
     }
+
+    /// Returns true if the map has no entries.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::map_view::CustomMapView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut map: CustomMapView<_, u128, _> = CustomMapView::load(context).await.unwrap();
+    /// assert!(map.is_empty().await.unwrap());
+    /// map.insert(&(24 as u128), String::from("Hello"));
+    /// assert!(!map.is_empty().await.unwrap());
+    /// # })
+    /// ```
+    pub async fn is_empty(&self) -> Result<bool, ViewError> {
+        Ok(self.count().await? == 0)
+    }
 }
 
 impl<C, I, V> CustomMapView<C, I, V>
@@ -1936,6 +2859,26 @@ where
     }
 }
 
+#[async_trait]
+impl<C, I, V> EntryHashableView<C> for CustomMapView<C, I, V>
+where
+    C: Context + Send + Sync,
+    ViewError: From<C::Error>,
+    I: Send + Sync + CustomSerialize,
+    V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    async fn for_each_entry<F>(&self, f: F) -> Result<(), ViewError>
+    where
+        F: FnMut(&[u8], &[u8]) -> Result<(), ViewError> + Send,
+    {
+        self.map.for_each_entry(f).await
+    }
+
+    async fn pending_entry_changes(&self) -> Result<Option<EntryChanges>, ViewError> {
+        self.map.pending_entry_changes().await
+    }
+}
+
 /// Type wrapping `ByteMapView` while memoizing the hash.
 pub type HashedByteMapView<C, V> = WrappedHashableContainerView<C, ByteMapView<C, V>, HasherOutput>;
 
@@ -1946,6 +2889,15 @@ pub type HashedMapView<C, I, V> = WrappedHashableContainerView<C, MapView<C, I,
 pub type HashedCustomMapView<C, I, V> =
     WrappedHashableContainerView<C, CustomMapView<C, I, V>, HasherOutput>;
 
+/// Type wrapping `ByteMapView` while maintaining its hash incrementally.
+pub type IncrementalHashedByteMapView<C, V> = IncrementalHashedView<C, ByteMapView<C, V>>;
+
+/// Type wrapping `MapView` while maintaining its hash incrementally.
+pub type IncrementalHashedMapView<C, I, V> = IncrementalHashedView<C, MapView<C, I, V>>;
+
+/// Type wrapping `CustomMapView` while maintaining its hash incrementally.
+pub type IncrementalHashedCustomMapView<C, I, V> = IncrementalHashedView<C, CustomMapView<C, I, V>>;
+
 mod graphql {
     use std::borrow::Cow;
 
@@ -1999,15 +2951,22 @@ mod graphql {
             })
         }
 
+        /// The entries of the map, filtered by keys, or paginated with at most `first`
+        /// entries following the key `after`.
         async fn entries(
             &self,
             input: Option<MapInput<Vec<u8>>>,
+            first: Option<usize>,
+            after: Option<Vec<u8>>,
         ) -> Result<Vec<Entry<Vec<u8>, Option<V>>>, async_graphql::Error> {
             let keys = input
                 .and_then(|input| input.filters)
                 .and_then(|filters| filters.keys);
             let keys = if let Some(keys) = keys {
                 keys
+            } else if first.is_some() || after.is_some() {
+                self.keys_page(after.as_deref(), first.unwrap_or(usize::MAX))
+                    .await?
             } else {
                 self.keys().await?
             };
@@ -2076,15 +3035,29 @@ mod graphql {
             })
         }
 
+        /// The entries of the map, filtered by keys, or paginated with at most `first`
+        /// entries following the key `after`.
         async fn entries(
             &self,
             input: Option<MapInput<I>>,
+            first: Option<usize>,
+            after: Option<I>,
         ) -> Result<Vec<Entry<I, Option<V>>>, async_graphql::Error> {
             let keys = input
                 .and_then(|input| input.filters)
                 .and_then(|filters| filters.keys);
             let keys = if let Some(keys) = keys {
                 keys
+            } else if first.is_some() || after.is_some() {
+                let after = after.map(|after| C::derive_short_key(&after)).transpose()?;
+                let short_keys = self
+                    .map
+                    .keys_page(after.as_deref(), first.unwrap_or(usize::MAX))
+                    .await?;
+                short_keys
+                    .iter()
+                    .map(|short_key| C::deserialize_value(short_key))
+                    .collect::<Result<Vec<_>, _>>()?
             } else {
                 self.indices().await?
             };
@@ -2146,15 +3119,29 @@ mod graphql {
             })
         }
 
+        /// The entries of the map, filtered by keys, or paginated with at most `first`
+        /// entries following the key `after`.
         async fn entries(
             &self,
             input: Option<MapInput<I>>,
+            first: Option<usize>,
+            after: Option<I>,
         ) -> Result<Vec<Entry<I, Option<V>>>, async_graphql::Error> {
             let keys = input
                 .and_then(|input| input.filters)
                 .and_then(|filters| filters.keys);
             let keys = if let Some(keys) = keys {
                 keys
+            } else if first.is_some() || after.is_some() {
+                let after = after.map(|after| after.to_custom_bytes()).transpose()?;
+                let short_keys = self
+                    .map
+                    .keys_page(after.as_deref(), first.unwrap_or(usize::MAX))
+                    .await?;
+                short_keys
+                    .iter()
+                    .map(|short_key| I::from_custom_bytes(short_key))
+                    .collect::<Result<Vec<_>, _>>()?
             } else {
                 self.indices().await?
             };
@@ -11,12 +11,20 @@ pub use linera_views_derive::{
 use serde::Serialize;
 use thiserror::Error;
 
-use crate::{batch::Batch, common::HasherOutput};
+use crate::{
+    batch::{Batch, WriteOperation},
+    common::HasherOutput,
+    context::Context,
+};
 
 #[cfg(test)]
 #[path = "unit_tests/views.rs"]
 mod tests;
 
+#[cfg(doctest)]
+#[path = "unit_tests/derive_compile_fail.rs"]
+mod derive_compile_fail;
+
 /// The `RegisterView` implements a register for a single value.
 pub mod register_view;
 
@@ -38,6 +46,9 @@ pub mod set_view;
 /// The `CollectionView` implements a map structure whose keys are ordered and the values are views.
 pub mod collection_view;
 
+/// The `IndexedCollectionView` implements a collection of views indexed by consecutive integers.
+pub mod indexed_collection_view;
+
 /// The `ReentrantCollectionView` implements a map structure whose keys are ordered and the values are views with concurrent access.
 pub mod reentrant_collection_view;
 
@@ -47,12 +58,24 @@ pub mod key_value_store_view;
 /// Wrapping a view to compute a hash.
 pub mod hashable_wrapper;
 
+/// Wrapping a map-like or log-like view to maintain its hash incrementally.
+pub mod incremental_hash;
+
 /// The minimum value for the view tags. Values in 0..MIN_VIEW_TAG are used for other purposes.
 pub const MIN_VIEW_TAG: u8 = 1;
 
+/// The tag of the keys marking the presence of the optional fields of the derived views,
+/// next to the [`MIN_VIEW_TAG`] of the keys of their views.
+pub const OPTIONAL_VIEW_TAG: u8 = MIN_VIEW_TAG + 1;
+
 /// A view gives exclusive access to read and write the data stored at an underlying
 /// address in storage.
 #[async_trait]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a view with context `{C}`",
+    note = "the fields of a struct deriving a view must be views; plain values are stored \
+        in views such as `RegisterView<C, T>`"
+)]
 pub trait View<C>: Sized {
     /// The number of keys used for the initialization
     const NUM_INIT_KEYS: usize;
@@ -69,7 +92,9 @@ pub trait View<C>: Sized {
     /// Loads a view
     async fn load(context: C) -> Result<Self, ViewError>;
 
-    /// Discards all pending changes. After that `flush` should have no effect to storage.
+    /// Discards all pending changes, including the ones of nested views, so that the view
+    /// is back to its state when it was loaded or last flushed. After that `flush` should
+    /// have no effect to storage.
     fn rollback(&mut self);
 
     /// Returns [`true`] if flushing this view would result in changes to the persistent storage.
@@ -102,6 +127,15 @@ pub enum ViewError {
     #[error(transparent)]
     BcsError(#[from] bcs::Error),
 
+    /// Serialization error of a [`crate::common::Codec`].
+    #[error("Serialization error with the {codec} codec: {error}")]
+    Serialization {
+        /// The name of the codec that failed.
+        codec: &'static str,
+        /// The error reported by the codec.
+        error: String,
+    },
+
     /// We failed to acquire an entry in a CollectionView or a ReentrantCollectionView.
     #[error("trying to access a collection view or reentrant collection view while some entries are still being accessed")]
     CannotAcquireCollectionEntry,
@@ -129,6 +163,8 @@ pub enum ViewError {
         backend: String,
         /// error is the specific problem that occurred within that context
         error: String,
+        /// Whether the operation may succeed if it is retried.
+        retryable: bool,
     },
 
     /// The key must not be too long
@@ -139,6 +175,18 @@ pub enum ViewError {
     #[error("Entry does not exist in memory: {0}")]
     NotFound(String),
 
+    /// A key that was expected to be present in the storage is missing.
+    #[error("Key not found in storage: {}", hex::encode(.0))]
+    KeyNotFound(Vec<u8>),
+
+    /// The value of a key in the destination of a copy differs from the source.
+    #[error("The copied value of the key {} differs from the source", hex::encode(.0))]
+    CopyMismatch(Vec<u8>),
+
+    /// A copy was requested with pages of zero keys.
+    #[error("The chunk size of a copy must be positive")]
+    ZeroChunkSize,
+
     /// The database is corrupt: Entries don't have the expected hash.
     #[error("Inconsistent database entries")]
     InconsistentEntries,
@@ -151,10 +199,28 @@ pub enum ViewError {
     #[error("Post load values error")]
     PostLoadValuesError,
 
+    /// The key is reserved by the context, e.g. for the dictionary of the compressed base
+    /// keys, and cannot be written by the views.
+    #[error("The key {} is reserved by the context", hex::encode(.0))]
+    ReservedKey(Vec<u8>),
+
     /// The value is too large for the client
     #[error("The value is too large for the client")]
     TooLargeValue,
 
+    /// The store does not support conditional writes.
+    #[error("The store does not support compare-and-put operations")]
+    CompareAndPutUnsupported,
+
+    /// The advisory lock of a scope is held by another process.
+    #[error("The lock of the scope is held by another process")]
+    LockHeld,
+
+    /// The advisory lock of the context was lost, so that writing could corrupt the data
+    /// of another process.
+    #[error("The lock of the scope was lost")]
+    LockLost,
+
     /// Some blobs were not found.
     #[error("Blobs not found: {0:?}")]
     BlobsNotFound(Vec<BlobId>),
@@ -165,6 +231,18 @@ impl ViewError {
     pub fn not_found<T: Debug>(msg: &str, key: T) -> ViewError {
         ViewError::NotFound(format!("{} {:?}", msg, key))
     }
+
+    /// Whether the error is caused by a transient condition of the storage, such as
+    /// throttling or a timeout, so that the operation may succeed if it is retried.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ViewError::StoreError {
+                retryable: true,
+                ..
+            }
+        )
+    }
 }
 
 /// A view that supports hashing its values.
@@ -222,6 +300,24 @@ impl Hasher for sha3::Sha3_256 {
 pub trait RootView<C>: View<C> {
     /// Saves the root view to the database context
     async fn save(&mut self) -> Result<(), ViewError>;
+
+    /// Saves the root view in batches whose estimated size is at most `max_batch_bytes`
+    /// (or which hold a single larger operation), e.g. to initialize a large view on a
+    /// backend limiting the size of its transactions. Unlike [`RootView::save`], the
+    /// changes are not written atomically: if writing a batch fails, the previous ones
+    /// remain written and the view should be reloaded.
+    async fn flush_in_chunks(&mut self, max_batch_bytes: usize) -> Result<(), ViewError>
+    where
+        Self: Send,
+        C: Context + Send + Sync,
+    {
+        let mut batch = Batch::new();
+        self.flush(&mut batch)?;
+        for batch in batch.split_at_size(max_batch_bytes) {
+            self.context().write_batch(batch).await?;
+        }
+        Ok(())
+    }
 }
 
 /// A [`View`] that also supports crypto hash
@@ -249,4 +345,13 @@ pub trait ClonableView<C>: View<C> {
     /// Creates a clone of this view, sharing the underlying storage context but prone to
     /// data races which can corrupt the view state.
     fn clone_unchecked(&mut self) -> Result<Self, ViewError>;
+
+    /// Returns the operations that flushing the view would write, without flushing it,
+    /// e.g. to log or to veto the pending changes before calling [`View::rollback`].
+    fn pending_operations(&mut self) -> Result<Vec<WriteOperation>, ViewError> {
+        let mut view = self.clone_unchecked()?;
+        let mut batch = Batch::new();
+        view.flush(&mut batch)?;
+        Ok(batch.operations)
+    }
 }
@@ -108,7 +108,7 @@ where
     fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
         let mut delete_view = false;
         if self.delete_storage_first {
-            batch.delete_key_prefix(self.context.base_key());
+            batch.delete_key_prefix(self.context.base_key().to_vec());
             delete_view = true;
         }
         if self.stored_count() == 0 {
@@ -245,11 +245,33 @@ where
     /// # })
     /// ```
     pub fn delete_front(&mut self) {
-        if self.stored_count() > 0 {
-            self.front_delete_count += 1;
-        } else {
-            self.new_back_values.pop_front();
-        }
+        self.delete_front_values(1);
+    }
+
+    /// Deletes the `count` front values, or all the values if there are fewer of them.
+    /// The stored values are not read: their keys are deleted one by one at the next
+    /// flush, since the serialized indices do not form a contiguous range of keys.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::queue_view::QueueView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut queue = QueueView::load(context).await.unwrap();
+    /// queue.push_back(34);
+    /// queue.push_back(37);
+    /// queue.push_back(42);
+    /// queue.delete_front_values(2);
+    /// assert_eq!(queue.elements().await.unwrap(), vec![42]);
+    /// queue.delete_front_values(5);
+    /// assert_eq!(queue.count(), 0);
+    /// # })
+    /// ```
+    pub fn delete_front_values(&mut self, count: usize) {
+        let stored_deletions = count.min(self.stored_count());
+        self.front_delete_count += stored_deletions;
+        let new_deletions = (count - stored_deletions).min(self.new_back_values.len());
+        self.new_back_values.drain(..new_deletions);
     }
 
     /// Pushes a value to the end of the queue.
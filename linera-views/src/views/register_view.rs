@@ -1,6 +1,7 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::marker::PhantomData;
 #[cfg(with_metrics)]
 use std::sync::LazyLock;
 
@@ -14,7 +15,7 @@ use {
 
 use crate::{
     batch::Batch,
-    common::{from_bytes_option_or_default, HasherOutput},
+    common::{BcsCodec, Codec, HasherOutput},
     context::Context,
     hashable_wrapper::WrappedHashableContainerView,
     views::{ClonableView, HashableView, Hasher, View, ViewError},
@@ -31,21 +32,24 @@ static REGISTER_VIEW_HASH_RUNTIME: LazyLock<HistogramVec> = LazyLock::new(|| {
     )
 });
 
-/// A view that supports modifying a single value of type `T`.
+/// A view that supports modifying a single value of type `T`. The value is stored
+/// using the codec `K`, which is BCS by default.
 #[derive(Debug)]
-pub struct RegisterView<C, T> {
+pub struct RegisterView<C, T, K = BcsCodec> {
     delete_storage_first: bool,
     context: C,
     stored_value: Box<T>,
     update: Option<Box<T>>,
+    _codec: PhantomData<K>,
 }
 
 #[async_trait]
-impl<C, T> View<C> for RegisterView<C, T>
+impl<C, T, K> View<C> for RegisterView<C, T, K>
 where
     C: Context + Send + Sync,
     ViewError: From<C::Error>,
     T: Default + Send + Sync + Serialize + DeserializeOwned,
+    K: Codec,
 {
     const NUM_INIT_KEYS: usize = 1;
 
@@ -54,18 +58,21 @@ where
     }
 
     fn pre_load(context: &C) -> Result<Vec<Vec<u8>>, ViewError> {
-        Ok(vec![context.base_key()])
+        Ok(vec![context.base_key().to_vec()])
     }
 
     fn post_load(context: C, values: &[Option<Vec<u8>>]) -> Result<Self, ViewError> {
-        let value =
-            from_bytes_option_or_default(values.first().ok_or(ViewError::PostLoadValuesError)?)?;
+        let value = match values.first().ok_or(ViewError::PostLoadValuesError)? {
+            Some(bytes) => K::decode(bytes)?,
+            None => T::default(),
+        };
         let stored_value = Box::new(value);
         Ok(Self {
             delete_storage_first: false,
             context,
             stored_value,
             update: None,
+            _codec: PhantomData,
         })
     }
 
@@ -90,12 +97,12 @@ where
     fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
         let mut delete_view = false;
         if self.delete_storage_first {
-            batch.delete_key(self.context.base_key());
+            batch.delete_key(self.context.base_key().to_vec());
             self.stored_value = Box::default();
             delete_view = true;
         } else if let Some(value) = self.update.take() {
-            let key = self.context.base_key();
-            batch.put_key_value(key, &value)?;
+            let key = self.context.base_key().to_vec();
+            batch.put_key_value_with_codec::<K>(key, &value)?;
             self.stored_value = value;
         }
         self.delete_storage_first = false;
@@ -109,11 +116,12 @@ where
     }
 }
 
-impl<C, T> ClonableView<C> for RegisterView<C, T>
+impl<C, T, K> ClonableView<C> for RegisterView<C, T, K>
 where
     C: Context + Send + Sync,
     ViewError: From<C::Error>,
     T: Clone + Default + Send + Sync + Serialize + DeserializeOwned,
+    K: Codec,
 {
     fn clone_unchecked(&mut self) -> Result<Self, ViewError> {
         Ok(RegisterView {
@@ -121,11 +129,12 @@ where
             context: self.context.clone(),
             stored_value: self.stored_value.clone(),
             update: self.update.clone(),
+            _codec: PhantomData,
         })
     }
 }
 
-impl<C, T> RegisterView<C, T>
+impl<C, T, K> RegisterView<C, T, K>
 where
     C: Context,
 {
@@ -166,13 +175,31 @@ where
         self.update = Some(Box::new(value));
     }
 
+    /// Sets the value in the register to the result of `f` on the current value.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut register = RegisterView::<_, u32>::load(context).await.unwrap();
+    /// register.update_with(|value| value + 5);
+    /// register.update_with(|value| value * 2);
+    /// assert_eq!(*register.get(), 10);
+    /// # })
+    /// ```
+    pub fn update_with(&mut self, f: impl FnOnce(&T) -> T) {
+        let value = f(self.get());
+        self.set(value);
+    }
+
     /// Obtains the extra data.
     pub fn extra(&self) -> &C::Extra {
         self.context.extra()
     }
 }
 
-impl<C, T> RegisterView<C, T>
+impl<C, T, K> RegisterView<C, T, K>
 where
     C: Context,
     T: Clone + Serialize,
@@ -210,11 +237,12 @@ where
 }
 
 #[async_trait]
-impl<C, T> HashableView<C> for RegisterView<C, T>
+impl<C, T, K> HashableView<C> for RegisterView<C, T, K>
 where
     C: Context + Send + Sync,
     ViewError: From<C::Error>,
     T: Clone + Default + Send + Sync + Serialize + DeserializeOwned,
+    K: Codec,
 {
     type Hasher = sha3::Sha3_256;
 
@@ -227,6 +255,36 @@ where
     }
 }
 
+impl<C, T, K> RegisterView<C, T, K>
+where
+    C: Context,
+    T: Serialize,
+    K: Codec,
+{
+    /// Sets the value in the register to `new`, provided that the current value is
+    /// `expected`, and returns whether it did. The values are compared through their
+    /// encoding. As with [`RegisterView::set`], the new value is written by the next flush.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::register_view::RegisterView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut register = RegisterView::<_, u32>::load(context).await.unwrap();
+    /// assert!(register.compare_and_set(&0, 5).unwrap());
+    /// assert!(!register.compare_and_set(&0, 7).unwrap());
+    /// assert_eq!(*register.get(), 5);
+    /// # })
+    /// ```
+    pub fn compare_and_set(&mut self, expected: &T, new: T) -> Result<bool, ViewError> {
+        if K::encode(self.get())? != K::encode(expected)? {
+            return Ok(false);
+        }
+        self.set(new);
+        Ok(true)
+    }
+}
+
 /// Type wrapping `RegisterView` while memoizing the hash.
 pub type HashedRegisterView<C, T> =
     WrappedHashableContainerView<C, RegisterView<C, T>, HasherOutput>;
@@ -237,10 +295,11 @@ mod graphql {
     use super::RegisterView;
     use crate::context::Context;
 
-    impl<C, T> async_graphql::OutputType for RegisterView<C, T>
+    impl<C, T, K> async_graphql::OutputType for RegisterView<C, T, K>
     where
         C: Context + Send + Sync,
         T: async_graphql::OutputType + Send + Sync,
+        K: Send + Sync,
     {
         fn type_name() -> Cow<'static, str> {
             T::type_name()
@@ -1,6 +1,16 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+//! The `SetView` implements a set that can be modified.
+//!
+//! Only the keys are stored, with empty values, so a set should be preferred to a
+//! `MapView` with `()` values. There are 3 different variants:
+//! * The [`ByteSetView`] whose keys are the `Vec<u8>`, in lexicographic order.
+//! * The [`SetView`] whose keys are a serializable type `I`, in the order of their
+//!   BCS serialization.
+//! * The [`CustomSetView`] whose keys are a serializable type `I`, in the order of their
+//!   custom serialization.
+
 #[cfg(with_metrics)]
 use std::sync::LazyLock;
 use std::{borrow::Borrow, collections::BTreeMap, marker::PhantomData, mem};
@@ -85,7 +95,7 @@ where
         let mut delete_view = false;
         if self.delete_storage_first {
             delete_view = true;
-            batch.delete_key_prefix(self.context.base_key());
+            batch.delete_key_prefix(self.context.base_key().to_vec());
             for (index, update) in mem::take(&mut self.updates) {
                 if let Update::Set(_) = update {
                     let key = self.context.base_index(&index);
@@ -232,7 +242,8 @@ where
         Ok(keys)
     }
 
-    /// Returns the number of entries in the set.
+    /// Returns the number of entries in the set. The stored keys are counted by the
+    /// database and the pending updates are accounted for without listing the keys.
     /// ```rust
     /// # tokio_test::block_on(async {
     /// # use linera_views::{context::create_test_memory_context, set_view::ByteSetView};
@@ -241,19 +252,52 @@ where
     /// let mut set = ByteSetView::load(context).await.unwrap();
     /// set.insert(vec![0, 1]);
     /// set.insert(vec![0, 2]);
-    /// assert_eq!(set.keys().await.unwrap(), vec![vec![0, 1], vec![0, 2]]);
+    /// assert_eq!(set.count().await.unwrap(), 2);
     /// # })
     /// ```
     pub async fn count(&self) -> Result<usize, ViewError> {
-        let mut count = 0;
-        self.for_each_key(|_key| {
-            count += 1;
-            Ok(())
-        })
-        .await?;
+        let num_insertions = self
+            .updates
+            .values()
+            .filter(|update| matches!(update, Update::Set(())))
+            .count();
+        if self.delete_storage_first {
+            return Ok(num_insertions);
+        }
+        let base = self.context.base_key();
+        let mut count = self.context.count_keys_by_prefix(base).await?;
+        let keys = self
+            .updates
+            .keys()
+            .map(|short_key| self.context.base_index(short_key))
+            .collect();
+        let stored = self.context.contains_keys(keys).await?;
+        for (update, stored) in self.updates.values().zip(stored) {
+            match update {
+                Update::Set(()) if !stored => count += 1,
+                Update::Removed if stored => count -= 1,
+                _ => {}
+            }
+        }
         Ok(count)
     }
 
+    /// Returns true if the set has no entries.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::{context::create_test_memory_context, set_view::ByteSetView};
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut set = ByteSetView::load(context).await.unwrap();
+    /// assert!(set.is_empty().await.unwrap());
+    /// set.insert(vec![0, 1]);
+    /// assert!(!set.is_empty().await.unwrap());
+    /// # })
+    /// ```
+    pub async fn is_empty(&self) -> Result<bool, ViewError> {
+        Ok(self.count().await? == 0)
+    }
+
     /// Applies a function f on each index (aka key). Keys are visited in a
     /// lexicographic order. If the function returns false, then the loop ends
     /// prematurely.
@@ -284,7 +328,7 @@ where
         let mut update = updates.next();
         if !self.delete_storage_first {
             let base = self.context.base_key();
-            for index in self.context.find_keys_by_prefix(&base).await?.iterator() {
+            for index in self.context.find_keys_by_prefix(base).await?.iterator() {
                 let index = index?;
                 loop {
                     match update {
@@ -573,6 +617,22 @@ where
         self.set.count().await
     }
 
+    /// Returns true if the set has no entries.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::{context::create_test_memory_context, set_view::SetView};
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut set: SetView<_, u32> = SetView::load(context).await.unwrap();
+    /// assert!(set.is_empty().await.unwrap());
+    /// set.insert(&(34 as u32));
+    /// assert!(!set.is_empty().await.unwrap());
+    /// # })
+    /// ```
+    pub async fn is_empty(&self) -> Result<bool, ViewError> {
+        Ok(self.count().await? == 0)
+    }
+
     /// Applies a function f on each index. Indices are visited in an order
     /// determined by the serialization. If the function returns false, then the
     /// loop ends prematurely.
@@ -863,6 +923,23 @@ where
         self.set.count().await
     }
 
+    /// Returns true if the set has no entries.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::create_test_memory_context;
+    /// # use linera_views::set_view::CustomSetView;
+    /// # use linera_views::views::View;
+    /// # let context = create_test_memory_context();
+    /// let mut set = CustomSetView::<_, u128>::load(context).await.unwrap();
+    /// assert!(set.is_empty().await.unwrap());
+    /// set.insert(&(34 as u128));
+    /// assert!(!set.is_empty().await.unwrap());
+    /// # })
+    /// ```
+    pub async fn is_empty(&self) -> Result<bool, ViewError> {
+        Ok(self.count().await? == 0)
+    }
+
     /// Applies a function f on each index. Indices are visited in an order
     /// determined by the custom serialization. If the function does return
     /// false, then the loop prematurely ends.
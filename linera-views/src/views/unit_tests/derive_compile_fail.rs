@@ -0,0 +1,64 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The shapes of structs that the view derives reject.
+//!
+//! Enums are not views, since the keys of a view must be known before loading it:
+//! ```compile_fail
+//! use linera_views::{register_view::RegisterView, views::View};
+//!
+//! #[derive(View)]
+//! enum StateView<C> {
+//!     Counter(RegisterView<C, u64>),
+//! }
+//! ```
+//!
+//! The fields of a view must be named:
+//! ```compile_fail
+//! use linera_views::{register_view::RegisterView, views::View};
+//!
+//! #[derive(View)]
+//! struct StateView<C>(RegisterView<C, u64>);
+//! ```
+//!
+//! A view needs a field that is not optional, which gives the context of the view:
+//! ```compile_fail
+//! use linera_views::{register_view::RegisterView, views::View};
+//!
+//! #[derive(View)]
+//! struct StateView<C> {
+//!     counter: Option<RegisterView<C, u64>>,
+//! }
+//! ```
+//!
+//! The context must be a type parameter or be given by an attribute:
+//! ```compile_fail
+//! use linera_views::{context::MemoryContext, register_view::RegisterView, views::View};
+//!
+//! #[derive(View)]
+//! struct StateView {
+//!     counter: RegisterView<MemoryContext<()>, u64>,
+//! }
+//! ```
+//!
+//! The fields of a view must be views:
+//! ```compile_fail
+//! use linera_views::{context::MemoryContext, views::View};
+//!
+//! #[derive(View)]
+//! #[view(context = "MemoryContext<()>")]
+//! struct StateView {
+//!     counter: u64,
+//! }
+//! ```
+//!
+//! For comparison, the same view with a register compiles:
+//! ```
+//! use linera_views::{context::MemoryContext, register_view::RegisterView, views::View};
+//!
+//! #[derive(View)]
+//! #[view(context = "MemoryContext<()>")]
+//! struct StateView {
+//!     counter: RegisterView<MemoryContext<()>, u64>,
+//! }
+//! ```
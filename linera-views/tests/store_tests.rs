@@ -7,10 +7,13 @@ use linera_views::{
     key_value_store_view::ViewContainer,
     memory::MemoryStore,
     random::make_deterministic_rng,
-    store::TestKeyValueStore as _,
+    store::{Order, TestKeyValueStore as _},
     test_utils::{
-        big_read_multi_values, get_random_test_scenarios, run_big_write_read, run_reads,
-        run_writes_from_blank, run_writes_from_state,
+        big_read_multi_values, get_random_test_scenarios, run_batch_semantics_suite,
+        run_big_write_read, run_compare_and_put, run_count_keys_by_prefix, run_dump_and_restore,
+        run_find_keys_by_prefix_paginated, run_find_keys_in_range, run_prefix_scan_suite,
+        run_prefix_streams, run_reads, run_reads_with_order, run_reads_writes_suite,
+        run_size_limits, run_writes_from_blank, run_writes_from_state,
     },
     value_splitting::create_value_splitting_memory_store,
 };
@@ -45,6 +48,227 @@ async fn test_read_multi_values_scylla_db() {
     big_read_multi_values::<ScyllaDbStore>(config, 22200000, 200).await;
 }
 
+#[tokio::test]
+async fn test_conformance_memory() {
+    let store = MemoryStore::new_test_store().await.unwrap();
+    run_reads_writes_suite(&store).await;
+    run_prefix_scan_suite(&store).await;
+    run_batch_semantics_suite(&store).await;
+}
+
+#[tokio::test]
+async fn test_conformance_value_splitting_memory() {
+    use linera_views::value_splitting::ValueSplittingStore;
+    let store = ValueSplittingStore::<MemoryStore>::new_test_store()
+        .await
+        .unwrap();
+    run_reads_writes_suite(&store).await;
+    run_prefix_scan_suite(&store).await;
+    run_batch_semantics_suite(&store).await;
+}
+
+#[tokio::test]
+async fn test_conformance_lru_caching_memory() {
+    use linera_views::lru_caching::LruCachingStore;
+    let store = LruCachingStore::<MemoryStore>::new_test_store()
+        .await
+        .unwrap();
+    run_reads_writes_suite(&store).await;
+    run_prefix_scan_suite(&store).await;
+    run_batch_semantics_suite(&store).await;
+}
+
+#[cfg(with_encryption)]
+#[tokio::test]
+async fn test_conformance_encrypted_memory() {
+    use linera_views::encrypted::EncryptedMemoryStore;
+    let store = EncryptedMemoryStore::new_test_store().await.unwrap();
+    run_reads_writes_suite(&store).await;
+    run_prefix_scan_suite(&store).await;
+    run_batch_semantics_suite(&store).await;
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_conformance_rocks_db() {
+    let store = linera_views::rocks_db::RocksDbStore::new_test_store()
+        .await
+        .unwrap();
+    run_reads_writes_suite(&store).await;
+    run_prefix_scan_suite(&store).await;
+    run_batch_semantics_suite(&store).await;
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_conformance_rocks_db_with_prefix_bloom_filters() {
+    use linera_views::{
+        random::generate_test_namespace,
+        rocks_db::{RocksDbConfig, RocksDbStore},
+        store::AdminKeyValueStore as _,
+    };
+    // The prefixes of the scans of the suites are both shorter and longer than the
+    // extracted ones.
+    let db_config = RocksDbConfig {
+        prefix_length: Some(2),
+        bloom_filter_bits_per_key: Some(10.0),
+        memtable_prefix_bloom_ratio: 0.1,
+        ..RocksDbConfig::default()
+    };
+    let config = RocksDbStore::new_test_config()
+        .await
+        .unwrap()
+        .with_db_config(db_config);
+    let namespace = generate_test_namespace();
+    let store = RocksDbStore::recreate_and_connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    run_reads_writes_suite(&store).await;
+    run_prefix_scan_suite(&store).await;
+    run_batch_semantics_suite(&store).await;
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_conformance_dynamo_db() {
+    let store = linera_views::dynamo_db::DynamoDbStore::new_test_store()
+        .await
+        .unwrap();
+    run_reads_writes_suite(&store).await;
+    run_prefix_scan_suite(&store).await;
+    run_batch_semantics_suite(&store).await;
+}
+
+#[cfg(with_fs)]
+#[tokio::test]
+async fn test_conformance_fs_store() {
+    // The batches of the file system store are not crash-atomic, which the suites do not
+    // test.
+    let store = linera_views::fs_store::FileSystemStore::new_test_store()
+        .await
+        .unwrap();
+    run_reads_writes_suite(&store).await;
+    run_prefix_scan_suite(&store).await;
+    run_batch_semantics_suite(&store).await;
+}
+
+#[cfg(with_fs)]
+#[tokio::test]
+async fn test_fs_store_layout() {
+    use linera_views::{
+        fs_store::FileSystemStore,
+        store::{AdminKeyValueStore as _, ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    let config = FileSystemStore::new_test_config().await.unwrap();
+    let store = FileSystemStore::recreate_and_connect(&config, "layout", &[1])
+        .await
+        .unwrap();
+    let long_key = (0..150).collect::<Vec<u8>>();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![], vec![0]);
+    batch.put_key_value_bytes(vec![10], vec![1]);
+    batch.put_key_value_bytes(vec![10, 11, 12], vec![2]);
+    batch.put_key_value_bytes(long_key.clone(), vec![3]);
+    store.write_batch(batch).await.unwrap();
+
+    let directory = config.path_buf.join("layout").join("01.root");
+    assert!(directory.join(".value").is_file());
+    assert!(directory.join("0a.value").is_file());
+    assert_eq!(
+        std::fs::read(directory.join("0a").join("0b0c.value")).unwrap(),
+        vec![2]
+    );
+    let long_key_path = directory
+        .join("00")
+        .join(hex::encode(&long_key[1..65]))
+        .join(hex::encode(&long_key[65..129]))
+        .join(format!("{}.value", hex::encode(&long_key[129..])));
+    assert!(long_key_path.is_file());
+
+    // The temporary files of interrupted writes are ignored.
+    std::fs::write(directory.join("0a").join("0d.value.0.tmp"), [4]).unwrap();
+    let keys = store.find_keys_by_prefix(&[]).await.unwrap();
+    assert_eq!(keys, vec![vec![], long_key, vec![10], vec![10, 11, 12]]);
+
+    let mut batch = Batch::new();
+    batch.delete_key_prefix(vec![10]);
+    store.write_batch(batch).await.unwrap();
+    assert!(!directory.join("0a").exists());
+    assert_eq!(store.find_keys_by_prefix(&[10]).await.unwrap().len(), 0);
+}
+
+#[cfg(with_s3)]
+#[tokio::test]
+async fn test_conformance_s3() {
+    // The batches of S3 are not atomic, which the suites do not test.
+    let store = linera_views::s3::S3Store::new_test_store().await.unwrap();
+    run_reads_writes_suite(&store).await;
+    run_prefix_scan_suite(&store).await;
+    run_batch_semantics_suite(&store).await;
+}
+
+#[cfg(with_scylladb)]
+#[tokio::test]
+async fn test_conformance_scylla_db() {
+    let store = linera_views::scylla_db::ScyllaDbStore::new_test_store()
+        .await
+        .unwrap();
+    run_reads_writes_suite(&store).await;
+    run_prefix_scan_suite(&store).await;
+    run_batch_semantics_suite(&store).await;
+}
+
+#[tokio::test]
+async fn test_size_limits_memory() {
+    use linera_views::store::{ReadableKeyValueStore as _, WritableKeyValueStore as _};
+    assert_eq!(MemoryStore::MAX_KEY_SIZE, usize::MAX);
+    assert_eq!(MemoryStore::MAX_VALUE_SIZE, usize::MAX);
+    let store = MemoryStore::new_test_store().await.unwrap();
+    run_size_limits(store).await;
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_size_limits_rocks_db() {
+    use linera_views::{
+        rocks_db::RocksDbStore,
+        store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    // The 4 bytes of the segment indices of the values are taken from the keys.
+    assert_eq!(RocksDbStore::MAX_KEY_SIZE, 8388208 - 4);
+    assert_eq!(RocksDbStore::MAX_VALUE_SIZE, usize::MAX);
+    let store = RocksDbStore::new_test_store().await.unwrap();
+    run_size_limits(store).await;
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_size_limits_dynamo_db() {
+    use linera_views::{
+        dynamo_db::DynamoDbStore,
+        store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    // DynamoDB limits the sort keys to 1024 bytes, 4 of which are taken by the segment
+    // indices of the values.
+    assert_eq!(DynamoDbStore::MAX_KEY_SIZE, 1020);
+    assert_eq!(DynamoDbStore::MAX_VALUE_SIZE, usize::MAX);
+    let store = DynamoDbStore::new_test_store().await.unwrap();
+    run_size_limits(store).await;
+}
+
+#[cfg(with_scylladb)]
+#[tokio::test]
+async fn test_size_limits_scylla_db() {
+    use linera_views::{
+        scylla_db::ScyllaDbStore,
+        store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    assert_eq!(ScyllaDbStore::MAX_KEY_SIZE, 10240 - 4);
+    assert_eq!(ScyllaDbStore::MAX_VALUE_SIZE, usize::MAX);
+    let store = ScyllaDbStore::new_test_store().await.unwrap();
+    run_size_limits(store).await;
+}
+
 #[tokio::test]
 async fn test_reads_test_memory() {
     for scenario in get_random_test_scenarios() {
@@ -54,102 +278,1956 @@ async fn test_reads_test_memory() {
 }
 
 #[tokio::test]
-async fn test_reads_memory() {
-    for scenario in get_random_test_scenarios() {
-        let store = MemoryStore::new_test_store().await.unwrap();
-        run_reads(store, scenario).await;
+async fn test_reads_memory() {
+    for scenario in get_random_test_scenarios() {
+        let store = MemoryStore::new_test_store().await.unwrap();
+        run_reads(store, scenario).await;
+    }
+}
+
+#[cfg(with_encryption)]
+#[tokio::test]
+async fn test_reads_encrypted_memory() {
+    use linera_views::encrypted::EncryptedMemoryStore;
+    for scenario in get_random_test_scenarios() {
+        let store = EncryptedMemoryStore::new_test_store().await.unwrap();
+        run_reads(store, scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_write_batch_stats_memory() {
+    use linera_views::store::WritableKeyValueStore as _;
+    let store = MemoryStore::new_test_store().await.unwrap();
+    let mut batch = Batch::new();
+    for i in 0..3u8 {
+        batch.put_key_value_bytes(vec![1, i], vec![i; 4]);
+    }
+    let stats = store.write_batch_with_stats(batch).await.unwrap();
+    assert_eq!(stats.puts, 3);
+    assert_eq!(stats.bytes_written, 3 * (2 + 4));
+    assert_eq!(stats.round_trips, 1);
+
+    let mut batch = Batch::new();
+    batch.delete_key_prefix(vec![1]);
+    batch.delete_key(vec![2]);
+    let stats = store.write_batch_with_stats(batch).await.unwrap();
+    assert_eq!(stats.puts, 0);
+    assert_eq!(stats.deletes, 1);
+    assert_eq!(stats.delete_prefixes_expanded, vec![3]);
+}
+
+#[tokio::test]
+async fn test_counting_store_memory() {
+    use linera_views::{
+        counting::{CountingStore, StoreMetricsSnapshot},
+        store::{AdminKeyValueStore as _, ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    let store = CountingStore::new(MemoryStore::new_test_store().await.unwrap());
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1, 0], vec![0; 10]);
+    batch.put_key_value_bytes(vec![1, 1], vec![1; 20]);
+    store.write_batch(batch).await.unwrap();
+    let mut batch = Batch::new();
+    batch.delete_key(vec![2]);
+    store.write_batch(batch).await.unwrap();
+
+    let value = store.read_value_bytes(&[1, 0]).await.unwrap();
+    assert_eq!(value, Some(vec![0; 10]));
+    let values = store
+        .read_multi_values_bytes(vec![vec![1, 1], vec![3]])
+        .await
+        .unwrap();
+    assert_eq!(values, vec![Some(vec![1; 20]), None]);
+    let keys = store
+        .find_keys_by_prefix_with_options(&[1], Order::Forward)
+        .await
+        .unwrap();
+    assert_eq!(keys, vec![vec![0], vec![1]]);
+
+    let other_store = store.clone_with_root_key(&[5]).unwrap();
+    assert!(!other_store.contains_key(&[1, 0]).await.unwrap());
+
+    let snapshot = store.metrics().snapshot();
+    assert_eq!(snapshot.read_latency.count(), 4);
+    assert_eq!(snapshot.write_latency.count(), 2);
+    let read_latency = snapshot.read_latency.clone();
+    let write_latency = snapshot.write_latency.clone();
+    assert_eq!(
+        snapshot,
+        StoreMetricsSnapshot {
+            reads: 3,
+            keys_read: 4,
+            prefix_scans: 1,
+            batch_writes: 2,
+            bytes_read: 10 + 20 + 2,
+            bytes_written: (2 + 10) + (2 + 20) + 1,
+            read_latency,
+            write_latency,
+        }
+    );
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn test_counting_store_prometheus_export() {
+    use linera_views::{counting::CountingStore, store::WritableKeyValueStore as _};
+    let store = CountingStore::new(MemoryStore::new_test_store().await.unwrap());
+    store
+        .metrics()
+        .register("test_counting_store_export")
+        .unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1], vec![2; 3]);
+    store.write_batch(batch).await.unwrap();
+
+    let families = prometheus::gather();
+    let family = |name: &str| {
+        families
+            .iter()
+            .find(|family| family.get_name() == name)
+            .unwrap()
+    };
+    let batch_writes = family("test_counting_store_export_batch_writes");
+    assert_eq!(batch_writes.get_metric()[0].get_counter().get_value(), 1.0);
+    let bytes_written = family("test_counting_store_export_bytes_written");
+    assert_eq!(bytes_written.get_metric()[0].get_counter().get_value(), 4.0);
+    let write_latency = family("test_counting_store_export_write_latency");
+    assert_eq!(
+        write_latency.get_metric()[0]
+            .get_histogram()
+            .get_sample_count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_reads_and_writes_mirroring_memory() {
+    use linera_views::mirroring::MirroringStore;
+    for scenario in get_random_test_scenarios() {
+        let store = MirroringStore::<MemoryStore, MemoryStore>::new_test_store()
+            .await
+            .unwrap();
+        run_reads(store, scenario).await;
+    }
+    let store = MirroringStore::<MemoryStore, MemoryStore>::new_test_store()
+        .await
+        .unwrap();
+    run_writes_from_blank(&store).await;
+    assert!(store.verify_consistency(&[]).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_mirroring_secondary_failures() {
+    use linera_views::{
+        mirroring::{MirroringStore, MirroringStoreError, SecondaryFailurePolicy},
+        read_only::ReadOnlyStore,
+        store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    // A read-only store fails on every write, which stands for a faulty secondary store.
+    let primary = MemoryStore::new_test_store().await.unwrap();
+    let secondary = MemoryStore::new_test_store().await.unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0, 1], vec![1]);
+    batch.put_key_value_bytes(vec![0, 2], vec![2]);
+    primary.write_batch(batch.clone()).await.unwrap();
+    batch.put_key_value_bytes(vec![0, 2], vec![3]);
+    secondary.write_batch(batch).await.unwrap();
+
+    let store = MirroringStore::new(
+        primary.clone(),
+        ReadOnlyStore::new(secondary.clone()),
+        SecondaryFailurePolicy::Fatal,
+    );
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0, 3], vec![3]);
+    let error = store.write_batch(batch.clone()).await.unwrap_err();
+    assert!(matches!(error, MirroringStoreError::Secondary(_)));
+    // The primary store was written before the secondary store failed.
+    assert_eq!(
+        store.read_value_bytes(&[0, 3]).await.unwrap(),
+        Some(vec![3])
+    );
+
+    let store = MirroringStore::new(
+        primary,
+        ReadOnlyStore::new(secondary),
+        SecondaryFailurePolicy::Log,
+    );
+    batch.put_key_value_bytes(vec![0, 4], vec![4]);
+    store.write_batch(batch).await.unwrap();
+    let divergent_keys = store.verify_consistency(&[0]).await.unwrap();
+    assert_eq!(divergent_keys, vec![vec![0, 2], vec![0, 3], vec![0, 4]]);
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_reads_rocks_db() {
+    for scenario in get_random_test_scenarios() {
+        let store = linera_views::rocks_db::RocksDbStore::new_test_store()
+            .await
+            .unwrap();
+        run_reads(store, scenario).await;
+    }
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_reads_dynamo_db() {
+    for scenario in get_random_test_scenarios() {
+        let store = linera_views::dynamo_db::DynamoDbStore::new_test_store()
+            .await
+            .unwrap();
+        run_reads(store, scenario).await;
+    }
+}
+
+#[cfg(with_scylladb)]
+#[tokio::test]
+async fn test_reads_scylla_db() {
+    for scenario in get_random_test_scenarios() {
+        let store = linera_views::scylla_db::ScyllaDbStore::new_test_store()
+            .await
+            .unwrap();
+        run_reads(store, scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_reads_with_order_memory() {
+    for scenario in get_random_test_scenarios() {
+        let store = MemoryStore::new_test_store().await.unwrap();
+        run_reads_with_order(store, scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_reads_with_order_test_memory() {
+    for scenario in get_random_test_scenarios() {
+        let key_value_store = create_value_splitting_memory_store();
+        run_reads_with_order(key_value_store, scenario).await;
+    }
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_reads_with_order_rocks_db() {
+    for scenario in get_random_test_scenarios() {
+        let store = linera_views::rocks_db::RocksDbStore::new_test_store()
+            .await
+            .unwrap();
+        run_reads_with_order(store, scenario).await;
+    }
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_reads_with_order_dynamo_db() {
+    for scenario in get_random_test_scenarios() {
+        let store = linera_views::dynamo_db::DynamoDbStore::new_test_store()
+            .await
+            .unwrap();
+        run_reads_with_order(store, scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_find_keys_in_range_memory() {
+    for scenario in get_random_test_scenarios() {
+        let store = MemoryStore::new_test_store().await.unwrap();
+        run_find_keys_in_range(store, scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_find_keys_in_range_test_memory() {
+    for scenario in get_random_test_scenarios() {
+        let key_value_store = create_value_splitting_memory_store();
+        run_find_keys_in_range(key_value_store, scenario).await;
+    }
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_find_keys_in_range_rocks_db() {
+    for scenario in get_random_test_scenarios() {
+        let store = linera_views::rocks_db::RocksDbStore::new_test_store()
+            .await
+            .unwrap();
+        run_find_keys_in_range(store, scenario).await;
+    }
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_find_keys_in_range_dynamo_db() {
+    for scenario in get_random_test_scenarios() {
+        let store = linera_views::dynamo_db::DynamoDbStore::new_test_store()
+            .await
+            .unwrap();
+        run_find_keys_in_range(store, scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_count_keys_by_prefix_memory() {
+    let store = MemoryStore::new_test_store().await.unwrap();
+    run_count_keys_by_prefix(store, 100, 10).await;
+}
+
+#[tokio::test]
+async fn test_count_keys_by_prefix_test_memory() {
+    let key_value_store = create_value_splitting_memory_store();
+    run_count_keys_by_prefix(key_value_store, 100, 10).await;
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_count_keys_by_prefix_dynamo_db() {
+    use linera_views::{dynamo_db::DynamoDbStoreInternal, journaling::JournalingKeyValueStore};
+
+    // DynamoDB reads at most 1MB per page, so 4MB of values make the count span pages.
+    let store = JournalingKeyValueStore::<DynamoDbStoreInternal>::new_test_store()
+        .await
+        .unwrap();
+    run_count_keys_by_prefix(store, 40, 100_000).await;
+    let store = linera_views::dynamo_db::DynamoDbStore::new_test_store()
+        .await
+        .unwrap();
+    run_count_keys_by_prefix(store, 40, 100_000).await;
+}
+
+#[tokio::test]
+async fn test_prefix_streams_memory() {
+    for scenario in get_random_test_scenarios() {
+        let store = MemoryStore::new_test_store().await.unwrap();
+        run_prefix_streams(store, scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_prefix_streams_test_memory() {
+    for scenario in get_random_test_scenarios() {
+        let key_value_store = create_value_splitting_memory_store();
+        run_prefix_streams(key_value_store, scenario).await;
+    }
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_prefix_streams_dynamo_db() {
+    use linera_views::{dynamo_db::DynamoDbStoreInternal, journaling::JournalingKeyValueStore};
+
+    for scenario in get_random_test_scenarios() {
+        let store = linera_views::dynamo_db::DynamoDbStore::new_test_store()
+            .await
+            .unwrap();
+        run_prefix_streams(store, scenario).await;
+    }
+    // DynamoDB returns at most 1MB per page, so these values are streamed in several pages.
+    let key_values = (0..40u8)
+        .map(|index| (vec![0, index], vec![index; 100_000]))
+        .collect();
+    let store = JournalingKeyValueStore::<DynamoDbStoreInternal>::new_test_store()
+        .await
+        .unwrap();
+    run_prefix_streams(store, key_values).await;
+}
+
+#[tokio::test]
+async fn test_dump_and_restore_memory() {
+    for scenario in get_random_test_scenarios() {
+        let store1 = create_value_splitting_memory_store();
+        let store2 = MemoryStore::new_test_store().await.unwrap();
+        run_dump_and_restore(store1, store2, scenario).await;
+    }
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_dump_and_restore_dynamo_db() {
+    for scenario in get_random_test_scenarios() {
+        let store1 = linera_views::dynamo_db::DynamoDbStore::new_test_store()
+            .await
+            .unwrap();
+        let store2 = MemoryStore::new_test_store().await.unwrap();
+        run_dump_and_restore(store1, store2, scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_dump_and_restore_rejects_unknown_version() {
+    use linera_views::{store::WritableKeyValueStore as _, views::ViewError};
+
+    let store = MemoryStore::new_test_store().await.unwrap();
+    let mut dump = b"LINERAKV".to_vec();
+    dump.push(2);
+    let error = store.restore_from_reader(&dump[..]).await.unwrap_err();
+    assert!(matches!(error, ViewError::IoError(_)));
+}
+
+#[tokio::test]
+async fn test_dump_and_restore_rejects_corrupted_lengths() {
+    use std::io::ErrorKind;
+
+    use linera_views::{
+        store::WritableKeyValueStore as _, value_splitting::LimitedTestMemoryStore,
+        views::ViewError,
+    };
+
+    // A dump with a key of 2 bytes and a value of 100 bytes, whose lengths may be corrupted.
+    let dump = |key_len: u32, value_len: u32| {
+        let mut dump = b"LINERAKV".to_vec();
+        dump.push(1);
+        dump.extend(key_len.to_le_bytes());
+        dump.extend([1, 2]);
+        dump.extend(value_len.to_le_bytes());
+        dump.extend([0; 100]);
+        dump
+    };
+    let error_kind = |error: ViewError| match error {
+        ViewError::IoError(error) => error.kind(),
+        error => panic!("unexpected error {error}"),
+    };
+    // The huge lengths fail on the end of the dump instead of being allocated.
+    let store = MemoryStore::new_test_store().await.unwrap();
+    for (key_len, value_len) in [(u32::MAX, 100), (2, u32::MAX)] {
+        let error = store
+            .restore_from_reader(&dump(key_len, value_len)[..])
+            .await
+            .unwrap_err();
+        assert_eq!(error_kind(error), ErrorKind::UnexpectedEof);
+    }
+    // The values that the store cannot hold are rejected before being read.
+    let store = LimitedTestMemoryStore::new();
+    let error = store
+        .restore_from_reader(&dump(2, 101)[..])
+        .await
+        .unwrap_err();
+    assert_eq!(error_kind(error), ErrorKind::InvalidData);
+    assert_eq!(
+        store.restore_from_reader(&dump(2, 100)[..]).await.unwrap(),
+        1
+    );
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[tokio::test]
+async fn test_memory_snapshot_files() {
+    use linera_views::{
+        memory::MemoryStoreError,
+        store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    use rand::Rng as _;
+
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("store.snapshot");
+
+    // An empty store.
+    let store = MemoryStore::new_test_store().await.unwrap();
+    store.save_to_file(&path).unwrap();
+    let loaded = MemoryStore::new_test_store().await.unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1], vec![1]);
+    loaded.write_batch(batch).await.unwrap();
+    loaded.load_from_file(&path).unwrap();
+    assert!(loaded.snapshot().is_empty());
+
+    // Multi-megabyte values.
+    let mut rng = make_deterministic_rng();
+    let large_value = (0..3_000_000).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0], large_value.clone());
+    batch.put_key_value_bytes(vec![0, 1], vec![]);
+    batch.put_key_value_bytes(vec![2, 3], vec![4; 5_000_000]);
+    store.write_batch(batch).await.unwrap();
+    store.save_to_file(&path).unwrap();
+    loaded.load_from_file(&path).unwrap();
+    assert_eq!(loaded.snapshot(), store.snapshot());
+    assert_eq!(loaded.snapshot().len(), 3);
+    let value = loaded.read_value_bytes(&[0]).await.unwrap();
+    assert_eq!(value, Some(large_value));
+
+    // Files of another version are rejected.
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[8] = 2;
+    std::fs::write(&path, &bytes).unwrap();
+    let error = loaded.load_from_file(&path).unwrap_err();
+    assert!(matches!(
+        error,
+        MemoryStoreError::SnapshotVersionMismatch {
+            found: 2,
+            expected: 1
+        }
+    ));
+    std::fs::write(&path, b"not a snapshot").unwrap();
+    let error = loaded.load_from_file(&path).unwrap_err();
+    assert!(matches!(error, MemoryStoreError::NotASnapshotFile));
+    // The store is unchanged by the failed loads.
+    assert_eq!(loaded.snapshot(), store.snapshot());
+}
+
+#[tokio::test]
+async fn test_memory_snapshot_and_restore() {
+    use linera_views::store::{ReadableKeyValueStore as _, WritableKeyValueStore as _};
+
+    let store = MemoryStore::new_test_store().await.unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1], vec![10]);
+    store.write_batch(batch).await.unwrap();
+    let snapshot = store.snapshot();
+
+    let mut batch = Batch::new();
+    batch.delete_key(vec![1]);
+    batch.put_key_value_bytes(vec![2], vec![20]);
+    store.write_batch(batch).await.unwrap();
+    assert_eq!(store.read_value_bytes(&[1]).await.unwrap(), None);
+
+    store.restore(&snapshot);
+    assert_eq!(store.read_value_bytes(&[1]).await.unwrap(), Some(vec![10]));
+    assert_eq!(store.read_value_bytes(&[2]).await.unwrap(), None);
+
+    // Restoring an empty snapshot clears the store.
+    store.restore(&Default::default());
+    assert!(store.snapshot().is_empty());
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_memory_store_shared_between_tasks() {
+    use linera_views::store::{ReadableKeyValueStore as _, WritableKeyValueStore as _};
+
+    let store = MemoryStore::new_test_store().await.unwrap();
+    let writer = {
+        let store = store.clone();
+        tokio::spawn(async move {
+            for round in 0..200u8 {
+                // Each batch replaces all the values, so that a scan must see them equal.
+                let mut batch = Batch::new();
+                batch.delete_key_prefix(vec![0]);
+                for i in 0..16u8 {
+                    batch.put_key_value_bytes(vec![0, i], vec![round]);
+                }
+                store.write_batch(batch).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+        })
+    };
+    let scanner = {
+        let store = store.clone();
+        tokio::spawn(async move {
+            for _ in 0..200 {
+                let key_values = store.find_key_values_by_prefix(&[0]).await.unwrap();
+                assert!(key_values.is_empty() || key_values.len() == 16);
+                assert!(key_values.windows(2).all(|pair| pair[0].1 == pair[1].1));
+                tokio::task::yield_now().await;
+            }
+        })
+    };
+    writer.await.unwrap();
+    scanner.await.unwrap();
+
+    // The writes of the tasks are seen by the original store.
+    let key_values = store.find_key_values_by_prefix(&[0]).await.unwrap();
+    assert_eq!(key_values.len(), 16);
+    assert!(key_values.iter().all(|(_, value)| value == &vec![199]));
+}
+
+#[tokio::test]
+async fn test_memory_store_new_isolated() {
+    use linera_views::store::{ReadableKeyValueStore as _, WritableKeyValueStore as _};
+
+    let store = MemoryStore::new_test_store().await.unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1], vec![10]);
+    store.write_batch(batch).await.unwrap();
+
+    let shared = store.clone();
+    let isolated = store.new_isolated();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![2], vec![20]);
+    shared.write_batch(batch).await.unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![3], vec![30]);
+    isolated.write_batch(batch).await.unwrap();
+
+    assert_eq!(
+        store.find_keys_by_prefix(&[]).await.unwrap(),
+        vec![vec![1], vec![2]]
+    );
+    assert_eq!(
+        isolated.find_keys_by_prefix(&[]).await.unwrap(),
+        vec![vec![1], vec![3]]
+    );
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[tokio::test]
+async fn test_memory_store_latency() {
+    use std::time::{Duration, Instant};
+
+    use linera_views::{
+        faulty::{FaultSchedule, FaultyStore, FaultyStoreError},
+        memory::{Latency, LatencyConfig, MemoryStoreError},
+        store::{AdminKeyValueStore as _, ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+
+    let delay = Duration::from_millis(50);
+    let latency = LatencyConfig {
+        read: Latency::Fixed(delay),
+        scan: Latency::Uniform {
+            min: delay,
+            max: 2 * delay,
+        },
+        write: Latency::Fixed(delay),
+    };
+    let store = MemoryStore::new_test_store()
+        .await
+        .unwrap()
+        .with_latency(latency)
+        .unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1, 2], vec![3]);
+    let start = Instant::now();
+    store.write_batch(batch).await.unwrap();
+    assert!(start.elapsed() >= delay);
+    let start = Instant::now();
+    assert_eq!(
+        store.read_value_bytes(&[1, 2]).await.unwrap(),
+        Some(vec![3])
+    );
+    assert!(start.elapsed() >= delay);
+    let start = Instant::now();
+    assert_eq!(
+        store.find_keys_by_prefix(&[1]).await.unwrap(),
+        vec![vec![2]]
+    );
+    assert!(start.elapsed() >= delay);
+
+    // The stores for other root keys have the same latency.
+    let other_store = store.clone_with_root_key(&[7]).unwrap();
+    let start = Instant::now();
+    assert_eq!(other_store.read_value_bytes(&[1, 2]).await.unwrap(), None);
+    assert!(start.elapsed() >= delay);
+
+    // The latency composes with the injection of faults.
+    let schedule = FaultSchedule::default().with_failing_write_batch(1);
+    let faulty_store = FaultyStore::new(store, schedule);
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![4], vec![5]);
+    let error = faulty_store.write_batch(batch.clone()).await.unwrap_err();
+    assert!(matches!(
+        error,
+        FaultyStoreError::<MemoryStoreError>::InjectedFault
+    ));
+    let start = Instant::now();
+    faulty_store.write_batch(batch).await.unwrap();
+    assert!(start.elapsed() >= delay);
+    assert_eq!(
+        faulty_store.read_value_bytes(&[4]).await.unwrap(),
+        Some(vec![5])
+    );
+
+    // The bounds of a uniform latency must be in order.
+    let latency = LatencyConfig {
+        write: Latency::Uniform {
+            min: 2 * delay,
+            max: delay,
+        },
+        ..LatencyConfig::default()
+    };
+    let result = MemoryStore::new_test_store()
+        .await
+        .unwrap()
+        .with_latency(latency);
+    assert!(matches!(
+        result,
+        Err(MemoryStoreError::InvalidLatency { min, max }) if min == 2 * delay && max == delay
+    ));
+}
+
+#[tokio::test]
+async fn test_compare_and_put_memory() {
+    let store = MemoryStore::new_test_store().await.unwrap();
+    run_compare_and_put(store).await;
+}
+
+#[tokio::test]
+async fn test_compare_and_put_test_memory() {
+    let key_value_store = create_value_splitting_memory_store();
+    run_compare_and_put(key_value_store).await;
+}
+
+#[tokio::test]
+async fn test_compare_and_put_lru_caching() {
+    use linera_views::lru_caching::LruCachingStore;
+
+    let store = MemoryStore::new_test_store().await.unwrap();
+    run_compare_and_put(LruCachingStore::new(store, 10)).await;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_lru_caching_concurrent_reads_and_writes() {
+    use linera_views::{
+        lru_caching::LruCachingStore,
+        store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+
+    const NUM_KEYS: u8 = 4;
+    const NUM_VALUES: u32 = 200;
+    let inner_store = MemoryStore::new_test_store().await.unwrap();
+    // The cache is smaller than the set of keys, so that entries keep being evicted.
+    let store = LruCachingStore::new(inner_store.clone(), 2);
+    let mut handles = Vec::new();
+    for key in 0..NUM_KEYS {
+        let store = store.clone();
+        handles.push(tokio::spawn(async move {
+            for value in 0..NUM_VALUES {
+                let mut batch = Batch::new();
+                batch.put_key_value_bytes(vec![key], value.to_le_bytes().to_vec());
+                store.write_batch(batch).await.unwrap();
+            }
+        }));
+    }
+    for _ in 0..4 {
+        let store = store.clone();
+        handles.push(tokio::spawn(async move {
+            // A key never goes back to an older value.
+            let mut last_values = [0; NUM_KEYS as usize];
+            for i in 0..NUM_VALUES as usize {
+                let key = i % NUM_KEYS as usize;
+                if let Some(value) = store.read_value_bytes(&[key as u8]).await.unwrap() {
+                    let value = u32::from_le_bytes(value.try_into().unwrap());
+                    assert!(value >= last_values[key]);
+                    last_values[key] = value;
+                }
+            }
+        }));
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    // No stale value remains in the cache.
+    for key in 0..NUM_KEYS {
+        let expected = Some((NUM_VALUES - 1).to_le_bytes().to_vec());
+        assert_eq!(
+            inner_store.read_value_bytes(&[key]).await.unwrap(),
+            expected
+        );
+        assert_eq!(store.read_value_bytes(&[key]).await.unwrap(), expected);
+        let values = store
+            .read_multi_values_bytes(vec![vec![key]])
+            .await
+            .unwrap();
+        assert_eq!(values, vec![expected]);
+    }
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_compare_and_put_dynamo_db() {
+    let store = linera_views::dynamo_db::DynamoDbStore::new_test_store()
+        .await
+        .unwrap();
+    run_compare_and_put(store).await;
+}
+
+#[tokio::test]
+async fn test_read_value_bytes_or_err_memory() {
+    use linera_views::{
+        store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
+        views::ViewError,
+    };
+
+    let store = MemoryStore::new_test_store().await.unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1, 2], vec![3]);
+    store.write_batch(batch).await.unwrap();
+    assert_eq!(
+        store.read_value_bytes_or_err(&[1, 2]).await.unwrap(),
+        vec![3]
+    );
+    let error = store.read_value_bytes_or_err(&[1, 171]).await.unwrap_err();
+    assert!(matches!(&error, ViewError::KeyNotFound(key) if key == &[1, 171]));
+    assert_eq!(error.to_string(), "Key not found in storage: 01ab");
+}
+
+#[tokio::test]
+async fn test_find_keys_by_prefix_paginated_memory() {
+    for scenario in get_random_test_scenarios() {
+        let store = MemoryStore::new_test_store().await.unwrap();
+        run_find_keys_by_prefix_paginated(store, scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_find_keys_by_prefix_paginated_test_memory() {
+    for scenario in get_random_test_scenarios() {
+        let key_value_store = create_value_splitting_memory_store();
+        run_find_keys_by_prefix_paginated(key_value_store, scenario).await;
+    }
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_find_keys_by_prefix_paginated_dynamo_db() {
+    for scenario in get_random_test_scenarios() {
+        let store = linera_views::dynamo_db::DynamoDbStore::new_test_store()
+            .await
+            .unwrap();
+        run_find_keys_by_prefix_paginated(store, scenario).await;
+    }
+}
+
+#[cfg(with_indexeddb)]
+#[wasm_bindgen_test]
+async fn test_reads_indexed_db() {
+    for scenario in get_random_test_scenarios() {
+        let key_value_store = linera_views::indexed_db::create_indexed_db_test_store().await;
+        run_reads(key_value_store, scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_reads_key_value_store_view_memory() {
+    for scenario in get_random_test_scenarios() {
+        let context = create_test_memory_context();
+        let key_value_store = ViewContainer::new(context).await.unwrap();
+        run_reads(key_value_store, scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_specific_reads_memory() {
+    let store = MemoryStore::new_test_store().await.unwrap();
+    let key_values = vec![
+        (vec![0, 1, 255], Vec::new()),
+        (vec![0, 1, 255, 37], Vec::new()),
+        (vec![0, 2], Vec::new()),
+        (vec![0, 2, 0], Vec::new()),
+    ];
+    run_reads(store, key_values).await;
+}
+
+#[tokio::test]
+async fn test_test_memory_writes_from_blank() {
+    let key_value_store = create_value_splitting_memory_store();
+    run_writes_from_blank(&key_value_store).await;
+}
+
+#[tokio::test]
+async fn test_memory_writes_from_blank() {
+    let store = MemoryStore::new_test_store().await.unwrap();
+    run_writes_from_blank(&store).await;
+}
+
+#[tokio::test]
+async fn test_key_value_store_view_memory_writes_from_blank() {
+    let context = create_test_memory_context();
+    let key_value_store = ViewContainer::new(context).await.unwrap();
+    run_writes_from_blank(&key_value_store).await;
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_rocks_db_writes_from_blank() {
+    let store = linera_views::rocks_db::RocksDbStore::new_test_store()
+        .await
+        .unwrap();
+    run_writes_from_blank(&store).await;
+}
+
+#[cfg(all(with_dynamodb, with_metrics))]
+#[tokio::test]
+async fn test_dynamo_db_tracing_spans() {
+    use std::sync::{Arc, Mutex};
+
+    use linera_views::store::{ReadableKeyValueStore as _, WritableKeyValueStore as _};
+    use tracing::{span, Subscriber};
+    use tracing_subscriber::{
+        layer::{Context, SubscriberExt as _},
+        registry::LookupSpan,
+        Layer,
+    };
+
+    /// Records the names of the spans being created.
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<&'static str>>>);
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanRecorder {
+        fn on_new_span(
+            &self,
+            attributes: &span::Attributes<'_>,
+            _id: &span::Id,
+            _ctx: Context<'_, S>,
+        ) {
+            self.0.lock().unwrap().push(attributes.metadata().name());
+        }
+    }
+
+    let recorder = SpanRecorder::default();
+    let subscriber = tracing_subscriber::registry().with(recorder.clone());
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let store = linera_views::dynamo_db::DynamoDbStore::new_test_store()
+        .await
+        .unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1, 2], vec![3]);
+    store.write_batch(batch).await.unwrap();
+    store.read_value_bytes(&[1, 3]).await.unwrap();
+    store.find_keys_by_prefix(&[1]).await.unwrap();
+    store.find_key_values_by_prefix(&[1]).await.unwrap();
+
+    let names = recorder.0.lock().unwrap().clone();
+    for name in [
+        "dynamo_db_write_batch",
+        "dynamo_db_read_value_bytes",
+        "dynamo_db_find_keys_by_prefix",
+        "dynamo_db_find_key_values_by_prefix",
+    ] {
+        assert!(names.contains(&name), "missing span {name}");
+    }
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_dynamo_db_write_batch_with_byte_budget() {
+    use linera_views::{
+        dynamo_db::DynamoDbStore,
+        random::generate_test_namespace,
+        store::{AdminKeyValueStore as _, ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+
+    let config = DynamoDbStore::new_test_config()
+        .await
+        .unwrap()
+        .with_max_batch_total_size(1_000_000);
+    let namespace = generate_test_namespace();
+    let store = DynamoDbStore::recreate_and_connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    // Far fewer items than the transaction limit, but too many bytes for one transaction.
+    let mut batch = Batch::new();
+    for i in 0..5u8 {
+        batch.put_key_value_bytes(vec![1, i], vec![i; 300_000]);
+    }
+    store.write_batch(batch).await.unwrap();
+    // Reading from a new connection to bypass the cache.
+    let store = DynamoDbStore::connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    for i in 0..5u8 {
+        let value = store.read_value_bytes(&[1, i]).await.unwrap();
+        assert_eq!(value, Some(vec![i; 300_000]));
+    }
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_dynamo_db_static_credentials() {
+    use aws_sdk_dynamodb::config::{Credentials, Region};
+    use linera_views::{
+        dynamo_db::{get_config_with_credentials, DynamoDbStore, DynamoDbStoreConfig},
+        random::generate_test_namespace,
+        store::{
+            AdminKeyValueStore as _, CommonStoreConfig, ReadableKeyValueStore as _,
+            WritableKeyValueStore as _,
+        },
+    };
+
+    let credentials = Credentials::new("test", "test", None, None, "static");
+    let use_localstack = true;
+    let config =
+        get_config_with_credentials(credentials, Region::new("us-east-1"), use_localstack).unwrap();
+    let config = DynamoDbStoreConfig::new(config, CommonStoreConfig::default());
+    let namespace = generate_test_namespace();
+    let store = DynamoDbStore::recreate_and_connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1, 2], vec![3]);
+    store.write_batch(batch).await.unwrap();
+    let store = DynamoDbStore::connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    assert_eq!(
+        store.read_value_bytes(&[1, 2]).await.unwrap(),
+        Some(vec![3])
+    );
+    DynamoDbStore::delete(&config, &namespace).await.unwrap();
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_dynamo_db_table_options() {
+    use aws_sdk_dynamodb::{types::PointInTimeRecoveryStatus, Client};
+    use linera_views::{
+        dynamo_db::{DynamoDbStore, DynamoDbStoreConfig, TableOptions},
+        localstack,
+        random::generate_test_namespace,
+        store::{AdminKeyValueStore as _, CommonStoreConfig},
+    };
+
+    let aws_config = localstack::get_test_config().await.unwrap();
+    let client = Client::from_conf(aws_config.clone());
+    let table_options = TableOptions {
+        point_in_time_recovery: Some(true),
+        tags: vec![("team".to_string(), "storage".to_string())],
+    };
+    let config = DynamoDbStoreConfig::new(aws_config, CommonStoreConfig::default())
+        .with_table_options(table_options);
+    let namespace = generate_test_namespace();
+    DynamoDbStore::recreate_and_connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    // Connecting again applies the options to the existing table without failing.
+    DynamoDbStore::connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+
+    let backups = client
+        .describe_continuous_backups()
+        .table_name(&namespace)
+        .send()
+        .await
+        .unwrap();
+    let status = backups
+        .continuous_backups_description
+        .and_then(|description| description.point_in_time_recovery_description)
+        .and_then(|description| description.point_in_time_recovery_status);
+    assert_eq!(status, Some(PointInTimeRecoveryStatus::Enabled));
+
+    let table = client
+        .describe_table()
+        .table_name(&namespace)
+        .send()
+        .await
+        .unwrap()
+        .table
+        .unwrap();
+    let tags = client
+        .list_tags_of_resource()
+        .resource_arn(table.table_arn.unwrap())
+        .send()
+        .await
+        .unwrap()
+        .tags
+        .unwrap_or_default();
+    let tags = tags
+        .into_iter()
+        .map(|tag| (tag.key, tag.value))
+        .collect::<Vec<_>>();
+    assert_eq!(tags, vec![("team".to_string(), "storage".to_string())]);
+    DynamoDbStore::delete(&config, &namespace).await.unwrap();
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_dynamo_db_connect_existing() {
+    use linera_views::{
+        dynamo_db::{
+            DynamoDbStore, DynamoDbStoreConfig, DynamoDbStoreError, DynamoDbStoreInternalError,
+        },
+        localstack,
+        random::generate_test_namespace,
+        store::{AdminKeyValueStore as _, CommonStoreConfig},
+        value_splitting::ValueSplittingError,
+    };
+
+    let config = localstack::get_test_config().await.unwrap();
+    let config = DynamoDbStoreConfig::new(config, CommonStoreConfig::default());
+    let namespace = generate_test_namespace();
+    let error = DynamoDbStore::connect_existing(&config, &namespace, &[])
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(
+        error,
+        ValueSplittingError::InnerStoreError(DynamoDbStoreInternalError::TableMissing(ref table))
+            if *table == namespace
+    ));
+    // The missing table was not created.
+    assert!(!DynamoDbStore::exists(&config, &namespace).await.unwrap());
+
+    DynamoDbStore::create(&config, &namespace).await.unwrap();
+    let result: Result<_, DynamoDbStoreError> =
+        DynamoDbStore::connect_existing(&config, &namespace, &[]).await;
+    assert!(result.is_ok());
+    DynamoDbStore::delete(&config, &namespace).await.unwrap();
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_dynamo_db_write_batch_stats() {
+    use linera_views::{dynamo_db::DynamoDbStore, store::WritableKeyValueStore as _};
+    let store = DynamoDbStore::new_test_store().await.unwrap();
+    let mut batch = Batch::new();
+    for i in 0..3u8 {
+        batch.put_key_value_bytes(vec![1, i], vec![i; 4]);
+    }
+    let stats = store.write_batch_with_stats(batch).await.unwrap();
+    assert_eq!(stats.puts, 3);
+    assert_eq!(stats.round_trips, 1);
+    assert_eq!(stats.retries, 0);
+    assert!(stats.bytes_written > 0);
+
+    let mut batch = Batch::new();
+    batch.delete_key_prefix(vec![1]);
+    let stats = store.write_batch_with_stats(batch).await.unwrap();
+    assert_eq!(stats.delete_prefixes_expanded, vec![3]);
+    assert_eq!(stats.round_trips, 1);
+}
+
+/// Copies a few thousand entries from a memory store to `store`, then back to another
+/// memory store, and checks that they are preserved.
+async fn run_copy_store_round_trip<S>(store: S)
+where
+    S: linera_views::store::RestrictedKeyValueStore + Sync,
+{
+    use linera_views::{
+        common::copy_store,
+        store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    let num_entries = 3000u16;
+    let source = MemoryStore::new_test_store().await.unwrap();
+    let mut batch = Batch::new();
+    for i in 0..num_entries {
+        let key = [&[1], &i.to_be_bytes()[..]].concat();
+        batch.put_key_value_bytes(key, vec![i as u8; (i % 50) as usize + 1]);
+    }
+    batch.put_key_value_bytes(vec![2, 0], vec![0]);
+    source.write_batch(batch).await.unwrap();
+    // The values already in the destination are overwritten.
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1, 0, 0], vec![42; 10]);
+    store.write_batch(batch).await.unwrap();
+
+    let mut progress_calls = 0;
+    let stats = copy_store(&source, &store, &[1], 500, Some(100), |_| {
+        progress_calls += 1
+    })
+    .await
+    .unwrap();
+    assert_eq!(stats.keys_copied, num_entries as usize);
+    assert_eq!(stats.batches_written, 6);
+    assert_eq!(stats.keys_verified, 100);
+    assert_eq!(progress_calls, 6);
+    assert_eq!(store.read_value_bytes(&[2, 0]).await.unwrap(), None);
+
+    let destination = MemoryStore::new_test_store().await.unwrap();
+    let stats = copy_store(&store, &destination, &[1], 700, None, |_| {})
+        .await
+        .unwrap();
+    assert_eq!(stats.keys_copied, num_entries as usize);
+    assert_eq!(stats.keys_verified, 0);
+    let mut expected = source.find_key_values_by_prefix(&[]).await.unwrap();
+    expected.retain(|(key, _)| key[0] == 1);
+    let copied = destination.find_key_values_by_prefix(&[]).await.unwrap();
+    assert_eq!(copied, expected);
+
+    let error = copy_store(&store, &destination, &[1], 0, None, |_| {})
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        linera_views::views::ViewError::ZeroChunkSize
+    ));
+}
+
+#[tokio::test]
+async fn test_copy_store_memory() {
+    let store = MemoryStore::new_test_store().await.unwrap();
+    run_copy_store_round_trip(store).await;
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_copy_store_dynamo_db() {
+    use linera_views::dynamo_db::DynamoDbStore;
+    let store = DynamoDbStore::new_test_store().await.unwrap();
+    run_copy_store_round_trip(store).await;
+}
+
+/// Checks that the values put after the deletion of their prefix in the same batch
+/// survive it.
+async fn run_put_after_delete_prefix<S>(store: S)
+where
+    S: linera_views::store::RestrictedKeyValueStore + Sync,
+{
+    use linera_views::store::{ReadableKeyValueStore as _, WritableKeyValueStore as _};
+    let mut batch = Batch::new();
+    for i in 0..20u8 {
+        batch.put_key_value_bytes(vec![1, 2, i], vec![i]);
+    }
+    store.write_batch(batch).await.unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1, 2, 0], vec![100]);
+    batch.delete_key_prefix(vec![1, 2]);
+    for i in (0..20u8).step_by(2) {
+        batch.put_key_value_bytes(vec![1, 2, i], vec![i + 1]);
+    }
+    batch.delete_key(vec![1, 2, 4]);
+    store.write_batch(batch).await.unwrap();
+    let key_values = store
+        .find_key_values_by_prefix_with_options(&[1, 2], Order::Forward)
+        .await
+        .unwrap();
+    let expected = (0..20u8)
+        .step_by(2)
+        .filter(|i| *i != 4)
+        .map(|i| (vec![i], vec![i + 1]))
+        .collect::<Vec<_>>();
+    assert_eq!(key_values, expected);
+}
+
+#[tokio::test]
+async fn test_put_after_delete_prefix_memory() {
+    let store = MemoryStore::new_test_store().await.unwrap();
+    run_put_after_delete_prefix(store).await;
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_put_after_delete_prefix_dynamo_db() {
+    use linera_views::dynamo_db::DynamoDbStore;
+    let store = DynamoDbStore::new_test_store().await.unwrap();
+    run_put_after_delete_prefix(store).await;
+}
+
+/// Checks that a `DeleteRange` deletes the keys of the range that are stored or put
+/// earlier in the batch, but not the ones put after it.
+async fn run_delete_range<S>(store: S)
+where
+    S: linera_views::store::RestrictedKeyValueStore + Sync,
+{
+    use linera_views::store::{ReadableKeyValueStore as _, WritableKeyValueStore as _};
+    let mut batch = Batch::new();
+    for i in 0..20u8 {
+        batch.put_key_value_bytes(vec![1, 2, i], vec![i]);
     }
+    batch.put_key_value_bytes(vec![1, 2, 5, 0], vec![50]);
+    store.write_batch(batch).await.unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1, 2, 8, 1], vec![81]);
+    batch.delete_key_range(vec![1, 2, 5], vec![1, 2, 10]);
+    batch.put_key_value_bytes(vec![1, 2, 7], vec![70]);
+    batch.delete_key(vec![1, 2, 8]);
+    batch.delete_key_range(vec![1, 2, 15], vec![1, 2, 12]);
+    store.write_batch(batch).await.unwrap();
+    let key_values = store
+        .find_key_values_by_prefix_with_options(&[1, 2], Order::Forward)
+        .await
+        .unwrap();
+    let mut expected = (0..20u8)
+        .filter(|i| !(5..10).contains(i))
+        .map(|i| (vec![i], vec![i]))
+        .collect::<Vec<_>>();
+    expected.push((vec![7], vec![70]));
+    expected.sort();
+    assert_eq!(key_values, expected);
+}
+
+#[tokio::test]
+async fn test_delete_range_memory() {
+    let store = MemoryStore::new_test_store().await.unwrap();
+    run_delete_range(store).await;
+}
+
+#[tokio::test]
+async fn test_delete_range_value_splitting_memory() {
+    let store = create_value_splitting_memory_store();
+    run_delete_range(store).await;
 }
 
 #[cfg(with_rocksdb)]
 #[tokio::test]
-async fn test_reads_rocks_db() {
-    for scenario in get_random_test_scenarios() {
-        let store = linera_views::rocks_db::RocksDbStore::new_test_store()
-            .await
-            .unwrap();
-        run_reads(store, scenario).await;
+async fn test_delete_range_rocks_db() {
+    use linera_views::rocks_db::RocksDbStore;
+    let store = RocksDbStore::new_test_store().await.unwrap();
+    run_delete_range(store).await;
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_rocks_db_failed_batch_is_not_applied() {
+    use linera_views::{
+        random::generate_test_namespace,
+        rocks_db::RocksDbStore,
+        store::{AdminKeyValueStore as _, ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    // The write crashes once half of the operations are in the native write batch.
+    let config = RocksDbStore::new_test_config()
+        .await
+        .unwrap()
+        .with_write_hook(|index| assert!(index < 50, "simulated crash"));
+    let namespace = generate_test_namespace();
+    let store = RocksDbStore::recreate_and_connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    let mut batch = Batch::new();
+    for index in 0..100u32 {
+        batch.put_key_value_bytes(index.to_be_bytes().to_vec(), vec![1]);
     }
+    batch.delete_key_prefix(vec![0, 0]);
+    assert!(store.write_batch(batch).await.is_err());
+    assert!(store.find_keys_by_prefix(&[]).await.unwrap().is_empty());
 }
 
-#[cfg(with_dynamodb)]
+#[cfg(with_rocksdb)]
 #[tokio::test]
-async fn test_reads_dynamo_db() {
-    for scenario in get_random_test_scenarios() {
-        let store = linera_views::dynamo_db::DynamoDbStore::new_test_store()
-            .await
-            .unwrap();
-        run_reads(store, scenario).await;
+async fn test_rocks_db_delete_unbounded_prefix_in_order() {
+    use linera_views::{
+        rocks_db::RocksDbStore,
+        store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    let store = RocksDbStore::new_test_store().await.unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![255, 255, 1], vec![1]);
+    store.write_batch(batch).await.unwrap();
+
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![255, 255, 2], vec![2]);
+    batch.put_key_value_bytes(vec![255, 254], vec![3]);
+    batch.delete_key_prefix(vec![255, 255]);
+    batch.put_key_value_bytes(vec![255, 255, 3], vec![4]);
+    store.write_batch(batch).await.unwrap();
+
+    let keys = store.find_keys_by_prefix(&[255]).await.unwrap();
+    assert_eq!(keys, vec![vec![254], vec![255, 3]]);
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_rocks_db_synced_batch_is_persisted() {
+    use linera_views::{
+        random::generate_test_namespace,
+        rocks_db::{RocksDbStore, RocksDbWriteOptions},
+        store::{AdminKeyValueStore as _, ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    let write_options = RocksDbWriteOptions {
+        sync: true,
+        ..RocksDbWriteOptions::default()
+    };
+    let config = RocksDbStore::new_test_config()
+        .await
+        .unwrap()
+        .with_write_options(write_options);
+    let namespace = generate_test_namespace();
+    let store = RocksDbStore::recreate_and_connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    let mut batch = Batch::new();
+    for index in 0..1000u32 {
+        batch.put_key_value_bytes(index.to_be_bytes().to_vec(), index.to_le_bytes().to_vec());
     }
+    store.write_batch(batch).await.unwrap();
+    drop(store);
+
+    let store = RocksDbStore::connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    assert_eq!(store.count_keys_by_prefix(&[]).await.unwrap(), 1000);
+    let value = store.read_value_bytes(&7u32.to_be_bytes()).await.unwrap();
+    assert_eq!(value, Some(7u32.to_le_bytes().to_vec()));
 }
 
-#[cfg(with_scylladb)]
+#[cfg(with_rocksdb)]
 #[tokio::test]
-async fn test_reads_scylla_db() {
-    for scenario in get_random_test_scenarios() {
-        let store = linera_views::scylla_db::ScyllaDbStore::new_test_store()
-            .await
-            .unwrap();
-        run_reads(store, scenario).await;
+async fn test_rocks_db_secondary_catches_up_with_primary() {
+    use linera_views::{
+        context::ViewContext,
+        random::generate_test_namespace,
+        register_view::RegisterView,
+        rocks_db::{RocksDbAccessMode, RocksDbStore, RocksDbStoreError, RocksDbStoreInternalError},
+        store::{AdminKeyValueStore as _, ReadableKeyValueStore as _, WritableKeyValueStore as _},
+        views::View as _,
+    };
+
+    let config = RocksDbStore::new_test_config().await.unwrap();
+    let namespace = generate_test_namespace();
+    let primary = RocksDbStore::recreate_and_connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    let context = ViewContext::create_root_context(primary.clone(), ())
+        .await
+        .unwrap();
+    let mut view = RegisterView::<_, u64>::load(context).await.unwrap();
+    view.set(1);
+    let mut batch = Batch::new();
+    view.flush(&mut batch).unwrap();
+    primary.write_batch(batch).await.unwrap();
+
+    let secondary_dir = tempfile::TempDir::new().unwrap();
+    let secondary_config = config.with_access_mode(RocksDbAccessMode::Secondary {
+        secondary_path: secondary_dir.path().to_path_buf(),
+    });
+    let secondary = RocksDbStore::connect(&secondary_config, &namespace, &[])
+        .await
+        .unwrap();
+    let context = ViewContext::create_root_context(secondary.clone(), ())
+        .await
+        .unwrap();
+    let secondary_view = RegisterView::<_, u64>::load(context.clone()).await.unwrap();
+    assert_eq!(*secondary_view.get(), 1);
+
+    view.set(2);
+    let mut batch = Batch::new();
+    view.flush(&mut batch).unwrap();
+    primary.write_batch(batch).await.unwrap();
+    secondary.try_catch_up_with_primary().await.unwrap();
+    let secondary_view = RegisterView::<_, u64>::load(context).await.unwrap();
+    assert_eq!(*secondary_view.get(), 2);
+
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0], vec![0]);
+    let error = secondary.write_batch(batch).await.unwrap_err();
+    assert!(matches!(
+        error,
+        RocksDbStoreError::InnerStoreError(RocksDbStoreInternalError::ReadOnly)
+    ));
+    assert!(primary.read_value_bytes(&[0]).await.unwrap().is_none());
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_rocks_db_read_only_rejects_writes() {
+    use linera_views::{
+        random::generate_test_namespace,
+        rocks_db::{RocksDbAccessMode, RocksDbStore, RocksDbStoreError, RocksDbStoreInternalError},
+        store::{AdminKeyValueStore as _, ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+
+    let config = RocksDbStore::new_test_config().await.unwrap();
+    let namespace = generate_test_namespace();
+    let primary = RocksDbStore::recreate_and_connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1], vec![1]);
+    primary.write_batch(batch).await.unwrap();
+
+    let read_only_config = config.with_access_mode(RocksDbAccessMode::ReadOnly);
+    let store = RocksDbStore::connect(&read_only_config, &namespace, &[])
+        .await
+        .unwrap();
+    assert_eq!(store.read_value_bytes(&[1]).await.unwrap(), Some(vec![1]));
+    let mut batch = Batch::new();
+    batch.delete_key(vec![1]);
+    let error = store.write_batch(batch).await.unwrap_err();
+    assert!(matches!(
+        error,
+        RocksDbStoreError::InnerStoreError(RocksDbStoreInternalError::ReadOnly)
+    ));
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_rocks_db_backup_and_restore() {
+    use linera_views::{
+        random::generate_test_namespace,
+        rocks_db::{
+            PathWithGuard, RocksDbSpawnMode, RocksDbStore, RocksDbStoreConfig, RocksDbStoreInternal,
+        },
+        store::{
+            AdminKeyValueStore as _, CommonStoreConfig, ReadableKeyValueStore as _,
+            WritableKeyValueStore as _,
+        },
+    };
+
+    let config = RocksDbStore::new_test_config().await.unwrap();
+    let namespace = generate_test_namespace();
+    let store = RocksDbStore::recreate_and_connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    let mut batch = Batch::new();
+    for index in 0..10u8 {
+        batch.put_key_value_bytes(vec![index], vec![index]);
+    }
+    store.write_batch(batch).await.unwrap();
+
+    let backup_dir = tempfile::TempDir::new().unwrap();
+    let first_backup = store.create_backup(backup_dir.path()).await.unwrap();
+    assert!(first_backup.num_files > 0);
+    assert!(first_backup.size > 0);
+
+    let mut batch = Batch::new();
+    batch.delete_key(vec![0]);
+    for index in 10..20u8 {
+        batch.put_key_value_bytes(vec![index], vec![index]);
     }
+    store.write_batch(batch).await.unwrap();
+
+    let target = PathWithGuard::new_testing();
+    let target_dir = target.path_buf.join(&namespace);
+    let restored = RocksDbStoreInternal::restore_latest(backup_dir.path(), &target_dir).unwrap();
+    assert_eq!(restored, first_backup);
+    let spawn_mode = RocksDbSpawnMode::get_spawn_mode_from_runtime();
+    let restored_config = RocksDbStoreConfig::new(spawn_mode, target, CommonStoreConfig::default());
+    let restored_store = RocksDbStore::connect(&restored_config, &namespace, &[])
+        .await
+        .unwrap();
+    let keys = restored_store.find_keys_by_prefix(&[]).await.unwrap();
+    assert_eq!(keys, (0..10u8).map(|index| vec![index]).collect::<Vec<_>>());
+
+    let second_backup = store.create_backup(backup_dir.path()).await.unwrap();
+    let backups = RocksDbStoreInternal::list_backups(backup_dir.path()).unwrap();
+    assert_eq!(backups, vec![first_backup, second_backup]);
+    RocksDbStoreInternal::purge_old_backups(backup_dir.path(), 1).unwrap();
+    let backups = RocksDbStoreInternal::list_backups(backup_dir.path()).unwrap();
+    assert_eq!(backups, vec![second_backup]);
 }
 
-#[cfg(with_indexeddb)]
-#[wasm_bindgen_test]
-async fn test_reads_indexed_db() {
-    for scenario in get_random_test_scenarios() {
-        let key_value_store = linera_views::indexed_db::create_indexed_db_test_store().await;
-        run_reads(key_value_store, scenario).await;
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_rocks_db_statistics() {
+    use linera_views::{
+        random::generate_test_namespace,
+        rocks_db::{RocksDbConfig, RocksDbStore},
+        store::{AdminKeyValueStore as _, ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    };
+
+    let db_config = RocksDbConfig {
+        enable_statistics: true,
+        ..RocksDbConfig::default()
+    };
+    let config = RocksDbStore::new_test_config()
+        .await
+        .unwrap()
+        .with_db_config(db_config);
+    let namespace = generate_test_namespace();
+    let store = RocksDbStore::recreate_and_connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    for chunk in 0..10u32 {
+        let mut batch = Batch::new();
+        for index in 0..1000u32 {
+            let key = [chunk.to_be_bytes(), index.to_be_bytes()].concat();
+            batch.put_key_value_bytes(key, vec![0; 100]);
+        }
+        store.write_batch(batch).await.unwrap();
+    }
+    // Compacting flushes the memtable to SST files.
+    store.trigger_compaction(&[]).await.unwrap();
+    // A store with an empty LRU cache reads the values from the blocks.
+    let reader = store.clone_with_root_key(&[]).unwrap();
+    for chunk in 0..10u32 {
+        let key = [chunk.to_be_bytes(), 7u32.to_be_bytes()].concat();
+        assert!(reader.read_value_bytes(&key).await.unwrap().is_some());
     }
+
+    let statistics = store.statistics().await.unwrap();
+    assert!(statistics.num_live_sst_files > 0);
+    assert!(statistics.total_sst_files_size > 0);
+    assert!(statistics.estimated_num_keys > 0);
+    let hit_rate = statistics.block_cache_hit_rate.unwrap();
+    assert!((0.0..=1.0).contains(&hit_rate));
 }
 
+#[cfg(with_s3)]
 #[tokio::test]
-async fn test_reads_key_value_store_view_memory() {
-    for scenario in get_random_test_scenarios() {
-        let context = create_test_memory_context();
-        let key_value_store = ViewContainer::new(context).await.unwrap();
-        run_reads(key_value_store, scenario).await;
+async fn test_s3_read_value_stream() {
+    use linera_views::{
+        s3::S3StoreInternal,
+        store::{AdminKeyValueStore as _, WritableKeyValueStore as _},
+    };
+    let store = S3StoreInternal::new_test_store().await.unwrap();
+    let value = (0..8_000_000u32).map(|i| i as u8).collect::<Vec<_>>();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1], value.clone());
+    store.write_batch(batch).await.unwrap();
+
+    let mut stream = store.read_value_stream(&[1]).await.unwrap().unwrap();
+    let mut read_value = Vec::new();
+    while let Some(bytes) = stream.try_next().await.unwrap() {
+        read_value.extend_from_slice(&bytes);
     }
+    assert_eq!(read_value, value);
+    assert!(store.read_value_stream(&[2]).await.unwrap().is_none());
+
+    let other_store = store.clone_with_root_key(&[1]).unwrap();
+    assert!(other_store.read_value_stream(&[1]).await.unwrap().is_none());
 }
 
+#[cfg(with_dynamodb)]
 #[tokio::test]
-async fn test_specific_reads_memory() {
-    let store = MemoryStore::new_test_store().await.unwrap();
-    let key_values = vec![
-        (vec![0, 1, 255], Vec::new()),
-        (vec![0, 1, 255, 37], Vec::new()),
-        (vec![0, 2], Vec::new()),
-        (vec![0, 2, 0], Vec::new()),
-    ];
-    run_reads(store, key_values).await;
+async fn test_delete_range_dynamo_db() {
+    use linera_views::dynamo_db::DynamoDbStore;
+    let store = DynamoDbStore::new_test_store().await.unwrap();
+    run_delete_range(store).await;
 }
 
+#[cfg(with_dynamodb)]
 #[tokio::test]
-async fn test_test_memory_writes_from_blank() {
-    let key_value_store = create_value_splitting_memory_store();
-    run_writes_from_blank(&key_value_store).await;
+async fn test_dynamo_db_write_batch_in_chunks() {
+    use linera_views::{dynamo_db::DynamoDbStore, store::ReadableKeyValueStore as _};
+    let store = DynamoDbStore::new_test_store().await.unwrap();
+    // More operations than fit in one transaction.
+    let mut batch = Batch::new();
+    for i in 0..250u16 {
+        batch.put_key_value_bytes(i.to_be_bytes().to_vec(), vec![1; 100]);
+    }
+    batch.delete_key(42u16.to_be_bytes().to_vec());
+    store.write_batch_in_chunks(batch).await.unwrap();
+
+    let key_values = store
+        .find_key_values_by_prefix_with_options(&[], Order::Forward)
+        .await
+        .unwrap();
+    let expected = (0..250u16)
+        .filter(|i| *i != 42)
+        .map(|i| (i.to_be_bytes().to_vec(), vec![1; 100]))
+        .collect::<Vec<_>>();
+    assert_eq!(key_values, expected);
 }
 
+#[cfg(all(with_dynamodb, with_encryption))]
 #[tokio::test]
-async fn test_memory_writes_from_blank() {
-    let store = MemoryStore::new_test_store().await.unwrap();
-    run_writes_from_blank(&store).await;
+async fn test_dynamo_db_encryption() {
+    use linera_views::{
+        context::ViewContext,
+        dynamo_db::{DynamoDbStore, DynamoDbStoreConfig, EncryptedDynamoDbStore},
+        encrypted::EncryptionKey,
+        localstack,
+        random::generate_test_namespace,
+        register_view::RegisterView,
+        store::{AdminKeyValueStore as _, CommonStoreConfig, ReadableKeyValueStore as _},
+        views::View as _,
+    };
+
+    let config = localstack::get_test_config().await.unwrap();
+    let plain_config = DynamoDbStoreConfig::new(config.clone(), CommonStoreConfig::default());
+    let encrypted_config = DynamoDbStoreConfig::new(config, CommonStoreConfig::default())
+        .with_encryption(EncryptionKey::generate());
+    let namespace = generate_test_namespace();
+    let store = EncryptedDynamoDbStore::recreate_and_connect(&encrypted_config, &namespace, &[])
+        .await
+        .unwrap();
+
+    let text = "a secret that DynamoDB must not see".to_string();
+    let context = ViewContext::create_root_context(store.clone(), ())
+        .await
+        .unwrap();
+    let mut view = RegisterView::<_, String>::load(context).await.unwrap();
+    view.set(text.clone());
+    let mut batch = Batch::new();
+    view.flush(&mut batch).unwrap();
+    view.context().write_batch(batch).await.unwrap();
+
+    let context = ViewContext::create_root_context(store, ()).await.unwrap();
+    let view = RegisterView::<_, String>::load(context).await.unwrap();
+    assert_eq!(view.get(), &text);
+
+    // The values at rest do not contain the plaintext.
+    let plain_store = DynamoDbStore::connect(&plain_config, &namespace, &[])
+        .await
+        .unwrap();
+    let key_values = plain_store.find_key_values_by_prefix(&[]).await.unwrap();
+    assert!(!key_values.is_empty());
+    for (_, value) in key_values {
+        assert!(!value
+            .windows(text.len())
+            .any(|window| window == text.as_bytes()));
+    }
+    DynamoDbStore::delete(&plain_config, &namespace)
+        .await
+        .unwrap();
 }
 
+#[cfg(with_dynamodb)]
 #[tokio::test]
-async fn test_key_value_store_view_memory_writes_from_blank() {
-    let context = create_test_memory_context();
-    let key_value_store = ViewContainer::new(context).await.unwrap();
-    run_writes_from_blank(&key_value_store).await;
+async fn test_dynamo_db_compression_mixed_table() {
+    use futures::TryStreamExt as _;
+    use linera_views::{
+        batch::Batch,
+        dynamo_db::{DynamoDbStore, DynamoDbStoreConfig},
+        localstack,
+        random::generate_test_namespace,
+        store::{
+            AdminKeyValueStore as _, CommonStoreConfig, ReadableKeyValueStore as _,
+            WritableKeyValueStore as _,
+        },
+    };
+
+    let config = localstack::get_test_config().await.unwrap();
+    let legacy_config = DynamoDbStoreConfig::new(config.clone(), CommonStoreConfig::default());
+    let compressed_config =
+        DynamoDbStoreConfig::new(config, CommonStoreConfig::default()).with_compression(3);
+    let namespace = generate_test_namespace();
+    let legacy_store = DynamoDbStore::recreate_and_connect(&legacy_config, &namespace, &[])
+        .await
+        .unwrap();
+    let compressed_store = DynamoDbStore::connect(&compressed_config, &namespace, &[])
+        .await
+        .unwrap();
+
+    // Items written with and without compression, small and large, share the table.
+    let mut key_values = Vec::new();
+    for (index, store) in [&legacy_store, &compressed_store].into_iter().enumerate() {
+        let mut batch = Batch::new();
+        for (size, byte) in [(10, 1), (100_000, 2), (1_000_000, 3)] {
+            let key = vec![0, index as u8, byte];
+            let value = vec![byte; size];
+            batch.put_key_value_bytes(key.clone(), value.clone());
+            key_values.push((key, value));
+        }
+        store.write_batch(batch).await.unwrap();
+    }
+
+    for store in [&legacy_store, &compressed_store] {
+        for (key, value) in &key_values {
+            assert_eq!(
+                store.read_value_bytes(key).await.unwrap().as_ref(),
+                Some(value)
+            );
+        }
+        let read_key_values = store.find_key_values_by_prefix(&[0]).await.unwrap();
+        let read_key_values = read_key_values
+            .into_iter()
+            .map(|(key, value)| ([vec![0], key].concat(), value))
+            .collect::<Vec<_>>();
+        assert_eq!(read_key_values, key_values);
+        let streamed_key_values = store
+            .find_key_values_by_prefix_stream(&[0])
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(streamed_key_values, read_key_values);
+    }
+    DynamoDbStore::delete(&legacy_config, &namespace)
+        .await
+        .unwrap();
 }
 
-#[cfg(with_rocksdb)]
+#[cfg(with_dynamodb)]
 #[tokio::test]
-async fn test_rocks_db_writes_from_blank() {
-    let store = linera_views::rocks_db::RocksDbStore::new_test_store()
+async fn test_dynamo_db_table_reuse() {
+    use linera_base::time::Duration;
+    use linera_views::{
+        dynamo_db::{DynamoDbStore, DynamoDbStoreConfig, TableWaitOptions},
+        localstack,
+        random::generate_test_namespace,
+        store::{
+            AdminKeyValueStore as _, CommonStoreConfig, ReadableKeyValueStore as _,
+            WritableKeyValueStore as _,
+        },
+    };
+
+    let config = localstack::get_test_config().await.unwrap();
+    let config = DynamoDbStoreConfig::new(config, CommonStoreConfig::default())
+        .with_table_wait_options(TableWaitOptions {
+            poll_interval: Duration::from_millis(50),
+            timeout: Duration::from_secs(60),
+        });
+    let namespace = generate_test_namespace();
+    // The table is deleted and created again under the same name, and is usable each time.
+    for round in 0..3u8 {
+        let store = DynamoDbStore::recreate_and_connect(&config, &namespace, &[])
+            .await
+            .unwrap();
+        DynamoDbStore::wait_until_active(&config, &namespace)
+            .await
+            .unwrap();
+        assert!(store.find_keys_by_prefix(&[0]).await.unwrap().is_empty());
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![0, round], vec![round]);
+        store.write_batch(batch).await.unwrap();
+        assert_eq!(
+            store.read_value_bytes(&[0, round]).await.unwrap(),
+            Some(vec![round])
+        );
+    }
+    DynamoDbStore::delete(&config, &namespace).await.unwrap();
+    DynamoDbStore::wait_for_table_deletion(&config, &namespace)
         .await
         .unwrap();
-    run_writes_from_blank(&store).await;
+    assert!(!DynamoDbStore::exists(&config, &namespace).await.unwrap());
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_dynamo_db_key_namespaces() {
+    use linera_views::{
+        batch::Batch,
+        dynamo_db::{DynamoDbStore, DynamoDbStoreConfig},
+        localstack,
+        random::generate_test_namespace,
+        store::{
+            AdminKeyValueStore as _, CommonStoreConfig, ReadableKeyValueStore as _,
+            WritableKeyValueStore as _,
+        },
+    };
+
+    let aws_config = localstack::get_test_config().await.unwrap();
+    let config = DynamoDbStoreConfig::new(aws_config.clone(), CommonStoreConfig::default());
+    let namespace = generate_test_namespace();
+    let plain_store = DynamoDbStore::recreate_and_connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    let mut stores = Vec::new();
+    for key_namespace in ["alice", "bob"] {
+        let config = DynamoDbStoreConfig::new(aws_config.clone(), CommonStoreConfig::default())
+            .with_key_namespace(key_namespace);
+        let store = DynamoDbStore::connect(&config, &namespace, &[])
+            .await
+            .unwrap();
+        stores.push(store);
+    }
+
+    // The same keys are written by each store with different values.
+    for (index, store) in [&plain_store, &stores[0], &stores[1]]
+        .into_iter()
+        .enumerate()
+    {
+        let mut batch = Batch::new();
+        for key in [vec![0, 1], vec![0, 2]] {
+            batch.put_key_value_bytes(key, vec![index as u8]);
+        }
+        store.write_batch(batch).await.unwrap();
+        let sub_store = store.clone_with_root_key(&[7]).unwrap();
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![0, 3], vec![index as u8]);
+        sub_store.write_batch(batch).await.unwrap();
+    }
+    for (index, store) in [&plain_store, &stores[0], &stores[1]]
+        .into_iter()
+        .enumerate()
+    {
+        let key_values = store.find_key_values_by_prefix(&[0]).await.unwrap();
+        assert_eq!(
+            key_values,
+            vec![(vec![1], vec![index as u8]), (vec![2], vec![index as u8])]
+        );
+        let sub_store = store.clone_with_root_key(&[7]).unwrap();
+        let key_values = sub_store.find_key_values_by_prefix(&[0]).await.unwrap();
+        assert_eq!(key_values, vec![(vec![3], vec![index as u8])]);
+    }
+
+    // Deleting a prefix only affects the keys of the key namespace.
+    let mut batch = Batch::new();
+    batch.delete_key_prefix(vec![0]);
+    stores[0].write_batch(batch).await.unwrap();
+    assert!(stores[0]
+        .find_keys_by_prefix(&[0])
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(stores[1].find_keys_by_prefix(&[0]).await.unwrap().len(), 2);
+    assert_eq!(
+        plain_store.find_keys_by_prefix(&[0]).await.unwrap().len(),
+        2
+    );
+
+    assert_eq!(
+        DynamoDbStore::list_key_namespaces(&config, &namespace)
+            .await
+            .unwrap(),
+        vec!["alice".to_string(), "bob".to_string()]
+    );
+    DynamoDbStore::delete_key_namespace(&config, &namespace, "bob")
+        .await
+        .unwrap();
+    assert!(stores[1]
+        .find_keys_by_prefix(&[0])
+        .await
+        .unwrap()
+        .is_empty());
+    let sub_store = stores[1].clone_with_root_key(&[7]).unwrap();
+    assert!(sub_store
+        .find_keys_by_prefix(&[0])
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        plain_store.find_keys_by_prefix(&[0]).await.unwrap().len(),
+        2
+    );
+    assert_eq!(
+        DynamoDbStore::list_key_namespaces(&config, &namespace)
+            .await
+            .unwrap(),
+        vec!["alice".to_string()]
+    );
+    DynamoDbStore::delete(&config, &namespace).await.unwrap();
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_dynamo_db_timeout() {
+    use std::time::{Duration, Instant};
+
+    use aws_sdk_dynamodb::config::{Credentials, Region};
+    use linera_views::{
+        dynamo_db::{
+            get_config_with_credentials, DynamoDbStoreConfig, DynamoDbStoreInternal,
+            DynamoDbStoreInternalError,
+        },
+        store::{AdminKeyValueStore as _, CommonStoreConfig, ReadableKeyValueStore as _},
+    };
+    use tokio::net::TcpListener;
+
+    // The connections are accepted but no request is ever answered.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((connection, _)) = listener.accept().await {
+            connections.push(connection);
+        }
+    });
+
+    let credentials = Credentials::new("test", "test", None, None, "static");
+    let use_localstack = false;
+    let config =
+        get_config_with_credentials(credentials, Region::new("us-east-1"), use_localstack).unwrap();
+    let config = config
+        .to_builder()
+        .endpoint_url(format!("http://{address}"))
+        .build();
+    let timeout = Duration::from_millis(200);
+    let config = DynamoDbStoreConfig::new(config, CommonStoreConfig::default())
+        .with_operation_timeout(timeout)
+        .with_connect_timeout(timeout);
+    let store = DynamoDbStoreInternal::connect(&config.inner_config, "unreachable", &[])
+        .await
+        .unwrap();
+    let start = Instant::now();
+    let error = store.read_value_bytes(&[1]).await.unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(matches!(
+        error,
+        DynamoDbStoreInternalError::Timeout {
+            operation: "GetItem",
+            ..
+        }
+    ));
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_dynamo_db_value_too_large() {
+    use linera_views::{
+        dynamo_db::{DynamoDbStoreInternal, DynamoDbStoreInternalError},
+        journaling::JournalingKeyValueStore,
+        store::WritableKeyValueStore as _,
+    };
+
+    let store = JournalingKeyValueStore::<DynamoDbStoreInternal>::new_test_store()
+        .await
+        .unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1], vec![0; 500_000]);
+    let error = store.write_batch(batch).await.unwrap_err();
+    assert!(matches!(
+        error,
+        DynamoDbStoreInternalError::ValueTooLarge {
+            key_len: 1,
+            value_len: 500_000
+        }
+    ));
 }
 
 #[cfg(with_dynamodb)]
@@ -278,6 +2356,40 @@ async fn test_indexed_db_writes_from_state() {
     run_writes_from_state(&key_value_store).await;
 }
 
+#[cfg(with_indexeddb)]
+#[wasm_bindgen_test]
+async fn test_indexed_db_batch_is_committed() {
+    use linera_views::{
+        indexed_db::{IndexedDbStore, IndexedDbStoreConfig, TEST_INDEX_DB_MAX_STREAM_QUERIES},
+        random::generate_test_namespace,
+        store::{
+            LocalAdminKeyValueStore as _, LocalReadableKeyValueStore as _,
+            LocalWritableKeyValueStore as _,
+        },
+    };
+    let config = IndexedDbStoreConfig::new(TEST_INDEX_DB_MAX_STREAM_QUERIES);
+    let namespace = generate_test_namespace();
+    let store = IndexedDbStore::connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1, 1], vec![1]);
+    batch.put_key_value_bytes(vec![1, 2], vec![2]);
+    batch.put_key_value_bytes(vec![2, 1], vec![3]);
+    batch.delete_key_prefix(vec![1]);
+    batch.put_key_value_bytes(vec![1, 3], vec![4]);
+    store.write_batch(batch).await.unwrap();
+
+    let store = IndexedDbStore::connect(&config, &namespace, &[])
+        .await
+        .unwrap();
+    let key_values = store.find_key_values_by_prefix(&[]).await.unwrap();
+    assert_eq!(
+        key_values,
+        vec![(vec![1, 3], vec![4]), (vec![2, 1], vec![3])]
+    );
+}
+
 #[cfg(with_dynamodb)]
 #[tokio::test]
 async fn test_dynamo_db_writes_from_state() {
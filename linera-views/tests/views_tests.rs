@@ -1,43 +1,58 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::BTreeSet;
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
 
 use anyhow::Result;
 use async_trait::async_trait;
+use futures::TryStreamExt as _;
 #[cfg(with_dynamodb)]
 use linera_views::dynamo_db::DynamoDbStore;
 #[cfg(with_rocksdb)]
 use linera_views::rocks_db::RocksDbStore;
 #[cfg(with_scylladb)]
 use linera_views::scylla_db::ScyllaDbStore;
-#[cfg(any(with_scylladb, with_rocksdb, with_dynamodb))]
-use linera_views::store::AdminKeyValueStore as _;
 use linera_views::{
     batch::{
         Batch, WriteOperation,
         WriteOperation::{Delete, DeletePrefix, Put},
     },
-    collection_view::HashedCollectionView,
-    context::{create_test_memory_context, Context, MemoryContext, ViewContext},
+    collection_view::{CollectionView, CustomCollectionView, HashedCollectionView},
+    common::Codec,
+    context::{create_test_memory_context, Context, MemoryContext, StoreOperation, ViewContext},
+    faulty::{FaultSchedule, FaultyMemoryStore, FaultyStoreError},
+    indexed_collection_view::HashedIndexedCollectionView,
+    journaling::JournalingKeyValueStore,
     key_value_store_view::{KeyValueStoreView, ViewContainer},
-    log_view::HashedLogView,
+    lock::{acquire_lock, LOCK_ROOT_KEY_PREFIX},
+    log_view::{HashedLogView, IncrementalHashedLogView, LogView},
     lru_caching::{LruCachingMemoryStore, LruCachingStore},
-    map_view::{ByteMapView, HashedMapView},
-    memory::MemoryStore,
-    queue_view::HashedQueueView,
+    map_view::{
+        ByteMapView, HashedMapView, IncrementalHashedByteMapView, IncrementalHashedMapView, MapView,
+    },
+    memory::{MemoryStore, MemoryStoreError},
+    queue_view::{HashedQueueView, QueueView},
     random::make_deterministic_rng,
-    reentrant_collection_view::HashedReentrantCollectionView,
-    register_view::HashedRegisterView,
-    set_view::HashedSetView,
-    store::TestKeyValueStore as _,
+    read_only::ReadOnlyStoreError,
+    reentrant_collection_view::{HashedReentrantCollectionView, ReentrantCollectionView},
+    register_view::{HashedRegisterView, RegisterView},
+    set_view::{HashedSetView, SetView},
+    snapshot::{SnapshotStore, SnapshotStoreError},
+    store::{
+        AdminKeyValueStore as _, ReadableKeyValueStore as _, TestKeyValueStore as _,
+        WritableKeyValueStore as _,
+    },
     test_utils::{
         get_random_byte_vector, get_random_key_value_operations, get_random_key_values,
-        span_random_reordering_put_delete,
+        span_random_reordering_put_delete, RecordedOperation, RecordingObserver,
+    },
+    views::{
+        ClonableView as _, CryptoHashRootView, CryptoHashView as _, HashableView, Hasher, RootView,
+        View, ViewError, MIN_VIEW_TAG, OPTIONAL_VIEW_TAG,
     },
-    views::{CryptoHashRootView, HashableView, Hasher, RootView, View, ViewError},
 };
 use rand::{Rng, RngCore};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[derive(CryptoHashRootView)]
 pub struct StateView<C> {
@@ -148,6 +163,33 @@ impl StateStorage for LruMemoryStorage {
     }
 }
 
+pub struct FaultyJournalingMemoryStorage {
+    accessed_chains: BTreeSet<usize>,
+    store: JournalingKeyValueStore<FaultyMemoryStore>,
+}
+
+#[async_trait]
+impl StateStorage for FaultyJournalingMemoryStorage {
+    type Context = ViewContext<usize, JournalingKeyValueStore<FaultyMemoryStore>>;
+
+    async fn new() -> Self {
+        let store = FaultyMemoryStore::new_test_store().await.unwrap();
+        let store = JournalingKeyValueStore::new(store);
+        FaultyJournalingMemoryStorage {
+            accessed_chains: BTreeSet::new(),
+            store,
+        }
+    }
+
+    async fn load(&mut self, id: usize) -> Result<StateView<Self::Context>, ViewError> {
+        self.accessed_chains.insert(id);
+        let base_key = bcs::to_bytes(&id)?;
+        let store = self.store.clone();
+        let context = Self::Context::new_unsafe(store, base_key, id);
+        StateView::load(context).await
+    }
+}
+
 #[cfg(with_rocksdb)]
 pub struct RocksDbTestStorage {
     store: RocksDbStore,
@@ -652,6 +694,258 @@ async fn test_byte_map_view() -> Result<()> {
     Ok(())
 }
 
+/// A view generic over its values, which only requires the bounds needed by its fields.
+#[derive(CryptoHashRootView, linera_views::views::ClonableView)]
+pub struct GenericStateView<C, T>
+where
+    T: Clone,
+{
+    pub value: HashedRegisterView<C, Option<T>>,
+    pub log: linera_views::log_view::HashedLogView<C, T>,
+    pub count: HashedRegisterView<C, u64>,
+}
+
+#[tokio::test]
+async fn test_generic_derived_view() -> Result<()> {
+    let context = create_test_memory_context();
+    let mut view = GenericStateView::<_, String>::load(context.clone()).await?;
+    view.value.set(Some("Hello".to_string()));
+    view.log.push("Bonjour".to_string());
+    view.count.set(2);
+    let mut clone = view.clone_unchecked()?;
+    let hash = view.hash().await?;
+    assert_eq!(clone.hash_mut().await?, hash);
+    view.save().await?;
+
+    let view = GenericStateView::<_, String>::load(context).await?;
+    assert_eq!(view.value.get(), &Some("Hello".to_string()));
+    assert_eq!(view.log.read(..).await?, vec!["Bonjour".to_string()]);
+    assert_eq!(*view.count.get(), 2);
+    assert_eq!(view.hash().await?, hash);
+    Ok(())
+}
+
+/// A view with an optional subview, which is only stored once it is inserted.
+#[derive(CryptoHashRootView, linera_views::views::ClonableView)]
+pub struct OptionalStateView<C> {
+    pub count: HashedRegisterView<C, u64>,
+    pub details: Option<HashedMapView<C, u64, String>>,
+}
+
+#[tokio::test]
+async fn test_optional_field_of_derived_view() -> Result<()> {
+    let context = create_test_memory_context();
+    let mut view = OptionalStateView::load(context.clone()).await?;
+    assert!(view.details.is_none());
+    let empty_hash = view.crypto_hash().await?;
+
+    // An inserted subview is stored, even if it is empty.
+    view.details_or_insert()?;
+    assert_ne!(view.crypto_hash().await?, empty_hash);
+    view.save().await?;
+    let mut view = OptionalStateView::load(context.clone()).await?;
+    assert!(view.details.is_some());
+    view.details_or_insert()?.insert(&1, "one".to_string())?;
+    view.count.set(1);
+    let mut clone = view.clone_unchecked()?;
+    let hash = view.crypto_hash().await?;
+    assert_eq!(clone.crypto_hash_mut().await?, hash);
+    view.save().await?;
+
+    let mut view = OptionalStateView::load(context.clone()).await?;
+    assert_eq!(view.crypto_hash().await?, hash);
+    let details = view.details.as_ref().unwrap();
+    assert_eq!(details.get(&1).await?, Some("one".to_string()));
+    let details_key = context.derive_tag_key(MIN_VIEW_TAG, &1)?;
+    let marker_key = context.derive_tag_key(OPTIONAL_VIEW_TAG, &1)?;
+    assert!(context.count_keys_by_prefix(&details_key).await? > 0);
+    assert!(context.contains_key(&marker_key).await?);
+    assert!(!view.has_pending_changes().await);
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    assert!(batch.is_empty());
+
+    // Removing the subview deletes its keys.
+    view.details = None;
+    view.save().await?;
+    let mut view = OptionalStateView::load(context.clone()).await?;
+    assert!(view.details.is_none());
+    assert_eq!(*view.count.get(), 1);
+    assert_eq!(context.count_keys_by_prefix(&details_key).await?, 0);
+    assert!(!context.contains_key(&marker_key).await?);
+    view.count.set(0);
+    assert_eq!(view.crypto_hash().await?, empty_hash);
+    Ok(())
+}
+
+type NestedCollections<C> = CollectionView<
+    C,
+    String,
+    CollectionView<
+        C,
+        String,
+        CollectionView<C, String, CollectionView<C, String, RegisterView<C, u64>>>,
+    >,
+>;
+
+#[derive(RootView)]
+pub struct NestedCollectionsView<C> {
+    pub nested: NestedCollections<C>,
+}
+
+/// Returns the maximal size of the keys of the `store`, their total size and the number
+/// of keys outside of the dictionary of the compressed prefixes.
+async fn key_statistics(store: &MemoryStore) -> Result<(usize, usize, usize)> {
+    let keys = store.find_keys_by_prefix(&[]).await?;
+    let max_size = keys.iter().map(Vec::len).max().unwrap_or_default();
+    let total_size = keys.iter().map(Vec::len).sum();
+    let num_data_keys = keys.iter().filter(|key| !key.starts_with(&[0, 0])).count();
+    Ok((max_size, total_size, num_data_keys))
+}
+
+#[tokio::test]
+async fn test_prefix_compression_of_nested_collections() -> Result<()> {
+    let indices = (0..3)
+        .map(|i| format!("a rather long index in the collections {i}"))
+        .collect::<Vec<_>>();
+    let mut statistics = Vec::new();
+    for compress in [false, true] {
+        let store = MemoryStore::new_test_store().await?;
+        let context = ViewContext::create_root_context(store.clone(), ()).await?;
+        let context = if compress {
+            context.with_prefix_compression().await?
+        } else {
+            context
+        };
+        let mut view = NestedCollectionsView::load(context).await?;
+        for (value, a) in indices.iter().enumerate() {
+            for b in &indices {
+                for c in &indices {
+                    for d in &indices {
+                        view.nested
+                            .load_entry_mut(a)
+                            .await?
+                            .load_entry_mut(b)
+                            .await?
+                            .load_entry_mut(c)
+                            .await?
+                            .load_entry_mut(d)
+                            .await?
+                            .set(value as u64);
+                    }
+                }
+            }
+        }
+        view.save().await?;
+        let (max_size, total_size, num_data_keys) = key_statistics(&store).await?;
+
+        // Opting in keeps the data stored without compression readable.
+        let context = ViewContext::create_root_context(store.clone(), ())
+            .await?
+            .with_prefix_compression()
+            .await?;
+        assert_eq!(context.has_prefix_compression(), compress);
+        let mut view = NestedCollectionsView::load(context).await?;
+        {
+            let b = view.nested.try_load_entry(&indices[2]).await?.unwrap();
+            let c = b.try_load_entry(&indices[0]).await?.unwrap();
+            let d = c.try_load_entry(&indices[1]).await?.unwrap();
+            let register = d.try_load_entry(&indices[2]).await?.unwrap();
+            assert_eq!(*register.get(), 2);
+        }
+        // The entries nested in the stored ones remain writable after reloading.
+        view.nested
+            .load_entry_mut(&indices[2])
+            .await?
+            .load_entry_mut(&indices[0])
+            .await?
+            .load_entry_mut(&indices[1])
+            .await?
+            .load_entry_mut(&indices[2])
+            .await?
+            .set(7);
+        view.save().await?;
+        let context = ViewContext::create_root_context(store.clone(), ())
+            .await?
+            .with_prefix_compression()
+            .await?;
+        let mut view = NestedCollectionsView::load(context).await?;
+        {
+            let b = view.nested.try_load_entry(&indices[2]).await?.unwrap();
+            let c = b.try_load_entry(&indices[0]).await?.unwrap();
+            let d = c.try_load_entry(&indices[1]).await?.unwrap();
+            assert_eq!(*d.try_load_entry(&indices[2]).await?.unwrap().get(), 7);
+            assert_eq!(*d.try_load_entry(&indices[1]).await?.unwrap().get(), 2);
+        }
+        // The removal of an entry deletes the entries nested in it.
+        view.nested.remove_entry(&indices[0])?;
+        view.save().await?;
+        let (_, _, num_remaining_keys) = key_statistics(&store).await?;
+        assert!(view.nested.try_load_entry(&indices[0]).await?.is_none());
+        view.nested.clear();
+        view.save().await?;
+        let (_, _, num_cleared_keys) = key_statistics(&store).await?;
+        assert_eq!(num_cleared_keys, 0);
+        statistics.push((max_size, total_size, num_data_keys, num_remaining_keys));
+    }
+    let (plain, compressed) = (statistics[0], statistics[1]);
+    assert!(compressed.0 * 2 < plain.0);
+    assert!(compressed.1 < plain.1);
+    assert_eq!(compressed.2, plain.2);
+    assert_eq!(compressed.3, plain.3);
+    assert_eq!(plain.3 * 3, plain.2 * 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_byte_map_view_uses_raw_keys() -> Result<()> {
+    let keys = [vec![1, 1], vec![2], vec![1, 2, 3]];
+    let store = MemoryStore::new_test_store().await?;
+    let context = ViewContext::create_root_context(store, ())
+        .await?
+        .with_prefix_compression()
+        .await?;
+    let mut byte_map = ByteMapView::<_, u8>::load(context.clone_with_base_key(vec![1])).await?;
+    let mut map = MapView::<_, Vec<u8>, u8>::load(context.clone_with_base_key(vec![2])).await?;
+    for (value, key) in keys.iter().enumerate() {
+        byte_map.insert(key.clone(), value as u8);
+        map.insert(key, value as u8)?;
+    }
+    let mut batch = Batch::new();
+    byte_map.flush(&mut batch)?;
+    map.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+
+    // The keys are stored verbatim and iterated in the raw byte order, unlike the ones
+    // serialized with their length.
+    let byte_map = ByteMapView::<_, u8>::load(context.clone_with_base_key(vec![1])).await?;
+    let map = MapView::<_, Vec<u8>, u8>::load(context.clone_with_base_key(vec![2])).await?;
+    assert_eq!(byte_map.keys().await?, [vec![1, 1], vec![1, 2, 3], vec![2]]);
+    assert_eq!(
+        byte_map.keys_in_range(vec![1, 2]..vec![2]).await?,
+        [vec![1, 2, 3]]
+    );
+    assert_eq!(map.indices().await?, [vec![2], vec![1, 1], vec![1, 2, 3]]);
+
+    // As a root view, the map cannot write the keys reserved by the context.
+    let mut byte_map = ByteMapView::<_, u8>::load(context.clone()).await?;
+    byte_map.insert(vec![0, 0, 7], 7);
+    let mut batch = Batch::new();
+    byte_map.flush(&mut batch)?;
+    assert!(matches!(
+        context.write_batch(batch).await,
+        Err(ViewError::ReservedKey(_))
+    ));
+    byte_map.rollback();
+    byte_map.insert(vec![MIN_VIEW_TAG, 0, 7], 7);
+    let mut batch = Batch::new();
+    byte_map.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    let byte_map = ByteMapView::<_, u8>::load(context).await?;
+    assert_eq!(byte_map.get(&[MIN_VIEW_TAG, 0, 7]).await?, Some(7));
+    Ok(())
+}
+
 #[cfg(test)]
 async fn test_views_in_lru_memory_param(config: &TestConfig) -> Result<()> {
     tracing::warn!("Testing config {:?} with lru memory", config);
@@ -686,6 +980,15 @@ async fn test_views_in_memory() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_views_in_faulty_journaling_memory() -> Result<()> {
+    for config in TestConfig::samples() {
+        let mut store = FaultyJournalingMemoryStorage::new().await;
+        test_store(&mut store, &config).await?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 async fn test_views_in_key_value_store_view_memory_param(config: &TestConfig) -> Result<()> {
     tracing::warn!(
@@ -980,7 +1283,7 @@ where
                 view.map.remove(&key_str)?;
                 view.key_value_store.remove(key).await?;
             }
-            DeletePrefix { key_prefix: _ } => {}
+            DeletePrefix { key_prefix: _ } | DeleteRange { .. } => {}
         }
         //
         let choice = rng.gen_range(0..10);
@@ -1205,3 +1508,1020 @@ async fn check_large_write_memory() -> Result<()> {
     let mut store = MemoryTestStorage::new().await;
     check_large_write(&mut store, vector).await
 }
+
+#[tokio::test]
+async fn test_read_only_context() -> Result<()> {
+    type EntryType<C> = HashedRegisterView<C, u8>;
+    type CollectionViewType<C> = HashedCollectionView<C, u8, EntryType<C>>;
+
+    let store = MemoryStore::new_test_store().await?;
+    let context = ViewContext::create_root_context(store.clone(), ()).await?;
+    let mut collection = CollectionViewType::load(context).await?;
+    collection.load_entry_mut(&1).await?.set(7);
+    let mut batch = Batch::new();
+    collection.flush(&mut batch)?;
+    collection.context().write_batch(batch).await?;
+
+    // The views of a read-only context see the stored values.
+    let context = ViewContext::read_only(store.clone(), ());
+    let mut collection = CollectionViewType::load(context).await?;
+    assert_eq!(collection.indices().await?, vec![1]);
+    let entry = collection.try_load_entry(&1).await?.unwrap();
+    assert_eq!(*entry.get(), 7);
+    drop(entry);
+
+    // Saving changes fails instead of dropping them.
+    collection.load_entry_mut(&2).await?.set(8);
+    let mut batch = Batch::new();
+    collection.flush(&mut batch)?;
+    let expected = ReadOnlyStoreError::<MemoryStoreError>::WriteForbidden.to_string();
+    let error = collection.context().write_batch(batch).await.unwrap_err();
+    assert!(matches!(error, ViewError::StoreError { error, .. } if error == expected));
+
+    // Even a batch only deleting a prefix is rejected.
+    let mut batch = Batch::new();
+    batch.delete_key_prefix(vec![]);
+    let error = collection.context().write_batch(batch).await.unwrap_err();
+    assert!(matches!(error, ViewError::StoreError { error, .. } if error == expected));
+
+    // Nothing was written to the store.
+    let context = ViewContext::create_root_context(store, ()).await?;
+    let collection = CollectionViewType::load(context).await?;
+    assert_eq!(collection.indices().await?, vec![1]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_snapshot_isolates_readers_from_flushes() -> Result<()> {
+    const NUM_ENTRIES: u64 = 100;
+
+    let store = SnapshotStore::new(MemoryStore::new_test_store().await?);
+    let context = ViewContext::create_root_context(store, ()).await?;
+    let mut map = MapView::<_, u64, u64>::load(context.clone()).await?;
+    for i in 0..NUM_ENTRIES {
+        map.insert(&i, i)?;
+    }
+    let mut batch = Batch::new();
+    map.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+
+    let snapshot = MapView::<_, u64, u64>::load(context.snapshot().await).await?;
+    // The reader iterates over the snapshot while the writer flushes several times.
+    let read = async {
+        for i in 0..NUM_ENTRIES {
+            assert_eq!(snapshot.get(&i).await?, Some(i));
+            tokio::task::yield_now().await;
+        }
+        anyhow::Ok(())
+    };
+    let write = async {
+        for round in 1..=5 {
+            for i in 0..NUM_ENTRIES {
+                map.insert(&i, i + 1000 * round)?;
+            }
+            map.remove(&(NUM_ENTRIES - round))?;
+            map.insert(&(NUM_ENTRIES + round), 0)?;
+            let mut batch = Batch::new();
+            map.flush(&mut batch)?;
+            context.write_batch(batch).await?;
+            tokio::task::yield_now().await;
+        }
+        anyhow::Ok(())
+    };
+    let (read, write) = tokio::join!(read, write);
+    read?;
+    write?;
+
+    // Scans of the snapshot are isolated as well.
+    assert_eq!(
+        snapshot.indices().await?,
+        (0..NUM_ENTRIES).collect::<Vec<_>>()
+    );
+    let mut values = Vec::new();
+    snapshot
+        .for_each_index_value(|_, value| {
+            values.push(value.into_owned());
+            Ok(())
+        })
+        .await?;
+    assert_eq!(values, (0..NUM_ENTRIES).collect::<Vec<_>>());
+
+    // New views see the flushed state.
+    let map = MapView::<_, u64, u64>::load(context.clone()).await?;
+    assert_eq!(map.get(&0).await?, Some(5000));
+    assert_eq!(map.get(&(NUM_ENTRIES - 1)).await?, None);
+    assert_eq!(map.get(&(NUM_ENTRIES + 1)).await?, Some(0));
+
+    // A snapshot cannot be written to.
+    let mut batch = Batch::new();
+    batch.delete_key_prefix(vec![]);
+    let error = snapshot.context().write_batch(batch).await.unwrap_err();
+    let expected = SnapshotStoreError::<MemoryStoreError>::WriteToSnapshot.to_string();
+    assert!(matches!(error, ViewError::StoreError { error, .. } if error == expected));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_flush_failure_mid_way_is_retried() -> Result<()> {
+    type MapViewType<C> = HashedMapView<C, u64, u64>;
+    const NUM_ENTRIES: u64 = 100;
+
+    let faulty_store = FaultyMemoryStore::new_test_store().await?;
+    let store = JournalingKeyValueStore::new(faulty_store.clone());
+    let context = ViewContext::create_root_context(store.clone(), ()).await?;
+    let mut view = MapViewType::load(context).await?;
+    for i in 0..NUM_ENTRIES {
+        view.insert(&i, i)?;
+    }
+
+    // The batch is too large to be written at once, so it goes through the journal. One
+    // of the writes of the blocks fails.
+    faulty_store.set_schedule(FaultSchedule::default().with_failing_write_batch(5));
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    let error = view.context().write_batch(batch).await.unwrap_err();
+    let expected = FaultyStoreError::<MemoryStoreError>::InjectedFault.to_string();
+    assert!(matches!(error, ViewError::StoreError { error, .. } if error == expected));
+
+    // Resolving the journal makes the stored view consistent.
+    faulty_store.set_schedule(FaultSchedule::default());
+    store.clear_journal().await?;
+    let context = ViewContext::create_root_context(store.clone(), ()).await?;
+    let stored_view = MapViewType::load(context).await?;
+    let count = stored_view.count().await?;
+    assert!(count == 0 || count == NUM_ENTRIES as usize);
+
+    // Retrying the write stores all the entries.
+    let mut view = MapViewType::load(view.context().clone()).await?;
+    for i in 0..NUM_ENTRIES {
+        view.insert(&i, i)?;
+    }
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    view.context().write_batch(batch).await?;
+    let context = ViewContext::create_root_context(store, ()).await?;
+    let stored_view = MapViewType::load(context).await?;
+    assert_eq!(stored_view.count().await?, NUM_ENTRIES as usize);
+    for i in 0..NUM_ENTRIES {
+        assert_eq!(stored_view.get(&i).await?, Some(i));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sub_scopes_from_bytes_and_markers() -> Result<()> {
+    type MapViewType<C> = HashedMapView<C, u64, u64>;
+
+    let store = MemoryStore::new_test_store().await?;
+    let context = ViewContext::create_root_context(store, ()).await?;
+    let marker = String::from("scope");
+    let suffix = bcs::to_bytes(&marker)?;
+    let serde_context = context.clone_with_sub_scope(&marker)?;
+    let bytes_context = context.clone_with_sub_scope_bytes(&suffix);
+    assert_eq!(serde_context.base_key(), bytes_context.base_key());
+    assert_eq!(
+        context.derive_key(&marker)?,
+        context.derive_key_bytes(&suffix)
+    );
+    assert_eq!(bytes_context.base_key(), &suffix[..]);
+
+    // Values saved through one scope are seen through the other one.
+    let mut view = MapViewType::load(serde_context).await?;
+    view.insert(&1, 10)?;
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    view.context().write_batch(batch).await?;
+    let mut view = MapViewType::load(bytes_context.clone()).await?;
+    assert_eq!(view.get(&1).await?, Some(10));
+    view.insert(&2, 20)?;
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    view.context().write_batch(batch).await?;
+    let view = MapViewType::load(context.clone_with_sub_scope(&marker)?).await?;
+    assert_eq!(view.indices().await?, vec![1, 2]);
+
+    // A different suffix is a different scope.
+    let view = MapViewType::load(context.clone_with_sub_scope_bytes(b"other")).await?;
+    assert_eq!(view.count().await?, 0);
+    Ok(())
+}
+
+/// A codec storing the values as JSON.
+#[derive(Debug)]
+struct JsonCodec;
+
+impl Codec for JsonCodec {
+    const NAME: &'static str = "json";
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ViewError> {
+        serde_json::to_vec(value).map_err(|error| ViewError::Serialization {
+            codec: Self::NAME,
+            error: error.to_string(),
+        })
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ViewError> {
+        serde_json::from_slice(bytes).map_err(|error| ViewError::Serialization {
+            codec: Self::NAME,
+            error: error.to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+struct Profile {
+    name: String,
+    age: u32,
+}
+
+#[tokio::test]
+async fn test_register_view_with_json_codec() -> Result<()> {
+    let context = create_test_memory_context();
+    let mut view = RegisterView::<_, Profile, JsonCodec>::load(context.clone()).await?;
+    assert_eq!(*view.get(), Profile::default());
+    let profile = Profile {
+        name: "Alice".to_string(),
+        age: 42,
+    };
+    view.set(profile.clone());
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+
+    // The value is stored as JSON, without any BCS encoding.
+    let bytes = context.read_value_raw(context.base_key()).await?.unwrap();
+    assert_eq!(bytes, serde_json::to_vec(&profile)?);
+    let value = context
+        .read_value_with_codec::<JsonCodec, Profile>(context.base_key())
+        .await?;
+    assert_eq!(value, Some(profile));
+
+    // Pre-serialized JSON blobs are read back by the view.
+    let bytes = br#"{"name":"Bob","age":7}"#.to_vec();
+    context
+        .write_value_raw(context.base_key().to_vec(), bytes)
+        .await?;
+    let view = RegisterView::<_, Profile, JsonCodec>::load(context.clone()).await?;
+    assert_eq!(view.get().name, "Bob");
+    assert_eq!(view.get().age, 7);
+
+    // Decoding errors report the codec that failed.
+    context
+        .write_value_raw(context.base_key().to_vec(), b"not json".to_vec())
+        .await?;
+    let error = RegisterView::<_, Profile, JsonCodec>::load(context)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ViewError::Serialization { codec: "json", .. }
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_observer_records_view_traffic() -> Result<()> {
+    let store = MemoryStore::new_test_store().await?;
+    let mut context = ViewContext::create_root_context(store, ()).await?;
+    let observer = Arc::new(RecordingObserver::with_keys());
+    context.set_observer(observer.clone());
+    let context = context.clone_with_sub_scope_bytes(b"s");
+
+    let mut view = ByteMapView::<_, u8>::load(context).await?;
+    assert!(observer.take().is_empty());
+    view.insert(vec![1], 10);
+    view.insert(vec![2], 20);
+    view.remove(vec![3]);
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    view.context().write_batch(batch).await?;
+    fn recorded(operation: StoreOperation, key: &[u8]) -> RecordedOperation {
+        RecordedOperation {
+            operation,
+            key_len: key.len(),
+            scope: b"s".to_vec(),
+            key: Some(key.to_vec()),
+        }
+    }
+    assert_eq!(
+        observer.take(),
+        vec![
+            recorded(StoreOperation::Put, b"s\x01"),
+            recorded(StoreOperation::Put, b"s\x02"),
+            recorded(StoreOperation::Delete, b"s\x03"),
+        ]
+    );
+
+    assert_eq!(view.get(&[1]).await?, Some(10));
+    assert_eq!(
+        observer.take(),
+        vec![recorded(StoreOperation::ReadValue, b"s\x01")]
+    );
+
+    // Without asking for them, the observer is only given the lengths of the keys.
+    let store = MemoryStore::new_test_store().await?;
+    let mut context = ViewContext::create_root_context(store, ()).await?;
+    let observer = Arc::new(RecordingObserver::default());
+    context.set_observer(observer.clone());
+    let view = ByteMapView::<_, u8>::load(context).await?;
+    assert_eq!(view.get(&[1, 2]).await?, None);
+    assert_eq!(
+        observer.take(),
+        vec![RecordedOperation {
+            operation: StoreOperation::ReadValue,
+            key_len: 2,
+            scope: Vec::new(),
+            key: None,
+        }]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_lock_is_exclusive() -> Result<()> {
+    let store = MemoryStore::new_test_store().await?;
+    let ttl = Duration::from_secs(60);
+    let guard = acquire_lock(&store, b"scope", ttl).await?;
+    let error = acquire_lock(&store, b"scope", ttl).await.unwrap_err();
+    assert!(matches!(error, ViewError::LockHeld));
+
+    // Other scopes have their own locks.
+    let other_guard = acquire_lock(&store, b"other", ttl).await?;
+
+    // The lock can be acquired again once released.
+    guard.release().await?;
+    let guard = acquire_lock(&store, b"scope", ttl).await?;
+    assert!(!guard.is_lost());
+    assert!(!other_guard.is_lost());
+
+    // Without a refresher, a lease can be taken over after it expires.
+    drop(guard);
+    let ttl = Duration::from_millis(100);
+    let guard = acquire_lock(&store, b"short", ttl).await?;
+    drop(guard);
+    tokio::time::sleep(2 * ttl).await;
+    let guard = acquire_lock(&store, b"short", ttl).await?;
+
+    // A refreshed lease does not expire.
+    tokio::time::sleep(3 * ttl).await;
+    assert!(!guard.is_lost());
+    let error = acquire_lock(&store, b"short", ttl).await.unwrap_err();
+    assert!(matches!(error, ViewError::LockHeld));
+
+    // A refreshed lease is released at once.
+    guard.release().await?;
+    let guard = acquire_lock(&store, b"short", ttl).await?;
+
+    // A lease that was taken over is not deleted.
+    let mut root_key = LOCK_ROOT_KEY_PREFIX.to_vec();
+    root_key.extend_from_slice(b"short");
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0], vec![1, 2, 3]);
+    store
+        .clone_with_root_key(&root_key)?
+        .write_batch(batch)
+        .await?;
+    let error = guard.release().await.unwrap_err();
+    assert!(matches!(error, ViewError::LockLost));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_exclusive_context_refuses_writes_after_losing_the_lock() -> Result<()> {
+    type MapViewType<C> = HashedMapView<C, u64, u64>;
+
+    let store = MemoryStore::new_test_store().await?;
+    let ttl = Duration::from_millis(300);
+    let context = ViewContext::with_exclusive_access(store.clone(), b"scope", ttl, ()).await?;
+    let error = ViewContext::with_exclusive_access(store.clone(), b"scope", ttl, ())
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ViewError::LockHeld));
+
+    let mut view = MapViewType::load(context).await?;
+    view.insert(&1, 1)?;
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    view.context().write_batch(batch).await?;
+
+    // A process ignoring the lock overwrites the lease, so the refresh fails.
+    let mut root_key = LOCK_ROOT_KEY_PREFIX.to_vec();
+    root_key.extend_from_slice(b"scope");
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0], vec![1, 2, 3]);
+    store
+        .clone_with_root_key(&root_key)?
+        .write_batch(batch)
+        .await?;
+    tokio::time::sleep(ttl / 2).await;
+
+    view.insert(&2, 2)?;
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    let error = view.context().write_batch(batch).await.unwrap_err();
+    assert!(matches!(error, ViewError::LockLost));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_range_iteration_merges_pending_and_stored_entries() -> Result<()> {
+    let context = create_test_memory_context();
+    let mut map = ByteMapView::<_, u8>::load(context.clone()).await?;
+    for key in [
+        vec![0],
+        vec![1, 0],
+        vec![1, 1],
+        vec![1, 2],
+        vec![2],
+        vec![2, 1],
+    ] {
+        map.insert(key.clone(), key.iter().sum());
+    }
+    let mut batch = Batch::new();
+    map.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+
+    // Pending updates are layered over the stored entries.
+    map.insert(vec![1, 3], 13);
+    map.remove(vec![1, 1]);
+    map.remove_by_prefix(vec![2]);
+    map.insert(vec![2, 5], 25);
+    assert_eq!(
+        map.keys_in_range(vec![1]..vec![3]).await?,
+        vec![vec![1, 0], vec![1, 2], vec![1, 3], vec![2, 5]]
+    );
+    assert_eq!(
+        map.key_values_in_range(vec![1, 1]..vec![2, 5]).await?,
+        vec![(vec![1, 2], 3), (vec![1, 3], 13)]
+    );
+    assert!(map.keys_in_range(vec![3]..vec![1]).await?.is_empty());
+    map.clear();
+    map.insert(vec![1, 4], 14);
+    assert_eq!(map.keys_in_range(vec![0]..vec![4]).await?, vec![vec![1, 4]]);
+
+    // The order of the indices is the one of their serialization.
+    let context = create_test_memory_context();
+    let mut view =
+        CustomCollectionView::<_, u128, RegisterView<_, u64>>::load(context.clone()).await?;
+    for index in 1..10u128 {
+        view.load_entry_mut(&index).await?;
+    }
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    view.remove_entry(&5)?;
+    view.load_entry_mut(&12).await?;
+    assert_eq!(
+        view.indices_in_range(&3..&13).await?,
+        vec![3, 4, 6, 7, 8, 9, 12]
+    );
+    let mut indices = Vec::new();
+    view.for_each_index_in_range_while(
+        |index| {
+            indices.push(index);
+            Ok(indices.len() < 2)
+        },
+        &8..&20,
+    )
+    .await?;
+    assert_eq!(indices, vec![8, 9]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_count_combines_stored_keys_and_pending_updates() -> Result<()> {
+    let context = create_test_memory_context();
+    let mut map = ByteMapView::<_, u8>::load(context.clone()).await?;
+    for key in [vec![0], vec![1, 0], vec![1, 1], vec![2]] {
+        map.insert(key, 0);
+    }
+    let mut batch = Batch::new();
+    map.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    assert_eq!(map.count().await?, 4);
+
+    // An overwrite, a new key, a removal and a removed prefix holding a new key.
+    map.insert(vec![0], 1);
+    map.insert(vec![3], 1);
+    map.remove(vec![2]);
+    map.remove(vec![4]);
+    map.remove_by_prefix(vec![1]);
+    map.insert(vec![1, 5], 1);
+    assert_eq!(map.count().await?, 3);
+    assert_eq!(map.count().await?, map.keys().await?.len());
+    assert!(!map.is_empty().await?);
+    map.clear();
+    assert!(map.is_empty().await?);
+    map.insert(vec![2], 1);
+    assert_eq!(map.count().await?, 1);
+
+    let context = create_test_memory_context();
+    let mut set = SetView::<_, u64>::load(context.clone()).await?;
+    for index in 0..5 {
+        set.insert(&index)?;
+    }
+    let mut batch = Batch::new();
+    set.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    set.insert(&3)?;
+    set.insert(&7)?;
+    set.remove(&0)?;
+    set.remove(&9)?;
+    assert_eq!(set.count().await?, 5);
+    assert_eq!(set.count().await?, set.indices().await?.len());
+    set.clear();
+    assert!(set.is_empty().await?);
+
+    let context = create_test_memory_context();
+    let mut view = CollectionView::<_, u64, RegisterView<_, u64>>::load(context.clone()).await?;
+    for index in 0..3 {
+        view.load_entry_mut(&index).await?;
+    }
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    let mut view = CollectionView::<_, u64, RegisterView<_, u64>>::load(context.clone()).await?;
+    assert_eq!(view.count().await?, 3);
+    view.load_entry_mut(&1).await?;
+    view.load_entry_mut(&5).await?;
+    view.remove_entry(&2)?;
+    view.remove_entry(&8)?;
+    assert_eq!(view.count().await?, 3);
+    assert_eq!(view.count().await?, view.indices().await?.len());
+    view.clear();
+    assert!(view.is_empty().await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_load_entries_mixes_stored_and_pending_entries() -> Result<()> {
+    let context = create_test_memory_context();
+    let mut view = CollectionView::<_, u64, RegisterView<_, u64>>::load(context.clone()).await?;
+    for index in 0..4 {
+        view.load_entry_mut(&index).await?.set(index);
+    }
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+
+    // Stored entries, a modified entry, a removed one, a new one and an absent one.
+    let mut view = CollectionView::<_, u64, RegisterView<_, u64>>::load(context.clone()).await?;
+    view.load_entry_mut(&1).await?.set(10);
+    view.remove_entry(&2)?;
+    view.load_entry_mut(&5).await?.set(50);
+    let values = view
+        .try_load_entries(&[0, 1, 2, 3, 5, 7, 0], 2)
+        .await?
+        .into_iter()
+        .map(|subview| subview.map(|subview| *subview.get()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        [Some(0), Some(10), None, Some(3), Some(50), None, Some(0)]
+    );
+    let entries = view
+        .try_load_all_entries()
+        .await?
+        .into_iter()
+        .map(|(index, subview)| (index, *subview.get()))
+        .collect::<Vec<_>>();
+    assert_eq!(entries, [(0, 0), (1, 10), (3, 3), (5, 50)]);
+
+    let context = create_test_memory_context();
+    let mut view =
+        ReentrantCollectionView::<_, u64, RegisterView<_, u64>>::load(context.clone()).await?;
+    view.try_load_entry_mut(&0).await?.set(0);
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    let mut view =
+        ReentrantCollectionView::<_, u64, RegisterView<_, u64>>::load(context.clone()).await?;
+    view.try_load_entry_mut(&6).await?.set(60);
+    let values = view
+        .try_load_entries(&[0, 6, 8])
+        .await?
+        .into_iter()
+        .map(|subview| subview.map(|subview| *subview.get()))
+        .collect::<Vec<_>>();
+    assert_eq!(values, [Some(0), Some(60), None]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_register_compare_and_set() -> Result<()> {
+    let context = create_test_memory_context();
+    let mut register = HashedRegisterView::<_, u64>::load(context.clone()).await?;
+    let initial_hash = register.hash_mut().await?;
+    register.update_with(|value| value + 3);
+
+    // The comparison is made with the staged value, which is then replaced.
+    assert!(!register.compare_and_set(&0, 1)?);
+    assert!(register.compare_and_set(&3, 1)?);
+    assert_eq!(*register.get(), 1);
+    assert!(register.has_pending_changes().await);
+    let hash = register.hash_mut().await?;
+    assert_ne!(hash, initial_hash);
+
+    // The flush persists the new value, with the matching hash.
+    let mut batch = Batch::new();
+    register.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    let mut register = HashedRegisterView::<_, u64>::load(context.clone()).await?;
+    assert_eq!(*register.get(), 1);
+    assert_eq!(register.hash_mut().await?, hash);
+    let mut expected = HashedRegisterView::<_, u64>::load(create_test_memory_context()).await?;
+    expected.set(1);
+    assert_eq!(expected.hash_mut().await?, hash);
+    Ok(())
+}
+
+#[derive(RootView, linera_views::views::ClonableView)]
+pub struct BulkLoadView<C> {
+    pub map: MapView<C, u64, u64>,
+    pub log: LogView<C, u64>,
+}
+
+const NUM_BULK_LOADED_ENTRIES: u64 = 100_000;
+
+async fn run_bulk_load<C>(context: C) -> Result<()>
+where
+    C: Context + Clone + Send + Sync + 'static,
+    ViewError: From<C::Error>,
+{
+    let mut view = BulkLoadView::load(context.clone()).await?;
+    view.map
+        .extend((0..NUM_BULK_LOADED_ENTRIES).map(|index| (index, index * index)))?;
+    view.log.extend(0..NUM_BULK_LOADED_ENTRIES);
+    let mut batch = Batch::new();
+    view.clone_unchecked()?.flush(&mut batch)?;
+    let max_batch_bytes = 64 * 1024;
+    assert!(batch.split_at_size(max_batch_bytes).len() > 1);
+    view.flush_in_chunks(max_batch_bytes).await?;
+    assert!(!view.has_pending_changes().await);
+
+    let view = BulkLoadView::load(context).await?;
+    let last = NUM_BULK_LOADED_ENTRIES - 1;
+    assert_eq!(view.map.count().await?, NUM_BULK_LOADED_ENTRIES as usize);
+    assert_eq!(view.map.get(&last).await?, Some(last * last));
+    assert_eq!(view.log.count(), NUM_BULK_LOADED_ENTRIES as usize);
+    assert_eq!(view.log.get(last as usize).await?, Some(last));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_load_in_memory() -> Result<()> {
+    run_bulk_load(create_test_memory_context()).await
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_bulk_load_in_dynamo_db() -> Result<()> {
+    let store = DynamoDbStore::new_test_store().await?;
+    let context = ViewContext::create_root_context(store, ()).await?;
+    run_bulk_load(context).await
+}
+
+#[derive(CryptoHashRootView)]
+pub struct EventLogView<C> {
+    pub events: HashedIndexedCollectionView<C, RegisterView<C, u64>>,
+}
+
+#[tokio::test]
+async fn test_indexed_collection_view_prunes_and_appends() -> Result<()> {
+    let context = create_test_memory_context();
+    let mut view = EventLogView::load(context.clone()).await?;
+    for value in 0..5 {
+        view.events.push_entry().await?.set(value);
+    }
+    view.save().await?;
+
+    // Stored entries are pruned and new ones are appended in the same flush.
+    let mut view = EventLogView::load(context.clone()).await?;
+    assert_eq!(view.events.index_range(), 0..5);
+    for value in 5..8 {
+        view.events.push_entry().await?.set(value);
+    }
+    view.events.prune_below(6);
+    assert_eq!(view.events.index_range(), 6..8);
+    assert_eq!(*view.events.front().await?.unwrap().get(), 6);
+    let hash = view.crypto_hash().await?;
+    view.save().await?;
+
+    let mut view = EventLogView::load(context.clone()).await?;
+    assert_eq!(view.crypto_hash().await?, hash);
+    assert_eq!(view.events.index_range(), 6..8);
+    assert!(view.events.try_load_entry(5).await?.is_none());
+    assert_eq!(*view.events.back().await?.unwrap().get(), 7);
+    let mut indices = Vec::new();
+    view.events
+        .for_each_index(|index| {
+            indices.push(index);
+            Ok(())
+        })
+        .await?;
+    assert_eq!(indices, [6, 7]);
+    // The range of the indices, the hash, and two keys per remaining entry.
+    assert_eq!(context.count_keys_by_prefix(&[]).await?, 6);
+
+    // Pruning all the entries does not reuse their indices.
+    view.events.prune_below(u64::MAX);
+    assert_eq!(view.events.count(), 0);
+    view.events.push_entry().await?.set(8);
+    view.save().await?;
+    let view = EventLogView::load(context).await?;
+    assert_eq!(view.events.index_range(), 8..9);
+    assert_eq!(*view.events.front().await?.unwrap().get(), 8);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_incremental_hash_only_reads_changed_entries() -> Result<()> {
+    const NUM_ENTRIES: u64 = 1000;
+
+    let store = MemoryStore::new_test_store().await?;
+    let mut context = ViewContext::create_root_context(store, ()).await?;
+    let observer = Arc::new(RecordingObserver::default());
+    context.set_observer(observer.clone());
+    let mut map = IncrementalHashedMapView::<_, u64, u64>::load(context.clone()).await?;
+    for index in 0..NUM_ENTRIES {
+        map.insert(&index, index)?;
+    }
+    let hash = map.hash().await?;
+    let mut batch = Batch::new();
+    map.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+
+    // Touching a few entries only reads the replaced values.
+    let mut map = IncrementalHashedMapView::<_, u64, u64>::load(context.clone()).await?;
+    assert_eq!(map.hash().await?, hash);
+    map.insert(&7, 70)?;
+    map.remove(&8)?;
+    map.insert(&NUM_ENTRIES, 0)?;
+    observer.take();
+    let hash = map.hash().await?;
+    let operations = observer
+        .take()
+        .into_iter()
+        .map(|recorded| recorded.operation)
+        .collect::<Vec<_>>();
+    assert_eq!(operations, [StoreOperation::ReadValue; 3]);
+    assert_eq!(map.recompute_hash().await?, hash);
+    let operations = observer.take();
+    assert!(operations
+        .iter()
+        .any(|recorded| recorded.operation == StoreOperation::FindKeyValuesByPrefix));
+
+    // The same entries give the same hash, whatever their history.
+    let other_context = create_test_memory_context();
+    let mut other_map = IncrementalHashedMapView::<_, u64, u64>::load(other_context).await?;
+    for index in (0..=NUM_ENTRIES).filter(|index| *index != 8) {
+        let value = match index {
+            7 => 70,
+            NUM_ENTRIES => 0,
+            index => index,
+        };
+        other_map.insert(&index, value)?;
+    }
+    assert_eq!(other_map.hash().await?, hash);
+
+    // Flushing changes made after the last hash drops the stored sum.
+    let mut batch = Batch::new();
+    map.flush(&mut batch)?;
+    map.insert(&9, 90)?;
+    map.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    let map = IncrementalHashedMapView::<_, u64, u64>::load(context.clone()).await?;
+    assert_eq!(map.hash().await?, map.recompute_hash().await?);
+    assert_ne!(map.hash().await?, hash);
+
+    // Removed prefixes and appended logs are handled as well.
+    let context = create_test_memory_context();
+    let mut map = IncrementalHashedByteMapView::<_, u8>::load(context.clone()).await?;
+    for key in [vec![0], vec![1, 0], vec![1, 1], vec![2]] {
+        map.insert(key, 0);
+    }
+    map.hash().await?;
+    let mut log =
+        IncrementalHashedLogView::<_, u32>::load(context.clone_with_sub_scope_bytes(b"l")).await?;
+    log.push(1);
+    log.hash().await?;
+    let mut batch = Batch::new();
+    map.flush(&mut batch)?;
+    log.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    map.remove_by_prefix(vec![1]);
+    map.insert(vec![1, 2], 1);
+    map.insert(vec![2], 1);
+    assert_eq!(map.hash().await?, map.recompute_hash().await?);
+    log.push(2);
+    assert_eq!(log.hash().await?, log.recompute_hash().await?);
+    map.clear();
+    map.insert(vec![3], 3);
+    assert_eq!(map.hash().await?, map.recompute_hash().await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pending_operations_and_rollback_of_nested_views() -> Result<()> {
+    type InnerView<C> = CollectionView<C, u64, RegisterView<C, u64>>;
+    type OuterView<C> = HashedCollectionView<C, u64, InnerView<C>>;
+
+    let context = create_test_memory_context();
+    let mut view = OuterView::load(context.clone()).await?;
+    for outer in 0..3 {
+        let inner = view.load_entry_mut(&outer).await?;
+        for index in 0..3 {
+            inner.load_entry_mut(&index).await?.set(outer * 10 + index);
+        }
+    }
+    let mut batch = Batch::new();
+    view.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+
+    let mut view = OuterView::load(context.clone()).await?;
+    assert!(!view.has_pending_changes().await);
+    assert!(view.pending_operations()?.is_empty());
+    let loaded_hash = view.hash().await?;
+    let inner = view.load_entry_mut(&1).await?;
+    inner.load_entry_mut(&2).await?.set(100);
+    inner.remove_entry(&0)?;
+    view.load_entry_mut(&5)
+        .await?
+        .load_entry_mut(&0)
+        .await?
+        .set(5);
+    view.remove_entry(&2)?;
+    assert!(view.has_pending_changes().await);
+
+    // The pending operations can be inspected without being flushed.
+    let operations = view.pending_operations()?;
+    assert_eq!(view.pending_operations()?, operations);
+    assert!(operations
+        .iter()
+        .any(|operation| matches!(operation, DeletePrefix { .. })));
+    assert!(operations
+        .iter()
+        .any(|operation| matches!(operation, Put { .. })));
+    assert!(operations
+        .iter()
+        .any(|operation| matches!(operation, Delete { .. })));
+    assert!(view.has_pending_changes().await);
+
+    // Rolling back restores the loaded state, nested entries included.
+    view.rollback();
+    assert!(!view.has_pending_changes().await);
+    assert!(view.pending_operations()?.is_empty());
+    assert_eq!(view.indices().await?, [0, 1, 2]);
+    let inner = view.load_entry_mut(&1).await?;
+    assert_eq!(inner.indices().await?, [0, 1, 2]);
+    assert_eq!(*inner.load_entry_mut(&2).await?.get(), 12);
+    assert_eq!(view.hash_mut().await?, loaded_hash);
+    Ok(())
+}
+
+#[derive(async_graphql::SimpleObject)]
+struct GraphQlState {
+    map: MapView<MemoryContext<()>, u64, String>,
+    collection: CollectionView<MemoryContext<()>, u64, RegisterView<MemoryContext<()>, u64>>,
+}
+
+#[tokio::test]
+async fn test_graphql_pagination_of_maps_and_collections() -> Result<()> {
+    let context = create_test_memory_context();
+    let mut map = MapView::load(context.clone_with_sub_scope_bytes(b"m")).await?;
+    let mut collection = CollectionView::load(context.clone_with_sub_scope_bytes(b"c")).await?;
+    for index in 0..3 {
+        map.insert(&index, format!("value {index}"))?;
+        collection.load_entry_mut(&index).await?.set(index * 10);
+    }
+    let mut batch = Batch::new();
+    map.flush(&mut batch)?;
+    collection.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    // Pending entries are paginated along with the stored ones.
+    map.insert(&3, "value 3".to_string())?;
+    map.remove(&1)?;
+    collection.load_entry_mut(&3).await?.set(30);
+
+    let schema = async_graphql::Schema::new(
+        GraphQlState { map, collection },
+        async_graphql::EmptyMutation,
+        async_graphql::EmptySubscription,
+    );
+    let query = "{
+        map { first: entries(first: 2) { key value } next: entries(first: 2, after: 2) { key } }
+        collection { entries(first: 2, after: 0) { key value } }
+    }";
+    let response = schema.execute(query).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data.into_json()?,
+        serde_json::json!({
+            "map": {
+                "first": [
+                    { "key": 0, "value": "value 0" },
+                    { "key": 2, "value": "value 2" },
+                ],
+                "next": [{ "key": 3 }],
+            },
+            "collection": {
+                "entries": [
+                    { "key": 1, "value": 10 },
+                    { "key": 2, "value": 20 },
+                ],
+            },
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_queue_view_delete_front_values() -> Result<()> {
+    let context = create_test_memory_context();
+    let mut queue = QueueView::<_, u64>::load(context.clone()).await?;
+    for value in 0..10 {
+        queue.push_back(value);
+    }
+    let mut batch = Batch::new();
+    queue.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+
+    // The deletions take the stored values first, even when interleaved with pushes.
+    queue.delete_front_values(3);
+    queue.push_back(10);
+    queue.delete_front_values(2);
+    queue.push_back(11);
+    assert_eq!(queue.count(), 7);
+    assert_eq!(queue.read_front(2).await?, vec![5, 6]);
+    assert_eq!(queue.read_back(3).await?, vec![9, 10, 11]);
+    let staged_hash = queue.hash().await?;
+
+    // Only the keys of the deleted values are removed.
+    let mut batch = Batch::new();
+    queue.flush(&mut batch)?;
+    let num_deletions = batch
+        .operations
+        .iter()
+        .filter(|operation| matches!(operation, Delete { .. }))
+        .count();
+    assert_eq!(num_deletions, 5);
+    context.write_batch(batch).await?;
+    let mut queue = QueueView::<_, u64>::load(context.clone()).await?;
+    assert_eq!(queue.hash().await?, staged_hash);
+    assert_eq!(queue.elements().await?, vec![5, 6, 7, 8, 9, 10, 11]);
+
+    // Deleting more values than the length empties the queue.
+    queue.push_back(12);
+    queue.delete_front_values(100);
+    assert_eq!(queue.count(), 0);
+    assert_eq!(queue.front().await?, None);
+    let mut batch = Batch::new();
+    queue.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    let queue = QueueView::<_, u64>::load(context).await?;
+    assert!(queue.elements().await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_log_view_tail_pagination_and_stream() -> Result<()> {
+    let context = create_test_memory_context();
+    let mut log = LogView::<_, u32>::load(context.clone()).await?;
+    for value in 0..500 {
+        log.push(value);
+    }
+    let mut batch = Batch::new();
+    log.flush(&mut batch)?;
+    context.write_batch(batch).await?;
+    let mut log = LogView::<_, u32>::load(context).await?;
+    for value in 500..520 {
+        log.push(value);
+    }
+    let expected = (0..520).collect::<Vec<_>>();
+
+    assert_eq!(log.read_last(3).await?, vec![519, 518, 517]);
+    assert_eq!(
+        log.read_last(25).await?,
+        (495..520).rev().collect::<Vec<_>>()
+    );
+    assert_eq!(log.read_last(1000).await?.len(), 520);
+
+    // Walking the pages while new values are staged sees the values of the start.
+    let count = log.count();
+    let mut values = Vec::new();
+    let mut offset = 0;
+    while offset < count {
+        let page = log.read_paginated(offset, 64).await?;
+        offset += page.len();
+        values.extend(page);
+        log.push(1000);
+    }
+    values.truncate(count);
+    assert_eq!(values, expected);
+    assert!(log.read_paginated(10_000, 64).await?.is_empty());
+
+    let values = log.values_stream(64).try_collect::<Vec<_>>().await?;
+    assert_eq!(values, log.read(..).await?);
+    assert_eq!(values[..520], expected[..]);
+    Ok(())
+}